@@ -0,0 +1,420 @@
+//! Criterion benchmarks of the engine's hot paths on synthetic data.
+//!
+//! `cargo bench` runs them all, `cargo bench -- merge` those whose name contains `merge`.
+//! Criterion keeps each run's estimates under `target/criterion` and reports the change
+//! against the previous run; `--save-baseline` and `--baseline` compare against a named one.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use databento::dbn::encode::dbn::Encoder;
+use databento::dbn::encode::{DbnEncodable, EncodeRecord};
+use databento::dbn::record::str_to_c_chars;
+use databento::dbn::{InstrumentDefMsg, Metadata, RType, RecordHeader, SType, Schema, TradeMsg};
+use futures::StreamExt;
+use inkback::backtester::{run_backtest_on_stream, BacktestOptions};
+use inkback::event::MarketEvent;
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{Order, OrderType, Strategy};
+use inkback::utils::aggressor::{ClassificationStats, SideClassifier};
+use inkback::utils::fetch::{footprint_to_csv, get_file_stream, merge_streams_to_csv};
+use inkback::utils::synthetic::{self, SyntheticMarket};
+use inkback::vectorized::{sma, Bars, VectorBacktest};
+use inkback::UnderlyingQuotes;
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const EVENTS: usize = 1_000_000;
+const MERGE_STEPS: usize = 200_000;
+// A run of the slow paths takes a few hundred milliseconds, so Criterion's minimum of 10
+// samples in a few seconds is enough
+const SAMPLES: usize = 10;
+const MEASUREMENT: Duration = Duration::from_secs(5);
+/// Long/short moving average cross, so the inner loop fills and marks positions
+struct MaCross {
+    closes: VecDeque<f64>,
+    long: Option<bool>,
+}
+
+impl Strategy for MaCross {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let price = event.price();
+        self.closes.push_back(price);
+        if self.closes.len() > 50 {
+            self.closes.pop_front();
+        }
+        if self.closes.len() < 50 {
+            return None;
+        }
+        let fast = self.closes.iter().rev().take(10).sum::<f64>() / 10.0;
+        let slow = self.closes.iter().sum::<f64>() / 50.0;
+        let want_long = fast > slow;
+        match self.long {
+            Some(long) if long == want_long => None,
+            Some(long) => {
+                self.long = None;
+                let exit = if long {
+                    OrderType::MarketSell
+                } else {
+                    OrderType::MarketBuy
+                };
+                Some(Order::new(exit, price))
+            }
+            None => {
+                self.long = Some(want_long);
+                let entry = if want_long {
+                    OrderType::MarketBuy
+                } else {
+                    OrderType::MarketSell
+                };
+                Some(Order::new(entry, price))
+            }
+        }
+    }
+}
+
+fn metadata(schema: Schema) -> Metadata {
+    Metadata::builder()
+        .dataset("SYNTHETIC")
+        .schema(Some(schema))
+        .start(0)
+        .stype_in(Some(SType::RawSymbol))
+        .stype_out(SType::InstrumentId)
+        .build()
+}
+
+fn write_dbn<R: DbnEncodable>(path: &Path, schema: Schema, records: impl Iterator<Item = R>) {
+    let file = std::fs::File::create(path).expect("bench data directory is writable");
+    let mut encoder = Encoder::with_zstd(file, &metadata(schema)).expect("DBN encoder");
+    for record in records {
+        encoder.encode_record(&record).expect("DBN record encodes");
+    }
+}
+
+// Trades at 100ms, so footprint bars collect a few hundred prints each
+fn trades_file(dir: &Path, market: &SyntheticMarket) -> PathBuf {
+    let path = dir.join("trades.dbn.zst");
+    write_dbn(
+        &path,
+        Schema::Trades,
+        market.trades(EVENTS).into_iter().filter_map(|e| match e {
+            MarketEvent::Trade(t) => Some(t),
+            _ => None,
+        }),
+    );
+    path
+}
+
+// Underlying quotes, option trades and their definitions of the synthetic merged chain
+fn options_files(dir: &Path, market: &SyntheticMarket) -> (PathBuf, PathBuf, PathBuf) {
+    let events = market.merged_options(MERGE_STEPS, 30);
+    let underlying = dir.join("underlying.mbp1.dbn.zst");
+    let options = dir.join("options.trades.dbn.zst");
+    let definitions = dir.join("options.definition.dbn.zst");
+
+    write_dbn(
+        &underlying,
+        Schema::Mbp1,
+        events.iter().filter_map(|e| match e {
+            MarketEvent::Mbp1(m) => Some(m.clone()),
+            _ => None,
+        }),
+    );
+    let trades: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            MarketEvent::OptionTrade(o) => Some(o),
+            _ => None,
+        })
+        .collect();
+    write_dbn(
+        &options,
+        Schema::Trades,
+        trades.iter().map(|o| TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(RType::Mbp0.into(), 0, o.instrument_id, o.ts_event),
+            price: (o.price * 1e9).round() as i64,
+            size: o.size as u32,
+            ts_recv: o.ts_event,
+            ..TradeMsg::default()
+        }),
+    );
+
+    let mut listed: Vec<_> = trades.iter().map(|o| o.instrument_id).collect();
+    listed.sort_unstable();
+    listed.dedup();
+    write_dbn(
+        &definitions,
+        Schema::Definition,
+        listed.into_iter().map(|id| {
+            let trade = trades.iter().find(|o| o.instrument_id == id).unwrap();
+            InstrumentDefMsg {
+                hd: RecordHeader::new::<InstrumentDefMsg>(
+                    RType::InstrumentDef.into(),
+                    0,
+                    id,
+                    market.start_ts,
+                ),
+                raw_symbol: str_to_c_chars(&trade.symbol).expect("symbol fits"),
+                strike_price: (trade.strike_price * 1e9).round() as i64,
+                expiration: trade.expiration,
+                instrument_class: trade.option_type.as_bytes()[0] as _,
+                ..InstrumentDefMsg::default()
+            }
+        }),
+    );
+    (underlying, options, definitions)
+}
+
+// Read every event of a data file, returning how many there were
+fn drain(runtime: &tokio::runtime::Runtime, path: &str, schema: Schema) -> usize {
+    runtime.block_on(async {
+        let mut events = get_file_stream(path, schema).await.unwrap();
+        let mut n = 0;
+        while let Some(event) = events.next().await {
+            black_box(event.unwrap());
+            n += 1;
+        }
+        n
+    })
+}
+
+/// Synthetic data shared by the benchmarks, the files written the first time one needs
+/// them so a filtered run only pays for its own
+struct Fixture {
+    runtime: tokio::runtime::Runtime,
+    dir: PathBuf,
+    market: SyntheticMarket,
+    trades: Vec<MarketEvent>,
+    trades_file: OnceLock<PathBuf>,
+    footprint_csv: OnceLock<PathBuf>,
+    merged_csv: OnceLock<PathBuf>,
+    options_files: OnceLock<(PathBuf, PathBuf, PathBuf)>,
+}
+
+impl Fixture {
+    fn get() -> &'static Fixture {
+        static FIXTURE: OnceLock<Fixture> = OnceLock::new();
+        FIXTURE.get_or_init(|| {
+            // Rewritten on every run, next to Criterion's own output
+            let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/inkback-bench");
+            std::fs::create_dir_all(&dir).expect("bench data directory");
+            let market = SyntheticMarket::new(7).with_start(1_704_205_800_000_000_000, 100_000_000);
+            Fixture {
+                runtime: tokio::runtime::Runtime::new().expect("tokio runtime"),
+                trades: market.trades(EVENTS),
+                dir,
+                market,
+                trades_file: OnceLock::new(),
+                footprint_csv: OnceLock::new(),
+                merged_csv: OnceLock::new(),
+                options_files: OnceLock::new(),
+            }
+        })
+    }
+
+    fn trades_file(&self) -> &str {
+        self.trades_file
+            .get_or_init(|| trades_file(&self.dir, &self.market))
+            .to_str()
+            .unwrap()
+    }
+
+    fn options_files(&self) -> &(PathBuf, PathBuf, PathBuf) {
+        self.options_files
+            .get_or_init(|| options_files(&self.dir, &self.market))
+    }
+
+    // Footprint bars of the trades at 15s
+    fn write_footprints(&self) -> ClassificationStats {
+        let path = self.dir.join("footprint.csv");
+        self.runtime
+            .block_on(footprint_to_csv(
+                self.trades_file(),
+                path.to_str().unwrap(),
+                15_000_000_000,
+                &SideClassifier::default(),
+            ))
+            .unwrap()
+    }
+
+    fn footprint_csv(&self) -> &str {
+        self.footprint_csv
+            .get_or_init(|| {
+                self.write_footprints();
+                self.dir.join("footprint.csv")
+            })
+            .to_str()
+            .unwrap()
+    }
+
+    // The k-way merge of the options files into one CSV
+    fn write_merged(&self) {
+        let (underlying, options, definitions) = self.options_files();
+        self.runtime
+            .block_on(merge_streams_to_csv(
+                underlying.to_str().unwrap(),
+                UnderlyingQuotes::Mbp1,
+                &[options.to_str().unwrap().to_string()],
+                definitions.to_str().unwrap(),
+                self.dir.join("merged.csv.zst").to_str().unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn merged_csv(&self) -> &str {
+        self.merged_csv
+            .get_or_init(|| {
+                self.write_merged();
+                self.dir.join("merged.csv.zst")
+            })
+            .to_str()
+            .unwrap()
+    }
+}
+
+fn decode(c: &mut Criterion) {
+    let fixture = Fixture::get();
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(SAMPLES).measurement_time(MEASUREMENT);
+
+    group.throughput(Throughput::Elements(EVENTS as u64));
+    group.bench_function("trades", |b| {
+        let path = fixture.trades_file();
+        b.iter(|| drain(&fixture.runtime, path, Schema::Trades))
+    });
+    group.throughput(Throughput::Elements((EVENTS / 150) as u64));
+    group.bench_function("footprint_csv", |b| {
+        let path = fixture.footprint_csv();
+        b.iter(|| drain(&fixture.runtime, path, Schema::Trades))
+    });
+    group.throughput(Throughput::Elements(2 * MERGE_STEPS as u64));
+    group.bench_function("merged_csv", |b| {
+        let path = fixture.merged_csv();
+        b.iter(|| drain(&fixture.runtime, path, Schema::Trades))
+    });
+    group.finish();
+}
+
+fn backtest(c: &mut Criterion) {
+    let fixture = Fixture::get();
+    let mut group = c.benchmark_group("backtest");
+    group
+        .sample_size(SAMPLES)
+        .measurement_time(MEASUREMENT)
+        .throughput(Throughput::Elements(EVENTS as u64));
+    group.bench_function("ma_cross", |b| {
+        b.iter_batched(
+            || fixture.trades.clone(),
+            |events| {
+                let mut strategy = MaCross {
+                    closes: VecDeque::new(),
+                    long: None,
+                };
+                fixture
+                    .runtime
+                    .block_on(run_backtest_on_stream(
+                        "SYN",
+                        synthetic::stream(events),
+                        &mut strategy,
+                        TransactionCosts::equity_trading(),
+                        100_000.0,
+                        1.0,
+                        None,
+                        None,
+                        &BacktestOptions::default(),
+                    ))
+                    .unwrap()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn vectorized(c: &mut Criterion) {
+    let fixture = Fixture::get();
+    let mut group = c.benchmark_group("vectorized");
+    group
+        .sample_size(SAMPLES)
+        .throughput(Throughput::Elements(EVENTS as u64));
+    group.bench_function("ma_cross", |b| {
+        let vectorized = VectorBacktest::new(
+            Bars::from_events(&fixture.trades),
+            &TransactionCosts::equity_trading(),
+            100_000.0,
+            1.0,
+        );
+        b.iter(|| {
+            let bars = vectorized.bars();
+            let (fast, slow) = (sma(&bars.close, 10), sma(&bars.close, 50));
+            let positions: Vec<f64> = fast
+                .iter()
+                .zip(&slow)
+                .map(|(f, s)| if f > s { 1.0 } else { -1.0 })
+                .collect();
+            vectorized.run(&positions).unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn costs(c: &mut Criterion) {
+    let fixture = Fixture::get();
+    let prices: Vec<f64> = fixture.trades.iter().map(MarketEvent::price).collect();
+    let mut group = c.benchmark_group("costs");
+    group
+        .sample_size(SAMPLES)
+        .throughput(Throughput::Elements(prices.len() as u64));
+    for (name, costs) in [
+        ("equity", TransactionCosts::equity_trading()),
+        ("futures", TransactionCosts::futures_trading(0.25)),
+        ("options", TransactionCosts::options_trading()),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                prices
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &price)| {
+                        let size = (i % 10 + 1) as f64;
+                        costs.calculate_entry_cost(price, size, 500.0)
+                            + costs.calculate_exit_cost(price, size, 500.0)
+                    })
+                    .sum::<f64>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn footprint(c: &mut Criterion) {
+    let fixture = Fixture::get();
+    let mut group = c.benchmark_group("footprint");
+    group
+        .sample_size(SAMPLES)
+        .measurement_time(MEASUREMENT)
+        .throughput(Throughput::Elements(EVENTS as u64));
+    group.bench_function("15s", |b| {
+        fixture.trades_file();
+        b.iter(|| fixture.write_footprints())
+    });
+    group.finish();
+}
+
+fn merge(c: &mut Criterion) {
+    let fixture = Fixture::get();
+    let mut group = c.benchmark_group("merge");
+    group
+        .sample_size(SAMPLES)
+        .measurement_time(MEASUREMENT)
+        .throughput(Throughput::Elements(2 * MERGE_STEPS as u64));
+    group.bench_function("options", |b| {
+        fixture.options_files();
+        b.iter(|| fixture.write_merged())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode, backtest, vectorized, costs, footprint, merge);
+criterion_main!(benches);
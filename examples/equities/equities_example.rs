@@ -1,30 +1,16 @@
 use anyhow::Result;
 use databento::dbn::{SType, Schema};
-use std::{collections::VecDeque, usize};
+use std::collections::VecDeque;
 use time::{macros::date, macros::time};
 
-mod backtester;
-mod event;
-mod plot;
-pub mod slippage_models;
-mod strategy;
-mod utils;
-
-use crate::{
+use inkback::{
     backtester::{display_results, run_parallel_backtest},
     event::MarketEvent,
     slippage_models::TransactionCosts,
-    strategy::{Order, OrderType, StrategyParams},
+    strategy::{Order, OrderType, Strategy, StrategyParams},
+    utils::fetch::fetch_and_save_data,
+    InkBackSchema,
 };
-use strategy::Strategy;
-use utils::fetch::fetch_and_save_data;
-
-// InkBack schemas
-#[derive(Clone)]
-pub enum InkBackSchema {
-    FootPrint,
-    CombinedOptionsUnderlying,
-}
 
 /// Moving Average Cross Strategy
 pub struct MovingAverageCrossStrategy {
@@ -228,7 +214,7 @@ impl Strategy for MovingAverageCrossStrategy {
         }
 
         // Get volume (handle different possible field names)
-        let volume = event.volume() as u64;
+        let volume = event.volume();
 
         // Update volume history
         self.volume_history.push_back(volume);
@@ -254,19 +240,16 @@ impl Strategy for MovingAverageCrossStrategy {
                 self.entry_price = 0.0;
                 self.entry_time.clear();
 
-                return Some(Order {
-                    order_type: exit_order,
-                    price: current_price,
-                });
+                return Some(Order::new(exit_order, current_price));
             }
 
             // Check for opposite crossover signal to close position
             if let Some(signal) = self.check_crossover_signal() {
-                let should_close = match (self.position_state, signal) {
-                    (PositionState::Long, OrderType::MarketSell) => true,
-                    (PositionState::Short, OrderType::MarketBuy) => true,
-                    _ => false,
-                };
+                let should_close = matches!(
+                    (self.position_state, signal),
+                    (PositionState::Long, OrderType::MarketSell)
+                        | (PositionState::Short, OrderType::MarketBuy)
+                );
 
                 if should_close {
                     let exit_order = match self.position_state {
@@ -280,10 +263,7 @@ impl Strategy for MovingAverageCrossStrategy {
                     self.entry_price = 0.0;
                     self.entry_time.clear();
 
-                    return Some(Order {
-                        order_type: exit_order,
-                        price: current_price,
-                    });
+                    return Some(Order::new(exit_order, current_price));
                 }
             }
 
@@ -299,18 +279,13 @@ impl Strategy for MovingAverageCrossStrategy {
 
             // Update position state
             self.position_state = match signal {
-                OrderType::MarketBuy => PositionState::Long,
-                OrderType::MarketSell => PositionState::Short,
-                OrderType::LimitBuy => todo!(),
-                OrderType::LimitSell => todo!(),
+                OrderType::MarketBuy | OrderType::LimitBuy => PositionState::Long,
+                OrderType::MarketSell | OrderType::LimitSell => PositionState::Short,
             };
             self.entry_price = current_price;
             self.entry_time = event.date_string();
 
-            return Some(Order {
-                order_type: signal,
-                price: current_price,
-            });
+            return Some(Order::new(signal, current_price));
         }
 
         None
@@ -331,9 +306,7 @@ async fn main() -> anyhow::Result<()> {
 
     // Fetch and save footprint data to CSV
     let schema = Schema::Ohlcv1H;
-    // Set the tick size for equities
-    let tick_size: f64 = 0.01;
-    let transaction_costs = TransactionCosts::equities_trading(tick_size);
+    let transaction_costs = TransactionCosts::equity_trading();
     let symbol = "SPY";
     let symbol_manager = fetch_and_save_data(
         "XNAS.ITCH",
@@ -381,9 +354,9 @@ async fn main() -> anyhow::Result<()> {
     let sorted_results = run_parallel_backtest(
         parameter_combinations,
         symbol_manager.clone(),
-        &symbol,
+        symbol,
         schema,
-        Some(InkBackSchema::FootPrint),
+        Some(InkBackSchema::footprint()),
         |params| Ok(Box::new(MovingAverageCrossStrategy::new(params)?)),
         starting_equity,
         exposure,
@@ -393,9 +366,9 @@ async fn main() -> anyhow::Result<()> {
     display_results(
         sorted_results,
         &symbol_manager.data_path,
-        &symbol,
+        symbol,
         schema,
-        Some(InkBackSchema::FootPrint),
+        Some(InkBackSchema::footprint()),
         starting_equity,
         exposure,
     )
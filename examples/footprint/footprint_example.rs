@@ -2,31 +2,19 @@ use anyhow::Result;
 use databento::dbn::{SType, Schema};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::{collections::VecDeque, usize};
+use std::collections::VecDeque;
 use time::{macros::date, macros::time};
 
-mod backtester;
-mod event;
-mod plot;
-pub mod slippage_models;
-mod strategy;
-mod utils;
-
-use crate::{
+use inkback::{
     backtester::{display_results, run_parallel_backtest},
-    event::MarketEvent,
+    event::{EventKind, MarketEvent},
+    requirements::EventRequirements,
     slippage_models::TransactionCosts,
-    strategy::{Order, OrderType, StrategyParams},
+    strategy::{Order, OrderType, Strategy, StrategyParams},
+    utils::aggressor::SideClassifier,
+    utils::fetch::fetch_and_save_data,
+    InkBackSchema,
 };
-use strategy::Strategy;
-use utils::fetch::fetch_and_save_data;
-
-// InkBack schemas
-#[derive(Clone)]
-pub enum InkBackSchema {
-    FootPrint,
-    CombinedOptionsUnderlying,
-}
 
 /// A footprint-based volume imbalance strategy
 pub struct FootprintVolumeImbalance {
@@ -47,8 +35,7 @@ impl FootprintVolumeImbalance {
     pub fn new(params: &StrategyParams) -> Result<Self, anyhow::Error> {
         let imbalance_threshold = params
             .get("imbalance_threshold")
-            .ok_or_else(|| anyhow::anyhow!("Missing imbalance_threshold parameter"))?
-            as f64;
+            .ok_or_else(|| anyhow::anyhow!("Missing imbalance_threshold parameter"))?;
         let volume_threshold = params
             .get("volume_threshold")
             .ok_or_else(|| anyhow::anyhow!("Missing volume_threshold parameter"))?
@@ -60,10 +47,10 @@ impl FootprintVolumeImbalance {
 
         let tp = params
             .get("tp")
-            .ok_or_else(|| anyhow::anyhow!("Missing tp parameter"))? as f64;
+            .ok_or_else(|| anyhow::anyhow!("Missing tp parameter"))?;
         let sl = params
             .get("sl")
-            .ok_or_else(|| anyhow::anyhow!("Missing sl parameter"))? as f64;
+            .ok_or_else(|| anyhow::anyhow!("Missing sl parameter"))?;
 
         Ok(Self {
             imbalance_threshold,
@@ -153,10 +140,16 @@ impl FootprintVolumeImbalance {
 }
 
 impl Strategy for FootprintVolumeImbalance {
+    fn requirements(&self) -> EventRequirements {
+        EventRequirements::default()
+            .with_variant(EventKind::Footprint)
+            .with_field("footprint_data")
+    }
+
     fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
         let close = event.price();
 
-        let volume = event.volume() as u64;
+        let volume = event.volume();
 
         // Add event to history
         self.event_history.push_back(event.clone());
@@ -173,32 +166,24 @@ impl Strategy for FootprintVolumeImbalance {
         // If in a position, check TP/SL
         if let (Some(position), Some(entry)) = (self.current_position, self.entry_price) {
             match position {
-                OrderType::MarketBuy => {
+                OrderType::MarketBuy | OrderType::LimitBuy => {
                     if close >= entry * (1.0 + self.tp) || close <= entry * (1.0 - self.sl) {
                         //println!("Exiting BUY position: close={:.2}, entry={:.2}, tp_level={:.2}, sl_level={:.2}",
                         //        close, entry, entry * (1.0 + self.tp), entry * (1.0 - self.sl));
                         self.current_position = None;
                         self.entry_price = None;
-                        return Some(Order {
-                            order_type: OrderType::MarketSell,
-                            price: close,
-                        });
+                        return Some(Order::new(OrderType::MarketSell, close));
                     }
                 }
-                OrderType::MarketSell => {
+                OrderType::MarketSell | OrderType::LimitSell => {
                     if close <= entry * (1.0 - self.tp) || close >= entry * (1.0 + self.sl) {
                         //println!("Exiting SELL position: close={:.2}, entry={:.2}, tp_level={:.2}, sl_level={:.2}",
                         //        close, entry, entry * (1.0 - self.tp), entry * (1.0 + self.sl));
                         self.current_position = None;
                         self.entry_price = None;
-                        return Some(Order {
-                            order_type: OrderType::MarketBuy,
-                            price: close,
-                        });
+                        return Some(Order::new(OrderType::MarketBuy, close));
                     }
                 }
-                OrderType::LimitBuy => todo!(),
-                OrderType::LimitSell => todo!(),
             }
         }
 
@@ -258,10 +243,7 @@ impl Strategy for FootprintVolumeImbalance {
                 self.last_signal = Some(signal);
                 self.current_position = Some(signal);
                 self.entry_price = Some(close);
-                return Some(Order {
-                    order_type: signal,
-                    price: close,
-                });
+                return Some(Order::new(signal, close));
             } else {
                 //println!("Signal {:?} matches last signal, skipping", signal);
             }
@@ -290,13 +272,18 @@ async fn main() -> anyhow::Result<()> {
     let transaction_costs = TransactionCosts::futures_trading(es_tick_size);
     let symbol = "NQ.v.0";
     let bar_interval = 15_000_000_000u64; // 15 seconds
+                                          // DataBento's side byte; SideClassifier::TickRule for venues that leave it unset
+    let custom_schema = InkBackSchema::FootPrint {
+        sides: SideClassifier::default(),
+        quotes: false,
+    };
     let symbol_manager = fetch_and_save_data(
         "GLBX.MDP3",
         SType::Continuous,
         symbol,
         None,
         schema,
-        Some(InkBackSchema::FootPrint),
+        Some(custom_schema.clone()),
         start,
         end,
         Some(bar_interval),
@@ -333,9 +320,9 @@ async fn main() -> anyhow::Result<()> {
     let sorted_results = run_parallel_backtest(
         parameter_combinations,
         symbol_manager.clone(),
-        &symbol,
+        symbol,
         schema,
-        Some(InkBackSchema::FootPrint),
+        Some(custom_schema.clone()),
         |params| Ok(Box::new(FootprintVolumeImbalance::new(params)?)),
         starting_equity,
         exposure,
@@ -345,9 +332,9 @@ async fn main() -> anyhow::Result<()> {
     display_results(
         sorted_results,
         &symbol_manager.data_path,
-        &symbol,
+        symbol,
         schema,
-        Some(InkBackSchema::FootPrint),
+        Some(custom_schema.clone()),
         starting_equity,
         exposure,
     )
@@ -1,30 +1,17 @@
 use anyhow::Result;
 use databento::dbn::{SType, Schema};
-use std::{collections::VecDeque, usize};
+use std::collections::VecDeque;
 use time::{macros::date, macros::time};
 
-mod backtester;
-mod event;
-mod plot;
-pub mod slippage_models;
-mod strategy;
-mod utils;
-
-use crate::{
+use inkback::{
     backtester::{display_results, run_parallel_backtest},
-    event::MarketEvent,
+    event::{EventKind, MarketEvent},
+    requirements::EventRequirements,
     slippage_models::TransactionCosts,
-    strategy::{Order, OrderType, StrategyParams},
+    strategy::{Order, OrderType, Strategy, StrategyParams},
+    utils::fetch::fetch_and_save_data,
+    InkBackSchema, UnderlyingQuotes,
 };
-use strategy::Strategy;
-use utils::fetch::fetch_and_save_data;
-
-// InkBack schemas
-#[derive(Clone)]
-pub enum InkBackSchema {
-    FootPrint,
-    CombinedOptionsUnderlying,
-}
 
 /// Option Momentum Strategy
 pub struct OptionsMomentumStrategy {
@@ -153,7 +140,7 @@ impl OptionsMomentumStrategy {
 
         // Expiration, must be positive
         let expiration = event.get_u64("expiration")?;
-        if expiration <= 0 {
+        if expiration == 0 {
             return None;
         }
 
@@ -175,40 +162,20 @@ impl OptionsMomentumStrategy {
     fn should_trade_option(&self, event: &MarketEvent) -> Option<OrderType> {
         // Attempt to parse info
         let parse_result = self.parse_option_info(event);
-        if parse_result.is_none() {
-            // println!("No parse result");
-            return None;
-        }
-        let (option_type, strike_price, expiration, _instrument_id, _symbol, _price) =
+        parse_result.as_ref()?;
+        let (option_type, strike_price, _expiration, _instrument_id, _symbol, _price) =
             parse_result?;
 
-        let lower = strike_price as f64 * 0.5;
-        let upper = strike_price as f64 * 1.5;
-        let within_50pct = (strike_price as f64) >= lower && (strike_price as f64) <= upper;
+        let lower = strike_price * 0.5;
+        let upper = strike_price * 1.5;
+        let within_50pct = strike_price >= lower && strike_price <= upper;
         if !within_50pct {
             return None;
         }
 
-        // Check days to expiration
-        let current_time_ns = event.timestamp();
-
-        // Validate that we have valid timestamps
-        if current_time_ns == 0 || expiration == 0 {
-            return None;
-        }
-
-        // Convert nanoseconds to seconds
-        let current_time = current_time_ns as f64 / 1_000_000_000.0;
-        let expiration_seconds = expiration as f64 / 1_000_000_000.0;
-
-        // Validate that expiration is in the future
-        if expiration_seconds <= current_time {
-            return None;
-        }
-
-        let days_to_expiry = (expiration_seconds - current_time) / 86400.0;
-
-        if days_to_expiry <= self.min_days_to_expiry {
+        // Check days to expiration, skipping expired contracts
+        let now = event.timestamp();
+        if event.is_expired(now) || event.days_to_expiry(now)? <= self.min_days_to_expiry {
             return None;
         }
 
@@ -274,22 +241,25 @@ impl OptionsMomentumStrategy {
 }
 
 impl Strategy for OptionsMomentumStrategy {
+    fn requirements(&self) -> EventRequirements {
+        EventRequirements::default()
+            .with_variant(EventKind::OptionTrade)
+            .with_field("underlying_bid")
+            .with_field("underlying_ask")
+    }
+
     fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
         // First, always try to update underlying state from any event that has underlying data
-        if let Some(underlying_bid) = event.get("underlying_bid") {
-            if let Some(underlying_ask) = event.get("underlying_ask") {
-                let underlying_price = (underlying_bid + underlying_ask) / 2.0;
-
-                // Update history
-                self.underlying_history.push_back(underlying_price);
-                if self.underlying_history.len() > self.lookback_periods + 1 {
-                    self.underlying_history.pop_front();
-                }
+        if let Some(underlying_price) = event.underlying_mid() {
+            // Update history
+            self.underlying_history.push_back(underlying_price);
+            if self.underlying_history.len() > self.lookback_periods + 1 {
+                self.underlying_history.pop_front();
             }
         }
 
         // Update volume history from any event
-        let size = event.volume() as u64;
+        let size = event.volume();
         self.volume_history.push_back(size);
         if self.volume_history.len() > self.lookback_periods + 1 {
             self.volume_history.pop_front();
@@ -301,9 +271,7 @@ impl Strategy for OptionsMomentumStrategy {
         }
 
         // Get current prices for option trading
-        let underlying_bid = event.get("underlying_bid")?;
-        let underlying_ask = event.get("underlying_ask")?;
-        let underlying_price = (underlying_bid + underlying_ask) / 2.0;
+        let underlying_price = event.underlying_mid()?;
         let option_price = event.price();
 
         // If we're in a position, check for exit conditions first
@@ -323,10 +291,7 @@ impl Strategy for OptionsMomentumStrategy {
                             self.position_state = PositionState::Flat;
                             self.current_contract = None;
 
-                            return Some(Order {
-                                order_type: OrderType::MarketSell,
-                                price: option_price,
-                            });
+                            return Some(Order::new(OrderType::MarketSell, option_price));
                         }
                     }
                 }
@@ -362,17 +327,12 @@ impl Strategy for OptionsMomentumStrategy {
 
                 // Update position state
                 self.position_state = match order_type {
-                    OrderType::MarketBuy => PositionState::Long,
-                    OrderType::MarketSell => PositionState::Short,
-                    OrderType::LimitBuy => todo!(),
-                    OrderType::LimitSell => todo!(),
+                    OrderType::MarketBuy | OrderType::LimitBuy => PositionState::Long,
+                    OrderType::MarketSell | OrderType::LimitSell => PositionState::Short,
                 };
                 self.current_contract = Some(contract_info);
 
-                return Some(Order {
-                    order_type,
-                    price: option_price,
-                });
+                return Some(Order::new(order_type, option_price));
             }
         }
 
@@ -396,13 +356,17 @@ async fn main() -> anyhow::Result<()> {
     let schema = Schema::Trades;
     let transaction_costs = TransactionCosts::options_trading();
     let symbol = "CL.v.0";
+    // Underlying top of book on every update; Bbo1s or Trades cost less to download
+    let custom_schema = InkBackSchema::CombinedOptionsUnderlying {
+        underlying: UnderlyingQuotes::Mbp1,
+    };
     let symbol_manager = fetch_and_save_data(
         "GLBX.MDP3",
         SType::Continuous,
         symbol,
         Some("LO.OPT"),
         schema,
-        Some(InkBackSchema::CombinedOptionsUnderlying),
+        Some(custom_schema.clone()),
         start,
         end,
         None,
@@ -439,9 +403,9 @@ async fn main() -> anyhow::Result<()> {
     let sorted_results = run_parallel_backtest(
         parameter_combinations,
         symbol_manager.clone(),
-        &symbol,
+        symbol,
         schema,
-        Some(InkBackSchema::CombinedOptionsUnderlying),
+        Some(custom_schema.clone()),
         |params| Ok(Box::new(OptionsMomentumStrategy::new(params)?)),
         starting_equity,
         exposure,
@@ -451,9 +415,9 @@ async fn main() -> anyhow::Result<()> {
     display_results(
         sorted_results,
         &symbol_manager.data_path,
-        &symbol,
+        symbol,
         schema,
-        Some(InkBackSchema::CombinedOptionsUnderlying),
+        Some(custom_schema.clone()),
         starting_equity,
         exposure,
     )
@@ -1,33 +1,17 @@
 use anyhow::Result;
 use databento::dbn::{SType, Schema};
-use std::{collections::VecDeque, usize};
+use std::collections::VecDeque;
 use time::{macros::date, macros::time};
 
-mod backtester;
-mod event;
-mod plot;
-pub mod slippage_models;
-mod strategy;
-mod utils;
-mod walkforward;
-
-use crate::{
+use inkback::{
     event::MarketEvent,
     slippage_models::TransactionCosts,
-    strategy::{Order, OrderType, StrategyParams},
+    strategy::{Order, OrderType, Strategy, StrategyParams},
+    utils::fetch::fetch_and_save_data,
     walkforward::{
         display_walk_forward_results, plot_walk_forward, run_walk_forward, WalkForwardConfig,
     },
 };
-use strategy::Strategy;
-use utils::fetch::fetch_and_save_data;
-
-// InkBack schemas
-#[derive(Clone)]
-pub enum InkBackSchema {
-    FootPrint,
-    CombinedOptionsUnderlying,
-}
 
 /// Moving Average Cross Strategy
 pub struct MovingAverageCrossStrategy {
@@ -231,7 +215,7 @@ impl Strategy for MovingAverageCrossStrategy {
         }
 
         // Get volume (handle different possible field names)
-        let volume = event.volume() as u64;
+        let volume = event.volume();
 
         // Update volume history
         self.volume_history.push_back(volume);
@@ -257,19 +241,16 @@ impl Strategy for MovingAverageCrossStrategy {
                 self.entry_price = 0.0;
                 self.entry_time.clear();
 
-                return Some(Order {
-                    order_type: exit_order,
-                    price: current_price,
-                });
+                return Some(Order::new(exit_order, current_price));
             }
 
             // Check for opposite crossover signal to close position
             if let Some(signal) = self.check_crossover_signal() {
-                let should_close = match (self.position_state, signal) {
-                    (PositionState::Long, OrderType::MarketSell) => true,
-                    (PositionState::Short, OrderType::MarketBuy) => true,
-                    _ => false,
-                };
+                let should_close = matches!(
+                    (self.position_state, signal),
+                    (PositionState::Long, OrderType::MarketSell)
+                        | (PositionState::Short, OrderType::MarketBuy)
+                );
 
                 if should_close {
                     let exit_order = match self.position_state {
@@ -283,10 +264,7 @@ impl Strategy for MovingAverageCrossStrategy {
                     self.entry_price = 0.0;
                     self.entry_time.clear();
 
-                    return Some(Order {
-                        order_type: exit_order,
-                        price: current_price,
-                    });
+                    return Some(Order::new(exit_order, current_price));
                 }
             }
 
@@ -302,18 +280,13 @@ impl Strategy for MovingAverageCrossStrategy {
 
             // Update position state
             self.position_state = match signal {
-                OrderType::MarketBuy => PositionState::Long,
-                OrderType::MarketSell => PositionState::Short,
-                OrderType::LimitBuy => todo!(),
-                OrderType::LimitSell => todo!(),
+                OrderType::MarketBuy | OrderType::LimitBuy => PositionState::Long,
+                OrderType::MarketSell | OrderType::LimitSell => PositionState::Short,
             };
             self.entry_price = current_price;
             self.entry_time = event.date_string();
 
-            return Some(Order {
-                order_type: signal,
-                price: current_price,
-            });
+            return Some(Order::new(signal, current_price));
         }
 
         None
@@ -0,0 +1,185 @@
+use crate::backtester::{run_backtest_on_stream, BacktestOptions, BacktestResult};
+use crate::plot::{plot_equity_curves, PlotConfig};
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::{Strategy, StrategyParams};
+use crate::utils::fetch::{self, BacktestManager, MarketStream};
+use crate::utils::snapshot::fingerprint_data;
+use crate::InkBackSchema;
+use anyhow::{anyhow, Result};
+use databento::dbn::Schema;
+use futures::{stream, StreamExt};
+use tokio::sync::mpsc;
+
+/// Costs and latency of one account the strategy is simulated for
+#[derive(Debug, Clone)]
+pub struct AccountProfile {
+    pub name: String,
+    pub costs: TransactionCosts,
+    /// See [`BacktestOptions::latency`]
+    pub latency: u64,
+}
+
+impl AccountProfile {
+    pub fn new(name: &str, costs: TransactionCosts) -> Self {
+        Self {
+            name: name.to_string(),
+            costs,
+            latency: 0,
+        }
+    }
+
+    /// Zero costs and latency, the reference cost drag is measured from
+    pub fn ideal() -> Self {
+        Self::new("Ideal", TransactionCosts::zero())
+    }
+
+    pub fn with_latency(mut self, latency: u64) -> Self {
+        self.latency = latency;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct AccountResult {
+    pub profile: AccountProfile,
+    pub result: BacktestResult,
+}
+
+/// Events buffered per account before the reader waits for the slowest one
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// Run one instance of the strategy per account profile in a single pass over the data.
+///
+/// Events are decoded once and handed to every account's engine, each with its own
+/// strategy instance, costs and latency, so the runs differ only by their profiles.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_accounts<F>(
+    profiles: &[AccountProfile],
+    params: &StrategyParams,
+    strategy_constructor: F,
+    backtest_manager: &BacktestManager,
+    symbol: &str,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    starting_equity: f64,
+    exposure: f64,
+    time_range: Option<(u64, u64)>,
+    options: &BacktestOptions,
+) -> Result<Vec<AccountResult>>
+where
+    F: Fn(&StrategyParams) -> Result<Box<dyn Strategy>>,
+{
+    if profiles.is_empty() {
+        return Ok(Vec::new());
+    }
+    if backtest_manager.data_path.is_empty() {
+        return Err(anyhow!("No data path provided"));
+    }
+    let time_range = match &backtest_manager.holdout {
+        Some(holdout) => holdout.in_sample(time_range)?,
+        None => time_range,
+    };
+
+    let data_files = fingerprint_data(&backtest_manager.data_path)?;
+    let mut data = fetch::get_data_stream(&backtest_manager.data_path, schema).await?;
+
+    let mut senders = Vec::with_capacity(profiles.len());
+    let mut runs = Vec::with_capacity(profiles.len());
+    for profile in profiles {
+        let mut strategy = strategy_constructor(params)?;
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        senders.push(tx);
+
+        let events: MarketStream = Box::pin(stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|event| (event, rx))
+        }));
+        let options = BacktestOptions {
+            latency: profile.latency,
+            ..options.clone()
+        };
+        let custom_schema = custom_schema.clone();
+        runs.push(async move {
+            run_backtest_on_stream(
+                symbol,
+                events,
+                strategy.as_mut(),
+                profile.costs.clone(),
+                starting_equity,
+                exposure,
+                custom_schema,
+                time_range,
+                &options,
+            )
+            .await
+        });
+    }
+
+    let reader = async move {
+        while let Some(event) = data.next().await {
+            match event {
+                Ok(event) => {
+                    for tx in &senders {
+                        // An account that stopped early, e.g. at the end of its time
+                        // range, no longer needs events
+                        let _ = tx.send(Ok(event.clone())).await;
+                    }
+                }
+                Err(e) => {
+                    for tx in &senders {
+                        let _ = tx.send(Err(anyhow!("{}", e))).await;
+                    }
+                    break;
+                }
+            }
+        }
+        // Dropping the senders ends every account's stream
+    };
+
+    let ((), results) = futures::join!(reader, futures::future::join_all(runs));
+
+    profiles
+        .iter()
+        .zip(results)
+        .map(|(profile, result)| {
+            let mut result = result?;
+            result.data_files = data_files.clone();
+            Ok(AccountResult {
+                profile: profile.clone(),
+                result,
+            })
+        })
+        .collect()
+}
+
+/// Side-by-side account results, with each account's cost drag against the best return
+pub fn display_accounts(accounts: &[AccountResult]) {
+    println!("\n=== ACCOUNTS ===");
+    let best = accounts
+        .iter()
+        .map(|a| a.result.total_return_pct)
+        .filter(|r| r.is_finite())
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    for account in accounts {
+        let result = &account.result;
+        println!(
+            "{}: Ret: {:.2}%, DD: {:.2}%, Sharpe: {:.2}, Trades: {}, Fees: ${:.0}, Latency: {:.3}ms, Drag: {:.2}%",
+            account.profile.name,
+            result.total_return_pct,
+            result.max_drawdown_pct,
+            result.sharpe_ratio,
+            result.total_trades,
+            result.total_transaction_costs,
+            account.profile.latency as f64 / 1e6,
+            best - result.total_return_pct
+        );
+    }
+}
+
+pub fn plot_accounts(accounts: &[AccountResult]) {
+    let curves = accounts
+        .iter()
+        .map(|a| (a.profile.name.clone(), a.result.equity_curve.clone()))
+        .collect();
+    plot_equity_curves(curves, None, PlotConfig::default());
+}
@@ -0,0 +1,186 @@
+use crate::backtester::{BlockReason, BlockedEntry};
+use crate::strategy::{CancelReason, Order, OrderType};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+/// Who placed an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderOrigin {
+    Strategy,
+    /// The engine closing or reopening a position under a [`RollPolicy`]
+    ///
+    /// [`RollPolicy`]: crate::backtester::RollPolicy
+    Roll,
+    /// An option settled at intrinsic value on expiry
+    Expiration,
+    /// A position closed by its entry order's bracket, see [`Order::with_bracket`]
+    Bracket,
+    /// A position closed by its entry order's time stop, see [`Order::with_max_holding`]
+    TimeStop,
+    /// A position liquidated at the end of the data
+    EndOfData,
+}
+
+/// What happened to an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// The strategy emitted the order
+    Submitted,
+    Filled {
+        fill_price: f64,
+        quantity: f64,
+    },
+    /// A partial exit, `remaining` units of the position stay open
+    PartialFill {
+        fill_price: f64,
+        quantity: f64,
+        remaining: f64,
+    },
+    /// Refused by the entry guards, see [`BlockedEntry`]
+    Rejected {
+        reason: BlockReason,
+    },
+    Cancelled {
+        reason: CancelReason,
+    },
+    /// Refused by a pre-trade check before reaching the engine, see [`PreTradeCheck`]
+    ///
+    /// [`PreTradeCheck`]: crate::compliance::PreTradeCheck
+    Refused {
+        check: String,
+        reason: String,
+    },
+    /// Accepted but never acted on, e.g. an add without pyramiding or an entry while a
+    /// position is already open
+    Ignored {
+        reason: String,
+    },
+}
+
+/// One entry of the order audit trail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the trail, records of one event keep the order they happened in
+    pub seq: u64,
+    pub ts: u64,
+    pub origin: OrderOrigin,
+    pub order_type: OrderType,
+    /// Price of the order as placed
+    pub price: f64,
+    pub quantity: Option<f64>,
+    pub instrument: Option<u32>,
+    #[serde(default)]
+    pub reduce_only: bool,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+/// Collects the audit trail of a run when enabled, otherwise records nothing
+#[derive(Debug, Default)]
+pub(crate) struct AuditLog {
+    enabled: bool,
+    records: Vec<AuditRecord>,
+    // Blocked entries already turned into rejections
+    rejections_seen: usize,
+}
+
+impl AuditLog {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        ts: u64,
+        order: &Order,
+        origin: OrderOrigin,
+        event: AuditEvent,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        self.records.push(AuditRecord {
+            seq: self.records.len() as u64,
+            ts,
+            origin,
+            order_type: order.order_type,
+            price: order.price,
+            quantity: order.quantity,
+            instrument: order.instrument,
+            reduce_only: order.reduce_only,
+            event,
+        });
+    }
+
+    /// A fill the engine placed itself, with no strategy order behind it
+    pub(crate) fn engine_fill(
+        &mut self,
+        ts: u64,
+        order_type: OrderType,
+        fill_price: f64,
+        quantity: f64,
+        origin: OrderOrigin,
+    ) {
+        let order = Order::new(order_type, fill_price).with_quantity(quantity);
+        self.record(
+            ts,
+            &order,
+            origin,
+            AuditEvent::Filled {
+                fill_price,
+                quantity,
+            },
+        );
+    }
+
+    /// Turn entries blocked since the last call into rejections
+    pub(crate) fn rejections(&mut self, blocked: &[BlockedEntry]) {
+        if !self.enabled {
+            return;
+        }
+        for entry in &blocked[self.rejections_seen.min(blocked.len())..] {
+            let order = Order::new(entry.order_type, entry.price);
+            self.record(
+                entry.ts,
+                &order,
+                OrderOrigin::Strategy,
+                AuditEvent::Rejected {
+                    reason: entry.reason,
+                },
+            );
+        }
+        self.rejections_seen = blocked.len();
+    }
+
+    pub(crate) fn into_records(self) -> Vec<AuditRecord> {
+        self.records
+    }
+}
+
+/// Shorthand for an [`AuditEvent::Ignored`]
+pub(crate) fn ignored(reason: &str) -> AuditEvent {
+    AuditEvent::Ignored {
+        reason: reason.to_string(),
+    }
+}
+
+/// Write an audit trail as JSON lines, one record per line
+pub fn write_audit_jsonl(records: &[AuditRecord], path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let mut file = std::io::BufWriter::new(
+        std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?,
+    );
+    for record in records {
+        serde_json::to_writer(&mut file, record)?;
+        writeln!(file)?;
+    }
+    file.flush()?;
+    Ok(())
+}
@@ -1,9 +1,39 @@
-use crate::event::MarketEvent;
-use crate::slippage_models::TransactionCosts;
-use crate::utils::fetch::{self, BacktestManager};
+use crate::audit::{ignored, AuditEvent, AuditLog, AuditRecord, OrderOrigin};
+use crate::beta::{self, BetaConfig, BetaPoint, DailyMarks};
+use crate::capital::{self, CapitalTracker, CapitalUsage, FuturesMargin};
+use crate::compliance::{self, OrderCheck, PreTradeChecks, PreTradeRejection};
+use crate::context::{EventCtx, IndicatorRecorder, IndicatorSeries};
+use crate::event::{self, MarketEvent, OptionTradeMsg};
+use crate::greeks::{self, Greeks, GreeksPoint};
+use crate::hedge::{HedgeBook, HedgedExposure, LegSummary, UNDERLYING_LEG};
+use crate::indicators::{BarHistory, OptionLiquidity, RollingVolatility, VwapConfig, VwapService};
+use crate::invariants::{EngineState, Invariants};
+use crate::ledger::{Ledger, LedgerConfig, LedgerEntry, LedgerPosition};
+use crate::marginals;
+use crate::metadata::{self, RunMetadata};
+use crate::microstructure::book_tick_size;
+use crate::orderflow::FootprintService;
+use crate::progress::{ProgressConfig, ProgressMeter};
+use crate::regimes::{self, DailyBars, RegimeConfig, RegimeDay};
+use crate::requirements;
+use crate::seeds::Seeds;
+use crate::signals::{ExternalSignals, SignalValues};
+use crate::significance;
+use crate::sizing::{
+    self, lot_step, round_to_lot, DrawdownThrottle, LotSize, Sizing, SizingReport, ThrottleReport,
+    ThrottleState,
+};
+use crate::slippage_models::{resolve_costs, CostOverride, TransactionCosts};
+use crate::stress::{self, Exposure, StressConfig, StressPoint, StressedPosition};
+use crate::symbology::SymbologyMap;
+use crate::utils::fetch::{self, BacktestManager, MarketStream};
+use crate::utils::snapshot::{check_same_data, fingerprint_data, DataFingerprint};
 use crate::{
-    plot::plot_equity_curves,
-    strategy::{Order, OrderType, Strategy, StrategyParams},
+    plot::{plot_equity_curves_with_indicators, PlotConfig, PlotSender},
+    strategy::{
+        Bracket, BracketDistance, CancelReason, ExitReason, Order, OrderType, Strategy,
+        StrategyParams, TimeInForce,
+    },
     InkBackSchema,
 };
 use anyhow::Result;
@@ -11,6 +41,7 @@ use databento::dbn::Schema;
 use futures::StreamExt;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 
 #[derive(Debug, PartialEq)]
 enum Position {
@@ -27,6 +58,7 @@ enum Position {
     Neutral,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 enum FutureTraded {
     NQ,
     ES,
@@ -34,9 +66,71 @@ enum FutureTraded {
     CL,
     GC,
     SI,
+    // Micro contracts
+    MNQ,
+    MES,
+    MYM,
+    MCL,
+    MGC,
+    SIL,
 }
 
 impl Position {
+    fn size(&self) -> f64 {
+        match self {
+            Position::Long { size, .. } | Position::Short { size, .. } => *size,
+            Position::Neutral => 0.0,
+        }
+    }
+
+    // Net position of `lots`, entered at their size weighted average
+    fn from_lots(long: bool, lots: &[Lot]) -> Self {
+        let size: f64 = lots.iter().map(|l| l.size).sum();
+        let Some(first) = lots.first().filter(|_| size > 0.0) else {
+            return Position::Neutral;
+        };
+        let entry = lots.iter().map(|l| l.entry * l.size).sum::<f64>() / size;
+        let entry_date = first.entry_date.clone();
+        if long {
+            Position::Long {
+                entry,
+                size,
+                entry_date,
+            }
+        } else {
+            Position::Short {
+                entry,
+                size,
+                entry_date,
+            }
+        }
+    }
+
+    // The single lot of a freshly opened position
+    fn opening_lot(&self, opened_at: u64, reference: f64, risk: Option<f64>) -> Vec<Lot> {
+        match self {
+            Position::Long {
+                entry,
+                size,
+                entry_date,
+            }
+            | Position::Short {
+                entry,
+                size,
+                entry_date,
+            } => vec![Lot {
+                entry: *entry,
+                reference,
+                size: *size,
+                entry_date: entry_date.clone(),
+                opened_at,
+                index: 0,
+                risk,
+            }],
+            Position::Neutral => Vec::new(),
+        }
+    }
+
     fn calculate_pnl_with_costs(
         &self,
         exit_price: f64,
@@ -44,6 +138,7 @@ impl Position {
         vol: f64,
         is_options: bool,
         futures_multiplier: Option<f64>,
+        price_mode: PriceMode,
     ) -> f64 {
         match self {
             Position::Long { entry, size, .. } => {
@@ -53,12 +148,10 @@ impl Position {
                 // Apply appropriate multiplier based on instrument type
                 let multiplier = if is_options {
                     100.0
-                } else if let Some(futures_mult) = futures_multiplier {
-                    futures_mult
                 } else {
-                    1.0
+                    futures_multiplier.unwrap_or(1.0)
                 };
-                let gross_pnl = (exit_price - entry) * size * multiplier;
+                let gross_pnl = price_mode.diff(*entry, exit_price) * size * multiplier;
 
                 // Validate costs are finite
                 if !entry_cost.is_finite() || !exit_cost.is_finite() || !gross_pnl.is_finite() {
@@ -74,12 +167,10 @@ impl Position {
 
                 let multiplier = if is_options {
                     100.0
-                } else if let Some(futures_mult) = futures_multiplier {
-                    futures_mult
                 } else {
-                    1.0
+                    futures_multiplier.unwrap_or(1.0)
                 };
-                let gross_pnl = (entry - exit_price) * size * multiplier;
+                let gross_pnl = price_mode.diff(exit_price, *entry) * size * multiplier;
 
                 if !entry_cost.is_finite() || !exit_cost.is_finite() || !gross_pnl.is_finite() {
                     println!("Warning: Non-finite values in PnL calculation");
@@ -93,6 +184,355 @@ impl Position {
     }
 }
 
+/// A slice of the open position opened by one fill
+#[derive(Debug, Clone)]
+pub struct Lot {
+    pub entry: f64,
+    /// Price before fill slippage, the entry of the cost-free baseline
+    pub reference: f64,
+    pub size: f64,
+    pub entry_date: String,
+    pub opened_at: u64,
+    /// 0 for the opening fill, n for the position's nth add
+    pub index: usize,
+    /// Initial risk per unit in price, see [`Order::with_risk`]
+    pub risk: Option<f64>,
+}
+
+/// Order in which exits consume the open lots
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LotMethod {
+    /// Oldest lots first
+    #[default]
+    Fifo,
+    /// Newest lots first
+    Lifo,
+}
+
+/// Limits on scaling into an open position. With them any entry signal in the
+/// position's direction adds a lot, sized like an entry unless the order says otherwise.
+#[derive(Debug, Clone)]
+pub struct PyramidRules {
+    /// Adds allowed after the opening fill
+    pub max_adds: usize,
+    /// Cap on the position's market value as a multiple of equity
+    pub max_exposure: f64,
+}
+
+impl Default for PyramidRules {
+    fn default() -> Self {
+        Self {
+            max_adds: 3,
+            max_exposure: 1.0,
+        }
+    }
+}
+
+// Remove `quantity` from `lots` in `method` order, splitting the last lot touched, and
+// return what was removed
+fn take_lots(lots: &mut Vec<Lot>, quantity: f64, method: LotMethod) -> Vec<Lot> {
+    let mut taken = Vec::new();
+    let mut left = quantity;
+    while left > 0.0 && !lots.is_empty() {
+        let idx = match method {
+            LotMethod::Fifo => 0,
+            LotMethod::Lifo => lots.len() - 1,
+        };
+        if lots[idx].size <= left {
+            left -= lots[idx].size;
+            taken.push(lots.remove(idx));
+        } else {
+            lots[idx].size -= left;
+            taken.push(Lot {
+                size: left,
+                ..lots[idx].clone()
+            });
+            left = 0.0;
+        }
+    }
+    taken
+}
+
+// An exit fill of some open lots, priced lot by lot
+struct ExitFill<'a> {
+    long: bool,
+    exit_price: f64,
+    // Exit price before fill slippage
+    reference_price: f64,
+    instrument: Option<u32>,
+    costs: &'a TransactionCosts,
+    vol: f64,
+    is_options: bool,
+    futures_multiplier: Option<f64>,
+    price_mode: PriceMode,
+    ts: u64,
+    exit_date: String,
+    exit_reason: ExitReason,
+    rolled_in: bool,
+}
+
+impl ExitFill<'_> {
+    // One trade per lot, None when any P&L is not finite
+    fn trades(&self, lots: &[Lot]) -> Option<Vec<Trade>> {
+        lots.iter()
+            .map(|lot| {
+                let position = Position::from_lots(self.long, std::slice::from_ref(lot));
+                let mut pnl = position.calculate_pnl_with_costs(
+                    self.exit_price,
+                    self.costs,
+                    self.vol,
+                    self.is_options,
+                    self.futures_multiplier,
+                    self.price_mode,
+                );
+                if !self.long {
+                    pnl -= self.costs.calculate_borrow_cost(
+                        lot.entry,
+                        lot.size,
+                        self.ts.saturating_sub(lot.opened_at),
+                    );
+                }
+
+                let multiplier = if self.is_options {
+                    100.0
+                } else {
+                    self.futures_multiplier.unwrap_or(1.0)
+                };
+                let r_multiple = lot
+                    .risk
+                    .filter(|risk| *risk > 0.0)
+                    .map(|risk| pnl / (risk * lot.size * multiplier));
+                let gross_pnl = if self.long {
+                    self.price_mode.diff(lot.reference, self.reference_price)
+                } else {
+                    self.price_mode.diff(self.reference_price, lot.reference)
+                } * lot.size
+                    * multiplier;
+
+                pnl.is_finite().then(|| Trade {
+                    entry_date: lot.entry_date.clone(),
+                    exit_date: self.exit_date.clone(),
+                    entry_price: lot.entry,
+                    exit_price: self.exit_price,
+                    size: lot.size,
+                    pnl,
+                    pnl_pct: if self.long {
+                        ((self.exit_price / lot.entry) - 1.0) * 100.0
+                    } else if self.exit_price > 0.0 {
+                        ((lot.entry / self.exit_price) - 1.0) * 100.0
+                    } else {
+                        // A short option expiring worthless keeps its whole premium
+                        100.0
+                    },
+                    trade_type: if self.long { "Long" } else { "Short" }.to_string(),
+                    exit_reason: self.exit_reason,
+                    transaction_costs: gross_pnl - pnl,
+                    gross_pnl,
+                    rolled_in: self.rolled_in,
+                    lot: lot.index,
+                    r_multiple,
+                    risk: lot.risk,
+                    instrument_id: self.instrument,
+                    entry_ts: lot.opened_at,
+                    exit_ts: self.ts,
+                })
+            })
+            .collect()
+    }
+}
+
+// The engine position's lots and the terms its exits are priced on
+struct PositionBook<'a> {
+    lots: Vec<Lot>,
+    costs: &'a TransactionCosts,
+    // Instrument of the main position, against which hedges are reported
+    instrument: Option<u32>,
+    // Whether a roll opened the position
+    rolled_in: bool,
+    is_options: bool,
+    futures_multiplier: Option<f64>,
+    price_mode: PriceMode,
+    // Adds scaled into the position since its entry
+    adds: usize,
+    // When the time stop closes the position, from its entry order's max holding
+    time_stop_at: Option<u64>,
+    bracket: Option<BracketLevels>,
+}
+
+impl<'a> PositionBook<'a> {
+    // Close `quantity` of `position` on `event` at `exit_price`, all of it when None, and
+    // post the trades to the ledger. `position` is left holding what remains. Returns the
+    // P&L closed, None when any P&L is not finite and nothing was closed.
+    #[allow(clippy::too_many_arguments)]
+    fn close(
+        &mut self,
+        position: &mut Position,
+        event: &MarketEvent,
+        quantity: Option<f64>,
+        exit_price: f64,
+        reference_price: f64,
+        exit_reason: ExitReason,
+        lot_method: LotMethod,
+        ledger: &mut Ledger,
+        trades: &mut Vec<Trade>,
+    ) -> Option<f64> {
+        let long = matches!(position, Position::Long { .. });
+        let mut remaining = self.lots.clone();
+        let closed = match quantity {
+            Some(quantity) => take_lots(&mut remaining, quantity, lot_method),
+            None => std::mem::take(&mut remaining),
+        };
+        let fill = ExitFill {
+            long,
+            exit_price,
+            reference_price,
+            instrument: self.instrument,
+            costs: self.costs,
+            vol: event.volume() as f64,
+            is_options: self.is_options,
+            futures_multiplier: self.futures_multiplier,
+            price_mode: self.price_mode,
+            ts: event.timestamp(),
+            exit_date: event.date_string(),
+            exit_reason,
+            rolled_in: self.rolled_in,
+        };
+        let closed_trades = fill.trades(&closed)?;
+        let pnl = closed_trades.iter().map(|t| t.pnl).sum();
+        ledger.post_all(&closed_trades);
+        trades.extend(closed_trades);
+        self.lots = remaining;
+        *position = Position::from_lots(long, &self.lots);
+        Some(pnl)
+    }
+
+    // Open `position` from flat on the entry `order` filling at `fill_price`, unless the
+    // liquidity filter, the short option rules or compliance block it, as recorded in
+    // `constraints`. The size comes from the short option rules, else `capital` rounded to
+    // the lot step, capped by the order's quantity. The entry is priced under the costs
+    // resolved for the event and opens with its lots, bracket and time stop. Returns the
+    // entry price after costs and the size, None when blocked.
+    #[allow(clippy::too_many_arguments)]
+    fn open(
+        &mut self,
+        position: &mut Position,
+        order: &Order,
+        fill_price: f64,
+        event: &MarketEvent,
+        capital: f64,
+        covered: f64,
+        transaction_costs: &'a TransactionCosts,
+        options: &'a BacktestOptions,
+        symbology: &SymbologyMap,
+        liquidity: &OptionLiquidity,
+        bars: &BarHistory,
+        constraints: &mut ConstraintTracker,
+    ) -> Option<(f64, f64)> {
+        if !options.liquidity.allows(event, liquidity) {
+            constraints.block(order, event, BlockReason::Illiquid);
+            return None;
+        }
+        let short_size = match &options.short_options {
+            Some(rules) => {
+                match rules.short_size(event, order.order_type, fill_price, capital, covered) {
+                    Ok(size) => size,
+                    Err(reason) => {
+                        constraints.block(order, event, reason);
+                        return None;
+                    }
+                }
+            }
+            None => None,
+        };
+        if !constraints.allow_entry(order, event) {
+            return None;
+        }
+
+        let contract_price = if self.is_options {
+            fill_price * 100.0
+        } else {
+            fill_price
+        };
+        let size = short_size.unwrap_or_else(|| {
+            round_to_lot(
+                capital / contract_price,
+                lot_step(&options.lot_sizes, event, symbology),
+            )
+        });
+        let size = order.quantity.map_or(size, |q| q.min(size));
+
+        let ts = event.timestamp();
+        let is_buy = matches!(order.order_type, OrderType::MarketBuy | OrderType::LimitBuy);
+        self.costs = resolve_costs(&options.cost_overrides, transaction_costs, event, symbology);
+        let entry = self.price_mode.snap(
+            self.costs
+                .fill_price(fill_price, size, is_buy, own_quote(event)),
+            is_buy,
+        );
+        let entry_date = event.date_string();
+        *position = if is_buy {
+            Position::Long {
+                entry,
+                size,
+                entry_date,
+            }
+        } else {
+            Position::Short {
+                entry,
+                size,
+                entry_date,
+            }
+        };
+
+        let instrument = event.get_u64("instrument_id").map(|id| id as u32);
+        self.bracket = order
+            .bracket
+            .map(|b| BracketLevels::new(&b, is_buy, fill_price, instrument, bars, ts));
+        let risk = order.risk.or(self.bracket.and_then(|b| b.risk(fill_price)));
+        self.lots = position.opening_lot(ts, fill_price, risk);
+        self.instrument = instrument;
+        self.adds = 0;
+        self.time_stop_at = order.max_holding.map(|holding| ts.saturating_add(holding));
+        Some((entry, size))
+    }
+
+    // Close the whole position on an engine decision from `origin`, recording the fill in
+    // the audit log
+    #[allow(clippy::too_many_arguments)]
+    fn close_all(
+        &mut self,
+        position: &mut Position,
+        event: &MarketEvent,
+        exit_price: f64,
+        reference_price: f64,
+        exit_reason: ExitReason,
+        origin: OrderOrigin,
+        ledger: &mut Ledger,
+        trades: &mut Vec<Trade>,
+        audit: &mut AuditLog,
+    ) -> Option<f64> {
+        let size = position.size();
+        let closing = if matches!(position, Position::Long { .. }) {
+            OrderType::MarketSell
+        } else {
+            OrderType::MarketBuy
+        };
+        let pnl = self.close(
+            position,
+            event,
+            None,
+            exit_price,
+            reference_price,
+            exit_reason,
+            LotMethod::Fifo,
+            ledger,
+            trades,
+        )?;
+        audit.engine_fill(event.timestamp(), closing, exit_price, size, origin);
+        Some(pnl)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub entry_date: String,
@@ -103,8 +543,31 @@ pub struct Trade {
     pub pnl: f64,
     pub pnl_pct: f64,
     pub trade_type: String,
-    pub exit_reason: String,
+    pub exit_reason: ExitReason,
     pub transaction_costs: f64,
+    /// P&L at the prices before slippage, without any costs
+    #[serde(default)]
+    pub gross_pnl: f64,
+    /// Opened by a [`RollPolicy`] roll rather than a strategy order
+    #[serde(default)]
+    pub rolled_in: bool,
+    /// The position's lot this trade closed, 0 for the opening fill and n for the nth add
+    #[serde(default)]
+    pub lot: usize,
+    /// P&L in multiples of the initial risk the entry was tagged with
+    #[serde(default)]
+    pub r_multiple: Option<f64>,
+    /// Initial risk per unit the entry was tagged with, see [`Order::with_risk`]
+    #[serde(default)]
+    pub risk: Option<f64>,
+    /// Instrument id of the contract traded, when the data carries one
+    #[serde(default)]
+    pub instrument_id: Option<u32>,
+    /// Entry and exit fill times in nanoseconds since the epoch
+    #[serde(default)]
+    pub entry_ts: u64,
+    #[serde(default)]
+    pub exit_ts: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -130,6 +593,215 @@ pub struct BacktestResult {
     pub equity_curve: Vec<f64>,
     pub trades: Vec<Trade>,
     pub total_transaction_costs: f64,
+    /// Entries refused by [`TradingConstraints`]
+    #[serde(default)]
+    pub blocked_entries: Vec<BlockedEntry>,
+    /// Orders refused by [`BacktestOptions::pre_trade`]
+    #[serde(default)]
+    pub pre_trade_rejections: Vec<PreTradeRejection>,
+    /// Short options that went in the money near expiry, see [`ShortOptionRules`]
+    #[serde(default)]
+    pub assignment_risks: Vec<AssignmentRisk>,
+    /// Net Greeks through time with [`BacktestOptions::greeks`] set, a zero point marks
+    /// each return to flat
+    #[serde(default)]
+    pub greeks: Vec<GreeksPoint>,
+    /// Stress grid of the open position at each day close with [`BacktestOptions::stress`]
+    /// set, days ending flat are skipped
+    #[serde(default)]
+    pub stress: Vec<StressPoint>,
+    /// Realized result per hedge leg, see [`Order::on_instrument`]
+    #[serde(default)]
+    pub hedge_legs: Vec<LegSummary>,
+    /// Closed hedge trades, kept apart from the main position's `trades`
+    #[serde(default)]
+    pub hedge_trades: Vec<Trade>,
+    /// Main position against its hedges at each day close once a hedge has traded
+    #[serde(default)]
+    pub hedged_exposure: Vec<HedgedExposure>,
+    /// Trades forced closed at the end of the data, main position and hedges, also
+    /// listed in `trades` and `hedge_trades`
+    #[serde(default)]
+    pub force_closed: Vec<Trade>,
+    /// Length in nanoseconds of the [`EquitySampling`] intervals, None without sampling
+    #[serde(default)]
+    pub sample_interval: Option<u64>,
+    /// Equity at the close of each sampling interval that saw events, timestamped at the
+    /// interval's end
+    #[serde(default)]
+    pub sampled_equity: Vec<(u64, f64)>,
+    /// Content hashes of the input files, see [`check_same_data`]
+    #[serde(default)]
+    pub data_files: Vec<DataFingerprint>,
+    /// Seeds the run was made with
+    #[serde(default)]
+    pub seeds: Seeds,
+    /// Tags and notes the run was made with, see [`BacktestOptions::metadata`]
+    #[serde(default)]
+    pub metadata: RunMetadata,
+    /// Order audit trail with [`BacktestOptions::audit`] set
+    #[serde(default)]
+    pub audit: Vec<AuditRecord>,
+    /// Account ledger with [`BacktestOptions::ledger`] set
+    #[serde(default)]
+    pub ledger: Vec<LedgerEntry>,
+    /// How entries were scaled with [`BacktestOptions::throttle`] set
+    #[serde(default)]
+    pub throttle: Option<ThrottleReport>,
+    /// Regime of each day of the underlying with [`BacktestOptions::regimes`] set, see
+    /// [`regime_breakdown`]
+    ///
+    /// [`regime_breakdown`]: crate::regimes::regime_breakdown
+    #[serde(default)]
+    pub regimes: Vec<RegimeDay>,
+    /// Rolling correlation and beta of the marked daily returns to the underlying's with
+    /// [`BacktestOptions::beta`] set
+    #[serde(default)]
+    pub beta: Vec<BetaPoint>,
+    /// Marked equity at the last event of each UTC hour with events, with
+    /// [`BacktestOptions::hourly_marks`] set
+    #[serde(default)]
+    pub hourly_equity: Vec<(u64, f64)>,
+    /// Peak and average capital employed by the main position, and the return on it
+    #[serde(default)]
+    pub capital: CapitalUsage,
+    /// Realized performance of the trades both compounded and at a fixed size
+    #[serde(default)]
+    pub sizing: SizingReport,
+    /// The same trades filled at their prices before slippage and without costs
+    #[serde(default)]
+    pub cost_free: CostFreeBaseline,
+    /// Headline metrics without the best 1, 3 and 5 trades, where enough trades remain
+    #[serde(default)]
+    pub outliers: Vec<OutlierExclusion>,
+    /// Series the strategy published with [`EventCtx::record`]
+    #[serde(default)]
+    pub indicators: Vec<IndicatorSeries>,
+}
+
+/// The run's trade sequence with its costs taken out, to show how much of the gross
+/// edge the cost model consumes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostFreeBaseline {
+    pub gross_pnl: f64,
+    pub ending_equity: f64,
+    pub total_return_pct: f64,
+    pub win_rate: f64,
+    /// Gross P&L less net P&L: commissions, fees, borrow, spread and slippage
+    pub costs: f64,
+    /// Share of the gross P&L lost to costs, None without a gross profit
+    pub edge_consumed_pct: Option<f64>,
+}
+
+impl CostFreeBaseline {
+    fn from_trades(starting_equity: f64, trades: &[Trade]) -> Self {
+        let finite = || {
+            trades
+                .iter()
+                .filter(|t| t.pnl.is_finite() && t.gross_pnl.is_finite())
+        };
+        let gross_pnl: f64 = finite().map(|t| t.gross_pnl).sum();
+        let net_pnl: f64 = finite().map(|t| t.pnl).sum();
+        let count = finite().count();
+        let costs = gross_pnl - net_pnl;
+        Self {
+            gross_pnl,
+            ending_equity: starting_equity + gross_pnl,
+            total_return_pct: if starting_equity == 0.0 {
+                0.0
+            } else {
+                gross_pnl / starting_equity * 100.0
+            },
+            win_rate: if count == 0 {
+                0.0
+            } else {
+                finite().filter(|t| t.gross_pnl > 0.0).count() as f64 / count as f64 * 100.0
+            },
+            costs,
+            edge_consumed_pct: (gross_pnl > 0.0).then(|| costs / gross_pnl * 100.0),
+        }
+    }
+}
+
+/// Headline metrics recomputed with the best trades by P&L left out, to show how much
+/// of the result a few of them carry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlierExclusion {
+    /// Best trades left out
+    pub removed: usize,
+    /// Their combined P&L
+    pub removed_pnl: f64,
+    pub total_trades: usize,
+    pub total_return_pct: f64,
+    pub win_rate: f64,
+    pub profit_factor: f64,
+    /// Per-trade Sharpe ratio of the trades left in
+    pub sharpe_ratio: f64,
+}
+
+impl OutlierExclusion {
+    const REMOVED: [usize; 3] = [1, 3, 5];
+
+    // One row per count in `REMOVED` that leaves at least one trade in
+    fn from_trades(starting_equity: f64, total_return: f64, trades: &[Trade]) -> Vec<Self> {
+        // P&L and return of each trade, best first
+        let mut ranked: Vec<(f64, f64)> = trades
+            .iter()
+            .filter(|t| t.pnl.is_finite())
+            .map(|t| (t.pnl, t.pnl_pct / 100.0))
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let pnls: Vec<f64> = ranked.iter().map(|(pnl, _)| *pnl).collect();
+
+        Self::REMOVED
+            .into_iter()
+            .filter(|&n| n < pnls.len())
+            .map(|n| {
+                let removed_pnl: f64 = pnls[..n].iter().sum();
+                let kept = &pnls[n..];
+                let gross_profit = kept
+                    .iter()
+                    .filter(|p| **p > 0.0)
+                    .fold(0.0, |sum, p| sum + p);
+                let gross_loss: f64 = kept.iter().filter(|p| **p < 0.0).map(|p| p.abs()).sum();
+                let rs: Vec<f64> = ranked[n..].iter().map(|(_, r)| *r).collect();
+                let mean = rs.iter().sum::<f64>() / rs.len() as f64;
+                let std =
+                    (rs.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / rs.len() as f64).sqrt();
+                Self {
+                    removed: n,
+                    removed_pnl,
+                    total_trades: kept.len(),
+                    total_return_pct: if starting_equity == 0.0 {
+                        0.0
+                    } else {
+                        (total_return - removed_pnl) / starting_equity * 100.0
+                    },
+                    win_rate: kept.iter().filter(|p| **p > 0.0).count() as f64 / kept.len() as f64
+                        * 100.0,
+                    profit_factor: profit_factor(gross_profit, gross_loss),
+                    sharpe_ratio: if rs.len() >= 2 && std > 0.0 {
+                        mean / std
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+// Gross profit over gross loss, capped at 1000 without losses
+fn profit_factor(gross_profit: f64, gross_loss: f64) -> f64 {
+    if gross_loss == 0.0 {
+        if gross_profit > 0.0 {
+            1000.0
+        } else {
+            0.0
+        }
+    } else {
+        gross_profit / gross_loss
+    }
 }
 
 impl BacktestResult {
@@ -182,15 +854,7 @@ impl BacktestResult {
             .filter(|t| t.pnl < 0.0)
             .map(|t| t.pnl.abs())
             .sum();
-        let profit_factor = if gross_loss == 0.0 {
-            if gross_profit > 0.0 {
-                1000.0
-            } else {
-                0.0
-            }
-        } else {
-            gross_profit / gross_loss
-        };
+        let profit_factor = profit_factor(gross_profit, gross_loss);
 
         let avg_win = if winning_trades == 0 {
             0.0
@@ -258,254 +922,2576 @@ impl BacktestResult {
             largest_win,
             largest_loss,
             equity_curve,
-            trades,
             total_transaction_costs,
+            blocked_entries: Vec::new(),
+            pre_trade_rejections: Vec::new(),
+            assignment_risks: Vec::new(),
+            greeks: Vec::new(),
+            stress: Vec::new(),
+            hedge_legs: Vec::new(),
+            hedge_trades: Vec::new(),
+            hedged_exposure: Vec::new(),
+            force_closed: Vec::new(),
+            sample_interval: None,
+            sampled_equity: Vec::new(),
+            data_files: Vec::new(),
+            seeds: Seeds::default(),
+            metadata: RunMetadata::default(),
+            audit: Vec::new(),
+            ledger: Vec::new(),
+            throttle: None,
+            regimes: Vec::new(),
+            beta: Vec::new(),
+            hourly_equity: Vec::new(),
+            capital: CapitalUsage::default(),
+            sizing: SizingReport::default(),
+            cost_free: CostFreeBaseline::from_trades(starting_equity, &trades),
+            outliers: OutlierExclusion::from_trades(starting_equity, total_return, &trades),
+            indicators: Vec::new(),
+            trades,
         }
     }
-}
-
-// Core backtesting logic that works with events
-pub async fn run_backtest(
-    symbol: &str,
-    backtest_manager: BacktestManager,
-    strategy: &mut dyn Strategy,
-    transaction_costs: TransactionCosts,
-    starting_equity: f64,
-    exposure: f64,
-    schema: Schema,
-    custom_schema: Option<InkBackSchema>,
-    time_range: Option<(u64, u64)>,
-) -> Result<BacktestResult> {
-    let is_options_trading = matches!(
-        custom_schema,
-        Some(InkBackSchema::CombinedOptionsUnderlying)
-    );
-    let is_futures_trading =
-        symbol.ends_with(".v.0") || symbol.ends_with(".c.0") || symbol.ends_with(".FUT");
-    let futures_multiplier = if is_futures_trading {
-        get_future_from_symbol(symbol).map(|future| get_future_multiplier(future))
-    } else {
-        None
-    };
 
-    let mut equity = starting_equity;
-    let mut position = Position::Neutral;
-    let mut trades = Vec::new();
-    let mut equity_curve = vec![starting_equity];
+    /// Replace the per-trade Sharpe and Sortino ratios with ones annualized from the
+    /// returns of `sampled_equity`, at the sampling rate observed over its span
+    pub fn apply_time_metrics(&mut self) {
+        let returns: Vec<f64> = self
+            .sampled_equity
+            .windows(2)
+            .map(|w| {
+                if w[0].1 != 0.0 {
+                    w[1].1 / w[0].1 - 1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let (Some(first), Some(last)) = (self.sampled_equity.first(), self.sampled_equity.last())
+        else {
+            return;
+        };
+        let years = last.0.saturating_sub(first.0) as f64 / (365.25 * NANOS_PER_DAY as f64);
+        if returns.len() < 2 || years <= 0.0 {
+            return;
+        }
 
-    let mut pending_order: Option<Order> = None;
-    let mut pending_limit_orders: Vec<Order> = Vec::new();
+        let periods_per_year = returns.len() as f64 / years;
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let std = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let downside = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / n).sqrt();
+        let scale = periods_per_year.sqrt();
 
-    let data_path = &backtest_manager.data_path;
-    if data_path.is_empty() {
-        return Err(anyhow::anyhow!("No data path provided"));
+        self.sharpe_ratio = if std > 0.0 { mean / std * scale } else { 0.0 };
+        self.sortino_ratio = if downside > 0.0 {
+            mean / downside * scale
+        } else {
+            0.0
+        };
     }
 
-    // GET THE STREAM
-    let mut data_iter = fetch::get_data_stream(data_path, schema).await?;
+    /// Trade performance broken down by exit reason, in [`ExitReason`] order
+    pub fn by_exit_reason(&self) -> Vec<ExitReasonStats> {
+        let mut groups: std::collections::BTreeMap<ExitReason, Vec<&Trade>> = Default::default();
+        for trade in &self.trades {
+            groups.entry(trade.exit_reason).or_default().push(trade);
+        }
 
-    let mut prev_event: Option<MarketEvent> = None;
+        groups
+            .into_iter()
+            .map(|(reason, trades)| {
+                let n = trades.len() as f64;
+                let total_pnl: f64 = trades.iter().map(|t| t.pnl).sum();
+                ExitReasonStats {
+                    reason,
+                    trades: trades.len(),
+                    win_rate: trades.iter().filter(|t| t.pnl > 0.0).count() as f64 / n * 100.0,
+                    total_pnl,
+                    avg_pnl: total_pnl / n,
+                    avg_pnl_pct: trades.iter().map(|t| t.pnl_pct).sum::<f64>() / n,
+                }
+            })
+            .collect()
+    }
 
-    // ASYNC LOOP
-    while let Some(event_res) = data_iter.next().await {
-        let event = event_res?; // Handle Result
+    /// Outcome statistics of the trades tagged with an initial risk, None without any.
+    /// The histogram buckets R-multiples `bucket_width` wide.
+    pub fn r_statistics(&self, bucket_width: f64) -> Option<RStatistics> {
+        let rs: Vec<f64> = self
+            .trades
+            .iter()
+            .filter_map(|t| t.r_multiple)
+            .filter(|r| r.is_finite())
+            .collect();
+        if rs.is_empty() || bucket_width <= 0.0 {
+            return None;
+        }
 
-        // Time filter
-        if let Some((start_ts, end_ts)) = time_range {
-            let ts = event.timestamp();
-            if ts < start_ts {
-                continue;
-            }
-            if ts >= end_ts {
-                break;
+        let mean = |v: &[f64]| {
+            if v.is_empty() {
+                0.0
+            } else {
+                v.iter().sum::<f64>() / v.len() as f64
             }
+        };
+        let wins: Vec<f64> = rs.iter().copied().filter(|r| *r > 0.0).collect();
+        let losses: Vec<f64> = rs.iter().copied().filter(|r| *r <= 0.0).collect();
+
+        let mut buckets: std::collections::BTreeMap<i64, usize> = Default::default();
+        for r in &rs {
+            *buckets
+                .entry((r / bucket_width).floor() as i64)
+                .or_default() += 1;
         }
 
-        // Update Avg Volume for slippage
-        let vol = event.volume() as f64;
+        Some(RStatistics {
+            trades: rs.len(),
+            expectancy: mean(&rs),
+            avg_win: mean(&wins),
+            avg_loss: mean(&losses),
+            best: rs.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            worst: rs.iter().copied().fold(f64::INFINITY, f64::min),
+            bucket_width,
+            histogram: buckets
+                .into_iter()
+                .map(|(bucket, count)| (bucket as f64 * bucket_width, count))
+                .collect(),
+        })
+    }
+}
 
-        // Check Limit Orders
-        let mut filled_limit_orders = Vec::new();
-        pending_limit_orders.retain(|order| {
-            if should_fill_limit_order(order, &event) {
-                filled_limit_orders.push(*order);
-                false
-            } else {
-                true
-            }
-        });
+/// Performance of the trades closed for one [`ExitReason`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitReasonStats {
+    pub reason: ExitReason,
+    pub trades: usize,
+    pub win_rate: f64,
+    pub total_pnl: f64,
+    pub avg_pnl: f64,
+    pub avg_pnl_pct: f64,
+}
 
-        if let Some(order) = filled_limit_orders.first() {
-            if matches!(position, Position::Neutral) {
-                let capital = equity * exposure;
-                let size = if is_options_trading {
-                    (capital / (order.price * 100.0)).floor()
-                } else {
-                    (capital / order.price).floor()
-                };
+/// R-multiple summary of the risk-tagged trades of a backtest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RStatistics {
+    pub trades: usize,
+    /// Mean R per trade
+    pub expectancy: f64,
+    pub avg_win: f64,
+    pub avg_loss: f64,
+    pub best: f64,
+    pub worst: f64,
+    pub bucket_width: f64,
+    /// Lower bucket bound and trade count, ascending, empty buckets left out
+    pub histogram: Vec<(f64, usize)>,
+}
 
-                let adjusted_entry = transaction_costs.adjust_fill_price(
-                    order.price,
-                    size,
-                    matches!(order.order_type, OrderType::LimitBuy),
-                );
+/// Print the per exit reason breakdown of a result
+pub fn display_exit_reasons(result: &BacktestResult) {
+    println!("\n=== EXIT REASONS ===");
+    println!(
+        "{:<12} {:<8} {:<8} {:<14} {:<12} {:<10}",
+        "Reason", "Trades", "WR%", "Total PnL", "Avg PnL", "Avg %"
+    );
+    println!("{}", "-".repeat(66));
+    for stats in result.by_exit_reason() {
+        println!(
+            "{:<12} {:<8} {:<8.1} {:<14.2} {:<12.2} {:<10.2}",
+            format!("{:?}", stats.reason),
+            stats.trades,
+            stats.win_rate,
+            stats.total_pnl,
+            stats.avg_pnl,
+            stats.avg_pnl_pct,
+        );
+    }
+}
 
-                match order.order_type {
-                    OrderType::LimitBuy => {
-                        position = Position::Long {
-                            entry: adjusted_entry,
-                            size,
-                            entry_date: event.date_string(),
-                        }
-                    }
-                    OrderType::LimitSell => {
-                        position = Position::Short {
-                            entry: adjusted_entry,
-                            size,
-                            entry_date: event.date_string(),
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
+/// Print R-multiple statistics with a text histogram
+pub fn display_r_statistics(stats: &RStatistics) {
+    println!("\n=== R-MULTIPLES ({} trades) ===", stats.trades);
+    println!(
+        "Expectancy: {:.2}R | Avg Win: {:.2}R | Avg Loss: {:.2}R | Best: {:.2}R | Worst: {:.2}R",
+        stats.expectancy, stats.avg_win, stats.avg_loss, stats.best, stats.worst
+    );
 
-        // Check Market Orders
-        if let Some(order) = pending_order.take() {
-            if matches!(position, Position::Neutral) {
-                // Approximate fill at price
-                let fill_price = event.price();
-                let capital = equity * exposure;
-                let size = if is_options_trading {
-                    (capital / (fill_price * 100.0)).floor()
-                } else {
-                    (capital / fill_price).floor()
-                };
+    let max = stats.histogram.iter().map(|(_, c)| *c).max().unwrap_or(1);
+    for (start, count) in &stats.histogram {
+        println!(
+            "{:>7.2}R to {:>7.2}R | {:<40} {}",
+            start,
+            start + stats.bucket_width,
+            "#".repeat((count * 40).div_ceil(max)),
+            count
+        );
+    }
+}
 
-                let adjusted_entry = transaction_costs.adjust_fill_price(
-                    fill_price,
-                    size,
-                    order.order_type == OrderType::MarketBuy,
-                );
+/// Sampling of equity at fixed wall-clock intervals, so dense periods do not dominate the
+/// curve and time-based metrics have a regular series
+#[derive(Debug, Clone)]
+pub struct EquitySampling {
+    /// Interval length in nanoseconds, aligned to the UNIX epoch (UTC days for daily)
+    pub interval: u64,
+    /// Keep the sampled series as `equity_curve` instead of one point per event
+    pub replace_curve: bool,
+}
 
-                match order.order_type {
-                    OrderType::MarketBuy => {
-                        position = Position::Long {
-                            entry: adjusted_entry,
-                            size,
-                            entry_date: event.date_string(),
-                        }
-                    }
-                    OrderType::MarketSell => {
-                        position = Position::Short {
-                            entry: adjusted_entry,
-                            size,
-                            entry_date: event.date_string(),
-                        }
-                    }
-                    _ => {}
-                }
-            }
+impl EquitySampling {
+    pub fn minutes(minutes: u64) -> Self {
+        Self {
+            interval: minutes * 60_000_000_000,
+            replace_curve: false,
         }
+    }
 
-        // Strategy Logic
-        if let Some(order) = strategy.on_event(&event, prev_event.as_ref()) {
-            match position {
-                Position::Long {
-                    entry,
-                    size,
-                    ref entry_date,
-                } => {
-                    if order.order_type == OrderType::MarketSell {
-                        let exit_price =
-                            transaction_costs.adjust_fill_price(order.price, size, false);
-                        let pnl = position.calculate_pnl_with_costs(
-                            exit_price,
-                            &transaction_costs,
-                            vol,
-                            is_options_trading,
-                            futures_multiplier,
-                        );
+    pub fn daily() -> Self {
+        Self {
+            interval: NANOS_PER_DAY,
+            replace_curve: false,
+        }
+    }
+
+    pub fn replacing_curve(mut self) -> Self {
+        self.replace_curve = true;
+        self
+    }
+}
+
+// Last equity of each interval that saw events
+struct EquitySampler {
+    config: EquitySampling,
+    bucket: Option<u64>,
+    last: f64,
+    points: Vec<(u64, f64)>,
+}
+
+impl EquitySampler {
+    fn new(config: EquitySampling) -> Self {
+        Self {
+            config: EquitySampling {
+                interval: config.interval.max(1),
+                ..config
+            },
+            bucket: None,
+            last: 0.0,
+            points: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, equity: f64, ts: u64) {
+        let bucket = ts / self.config.interval;
+        if let Some(current) = self.bucket.filter(|b| *b != bucket) {
+            self.close(current);
+        }
+        self.bucket = Some(bucket);
+        self.last = equity;
+    }
+
+    fn close(&mut self, bucket: u64) {
+        self.points
+            .push(((bucket + 1).saturating_mul(self.config.interval), self.last));
+    }
+
+    fn finish(mut self) -> (EquitySampling, Vec<(u64, f64)>) {
+        if let Some(bucket) = self.bucket {
+            self.close(bucket);
+        }
+        (self.config, self.points)
+    }
+}
+
+/// Decimation of the equity curve for tick backtests whose per-event curve does not fit
+/// in memory. The curve keeps one point per `every_events` events, and a row summarizing
+/// each point is appended to `log_path` as it closes. Only the kept curve is thinned:
+/// drawdown is tracked on every event and the other headline metrics do not read the
+/// curve, so results do not depend on `every_events`.
+#[derive(Debug, Clone)]
+pub struct CurveDecimation {
+    pub every_events: usize,
+    pub log_path: String,
+}
+
+impl CurveDecimation {
+    pub fn new(every_events: usize, log_path: &str) -> Self {
+        Self {
+            every_events: every_events.max(1),
+            log_path: log_path.to_string(),
+        }
+    }
+}
+
+/// How fill prices and PnL are represented
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PriceMode {
+    /// Prices and PnL in f64, fills at the exact slippage-adjusted price
+    #[default]
+    Float,
+    /// Fills are snapped to the `tick_size` grid (against the trader) and gross PnL is
+    /// computed from the integer tick difference, so there is no float drift in long runs
+    Ticks { tick_size: f64 },
+}
+
+impl PriceMode {
+    /// The tick grid, None in float mode
+    pub fn tick_size(&self) -> Option<f64> {
+        match self {
+            PriceMode::Float => None,
+            PriceMode::Ticks { tick_size } => Some(*tick_size),
+        }
+    }
+
+    pub fn to_ticks(&self, price: f64) -> Option<i64> {
+        match self {
+            PriceMode::Float => None,
+            PriceMode::Ticks { tick_size } => Some((price / tick_size).round() as i64),
+        }
+    }
+
+    // Round a fill onto the tick grid, buys up and sells down
+    fn snap(&self, price: f64, is_buy: bool) -> f64 {
+        match self {
+            PriceMode::Float => price,
+            PriceMode::Ticks { tick_size } => {
+                let ticks = price / tick_size;
+                // Remove representation noise before rounding away from the trader
+                let ticks = if (ticks - ticks.round()).abs() < 1e-6 {
+                    ticks.round()
+                } else if is_buy {
+                    ticks.ceil()
+                } else {
+                    ticks.floor()
+                };
+                ticks * tick_size
+            }
+        }
+    }
+
+    // Price difference `to - from`, from whole ticks in tick mode
+    fn diff(&self, from: f64, to: f64) -> f64 {
+        match (self.to_ticks(from), self.to_ticks(to), self) {
+            (Some(a), Some(b), PriceMode::Ticks { tick_size }) => (b - a) as f64 * tick_size,
+            _ => to - from,
+        }
+    }
+}
+
+/// Over-trading limits enforced by the engine on every entry fill, so strategies don't
+/// each have to implement them. Unset limits are not enforced.
+#[derive(Debug, Clone, Default)]
+pub struct TradingConstraints {
+    /// Nanoseconds after a losing exit during which no new position is opened
+    pub cooldown_after_loss: Option<u64>,
+    /// Entries allowed per UTC day
+    pub max_trades_per_day: Option<usize>,
+    /// Losing trades in a row after which trading halts for the rest of the UTC day
+    pub max_consecutive_losses: Option<usize>,
+}
+
+impl TradingConstraints {
+    pub fn cooldown_after_loss(mut self, duration: time::Duration) -> Self {
+        self.cooldown_after_loss = Some(duration.whole_nanoseconds().max(0) as u64);
+        self
+    }
+
+    pub fn max_trades_per_day(mut self, trades: usize) -> Self {
+        self.max_trades_per_day = Some(trades);
+        self
+    }
+
+    pub fn max_consecutive_losses(mut self, losses: usize) -> Self {
+        self.max_consecutive_losses = Some(losses);
+        self
+    }
+}
+
+/// Why an entry was refused
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BlockReason {
+    CooldownAfterLoss,
+    MaxTradesPerDay,
+    /// Halted for the day after too many consecutive losses
+    ConsecutiveLosses,
+    /// The option contract is below the [`LiquidityFilter`] thresholds
+    Illiquid,
+    /// Not enough capital for the margin of a single short option contract
+    InsufficientMargin,
+    /// A single short contract would exceed [`ShortOptionRules::max_naked_notional`]
+    NakedShortCap,
+    /// The position already has [`PyramidRules::max_adds`] adds
+    MaxAdds,
+    /// The add would take the position over [`PyramidRules::max_exposure`]
+    MaxExposure,
+}
+
+/// An entry fill refused by [`TradingConstraints`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockedEntry {
+    pub ts: u64,
+    pub date: String,
+    pub order_type: OrderType,
+    pub price: f64,
+    pub reason: BlockReason,
+}
+
+pub(crate) const NANOS_PER_DAY: u64 = 86_400_000_000_000;
+pub(crate) const NANOS_PER_HOUR: u64 = 3_600_000_000_000;
+
+/// Rejects option entries in contracts that trade too little. Volume is counted over the
+/// trailing `window`; open interest is only known when the merged stream carries it, and
+/// contracts without it pass the open interest check.
+#[derive(Debug, Clone)]
+pub struct LiquidityFilter {
+    /// Nanoseconds of trailing volume behind [`OptionLiquidity::volume`]
+    pub window: u64,
+    pub min_volume: Option<u64>,
+    pub min_open_interest: Option<u64>,
+}
+
+impl Default for LiquidityFilter {
+    fn default() -> Self {
+        Self {
+            window: NANOS_PER_DAY,
+            min_volume: None,
+            min_open_interest: None,
+        }
+    }
+}
+
+impl LiquidityFilter {
+    fn allows(&self, event: &MarketEvent, liquidity: &OptionLiquidity) -> bool {
+        let MarketEvent::OptionTrade(m) = event else {
+            return true;
+        };
+        let volume_ok = self
+            .min_volume
+            .is_none_or(|min| liquidity.volume(m.instrument_id) >= min);
+        let oi_ok = match (
+            self.min_open_interest,
+            liquidity.open_interest(m.instrument_id),
+        ) {
+            (Some(min), Some(oi)) => oi >= min,
+            _ => true,
+        };
+        volume_ok && oi_ok
+    }
+}
+
+/// Margin and risk guards for selling options. Without them short option entries are
+/// sized like long ones, by premium.
+#[derive(Debug, Clone)]
+pub struct ShortOptionRules {
+    /// Share of the underlying value required, less the out-of-the-money amount
+    /// (Reg T style, 0.20)
+    pub base_margin_pct: f64,
+    /// Margin floor as a share of the underlying for calls and of the strike for puts (0.10)
+    pub min_margin_pct: f64,
+    /// Hard cap on the notional of a naked short position: underlying value for calls,
+    /// strike for puts, times 100 per contract
+    pub max_naked_notional: Option<f64>,
+    /// Nanoseconds before expiry within which an in-the-money short is flagged
+    pub assignment_window: u64,
+}
+
+impl Default for ShortOptionRules {
+    fn default() -> Self {
+        Self {
+            base_margin_pct: 0.20,
+            min_margin_pct: 0.10,
+            max_naked_notional: None,
+            assignment_window: 2 * NANOS_PER_DAY,
+        }
+    }
+}
+
+impl ShortOptionRules {
+    /// Margin of one short contract (100 multiplier) including the premium received.
+    ///
+    /// A call covered by 100 long underlying shares, or a put covered by 100 short
+    /// shares, needs no margin beyond the covering position, so `covered` nets it to zero.
+    pub fn margin_per_contract(
+        &self,
+        is_call: bool,
+        strike: f64,
+        underlying: f64,
+        premium: f64,
+        covered: bool,
+    ) -> f64 {
+        if covered {
+            return 0.0;
+        }
+        let otm = if is_call {
+            (strike - underlying).max(0.0)
+        } else {
+            (underlying - strike).max(0.0)
+        };
+        let floor_base = if is_call { underlying } else { strike };
+        let requirement = (self.base_margin_pct * underlying - otm)
+            .max(self.min_margin_pct * floor_base)
+            + premium;
+        requirement * 100.0
+    }
+
+    /// Contracts of a short call covered by long underlying units held, or of a short put
+    /// covered by short ones, 100 units each
+    pub fn covered_contracts(is_call: bool, underlying_quantity: f64) -> f64 {
+        let covering = if is_call {
+            underlying_quantity
+        } else {
+            -underlying_quantity
+        };
+        (covering / 100.0).floor().max(0.0)
+    }
+
+    /// Margin of `contracts` short contracts with `underlying_quantity` units of the
+    /// underlying held: the covered ones net to zero, the rest are margined as naked
+    pub fn position_margin(
+        &self,
+        is_call: bool,
+        strike: f64,
+        underlying: f64,
+        premium: f64,
+        contracts: f64,
+        underlying_quantity: f64,
+    ) -> f64 {
+        let covered = Self::covered_contracts(is_call, underlying_quantity).min(contracts);
+        self.margin_per_contract(is_call, strike, underlying, premium, true) * covered
+            + self.margin_per_contract(is_call, strike, underlying, premium, false)
+                * (contracts - covered)
+    }
+
+    /// Notional at risk of one naked contract
+    pub fn naked_notional(is_call: bool, strike: f64, underlying: f64) -> f64 {
+        if is_call {
+            underlying * 100.0
+        } else {
+            strike * 100.0
+        }
+    }
+
+    // Contracts a short entry into the event's option may open with `underlying_quantity`
+    // units of the underlying held, Ok(None) when the entry is not a short option. The
+    // contracts the underlying covers need no margin and count toward no naked cap.
+    fn short_size(
+        &self,
+        event: &MarketEvent,
+        order_type: OrderType,
+        premium: f64,
+        capital: f64,
+        underlying_quantity: f64,
+    ) -> std::result::Result<Option<f64>, BlockReason> {
+        let MarketEvent::OptionTrade(m) = event else {
+            return Ok(None);
+        };
+        if !matches!(order_type, OrderType::MarketSell | OrderType::LimitSell) {
+            return Ok(None);
+        }
+
+        let is_call = m.option_type.starts_with('C');
+        let underlying = m.underlying_mid();
+        let covered = Self::covered_contracts(is_call, underlying_quantity);
+        let margin = self.margin_per_contract(is_call, m.strike_price, underlying, premium, false);
+        let mut naked = if margin > 0.0 {
+            (capital / margin).floor()
+        } else {
+            0.0
+        };
+        if covered + naked < 1.0 {
+            return Err(BlockReason::InsufficientMargin);
+        }
+
+        if let Some(cap) = self.max_naked_notional {
+            let notional = Self::naked_notional(is_call, m.strike_price, underlying);
+            naked = naked.min((cap / notional).floor());
+            if covered + naked < 1.0 {
+                return Err(BlockReason::NakedShortCap);
+            }
+        }
+        Ok(Some(covered + naked))
+    }
+}
+
+/// Automatic rolling of option positions into the next expiry, so positions can be held
+/// across expiry cycles.
+#[derive(Debug, Clone)]
+pub struct RollPolicy {
+    /// Days to expiry at which the held contract is closed and rolled
+    pub dte: f64,
+    /// Width of the strike / underlying buckets the new contract is matched in
+    pub moneyness_step: f64,
+}
+
+impl Default for RollPolicy {
+    fn default() -> Self {
+        Self {
+            dte: 5.0,
+            moneyness_step: 0.025,
+        }
+    }
+}
+
+impl RollPolicy {
+    fn bucket(&self, moneyness: f64) -> i64 {
+        ((moneyness - 1.0) / self.moneyness_step).round() as i64
+    }
+}
+
+/// Settings for tracking the Greeks of option positions
+#[derive(Debug, Clone)]
+pub struct GreeksConfig {
+    /// Continuously compounded rate used for implied volatility and the Greeks
+    pub risk_free_rate: f64,
+}
+
+impl Default for GreeksConfig {
+    fn default() -> Self {
+        Self {
+            risk_free_rate: 0.04,
+        }
+    }
+}
+
+// The option contract behind the open position
+struct HeldOption {
+    symbol: String,
+    strike: f64,
+    expiration: u64,
+    is_call: bool,
+    /// Strike over underlying at entry
+    moneyness: f64,
+    /// Last underlying price seen
+    spot: f64,
+    /// Implied volatility at the contract's last trade
+    implied_vol: Option<f64>,
+}
+
+impl HeldOption {
+    fn from_event(event: &MarketEvent) -> Option<Self> {
+        let MarketEvent::OptionTrade(m) = event else {
+            return None;
+        };
+        Some(Self {
+            symbol: m.symbol.clone(),
+            strike: m.strike_price,
+            expiration: m.expiration,
+            is_call: m.option_type.starts_with('C'),
+            moneyness: m.strike_price / m.underlying_mid(),
+            spot: m.underlying_mid(),
+            implied_vol: None,
+        })
+    }
+
+    // Follow the underlying and re-solve the implied volatility on the contract's own
+    // trades, carrying it between them
+    fn observe(&mut self, event: &MarketEvent, rate: f64) {
+        match event {
+            MarketEvent::OptionTrade(m) => {
+                self.spot = m.underlying_mid();
+                if m.symbol == self.symbol {
+                    let t = greeks::years(self.expiration.saturating_sub(m.ts_event));
+                    self.implied_vol =
+                        greeks::implied_vol(self.is_call, m.price, self.spot, self.strike, t, rate)
+                            .or(self.implied_vol);
+                }
+            }
+            MarketEvent::Mbp1(_) => self.spot = event.price(),
+            _ => {}
+        }
+    }
+
+    // Net Greeks of `quantity` contracts (negative when short)
+    fn greeks_point(&self, ts: u64, quantity: f64, rate: f64) -> Option<GreeksPoint> {
+        let vol = self.implied_vol?;
+        let t = greeks::years(self.expiration.saturating_sub(ts));
+        Some(GreeksPoint {
+            ts,
+            greeks: greeks::greeks(self.is_call, self.spot, self.strike, t, rate, vol)
+                .scaled(quantity * 100.0),
+            implied_vol: Some(vol),
+        })
+    }
+
+    fn exposure(&self, ts: u64, rate: f64) -> Option<Exposure> {
+        Some(Exposure::Option {
+            is_call: self.is_call,
+            strike: self.strike,
+            spot: self.spot,
+            years: greeks::years(self.expiration.saturating_sub(ts)),
+            implied_vol: self.implied_vol?,
+            rate,
+        })
+    }
+}
+
+// A closed position waiting for its contract in the next expiry
+struct RollTarget {
+    long: bool,
+    is_call: bool,
+    after: u64,
+    moneyness: f64,
+}
+
+impl RollTarget {
+    fn matches(
+        &self,
+        m: &crate::event::OptionTradeMsg,
+        policy: &RollPolicy,
+        expirations: &[BTreeSet<u64>; 2],
+    ) -> bool {
+        let next_expiry = expirations[self.is_call as usize]
+            .range(self.after + 1..)
+            .next();
+        m.option_type.starts_with('C') == self.is_call
+            && next_expiry == Some(&m.expiration)
+            && policy.bucket(m.strike_price / m.underlying_mid()) == policy.bucket(self.moneyness)
+    }
+
+    // The strategy order that closes the rolled position
+    fn exit_order_type(&self) -> OrderType {
+        if self.long {
+            OrderType::MarketSell
+        } else {
+            OrderType::MarketBuy
+        }
+    }
+}
+
+/// An open short option that went in the money close to expiry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssignmentRisk {
+    pub ts: u64,
+    pub date: String,
+    pub symbol: String,
+    pub strike: f64,
+    pub underlying_price: f64,
+    pub days_to_expiry: f64,
+}
+
+// The short option held by the engine, watched for assignment risk
+struct ShortOption {
+    symbol: String,
+    strike: f64,
+    expiration: u64,
+    is_call: bool,
+    flagged: bool,
+}
+
+impl ShortOption {
+    fn check(&mut self, event: &MarketEvent, window: u64) -> Option<AssignmentRisk> {
+        if self.flagged {
+            return None;
+        }
+        let underlying = match event {
+            MarketEvent::OptionTrade(m) => m.underlying_mid(),
+            MarketEvent::Mbp1(_) => event.price(),
+            _ => return None,
+        };
+        let ts = event.timestamp();
+        let in_the_money = if self.is_call {
+            underlying > self.strike
+        } else {
+            underlying < self.strike
+        };
+        if !underlying.is_finite()
+            || underlying <= 0.0
+            || !in_the_money
+            || ts.saturating_add(window) < self.expiration
+        {
+            return None;
+        }
+
+        self.flagged = true;
+        Some(AssignmentRisk {
+            ts,
+            date: event.date_string(),
+            symbol: self.symbol.clone(),
+            strike: self.strike,
+            underlying_price: underlying,
+            days_to_expiry: self.expiration.saturating_sub(ts) as f64 / NANOS_PER_DAY as f64,
+        })
+    }
+}
+
+// Running state behind TradingConstraints
+struct ConstraintTracker {
+    constraints: TradingConstraints,
+    day: u64,
+    trades_today: usize,
+    consecutive_losses: usize,
+    halted_day: Option<u64>,
+    cooldown_until: u64,
+    blocked: Vec<BlockedEntry>,
+}
+
+impl ConstraintTracker {
+    fn new(constraints: TradingConstraints) -> Self {
+        Self {
+            constraints,
+            day: 0,
+            trades_today: 0,
+            consecutive_losses: 0,
+            halted_day: None,
+            cooldown_until: 0,
+            blocked: Vec::new(),
+        }
+    }
+
+    fn block(&mut self, order: &Order, event: &MarketEvent, reason: BlockReason) {
+        self.blocked.push(BlockedEntry {
+            ts: event.timestamp(),
+            date: event.date_string(),
+            order_type: order.order_type,
+            price: order.price,
+            reason,
+        });
+    }
+
+    // Check an entry fill and count it when allowed
+    fn allow_entry(&mut self, order: &Order, event: &MarketEvent) -> bool {
+        let ts = event.timestamp();
+        let day = ts / NANOS_PER_DAY;
+        if day != self.day {
+            self.day = day;
+            self.trades_today = 0;
+        }
+
+        let reason = if self.halted_day == Some(day) {
+            Some(BlockReason::ConsecutiveLosses)
+        } else if ts < self.cooldown_until {
+            Some(BlockReason::CooldownAfterLoss)
+        } else if self
+            .constraints
+            .max_trades_per_day
+            .is_some_and(|max| self.trades_today >= max)
+        {
+            Some(BlockReason::MaxTradesPerDay)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => {
+                self.block(order, event, reason);
+                false
+            }
+            None => {
+                self.trades_today += 1;
+                true
+            }
+        }
+    }
+
+    fn record_exit(&mut self, pnl: f64, ts: u64) {
+        if pnl >= 0.0 {
+            self.consecutive_losses = 0;
+            return;
+        }
+
+        if let Some(cooldown) = self.constraints.cooldown_after_loss {
+            self.cooldown_until = ts.saturating_add(cooldown);
+        }
+        self.consecutive_losses += 1;
+        if self
+            .constraints
+            .max_consecutive_losses
+            .is_some_and(|max| self.consecutive_losses >= max)
+        {
+            self.halted_day = Some(ts / NANOS_PER_DAY);
+            self.consecutive_losses = 0;
+        }
+    }
+}
+
+// A resting limit order with the expiry derived from its time in force
+struct PendingLimit {
+    order: Order,
+    expires_at: Option<u64>,
+}
+
+impl PendingLimit {
+    fn new(order: Order, placed_at: u64, vwap: &VwapConfig) -> Self {
+        let expires_at = match order.time_in_force {
+            TimeInForce::Gtc | TimeInForce::Ioc | TimeInForce::Fok => None,
+            TimeInForce::Day => Some(vwap.next_session_open(placed_at)),
+            TimeInForce::Gtd(ts) => Some(ts),
+        };
+        Self { order, expires_at }
+    }
+
+    fn immediate(&self) -> bool {
+        matches!(
+            self.order.time_in_force,
+            TimeInForce::Ioc | TimeInForce::Fok
+        )
+    }
+}
+
+/// Optional engine behaviour, defaults match [`run_backtest`]
+#[derive(Debug, Clone)]
+pub struct BacktestOptions {
+    pub decimation: Option<CurveDecimation>,
+    /// Session open and anchors of the VWAP service exposed through [`EventCtx`]
+    pub vwap: VwapConfig,
+    /// Number of returns behind [`EventCtx::rolling_vol`]
+    pub vol_window: usize,
+    pub price_mode: PriceMode,
+    pub constraints: TradingConstraints,
+    /// Compliance checks every strategy order passes when it reaches the market
+    pub pre_trade: PreTradeChecks,
+    /// Timestamped series from outside the engine, e.g. model scores, aligned to the
+    /// events and read through [`EventCtx::signal`]
+    pub signals: ExternalSignals,
+    /// Minimum liquidity of option contracts entered
+    pub liquidity: LiquidityFilter,
+    /// Margin sizing and guards for short option entries
+    pub short_options: Option<ShortOptionRules>,
+    /// Roll option positions into the next expiry as they near expiry
+    pub roll: Option<RollPolicy>,
+    /// Which lots partial exits close first
+    pub lot_method: LotMethod,
+    /// Events of history kept per instrument for [`Bracket`] distances, the longest ATR or
+    /// realized volatility window they can reference plus one
+    pub bracket_lookback: usize,
+    /// Let strategies add to open positions within limits
+    pub pyramiding: Option<PyramidRules>,
+    /// Sample equity at fixed intervals for [`BacktestResult::sampled_equity`] and the
+    /// time-based metrics
+    pub sampling: Option<EquitySampling>,
+    /// Track net Greeks of option positions into [`BacktestResult::greeks`]
+    pub greeks: Option<GreeksConfig>,
+    /// Shock open positions at each day close into [`BacktestResult::stress`]
+    pub stress: Option<StressConfig>,
+    /// Label each day of the underlying's regime into [`BacktestResult::regimes`]
+    pub regimes: Option<RegimeConfig>,
+    /// Track the rolling correlation and beta to the underlying into
+    /// [`BacktestResult::beta`]
+    pub beta: Option<BetaConfig>,
+    /// Record the equity marked with the open position at each UTC hour close into
+    /// [`BacktestResult::hourly_equity`]
+    pub hourly_marks: bool,
+    /// Margin futures positions are charged in [`BacktestResult::capital`]
+    pub futures_margin: FuturesMargin,
+    /// Size entries off current or starting equity
+    pub sizing: Sizing,
+    /// Scale entries down in drawdowns from the equity high-water mark, reported in
+    /// [`BacktestResult::throttle`]
+    pub throttle: Option<DrawdownThrottle>,
+    /// Per-instrument costs, the first matching override replaces the backtest's costs
+    /// for a position from entry to exit
+    pub cost_overrides: Vec<CostOverride>,
+    /// Per-instrument quantity steps sized orders round down to, whole units without a
+    /// match, e.g. [`LotSize::fractional_shares`]
+    pub lot_sizes: Vec<LotSize>,
+    /// Seeds every stochastic component draws from, recorded in [`BacktestResult::seeds`]
+    pub seeds: Seeds,
+    /// Tags and notes recorded in [`BacktestResult::metadata`] and shown in reports
+    pub metadata: RunMetadata,
+    /// Nanoseconds from a strategy's order to its arrival at the market. Orders are acted
+    /// on at the first event at or after arrival, market orders at that event's price.
+    pub latency: u64,
+    /// Record every order's life into [`BacktestResult::audit`]
+    pub audit: bool,
+    /// Record the account ledger into [`BacktestResult::ledger`]
+    pub ledger: Option<LedgerConfig>,
+    /// Check the engine's invariants after every event and abort the run with a dump of
+    /// its state on the first violation. Slow, meant for debugging the engine.
+    pub paranoid: bool,
+    /// Nanoseconds after which an option trade's underlying quote is stale, e.g. across
+    /// an overnight gap. Stale trades reach the strategy with
+    /// [`OptionTradeMsg::underlying_stale`] set and no underlying quote from the getters.
+    ///
+    /// [`OptionTradeMsg::underlying_stale`]: crate::event::OptionTradeMsg::underlying_stale
+    pub max_underlying_age: Option<u64>,
+    /// Report events processed, time range covered, equity and open positions
+    /// periodically while the backtest runs
+    pub progress: Option<ProgressConfig>,
+    /// Events before this timestamp only warm up the strategy and the engine's rolling
+    /// services: the strategy sees them but its orders are dropped and nothing is recorded
+    pub warmup_until: Option<u64>,
+}
+
+impl Default for BacktestOptions {
+    fn default() -> Self {
+        Self {
+            decimation: None,
+            vwap: VwapConfig::default(),
+            vol_window: 20,
+            price_mode: PriceMode::Float,
+            constraints: TradingConstraints::default(),
+            pre_trade: PreTradeChecks::default(),
+            signals: ExternalSignals::default(),
+            liquidity: LiquidityFilter::default(),
+            short_options: None,
+            roll: None,
+            lot_method: LotMethod::Fifo,
+            bracket_lookback: 256,
+            pyramiding: None,
+            sampling: None,
+            greeks: None,
+            stress: None,
+            regimes: None,
+            beta: None,
+            hourly_marks: false,
+            futures_margin: FuturesMargin::default(),
+            sizing: Sizing::Compounding,
+            throttle: None,
+            cost_overrides: Vec::new(),
+            lot_sizes: Vec::new(),
+            seeds: Seeds::default(),
+            metadata: RunMetadata::default(),
+            latency: 0,
+            audit: false,
+            ledger: None,
+            paranoid: false,
+            max_underlying_age: None,
+            progress: None,
+            warmup_until: None,
+        }
+    }
+}
+
+// Collects the equity curve, per event, per sampling interval or decimated with a log of
+// its points on disk, and the drawdown of every event whichever curve is kept
+struct EquityRecorder {
+    curve: Vec<f64>,
+    decimation: Option<CurveDecimation>,
+    sampler: Option<EquitySampler>,
+    writer: Option<csv::Writer<std::fs::File>>,
+    // Events in the decimated point being built and the first one's timestamp
+    point_events: usize,
+    point_start_ts: u64,
+    last: f64,
+    peak: f64,
+    max_dd: f64,
+    max_dd_pct: f64,
+}
+
+impl EquityRecorder {
+    fn new(
+        starting_equity: f64,
+        decimation: Option<CurveDecimation>,
+        sampling: Option<EquitySampling>,
+    ) -> Result<Self> {
+        let writer = match &decimation {
+            Some(config) => {
+                let mut writer = csv::Writer::from_path(&config.log_path)?;
+                writer.write_record([
+                    "point",
+                    "first_ts",
+                    "last_ts",
+                    "events",
+                    "equity",
+                    "peak_equity",
+                    "max_drawdown",
+                ])?;
+                Some(writer)
+            }
+            None => None,
+        };
+        Ok(Self {
+            curve: vec![starting_equity],
+            decimation,
+            sampler: sampling.map(EquitySampler::new),
+            writer,
+            point_events: 0,
+            point_start_ts: 0,
+            last: starting_equity,
+            peak: starting_equity,
+            max_dd: 0.0,
+            max_dd_pct: 0.0,
+        })
+    }
+
+    // The sampled series replaces the per-event curve
+    fn sampled_curve(&self) -> bool {
+        self.decimation.is_none()
+            && self
+                .sampler
+                .as_ref()
+                .is_some_and(|s| s.config.replace_curve)
+    }
+
+    // Index in the final curve of the point an event at `ts` lands in
+    fn point_index(&self, ts: u64) -> usize {
+        match &self.sampler {
+            Some(sampler) if self.sampled_curve() => {
+                let bucket = ts / sampler.config.interval;
+                let closing = sampler.bucket.is_some_and(|b| b != bucket) as usize;
+                sampler.points.len() + closing + 1
+            }
+            _ => self.curve.len(),
+        }
+    }
+
+    fn push(&mut self, equity: f64, ts: u64) -> Result<()> {
+        self.last = equity;
+        if let Some(sampler) = &mut self.sampler {
+            sampler.record(equity, ts);
+        }
+        self.peak = self.peak.max(equity);
+        let dd = self.peak - equity;
+        self.max_dd = self.max_dd.max(dd);
+        let dd_pct = dd / self.peak * 100.0;
+        if dd_pct > self.max_dd_pct {
+            self.max_dd_pct = dd_pct;
+        }
+
+        let Some(config) = &self.decimation else {
+            if !self.sampled_curve() {
+                self.curve.push(equity);
+            }
+            return Ok(());
+        };
+        let every = config.every_events;
+        if self.point_events == 0 {
+            self.point_start_ts = ts;
+        }
+        self.point_events += 1;
+        if self.point_events >= every {
+            self.close_point(ts)?;
+        }
+        Ok(())
+    }
+
+    fn close_point(&mut self, ts: u64) -> Result<()> {
+        if self.point_events == 0 {
+            return Ok(());
+        }
+        self.curve.push(self.last);
+        if let Some(writer) = &mut self.writer {
+            writer.write_record(&[
+                (self.curve.len() - 1).to_string(),
+                self.point_start_ts.to_string(),
+                ts.to_string(),
+                self.point_events.to_string(),
+                self.last.to_string(),
+                self.peak.to_string(),
+                self.max_dd.to_string(),
+            ])?;
+            writer.flush()?;
+        }
+        self.point_events = 0;
+        Ok(())
+    }
+
+    fn last(&self) -> f64 {
+        self.last
+    }
+
+    fn finish(
+        mut self,
+        starting_equity: f64,
+        ending_equity: f64,
+        trades: Vec<Trade>,
+        last_ts: u64,
+    ) -> Result<BacktestResult> {
+        self.close_point(last_ts)?;
+        let sampled_curve = self.sampled_curve();
+        let (max_dd, max_dd_pct) = (self.max_dd, self.max_dd_pct);
+
+        let samples = self.sampler.take().map(EquitySampler::finish);
+        let curve = match &samples {
+            Some((_, points)) if sampled_curve => std::iter::once(starting_equity)
+                .chain(points.iter().map(|&(_, eq)| eq))
+                .collect(),
+            _ => self.curve,
+        };
+
+        let mut result =
+            BacktestResult::calculate_metrics(starting_equity, ending_equity, curve, trades);
+        if let Some((config, points)) = samples {
+            result.sample_interval = Some(config.interval);
+            result.sampled_equity = points;
+            result.apply_time_metrics();
+        }
+        // Headline drawdown comes from every event, not from the curve kept
+        result.max_drawdown = max_dd;
+        result.max_drawdown_pct = max_dd_pct;
+        result.calmar_ratio = if max_dd_pct > 0.0 {
+            result.total_return_pct / max_dd_pct
+        } else {
+            0.0
+        };
+        Ok(result)
+    }
+}
+
+// Core backtesting logic that works with events
+#[allow(clippy::too_many_arguments)]
+pub async fn run_backtest(
+    symbol: &str,
+    backtest_manager: BacktestManager,
+    strategy: &mut dyn Strategy,
+    transaction_costs: TransactionCosts,
+    starting_equity: f64,
+    exposure: f64,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    time_range: Option<(u64, u64)>,
+) -> Result<BacktestResult> {
+    run_backtest_with_options(
+        symbol,
+        backtest_manager,
+        strategy,
+        transaction_costs,
+        starting_equity,
+        exposure,
+        schema,
+        custom_schema,
+        time_range,
+        &BacktestOptions::default(),
+    )
+    .await
+}
+
+/// [`run_backtest`] with non-default [`BacktestOptions`]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_backtest_with_options(
+    symbol: &str,
+    backtest_manager: BacktestManager,
+    strategy: &mut dyn Strategy,
+    transaction_costs: TransactionCosts,
+    starting_equity: f64,
+    exposure: f64,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    time_range: Option<(u64, u64)>,
+    options: &BacktestOptions,
+) -> Result<BacktestResult> {
+    let data_path = &backtest_manager.data_path;
+    if data_path.is_empty() {
+        return Err(anyhow::anyhow!("No data path provided"));
+    }
+    let time_range = match &backtest_manager.holdout {
+        Some(holdout) => holdout.in_sample(time_range)?,
+        None => time_range,
+    };
+
+    let data_files = fingerprint_data(data_path)?;
+    // GET THE STREAM
+    let data_iter = fetch::get_data_stream(data_path, schema).await?;
+
+    let mut result = run_backtest_on_stream(
+        symbol,
+        data_iter,
+        strategy,
+        transaction_costs,
+        starting_equity,
+        exposure,
+        custom_schema,
+        time_range,
+        options,
+    )
+    .await?;
+    result.data_files = data_files;
+    Ok(result)
+}
+
+/// [`run_backtest_with_options`] over an already opened event stream, such as the
+/// in-memory events of a [`WarmSession`](crate::research::WarmSession)
+#[allow(clippy::too_many_arguments)]
+pub async fn run_backtest_on_stream(
+    symbol: &str,
+    data_iter: MarketStream,
+    strategy: &mut dyn Strategy,
+    transaction_costs: TransactionCosts,
+    starting_equity: f64,
+    exposure: f64,
+    custom_schema: Option<InkBackSchema>,
+    time_range: Option<(u64, u64)>,
+    options: &BacktestOptions,
+) -> Result<BacktestResult> {
+    let mut data_iter = requirements::verify(&strategy.requirements(), data_iter).await?;
+    let is_options_trading = matches!(
+        custom_schema,
+        Some(InkBackSchema::CombinedOptionsUnderlying { .. })
+    );
+    let is_futures_trading =
+        symbol.ends_with(".v.0") || symbol.ends_with(".c.0") || symbol.ends_with(".FUT");
+    let futures_multiplier = if is_futures_trading {
+        get_future_from_symbol(symbol).map(get_future_multiplier)
+    } else {
+        None
+    };
+
+    let position_multiplier = if is_options_trading {
+        100.0
+    } else {
+        futures_multiplier.unwrap_or(1.0)
+    };
+    // The underlying leg of an options run trades at the underlying's own multiplier and,
+    // unless overridden, at the costs of the underlying's market rather than per contract
+    let underlying_costs = if !is_options_trading {
+        transaction_costs.clone()
+    } else if let Some(future) = get_future_from_symbol(symbol).filter(|_| is_futures_trading) {
+        TransactionCosts::futures_trading(get_future_tick_size(future))
+    } else {
+        TransactionCosts::equity_trading()
+    };
+
+    let mut ledger = Ledger::new(starting_equity).with_recording(options.ledger.clone());
+    let mut position = Position::Neutral;
+    let mut trades = Vec::new();
+    let mut equity_curve = EquityRecorder::new(
+        starting_equity,
+        options.decimation.clone(),
+        options.sampling.clone(),
+    )?;
+    let mut last_ts = 0;
+
+    let mut pending_order: Option<Order> = None;
+    // Orders still travelling to the market under `options.latency`, by arrival time
+    let mut in_flight: VecDeque<(u64, Order)> = VecDeque::new();
+    let mut audit = AuditLog::new(options.audit);
+    let mut invariants = options.paranoid.then(Invariants::default);
+    // Lots, costs and instrument of the open position, the instrument being the one
+    // hedges are reported against
+    let mut book = PositionBook {
+        lots: Vec::new(),
+        costs: &transaction_costs,
+        instrument: None,
+        rolled_in: false,
+        is_options: is_options_trading,
+        futures_multiplier,
+        price_mode: options.price_mode,
+        adds: 0,
+        time_stop_at: None,
+        bracket: None,
+    };
+    // Last price of the main position
+    let mut primary_mark = 0.0;
+    let mut hedges = HedgeBook::new();
+    let mut pending_hedges: Vec<Order> = Vec::new();
+    let mut pre_trade_rejections = Vec::new();
+    let mut hedge_trades = Vec::new();
+    let mut hedged_exposure = Vec::new();
+    let mut bars = BarHistory::new(options.bracket_lookback);
+    let mut throttle = options
+        .throttle
+        .as_ref()
+        .map(|config| ThrottleState::new(config, starting_equity));
+    let mut pending_limit_orders: Vec<PendingLimit> = Vec::new();
+
+    let mut prev_event: Option<MarketEvent> = None;
+    let mut symbology = SymbologyMap::new();
+    let price_mode = options.price_mode;
+    let tick_fallback = price_mode.tick_size();
+    let mut vwap = VwapService::new(&options.vwap);
+    let mut footprint = FootprintService::new();
+    let mut volatility = RollingVolatility::new(options.vol_window);
+    let mut constraints = ConstraintTracker::new(options.constraints.clone());
+    let mut liquidity = OptionLiquidity::new(options.liquidity.window);
+    let mut signals = SignalValues::new(&options.signals);
+    let mut short_option: Option<ShortOption> = None;
+    let mut assignment_risks = Vec::new();
+    let mut held_option: Option<HeldOption> = None;
+    let mut roll_target: Option<RollTarget> = None;
+    // Option expiries seen so far, puts then calls
+    let mut expirations: [BTreeSet<u64>; 2] = Default::default();
+    let mut greeks_series: Vec<GreeksPoint> = Vec::new();
+    let mut stress_points: Vec<StressPoint> = Vec::new();
+    let mut daily_bars = options.regimes.as_ref().map(|_| DailyBars::default());
+    let mut daily_marks = options.beta.as_ref().map(|_| DailyMarks::default());
+    let mut underlying_mark = 0.0;
+    let mut hourly_equity = Vec::new();
+    let mut capital_usage = CapitalTracker::default();
+    let mut indicators = IndicatorRecorder::default();
+    // Options are modelled at the Greeks report's rate, or its default
+    let option_rate = options.greeks.clone().unwrap_or_default().risk_free_rate;
+    let mut progress = options
+        .progress
+        .as_ref()
+        .map(|config| ProgressMeter::new(config, time_range));
+
+    // ASYNC LOOP
+    while let Some(event_res) = data_iter.next().await {
+        let mut event = event_res?; // Handle Result
+
+        // Definitions are reference data: record them and hand them to the strategy, but
+        // never fill against them. Definitions issued before the time range still apply.
+        if let MarketEvent::Definition(def) = &event {
+            if time_range.is_some_and(|(_, end_ts)| def.hd.ts_event >= end_ts) {
+                break;
+            }
+            symbology.update(def);
+            strategy.on_definition(def, &symbology);
+            continue;
+        }
+
+        // Time filter
+        if let Some((start_ts, end_ts)) = time_range {
+            let ts = event.timestamp();
+            if ts < start_ts {
+                continue;
+            }
+            if ts >= end_ts {
+                break;
+            }
+        }
+
+        if let (Some(max_age), MarketEvent::OptionTrade(m)) =
+            (options.max_underlying_age, &mut event)
+        {
+            m.underlying_stale = m
+                .underlying_ts
+                .is_some_and(|ts| m.ts_event.saturating_sub(ts) > max_age);
+        }
+
+        if options
+            .warmup_until
+            .is_some_and(|until| event.timestamp() < until)
+        {
+            vwap.update(&event);
+            footprint.update(&event);
+            volatility.update(&event);
+            bars.update(&event);
+            liquidity.update(&event);
+            signals.update(&event);
+            {
+                let ctx =
+                    EventCtx::new(&event, prev_event.as_ref(), &vwap, &volatility, &liquidity)
+                        .with_signals(&signals)
+                        .with_footprint(&footprint)
+                        .with_tick_size(book_tick_size(&event, &symbology, tick_fallback));
+                strategy.on_event_ctx(&event, prev_event.as_ref(), &ctx);
+            }
+            prev_event = Some(event);
+            continue;
+        }
+
+        if let Some(bars) = &mut daily_bars {
+            bars.observe(&event);
+        }
+
+        let day_closed =
+            last_ts != 0 && event.timestamp() / NANOS_PER_DAY != last_ts / NANOS_PER_DAY;
+
+        // Main position against its hedges at the previous day's close
+        if let (true, Some(last)) = (day_closed && !hedges.is_empty(), &prev_event) {
+            let primary = primary_notional(
+                &position,
+                held_option.as_ref(),
+                primary_mark,
+                position_multiplier,
+                option_rate,
+                last.timestamp(),
+            );
+            hedged_exposure.push(HedgedExposure {
+                ts: last.timestamp(),
+                date: last.date_string(),
+                primary,
+                hedges: hedges.notional(),
+                net: primary + hedges.notional(),
+            });
+        }
+
+        // Stress the position held at the previous day's close
+        if let Some(config) = &options.stress {
+            if day_closed {
+                stress_points.extend(stress_at_close(
+                    config,
+                    &position,
+                    held_option.as_ref(),
+                    prev_event.as_ref(),
+                    ledger.cash(),
+                    option_rate,
+                    position_multiplier,
+                ));
+            }
+        }
+
+        // Marked equity at the previous day's and hour's close
+        if let Some(last) = &prev_event {
+            let mark = if primary_mark > 0.0 {
+                primary_mark
+            } else {
+                last.price()
+            };
+            let marked = ledger.cash() + open_pnl(&position, mark, position_multiplier);
+            if let (Some(marks), true) = (&mut daily_marks, day_closed) {
+                marks.push(last.timestamp(), marked, underlying_mark);
+            }
+            if options.hourly_marks
+                && event.timestamp() / NANOS_PER_HOUR != last.timestamp() / NANOS_PER_HOUR
+                && marked.is_finite()
+            {
+                hourly_equity.push((last.timestamp(), marked));
+            }
+        }
+
+        // Update Avg Volume for slippage
+        let vol = event.volume() as f64;
+        let instrument_id = event.get_u64("instrument_id").map(|id| id as u32);
+        let is_underlying = !matches!(event, MarketEvent::OptionTrade(_));
+        if is_underlying {
+            hedges.mark(UNDERLYING_LEG, event.price());
+        }
+        if let Some(id) = instrument_id {
+            hedges.mark(id, event.price());
+            if book.instrument == Some(id) {
+                primary_mark = event.price();
+            }
+        }
+        underlying_mark = match &event {
+            MarketEvent::OptionTrade(m) => m.underlying_price,
+            _ => event.price(),
+        };
+        vwap.update(&event);
+        footprint.update(&event);
+        volatility.update(&event);
+        bars.update(&event);
+        liquidity.update(&event);
+
+        // Entries, adds and engine-sized hedges are scaled down while equity is throttled
+        let exposure = exposure * throttle.as_ref().map_or(1.0, ThrottleState::scale);
+
+        // Expire Day / GTD limit orders before they can fill
+        let ts = event.timestamp();
+        pending_limit_orders.retain(|pending| {
+            if pending.expires_at.is_some_and(|expiry| ts >= expiry) {
+                strategy.on_order_cancelled(&pending.order, CancelReason::Expired);
+                audit.record(
+                    ts,
+                    &pending.order,
+                    OrderOrigin::Strategy,
+                    AuditEvent::Cancelled {
+                        reason: CancelReason::Expired,
+                    },
+                );
+                false
+            } else {
+                true
+            }
+        });
+
+        // Reduce-only orders rest only while the position they reduce is open
+        pending_limit_orders.retain(|pending| {
+            let order = &pending.order;
+            if !order.reduce_only || reduces_position(order, &position) {
+                return true;
+            }
+            strategy.on_order_cancelled(order, CancelReason::PositionClosed);
+            audit.record(
+                ts,
+                order,
+                OrderOrigin::Strategy,
+                AuditEvent::Cancelled {
+                    reason: CancelReason::PositionClosed,
+                },
+            );
+            false
+        });
+
+        // Check Limit Orders, IOC / FOK orders get this one event to fill
+        let mut filled_limit_orders = Vec::new();
+        pending_limit_orders.retain(|pending| {
+            let order = &pending.order;
+            // Exits fill on the held instrument's prices only
+            let on_position = book.instrument.is_none() || instrument_id == book.instrument;
+            let mut fills =
+                should_fill_limit_order(order, &event) && (!order.reduce_only || on_position);
+            if fills && order.time_in_force == TimeInForce::Fok {
+                let contract_price = if is_options_trading {
+                    order.price * 100.0
+                } else {
+                    order.price
+                };
+                let size = if order.reduce_only {
+                    order
+                        .quantity
+                        .map_or(position.size(), |q| q.min(position.size()))
+                } else {
+                    let size = round_to_lot(
+                        options.sizing.base(ledger.cash(), starting_equity) * exposure
+                            / contract_price,
+                        lot_step(&options.lot_sizes, &event, &symbology),
+                    );
+                    order.quantity.map_or(size, |q| q.min(size))
+                };
+                fills = event.volume() as f64 >= size;
+            }
+
+            if fills {
+                filled_limit_orders.push(*order);
+                false
+            } else if pending.immediate() {
+                strategy.on_order_cancelled(order, CancelReason::NotFilled);
+                audit.record(
+                    ts,
+                    order,
+                    OrderOrigin::Strategy,
+                    AuditEvent::Cancelled {
+                        reason: CancelReason::NotFilled,
+                    },
+                );
+                false
+            } else {
+                true
+            }
+        });
 
-                        if pnl.is_finite() {
-                            equity += pnl;
-                            trades.push(Trade {
-                                entry_date: entry_date.clone(),
-                                exit_date: event.date_string(),
-                                entry_price: entry,
-                                exit_price,
-                                size,
-                                pnl,
-                                pnl_pct: ((exit_price / entry) - 1.0) * 100.0,
-                                trade_type: "Long".to_string(),
-                                exit_reason: "Strategy".to_string(),
-                                transaction_costs: 0.0, // Simplified
-                            });
-                            position = Position::Neutral;
+        // Reduce-only limits close part of the open position at their price, ahead of
+        // any entry filling on the same event
+        let (limit_exits, filled_limit_orders): (Vec<Order>, Vec<Order>) = filled_limit_orders
+            .into_iter()
+            .partition(|order| order.reduce_only);
+        for order in limit_exits {
+            let long = matches!(position, Position::Long { .. });
+            if !reduces_position(&order, &position) {
+                strategy.on_order_cancelled(&order, CancelReason::PositionClosed);
+                audit.record(
+                    ts,
+                    &order,
+                    OrderOrigin::Strategy,
+                    AuditEvent::Cancelled {
+                        reason: CancelReason::PositionClosed,
+                    },
+                );
+                continue;
+            }
+            if let Some(invariants) = &mut invariants {
+                invariants.fill(&event, order.order_type, order.price);
+            }
+            let size = position.size();
+            let quantity = order.quantity.map_or(size, |q| q.min(size));
+            let exit_price = price_mode.snap(
+                book.costs
+                    .fill_price(order.price, quantity, !long, own_quote(&event)),
+                !long,
+            );
+            if let Some(pnl) = book.close(
+                &mut position,
+                &event,
+                Some(quantity),
+                exit_price,
+                order.price,
+                order.exit_reason,
+                options.lot_method,
+                &mut ledger,
+                &mut trades,
+            ) {
+                constraints.record_exit(pnl, ts);
+                let left = position.size();
+                audit.record(
+                    ts,
+                    &order,
+                    OrderOrigin::Strategy,
+                    if left > 0.0 {
+                        AuditEvent::PartialFill {
+                            fill_price: exit_price,
+                            quantity,
+                            remaining: left,
                         }
-                    }
+                    } else {
+                        AuditEvent::Filled {
+                            fill_price: exit_price,
+                            quantity,
+                        }
+                    },
+                );
+                if left <= 0.0 {
+                    strategy.on_position_closed(order.exit_reason);
+                }
+            }
+        }
+
+        for order in filled_limit_orders.iter().skip(1) {
+            audit.record(
+                ts,
+                order,
+                OrderOrigin::Strategy,
+                ignored("another limit order filled on the same event"),
+            );
+        }
+        if let Some(order) = filled_limit_orders.first() {
+            if !matches!(position, Position::Neutral) {
+                audit.record(
+                    ts,
+                    order,
+                    OrderOrigin::Strategy,
+                    ignored("position already open"),
+                );
+            } else if let Some((entry, size)) = book.open(
+                &mut position,
+                order,
+                order.price,
+                &event,
+                options.sizing.base(ledger.cash(), starting_equity) * exposure,
+                hedges.leg(UNDERLYING_LEG).map_or(0.0, |leg| leg.quantity),
+                &transaction_costs,
+                options,
+                &symbology,
+                &liquidity,
+                &bars,
+                &mut constraints,
+            ) {
+                if let Some(invariants) = &mut invariants {
+                    invariants.fill(&event, order.order_type, order.price);
+                }
+                if matches!(position, Position::Short { .. }) {
+                    short_option = open_short_option(&event);
+                }
+                held_option = HeldOption::from_event(&event);
+                primary_mark = event.price();
+                if let Some(throttle) = &mut throttle {
+                    throttle.entry();
+                }
+                audit.record(
+                    ts,
+                    order,
+                    OrderOrigin::Strategy,
+                    AuditEvent::Filled {
+                        fill_price: entry,
+                        quantity: size,
+                    },
+                );
+            }
+        }
+        audit.rejections(&constraints.blocked);
+
+        // Fill hedge orders on their instrument's events
+        pending_hedges.retain(|order| {
+            let fills = matches!(
+                order.order_type,
+                OrderType::MarketBuy | OrderType::MarketSell
+            ) || should_fill_limit_order(order, &event);
+            let on_underlying = order.instrument == Some(UNDERLYING_LEG);
+            if !fills || !(order.instrument == instrument_id || on_underlying && is_underlying) {
+                return true;
+            }
+
+            let is_buy = matches!(order.order_type, OrderType::MarketBuy | OrderType::LimitBuy);
+            let price = match order.order_type {
+                OrderType::MarketBuy | OrderType::MarketSell => event.price(),
+                _ => order.price,
+            };
+            let costs = resolve_costs(
+                &options.cost_overrides,
+                if on_underlying {
+                    &underlying_costs
+                } else {
+                    &transaction_costs
+                },
+                &event,
+                &symbology,
+            );
+            // Reduce-only orders trade at most the leg's open quantity against them, all
+            // of it without a quantity
+            let held = hedges
+                .leg(order.instrument.unwrap_or_default())
+                .map_or(0.0, |leg| leg.quantity);
+            let reducible = if is_buy { -held } else { held }.max(0.0);
+            let quantity = match order.quantity {
+                _ if order.reduce_only => order.quantity.map_or(reducible, |q| q.min(reducible)),
+                Some(q) => q,
+                None => round_to_lot(
+                    options.sizing.base(ledger.cash(), starting_equity) * exposure / price,
+                    lot_step(&options.lot_sizes, &event, &symbology),
+                ),
+            };
+            let fill_price = price_mode.snap(
+                costs.fill_price(price, quantity, is_buy, own_quote(&event)),
+                is_buy,
+            );
+            let multiplier = if on_underlying {
+                futures_multiplier.unwrap_or(1.0)
+            } else {
+                symbology
+                    .get(order.instrument.unwrap_or_default(), ts)
+                    .map_or(
+                        if is_options_trading {
+                            1.0
+                        } else {
+                            position_multiplier
+                        },
+                        |info| info.multiplier,
+                    )
+            };
+
+            if quantity > 0.0 {
+                audit.record(
+                    ts,
+                    order,
+                    OrderOrigin::Strategy,
+                    AuditEvent::Filled {
+                        fill_price,
+                        quantity,
+                    },
+                );
+                if let Some(trade) = hedges.fill(
+                    order.instrument.unwrap_or_default(),
+                    if is_buy { quantity } else { -quantity },
+                    fill_price,
+                    multiplier,
+                    costs,
+                    vol,
+                    ts,
+                    &event.date_string(),
+                    order.exit_reason,
+                ) {
+                    ledger.post(&trade);
+                    hedge_trades.push(trade);
+                }
+            } else if order.reduce_only {
+                audit.record(
+                    ts,
+                    order,
+                    OrderOrigin::Strategy,
+                    ignored("reduce-only order with no leg to reduce"),
+                );
+            }
+            false
+        });
+
+        // Scale into the open position
+        if let Some(order) = pending_order.take_if(|_| !matches!(position, Position::Neutral)) {
+            let long = matches!(position, Position::Long { .. });
+            let fill_price = event.price();
+            let contract_price = if is_options_trading {
+                fill_price * 100.0
+            } else {
+                fill_price
+            };
+            let lot = lot_step(&options.lot_sizes, &event, &symbology);
+            let mut quantity = order.quantity.unwrap_or_else(|| {
+                round_to_lot(
+                    options.sizing.base(ledger.cash(), starting_equity) * exposure / contract_price,
+                    lot,
+                )
+            });
+
+            if let Some(rules) = &options.pyramiding {
+                let room = rules.max_exposure * ledger.cash() - position.size() * contract_price;
+                let room = round_to_lot(room / contract_price, lot);
+                if book.adds >= rules.max_adds {
+                    constraints.block(&order, &event, BlockReason::MaxAdds);
+                    quantity = 0.0;
+                } else if quantity.min(room) < lot {
+                    constraints.block(&order, &event, BlockReason::MaxExposure);
+                    quantity = 0.0;
+                } else {
+                    quantity = quantity.min(room);
                 }
-                Position::Short {
+            }
+
+            if quantity > 0.0 {
+                let entry = price_mode.snap(
+                    book.costs
+                        .fill_price(fill_price, quantity, long, own_quote(&event)),
+                    long,
+                );
+                book.adds += 1;
+                book.lots.push(Lot {
                     entry,
-                    size,
-                    ref entry_date,
-                } => {
-                    if order.order_type == OrderType::MarketBuy {
-                        let exit_price =
-                            transaction_costs.adjust_fill_price(order.price, size, true);
-                        let pnl = position.calculate_pnl_with_costs(
-                            exit_price,
-                            &transaction_costs,
-                            vol,
-                            is_options_trading,
-                            futures_multiplier,
-                        );
+                    reference: fill_price,
+                    size: quantity,
+                    entry_date: event.date_string(),
+                    opened_at: ts,
+                    index: book.adds,
+                    risk: order.risk,
+                });
+                position = Position::from_lots(long, &book.lots);
+                audit.record(
+                    ts,
+                    &order,
+                    OrderOrigin::Strategy,
+                    AuditEvent::Filled {
+                        fill_price: entry,
+                        quantity,
+                    },
+                );
+            }
+        }
+
+        // Check Market Orders, approximately filled at the event's price
+        if let Some(order) = pending_order.take() {
+            if !matches!(position, Position::Neutral) {
+                audit.record(
+                    ts,
+                    &order,
+                    OrderOrigin::Strategy,
+                    ignored("position already open"),
+                );
+            } else if let Some((entry, size)) = book.open(
+                &mut position,
+                &order,
+                event.price(),
+                &event,
+                options.sizing.base(ledger.cash(), starting_equity) * exposure,
+                hedges.leg(UNDERLYING_LEG).map_or(0.0, |leg| leg.quantity),
+                &transaction_costs,
+                options,
+                &symbology,
+                &liquidity,
+                &bars,
+                &mut constraints,
+            ) {
+                if matches!(position, Position::Short { .. }) {
+                    short_option = open_short_option(&event);
+                }
+                held_option = HeldOption::from_event(&event);
+                primary_mark = event.price();
+                if let Some(throttle) = &mut throttle {
+                    throttle.entry();
+                }
+                audit.record(
+                    ts,
+                    &order,
+                    OrderOrigin::Strategy,
+                    AuditEvent::Filled {
+                        fill_price: entry,
+                        quantity: size,
+                    },
+                );
+            }
+        }
+        audit.rejections(&constraints.blocked);
+
+        // Close the held option once it reaches the roll policy's days to expiry
+        if let (Some(policy), Some(held), MarketEvent::OptionTrade(m)) =
+            (&options.roll, &held_option, &event)
+        {
+            expirations[m.option_type.starts_with('C') as usize].insert(m.expiration);
+
+            let due = m.symbol == held.symbol
+                && held.expiration.saturating_sub(ts) as f64 <= policy.dte * NANOS_PER_DAY as f64;
+            let open = match position {
+                Position::Long { size, .. } => Some((true, size)),
+                Position::Short { size, .. } => Some((false, size)),
+                Position::Neutral => None,
+            };
+            if let (true, Some((long, size))) = (due, open) {
+                let exit_price = price_mode.snap(
+                    book.costs
+                        .fill_price(event.price(), size, !long, own_quote(&event)),
+                    !long,
+                );
+                let closed = book.close_all(
+                    &mut position,
+                    &event,
+                    exit_price,
+                    event.price(),
+                    ExitReason::Roll,
+                    OrderOrigin::Roll,
+                    &mut ledger,
+                    &mut trades,
+                    &mut audit,
+                );
+                if closed.is_some() {
+                    roll_target = Some(RollTarget {
+                        long,
+                        is_call: held.is_call,
+                        after: held.expiration,
+                        moneyness: held.moneyness,
+                    });
+                    short_option = None;
+                }
+            }
+        }
+
+        // An option still held at its expiration settles at intrinsic value
+        if let (Some(held), Position::Long { .. } | Position::Short { .. }) =
+            (&held_option, &position)
+        {
+            let spot = match &event {
+                MarketEvent::OptionTrade(m) => Some(m.underlying_mid()),
+                MarketEvent::Mbp1(_) => Some(event.price()),
+                _ => None,
+            };
+            if let Some(spot) = spot.filter(|_| ts >= held.expiration) {
+                let intrinsic = if held.is_call {
+                    (spot - held.strike).max(0.0)
+                } else {
+                    (held.strike - spot).max(0.0)
+                };
+                if let Some(pnl) = book.close_all(
+                    &mut position,
+                    &event,
+                    intrinsic,
+                    intrinsic,
+                    ExitReason::Expiration,
+                    OrderOrigin::Expiration,
+                    &mut ledger,
+                    &mut trades,
+                    &mut audit,
+                ) {
+                    constraints.record_exit(pnl, ts);
+                    strategy.on_position_closed(ExitReason::Expiration);
+                }
+            }
+        }
+
+        // Engine-managed exits at the prices of the position's instrument: a bracket leg
+        // the event reached, else the time stop once past its deadline
+        let on_position = book.instrument.is_none() || instrument_id == book.instrument;
+        let long = matches!(position, Position::Long { .. });
+        let engine_exit = match position {
+            Position::Neutral => None,
+            _ if !on_position => None,
+            _ => book.bracket.and_then(|b| b.hit(long, &event)).or_else(|| {
+                book.time_stop_at
+                    .filter(|&deadline| ts >= deadline)
+                    .map(|_| (ExitReason::TimeStop, event.price()))
+            }),
+        };
+        if let Some((reason, reference)) = engine_exit {
+            let exit_price = price_mode.snap(
+                book.costs
+                    .fill_price(reference, position.size(), !long, own_quote(&event)),
+                !long,
+            );
+            let origin = if reason == ExitReason::TimeStop {
+                OrderOrigin::TimeStop
+            } else {
+                OrderOrigin::Bracket
+            };
+            if let Some(pnl) = book.close_all(
+                &mut position,
+                &event,
+                exit_price,
+                reference,
+                reason,
+                origin,
+                &mut ledger,
+                &mut trades,
+                &mut audit,
+            ) {
+                constraints.record_exit(pnl, ts);
+                short_option = None;
+                book.time_stop_at = None;
+                book.bracket = None;
+                strategy.on_position_closed(reason);
+            }
+        }
+
+        // Open the analogous contract in the next expiry
+        if let (Some(policy), Some(target), MarketEvent::OptionTrade(m)) =
+            (&options.roll, &roll_target, &event)
+        {
+            if target.matches(m, policy, &expirations) {
+                let fill_price = event.price();
+                let capital = options.sizing.base(ledger.cash(), starting_equity) * exposure;
+                let order_type = if target.long {
+                    OrderType::MarketBuy
+                } else {
+                    OrderType::MarketSell
+                };
+                let short_size = match &options.short_options {
+                    Some(rules) => rules.short_size(
+                        &event,
+                        order_type,
+                        fill_price,
+                        capital,
+                        hedges.leg(UNDERLYING_LEG).map_or(0.0, |leg| leg.quantity),
+                    ),
+                    None => Ok(None),
+                };
+                let size = match short_size {
+                    Ok(Some(size)) => size,
+                    Ok(None) => (capital / (fill_price * 100.0)).floor(),
+                    Err(reason) => {
+                        constraints.block(&Order::new(order_type, fill_price), &event, reason);
+                        0.0
+                    }
+                };
+
+                if size >= 1.0 {
+                    book.costs = resolve_costs(
+                        &options.cost_overrides,
+                        &transaction_costs,
+                        &event,
+                        &symbology,
+                    );
+                    let entry = price_mode.snap(
+                        book.costs
+                            .fill_price(fill_price, size, target.long, own_quote(&event)),
+                        target.long,
+                    );
+                    let entry_date = event.date_string();
+                    position = if target.long {
+                        Position::Long {
+                            entry,
+                            size,
+                            entry_date,
+                        }
+                    } else {
+                        short_option = open_short_option(&event);
+                        Position::Short {
+                            entry,
+                            size,
+                            entry_date,
+                        }
+                    };
+                    held_option = HeldOption::from_event(&event);
+                    book.lots = position.opening_lot(ts, fill_price, None);
+                    book.instrument = instrument_id;
+                    primary_mark = event.price();
+                    book.adds = 0;
+                    book.rolled_in = true;
+                    audit.engine_fill(ts, order_type, entry, size, OrderOrigin::Roll);
+                }
+                roll_target = None;
+            }
+        }
+        audit.rejections(&constraints.blocked);
+
+        // Strategy Logic
+        signals.update(&event);
+        let ctx = EventCtx::new(&event, prev_event.as_ref(), &vwap, &volatility, &liquidity)
+            .with_signals(&signals)
+            .with_footprint(&footprint)
+            .with_tick_size(book_tick_size(&event, &symbology, tick_fallback));
+        let mut signal = strategy.on_event_ctx(&event, prev_event.as_ref(), &ctx);
+        indicators.push(ts, equity_curve.point_index(ts), ctx.take_records());
+        if let Some(order) = &signal {
+            audit.record(ts, order, OrderOrigin::Strategy, AuditEvent::Submitted);
+        }
+        // Orders reach the market after the latency, market orders at the price then
+        if options.latency > 0 {
+            if let Some(order) = signal.take() {
+                in_flight.push_back((ts + options.latency, order));
+            }
+            if in_flight.front().is_some_and(|(arrival, _)| *arrival <= ts) {
+                signal = in_flight.pop_front().map(|(_, mut order)| {
+                    if matches!(
+                        order.order_type,
+                        OrderType::MarketBuy | OrderType::MarketSell
+                    ) {
+                        order.price = event.price();
+                    }
+                    order
+                });
+            }
+        }
+        // Compliance checks see the order as it reaches the market
+        let refused = match &signal {
+            Some(order) if !options.pre_trade.is_empty() => {
+                let (instrument, held) = match (order.instrument, &position) {
+                    (Some(id), _) => (Some(id), hedges.leg(id).map_or(0.0, |leg| leg.quantity)),
+                    (None, Position::Long { size, .. }) => (book.instrument, *size),
+                    (None, Position::Short { size, .. }) => (book.instrument, -size),
+                    (None, Position::Neutral) => (instrument_id, 0.0),
+                };
+                let buy = compliance::is_buy(order.order_type);
+                let reduces = held > 0.0 && !buy || held < 0.0 && buy;
+                let quantity = match order.quantity {
+                    Some(q) if reduces && order.instrument.is_none() => q.min(held.abs()),
+                    Some(q) => q,
+                    None if reduces => held.abs(),
+                    None => {
+                        let contract_price = if is_options_trading {
+                            order.price * 100.0
+                        } else {
+                            order.price
+                        };
+                        round_to_lot(
+                            options.sizing.base(ledger.cash(), starting_equity) * exposure
+                                / contract_price,
+                            lot_step(&options.lot_sizes, &event, &symbology),
+                        )
+                    }
+                };
+                let order_symbol = match &event {
+                    MarketEvent::OptionTrade(m) if instrument == instrument_id => m.symbol.as_str(),
+                    _ => instrument
+                        .and_then(|id| symbology.get(id, ts))
+                        .map_or(symbol, |info| info.raw_symbol.as_str()),
+                };
+                let resting: Vec<Order> = pending_limit_orders
+                    .iter()
+                    .map(|pending| pending.order)
+                    .chain(pending_hedges.iter().copied())
+                    .collect();
+                options.pre_trade.review(&OrderCheck {
+                    order,
+                    ts,
+                    instrument_id: instrument,
+                    symbol: order_symbol,
+                    quantity,
+                    position: held,
+                    equity: ledger.cash(),
+                    resting: &resting,
+                })
+            }
+            _ => None,
+        };
+        if let Some(rejection) = refused {
+            if let Some(order) = signal.take() {
+                audit.record(
+                    ts,
+                    &order,
+                    OrderOrigin::Strategy,
+                    AuditEvent::Refused {
+                        check: rejection.check.clone(),
+                        reason: rejection.reason.clone(),
+                    },
+                );
+                strategy.on_order_rejected(&order, &rejection.check, &rejection.reason);
+                pre_trade_rejections.push(rejection);
+            }
+        }
+        // Orders on another instrument go to the hedge book, whatever the main position
+        if let Some(order) = signal.take_if(|order| order.instrument.is_some()) {
+            pending_hedges.push(order);
+        }
+        // While a roll waits for its contract the strategy still holds the position, and
+        // an exit abandons the roll
+        if let (Some(target), Some(order)) = (&roll_target, &signal) {
+            let abandon = order.order_type == target.exit_order_type();
+            audit.record(
+                ts,
+                order,
+                OrderOrigin::Strategy,
+                ignored(if abandon {
+                    "exit abandons the pending roll"
+                } else {
+                    "roll pending"
+                }),
+            );
+            signal = None;
+            if abandon {
+                roll_target = None;
+            }
+        }
+        if let Some(order) = signal {
+            match position {
+                Position::Long { size, .. } | Position::Short { size, .. } => {
+                    let long = matches!(position, Position::Long { .. });
+                    let (closing, adding) = if long {
+                        (OrderType::MarketSell, OrderType::MarketBuy)
+                    } else {
+                        (OrderType::MarketBuy, OrderType::MarketSell)
+                    };
 
-                        if pnl.is_finite() {
-                            equity += pnl;
-                            trades.push(Trade {
-                                entry_date: entry_date.clone(),
-                                exit_date: event.date_string(),
-                                entry_price: entry,
-                                exit_price,
-                                size,
-                                pnl,
-                                pnl_pct: ((entry / exit_price) - 1.0) * 100.0,
-                                trade_type: "Short".to_string(),
-                                exit_reason: "Strategy".to_string(),
-                                transaction_costs: 0.0,
-                            });
-                            position = Position::Neutral;
+                    if order.order_type == closing {
+                        if let Some(invariants) = &mut invariants {
+                            invariants.fill(&event, closing, order.price);
+                        }
+                        let quantity = order.quantity.map_or(size, |q| q.min(size));
+                        let exit_price = price_mode.snap(
+                            book.costs
+                                .fill_price(order.price, quantity, !long, own_quote(&event)),
+                            !long,
+                        );
+                        if let Some(pnl) = book.close(
+                            &mut position,
+                            &event,
+                            Some(quantity),
+                            exit_price,
+                            order.price,
+                            order.exit_reason,
+                            options.lot_method,
+                            &mut ledger,
+                            &mut trades,
+                        ) {
+                            constraints.record_exit(pnl, event.timestamp());
+                            let left = position.size();
+                            audit.record(
+                                ts,
+                                &order,
+                                OrderOrigin::Strategy,
+                                if left > 0.0 {
+                                    AuditEvent::PartialFill {
+                                        fill_price: exit_price,
+                                        quantity,
+                                        remaining: left,
+                                    }
+                                } else {
+                                    AuditEvent::Filled {
+                                        fill_price: exit_price,
+                                        quantity,
+                                    }
+                                },
+                            );
+                            if left <= 0.0 {
+                                strategy.on_position_closed(order.exit_reason);
+                            }
                         }
+                    } else if order.reduce_only && reduces_position(&order, &position) {
+                        // A resting take-profit or stop on part of the position
+                        pending_limit_orders.push(PendingLimit::new(
+                            order,
+                            event.timestamp(),
+                            &options.vwap,
+                        ));
+                    } else if order.reduce_only {
+                        audit.record(
+                            ts,
+                            &order,
+                            OrderOrigin::Strategy,
+                            ignored("reduce-only order would add to the position"),
+                        );
+                    } else if order.order_type == adding
+                        && (order.quantity.is_some() || options.pyramiding.is_some())
+                    {
+                        // Scale into the position at the next event
+                        pending_order = Some(order);
+                    } else {
+                        audit.record(
+                            ts,
+                            &order,
+                            OrderOrigin::Strategy,
+                            ignored("position already open"),
+                        );
                     }
                 }
+                Position::Neutral if order.reduce_only => audit.record(
+                    ts,
+                    &order,
+                    OrderOrigin::Strategy,
+                    ignored("reduce-only order with no position to reduce"),
+                ),
                 // Entry Logic
                 Position::Neutral => match order.order_type {
                     OrderType::MarketBuy | OrderType::MarketSell => pending_order = Some(order),
-                    OrderType::LimitBuy | OrderType::LimitSell => pending_limit_orders.push(order),
+                    OrderType::LimitBuy | OrderType::LimitSell => pending_limit_orders
+                        .push(PendingLimit::new(order, event.timestamp(), &options.vwap)),
                 },
             }
         }
 
+        // Watch the open short option for assignment risk
+        if matches!(position, Position::Neutral) {
+            short_option = None;
+            book.bracket = None;
+            // A position rolled into the next expiry keeps its time stop
+            if roll_target.is_none() {
+                held_option = None;
+                book.rolled_in = false;
+                book.time_stop_at = None;
+            }
+        }
+        if let (Some(short), Some(rules)) = (&mut short_option, &options.short_options) {
+            if let Some(risk) = short.check(&event, rules.assignment_window) {
+                assignment_risks.push(risk);
+            }
+        }
+
+        // Net Greeks of the held option
+        let quantity = match position {
+            Position::Long { size, .. } => size,
+            Position::Short { size, .. } => -size,
+            Position::Neutral => 0.0,
+        };
+        if options.greeks.is_some() || options.stress.is_some() || !hedges.is_empty() {
+            if let Some(held) = &mut held_option {
+                held.observe(&event, option_rate);
+            }
+        }
+        if options.greeks.is_some() {
+            match &held_option {
+                Some(held) if quantity != 0.0 => {
+                    if let Some(point) = held.greeks_point(ts, quantity, option_rate) {
+                        greeks_series.push(point);
+                    }
+                }
+                _ => {
+                    if greeks_series
+                        .last()
+                        .is_some_and(|p| p.implied_vol.is_some())
+                    {
+                        greeks_series.push(GreeksPoint {
+                            ts,
+                            greeks: Greeks::default(),
+                            implied_vol: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(invariants) = &mut invariants {
+            invariants.check(&EngineState {
+                event: &event,
+                equity: ledger.cash(),
+                starting_equity,
+                position: &position,
+                position_size: position.size(),
+                lots: &book.lots,
+                trades: &trades,
+                hedge_trades: &hedge_trades,
+                costs: book.costs,
+            })?;
+        }
+
+        let mark = if primary_mark > 0.0 {
+            primary_mark
+        } else {
+            event.price()
+        };
+        capital_usage.observe(
+            ts,
+            employed_capital(
+                &position,
+                held_option.as_ref(),
+                mark,
+                underlying_mark,
+                hedges.leg(UNDERLYING_LEG).map_or(0.0, |leg| leg.quantity),
+                is_options_trading,
+                futures_multiplier,
+                options,
+            ),
+        );
+
+        ledger.mark(ledger_positions(
+            &position,
+            book.instrument,
+            mark,
+            position_multiplier,
+            &hedges,
+        ));
+        ledger.reconcile(ts)?;
+        ledger.record(ts, false);
+        if let Some(throttle) = &mut throttle {
+            throttle.observe(ts, ledger.net_liquidation());
+        }
+
         // Update Equity Curve
-        if equity.is_finite() {
-            equity_curve.push(equity);
+        last_ts = event.timestamp();
+        if ledger.cash().is_finite() {
+            equity_curve.push(ledger.cash(), last_ts)?;
         } else {
-            equity_curve.push(*equity_curve.last().unwrap_or(&starting_equity));
+            let last = equity_curve.last();
+            equity_curve.push(last, last_ts)?;
+        }
+        if let Some(meter) = &mut progress {
+            meter.tick(last_ts, || {
+                (
+                    ledger.cash() + open_pnl(&position, mark, position_multiplier),
+                    open_positions(&position, &hedges),
+                )
+            });
         }
 
         prev_event = Some(event);
     }
 
-    Ok(BacktestResult::calculate_metrics(
-        starting_equity,
-        equity,
-        equity_curve,
-        trades,
-    ))
+    if let Some(config) = &options.stress {
+        stress_points.extend(stress_at_close(
+            config,
+            &position,
+            held_option.as_ref(),
+            prev_event.as_ref(),
+            ledger.cash(),
+            option_rate,
+            position_multiplier,
+        ));
+    }
+
+    if let Some(last) = &prev_event {
+        let mark = if primary_mark > 0.0 {
+            primary_mark
+        } else {
+            last.price()
+        };
+        let marked = ledger.cash() + open_pnl(&position, mark, position_multiplier);
+        if let Some(meter) = &mut progress {
+            meter.finish(last.timestamp(), || {
+                (marked, open_positions(&position, &hedges))
+            });
+        }
+        if let Some(marks) = &mut daily_marks {
+            marks.push(last.timestamp(), marked, underlying_mark);
+        }
+        if options.hourly_marks && marked.is_finite() {
+            hourly_equity.push((last.timestamp(), marked));
+        }
+    }
+
+    // Liquidate whatever is still open at the last price seen, costs included
+    let mut force_closed = Vec::new();
+    if let Some(last) = &prev_event {
+        let vol = last.volume() as f64;
+        let open = match position {
+            Position::Long { .. } => Some(true),
+            Position::Short { .. } => Some(false),
+            Position::Neutral => None,
+        };
+        if let Some(long) = open {
+            let mark = if primary_mark > 0.0 {
+                primary_mark
+            } else {
+                last.price()
+            };
+            let exit_price = price_mode.snap(
+                book.costs.fill_price(mark, position.size(), !long, None),
+                !long,
+            );
+            let closed_from = trades.len();
+            let closed = book.close_all(
+                &mut position,
+                last,
+                exit_price,
+                mark,
+                ExitReason::EndOfData,
+                OrderOrigin::EndOfData,
+                &mut ledger,
+                &mut trades,
+                &mut audit,
+            );
+            if closed.is_some() {
+                force_closed.extend_from_slice(&trades[closed_from..]);
+            }
+        }
+
+        let hedge_closes = hedges.liquidate(vol, last.timestamp(), &last.date_string());
+        ledger.post_all(&hedge_closes);
+        for trade in &hedge_closes {
+            audit.engine_fill(
+                last_ts,
+                if trade.trade_type == "Hedge Long" {
+                    OrderType::MarketSell
+                } else {
+                    OrderType::MarketBuy
+                },
+                trade.exit_price,
+                trade.size,
+                OrderOrigin::EndOfData,
+            );
+        }
+        hedge_trades.extend(hedge_closes.iter().cloned());
+        force_closed.extend(hedge_closes);
+
+        if !force_closed.is_empty() && ledger.cash().is_finite() {
+            equity_curve.push(ledger.cash(), last_ts)?;
+        }
+        ledger.mark([]);
+        ledger.reconcile(last_ts)?;
+        ledger.record(last_ts, true);
+    }
+    let mut result = equity_curve.finish(starting_equity, ledger.cash(), trades, last_ts)?;
+    result.blocked_entries = constraints.blocked;
+    result.pre_trade_rejections = pre_trade_rejections;
+    result.assignment_risks = assignment_risks;
+    result.greeks = greeks_series;
+    result.stress = stress_points;
+    result.force_closed = force_closed;
+    result.hedge_legs = hedges.summaries();
+    result.hedge_trades = hedge_trades;
+    result.hedged_exposure = hedged_exposure;
+    result.ledger = ledger.into_entries();
+    result.throttle = throttle.map(ThrottleState::finish);
+    result.seeds = options.seeds.clone();
+    result.metadata = options.metadata.clone();
+    result.audit = audit.into_records();
+    if let (Some(bars), Some(config)) = (daily_bars, &options.regimes) {
+        result.regimes = bars.label(config);
+    }
+    result.hourly_equity = hourly_equity;
+    result.capital = capital_usage.finish(result.total_return);
+    result.sizing = sizing::report(&result, options.sizing);
+    result.indicators = indicators.finish();
+    if let (Some(marks), Some(config)) = (daily_marks, &options.beta) {
+        result.beta = marks.rolling(config);
+    }
+    Ok(result)
 }
 
+/// Called from the worker threads with each combination's label and result as it finishes
+pub(crate) type ResultCallback<'a> = dyn Fn(&str, &BacktestResult) + Sync + 'a;
+
 // Internal: runs parallel backtest with optional time range, returns params alongside results
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn run_parallel_backtest_internal<F>(
     parameter_combinations: &[StrategyParams],
     backtest_manager: &BacktestManager,
@@ -517,10 +3503,19 @@ pub(crate) fn run_parallel_backtest_internal<F>(
     exposure: f64,
     transactions_model: &TransactionCosts,
     time_range: Option<(u64, u64)>,
+    on_result: Option<&ResultCallback<'_>>,
 ) -> Vec<(String, StrategyParams, BacktestResult, Vec<f64>)>
 where
     F: Fn(&StrategyParams) -> anyhow::Result<Box<dyn Strategy>> + Sync + Send,
 {
+    if let Some(Err(e)) = backtest_manager
+        .holdout
+        .as_ref()
+        .map(|holdout| holdout.in_sample(time_range))
+    {
+        eprintln!("Refusing the sweep: {}", e);
+        return Vec::new();
+    }
     let handle = tokio::runtime::Handle::current();
 
     let mut results: Vec<_> = parameter_combinations
@@ -537,7 +3532,7 @@ where
                     transactions_model.clone(),
                     starting_equity,
                     exposure,
-                    schema.clone(),
+                    schema,
                     custom_schema.clone(),
                     time_range,
                 ))
@@ -552,6 +3547,9 @@ where
                 index + 1,
                 params.to_string_representation()
             );
+            if let Some(callback) = on_result {
+                callback(&param_str, &result);
+            }
             let finite_curve = result.equity_curve.clone();
             Some((param_str, params.clone(), result, finite_curve))
         })
@@ -565,7 +3563,7 @@ where
     results
 }
 
-#[allow(dead_code)]
+#[allow(clippy::too_many_arguments)]
 pub fn run_parallel_backtest<F>(
     parameter_combinations: Vec<StrategyParams>,
     backtest_manager: BacktestManager,
@@ -596,6 +3594,7 @@ where
         exposure,
         &transactions_model,
         None,
+        None,
     );
 
     Some(
@@ -606,109 +3605,554 @@ where
     )
 }
 
-#[allow(dead_code)]
-pub async fn calculate_benchmark(
-    csv_path: &str,
+/// Same as [`run_parallel_backtest`], but every combination is sent to the plot window
+/// as soon as it finishes, so long sweeps can be monitored while they run. Curves are
+/// added to the `tab` tab and ranked by Sharpe ratio.
+#[allow(clippy::too_many_arguments)]
+pub fn run_parallel_backtest_live<F>(
+    parameter_combinations: Vec<StrategyParams>,
+    backtest_manager: BacktestManager,
     symbol: &str,
     schema: Schema,
     custom_schema: Option<InkBackSchema>,
+    strategy_constructor: F,
     starting_equity: f64,
     exposure: f64,
-) -> Result<BacktestResult> {
-    let mut data_iter = fetch::get_data_stream(csv_path, schema).await?;
+    transactions_model: TransactionCosts,
+    plot: &PlotSender,
+    tab: &str,
+) -> Option<Vec<(String, BacktestResult, Vec<f64>)>>
+where
+    F: Fn(&StrategyParams) -> anyhow::Result<Box<dyn Strategy>> + Sync + Send,
+{
+    let total = parameter_combinations.len();
+    println!("Testing {} parameter combinations (live)...", total);
 
-    let is_options_combined = matches!(
+    let finished = std::sync::atomic::AtomicUsize::new(0);
+    let on_result = |label: &str, result: &BacktestResult| {
+        let done = finished.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        let score = if result.sharpe_ratio.is_finite() {
+            result.sharpe_ratio
+        } else {
+            0.0
+        };
+        plot.add_ranked_curve(tab, label, result.equity_curve.clone(), score);
+        println!("[{}/{}] {} Sharpe: {:.2}", done, total, label, score);
+    };
+
+    let results = run_parallel_backtest_internal(
+        &parameter_combinations,
+        &backtest_manager,
+        symbol,
+        schema,
         custom_schema,
-        Some(InkBackSchema::CombinedOptionsUnderlying)
+        &strategy_constructor,
+        starting_equity,
+        exposure,
+        &transactions_model,
+        None,
+        Some(&on_result),
     );
 
-    // For combined options/underlying, filter to only underlying trades
-    let mut first_underlying_price: Option<f64> = None;
-    let mut last_underlying_price: Option<f64> = None;
-    let mut first_event_date: Option<String> = None;
-    let mut last_event_date: Option<String> = None;
+    Some(
+        results
+            .into_iter()
+            .map(|(label, _params, result, curve)| (label, result, curve))
+            .collect(),
+    )
+}
 
-    let multiplier = get_future_from_symbol(symbol)
-        .map(get_future_multiplier)
-        .unwrap_or(1.0);
+/// A reference curve strategies are measured against in [`display_results_with_baselines`]
+#[derive(Debug, Clone)]
+pub enum Baseline {
+    /// Buy the underlying on the first event with `exposure` of capital and hold to the end
+    BuyAndHold,
+    /// Buy-and-hold and cash split by `equity_weight` and never rebalanced,
+    /// `equity_weight: 0.6` gives a 60/40 blend
+    Blend { equity_weight: f64, cash_rate: f64 },
+    /// Starting equity left in cash, compounding at `annual_rate` (0.04 = 4%)
+    Cash { annual_rate: f64 },
+    /// The at-the-money call of the nearest expiry at least `min_dte` days out, bought
+    /// with `exposure` of equity, marked at its own trades and rolled `roll_dte` days
+    /// before expiry. Needs the option trades of a combined options stream.
+    RolledAtmCall { min_dte: f64, roll_dte: f64 },
+    /// The underlying held at the delta of the rolled ATM call, rebalanced at each roll,
+    /// for the direction of the call without its premium decay
+    DeltaEquivalent {
+        min_dte: f64,
+        roll_dte: f64,
+        risk_free_rate: f64,
+    },
+    /// A precomputed equity curve, one point per event like the strategy curves
+    Custom {
+        label: String,
+        equity_curve: Vec<f64>,
+    },
+}
+
+impl Baseline {
+    pub fn label(&self) -> String {
+        match self {
+            Baseline::BuyAndHold => "Buy & Hold".to_string(),
+            Baseline::Blend { equity_weight, .. } => format!(
+                "{:.0}/{:.0} Blend",
+                equity_weight * 100.0,
+                (1.0 - equity_weight) * 100.0
+            ),
+            Baseline::Cash { annual_rate } => format!("Cash {:.2}%", annual_rate * 100.0),
+            Baseline::RolledAtmCall { .. } => "Rolled ATM Call".to_string(),
+            Baseline::DeltaEquivalent { .. } => "Delta-Equivalent".to_string(),
+            Baseline::Custom { label, .. } => label.clone(),
+        }
+    }
+
+    /// What [`display_results`] compares against: the rolled ATM call and its delta
+    /// equivalent for combined options runs, where buy-and-hold of the multiplied
+    /// underlying is far more leveraged than the strategy, else buy-and-hold
+    pub fn defaults(custom_schema: &Option<InkBackSchema>) -> Vec<Baseline> {
+        match custom_schema {
+            Some(InkBackSchema::CombinedOptionsUnderlying { .. }) => vec![
+                Baseline::RolledAtmCall {
+                    min_dte: 30.0,
+                    roll_dte: 5.0,
+                },
+                Baseline::DeltaEquivalent {
+                    min_dte: 30.0,
+                    roll_dte: 5.0,
+                    risk_free_rate: GreeksConfig::default().risk_free_rate,
+                },
+            ],
+            _ => vec![Baseline::BuyAndHold],
+        }
+    }
+}
+
+/// Underlying prices of a data file, the input of every streamed baseline
+struct UnderlyingSeries {
+    points: Vec<(u64, f64)>,
+    /// Call trades of a combined options stream, in time order
+    calls: Vec<OptionTradeMsg>,
+    first_date: String,
+    last_date: String,
+}
+
+async fn load_underlying_series(
+    csv_path: &str,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+) -> Result<UnderlyingSeries> {
+    let mut data_iter = fetch::get_data_stream(csv_path, schema).await?;
+
+    let is_options_combined = matches!(
+        custom_schema,
+        Some(InkBackSchema::CombinedOptionsUnderlying { .. })
+    );
 
-    let mut equity_curve = vec![starting_equity];
+    let mut points = Vec::new();
+    let mut calls = Vec::new();
+    // Dates are formatted once, from the first and last timestamps
+    let mut first_ts: Option<u64> = None;
+    let mut last_ts: Option<u64> = None;
 
     // Iterate through all events
     while let Some(res) = data_iter.next().await {
         if let Ok(event) = res {
+            // For combined options/underlying, filter to only underlying trades
             if is_options_combined {
+                if let MarketEvent::OptionTrade(m) = event {
+                    if m.option_type.starts_with('C') {
+                        calls.push(m);
+                    }
+                    continue;
+                }
                 // underlying trades have event_type = "UND"
                 if let Some(event_type) = event.get_string("event_type") {
                     if event_type != "UND" {
                         continue; // Skip options trades
                     }
                 }
-                // Also check if it's an OptionTrade variant
-                if matches!(event, MarketEvent::OptionTrade(_)) {
-                    continue; // Skip option trades
-                }
-            }
-
-            let price = event.price();
-
-            // Set first price if not set
-            if first_underlying_price.is_none() {
-                first_underlying_price = Some(price);
-                first_event_date = Some(event.date_string());
             }
 
-            // Update last price
-            last_underlying_price = Some(price);
-            last_event_date = Some(event.date_string());
-
-            // Calculate equity based on buy and hold from first price
-            if let Some(entry_price) = first_underlying_price {
-                let capital = starting_equity * exposure;
-                let size = capital / entry_price;
-                let eq = (price - entry_price) * size * multiplier + starting_equity;
-                equity_curve.push(eq);
-            }
+            first_ts.get_or_insert(event.timestamp());
+            last_ts = Some(event.timestamp());
+            points.push((event.timestamp(), event.price()));
         }
     }
 
-    // Ensure we found underlying data
-    let entry_price = first_underlying_price
-        .ok_or_else(|| anyhow::anyhow!("No underlying data found for benchmark"))?;
-    let exit_price = last_underlying_price
-        .ok_or_else(|| anyhow::anyhow!("No underlying data found for benchmark"))?;
-    let entry_date = first_event_date
-        .ok_or_else(|| anyhow::anyhow!("No underlying data found for benchmark"))?;
-    let exit_date =
-        last_event_date.ok_or_else(|| anyhow::anyhow!("No underlying data found for benchmark"))?;
+    match (first_ts, last_ts) {
+        (Some(first_ts), Some(last_ts)) => Ok(UnderlyingSeries {
+            points,
+            calls,
+            first_date: event::date_string(first_ts),
+            last_date: event::date_string(last_ts),
+        }),
+        _ => Err(anyhow::anyhow!("No underlying data found for benchmark")),
+    }
+}
+
+fn buy_and_hold_result(
+    series: &UnderlyingSeries,
+    symbol: &str,
+    starting_equity: f64,
+    exposure: f64,
+) -> BacktestResult {
+    let multiplier = get_future_from_symbol(symbol)
+        .map(get_future_multiplier)
+        .unwrap_or(1.0);
 
+    let entry_price = series.points.first().map_or(0.0, |p| p.1);
+    let exit_price = series.points.last().map_or(0.0, |p| p.1);
     let capital = starting_equity * exposure;
     let size = capital / entry_price;
+
+    // Calculate equity based on buy and hold from first price
+    let mut equity_curve = Vec::with_capacity(series.points.len() + 1);
+    equity_curve.push(starting_equity);
+    equity_curve.extend(
+        series
+            .points
+            .iter()
+            .map(|&(_, price)| (price - entry_price) * size * multiplier + starting_equity),
+    );
+
     let pnl = (exit_price - entry_price) * size * multiplier;
 
     // Construct single trade result
     let trade = Trade {
-        entry_date,
-        exit_date,
+        entry_date: series.first_date.clone(),
+        exit_date: series.last_date.clone(),
         entry_price,
         exit_price,
         size,
         pnl,
         pnl_pct: (exit_price / entry_price - 1.0) * 100.0,
         trade_type: "Benchmark".to_string(),
-        exit_reason: "End".to_string(),
+        exit_reason: ExitReason::EndOfData,
         transaction_costs: 0.0,
+        gross_pnl: pnl,
+        rolled_in: false,
+        lot: 0,
+        r_multiple: None,
+        risk: None,
+        instrument_id: None,
+        entry_ts: series.points.first().map_or(0, |p| p.0),
+        exit_ts: series.points.last().map_or(0, |p| p.0),
     };
 
-    Ok(BacktestResult::calculate_metrics(
+    BacktestResult::calculate_metrics(
         starting_equity,
-        *equity_curve.last().unwrap(),
+        *equity_curve.last().unwrap_or(&starting_equity),
         equity_curve,
         vec![trade],
+    )
+}
+
+/// Cash compounding at `annual_rate` over the series' timestamps, aligned with the
+/// buy-and-hold curve
+fn cash_curve(series: &UnderlyingSeries, starting_equity: f64, annual_rate: f64) -> Vec<f64> {
+    const NANOS_PER_YEAR: f64 = 365.25 * 86_400.0 * 1e9;
+
+    let start_ts = series.points.first().map_or(0, |p| p.0);
+    let mut curve = Vec::with_capacity(series.points.len() + 1);
+    curve.push(starting_equity);
+    curve.extend(series.points.iter().map(|&(ts, _)| {
+        let years = ts.saturating_sub(start_ts) as f64 / NANOS_PER_YEAR;
+        starting_equity * (1.0 + annual_rate).powf(years)
+    }));
+    curve
+}
+
+// The call a rolled call benchmark holds
+struct BenchmarkCall {
+    instrument_id: u32,
+    strike: f64,
+    expiration: u64,
+    contracts: f64,
+    entry_ts: u64,
+    entry: f64,
+    mark: f64,
+    entry_spot: f64,
+    /// Underlying units held in its place, delta-equivalent benchmarks only
+    units: Option<f64>,
+}
+
+impl BenchmarkCall {
+    fn pnl(&self, spot: f64) -> f64 {
+        match self.units {
+            Some(units) => (spot - self.entry_spot) * units,
+            None => (self.mark - self.entry) * self.contracts * 100.0,
+        }
+    }
+
+    fn close(self, ts: u64, spot: f64, exit_reason: ExitReason) -> Trade {
+        let pnl = self.pnl(spot);
+        let (size, entry_price, exit_price) = match self.units {
+            Some(units) => (units, self.entry_spot, spot),
+            None => (self.contracts, self.entry, self.mark),
+        };
+        Trade {
+            entry_date: event::date_string(self.entry_ts),
+            exit_date: event::date_string(ts),
+            entry_price,
+            exit_price,
+            size,
+            pnl,
+            pnl_pct: (exit_price / entry_price - 1.0) * 100.0,
+            trade_type: "Benchmark".to_string(),
+            exit_reason,
+            transaction_costs: 0.0,
+            gross_pnl: pnl,
+            rolled_in: false,
+            lot: 0,
+            r_multiple: None,
+            risk: None,
+            instrument_id: Some(self.instrument_id),
+            entry_ts: self.entry_ts,
+            exit_ts: ts,
+        }
+    }
+}
+
+/// The rolled ATM call of [`Baseline::RolledAtmCall`], or with `delta_rate` the
+/// underlying held at its delta as in [`Baseline::DeltaEquivalent`]. Contracts are sized
+/// like the engine sizes option entries, `exposure` of equity over the premium of 100
+/// units, and only calls traded within the last day are picked.
+fn rolled_call_result(
+    series: &UnderlyingSeries,
+    starting_equity: f64,
+    exposure: f64,
+    min_dte: f64,
+    roll_dte: f64,
+    delta_rate: Option<f64>,
+) -> Result<BacktestResult> {
+    if series.calls.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No call trades in the data, a rolled call needs a combined options stream"
+        ));
+    }
+    if min_dte <= roll_dte {
+        return Err(anyhow::anyhow!(
+            "min_dte ({}) must exceed roll_dte ({}) or every call is rolled on entry",
+            min_dte,
+            roll_dte
+        ));
+    }
+    let roll_nanos = (roll_dte * NANOS_PER_DAY as f64) as u64;
+    let min_nanos = (min_dte * NANOS_PER_DAY as f64) as u64;
+
+    let mut latest: HashMap<u32, &OptionTradeMsg> = HashMap::new();
+    let mut next_call = 0;
+    let mut realized = starting_equity;
+    let mut held: Option<BenchmarkCall> = None;
+    let mut trades = Vec::new();
+    let mut equity_curve = Vec::with_capacity(series.points.len() + 1);
+    equity_curve.push(starting_equity);
+
+    for &(ts, spot) in &series.points {
+        while let Some(m) = series.calls.get(next_call).filter(|m| m.ts_event <= ts) {
+            if let Some(h) = held.as_mut().filter(|h| h.instrument_id == m.instrument_id) {
+                h.mark = m.price;
+            }
+            latest.insert(m.instrument_id, m);
+            next_call += 1;
+        }
+
+        if let Some(mut h) = held.take() {
+            if ts >= h.expiration {
+                h.mark = (spot - h.strike).max(0.0);
+                realized += h.pnl(spot);
+                trades.push(h.close(ts, spot, ExitReason::Expiration));
+            } else if h.expiration - ts <= roll_nanos {
+                realized += h.pnl(spot);
+                trades.push(h.close(ts, spot, ExitReason::Roll));
+            } else {
+                held = Some(h);
+            }
+        }
+
+        if held.is_none() && realized > 0.0 {
+            latest.retain(|_, m| m.expiration > ts);
+            let recent = || {
+                latest.values().filter(|m| {
+                    ts - m.ts_event <= NANOS_PER_DAY
+                        && m.expiration - ts >= min_nanos
+                        && m.price > 0.0
+                })
+            };
+            let expiry = recent().map(|m| m.expiration).min();
+            let atm = recent()
+                .filter(|m| Some(m.expiration) == expiry)
+                .min_by(|a, b| {
+                    (a.strike_price - spot)
+                        .abs()
+                        .total_cmp(&(b.strike_price - spot).abs())
+                });
+            if let Some(m) = atm {
+                let contracts = realized * exposure / (m.price * 100.0);
+                let units = delta_rate.map(|rate| {
+                    let t = greeks::years(m.expiration - ts);
+                    let delta = greeks::implied_vol(true, m.price, spot, m.strike_price, t, rate)
+                        .map_or(0.5, |vol| {
+                            greeks::greeks(true, spot, m.strike_price, t, rate, vol).delta
+                        });
+                    contracts * 100.0 * delta
+                });
+                held = Some(BenchmarkCall {
+                    instrument_id: m.instrument_id,
+                    strike: m.strike_price,
+                    expiration: m.expiration,
+                    contracts,
+                    entry_ts: ts,
+                    entry: m.price,
+                    mark: m.price,
+                    entry_spot: spot,
+                    units,
+                });
+            }
+        }
+
+        equity_curve.push(realized + held.as_ref().map_or(0.0, |h| h.pnl(spot)));
+    }
+
+    if let (Some(h), Some(&(ts, spot))) = (held, series.points.last()) {
+        trades.push(h.close(ts, spot, ExitReason::EndOfData));
+    }
+
+    Ok(BacktestResult::calculate_metrics(
+        starting_equity,
+        *equity_curve.last().unwrap_or(&starting_equity),
+        equity_curve,
+        trades,
+    ))
+}
+
+pub async fn calculate_benchmark(
+    csv_path: &str,
+    symbol: &str,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    starting_equity: f64,
+    exposure: f64,
+) -> Result<BacktestResult> {
+    let series = load_underlying_series(csv_path, schema, custom_schema).await?;
+    Ok(buy_and_hold_result(
+        &series,
+        symbol,
+        starting_equity,
+        exposure,
     ))
 }
 
-#[allow(dead_code)]
+/// Compute each baseline, streaming the data file at most once.
+///
+/// Baselines that cannot be computed are reported and left out rather than failing the rest.
+pub async fn calculate_baselines(
+    baselines: &[Baseline],
+    csv_path: &str,
+    symbol: &str,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    starting_equity: f64,
+    exposure: f64,
+) -> Vec<(String, BacktestResult)> {
+    let needs_series = baselines
+        .iter()
+        .any(|b| !matches!(b, Baseline::Custom { .. }));
+    let series = if needs_series {
+        Some(load_underlying_series(csv_path, schema, custom_schema).await)
+    } else {
+        None
+    };
+
+    let mut results = Vec::with_capacity(baselines.len());
+    for baseline in baselines {
+        let label = baseline.label();
+
+        if let Baseline::Custom { equity_curve, .. } = baseline {
+            let final_equity = *equity_curve.last().unwrap_or(&starting_equity);
+            results.push((
+                label,
+                BacktestResult::calculate_metrics(
+                    starting_equity,
+                    final_equity,
+                    equity_curve.clone(),
+                    Vec::new(),
+                ),
+            ));
+            continue;
+        }
+
+        let series = match &series {
+            Some(Ok(series)) => series,
+            Some(Err(e)) => {
+                eprintln!("{} unavailable ({})", label, e);
+                continue;
+            }
+            None => continue,
+        };
+
+        let result = match baseline {
+            Baseline::BuyAndHold => Ok(buy_and_hold_result(
+                series,
+                symbol,
+                starting_equity,
+                exposure,
+            )),
+            Baseline::Cash { annual_rate } => {
+                let curve = cash_curve(series, starting_equity, *annual_rate);
+                let final_equity = *curve.last().unwrap_or(&starting_equity);
+                Ok(BacktestResult::calculate_metrics(
+                    starting_equity,
+                    final_equity,
+                    curve,
+                    Vec::new(),
+                ))
+            }
+            Baseline::Blend {
+                equity_weight,
+                cash_rate,
+            } => {
+                let held = buy_and_hold_result(series, symbol, starting_equity, exposure);
+                let cash = cash_curve(series, starting_equity, *cash_rate);
+                let curve: Vec<f64> = held
+                    .equity_curve
+                    .iter()
+                    .zip(&cash)
+                    .map(|(h, c)| equity_weight * h + (1.0 - equity_weight) * c)
+                    .collect();
+                let final_equity = *curve.last().unwrap_or(&starting_equity);
+                Ok(BacktestResult::calculate_metrics(
+                    starting_equity,
+                    final_equity,
+                    curve,
+                    Vec::new(),
+                ))
+            }
+            Baseline::RolledAtmCall { min_dte, roll_dte } => {
+                rolled_call_result(series, starting_equity, exposure, *min_dte, *roll_dte, None)
+            }
+            Baseline::DeltaEquivalent {
+                min_dte,
+                roll_dte,
+                risk_free_rate,
+            } => rolled_call_result(
+                series,
+                starting_equity,
+                exposure,
+                *min_dte,
+                *roll_dte,
+                Some(*risk_free_rate),
+            ),
+            Baseline::Custom { .. } => unreachable!(),
+        };
+        match result {
+            Ok(result) => results.push((label, result)),
+            Err(e) => eprintln!("{} unavailable ({})", label, e),
+        }
+    }
+    results
+}
+
 pub async fn display_results(
     sorted_results: Option<Vec<(String, BacktestResult, Vec<f64>)>>,
     csv_path: &str,
@@ -717,37 +4161,88 @@ pub async fn display_results(
     custom_schema: Option<InkBackSchema>,
     starting_equity: f64,
     exposure: f64,
+) {
+    let baselines = Baseline::defaults(&custom_schema);
+    display_results_with_baselines(
+        sorted_results,
+        csv_path,
+        symbol,
+        schema,
+        custom_schema,
+        starting_equity,
+        exposure,
+        &baselines,
+    )
+    .await
+}
+
+/// [`display_results`] against any number of baselines, each listed in the comparison
+/// table and drawn on the chart
+#[allow(clippy::too_many_arguments)]
+pub async fn display_results_with_baselines(
+    sorted_results: Option<Vec<(String, BacktestResult, Vec<f64>)>>,
+    csv_path: &str,
+    symbol: &str,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    starting_equity: f64,
+    exposure: f64,
+    baselines: &[Baseline],
 ) {
     let mut equity_curves: Vec<(String, Vec<f64>)> = Vec::new();
 
-    // Run benchmark on underlying asset
-    let benchmark = calculate_benchmark(
-        &csv_path,
+    let mut baselines = calculate_baselines(
+        baselines,
+        csv_path,
         symbol,
         schema,
         custom_schema,
         starting_equity,
         exposure,
     )
-    .await
-    .unwrap();
+    .await;
 
-    println!(
-        "Benchmark Return: {:.2}%, Max Drawdown: {:.2}%",
-        benchmark.total_return_pct, benchmark.max_drawdown_pct
-    );
+    if baselines.is_empty() {
+        // A sweep's results are still worth reporting without a benchmark
+        eprintln!("No baseline available, comparing against flat equity");
+        baselines.push((
+            "Flat".to_string(),
+            BacktestResult::calculate_metrics(
+                starting_equity,
+                starting_equity,
+                vec![starting_equity],
+                Vec::new(),
+            ),
+        ));
+    }
+
+    for (label, baseline) in &baselines {
+        println!(
+            "{} Return: {:.2}%, Max Drawdown: {:.2}%, Sharpe: {:.2}",
+            label, baseline.total_return_pct, baseline.max_drawdown_pct, baseline.sharpe_ratio
+        );
+    }
 
     if let Some(sorted_results) = sorted_results {
+        check_same_data(
+            sorted_results
+                .iter()
+                .map(|(label, result, _)| (label.as_str(), result)),
+        );
+
         // Print results for all strategies
         println!("\n=== ALL STRATEGY RESULTS ===");
-        println!(
-            "Benchmark: Return {:.2}%, Max DD: {:.2}%\n",
-            benchmark.total_return_pct, benchmark.max_drawdown_pct
-        );
+        for (label, baseline) in &baselines {
+            println!(
+                "{}: Return {:.2}%, Max DD: {:.2}%",
+                label, baseline.total_return_pct, baseline.max_drawdown_pct
+            );
+        }
+        println!();
 
         for (i, (param_str, result, _)) in sorted_results.iter().enumerate() {
             println!(
-                "{}. {}: Ret: {:.2}%, DD: {:.2}%, Sharpe: {:.2}, Sortino: {:.2}, Calmar: {:.2}, WR: {:.1}%, PF: {:.2}, Trades: {}, Fees: ${:.0}",
+                "{}. {}: Ret: {:.2}%, DD: {:.2}%, Sharpe: {:.2}, Sortino: {:.2}, Calmar: {:.2}, WR: {:.1}%, PF: {:.2}, Trades: {}, Fees: ${:.0}, RoC: {:.2}%",
                 i + 1,
                 param_str,
                 if result.total_return_pct.is_finite() { result.total_return_pct } else { 0.0 },
@@ -758,7 +4253,8 @@ pub async fn display_results(
                 if result.win_rate.is_finite() { result.win_rate } else { 0.0 },
                 if result.profit_factor.is_finite() { result.profit_factor } else { 0.0 },
                 result.total_trades,
-                if result.total_transaction_costs.is_finite() { result.total_transaction_costs } else { 0.0 }
+                if result.total_transaction_costs.is_finite() { result.total_transaction_costs } else { 0.0 },
+                if result.capital.return_on_peak_pct.is_finite() { result.capital.return_on_peak_pct } else { 0.0 }
             );
 
             // Store equity curve for plotting
@@ -797,31 +4293,100 @@ pub async fn display_results(
             println!("Average return: {:.2}%", avg_return);
             println!("Best return: {:.2}%", best_return);
             println!("Worst return: {:.2}%", worst_return);
-            println!("Benchmark return: {:.2}%", benchmark.total_return_pct);
 
-            let outperforming = sorted_results
-                .iter()
-                .filter(|(_, result, _)| result.total_return_pct > benchmark.total_return_pct)
-                .count();
-            println!(
-                "Strategies beating benchmark: {} ({:.1}%)",
-                outperforming,
-                (outperforming as f64 / sorted_results.len() as f64) * 100.0
-            );
+            let all: Vec<&BacktestResult> = sorted_results.iter().map(|(_, r, _)| r).collect();
+            if let Some(bias) = significance::selection_bias(&all, 16) {
+                significance::display_selection_bias(&bias);
+            }
+            let marginals = marginals::sweep_marginals(&sorted_results, |r| r.sharpe_ratio);
+            if !marginals.is_empty() {
+                marginals::display_marginals("Sharpe", &marginals);
+            }
+
+            if let Some((_, best, _)) = sorted_results.first() {
+                if !best.metadata.is_empty() {
+                    metadata::display_run_metadata(&best.metadata);
+                }
+                if !best.force_closed.is_empty() {
+                    println!("\n=== FORCE CLOSED AT END OF DATA ===");
+                    for t in &best.force_closed {
+                        println!(
+                            "{} {:.2} @ {:.4} ({}) -> {:.4} ({}) | PnL: ${:.2}",
+                            t.trade_type,
+                            t.size,
+                            t.entry_price,
+                            t.entry_date,
+                            t.exit_price,
+                            t.exit_date,
+                            t.pnl
+                        );
+                    }
+                }
+                if !best.trades.is_empty() {
+                    display_exit_reasons(best);
+                }
+                if !best.pre_trade_rejections.is_empty() {
+                    compliance::display_pre_trade_rejections(best);
+                }
+                if let Some(stats) = best.r_statistics(0.5) {
+                    display_r_statistics(&stats);
+                }
+                if !best.regimes.is_empty() {
+                    regimes::display_regime_breakdown(best);
+                }
+                if !best.beta.is_empty() {
+                    beta::display_beta(best);
+                }
+                capital::display_capital_usage(best);
+                if !best.trades.is_empty() {
+                    display_cost_drag(best);
+                }
+                if !best.outliers.is_empty() {
+                    display_outlier_exclusion(best);
+                }
+                if !best.indicators.is_empty() {
+                    display_indicators(best);
+                }
+                if !best.trades.is_empty() {
+                    sizing::display_sizing(best);
+                }
+                sizing::display_throttle(best);
+            }
+
+            for (label, baseline) in &baselines {
+                println!("{} return: {:.2}%", label, baseline.total_return_pct);
+
+                let outperforming = sorted_results
+                    .iter()
+                    .filter(|(_, result, _)| result.total_return_pct > baseline.total_return_pct)
+                    .count();
+                println!(
+                    "Strategies beating {}: {} ({:.1}%)",
+                    label,
+                    outperforming,
+                    (outperforming as f64 / sorted_results.len() as f64) * 100.0
+                );
+            }
         }
 
         // Plot equity curves
         if !equity_curves.is_empty() {
             println!("\nLaunching performance chart for all strategies...");
-            let finite_benchmark: Vec<f64> = benchmark
-                .equity_curve
-                .iter()
-                .map(|&val| {
-                    if val.is_finite() {
-                        val
-                    } else {
-                        starting_equity
-                    }
+            let finite_baselines: Vec<(String, Vec<f64>)> = baselines
+                .into_iter()
+                .map(|(label, baseline)| {
+                    let curve = baseline
+                        .equity_curve
+                        .iter()
+                        .map(|&val| {
+                            if val.is_finite() {
+                                val
+                            } else {
+                                starting_equity
+                            }
+                        })
+                        .collect();
+                    (label, curve)
                 })
                 .collect();
 
@@ -838,13 +4403,105 @@ pub async fn display_results(
                 equity_curves
             };
 
-            plot_equity_curves(curves_to_plot, Some(finite_benchmark));
+            // The best strategy's recorded series go in the panel below
+            let indicators = sorted_results
+                .first()
+                .map(|(_, best, _)| best.indicators.clone())
+                .unwrap_or_default();
+            plot_equity_curves_with_indicators(
+                curves_to_plot,
+                finite_baselines,
+                indicators,
+                PlotConfig::default(),
+            );
         }
     } else {
         println!("Failed to run backtest - no results returned");
     }
 }
 
+fn display_indicators(result: &BacktestResult) {
+    println!("\n=== RECORDED SERIES ===");
+    for series in &result.indicators {
+        let values = series
+            .points
+            .iter()
+            .map(|p| p.value)
+            .filter(|v| v.is_finite());
+        let (min, max) = values
+            .clone()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(v), hi.max(v))
+            });
+        println!(
+            "{}: Points: {}, Last: {:.4}, Min: {:.4}, Max: {:.4}",
+            series.name,
+            series.points.len(),
+            series.points.last().map_or(f64::NAN, |p| p.value),
+            min,
+            max
+        );
+    }
+}
+
+fn display_cost_drag(result: &BacktestResult) {
+    let baseline = &result.cost_free;
+    println!("\n=== COST-FREE BASELINE ===");
+    println!(
+        "Gross PnL: ${:.2} ({:.2}%, WR: {:.1}%) | Net PnL: ${:.2} ({:.2}%, WR: {:.1}%) | Costs: ${:.2}",
+        baseline.gross_pnl,
+        baseline.total_return_pct,
+        baseline.win_rate,
+        baseline.gross_pnl - baseline.costs,
+        result.total_return_pct,
+        result.win_rate,
+        baseline.costs
+    );
+    match baseline.edge_consumed_pct {
+        Some(pct) => println!("Costs consume {:.1}% of the gross edge", pct),
+        None => println!("No gross edge before costs"),
+    }
+}
+
+fn display_outlier_exclusion(result: &BacktestResult) {
+    println!("\n=== OUTLIER TRADE EXCLUSION ===");
+    println!(
+        "{:<12} {:<8} {:<14} {:<10} {:<8} {:<8} {:<8}",
+        "Trades", "Count", "Removed PnL", "Return%", "WR%", "PF", "Sharpe"
+    );
+    println!("{}", "-".repeat(72));
+    println!(
+        "{:<12} {:<8} {:<14.2} {:<10.2} {:<8.1} {:<8.2} {:<8.2}",
+        "All",
+        result.total_trades,
+        0.0,
+        result.total_return_pct,
+        result.win_rate,
+        result.profit_factor,
+        result.sharpe_ratio
+    );
+    for row in &result.outliers {
+        println!(
+            "{:<12} {:<8} {:<14.2} {:<10.2} {:<8.1} {:<8.2} {:<8.2}",
+            format!("Best {} out", row.removed),
+            row.total_trades,
+            row.removed_pnl,
+            row.total_return_pct,
+            row.win_rate,
+            row.profit_factor,
+            row.sharpe_ratio
+        );
+    }
+    if result.total_return_pct > 0.0 {
+        if let Some(row) = result.outliers.iter().find(|r| r.total_return_pct <= 0.0) {
+            println!(
+                "WARNING: the whole return comes from the best {} trade(s)",
+                row.removed
+            );
+        }
+    }
+}
+
 fn get_future_multiplier(future_traded: FutureTraded) -> f64 {
     match future_traded {
         FutureTraded::NQ => 5.00,  // $5 per tick (0.25 tick size)
@@ -853,11 +4510,40 @@ fn get_future_multiplier(future_traded: FutureTraded) -> f64 {
         FutureTraded::CL => 10.00, // $10 per tick (0.01 tick size)
         FutureTraded::GC => 10.00, // $10 per tick (0.10 tick size)
         FutureTraded::SI => 25.00, // $25 per tick (0.005 tick size)
+        FutureTraded::MNQ => 0.50, // $0.50 per tick (0.25 tick size)
+        FutureTraded::MES => 1.25, // $1.25 per tick (0.25 tick size)
+        FutureTraded::MYM => 0.50, // $0.50 per tick (1.00 tick size)
+        FutureTraded::MCL => 1.00, // $1 per tick (0.01 tick size)
+        FutureTraded::MGC => 1.00, // $1 per tick (0.10 tick size)
+        FutureTraded::SIL => 5.00, // $5 per tick (0.005 tick size)
+    }
+}
+
+fn get_future_tick_size(future_traded: FutureTraded) -> f64 {
+    match future_traded {
+        FutureTraded::NQ | FutureTraded::ES | FutureTraded::MNQ | FutureTraded::MES => 0.25,
+        FutureTraded::YM | FutureTraded::MYM => 1.00,
+        FutureTraded::CL | FutureTraded::MCL => 0.01,
+        FutureTraded::GC | FutureTraded::MGC => 0.10,
+        FutureTraded::SI | FutureTraded::SIL => 0.005,
     }
 }
 
 fn get_future_from_symbol(symbol: &str) -> Option<FutureTraded> {
-    if symbol.starts_with("NQ") {
+    // Micro roots first, SIL would otherwise read as SI
+    if symbol.starts_with("MNQ") {
+        Some(FutureTraded::MNQ)
+    } else if symbol.starts_with("MES") {
+        Some(FutureTraded::MES)
+    } else if symbol.starts_with("MYM") {
+        Some(FutureTraded::MYM)
+    } else if symbol.starts_with("MCL") {
+        Some(FutureTraded::MCL)
+    } else if symbol.starts_with("MGC") {
+        Some(FutureTraded::MGC)
+    } else if symbol.starts_with("SIL") {
+        Some(FutureTraded::SIL)
+    } else if symbol.starts_with("NQ") {
         Some(FutureTraded::NQ)
     } else if symbol.starts_with("ES") {
         Some(FutureTraded::ES)
@@ -875,6 +4561,239 @@ fn get_future_from_symbol(symbol: &str) -> Option<FutureTraded> {
 }
 
 // Helper function to check if a limit order should be filled based on current candle
+// Signed value of the main position, in delta dollars for options with a solved
+// implied volatility
+fn primary_notional(
+    position: &Position,
+    held_option: Option<&HeldOption>,
+    mark: f64,
+    multiplier: f64,
+    rate: f64,
+    ts: u64,
+) -> f64 {
+    let quantity = match position {
+        Position::Long { size, .. } => *size,
+        Position::Short { size, .. } => -*size,
+        Position::Neutral => return 0.0,
+    };
+    match held_option.and_then(|held| held.greeks_point(ts, quantity, rate).map(|p| (held, p))) {
+        Some((held, point)) => point.greeks.delta * held.spot,
+        None => quantity * mark * multiplier,
+    }
+}
+
+// Prices of the open position's bracket legs, checked from the first event after entry
+#[derive(Debug, Clone, Copy)]
+struct BracketLevels {
+    stop: Option<f64>,
+    target: Option<f64>,
+    armed_after: u64,
+}
+
+impl BracketLevels {
+    // Legs whose indicator lacks history at the entry are left off
+    fn new(
+        bracket: &Bracket,
+        long: bool,
+        price: f64,
+        instrument: Option<u32>,
+        bars: &BarHistory,
+        ts: u64,
+    ) -> Self {
+        let side = if long { 1.0 } else { -1.0 };
+        let resolve = |distance: Option<BracketDistance>| {
+            distance.and_then(|d| d.resolve(price, instrument, bars))
+        };
+        Self {
+            stop: resolve(bracket.stop).map(|d| price - side * d),
+            target: resolve(bracket.target).map(|d| price + side * d),
+            armed_after: ts,
+        }
+    }
+
+    fn risk(&self, price: f64) -> Option<f64> {
+        self.stop.map(|stop| (price - stop).abs())
+    }
+
+    // The leg the event reached and its price, the stop when it reached both
+    fn hit(&self, long: bool, event: &MarketEvent) -> Option<(ExitReason, f64)> {
+        if event.timestamp() <= self.armed_after {
+            return None;
+        }
+        let (high, low) = (event.high(), event.low());
+        let stop = self
+            .stop
+            .filter(|&stop| if long { low <= stop } else { high >= stop });
+        let target = self
+            .target
+            .filter(|&target| if long { high >= target } else { low <= target });
+        stop.map(|stop| (ExitReason::Stop, stop))
+            .or(target.map(|target| (ExitReason::Target, target)))
+    }
+}
+
+// Whether `order` trades against the open main position: a sell of a long, a buy of a short
+fn reduces_position(order: &Order, position: &Position) -> bool {
+    match position {
+        Position::Long { .. } => matches!(
+            order.order_type,
+            OrderType::MarketSell | OrderType::LimitSell
+        ),
+        Position::Short { .. } => {
+            matches!(order.order_type, OrderType::MarketBuy | OrderType::LimitBuy)
+        }
+        Position::Neutral => false,
+    }
+}
+
+// The main position and open hedge legs as ledger positions, the main one at `mark`
+fn ledger_positions<'a>(
+    position: &Position,
+    instrument_id: Option<u32>,
+    mark: f64,
+    multiplier: f64,
+    hedges: &'a HedgeBook,
+) -> impl Iterator<Item = LedgerPosition> + 'a {
+    let main = match position {
+        Position::Long { entry, size, .. } => Some((*entry, *size)),
+        Position::Short { entry, size, .. } => Some((*entry, -*size)),
+        Position::Neutral => None,
+    }
+    .map(|(entry, quantity)| LedgerPosition {
+        instrument_id,
+        quantity,
+        entry,
+        mark,
+        multiplier,
+    });
+    main.into_iter()
+        .chain(hedges.legs().map(|leg| LedgerPosition {
+            instrument_id: Some(leg.instrument_id),
+            quantity: leg.quantity,
+            entry: leg.entry,
+            mark: leg.mark,
+            multiplier: leg.multiplier,
+        }))
+}
+
+// Main position and hedge legs open
+fn open_positions(position: &Position, hedges: &HedgeBook) -> usize {
+    usize::from(*position != Position::Neutral) + hedges.open_legs()
+}
+
+// Unrealized P&L of the main position at `mark`, before exit costs
+fn open_pnl(position: &Position, mark: f64, multiplier: f64) -> f64 {
+    match position {
+        Position::Long { entry, size, .. } => (mark - entry) * size * multiplier,
+        Position::Short { entry, size, .. } => (entry - mark) * size * multiplier,
+        Position::Neutral => 0.0,
+    }
+}
+
+// Capital the main position ties up at the current marks: the cost of stock and of long
+// options, the margin of short options net of the `underlying_quantity` covering them,
+// and of futures
+#[allow(clippy::too_many_arguments)]
+fn employed_capital(
+    position: &Position,
+    held_option: Option<&HeldOption>,
+    mark: f64,
+    underlying: f64,
+    underlying_quantity: f64,
+    is_options: bool,
+    futures_multiplier: Option<f64>,
+    options: &BacktestOptions,
+) -> f64 {
+    let (entry, size, long) = match position {
+        Position::Long { entry, size, .. } => (*entry, *size, true),
+        Position::Short { entry, size, .. } => (*entry, *size, false),
+        Position::Neutral => return 0.0,
+    };
+    if let Some(multiplier) = futures_multiplier {
+        return options.futures_margin.requirement(size, mark, multiplier);
+    }
+    match held_option {
+        Some(held) if is_options && !long => {
+            let rules = options.short_options.clone().unwrap_or_default();
+            rules.position_margin(
+                held.is_call,
+                held.strike,
+                underlying,
+                mark,
+                size,
+                underlying_quantity,
+            )
+        }
+        _ if is_options => entry * size * 100.0,
+        _ => entry * size,
+    }
+}
+
+// Stress grid of the position at the last event of a day
+fn stress_at_close(
+    config: &StressConfig,
+    position: &Position,
+    held_option: Option<&HeldOption>,
+    last_event: Option<&MarketEvent>,
+    equity: f64,
+    rate: f64,
+    multiplier: f64,
+) -> Option<StressPoint> {
+    let event = last_event?;
+    let quantity = match position {
+        Position::Long { size, .. } => *size,
+        Position::Short { size, .. } => -*size,
+        Position::Neutral => return None,
+    };
+    let ts = event.timestamp();
+    let exposure = match held_option {
+        Some(held) => held.exposure(ts, rate)?,
+        None => Exposure::Underlying {
+            price: event.price(),
+        },
+    };
+
+    let scenarios = stress::stress_test(
+        &StressedPosition {
+            quantity,
+            multiplier,
+            exposure,
+        },
+        config,
+    );
+    Some(StressPoint {
+        ts,
+        date: event.date_string(),
+        equity,
+        worst_pnl: stress::worst_pnl(&scenarios),
+        scenarios,
+    })
+}
+
+// Assignment watch for a short entry filled on an option trade
+fn open_short_option(event: &MarketEvent) -> Option<ShortOption> {
+    let MarketEvent::OptionTrade(m) = event else {
+        return None;
+    };
+    Some(ShortOption {
+        symbol: m.symbol.clone(),
+        strike: m.strike_price,
+        expiration: m.expiration,
+        is_call: m.option_type.starts_with('C'),
+        flagged: false,
+    })
+}
+
+// The traded instrument's own best bid and ask. Option trades only carry the
+// underlying's quote, so their fills fall back to the modeled spread.
+fn own_quote(event: &MarketEvent) -> Option<(f64, f64)> {
+    let MarketEvent::Mbp1(_) = event else {
+        return None;
+    };
+    let (bid, ask) = (event.get("bid_px")?, event.get("ask_px")?);
+    (bid.is_finite() && ask.is_finite() && bid > 0.0 && ask >= bid).then_some((bid, ask))
+}
+
 pub fn should_fill_limit_order(order: &Order, event: &MarketEvent) -> bool {
     let high = event.high();
     let low = event.low();
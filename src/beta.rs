@@ -0,0 +1,114 @@
+use crate::backtester::{BacktestResult, NANOS_PER_DAY};
+use serde::{Deserialize, Serialize};
+
+/// Settings for the rolling correlation and beta of the strategy to its underlying
+#[derive(Debug, Clone)]
+pub struct BetaConfig {
+    /// Days of returns in each rolling estimate
+    pub window: usize,
+}
+
+impl Default for BetaConfig {
+    fn default() -> Self {
+        Self { window: 60 }
+    }
+}
+
+/// Strategy against underlying over the window ending at one day close
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BetaPoint {
+    /// Timestamp of the last event of the day
+    pub ts: u64,
+    pub correlation: f64,
+    /// Strategy return per unit of underlying return
+    pub beta: f64,
+}
+
+/// Marked equity and underlying price at each day close
+#[derive(Debug, Default)]
+pub(crate) struct DailyMarks {
+    // (ts, equity including the open position, underlying)
+    marks: Vec<(u64, f64, f64)>,
+}
+
+impl DailyMarks {
+    pub(crate) fn push(&mut self, ts: u64, equity: f64, underlying: f64) {
+        if !(equity.is_finite() && underlying.is_finite() && underlying > 0.0) {
+            return;
+        }
+        match self.marks.last_mut() {
+            Some(last) if last.0 / NANOS_PER_DAY == ts / NANOS_PER_DAY => {
+                *last = (ts, equity, underlying)
+            }
+            _ => self.marks.push((ts, equity, underlying)),
+        }
+    }
+
+    pub(crate) fn rolling(&self, config: &BetaConfig) -> Vec<BetaPoint> {
+        let returns: Vec<(u64, f64, f64)> = self
+            .marks
+            .windows(2)
+            .filter(|w| w[0].1 != 0.0)
+            .map(|w| (w[1].0, w[1].1 / w[0].1 - 1.0, w[1].2 / w[0].2 - 1.0))
+            .collect();
+        let window = config.window.max(2);
+
+        returns
+            .windows(window)
+            .filter_map(|w| {
+                let (strategy, underlying): (Vec<f64>, Vec<f64>) =
+                    w.iter().map(|r| (r.1, r.2)).unzip();
+                let (correlation, beta) = correlation_beta(&strategy, &underlying)?;
+                Some(BetaPoint {
+                    ts: w[w.len() - 1].0,
+                    correlation,
+                    beta,
+                })
+            })
+            .collect()
+    }
+}
+
+// Correlation and beta of `y` on `x`, None when `x` does not move
+fn correlation_beta(y: &[f64], x: &[f64]) -> Option<(f64, f64)> {
+    let n = x.len() as f64;
+    let (mean_x, mean_y) = (x.iter().sum::<f64>() / n, y.iter().sum::<f64>() / n);
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in x.iter().zip(y) {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x <= 0.0 {
+        return None;
+    }
+    let correlation = if var_y > 0.0 {
+        cov / (var_x * var_y).sqrt()
+    } else {
+        0.0
+    };
+    Some((correlation, cov / var_x))
+}
+
+pub fn display_beta(result: &BacktestResult) {
+    let Some(last) = result.beta.last() else {
+        return;
+    };
+    let n = result.beta.len() as f64;
+    let mean_corr = result.beta.iter().map(|p| p.correlation).sum::<f64>() / n;
+    let mean_beta = result.beta.iter().map(|p| p.beta).sum::<f64>() / n;
+    let max_corr = result
+        .beta
+        .iter()
+        .map(|p| p.correlation)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    println!("\n=== EXPOSURE TO UNDERLYING ===");
+    println!(
+        "Rolling correlation: last {:.2}, mean {:.2}, max {:.2} | Rolling beta: last {:.2}, mean {:.2}",
+        last.correlation, mean_corr, max_corr, last.beta, mean_beta
+    );
+    if mean_corr > 0.7 {
+        println!("WARNING: returns track the underlying closely, the edge may be leveraged long exposure");
+    }
+}
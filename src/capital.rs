@@ -0,0 +1,126 @@
+use crate::backtester::BacktestResult;
+use serde::{Deserialize, Serialize};
+
+/// Initial margin posted per futures contract
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FuturesMargin {
+    /// Fixed dollars per contract, as published by the exchange
+    PerContract(f64),
+    /// Share of the contract's notional at the current mark
+    PctOfNotional(f64),
+}
+
+impl Default for FuturesMargin {
+    fn default() -> Self {
+        FuturesMargin::PctOfNotional(0.10)
+    }
+}
+
+impl FuturesMargin {
+    /// Margin of `contracts` contracts marked at `price`
+    pub fn requirement(&self, contracts: f64, price: f64, multiplier: f64) -> f64 {
+        match self {
+            FuturesMargin::PerContract(margin) => margin * contracts,
+            FuturesMargin::PctOfNotional(pct) => pct * price * contracts * multiplier,
+        }
+    }
+}
+
+/// Capital the main position tied up over the backtest: cash for stock, premium for
+/// long options, margin for short options and futures
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapitalUsage {
+    /// Most capital employed at once
+    pub peak: f64,
+    /// Time-weighted capital employed while a position was open
+    pub avg_in_use: f64,
+    /// Share of the backtest's time with capital employed
+    pub time_in_use_pct: f64,
+    /// Total return over peak capital
+    pub return_on_peak_pct: f64,
+    /// Total return over average capital in use, the return on margin for leveraged
+    /// instruments
+    pub return_on_avg_pct: f64,
+}
+
+/// Integrates employed capital over event time
+#[derive(Debug, Default)]
+pub(crate) struct CapitalTracker {
+    first_ts: Option<u64>,
+    last_ts: u64,
+    current: f64,
+    peak: f64,
+    // Capital times nanoseconds, and nanoseconds, while capital was employed
+    weighted: f64,
+    in_use: f64,
+}
+
+impl CapitalTracker {
+    /// Capital employed from `ts` until the next observation
+    pub(crate) fn observe(&mut self, ts: u64, capital: f64) {
+        let capital = if capital.is_finite() {
+            capital.abs()
+        } else {
+            0.0
+        };
+        if self.first_ts.is_none() {
+            self.first_ts = Some(ts);
+        } else if self.current > 0.0 {
+            let dt = ts.saturating_sub(self.last_ts) as f64;
+            self.weighted += self.current * dt;
+            self.in_use += dt;
+        }
+        self.last_ts = ts;
+        self.current = capital;
+        self.peak = self.peak.max(capital);
+    }
+
+    pub(crate) fn finish(&self, total_return: f64) -> CapitalUsage {
+        let span = self
+            .first_ts
+            .map_or(0.0, |first| self.last_ts.saturating_sub(first) as f64);
+        let avg_in_use = if self.in_use > 0.0 {
+            self.weighted / self.in_use
+        } else {
+            // Positions only ever held for a single event
+            self.peak
+        };
+        let pct_of = |capital: f64| {
+            if capital > 0.0 {
+                total_return / capital * 100.0
+            } else {
+                0.0
+            }
+        };
+        CapitalUsage {
+            peak: self.peak,
+            avg_in_use,
+            time_in_use_pct: if span > 0.0 {
+                self.in_use / span * 100.0
+            } else {
+                0.0
+            },
+            return_on_peak_pct: pct_of(self.peak),
+            return_on_avg_pct: pct_of(avg_in_use),
+        }
+    }
+}
+
+pub fn display_capital_usage(result: &BacktestResult) {
+    let capital = &result.capital;
+    if capital.peak <= 0.0 {
+        return;
+    }
+    println!("\n=== CAPITAL USAGE ===");
+    println!(
+        "Peak capital: ${:.2} ({:.1}% of starting equity), Avg in use: ${:.2}, In use: {:.1}% of the time",
+        capital.peak,
+        capital.peak / result.starting_equity * 100.0,
+        capital.avg_in_use,
+        capital.time_in_use_pct
+    );
+    println!(
+        "Return on peak capital: {:.2}%, Return on avg capital: {:.2}% (on equity: {:.2}%)",
+        capital.return_on_peak_pct, capital.return_on_avg_pct, result.total_return_pct
+    );
+}
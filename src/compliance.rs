@@ -0,0 +1,315 @@
+use crate::backtester::BacktestResult;
+use crate::event::date_string;
+use crate::strategy::{Order, OrderType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+/// An order as the pre-trade checks see it, when it reaches the market and before the
+/// engine acts on it
+pub struct OrderCheck<'a> {
+    pub order: &'a Order,
+    pub ts: u64,
+    /// Instrument the order trades: the hedge leg's, the held instrument for orders on
+    /// an open position, the event's for entries. `None` for data without instrument ids.
+    pub instrument_id: Option<u32>,
+    /// Symbol of that instrument from the point-in-time definitions, the event's own for
+    /// option trades, the backtest's symbol otherwise
+    pub symbol: &'a str,
+    /// Units the order would trade: its quantity, otherwise what sizing gives it
+    pub quantity: f64,
+    /// Units held in the instrument before the order, negative when short
+    pub position: f64,
+    pub equity: f64,
+    /// Orders of the run already resting: pending limit orders and hedge orders
+    pub resting: &'a [Order],
+}
+
+impl OrderCheck<'_> {
+    pub fn is_buy(&self) -> bool {
+        is_buy(self.order.order_type)
+    }
+
+    /// Units held if the order fills in full
+    pub fn resulting_position(&self) -> f64 {
+        if self.is_buy() {
+            self.position + self.quantity
+        } else {
+            self.position - self.quantity
+        }
+    }
+
+    /// Whether the order adds to the absolute position rather than reducing it
+    pub fn increases_exposure(&self) -> bool {
+        self.resulting_position().abs() > self.position.abs()
+    }
+}
+
+pub(crate) fn is_buy(order_type: OrderType) -> bool {
+    matches!(order_type, OrderType::MarketBuy | OrderType::LimitBuy)
+}
+
+/// A compliance rule every order passes before the engine accepts it
+pub trait PreTradeCheck: Send + Sync {
+    /// Name recorded with the check's rejections
+    fn name(&self) -> &str;
+
+    /// `Err` with the reason to refuse the order
+    fn check(&self, order: &OrderCheck) -> Result<(), String>;
+}
+
+/// The checks of a run, applied in order until one refuses. Empty by default.
+#[derive(Clone, Default)]
+pub struct PreTradeChecks {
+    checks: Vec<Arc<dyn PreTradeCheck>>,
+}
+
+impl PreTradeChecks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, check: impl PreTradeCheck + 'static) -> Self {
+        self.checks.push(Arc::new(check));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    // The first refusal of the order, if any
+    pub(crate) fn review(&self, order: &OrderCheck) -> Option<PreTradeRejection> {
+        self.checks.iter().find_map(|check| {
+            check.check(order).err().map(|reason| PreTradeRejection {
+                ts: order.ts,
+                date: date_string(order.ts),
+                order_type: order.order.order_type,
+                price: order.order.price,
+                quantity: order.quantity,
+                instrument: order.instrument_id,
+                symbol: order.symbol.to_string(),
+                check: check.name().to_string(),
+                reason,
+            })
+        })
+    }
+}
+
+impl fmt::Debug for PreTradeChecks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.checks.iter().map(|check| check.name()))
+            .finish()
+    }
+}
+
+/// An order refused by a [`PreTradeCheck`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreTradeRejection {
+    pub ts: u64,
+    pub date: String,
+    pub order_type: OrderType,
+    pub price: f64,
+    pub quantity: f64,
+    pub instrument: Option<u32>,
+    pub symbol: String,
+    pub check: String,
+    pub reason: String,
+}
+
+/// Maximum absolute units held per symbol. Orders that reduce a position always pass.
+#[derive(Debug, Clone, Default)]
+pub struct PositionLimit {
+    pub limits: HashMap<String, f64>,
+    /// Limit of symbols without their own
+    pub default: Option<f64>,
+}
+
+impl PositionLimit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limit(mut self, symbol: &str, units: f64) -> Self {
+        self.limits.insert(symbol.to_string(), units);
+        self
+    }
+
+    pub fn with_default(mut self, units: f64) -> Self {
+        self.default = Some(units);
+        self
+    }
+}
+
+impl PreTradeCheck for PositionLimit {
+    fn name(&self) -> &str {
+        "position_limit"
+    }
+
+    fn check(&self, order: &OrderCheck) -> Result<(), String> {
+        let Some(limit) = self.limits.get(order.symbol).copied().or(self.default) else {
+            return Ok(());
+        };
+        let resulting = order.resulting_position();
+        if order.increases_exposure() && resulting.abs() > limit {
+            return Err(format!(
+                "{} would hold {} units, limit {}",
+                order.symbol, resulting, limit
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Symbols no new exposure may be taken in. Orders that reduce a position pass, so
+/// holdings from before a symbol was restricted can still be closed.
+#[derive(Debug, Clone, Default)]
+pub struct RestrictedList {
+    pub symbols: HashSet<String>,
+}
+
+impl RestrictedList {
+    pub fn new<S: AsRef<str>>(symbols: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            symbols: symbols
+                .into_iter()
+                .map(|s| s.as_ref().to_string())
+                .collect(),
+        }
+    }
+}
+
+impl PreTradeCheck for RestrictedList {
+    fn name(&self) -> &str {
+        "restricted_list"
+    }
+
+    fn check(&self, order: &OrderCheck) -> Result<(), String> {
+        if order.increases_exposure() && self.symbols.contains(order.symbol) {
+            return Err(format!("{} is restricted", order.symbol));
+        }
+        Ok(())
+    }
+}
+
+/// Largest single order, in units and in notional at the order price
+#[derive(Debug, Clone, Default)]
+pub struct MaxOrderSize {
+    pub quantity: Option<f64>,
+    pub notional: Option<f64>,
+}
+
+impl MaxOrderSize {
+    pub fn units(quantity: f64) -> Self {
+        Self {
+            quantity: Some(quantity),
+            notional: None,
+        }
+    }
+
+    pub fn notional(notional: f64) -> Self {
+        Self {
+            quantity: None,
+            notional: Some(notional),
+        }
+    }
+}
+
+impl PreTradeCheck for MaxOrderSize {
+    fn name(&self) -> &str {
+        "max_order_size"
+    }
+
+    fn check(&self, order: &OrderCheck) -> Result<(), String> {
+        if let Some(max) = self.quantity.filter(|&max| order.quantity > max) {
+            return Err(format!("{} units over the {} maximum", order.quantity, max));
+        }
+        let notional = order.quantity * order.order.price;
+        if let Some(max) = self.notional.filter(|&max| notional > max) {
+            return Err(format!(
+                "{:.2} notional over the {:.2} maximum",
+                notional, max
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Refuses orders that would trade against a resting order of the run on the other side
+/// of the same instrument, e.g. a hedge leg's sell limit below a new buy, so the account
+/// never buys from itself
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WashTradeGuard;
+
+// Whether an order on one side at `price` trades against a resting one on the other,
+// market orders crossing anything
+fn crosses(buy: bool, price: Option<f64>, resting: Option<f64>) -> bool {
+    match (price, resting) {
+        (Some(bid), Some(ask)) if buy => bid >= ask,
+        (Some(ask), Some(bid)) => ask <= bid,
+        _ => true,
+    }
+}
+
+fn limit_price(order: &Order) -> Option<f64> {
+    matches!(order.order_type, OrderType::LimitBuy | OrderType::LimitSell).then_some(order.price)
+}
+
+impl PreTradeCheck for WashTradeGuard {
+    fn name(&self) -> &str {
+        "wash_trade"
+    }
+
+    fn check(&self, order: &OrderCheck) -> Result<(), String> {
+        let buy = order.is_buy();
+        let price = limit_price(order.order);
+        let crossed = order.resting.iter().any(|resting| {
+            resting.instrument == order.order.instrument
+                && is_buy(resting.order_type) != buy
+                && limit_price(resting).is_some()
+                && crosses(buy, price, limit_price(resting))
+        });
+        if crossed {
+            return Err(format!("crosses a resting order on {}", order.symbol));
+        }
+        Ok(())
+    }
+}
+
+/// Rejections per check, most first
+pub fn rejections_by_check(result: &BacktestResult) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for rejection in &result.pre_trade_rejections {
+        *counts.entry(rejection.check.as_str()).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(check, n)| (check.to_string(), n))
+        .collect();
+    counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    counts
+}
+
+pub fn display_pre_trade_rejections(result: &BacktestResult) {
+    println!(
+        "\n=== PRE-TRADE REJECTIONS ({}) ===",
+        result.pre_trade_rejections.len()
+    );
+    for (check, n) in rejections_by_check(result) {
+        println!("{:<20} {}", check, n);
+    }
+    if let Some(first) = result.pre_trade_rejections.first() {
+        println!(
+            "First: {} {:?} {} x {:.2} @ {:.4} by {}: {}",
+            first.date,
+            first.order_type,
+            first.symbol,
+            first.quantity,
+            first.price,
+            first.check,
+            first.reason
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use crate::context::EventCtx;
+use crate::event::MarketEvent;
+use crate::requirements::EventRequirements;
+use crate::strategy::{ExitReason, Order, OrderType, Strategy};
+use crate::symbology::SymbologyMap;
+use databento::dbn::InstrumentDefMsg;
+
+/// A strategy inside a [`Composite`], with the view it last signalled
+pub struct Member {
+    pub strategy: Box<dyn Strategy>,
+    pub weight: f64,
+    // +1 long, -1 short, 0 flat, assuming each of its orders filled
+    view: i8,
+}
+
+impl Member {
+    pub fn new(strategy: impl Strategy + 'static) -> Self {
+        Self::boxed(Box::new(strategy))
+    }
+
+    pub fn boxed(strategy: Box<dyn Strategy>) -> Self {
+        Self {
+            strategy,
+            weight: 1.0,
+            view: 0,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// +1 long, -1 short, 0 flat
+    pub fn view(&self) -> i8 {
+        self.view
+    }
+
+    fn observe(&mut self, order: &Order) {
+        // Orders on other instruments are hedges, they leave the view alone
+        if order.instrument.is_some() {
+            return;
+        }
+        let buy = matches!(order.order_type, OrderType::MarketBuy | OrderType::LimitBuy);
+        self.view = match (self.view, buy) {
+            (v, true) if v < 0 => 0,
+            (_, true) => 1,
+            (v, false) if v > 0 => 0,
+            (_, false) => -1,
+        };
+    }
+}
+
+enum Rule {
+    /// Long or short when more than half of the members are
+    Majority,
+    /// Long when the weighted mean view reaches `threshold`, short at `-threshold`
+    Weighted { threshold: f64 },
+    /// The first member's view, on the sides the second member's view allows
+    Filtered,
+    /// The second member's entries in the direction the first member turned to in the
+    /// last `window` nanoseconds
+    Sequenced { window: u64 },
+}
+
+/// Several strategies combined into one, trading a single position.
+///
+/// Every member sees every event and its orders are read as a view, long after a buy
+/// from flat, flat after a sell from long and so on, as if each order had filled. Limit
+/// orders, quantities and hedge orders of members are not passed on. The composite
+/// trades market orders at the event price toward the combined view: an exit when its
+/// direction changes, and the new entry on the following event.
+pub struct Composite {
+    members: Vec<Member>,
+    rule: Rule,
+    // Direction held, assuming the composite's own orders filled
+    held: i8,
+    // Direction and time of the setup member's last turn, for Sequenced
+    armed: Option<(i8, u64)>,
+}
+
+impl Composite {
+    fn new(members: Vec<Member>, rule: Rule) -> Self {
+        Self {
+            members,
+            rule,
+            held: 0,
+            armed: None,
+        }
+    }
+
+    /// Long or short when more than half of the members are, flat otherwise
+    pub fn majority(members: Vec<Member>) -> Self {
+        Self::new(members, Rule::Majority)
+    }
+
+    /// Long when the weight-averaged view of the members reaches `threshold` (in (0, 1]),
+    /// short when it reaches `-threshold`
+    pub fn weighted(members: Vec<Member>, threshold: f64) -> Self {
+        Self::new(members, Rule::Weighted { threshold })
+    }
+
+    /// `signal`'s longs only while `filter` is long, its shorts only while `filter` is
+    /// short. A position is closed when the filter stops allowing it.
+    pub fn filtered(signal: impl Strategy + 'static, filter: impl Strategy + 'static) -> Self {
+        Self::new(
+            vec![Member::new(signal), Member::new(filter)],
+            Rule::Filtered,
+        )
+    }
+
+    /// `trigger`'s entries only within `window` nanoseconds of `setup` turning long or
+    /// short, and in that direction. Exits follow `trigger`.
+    pub fn sequenced(
+        setup: impl Strategy + 'static,
+        trigger: impl Strategy + 'static,
+        window: u64,
+    ) -> Self {
+        Self::new(
+            vec![Member::new(setup), Member::new(trigger)],
+            Rule::Sequenced { window },
+        )
+    }
+
+    pub fn members(&self) -> &[Member] {
+        &self.members
+    }
+
+    fn target(&self, ts: u64) -> i8 {
+        let views = || self.members.iter().map(|m| m.view);
+        match self.rule {
+            Rule::Majority => {
+                let half = self.members.len() / 2;
+                let longs = views().filter(|&v| v > 0).count();
+                let shorts = views().filter(|&v| v < 0).count();
+                if longs > half {
+                    1
+                } else if shorts > half {
+                    -1
+                } else {
+                    0
+                }
+            }
+            Rule::Weighted { threshold } => {
+                let total: f64 = self.members.iter().map(|m| m.weight.abs()).sum();
+                if total == 0.0 {
+                    return 0;
+                }
+                let score = self
+                    .members
+                    .iter()
+                    .map(|m| m.weight * m.view as f64)
+                    .sum::<f64>()
+                    / total;
+                if score >= threshold {
+                    1
+                } else if score <= -threshold {
+                    -1
+                } else {
+                    0
+                }
+            }
+            Rule::Filtered => {
+                let (signal, filter) = (self.members[0].view, self.members[1].view);
+                if signal == filter {
+                    signal
+                } else {
+                    0
+                }
+            }
+            Rule::Sequenced { window } => {
+                let trigger = self.members[1].view;
+                if trigger == 0 {
+                    return 0;
+                }
+                // An open position is kept until the trigger leaves it
+                if trigger == self.held {
+                    return self.held;
+                }
+                match self.armed {
+                    Some((side, at)) if side == trigger && ts.saturating_sub(at) <= window => side,
+                    _ => 0,
+                }
+            }
+        }
+    }
+
+    fn step(
+        &mut self,
+        event: &MarketEvent,
+        mut call: impl FnMut(&mut dyn Strategy) -> Option<Order>,
+    ) -> Option<Order> {
+        let ts = event.timestamp();
+        for (i, member) in self.members.iter_mut().enumerate() {
+            let before = member.view;
+            if let Some(order) = call(member.strategy.as_mut()) {
+                member.observe(&order);
+            }
+            if i == 0 && member.view != before && member.view != 0 {
+                self.armed = Some((member.view, ts));
+            }
+        }
+
+        let target = self.target(ts);
+        if target == self.held {
+            return None;
+        }
+        let price = event.price();
+        if self.held != 0 {
+            let exit = if self.held > 0 {
+                OrderType::MarketSell
+            } else {
+                OrderType::MarketBuy
+            };
+            self.held = 0;
+            return Some(Order::new(exit, price));
+        }
+        self.held = target;
+        let entry = if target > 0 {
+            OrderType::MarketBuy
+        } else {
+            OrderType::MarketSell
+        };
+        Some(Order::new(entry, price))
+    }
+}
+
+impl Strategy for Composite {
+    fn on_event(&mut self, event: &MarketEvent, prev: Option<&MarketEvent>) -> Option<Order> {
+        self.step(event, |strategy| strategy.on_event(event, prev))
+    }
+
+    fn on_event_ctx(
+        &mut self,
+        event: &MarketEvent,
+        prev: Option<&MarketEvent>,
+        ctx: &EventCtx,
+    ) -> Option<Order> {
+        self.step(event, |strategy| strategy.on_event_ctx(event, prev, ctx))
+    }
+
+    fn on_definition(&mut self, def: &InstrumentDefMsg, symbology: &SymbologyMap) {
+        for member in &mut self.members {
+            member.strategy.on_definition(def, symbology);
+        }
+    }
+
+    /// The engine closed the position, so every member's view is reset with it
+    fn on_position_closed(&mut self, reason: ExitReason) {
+        self.held = 0;
+        for member in &mut self.members {
+            member.view = 0;
+            member.strategy.on_position_closed(reason);
+        }
+    }
+
+    /// Fields any member needs, and the variants every member that names some accepts
+    fn requirements(&self) -> EventRequirements {
+        let all: Vec<EventRequirements> = self
+            .members
+            .iter()
+            .map(|m| m.strategy.requirements())
+            .collect();
+        let mut merged = EventRequirements::default();
+        let mut restricting = all.iter().filter(|r| !r.variants.is_empty());
+        if let Some(first) = restricting.next() {
+            let rest: Vec<&EventRequirements> = restricting.collect();
+            merged.variants = first
+                .variants
+                .iter()
+                .copied()
+                .filter(|kind| rest.iter().all(|r| r.variants.contains(kind)))
+                .collect();
+        }
+        for field in all.iter().flat_map(|r| r.fields.iter()) {
+            if !merged.fields.contains(field) {
+                merged.fields.push(field);
+            }
+        }
+        merged
+    }
+}
@@ -0,0 +1,210 @@
+use crate::event::MarketEvent;
+use crate::indicators::{OptionLiquidity, RollingVolatility, VwapService};
+use crate::microstructure::BookFeatures;
+use crate::orderflow::{FootprintFeatures, FootprintService};
+use crate::signals::SignalValues;
+use serde::{Deserialize, Serialize};
+use std::cell::{OnceCell, RefCell};
+
+/// Engine-maintained state handed to [`crate::strategy::Strategy::on_event_ctx`] with
+/// every event. Derived values are computed on first access and cached for the rest of
+/// the event, so a strategy, its signals and the feature export read them once. The cache
+/// belongs to one run: the workers of a sweep each build their own contexts and compute
+/// the values again.
+pub struct EventCtx<'a> {
+    event: &'a MarketEvent,
+    prev: Option<&'a MarketEvent>,
+    vwap: &'a VwapService,
+    volatility: &'a RollingVolatility,
+    liquidity: &'a OptionLiquidity,
+    signals: Option<&'a SignalValues>,
+    footprint: Option<&'a FootprintService>,
+    tick_size: Option<f64>,
+    quote: OnceCell<Option<(f64, f64)>>,
+    pct_change: OnceCell<Option<f64>>,
+    book: OnceCell<Option<BookFeatures>>,
+    records: RefCell<Vec<(String, f64)>>,
+}
+
+impl<'a> EventCtx<'a> {
+    pub fn new(
+        event: &'a MarketEvent,
+        prev: Option<&'a MarketEvent>,
+        vwap: &'a VwapService,
+        volatility: &'a RollingVolatility,
+        liquidity: &'a OptionLiquidity,
+    ) -> Self {
+        Self {
+            event,
+            prev,
+            vwap,
+            volatility,
+            liquidity,
+            signals: None,
+            footprint: None,
+            tick_size: None,
+            quote: OnceCell::new(),
+            book: OnceCell::new(),
+            pct_change: OnceCell::new(),
+            records: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Expose the engine's current external signal values through [`EventCtx::signal`]
+    pub fn with_signals(mut self, signals: &'a SignalValues) -> Self {
+        self.signals = Some(signals);
+        self
+    }
+
+    /// Expose the engine's footprint analytics through [`EventCtx::footprint`]
+    pub fn with_footprint(mut self, footprint: &'a FootprintService) -> Self {
+        self.footprint = Some(footprint);
+        self
+    }
+
+    /// Tick size of the event's instrument, behind [`BookFeatures::spread_ticks`]
+    pub fn with_tick_size(mut self, tick_size: Option<f64>) -> Self {
+        self.tick_size = tick_size;
+        self
+    }
+
+    pub fn vwap(&self) -> &VwapService {
+        self.vwap
+    }
+
+    /// Per-contract option volume and open interest
+    pub fn liquidity(&self) -> &OptionLiquidity {
+        self.liquidity
+    }
+
+    // Best bid and ask: the book for MBP-1, the underlying quote for option trades
+    fn quote(&self) -> Option<(f64, f64)> {
+        *self.quote.get_or_init(|| {
+            let (bid, ask) = match self.event {
+                MarketEvent::Mbp1(_) => (self.event.get("bid_px")?, self.event.get("ask_px")?),
+                MarketEvent::OptionTrade(m) if !m.underlying_stale => {
+                    (m.underlying_bid, m.underlying_ask)
+                }
+                _ => return None,
+            };
+            (bid.is_finite() && ask.is_finite() && bid > 0.0 && ask >= bid).then_some((bid, ask))
+        })
+    }
+
+    /// Mid of the best bid and ask, None for events without a quote
+    pub fn mid(&self) -> Option<f64> {
+        self.quote().map(|(bid, ask)| (bid + ask) / 2.0)
+    }
+
+    /// Best ask minus best bid, None for events without a quote
+    pub fn spread(&self) -> Option<f64> {
+        self.quote().map(|(bid, ask)| ask - bid)
+    }
+
+    /// Imbalance, microprice, spread in ticks and depth-weighted mid of an MBP-1 event's
+    /// book, None for other events and books missing a side. The tick size comes from
+    /// the instrument's definition, else [`PriceMode::Ticks`].
+    ///
+    /// [`PriceMode::Ticks`]: crate::backtester::PriceMode::Ticks
+    pub fn book(&self) -> Option<BookFeatures> {
+        *self.book.get_or_init(|| match self.event {
+            MarketEvent::Mbp1(m) => BookFeatures::from_levels(&m.levels, self.tick_size),
+            _ => None,
+        })
+    }
+
+    /// Delta, POC migration, divergence and unfinished auctions of a footprint bar, None
+    /// for other events and bars without levels
+    pub fn footprint(&self) -> Option<&FootprintFeatures> {
+        let MarketEvent::Footprint(bar) = self.event else {
+            return None;
+        };
+        self.footprint?
+            .current()
+            .filter(|f| f.ts_event == bar.ts_event)
+    }
+
+    /// Price times volume of the event
+    pub fn dollar_volume(&self) -> f64 {
+        self.event.price() * self.event.volume() as f64
+    }
+
+    /// Simple return of this event's price against the previous event's
+    pub fn pct_change(&self) -> Option<f64> {
+        *self.pct_change.get_or_init(|| {
+            let prev = self.prev?.price();
+            (prev.is_finite() && prev != 0.0).then(|| self.event.price() / prev - 1.0)
+        })
+    }
+
+    /// Standard deviation of returns over `BacktestOptions::vol_window` events
+    pub fn rolling_vol(&self) -> Option<f64> {
+        self.volatility.value()
+    }
+
+    /// Current value of the external signal `name` from [`BacktestOptions::signals`],
+    /// None before its first value, once it is stale, or without such a signal
+    ///
+    /// [`BacktestOptions::signals`]: crate::backtester::BacktestOptions::signals
+    pub fn signal(&self, name: &str) -> Option<f64> {
+        self.signals?.get(name)
+    }
+
+    /// Publish `value` of the series `name` at this event, e.g. a moving average behind
+    /// a signal, into [`BacktestResult::indicators`]. A later value for the same name in
+    /// the same event replaces the earlier one.
+    ///
+    /// [`BacktestResult::indicators`]: crate::backtester::BacktestResult::indicators
+    pub fn record(&self, name: &str, value: f64) {
+        self.records.borrow_mut().push((name.to_string(), value));
+    }
+
+    pub(crate) fn take_records(&self) -> Vec<(String, f64)> {
+        self.records.take()
+    }
+}
+
+/// Values a strategy published through [`EventCtx::record`] under one name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndicatorSeries {
+    pub name: String,
+    pub points: Vec<IndicatorPoint>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IndicatorPoint {
+    pub ts: u64,
+    /// Index of the equity curve point the event landed in. Several events share a point
+    /// of a sampled or decimated curve, which keeps the last value recorded in it.
+    pub index: usize,
+    pub value: f64,
+}
+
+/// Collects recorded values into series, in the order names first appear
+#[derive(Debug, Default)]
+pub(crate) struct IndicatorRecorder {
+    series: Vec<IndicatorSeries>,
+}
+
+impl IndicatorRecorder {
+    /// Store the values recorded at an event landing in equity curve point `index`
+    pub(crate) fn push(&mut self, ts: u64, index: usize, records: Vec<(String, f64)>) {
+        for (name, value) in records {
+            let point = IndicatorPoint { ts, index, value };
+            match self.series.iter_mut().find(|s| s.name == name) {
+                Some(series) => match series.points.last_mut() {
+                    Some(last) if last.index == point.index => *last = point,
+                    _ => series.points.push(point),
+                },
+                None => self.series.push(IndicatorSeries {
+                    name,
+                    points: vec![point],
+                }),
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<IndicatorSeries> {
+        self.series
+    }
+}
@@ -0,0 +1,260 @@
+use crate::backtester::{BacktestResult, Trade};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Most divergence points kept per diff
+const MAX_DIVERGENCES: usize = 20;
+
+/// One headline metric of two runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDelta {
+    pub name: &'static str,
+    pub a: f64,
+    pub b: f64,
+}
+
+impl MetricDelta {
+    pub fn delta(&self) -> f64 {
+        self.b - self.a
+    }
+}
+
+/// A trade of both runs, keyed the same, that closed differently
+#[derive(Debug, Clone)]
+pub struct ChangedTrade {
+    pub a: Trade,
+    pub b: Trade,
+}
+
+/// Where two equity curves stop agreeing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Index into both equity curves
+    pub index: usize,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// What changed from run `a` to run `b`
+#[derive(Debug, Clone)]
+pub struct ResultDiff {
+    /// Relative tolerance the comparison was made at
+    pub tolerance: f64,
+    pub metrics: Vec<MetricDelta>,
+    /// Trades of one run without a trade of the other at the same entry time,
+    /// instrument, direction and lot
+    pub only_in_a: Vec<Trade>,
+    pub only_in_b: Vec<Trade>,
+    pub changed: Vec<ChangedTrade>,
+    /// Points where the equity curves move apart after agreeing, the first 20 of them
+    pub divergences: Vec<Divergence>,
+    /// Lengths of the two equity curves
+    pub curve_lengths: (usize, usize),
+}
+
+impl ResultDiff {
+    /// No metric, trade or equity point differs beyond the tolerance
+    pub fn is_identical(&self) -> bool {
+        self.metrics.iter().all(|m| same(m.a, m.b, self.tolerance))
+            && self.only_in_a.is_empty()
+            && self.only_in_b.is_empty()
+            && self.changed.is_empty()
+            && self.divergences.is_empty()
+            && self.curve_lengths.0 == self.curve_lengths.1
+    }
+}
+
+fn same(a: f64, b: f64, tolerance: f64) -> bool {
+    a == b || (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.0)
+}
+
+type TradeKey = (u64, Option<u32>, String, usize);
+
+fn key(trade: &Trade) -> TradeKey {
+    (
+        trade.entry_ts,
+        trade.instrument_id,
+        trade.trade_type.clone(),
+        trade.lot,
+    )
+}
+
+/// Compare run `b` against run `a`. Values within `tolerance` of each other, relative to
+/// the larger of the two and at least one unit, count as equal.
+pub fn diff_results(a: &BacktestResult, b: &BacktestResult, tolerance: f64) -> ResultDiff {
+    let metrics = [
+        ("Ending equity", a.ending_equity, b.ending_equity),
+        ("Return %", a.total_return_pct, b.total_return_pct),
+        ("Max DD %", a.max_drawdown_pct, b.max_drawdown_pct),
+        ("Sharpe", a.sharpe_ratio, b.sharpe_ratio),
+        ("Sortino", a.sortino_ratio, b.sortino_ratio),
+        ("Win rate %", a.win_rate, b.win_rate),
+        ("Profit factor", a.profit_factor, b.profit_factor),
+        ("Trades", a.total_trades as f64, b.total_trades as f64),
+        (
+            "Costs",
+            a.total_transaction_costs,
+            b.total_transaction_costs,
+        ),
+    ]
+    .into_iter()
+    .map(|(name, a, b)| MetricDelta { name, a, b })
+    .collect();
+
+    // Trades sharing a key are matched in exit order
+    let mut unmatched: BTreeMap<TradeKey, Vec<&Trade>> = BTreeMap::new();
+    for trade in &b.trades {
+        unmatched.entry(key(trade)).or_default().push(trade);
+    }
+    let mut only_in_a = Vec::new();
+    let mut changed = Vec::new();
+    for trade in &a.trades {
+        let matched = unmatched
+            .get_mut(&key(trade))
+            .and_then(|ts| (!ts.is_empty()).then(|| ts.remove(0)));
+        match matched {
+            Some(other) => {
+                let differs = other.exit_ts != trade.exit_ts
+                    || !same(other.size, trade.size, tolerance)
+                    || !same(other.exit_price, trade.exit_price, tolerance)
+                    || !same(other.pnl, trade.pnl, tolerance)
+                    || other.exit_reason != trade.exit_reason;
+                if differs {
+                    changed.push(ChangedTrade {
+                        a: trade.clone(),
+                        b: other.clone(),
+                    });
+                }
+            }
+            None => only_in_a.push(trade.clone()),
+        }
+    }
+    let only_in_b = unmatched.into_values().flatten().cloned().collect();
+
+    let mut divergences = Vec::new();
+    let mut apart = false;
+    for (index, (&ea, &eb)) in a.equity_curve.iter().zip(&b.equity_curve).enumerate() {
+        let differs = !same(ea, eb, tolerance);
+        if differs && !apart && divergences.len() < MAX_DIVERGENCES {
+            divergences.push(Divergence {
+                index,
+                a: ea,
+                b: eb,
+            });
+        }
+        apart = differs;
+    }
+
+    ResultDiff {
+        tolerance,
+        metrics,
+        only_in_a,
+        only_in_b,
+        changed,
+        divergences,
+        curve_lengths: (a.equity_curve.len(), b.equity_curve.len()),
+    }
+}
+
+/// Save a result as JSON, for diffing against later runs
+pub fn write_result_json(result: &BacktestResult, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    serde_json::to_writer(std::io::BufWriter::new(file), result)?;
+    Ok(())
+}
+
+pub fn read_result_json(path: impl AsRef<Path>) -> Result<BacktestResult> {
+    let path = path.as_ref();
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to read a result from {}", path.display()))
+}
+
+fn display_trade(prefix: &str, t: &Trade) {
+    println!(
+        "  {} {} {:.2} @ {:.4} ({}) -> {:.4} ({}) {:?} | PnL: ${:.2}",
+        prefix,
+        t.trade_type,
+        t.size,
+        t.entry_price,
+        t.entry_date,
+        t.exit_price,
+        t.exit_date,
+        t.exit_reason,
+        t.pnl
+    );
+}
+
+/// Print a diff, listing at most `max_trades` trades per section
+pub fn display_diff(label_a: &str, label_b: &str, diff: &ResultDiff, max_trades: usize) {
+    println!("\n=== RESULTS DIFF: {} -> {} ===", label_a, label_b);
+    println!(
+        "{:<16} {:>16} {:>16} {:>14}",
+        "Metric", label_a, label_b, "Delta"
+    );
+    println!("{}", "-".repeat(66));
+    for m in &diff.metrics {
+        let marker = if same(m.a, m.b, diff.tolerance) {
+            ""
+        } else {
+            " *"
+        };
+        println!(
+            "{:<16} {:>16.4} {:>16.4} {:>14.4}{}",
+            m.name,
+            m.a,
+            m.b,
+            m.delta(),
+            marker
+        );
+    }
+
+    for (title, trades, prefix) in [
+        (format!("Only in {}", label_a), &diff.only_in_a, "-"),
+        (format!("Only in {}", label_b), &diff.only_in_b, "+"),
+    ] {
+        if trades.is_empty() {
+            continue;
+        }
+        println!("\n{} ({} trades)", title, trades.len());
+        for t in trades.iter().take(max_trades) {
+            display_trade(prefix, t);
+        }
+    }
+    if !diff.changed.is_empty() {
+        println!("\nChanged ({} trades)", diff.changed.len());
+        for c in diff.changed.iter().take(max_trades) {
+            display_trade("-", &c.a);
+            display_trade("+", &c.b);
+        }
+    }
+
+    if diff.curve_lengths.0 != diff.curve_lengths.1 {
+        println!(
+            "\nEquity curves have {} and {} points",
+            diff.curve_lengths.0, diff.curve_lengths.1
+        );
+    }
+    if !diff.divergences.is_empty() {
+        println!("\nEquity divergence points");
+        for d in &diff.divergences {
+            println!(
+                "  point {}: {:.2} vs {:.2} ({:+.2})",
+                d.index,
+                d.a,
+                d.b,
+                d.b - d.a
+            );
+        }
+    }
+    if diff.is_identical() {
+        println!(
+            "\nThe runs are identical within a tolerance of {}",
+            diff.tolerance
+        );
+    }
+}
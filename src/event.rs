@@ -10,6 +10,58 @@ pub struct FootprintMsg {
     pub data: String, // The JSON string
 }
 
+/// Buy and sell volume traded at a single price level of a footprint bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FootprintLevel {
+    pub price: f64,
+    pub buy_volume: u64,
+    pub sell_volume: u64,
+    /// Volume traded at or through the prevailing bid, for bars built with quotes
+    pub at_bid: Option<u64>,
+    /// Volume traded at or through the prevailing ask, for bars built with quotes
+    pub at_ask: Option<u64>,
+}
+
+impl FootprintLevel {
+    pub fn delta(&self) -> i64 {
+        self.buy_volume as i64 - self.sell_volume as i64
+    }
+
+    pub fn total(&self) -> u64 {
+        self.buy_volume + self.sell_volume
+    }
+}
+
+impl FootprintMsg {
+    /// Parse the JSON footprint into price levels, sorted by ascending price.
+    /// Levels are `[buy, sell]`, or `[buy, sell, at_bid, at_ask]` for bars built with
+    /// quotes. Malformed entries are skipped.
+    pub fn levels(&self) -> Vec<FootprintLevel> {
+        let parsed: std::collections::HashMap<String, Vec<u64>> =
+            serde_json::from_str(&self.data).unwrap_or_default();
+
+        let mut levels: Vec<FootprintLevel> = parsed
+            .into_iter()
+            .filter_map(|(price, volumes)| {
+                let (buy, sell, quoted) = match *volumes.as_slice() {
+                    [buy, sell] => (buy, sell, None),
+                    [buy, sell, at_bid, at_ask] => (buy, sell, Some((at_bid, at_ask))),
+                    _ => return None,
+                };
+                price.parse::<f64>().ok().map(|p| FootprintLevel {
+                    price: p,
+                    buy_volume: buy,
+                    sell_volume: sell,
+                    at_bid: quoted.map(|q| q.0),
+                    at_ask: quoted.map(|q| q.1),
+                })
+            })
+            .collect();
+        levels.sort_by(|a, b| a.price.total_cmp(&b.price));
+        levels
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionTradeMsg {
     pub ts_event: u64,
@@ -25,6 +77,252 @@ pub struct OptionTradeMsg {
     pub underlying_price: f64,
     pub underlying_bid_sz: u32,
     pub underlying_ask_sz: u32,
+    /// Open interest in force at the trade, when statistics were merged into the stream
+    pub open_interest: Option<u64>,
+    /// Timestamp of the underlying record the trade was enriched with, None for merges
+    /// made before it was recorded
+    pub underlying_ts: Option<u64>,
+    /// Set by the engine when the underlying record is older than
+    /// `BacktestOptions::max_underlying_age`, the underlying quote getters then return None
+    pub underlying_stale: bool,
+}
+
+impl OptionTradeMsg {
+    /// Mid of the underlying quote, the underlying price when the quote is one-sided.
+    /// Unlike [`MarketEvent::underlying_mid`] this ignores `underlying_stale`.
+    pub fn underlying_mid(&self) -> f64 {
+        if self.underlying_bid > 0.0 && self.underlying_ask > 0.0 {
+            (self.underlying_bid + self.underlying_ask) / 2.0
+        } else {
+            self.underlying_price
+        }
+    }
+}
+
+/// A single named field of a market event
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    F64(f64),
+    U64(u64),
+    Char(char),
+    Str(String),
+}
+
+impl FieldValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            FieldValue::F64(v) => Some(*v),
+            FieldValue::U64(v) => Some(*v as f64),
+            FieldValue::Str(s) => s.parse().ok(),
+            FieldValue::Char(_) => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            FieldValue::U64(v) => Some(*v),
+            FieldValue::F64(v) if *v >= 0.0 && v.fract() == 0.0 => Some(*v as u64),
+            FieldValue::Str(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::F64(v) => write!(f, "{}", v),
+            FieldValue::U64(v) => write!(f, "{}", v),
+            FieldValue::Char(c) => write!(f, "{}", c),
+            FieldValue::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Fixed-point scale of DBN prices: one unit is 1e-9
+pub const PRICE_SCALE: i64 = 1_000_000_000;
+
+/// Parse a decimal string straight into a fixed-point price without going through f64,
+/// so CSV round trips are exact. Digits beyond the ninth decimal are truncated.
+pub fn parse_fixed_price(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+    if !int_part.bytes().all(|b| b.is_ascii_digit())
+        || !frac_part.bytes().all(|b| b.is_ascii_digit())
+    {
+        // Exponents and the like fall back to float parsing
+        return s.parse::<f64>().ok().map(to_fixed_price);
+    }
+
+    let int_value: i64 = if int_part.is_empty() {
+        0
+    } else {
+        int_part.parse().ok()?
+    };
+    let mut frac_value: i64 = 0;
+    for (i, b) in frac_part.bytes().take(9).enumerate() {
+        frac_value += (b - b'0') as i64 * 10i64.pow(8 - i as u32);
+    }
+
+    let value = int_value
+        .checked_mul(PRICE_SCALE)?
+        .checked_add(frac_value)?;
+    Some(if negative { -value } else { value })
+}
+
+/// Round an f64 price to the nearest fixed-point unit
+pub fn to_fixed_price(price: f64) -> i64 {
+    (price * PRICE_SCALE as f64).round() as i64
+}
+
+/// Format a fixed-point price as an exact decimal string
+pub fn format_fixed_price(price: i64) -> String {
+    let sign = if price < 0 { "-" } else { "" };
+    let abs = price.unsigned_abs();
+    let int_part = abs / PRICE_SCALE as u64;
+    let frac_part = abs % PRICE_SCALE as u64;
+    if frac_part == 0 {
+        return format!("{}{}", sign, int_part);
+    }
+    let frac = format!("{:09}", frac_part);
+    format!("{}{}.{}", sign, int_part, frac.trim_end_matches('0'))
+}
+
+const TRADE_FIELDS: &[&str] = &[
+    "ts_event",
+    "ts_recv",
+    "instrument_id",
+    "publisher_id",
+    "price",
+    "size",
+    "action",
+    "side",
+    "flags",
+    "depth",
+    "sequence",
+];
+const MBP1_FIELDS: &[&str] = &[
+    "ts_event",
+    "ts_recv",
+    "instrument_id",
+    "publisher_id",
+    "price",
+    "size",
+    "action",
+    "side",
+    "flags",
+    "depth",
+    "sequence",
+    "bid_px",
+    "ask_px",
+    "bid_sz",
+    "ask_sz",
+    "bid_ct",
+    "ask_ct",
+];
+const OHLCV_FIELDS: &[&str] = &[
+    "ts_event",
+    "instrument_id",
+    "publisher_id",
+    "open",
+    "high",
+    "low",
+    "close",
+    "volume",
+];
+const MBO_FIELDS: &[&str] = &[
+    "ts_event",
+    "ts_recv",
+    "instrument_id",
+    "publisher_id",
+    "order_id",
+    "price",
+    "size",
+    "action",
+    "side",
+    "flags",
+    "channel_id",
+    "sequence",
+];
+const FOOTPRINT_FIELDS: &[&str] = &["ts_event", "price", "volume", "footprint_data"];
+const OPTION_TRADE_FIELDS: &[&str] = &[
+    "ts_event",
+    "price",
+    "size",
+    "instrument_id",
+    "symbol",
+    "strike_price",
+    "expiration",
+    "option_type",
+    "underlying_bid",
+    "underlying_ask",
+    "underlying_price",
+    "underlying_bid_sz",
+    "underlying_ask_sz",
+    "open_interest",
+    "underlying_ts",
+];
+const DEFINITION_FIELDS: &[&str] = &[
+    "ts_event",
+    "ts_recv",
+    "instrument_id",
+    "publisher_id",
+    "raw_symbol",
+    "instrument_class",
+    "asset",
+    "exchange",
+    "currency",
+    "strike_price",
+    "expiration",
+    "activation",
+    "min_price_increment",
+    "min_price_increment_amount",
+    "unit_of_measure_qty",
+    "contract_multiplier",
+    "underlying_id",
+    "high_limit_price",
+    "low_limit_price",
+];
+
+// Fixed-point price to f64, None for UNDEF_PRICE
+fn px(value: i64) -> Option<FieldValue> {
+    (value != i64::MAX).then_some(FieldValue::F64(value as f64 * 1e-9))
+}
+
+// Nanosecond timestamp, None for UNDEF_TIMESTAMP
+fn ts(value: u64) -> Option<FieldValue> {
+    (value != u64::MAX).then_some(FieldValue::U64(value))
+}
+
+// Underlying fields of an option trade, hidden while its quote is stale
+fn is_underlying_quote(key: &str) -> bool {
+    matches!(
+        key,
+        "underlying_bid"
+            | "underlying_ask"
+            | "underlying_price"
+            | "underlying_bid_sz"
+            | "underlying_ask_sz"
+    )
+}
+
+fn ch(value: std::ffi::c_char) -> Option<FieldValue> {
+    Some(FieldValue::Char(value as u8 as char))
+}
+
+/// UTC date of a nanosecond timestamp, e.g. `2024-01-02`
+pub fn date_string(ts: u64) -> String {
+    match OffsetDateTime::from_unix_timestamp_nanos(ts as i128) {
+        Ok(odt) => odt.date().to_string(),
+        Err(_) => "UNKNOWN".to_string(),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -35,10 +333,181 @@ pub enum MarketEvent {
     Mbo(MboMsg),
     Footprint(FootprintMsg),
     OptionTrade(OptionTradeMsg),
-    Definition(InstrumentDefMsg),
+    Definition(Box<InstrumentDefMsg>),
+}
+
+/// The variant of a [`MarketEvent`], without its payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EventKind {
+    Trade,
+    Mbp1,
+    Ohlcv,
+    Mbo,
+    Footprint,
+    OptionTrade,
+    Definition,
 }
 
 impl MarketEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            MarketEvent::Trade(_) => EventKind::Trade,
+            MarketEvent::Mbp1(_) => EventKind::Mbp1,
+            MarketEvent::Ohlcv(_) => EventKind::Ohlcv,
+            MarketEvent::Mbo(_) => EventKind::Mbo,
+            MarketEvent::Footprint(_) => EventKind::Footprint,
+            MarketEvent::OptionTrade(_) => EventKind::OptionTrade,
+            MarketEvent::Definition(_) => EventKind::Definition,
+        }
+    }
+
+    /// Whether any of the getters returns a value for `key` on this event
+    pub fn has(&self, key: &str) -> bool {
+        self.field(key).is_some()
+            || self.get(key).is_some()
+            || self.get_u64(key).is_some()
+            || self.get_string(key).is_some()
+    }
+
+    /// Names of every field [`MarketEvent::field`] can return for this event, in a stable order
+    pub fn field_names(&self) -> &'static [&'static str] {
+        match self {
+            MarketEvent::Trade(_) => TRADE_FIELDS,
+            MarketEvent::Mbp1(_) => MBP1_FIELDS,
+            MarketEvent::Ohlcv(_) => OHLCV_FIELDS,
+            MarketEvent::Mbo(_) => MBO_FIELDS,
+            MarketEvent::Footprint(_) => FOOTPRINT_FIELDS,
+            MarketEvent::OptionTrade(_) => OPTION_TRADE_FIELDS,
+            MarketEvent::Definition(_) => DEFINITION_FIELDS,
+        }
+    }
+
+    /// Every defined field of this event as `(name, value)` pairs. Undefined values
+    /// (dbn `UNDEF_PRICE` / `UNDEF_TIMESTAMP` sentinels) are left out.
+    pub fn fields(&self) -> Vec<(&'static str, FieldValue)> {
+        self.field_names()
+            .iter()
+            .filter_map(|name| self.field(name).map(|v| (*name, v)))
+            .collect()
+    }
+
+    /// Look up any field by name, with prices scaled from fixed-point
+    pub fn field(&self, key: &str) -> Option<FieldValue> {
+        use FieldValue::{Str, U64};
+        match self {
+            MarketEvent::Trade(m) => match key {
+                "ts_event" => ts(m.hd.ts_event),
+                "ts_recv" => ts(m.ts_recv),
+                "instrument_id" => Some(U64(m.hd.instrument_id as u64)),
+                "publisher_id" => Some(U64(m.hd.publisher_id as u64)),
+                "price" => px(m.price),
+                "size" => Some(U64(m.size as u64)),
+                "action" => ch(m.action),
+                "side" => ch(m.side),
+                "flags" => Some(U64(m.flags.raw() as u64)),
+                "depth" => Some(U64(m.depth as u64)),
+                "sequence" => Some(U64(m.sequence as u64)),
+                _ => None,
+            },
+            MarketEvent::Mbp1(m) => match key {
+                "ts_event" => ts(m.hd.ts_event),
+                "ts_recv" => ts(m.ts_recv),
+                "instrument_id" => Some(U64(m.hd.instrument_id as u64)),
+                "publisher_id" => Some(U64(m.hd.publisher_id as u64)),
+                "price" => px(m.price),
+                "size" => Some(U64(m.size as u64)),
+                "action" => ch(m.action),
+                "side" => ch(m.side),
+                "flags" => Some(U64(m.flags.raw() as u64)),
+                "depth" => Some(U64(m.depth as u64)),
+                "sequence" => Some(U64(m.sequence as u64)),
+                "bid_px" => px(m.levels[0].bid_px),
+                "ask_px" => px(m.levels[0].ask_px),
+                "bid_sz" => Some(U64(m.levels[0].bid_sz as u64)),
+                "ask_sz" => Some(U64(m.levels[0].ask_sz as u64)),
+                "bid_ct" => Some(U64(m.levels[0].bid_ct as u64)),
+                "ask_ct" => Some(U64(m.levels[0].ask_ct as u64)),
+                _ => None,
+            },
+            MarketEvent::Ohlcv(m) => match key {
+                "ts_event" => ts(m.hd.ts_event),
+                "instrument_id" => Some(U64(m.hd.instrument_id as u64)),
+                "publisher_id" => Some(U64(m.hd.publisher_id as u64)),
+                "open" => px(m.open),
+                "high" => px(m.high),
+                "low" => px(m.low),
+                "close" | "price" => px(m.close),
+                "volume" => Some(U64(m.volume)),
+                _ => None,
+            },
+            MarketEvent::Mbo(m) => match key {
+                "ts_event" => ts(m.hd.ts_event),
+                "ts_recv" => ts(m.ts_recv),
+                "instrument_id" => Some(U64(m.hd.instrument_id as u64)),
+                "publisher_id" => Some(U64(m.hd.publisher_id as u64)),
+                "order_id" => Some(U64(m.order_id)),
+                "price" => px(m.price),
+                "size" => Some(U64(m.size as u64)),
+                "action" => ch(m.action),
+                "side" => ch(m.side),
+                "flags" => Some(U64(m.flags.raw() as u64)),
+                "channel_id" => Some(U64(m.channel_id as u64)),
+                "sequence" => Some(U64(m.sequence as u64)),
+                _ => None,
+            },
+            MarketEvent::Footprint(m) => match key {
+                "ts_event" => ts(m.ts_event),
+                "price" | "close" => Some(FieldValue::F64(m.price)),
+                "volume" => Some(U64(m.volume)),
+                "footprint_data" => Some(Str(m.data.clone())),
+                _ => None,
+            },
+            MarketEvent::OptionTrade(m) if m.underlying_stale && is_underlying_quote(key) => None,
+            MarketEvent::OptionTrade(m) => match key {
+                "ts_event" => ts(m.ts_event),
+                "price" => Some(FieldValue::F64(m.price)),
+                "size" => Some(U64(m.size)),
+                "instrument_id" => Some(U64(m.instrument_id as u64)),
+                "symbol" => Some(Str(m.symbol.clone())),
+                "strike_price" => Some(FieldValue::F64(m.strike_price)),
+                "expiration" => ts(m.expiration),
+                "option_type" | "instrument_class" => Some(Str(m.option_type.clone())),
+                "underlying_bid" => Some(FieldValue::F64(m.underlying_bid)),
+                "underlying_ask" => Some(FieldValue::F64(m.underlying_ask)),
+                "underlying_price" => Some(FieldValue::F64(m.underlying_price)),
+                "underlying_bid_sz" => Some(U64(m.underlying_bid_sz as u64)),
+                "underlying_ask_sz" => Some(U64(m.underlying_ask_sz as u64)),
+                "open_interest" => m.open_interest.map(U64),
+                "underlying_ts" => m.underlying_ts.and_then(ts),
+                _ => None,
+            },
+            MarketEvent::Definition(d) => match key {
+                "ts_event" => ts(d.hd.ts_event),
+                "ts_recv" => ts(d.ts_recv),
+                "instrument_id" => Some(U64(d.hd.instrument_id as u64)),
+                "publisher_id" => Some(U64(d.hd.publisher_id as u64)),
+                "raw_symbol" | "symbol" => d.raw_symbol().ok().map(|s| Str(s.to_string())),
+                "instrument_class" | "option_type" => ch(d.instrument_class),
+                "asset" => d.asset().ok().map(|s| Str(s.to_string())),
+                "exchange" => d.exchange().ok().map(|s| Str(s.to_string())),
+                "currency" => d.currency().ok().map(|s| Str(s.to_string())),
+                "strike_price" => px(d.strike_price),
+                "expiration" => ts(d.expiration),
+                "activation" => ts(d.activation),
+                "min_price_increment" | "tick_size" => px(d.min_price_increment),
+                "min_price_increment_amount" => px(d.min_price_increment_amount),
+                "unit_of_measure_qty" => px(d.unit_of_measure_qty),
+                "contract_multiplier" => (d.contract_multiplier > 0
+                    && d.contract_multiplier != i32::MAX)
+                    .then_some(U64(d.contract_multiplier as u64)),
+                "underlying_id" => Some(U64(d.underlying_id as u64)),
+                "high_limit_price" => px(d.high_limit_price),
+                "low_limit_price" => px(d.low_limit_price),
+                _ => None,
+            },
+        }
+    }
+
     pub fn price(&self) -> f64 {
         const SCALE: f64 = 1e-9;
         match self {
@@ -48,7 +517,70 @@ impl MarketEvent {
             MarketEvent::Mbo(m) => m.price as f64 * SCALE,
             MarketEvent::Footprint(m) => m.price,
             MarketEvent::OptionTrade(m) => m.price,
-            MarketEvent::Definition(_) => todo!(),
+            // Reference data, not a print
+            MarketEvent::Definition(_) => 0.0,
+        }
+    }
+
+    /// Price in fixed-point units of [`PRICE_SCALE`], exact for DBN records
+    pub fn price_fixed(&self) -> i64 {
+        match self {
+            MarketEvent::Trade(m) => m.price,
+            MarketEvent::Mbp1(m) => m.price,
+            MarketEvent::Ohlcv(m) => m.close,
+            MarketEvent::Mbo(m) => m.price,
+            MarketEvent::Footprint(m) => to_fixed_price(m.price),
+            MarketEvent::OptionTrade(m) => to_fixed_price(m.price),
+            MarketEvent::Definition(_) => 0,
+        }
+    }
+
+    /// Multiply every price of the event by `factor`, leaving undefined prices, sizes and
+    /// timestamps alone. A footprint's levels are moved to the scaled prices with their
+    /// volumes.
+    pub fn scale_prices(&mut self, factor: f64) {
+        let scale = |value: &mut i64| {
+            if *value != i64::MAX {
+                *value = (*value as f64 * factor).round() as i64;
+            }
+        };
+        match self {
+            MarketEvent::Trade(m) => scale(&mut m.price),
+            MarketEvent::Mbp1(m) => {
+                scale(&mut m.price);
+                scale(&mut m.levels[0].bid_px);
+                scale(&mut m.levels[0].ask_px);
+            }
+            MarketEvent::Ohlcv(m) => {
+                for value in [&mut m.open, &mut m.high, &mut m.low, &mut m.close] {
+                    scale(value);
+                }
+            }
+            MarketEvent::Mbo(m) => scale(&mut m.price),
+            MarketEvent::Footprint(m) => {
+                m.price *= factor;
+                if let Ok(levels) =
+                    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&m.data)
+                {
+                    let scaled: serde_json::Map<String, serde_json::Value> = levels
+                        .into_iter()
+                        .filter_map(|(price, volumes)| {
+                            let price = price.parse::<f64>().ok()? * factor;
+                            Some((price.to_string(), volumes))
+                        })
+                        .collect();
+                    m.data = serde_json::Value::Object(scaled).to_string();
+                }
+            }
+            MarketEvent::OptionTrade(m) => {
+                // Option prices are homogeneous in spot and strike
+                m.price *= factor;
+                m.strike_price *= factor;
+                m.underlying_bid *= factor;
+                m.underlying_ask *= factor;
+                m.underlying_price *= factor;
+            }
+            MarketEvent::Definition(_) => {}
         }
     }
 
@@ -60,11 +592,10 @@ impl MarketEvent {
             MarketEvent::Mbo(m) => m.size as u64,
             MarketEvent::Footprint(m) => m.volume,
             MarketEvent::OptionTrade(m) => m.size,
-            MarketEvent::Definition(_) => todo!(),
+            MarketEvent::Definition(_) => 0,
         }
     }
 
-    #[allow(dead_code)]
     pub fn side(&self) -> Option<char> {
         match self {
             MarketEvent::Trade(m) => Some(m.side as u8 as char),
@@ -74,7 +605,6 @@ impl MarketEvent {
         }
     }
 
-    #[allow(dead_code)]
     pub fn size(&self) -> Option<u32> {
         match self {
             MarketEvent::Trade(m) => Some(m.size),
@@ -113,15 +643,60 @@ impl MarketEvent {
     }
 
     pub fn date_string(&self) -> String {
-        let ts = self.timestamp();
-        match OffsetDateTime::from_unix_timestamp_nanos(ts as i128) {
-            Ok(odt) => odt.date().to_string(),
-            Err(_) => "UNKNOWN".to_string(),
+        date_string(self.timestamp())
+    }
+
+    /// Expiration in nanoseconds of an option trade or definition, None for other events
+    /// and the undefined sentinels (0 in merged rows, UNDEF_TIMESTAMP in definitions)
+    pub fn expiration(&self) -> Option<u64> {
+        let expiration = match self {
+            MarketEvent::OptionTrade(m) => m.expiration,
+            MarketEvent::Definition(d) => d.expiration,
+            _ => return None,
+        };
+        (expiration != 0 && expiration != u64::MAX).then_some(expiration)
+    }
+
+    /// [`MarketEvent::expiration`] as a UTC date and time
+    pub fn expiration_datetime(&self) -> Option<OffsetDateTime> {
+        OffsetDateTime::from_unix_timestamp_nanos(self.expiration()? as i128).ok()
+    }
+
+    /// Fractional days from `now` (nanoseconds) to the expiration, negative once expired
+    pub fn days_to_expiry(&self, now: u64) -> Option<f64> {
+        const NANOS_PER_DAY: f64 = 86_400.0 * 1e9;
+        Some((self.expiration()? as f64 - now as f64) / NANOS_PER_DAY)
+    }
+
+    /// Whether the contract has expired at `now`, false without an expiration
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiration()
+            .is_some_and(|expiration| expiration <= now)
+    }
+
+    /// Nanoseconds between an option trade and the underlying record it was enriched with,
+    /// None for other events and merges that did not record it
+    pub fn underlying_age(&self) -> Option<u64> {
+        match self {
+            MarketEvent::OptionTrade(m) => m.underlying_ts.map(|ts| m.ts_event.saturating_sub(ts)),
+            _ => None,
         }
     }
 
+    /// Mid of the underlying's quote: the book of an MBP-1 event, the enriched quote of an
+    /// option trade. None for other events, stale option trades and quotes without a price.
+    pub fn underlying_mid(&self) -> Option<f64> {
+        let mid = match self {
+            MarketEvent::Mbp1(_) => {
+                (self.get("underlying_bid")? + self.get("underlying_ask")?) / 2.0
+            }
+            MarketEvent::OptionTrade(m) if !m.underlying_stale => m.underlying_mid(),
+            _ => return None,
+        };
+        (mid.is_finite() && mid > 0.0).then_some(mid)
+    }
+
     // Helper to get underlying quotes to MBP1 and OptionTrade
-    #[allow(dead_code)]
     pub fn get(&self, key: &str) -> Option<f64> {
         const SCALE: f64 = 1e-9;
         match self {
@@ -132,6 +707,9 @@ impl MarketEvent {
                 "underlying_price" => Some(msg.price as f64 * SCALE),
                 _ => None,
             },
+            MarketEvent::OptionTrade(msg) if msg.underlying_stale && is_underlying_quote(key) => {
+                return None
+            }
             MarketEvent::OptionTrade(msg) => match key {
                 "strike_price" => Some(msg.strike_price),
                 "underlying_price" => Some(msg.underlying_price),
@@ -142,10 +720,10 @@ impl MarketEvent {
             },
             _ => None,
         }
+        .or_else(|| self.field(key).and_then(|v| v.as_f64()))
     }
 
     /// Generic getter for u64 fields
-    #[allow(dead_code)]
     pub fn get_u64(&self, key: &str) -> Option<u64> {
         match self {
             MarketEvent::Mbp1(msg) => match key {
@@ -153,6 +731,9 @@ impl MarketEvent {
                 "underlying_ask_sz" => Some(msg.levels[0].ask_sz as u64),
                 _ => None,
             },
+            MarketEvent::OptionTrade(msg) if msg.underlying_stale && is_underlying_quote(key) => {
+                return None
+            }
             MarketEvent::OptionTrade(msg) => match key {
                 "expiration" => Some(msg.expiration),
                 "instrument_id" => Some(msg.instrument_id as u64),
@@ -162,6 +743,7 @@ impl MarketEvent {
             },
             _ => None,
         }
+        .or_else(|| self.field(key).and_then(|v| v.as_u64()))
     }
 
     /// Generic getter for string fields
@@ -175,5 +757,10 @@ impl MarketEvent {
             },
             _ => None,
         }
+        .or_else(|| match self.field(key)? {
+            FieldValue::Str(s) => Some(s),
+            FieldValue::Char(c) => Some(c.to_string()),
+            _ => None,
+        })
     }
 }
@@ -0,0 +1,456 @@
+use crate::backtester::BacktestOptions;
+use crate::context::EventCtx;
+use crate::event::MarketEvent;
+use crate::indicators::{OptionLiquidity, RollingVolatility, VwapService};
+use crate::microstructure::book_tick_size;
+use crate::orderflow::FootprintService;
+use crate::signals::{read_signal_csv, SignalSeries, SignalValues, Threshold};
+use crate::strategy::{ExitReason, Order, Strategy};
+use crate::symbology::SymbologyMap;
+use crate::tools::parquet::{is_parquet_path, Column, ColumnType, ParquetTable};
+use crate::utils::fetch::MarketStream;
+use anyhow::{Context, Result};
+use csv::Writer;
+use futures::StreamExt;
+use std::collections::VecDeque;
+use std::io::Write;
+
+type FeatureFn = Box<dyn FnMut(&MarketEvent, &EventCtx) -> Option<f64> + Send>;
+
+/// Named feature functions evaluated on every priced event, in registration order.
+/// A function may keep its own state across events, like an indicator; `None` or a
+/// non-finite value leaves the cell empty.
+#[derive(Default)]
+pub struct FeatureSet {
+    features: Vec<(String, FeatureFn)>,
+}
+
+impl FeatureSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return, rolling volatility, spread, distance to the session VWAP and volume,
+    /// all from the engine services in [`EventCtx`]
+    pub fn standard() -> Self {
+        Self::new()
+            .with("pct_change", |_, ctx| ctx.pct_change())
+            .with("rolling_vol", |_, ctx| ctx.rolling_vol())
+            .with("spread", |_, ctx| ctx.spread())
+            .with("vwap_dist", |event, ctx| {
+                let vwap = ctx.vwap().session()?.vwap;
+                (vwap != 0.0).then(|| event.price() / vwap - 1.0)
+            })
+            .with("volume", |event, _| Some(event.volume() as f64))
+    }
+
+    /// Add the order book features of [`EventCtx::book`]: imbalance, depth imbalance,
+    /// microprice and depth-weighted mid relative to the mid, and the spread in ticks.
+    /// Empty for events without a book.
+    pub fn with_book(self) -> Self {
+        self.with("imbalance", |_, ctx| Some(ctx.book()?.imbalance))
+            .with("depth_imbalance", |_, ctx| {
+                Some(ctx.book()?.depth_imbalance)
+            })
+            .with("microprice_dist", |_, ctx| {
+                let book = ctx.book()?;
+                Some(book.microprice / book.mid() - 1.0)
+            })
+            .with("depth_mid_dist", |_, ctx| {
+                let book = ctx.book()?;
+                Some(book.depth_weighted_mid / book.mid() - 1.0)
+            })
+            .with("spread_ticks", |_, ctx| ctx.book()?.spread_ticks)
+    }
+
+    pub fn with(
+        mut self,
+        name: &str,
+        feature: impl FnMut(&MarketEvent, &EventCtx) -> Option<f64> + Send + 'static,
+    ) -> Self {
+        self.features.push((name.to_string(), Box::new(feature)));
+        self
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.features
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+}
+
+/// How far ahead a forward-return label looks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Horizon {
+    /// The price this many priced events later
+    Events(usize),
+    /// The price of the first event at least this many nanoseconds later
+    Nanos(u64),
+}
+
+impl Horizon {
+    /// Column of the label, e.g. `fwd_10ev` or `fwd_300s`
+    pub fn column(&self) -> String {
+        match *self {
+            Horizon::Events(n) => format!("fwd_{}ev", n),
+            Horizon::Nanos(ns) if ns % 1_000_000_000 == 0 => {
+                format!("fwd_{}s", ns / 1_000_000_000)
+            }
+            Horizon::Nanos(ns) => format!("fwd_{}ns", ns),
+        }
+    }
+}
+
+/// What [`export_features`] wrote
+#[derive(Debug, Clone)]
+pub struct FeatureExport {
+    pub rows: usize,
+    /// Header of the file: `ts_event`, `price`, the features, then one label per horizon
+    pub columns: Vec<String>,
+    /// Rows with a label, per horizon. The last rows of the data have none.
+    pub labelled: Vec<usize>,
+}
+
+struct Row {
+    ts: u64,
+    price: f64,
+    features: Vec<Option<f64>>,
+    labels: Vec<Option<f64>>,
+}
+
+/// Run `stream` through `features` and write the feature matrix, one row per priced
+/// event aligned with its forward returns at every horizon, to a CSV at `path`
+/// (zstd compressed when it ends in `.zst`), or to Parquet when it ends in `.parquet`,
+/// for training a model outside InkBack.
+///
+/// The [`EventCtx`] services use the VWAP, volatility window, liquidity window and
+/// external signals of `options`, so features match what a strategy sees in a backtest with them. Labels are
+/// simple returns from the row's price and never leak into its features.
+pub async fn export_features(
+    mut stream: MarketStream,
+    features: &mut FeatureSet,
+    horizons: &[Horizon],
+    path: &str,
+    options: &BacktestOptions,
+    time_range: Option<(u64, u64)>,
+) -> Result<FeatureExport> {
+    let mut columns = vec!["ts_event".to_string(), "price".to_string()];
+    columns.extend(features.names().iter().map(|name| name.to_string()));
+    columns.extend(horizons.iter().map(Horizon::column));
+
+    let mut rows = FeatureWriter::new(path, &columns, horizons.len())?;
+    let mut vwap = VwapService::new(&options.vwap);
+    let mut footprint = FootprintService::new();
+    let mut volatility = RollingVolatility::new(options.vol_window);
+    let mut liquidity = OptionLiquidity::new(options.liquidity.window);
+    let mut signals = SignalValues::new(&options.signals);
+    let mut symbology = SymbologyMap::new();
+    let tick_fallback = options.price_mode.tick_size();
+    let mut prev_event: Option<MarketEvent> = None;
+    let mut pending: VecDeque<Row> = VecDeque::new();
+    // Index of the first pending row each horizon has not labelled, and of the next row
+    let mut cursors = vec![0usize; horizons.len()];
+    let (mut front, mut next) = (0usize, 0usize);
+
+    while let Some(event) = stream.next().await {
+        let event = event?;
+        if let MarketEvent::Definition(def) = &event {
+            symbology.update(def);
+            continue;
+        }
+        let ts = event.timestamp();
+        if let Some((start_ts, end_ts)) = time_range {
+            if ts < start_ts {
+                continue;
+            }
+            if ts >= end_ts {
+                break;
+            }
+        }
+        let price = event.price();
+        if !price.is_finite() || price <= 0.0 {
+            continue;
+        }
+
+        // This event's price resolves the labels of the earlier rows that reach it
+        for (h, horizon) in horizons.iter().enumerate() {
+            while cursors[h] < next {
+                let row = &mut pending[cursors[h] - front];
+                let reached = match *horizon {
+                    Horizon::Events(n) => cursors[h] + n.max(1) <= next,
+                    Horizon::Nanos(ns) => ts >= row.ts.saturating_add(ns),
+                };
+                if !reached {
+                    break;
+                }
+                row.labels[h] = Some(price / row.price - 1.0);
+                cursors[h] += 1;
+            }
+        }
+        let done = cursors.iter().copied().min().unwrap_or(next);
+        while front < done {
+            rows.write(&pending.pop_front().expect("pending row"))?;
+            front += 1;
+        }
+
+        vwap.update(&event);
+        footprint.update(&event);
+        volatility.update(&event);
+        liquidity.update(&event);
+        signals.update(&event);
+        let values = {
+            let ctx = EventCtx::new(&event, prev_event.as_ref(), &vwap, &volatility, &liquidity)
+                .with_signals(&signals)
+                .with_footprint(&footprint)
+                .with_tick_size(book_tick_size(&event, &symbology, tick_fallback));
+            features
+                .features
+                .iter_mut()
+                .map(|(_, feature)| feature(&event, &ctx).filter(|v| v.is_finite()))
+                .collect()
+        };
+        pending.push_back(Row {
+            ts,
+            price,
+            features: values,
+            labels: vec![None; horizons.len()],
+        });
+        next += 1;
+        prev_event = Some(event);
+    }
+
+    for row in pending {
+        rows.write(&row)?;
+    }
+    let labelled = rows.labelled.clone();
+    let written = rows.finish()?;
+    println!(
+        "Exported {} rows of {} features and {} labels to {}",
+        written,
+        features.len(),
+        horizons.len(),
+        path
+    );
+    Ok(FeatureExport {
+        rows: written,
+        columns,
+        labelled,
+    })
+}
+
+enum Sink {
+    Plain(std::fs::File),
+    Zstd(zstd::Encoder<'static, std::fs::File>),
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Sink::Plain(file) => file.write(buf),
+            Sink::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Sink::Plain(file) => file.flush(),
+            Sink::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// Rows buffered per Parquet row group
+const PARQUET_BATCH: usize = 65_536;
+
+// One output per export, so the size of the CSV writer is not worth boxing
+#[allow(clippy::large_enum_variant)]
+enum Output {
+    Csv {
+        writer: Writer<Sink>,
+        record: Vec<String>,
+    },
+    Parquet {
+        table: ParquetTable,
+        ts: Vec<u64>,
+        // The price, the features, then the labels
+        values: Vec<Vec<Option<f64>>>,
+    },
+}
+
+struct FeatureWriter {
+    output: Output,
+    rows: usize,
+    labelled: Vec<usize>,
+}
+
+impl FeatureWriter {
+    fn new(path: &str, columns: &[String], labels: usize) -> Result<Self> {
+        let output = if is_parquet_path(path) {
+            let types: Vec<(String, ColumnType)> = columns
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let kind = if i == 0 {
+                        ColumnType::UInt64
+                    } else {
+                        ColumnType::Float64
+                    };
+                    (name.clone(), kind)
+                })
+                .collect();
+            Output::Parquet {
+                table: ParquetTable::create(path, &types)?,
+                ts: Vec::new(),
+                values: vec![Vec::new(); columns.len() - 1],
+            }
+        } else {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path))?;
+            let sink = if path.ends_with(".zst") {
+                Sink::Zstd(zstd::Encoder::new(file, 3).context("Failed to create zstd encoder")?)
+            } else {
+                Sink::Plain(file)
+            };
+            let mut writer = Writer::from_writer(sink);
+            writer.write_record(columns)?;
+            Output::Csv {
+                writer,
+                record: Vec::with_capacity(columns.len()),
+            }
+        };
+        Ok(Self {
+            output,
+            rows: 0,
+            labelled: vec![0; labels],
+        })
+    }
+
+    fn write(&mut self, row: &Row) -> Result<()> {
+        for (count, label) in self.labelled.iter_mut().zip(&row.labels) {
+            *count += label.is_some() as usize;
+        }
+        self.rows += 1;
+        match &mut self.output {
+            Output::Csv { writer, record } => {
+                let cell = |v: &Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+                record.clear();
+                record.push(row.ts.to_string());
+                record.push(row.price.to_string());
+                record.extend(row.features.iter().map(cell));
+                record.extend(row.labels.iter().map(cell));
+                writer.write_record(&*record)?;
+            }
+            Output::Parquet { ts, values, .. } => {
+                ts.push(row.ts);
+                let cells = std::iter::once(Some(row.price))
+                    .chain(row.features.iter().copied())
+                    .chain(row.labels.iter().copied());
+                for (column, cell) in values.iter_mut().zip(cells) {
+                    column.push(cell);
+                }
+                if ts.len() >= PARQUET_BATCH {
+                    self.flush_batch()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_batch(&mut self) -> Result<()> {
+        if let Output::Parquet { table, ts, values } = &mut self.output {
+            if ts.is_empty() {
+                return Ok(());
+            }
+            let mut columns = vec![Column::UInt64(std::mem::take(ts))];
+            columns.extend(
+                values
+                    .iter_mut()
+                    .map(|column| Column::Float64(std::mem::take(column))),
+            );
+            table.write(columns)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<usize> {
+        self.flush_batch()?;
+        match self.output {
+            Output::Csv { mut writer, .. } => {
+                writer.flush()?;
+                let sink = writer
+                    .into_inner()
+                    .map_err(|e| anyhow::anyhow!("Failed to flush feature CSV: {}", e))?;
+                if let Sink::Zstd(encoder) = sink {
+                    encoder.finish().context("Failed to finish zstd stream")?;
+                }
+            }
+            Output::Parquet { table, .. } => table.finish()?,
+        }
+        Ok(self.rows)
+    }
+}
+
+/// A model's predictions brought back as a strategy: long while the latest prediction at
+/// or before the event is above `long_above`, short while it is below `short_below`, flat
+/// in between. Changes of side trade a market exit, then the entry on the next event.
+///
+/// For predictions alongside other signals, or read by a custom strategy, pass them in
+/// [`BacktestOptions::signals`] instead.
+pub struct PredictionSignal {
+    predictions: SignalSeries,
+    next: usize,
+    threshold: Threshold,
+}
+
+impl PredictionSignal {
+    pub fn new(predictions: Vec<(u64, f64)>, long_above: f64, short_below: f64) -> Self {
+        Self::from_series(
+            SignalSeries::new("prediction", predictions),
+            long_above,
+            short_below,
+        )
+    }
+
+    pub fn from_series(predictions: SignalSeries, long_above: f64, short_below: f64) -> Self {
+        Self {
+            predictions,
+            next: 0,
+            threshold: Threshold::new(long_above, short_below),
+        }
+    }
+
+    /// Read predictions from a CSV of `ts_event,prediction` with a header, e.g. the
+    /// exported `ts_event` column next to the model's output. The second column is the
+    /// prediction whatever its name, further columns are ignored.
+    pub fn load(path: &str, long_above: f64, short_below: f64) -> Result<Self> {
+        let predictions = read_signal_csv(path)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} has no prediction column", path))?;
+        Ok(Self::from_series(predictions, long_above, short_below))
+    }
+
+    pub fn predictions(&self) -> &[(u64, f64)] {
+        self.predictions.points()
+    }
+}
+
+impl Strategy for PredictionSignal {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let prediction = self.predictions.advance(&mut self.next, event.timestamp());
+        self.threshold.step(prediction, event.price())
+    }
+
+    fn on_position_closed(&mut self, _reason: ExitReason) {
+        self.threshold.reset();
+    }
+}
@@ -0,0 +1,152 @@
+use crate::backtester::NANOS_PER_DAY;
+use serde::{Deserialize, Serialize};
+
+/// Years in a nanosecond span, on a 365 day year
+pub fn years(nanos: u64) -> f64 {
+    nanos as f64 / (365.0 * NANOS_PER_DAY as f64)
+}
+
+/// Black-Scholes sensitivities of one option unit (before the contract multiplier)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Greeks {
+    pub delta: f64,
+    pub gamma: f64,
+    /// Per one vol point (0.01) of implied volatility
+    pub vega: f64,
+    /// Per calendar day
+    pub theta: f64,
+}
+
+impl Greeks {
+    pub fn scaled(self, quantity: f64) -> Self {
+        Self {
+            delta: self.delta * quantity,
+            gamma: self.gamma * quantity,
+            vega: self.vega * quantity,
+            theta: self.theta * quantity,
+        }
+    }
+}
+
+fn norm_pdf(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+// Abramowitz and Stegun 26.2.17, accurate to about 1e-7
+pub(crate) fn norm_cdf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.231_641_9 * x.abs());
+    let poly = t
+        * (0.319_381_530
+            + t * (-0.356_563_782
+                + t * (1.781_477_937 + t * (-1.821_255_978 + t * 1.330_274_429))));
+    let tail = norm_pdf(x) * poly;
+    if x >= 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+fn d1_d2(spot: f64, strike: f64, t: f64, rate: f64, vol: f64) -> (f64, f64) {
+    let sd = vol * t.sqrt();
+    let d1 = ((spot / strike).ln() + (rate + 0.5 * vol * vol) * t) / sd;
+    (d1, d1 - sd)
+}
+
+/// Black-Scholes price of a European option, `t` in years. At or past expiry it is the
+/// intrinsic value.
+pub fn price(is_call: bool, spot: f64, strike: f64, t: f64, rate: f64, vol: f64) -> f64 {
+    let intrinsic = if is_call {
+        (spot - strike).max(0.0)
+    } else {
+        (strike - spot).max(0.0)
+    };
+    if t <= 0.0 || vol <= 0.0 || spot <= 0.0 || strike <= 0.0 {
+        return intrinsic;
+    }
+
+    let (d1, d2) = d1_d2(spot, strike, t, rate, vol);
+    let discount = (-rate * t).exp();
+    if is_call {
+        spot * norm_cdf(d1) - strike * discount * norm_cdf(d2)
+    } else {
+        strike * discount * norm_cdf(-d2) - spot * norm_cdf(-d1)
+    }
+}
+
+/// Volatility at which the Black-Scholes price matches `premium`, by bisection.
+///
+/// None when the premium is outside the no-arbitrage bounds or the option has expired.
+pub fn implied_vol(
+    is_call: bool,
+    premium: f64,
+    spot: f64,
+    strike: f64,
+    t: f64,
+    rate: f64,
+) -> Option<f64> {
+    if !premium.is_finite() || premium <= 0.0 || t <= 0.0 || spot <= 0.0 || strike <= 0.0 {
+        return None;
+    }
+
+    let (mut lo, mut hi) = (1e-4, 5.0);
+    if premium < price(is_call, spot, strike, t, rate, lo)
+        || premium > price(is_call, spot, strike, t, rate, hi)
+    {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (lo + hi);
+        if price(is_call, spot, strike, t, rate, mid) < premium {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+        if hi - lo < 1e-6 {
+            break;
+        }
+    }
+    Some(0.5 * (lo + hi))
+}
+
+/// Black-Scholes Greeks, zero at or past expiry
+pub fn greeks(is_call: bool, spot: f64, strike: f64, t: f64, rate: f64, vol: f64) -> Greeks {
+    if t <= 0.0 || vol <= 0.0 || spot <= 0.0 || strike <= 0.0 {
+        return Greeks::default();
+    }
+
+    let (d1, d2) = d1_d2(spot, strike, t, rate, vol);
+    let discount = (-rate * t).exp();
+    let pdf = norm_pdf(d1);
+    let gamma = pdf / (spot * vol * t.sqrt());
+    let vega = spot * pdf * t.sqrt() / 100.0;
+    let decay = -spot * pdf * vol / (2.0 * t.sqrt());
+
+    let (delta, theta) = if is_call {
+        (
+            norm_cdf(d1),
+            decay - rate * strike * discount * norm_cdf(d2),
+        )
+    } else {
+        (
+            norm_cdf(d1) - 1.0,
+            decay + rate * strike * discount * norm_cdf(-d2),
+        )
+    };
+
+    Greeks {
+        delta,
+        gamma,
+        vega,
+        theta: theta / 365.0,
+    }
+}
+
+/// Net portfolio Greeks at a point in time, contract multiplier and position sign applied
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GreeksPoint {
+    pub ts: u64,
+    pub greeks: Greeks,
+    /// Implied volatility of the held contract, None when flat or not solvable
+    pub implied_vol: Option<f64>,
+}
@@ -0,0 +1,239 @@
+use crate::backtester::Trade;
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::ExitReason;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Instrument id of the leg in the underlying itself, see [`Order::on_underlying`]
+///
+/// [`Order::on_underlying`]: crate::strategy::Order::on_underlying
+pub const UNDERLYING_LEG: u32 = u32::MAX;
+
+/// A position in another instrument held alongside the strategy's main position
+#[derive(Debug, Clone)]
+pub struct HedgeLeg {
+    pub instrument_id: u32,
+    /// Units held, negative when short
+    pub quantity: f64,
+    /// Average entry price of the open quantity
+    pub entry: f64,
+    pub entry_date: String,
+    pub opened_at: u64,
+    /// Last traded price of the instrument
+    pub mark: f64,
+    /// Dollar value of a one point move per unit
+    pub multiplier: f64,
+    pub realized_pnl: f64,
+    pub trades: usize,
+    /// Cost model the leg opened under, resolved for its instrument, which its closes
+    /// are charged by too
+    pub costs: TransactionCosts,
+}
+
+impl HedgeLeg {
+    /// Signed market value of the open quantity
+    pub fn notional(&self) -> f64 {
+        self.quantity * self.mark * self.multiplier
+    }
+}
+
+/// Realized result of one hedge leg over the backtest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegSummary {
+    pub instrument_id: u32,
+    pub realized_pnl: f64,
+    /// Quantity still open at the end, negative when short
+    pub open_quantity: f64,
+    pub trades: usize,
+}
+
+/// Signed exposure of the main position against its hedges at a day close
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HedgedExposure {
+    pub ts: u64,
+    pub date: String,
+    /// Market value of the main position, delta-adjusted for options with known Greeks
+    pub primary: f64,
+    pub hedges: f64,
+    pub net: f64,
+}
+
+/// Hedge legs keyed by instrument id, each netted on its own
+#[derive(Debug, Clone, Default)]
+pub struct HedgeBook {
+    legs: BTreeMap<u32, HedgeLeg>,
+}
+
+impl HedgeBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.legs.is_empty()
+    }
+
+    /// Legs with an open quantity
+    pub fn open_legs(&self) -> usize {
+        self.legs.values().filter(|leg| leg.quantity != 0.0).count()
+    }
+
+    /// Legs with an open quantity
+    pub fn legs(&self) -> impl Iterator<Item = &HedgeLeg> {
+        self.legs.values().filter(|leg| leg.quantity != 0.0)
+    }
+
+    pub fn leg(&self, instrument_id: u32) -> Option<&HedgeLeg> {
+        self.legs.get(&instrument_id)
+    }
+
+    /// Mark the leg of `instrument_id` at `price`
+    pub fn mark(&mut self, instrument_id: u32, price: f64) {
+        if let Some(leg) = self.legs.get_mut(&instrument_id) {
+            if price.is_finite() && price > 0.0 {
+                leg.mark = price;
+            }
+        }
+    }
+
+    /// Trade `quantity` (negative to sell) at `price`. The part that reduces the leg is
+    /// realized and returned as a trade, charged by the costs the leg opened under. The
+    /// rest opens or extends it at a blended entry, a new leg taking `costs`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill(
+        &mut self,
+        instrument_id: u32,
+        quantity: f64,
+        price: f64,
+        multiplier: f64,
+        costs: &TransactionCosts,
+        vol: f64,
+        ts: u64,
+        date: &str,
+        exit_reason: ExitReason,
+    ) -> Option<Trade> {
+        let leg = self.legs.entry(instrument_id).or_insert_with(|| HedgeLeg {
+            instrument_id,
+            quantity: 0.0,
+            entry: price,
+            entry_date: date.to_string(),
+            opened_at: ts,
+            mark: price,
+            multiplier,
+            realized_pnl: 0.0,
+            trades: 0,
+            costs: costs.clone(),
+        });
+        leg.mark = price;
+
+        let mut trade = None;
+        let mut closed = 0.0;
+        if leg.quantity != 0.0 && leg.quantity.signum() != quantity.signum() {
+            closed = quantity.abs().min(leg.quantity.abs());
+            let long = leg.quantity > 0.0;
+            let gross = if long {
+                price - leg.entry
+            } else {
+                leg.entry - price
+            } * closed
+                * leg.multiplier;
+            let pnl = gross
+                - leg.costs.calculate_entry_cost(leg.entry, closed, vol)
+                - leg.costs.calculate_exit_cost(price, closed, vol);
+
+            leg.realized_pnl += pnl;
+            leg.trades += 1;
+            leg.quantity -= closed * leg.quantity.signum();
+            trade = Some(Trade {
+                entry_date: leg.entry_date.clone(),
+                exit_date: date.to_string(),
+                entry_price: leg.entry,
+                exit_price: price,
+                size: closed,
+                pnl,
+                pnl_pct: if long {
+                    (price / leg.entry - 1.0) * 100.0
+                } else {
+                    (leg.entry / price - 1.0) * 100.0
+                },
+                trade_type: if long { "Hedge Long" } else { "Hedge Short" }.to_string(),
+                exit_reason,
+                transaction_costs: gross - pnl,
+                gross_pnl: gross,
+                rolled_in: false,
+                lot: 0,
+                r_multiple: None,
+                risk: None,
+                instrument_id: Some(instrument_id),
+                entry_ts: leg.opened_at,
+                exit_ts: ts,
+            });
+        }
+
+        let opened = quantity - closed * quantity.signum();
+        if opened != 0.0 {
+            if leg.quantity == 0.0 {
+                leg.entry = price;
+                leg.entry_date = date.to_string();
+                leg.opened_at = ts;
+                leg.costs = costs.clone();
+            } else {
+                leg.entry = (leg.entry * leg.quantity.abs() + price * opened.abs())
+                    / (leg.quantity.abs() + opened.abs());
+            }
+            leg.quantity += opened;
+        }
+        trade
+    }
+
+    /// Close every open leg at its mark, under the costs it opened with
+    pub fn liquidate(&mut self, vol: f64, ts: u64, date: &str) -> Vec<Trade> {
+        let open: Vec<(u32, f64, f64, f64, TransactionCosts)> = self
+            .legs
+            .values()
+            .filter(|leg| leg.quantity != 0.0)
+            .map(|leg| {
+                (
+                    leg.instrument_id,
+                    leg.quantity,
+                    leg.mark,
+                    leg.multiplier,
+                    leg.costs.clone(),
+                )
+            })
+            .collect();
+
+        open.into_iter()
+            .filter_map(|(id, quantity, mark, multiplier, costs)| {
+                self.fill(
+                    id,
+                    -quantity,
+                    mark,
+                    multiplier,
+                    &costs,
+                    vol,
+                    ts,
+                    date,
+                    ExitReason::EndOfData,
+                )
+            })
+            .collect()
+    }
+
+    /// Signed market value of every open leg
+    pub fn notional(&self) -> f64 {
+        self.legs.values().map(HedgeLeg::notional).sum()
+    }
+
+    pub fn summaries(&self) -> Vec<LegSummary> {
+        self.legs
+            .values()
+            .map(|leg| LegSummary {
+                instrument_id: leg.instrument_id,
+                realized_pnl: leg.realized_pnl,
+                open_quantity: leg.quantity,
+                trades: leg.trades,
+            })
+            .collect()
+    }
+}
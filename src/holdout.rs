@@ -0,0 +1,197 @@
+use crate::backtester::{run_backtest_with_options, BacktestOptions, BacktestResult};
+use crate::event::date_string;
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::{Strategy, StrategyParams};
+use crate::utils::chain;
+use crate::utils::fetch::BacktestManager;
+use crate::InkBackSchema;
+use anyhow::{anyhow, Result};
+use databento::dbn::Schema;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The end of a dataset locked away from research: runs through a
+/// [`BacktestManager`] carrying one only see the in-sample part, and
+/// [`evaluate_holdout`] alone runs on the rest, once.
+#[derive(Debug, Clone)]
+pub struct Holdout {
+    /// Start of the data
+    pub data_start: u64,
+    /// First timestamp locked, everything from here on is holdout
+    pub start_ts: u64,
+    // Shared between clones of the manager, so any one of them evaluates only once
+    evaluated: Arc<AtomicBool>,
+}
+
+impl Holdout {
+    /// Lock the final `fraction` of `[start_ts, end_ts)`
+    pub fn last_fraction(start_ts: u64, end_ts: u64, fraction: f64) -> Result<Self> {
+        if !(fraction > 0.0 && fraction < 1.0) {
+            return Err(anyhow!(
+                "The holdout fraction must be between 0 and 1, got {}",
+                fraction
+            ));
+        }
+        if end_ts <= start_ts || end_ts == u64::MAX {
+            return Err(anyhow!(
+                "A holdout needs a known time range, got {} to {}",
+                start_ts,
+                end_ts
+            ));
+        }
+        let in_sample = ((end_ts - start_ts) as f64 * (1.0 - fraction)) as u64;
+        Ok(Self {
+            data_start: start_ts,
+            start_ts: start_ts + in_sample,
+            evaluated: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Lock the final `fraction` of the time range of the files behind `data_path`
+    pub async fn of_data(data_path: &str, fraction: f64) -> Result<Self> {
+        let files = chain::scanned_files(data_path).await?;
+        Self::last_fraction(
+            files.first().map_or(0, |f| f.start),
+            files.last().map_or(0, |f| f.end),
+            fraction,
+        )
+    }
+
+    /// The range a run may use in place of `time_range`: the in-sample part without
+    /// one, an error when it reaches into the holdout
+    pub fn in_sample(&self, time_range: Option<(u64, u64)>) -> Result<Option<(u64, u64)>> {
+        match time_range {
+            None => Ok(Some((self.data_start, self.start_ts))),
+            Some((start, end)) if end <= self.start_ts => Ok(Some((start, end))),
+            Some((start, end)) => Err(anyhow!(
+                "Time range {} to {} touches the locked holdout starting at {}, only \
+                 evaluate_holdout may run on it",
+                start,
+                end,
+                self.start_ts
+            )),
+        }
+    }
+
+    pub fn is_evaluated(&self) -> bool {
+        self.evaluated.load(Ordering::SeqCst)
+    }
+}
+
+/// One parameter set's in-sample result next to its result on the holdout
+#[derive(Debug)]
+pub struct HoldoutReport {
+    pub params: String,
+    pub holdout_start: u64,
+    pub in_sample: BacktestResult,
+    pub holdout: BacktestResult,
+}
+
+/// Run the chosen parameters on the manager's in-sample data and then, for the first
+/// and only time, on its holdout. Later calls on the manager or any clone of it fail,
+/// so the holdout stays unseen by the choice of parameters. A call failing before the
+/// holdout run starts, in the in-sample run or building the strategy, leaves it unused.
+#[allow(clippy::too_many_arguments)]
+pub async fn evaluate_holdout<F>(
+    best_params: &StrategyParams,
+    strategy_constructor: F,
+    backtest_manager: &BacktestManager,
+    symbol: &str,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    transaction_costs: TransactionCosts,
+    starting_equity: f64,
+    exposure: f64,
+    options: &BacktestOptions,
+) -> Result<HoldoutReport>
+where
+    F: Fn(&StrategyParams) -> Result<Box<dyn Strategy>>,
+{
+    let holdout = backtest_manager
+        .holdout
+        .as_ref()
+        .ok_or_else(|| anyhow!("The backtest manager has no holdout to evaluate"))?;
+    let already_evaluated = || {
+        anyhow!(
+            "The holdout was already evaluated, running it again would let it leak into the choice"
+        )
+    };
+    if holdout.is_evaluated() {
+        return Err(already_evaluated());
+    }
+
+    let mut strategy = strategy_constructor(best_params)?;
+    let in_sample = run_backtest_with_options(
+        symbol,
+        backtest_manager.clone(),
+        strategy.as_mut(),
+        transaction_costs.clone(),
+        starting_equity,
+        exposure,
+        schema,
+        custom_schema.clone(),
+        None,
+        options,
+    )
+    .await?;
+
+    let unlocked = BacktestManager {
+        holdout: None,
+        ..backtest_manager.clone()
+    };
+    let mut strategy = strategy_constructor(best_params)?;
+    // Used up from here on, whether or not the run succeeds, since it reads the holdout
+    if holdout.evaluated.swap(true, Ordering::SeqCst) {
+        return Err(already_evaluated());
+    }
+    let held_out = run_backtest_with_options(
+        symbol,
+        unlocked,
+        strategy.as_mut(),
+        transaction_costs,
+        starting_equity,
+        exposure,
+        schema,
+        custom_schema,
+        Some((holdout.start_ts, u64::MAX)),
+        options,
+    )
+    .await?;
+
+    Ok(HoldoutReport {
+        params: best_params.to_string_representation(),
+        holdout_start: holdout.start_ts,
+        in_sample,
+        holdout: held_out,
+    })
+}
+
+pub fn display_holdout_report(report: &HoldoutReport) {
+    println!("\n=== IN-SAMPLE VS HOLDOUT ===");
+    println!("Parameters: [{}]", report.params);
+    println!(
+        "Holdout from {} ({})",
+        report.holdout_start,
+        date_string(report.holdout_start)
+    );
+    println!("{:<16} {:>14} {:>14}", "", "In-sample", "Holdout");
+    println!("{}", "-".repeat(46));
+    let (is, ho) = (&report.in_sample, &report.holdout);
+    for (label, a, b) in [
+        ("Return %", is.total_return_pct, ho.total_return_pct),
+        ("Max DD %", is.max_drawdown_pct, ho.max_drawdown_pct),
+        ("Sharpe", is.sharpe_ratio, ho.sharpe_ratio),
+        ("Sortino", is.sortino_ratio, ho.sortino_ratio),
+        ("Win rate %", is.win_rate, ho.win_rate),
+        ("Profit factor", is.profit_factor, ho.profit_factor),
+    ] {
+        println!("{:<16} {:>14.2} {:>14.2}", label, a, b);
+    }
+    println!(
+        "{:<16} {:>14} {:>14}",
+        "Trades", is.total_trades, ho.total_trades
+    );
+    if is.sharpe_ratio > 0.0 && ho.sharpe_ratio < is.sharpe_ratio / 2.0 {
+        println!("WARNING: the holdout Sharpe is under half the in-sample one, the fit may not generalize");
+    }
+}
@@ -0,0 +1,365 @@
+use crate::event::{FieldValue, MarketEvent};
+use time::Time;
+
+const DAY_NS: u64 = 86_400_000_000_000;
+
+/// What the engine's VWAP service tracks
+#[derive(Debug, Clone)]
+pub struct VwapConfig {
+    /// UTC time the trading session opens and the session VWAP resets,
+    /// e.g. 22:00 for CME Globex during US daylight saving time
+    pub session_open: Time,
+    /// Nanosecond timestamps to anchor a VWAP from, in addition to the session
+    pub anchors: Vec<u64>,
+}
+
+impl Default for VwapConfig {
+    fn default() -> Self {
+        Self {
+            session_open: Time::MIDNIGHT,
+            anchors: Vec::new(),
+        }
+    }
+}
+
+impl VwapConfig {
+    /// The first session open strictly after `ts`
+    pub fn next_session_open(&self, ts: u64) -> u64 {
+        let (h, m, s) = self.session_open.as_hms();
+        let offset = (h as u64 * 3600 + m as u64 * 60 + s as u64) * 1_000_000_000;
+        let open = ts - ts % DAY_NS + offset;
+        if open > ts {
+            open
+        } else {
+            open + DAY_NS
+        }
+    }
+}
+
+/// A VWAP reading with its volume-weighted standard deviation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VwapLevel {
+    pub vwap: f64,
+    pub std_dev: f64,
+    pub volume: f64,
+}
+
+impl VwapLevel {
+    /// `(lower, upper)` band `k` standard deviations from the VWAP
+    pub fn band(&self, k: f64) -> (f64, f64) {
+        (self.vwap - k * self.std_dev, self.vwap + k * self.std_dev)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct RunningVwap {
+    pv: f64,
+    pv2: f64,
+    volume: f64,
+}
+
+impl RunningVwap {
+    fn add(&mut self, price: f64, volume: f64) {
+        self.pv += price * volume;
+        self.pv2 += price * price * volume;
+        self.volume += volume;
+    }
+
+    fn level(&self) -> Option<VwapLevel> {
+        if self.volume <= 0.0 {
+            return None;
+        }
+        let vwap = self.pv / self.volume;
+        let variance = (self.pv2 / self.volume - vwap * vwap).max(0.0);
+        Some(VwapLevel {
+            vwap,
+            std_dev: variance.sqrt(),
+            volume: self.volume,
+        })
+    }
+}
+
+/// Session and anchored VWAP computed incrementally from the prints in the stream.
+///
+/// Trades and MBO/MBP fills contribute their price and size, bars contribute their
+/// typical price `(high + low + close) / 3` and volume.
+#[derive(Debug, Clone)]
+pub struct VwapService {
+    session_offset: u64,
+    session_id: Option<u64>,
+    session: RunningVwap,
+    anchors: Vec<(u64, RunningVwap)>,
+}
+
+impl VwapService {
+    pub fn new(config: &VwapConfig) -> Self {
+        let (h, m, s) = config.session_open.as_hms();
+        let session_offset = (h as u64 * 3600 + m as u64 * 60 + s as u64) * 1_000_000_000;
+
+        let mut anchors: Vec<(u64, RunningVwap)> = config
+            .anchors
+            .iter()
+            .map(|&ts| (ts, RunningVwap::default()))
+            .collect();
+        anchors.sort_by_key(|(ts, _)| *ts);
+
+        Self {
+            session_offset,
+            session_id: None,
+            session: RunningVwap::default(),
+            anchors,
+        }
+    }
+
+    // Price and volume an event adds to the VWAP, None for quotes and reference data
+    fn print(event: &MarketEvent) -> Option<(f64, f64)> {
+        let (price, volume) = match event {
+            MarketEvent::Trade(_) | MarketEvent::Footprint(_) => {
+                (event.price(), event.volume() as f64)
+            }
+            MarketEvent::Ohlcv(_) => (
+                (event.high() + event.low() + event.price()) / 3.0,
+                event.volume() as f64,
+            ),
+            MarketEvent::Mbp1(_) | MarketEvent::Mbo(_) => match event.field("action")? {
+                FieldValue::Char('T' | 'F') => (event.price(), event.volume() as f64),
+                _ => return None,
+            },
+            MarketEvent::OptionTrade(_) | MarketEvent::Definition(_) => return None,
+        };
+        (price.is_finite() && volume > 0.0).then_some((price, volume))
+    }
+
+    /// Fold an event into every VWAP, resetting the session VWAP at the session open
+    pub fn update(&mut self, event: &MarketEvent) {
+        let ts = event.timestamp();
+        let session_id = ts.saturating_sub(self.session_offset) / DAY_NS;
+        if self.session_id != Some(session_id) {
+            self.session_id = Some(session_id);
+            self.session = RunningVwap::default();
+        }
+
+        let Some((price, volume)) = Self::print(event) else {
+            return;
+        };
+        self.session.add(price, volume);
+        for (anchor, vwap) in &mut self.anchors {
+            if ts >= *anchor {
+                vwap.add(price, volume);
+            }
+        }
+    }
+
+    /// VWAP since the current session opened
+    pub fn session(&self) -> Option<VwapLevel> {
+        self.session.level()
+    }
+
+    /// VWAP anchored at the `index`-th anchor in time order, None before the anchor
+    pub fn anchored(&self, index: usize) -> Option<VwapLevel> {
+        self.anchors.get(index).and_then(|(_, v)| v.level())
+    }
+
+    /// Every anchored VWAP that has started, as `(anchor_ts, level)`
+    pub fn anchored_all(&self) -> Vec<(u64, VwapLevel)> {
+        self.anchors
+            .iter()
+            .filter_map(|(ts, v)| v.level().map(|l| (*ts, l)))
+            .collect()
+    }
+}
+
+/// Standard deviation of simple returns between consecutive prints over the last
+/// `window` returns, maintained in O(1) per event
+#[derive(Debug, Clone)]
+pub struct RollingVolatility {
+    window: usize,
+    returns: std::collections::VecDeque<f64>,
+    sum: f64,
+    sum_sq: f64,
+    last_price: Option<f64>,
+}
+
+impl RollingVolatility {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(2),
+            returns: std::collections::VecDeque::with_capacity(window.max(2)),
+            sum: 0.0,
+            sum_sq: 0.0,
+            last_price: None,
+        }
+    }
+
+    pub fn update(&mut self, event: &MarketEvent) {
+        if matches!(event, MarketEvent::Definition(_)) {
+            return;
+        }
+        let price = event.price();
+        if !price.is_finite() || price <= 0.0 {
+            return;
+        }
+
+        if let Some(last) = self.last_price {
+            let r = price / last - 1.0;
+            self.returns.push_back(r);
+            self.sum += r;
+            self.sum_sq += r * r;
+            if self.returns.len() > self.window {
+                if let Some(old) = self.returns.pop_front() {
+                    self.sum -= old;
+                    self.sum_sq -= old * old;
+                }
+            }
+        }
+        self.last_price = Some(price);
+    }
+
+    /// None until the window is full
+    pub fn value(&self) -> Option<f64> {
+        if self.returns.len() < self.window {
+            return None;
+        }
+        let n = self.returns.len() as f64;
+        let mean = self.sum / n;
+        Some((self.sum_sq / n - mean * mean).max(0.0).sqrt())
+    }
+}
+
+/// Recent high, low and close of every instrument, keeping `capacity` events each, from
+/// which the average true range and realized volatility of any window up to `capacity - 1`
+/// are computed on demand
+#[derive(Debug, Clone)]
+pub struct BarHistory {
+    capacity: usize,
+    bars: std::collections::HashMap<Option<u32>, std::collections::VecDeque<(f64, f64, f64)>>,
+}
+
+impl BarHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(2),
+            bars: std::collections::HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, event: &MarketEvent) {
+        if matches!(event, MarketEvent::Definition(_)) {
+            return;
+        }
+        let (high, low, close) = (event.high(), event.low(), event.price());
+        if !close.is_finite() || close <= 0.0 || !high.is_finite() || !low.is_finite() {
+            return;
+        }
+        let instrument = event.get_u64("instrument_id").map(|id| id as u32);
+        let bars = self.bars.entry(instrument).or_default();
+        if bars.len() == self.capacity {
+            bars.pop_front();
+        }
+        bars.push_back((high.max(close), low.min(close), close));
+    }
+
+    /// Mean true range of the instrument's last `window` events, None with fewer than
+    /// `window + 1` events seen
+    pub fn atr(&self, instrument: Option<u32>, window: usize) -> Option<f64> {
+        let bars = self.bars.get(&instrument)?;
+        if window == 0 || bars.len() <= window {
+            return None;
+        }
+        let recent = bars.range(bars.len() - window - 1..);
+        let sum: f64 = recent
+            .clone()
+            .zip(recent.skip(1))
+            .map(|(&(_, _, prev_close), &(high, low, _))| {
+                (high - low)
+                    .max((high - prev_close).abs())
+                    .max((low - prev_close).abs())
+            })
+            .sum();
+        Some(sum / window as f64)
+    }
+
+    /// Standard deviation of the instrument's simple returns over its last `window`
+    /// returns, as [`RollingVolatility`] computes it
+    pub fn realized_vol(&self, instrument: Option<u32>, window: usize) -> Option<f64> {
+        let bars = self.bars.get(&instrument)?;
+        if window < 2 || bars.len() <= window {
+            return None;
+        }
+        let closes = bars
+            .range(bars.len() - window - 1..)
+            .map(|&(_, _, close)| close);
+        let returns: Vec<f64> = closes
+            .clone()
+            .zip(closes.skip(1))
+            .map(|(prev, close)| close / prev - 1.0)
+            .collect();
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+        Some(var.sqrt())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ContractActivity {
+    // (ts, size) of the contract's prints inside the window
+    prints: std::collections::VecDeque<(u64, u64)>,
+    open_interest: Option<u64>,
+}
+
+/// Per-contract traded volume over a trailing time window, and open interest when the
+/// merged options stream carries it, keyed by instrument id
+#[derive(Debug, Clone)]
+pub struct OptionLiquidity {
+    window: u64,
+    now: u64,
+    contracts: std::collections::HashMap<u32, ContractActivity>,
+}
+
+impl OptionLiquidity {
+    /// `window` in nanoseconds
+    pub fn new(window: u64) -> Self {
+        Self {
+            window,
+            now: 0,
+            contracts: std::collections::HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, event: &MarketEvent) {
+        let ts = event.timestamp();
+        self.now = self.now.max(ts);
+
+        let MarketEvent::OptionTrade(m) = event else {
+            return;
+        };
+        let cutoff = ts.saturating_sub(self.window);
+        let activity = self.contracts.entry(m.instrument_id).or_default();
+        activity.prints.push_back((ts, m.size));
+        while activity.prints.front().is_some_and(|&(t, _)| t < cutoff) {
+            activity.prints.pop_front();
+        }
+        if m.open_interest.is_some() {
+            activity.open_interest = m.open_interest;
+        }
+    }
+
+    /// Contracts traded in the window up to the latest event
+    pub fn volume(&self, instrument_id: u32) -> u64 {
+        let cutoff = self.now.saturating_sub(self.window);
+        self.contracts.get(&instrument_id).map_or(0, |a| {
+            a.prints
+                .iter()
+                .rev()
+                .take_while(|&&(t, _)| t >= cutoff)
+                .map(|&(_, size)| size)
+                .sum()
+        })
+    }
+
+    /// Latest open interest seen for the contract
+    pub fn open_interest(&self, instrument_id: u32) -> Option<u64> {
+        self.contracts.get(&instrument_id)?.open_interest
+    }
+}
@@ -0,0 +1,154 @@
+use crate::backtester::{Lot, Trade};
+use crate::event::MarketEvent;
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::OrderType;
+use anyhow::{anyhow, Result};
+use std::fmt::Debug;
+
+/// Engine state checked after each event in paranoid mode
+pub(crate) struct EngineState<'a> {
+    pub event: &'a MarketEvent,
+    pub equity: f64,
+    pub starting_equity: f64,
+    pub position: &'a dyn Debug,
+    pub position_size: f64,
+    pub lots: &'a [Lot],
+    pub trades: &'a [Trade],
+    pub hedge_trades: &'a [Trade],
+    pub costs: &'a TransactionCosts,
+}
+
+/// Invariant checks of [`BacktestOptions::paranoid`], each failure aborting the run with
+/// a dump of the engine state
+///
+/// [`BacktestOptions::paranoid`]: crate::backtester::BacktestOptions::paranoid
+#[derive(Debug, Default)]
+pub(crate) struct Invariants {
+    last_ts: u64,
+    realized: f64,
+    trades_seen: usize,
+    hedge_trades_seen: usize,
+    // Fill outside the event's range, reported with the state at the end of the event
+    bad_fill: Option<String>,
+}
+
+fn violation(what: String, event: &MarketEvent, detail: &str) -> anyhow::Error {
+    anyhow!(
+        "Engine invariant violated: {}\n  event: {:?}\n{}",
+        what,
+        event,
+        detail
+    )
+}
+
+impl Invariants {
+    /// A fill's reference price, before costs, must be one the event reached: a buy at or
+    /// above its low, a sell at or below its high
+    pub(crate) fn fill(&mut self, event: &MarketEvent, order_type: OrderType, price: f64) {
+        let is_buy = matches!(order_type, OrderType::MarketBuy | OrderType::LimitBuy);
+        let (low, high) = (event.low(), event.high());
+        let tolerance = 1e-9 * price.abs().max(1.0);
+        let reached = if is_buy {
+            price >= low - tolerance
+        } else {
+            price <= high + tolerance
+        };
+        if (!price.is_finite() || !reached) && self.bad_fill.is_none() {
+            self.bad_fill = Some(format!(
+                "{:?} filled at {} outside the event's range [{}, {}]",
+                order_type, price, low, high
+            ));
+        }
+    }
+
+    pub(crate) fn check(&mut self, state: &EngineState) -> Result<()> {
+        let ts = state.event.timestamp();
+        let dump = || {
+            let recent: Vec<&Trade> = state.trades.iter().rev().take(3).collect();
+            format!(
+                "  equity: {}\n  position: {:?}\n  lots: {:?}\n  last trades: {:?}",
+                state.equity, state.position, state.lots, recent
+            )
+        };
+
+        if let Some(what) = self.bad_fill.take() {
+            return Err(violation(what, state.event, &dump()));
+        }
+        if ts < self.last_ts {
+            return Err(violation(
+                format!("timestamp {} before the previous {}", ts, self.last_ts),
+                state.event,
+                &dump(),
+            ));
+        }
+        self.last_ts = ts;
+
+        // Every closed trade this event, with the costs it was charged
+        let vol = state.event.volume() as f64;
+        for trade in state.trades[self.trades_seen..]
+            .iter()
+            .chain(&state.hedge_trades[self.hedge_trades_seen..])
+        {
+            let entry_cost = state
+                .costs
+                .calculate_entry_cost(trade.entry_price, trade.size, vol);
+            let exit_cost = state
+                .costs
+                .calculate_exit_cost(trade.exit_price, trade.size, vol);
+            if entry_cost < 0.0 || exit_cost < 0.0 || trade.size <= 0.0 {
+                return Err(violation(
+                    format!(
+                        "trade of size {} with entry cost {} and exit cost {}",
+                        trade.size, entry_cost, exit_cost
+                    ),
+                    state.event,
+                    &dump(),
+                ));
+            }
+            if trade.pnl.is_finite() {
+                self.realized += trade.pnl;
+            }
+        }
+        self.trades_seen = state.trades.len();
+        self.hedge_trades_seen = state.hedge_trades.len();
+
+        // Equity is cash: the starting equity plus everything realized
+        let expected = state.starting_equity + self.realized;
+        if !state.equity.is_finite()
+            || (state.equity - expected).abs() > 1e-6 * expected.abs().max(1.0)
+        {
+            return Err(violation(
+                format!(
+                    "equity {} differs from starting equity plus realized P&L {}",
+                    state.equity, expected
+                ),
+                state.event,
+                &dump(),
+            ));
+        }
+
+        let lot_size: f64 = state.lots.iter().map(|lot| lot.size).sum();
+        if state
+            .lots
+            .iter()
+            .any(|lot| lot.size.is_nan() || lot.size <= 0.0)
+        {
+            return Err(violation(
+                "lot with a non-positive size".to_string(),
+                state.event,
+                &dump(),
+            ));
+        }
+        if (lot_size - state.position_size).abs() > 1e-9 * lot_size.max(1.0) {
+            return Err(violation(
+                format!(
+                    "position size {} differs from its lots' {}",
+                    state.position_size, lot_size
+                ),
+                state.event,
+                &dump(),
+            ));
+        }
+        Ok(())
+    }
+}
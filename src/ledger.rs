@@ -0,0 +1,311 @@
+use crate::backtester::Trade;
+use anyhow::{anyhow, Context, Result};
+use csv::Writer;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// When the engine records the ledger into [`BacktestResult::ledger`]
+///
+/// [`BacktestResult::ledger`]: crate::backtester::BacktestResult::ledger
+#[derive(Debug, Clone, Default)]
+pub struct LedgerConfig {
+    /// Also record every `interval` nanoseconds, so marks move between fills. `None`
+    /// records only the events that change cash or the positions held.
+    pub interval: Option<u64>,
+}
+
+impl LedgerConfig {
+    pub fn every(interval: u64) -> Self {
+        Self {
+            interval: Some(interval),
+        }
+    }
+}
+
+/// One open position of the ledger, the main one or a hedge leg
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerPosition {
+    pub instrument_id: Option<u32>,
+    /// Units held, negative when short
+    pub quantity: f64,
+    /// Average entry price of the open quantity
+    pub entry: f64,
+    pub mark: f64,
+    /// Dollar value of a one point move per unit
+    pub multiplier: f64,
+}
+
+impl LedgerPosition {
+    /// Signed market value at the mark
+    pub fn market_value(&self) -> f64 {
+        self.quantity * self.mark * self.multiplier
+    }
+
+    /// P&L at the mark before the costs charged when it closes
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.quantity * (self.mark - self.entry) * self.multiplier
+    }
+}
+
+/// The account after one event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub ts: u64,
+    pub cash: f64,
+    /// Signed market value of the open positions
+    pub market_value: f64,
+    pub unrealized_pnl: f64,
+    /// Net of fees, everything closed so far
+    pub realized_pnl: f64,
+    /// Costs charged so far, slippage included. A position is charged its entry and
+    /// exit costs when it closes.
+    pub fees: f64,
+    /// Cash plus the unrealized P&L of the open positions
+    pub net_liquidation: f64,
+    pub positions: Vec<LedgerPosition>,
+}
+
+/// Books of a backtest account: cash moves only as trades realize, the open positions
+/// are marked every event, and both are reconciled against the trades posted
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    starting_cash: f64,
+    cash: f64,
+    realized_pnl: f64,
+    gross_pnl: f64,
+    fees: f64,
+    positions: Vec<LedgerPosition>,
+    config: Option<LedgerConfig>,
+    entries: Vec<LedgerEntry>,
+    // Cash and positions of the last entry, and when the next interval entry is due
+    recorded: Option<(f64, Vec<LedgerPosition>)>,
+    next_at: u64,
+}
+
+impl Ledger {
+    pub fn new(starting_cash: f64) -> Self {
+        Self {
+            starting_cash,
+            cash: starting_cash,
+            realized_pnl: 0.0,
+            gross_pnl: 0.0,
+            fees: 0.0,
+            positions: Vec::new(),
+            config: None,
+            entries: Vec::new(),
+            recorded: None,
+            next_at: 0,
+        }
+    }
+
+    /// Record entries as `config` says, see [`Ledger::entries`]
+    pub fn with_recording(mut self, config: Option<LedgerConfig>) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        self.realized_pnl
+    }
+
+    pub fn fees(&self) -> f64 {
+        self.fees
+    }
+
+    pub fn positions(&self) -> &[LedgerPosition] {
+        &self.positions
+    }
+
+    pub fn market_value(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(LedgerPosition::market_value)
+            .fold(0.0, |sum, v| sum + v)
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(LedgerPosition::unrealized_pnl)
+            .fold(0.0, |sum, v| sum + v)
+    }
+
+    pub fn net_liquidation(&self) -> f64 {
+        self.cash + self.unrealized_pnl()
+    }
+
+    /// Realize a closed trade into cash. Trades without a finite P&L are not booked.
+    pub fn post(&mut self, trade: &Trade) {
+        if !trade.pnl.is_finite() {
+            return;
+        }
+        self.cash += trade.pnl;
+        self.realized_pnl += trade.pnl;
+        self.gross_pnl += trade.gross_pnl;
+        self.fees += trade.transaction_costs;
+    }
+
+    pub fn post_all(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            self.post(trade);
+        }
+    }
+
+    /// Replace the open positions with `positions` marked at the current event
+    pub fn mark(&mut self, positions: impl IntoIterator<Item = LedgerPosition>) {
+        self.positions.clear();
+        self.positions
+            .extend(positions.into_iter().filter(|p| p.quantity != 0.0));
+    }
+
+    /// Check the books balance: cash is the starting cash plus everything realized, and
+    /// the realized P&L is the gross P&L less the fees
+    pub fn reconcile(&self, ts: u64) -> Result<()> {
+        let tolerance = |v: f64| 1e-6 * v.abs().max(1.0);
+        let expected_cash = self.starting_cash + self.realized_pnl;
+        if (self.cash - expected_cash).abs() > tolerance(expected_cash) {
+            return Err(anyhow!(
+                "Ledger out of balance at {}: cash {} but starting cash plus realized P&L is {}",
+                ts,
+                self.cash,
+                expected_cash
+            ));
+        }
+        let expected_realized = self.gross_pnl - self.fees;
+        if (self.realized_pnl - expected_realized).abs() > tolerance(expected_realized) {
+            return Err(anyhow!(
+                "Ledger out of balance at {}: realized P&L {} but gross P&L less fees is {}",
+                ts,
+                self.realized_pnl,
+                expected_realized
+            ));
+        }
+        if let Some(p) = self
+            .positions
+            .iter()
+            .find(|p| !p.market_value().is_finite() || !p.unrealized_pnl().is_finite())
+        {
+            return Err(anyhow!(
+                "Ledger position without a finite value at {}: {:?}",
+                ts,
+                p
+            ));
+        }
+        Ok(())
+    }
+
+    /// Record an entry at `ts` when recording and cash or the positions held changed since
+    /// the last one, or an interval passed. `force` records regardless, e.g. at the end.
+    pub fn record(&mut self, ts: u64, force: bool) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        let changed = self.recorded.as_ref().is_none_or(|(cash, positions)| {
+            *cash != self.cash
+                || positions.len() != self.positions.len()
+                || positions
+                    .iter()
+                    .zip(&self.positions)
+                    .any(|(a, b)| a.instrument_id != b.instrument_id || a.quantity != b.quantity)
+        });
+        let due = config.interval.is_some_and(|_| ts >= self.next_at);
+        if !(changed || due || force) {
+            return;
+        }
+        if let Some(interval) = config.interval.filter(|&i| i > 0) {
+            self.next_at = (ts / interval + 1) * interval;
+        }
+        self.recorded = Some((self.cash, self.positions.clone()));
+        self.entries.push(LedgerEntry {
+            ts,
+            cash: self.cash,
+            market_value: self.market_value(),
+            unrealized_pnl: self.unrealized_pnl(),
+            realized_pnl: self.realized_pnl,
+            fees: self.fees,
+            net_liquidation: self.net_liquidation(),
+            positions: self.positions.clone(),
+        });
+    }
+
+    /// Entries recorded so far
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.entries
+    }
+
+    pub fn into_entries(self) -> Vec<LedgerEntry> {
+        self.entries
+    }
+}
+
+#[derive(Serialize)]
+struct AccountRow {
+    ts: u64,
+    cash: f64,
+    market_value: f64,
+    unrealized_pnl: f64,
+    realized_pnl: f64,
+    fees: f64,
+    net_liquidation: f64,
+    open_positions: usize,
+}
+
+#[derive(Serialize)]
+struct PositionRow {
+    ts: u64,
+    instrument_id: Option<u32>,
+    quantity: f64,
+    entry: f64,
+    mark: f64,
+    multiplier: f64,
+    market_value: f64,
+    unrealized_pnl: f64,
+}
+
+/// Write ledger entries as a CSV of account rows, one per entry
+pub fn write_ledger_csv(entries: &[LedgerEntry], path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let mut writer =
+        Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    for entry in entries {
+        writer.serialize(AccountRow {
+            ts: entry.ts,
+            cash: entry.cash,
+            market_value: entry.market_value,
+            unrealized_pnl: entry.unrealized_pnl,
+            realized_pnl: entry.realized_pnl,
+            fees: entry.fees,
+            net_liquidation: entry.net_liquidation,
+            open_positions: entry.positions.len(),
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the positions of ledger entries as a CSV, one row per position per entry
+pub fn write_ledger_positions_csv(entries: &[LedgerEntry], path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let mut writer =
+        Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    for entry in entries {
+        for position in &entry.positions {
+            writer.serialize(PositionRow {
+                ts: entry.ts,
+                instrument_id: position.instrument_id,
+                quantity: position.quantity,
+                entry: position.entry,
+                mark: position.mark,
+                multiplier: position.multiplier,
+                market_value: position.market_value(),
+                unrealized_pnl: position.unrealized_pnl(),
+            })?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! InkBack: an event-driven backtesting engine on top of DataBento market data.
+
+pub mod accounts;
+pub mod audit;
+pub mod backtester;
+pub mod beta;
+pub mod capital;
+pub mod compliance;
+pub mod compose;
+pub mod context;
+pub mod diff;
+pub mod event;
+pub mod features;
+pub mod greeks;
+pub mod hedge;
+pub mod holdout;
+pub mod indicators;
+mod invariants;
+pub mod ledger;
+pub mod marginals;
+pub mod metadata;
+pub mod microstructure;
+pub mod orderflow;
+pub mod pairs;
+pub mod permutation;
+pub mod plot;
+pub mod portfolio;
+pub mod progress;
+pub mod regimes;
+pub mod replay;
+pub mod requirements;
+pub mod research;
+pub mod robustness;
+pub mod seeds;
+pub mod segments;
+pub mod signals;
+pub mod significance;
+pub mod sizing;
+pub mod slippage_models;
+pub mod strategy;
+pub mod stress;
+pub mod symbology;
+pub mod testing;
+pub mod time_of_day;
+pub mod tools;
+pub mod tournament;
+pub mod two_phase;
+pub mod utils;
+pub mod vectorized;
+pub mod walkforward;
+
+use utils::aggressor::SideClassifier;
+
+// InkBack schemas
+#[derive(Clone)]
+pub enum InkBackSchema {
+    /// Footprint bars built from trades, each trade's aggressor decided by `sides`. With
+    /// `quotes` the trades come from the TBBO schema, each with the quote before it, so
+    /// levels also record the volume traded at the bid and at the ask.
+    FootPrint { sides: SideClassifier, quotes: bool },
+    /// Option trades merged with the `underlying` records they are enriched with
+    CombinedOptionsUnderlying { underlying: UnderlyingQuotes },
+    /// OHLCV bars of any `interval` (nanoseconds) aggregated locally from trades
+    TimeBars { interval: u64 },
+}
+
+impl InkBackSchema {
+    /// Footprint bars from trades with DataBento's side byte, the former unit
+    /// `FootPrint` variant
+    pub fn footprint() -> Self {
+        InkBackSchema::FootPrint {
+            sides: SideClassifier::default(),
+            quotes: false,
+        }
+    }
+}
+
+/// Underlying schema the options merge reads quotes from, trading precision for cost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnderlyingQuotes {
+    /// Top of book on every update
+    #[default]
+    Mbp1,
+    /// Top of book sampled each second, so quotes are up to a second stale
+    Bbo1s,
+    /// Trades only, with bid and ask both the last trade price
+    Trades,
+}
+
+impl UnderlyingQuotes {
+    pub fn schema(&self) -> databento::dbn::Schema {
+        match self {
+            UnderlyingQuotes::Mbp1 => databento::dbn::Schema::Mbp1,
+            UnderlyingQuotes::Bbo1s => databento::dbn::Schema::Bbo1S,
+            UnderlyingQuotes::Trades => databento::dbn::Schema::Trades,
+        }
+    }
+
+    // Name in the cached file names
+    pub(crate) fn tag(&self) -> &'static str {
+        match self {
+            UnderlyingQuotes::Mbp1 => "mbp1",
+            UnderlyingQuotes::Bbo1s => "bbo1s",
+            UnderlyingQuotes::Trades => "trades",
+        }
+    }
+}
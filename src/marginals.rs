@@ -0,0 +1,142 @@
+use crate::backtester::BacktestResult;
+use crate::strategy::StrategyParams;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A metric over every combination of a sweep sharing one value of a parameter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginalPoint {
+    pub value: f64,
+    pub runs: usize,
+    pub mean: f64,
+    /// Population standard deviation across the combinations
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// How the mean metric moves as a parameter rises
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarginalTrend {
+    Increasing,
+    Decreasing,
+    /// The metric does not depend on the parameter
+    Flat,
+    Mixed,
+}
+
+/// The metric of a sweep against one parameter, the others averaged out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterMarginal {
+    pub parameter: String,
+    /// One point per value swept, ascending
+    pub points: Vec<MarginalPoint>,
+}
+
+impl ParameterMarginal {
+    pub fn trend(&self) -> MarginalTrend {
+        let means: Vec<f64> = self.points.iter().map(|p| p.mean).collect();
+        let scale = means.iter().fold(0.0_f64, |m, v| m.max(v.abs())).max(1.0);
+        let steps: Vec<f64> = means.windows(2).map(|w| w[1] - w[0]).collect();
+        let tolerance = 1e-9 * scale;
+        if steps.iter().all(|d| d.abs() <= tolerance) {
+            MarginalTrend::Flat
+        } else if steps.iter().all(|d| *d >= -tolerance) {
+            MarginalTrend::Increasing
+        } else if steps.iter().all(|d| *d <= tolerance) {
+            MarginalTrend::Decreasing
+        } else {
+            MarginalTrend::Mixed
+        }
+    }
+
+    /// Value with the best mean metric
+    pub fn best(&self) -> Option<&MarginalPoint> {
+        self.points.iter().max_by(|a, b| a.mean.total_cmp(&b.mean))
+    }
+}
+
+/// Aggregate a metric per value of every parameter swept over more than one value,
+/// in parameter name order. Combinations with a non-finite metric are left out.
+pub fn parameter_marginals(results: &[(StrategyParams, f64)]) -> Vec<ParameterMarginal> {
+    let mut by_parameter: BTreeMap<&str, Vec<(f64, f64)>> = BTreeMap::new();
+    for (params, metric) in results.iter().filter(|(_, m)| m.is_finite()) {
+        for (key, value) in params.iter() {
+            by_parameter.entry(key).or_default().push((value, *metric));
+        }
+    }
+
+    by_parameter
+        .into_iter()
+        .filter_map(|(parameter, mut samples)| {
+            samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let points: Vec<MarginalPoint> = samples
+                .chunk_by(|a, b| a.0 == b.0)
+                .map(|group| {
+                    let n = group.len() as f64;
+                    let mean = group.iter().map(|(_, m)| m).sum::<f64>() / n;
+                    MarginalPoint {
+                        value: group[0].0,
+                        runs: group.len(),
+                        mean,
+                        std: (group.iter().map(|(_, m)| (m - mean).powi(2)).sum::<f64>() / n)
+                            .sqrt(),
+                        min: group.iter().map(|(_, m)| *m).fold(f64::INFINITY, f64::min),
+                        max: group
+                            .iter()
+                            .map(|(_, m)| *m)
+                            .fold(f64::NEG_INFINITY, f64::max),
+                    }
+                })
+                .collect();
+            (points.len() > 1).then(|| ParameterMarginal {
+                parameter: parameter.to_string(),
+                points,
+            })
+        })
+        .collect()
+}
+
+/// [`parameter_marginals`] of a parameter sweep's results, reading each combination's
+/// parameters back from its `Strategy_N [key: value, ...]` label
+pub fn sweep_marginals(
+    results: &[(String, BacktestResult, Vec<f64>)],
+    metric: fn(&BacktestResult) -> f64,
+) -> Vec<ParameterMarginal> {
+    let samples: Vec<(StrategyParams, f64)> = results
+        .iter()
+        .filter_map(|(label, result, _)| {
+            let inner = label.split_once('[')?.1.strip_suffix(']')?;
+            Some((StrategyParams::parse(inner)?, metric(result)))
+        })
+        .collect();
+    parameter_marginals(&samples)
+}
+
+pub fn display_marginals(metric: &str, marginals: &[ParameterMarginal]) {
+    println!("\n=== PARAMETER MARGINALS ({}) ===", metric);
+    for marginal in marginals {
+        println!("{} ({:?})", marginal.parameter, marginal.trend());
+        println!(
+            "  {:<12} {:<6} {:<10} {:<10} {:<10} {:<10}",
+            "Value", "Runs", "Mean", "Std", "Min", "Max"
+        );
+        for p in &marginal.points {
+            println!(
+                "  {:<12} {:<6} {:<10.4} {:<10.4} {:<10.4} {:<10.4}",
+                p.value, p.runs, p.mean, p.std, p.min, p.max
+            );
+        }
+    }
+    let dead: Vec<&str> = marginals
+        .iter()
+        .filter(|m| m.trend() == MarginalTrend::Flat)
+        .map(|m| m.parameter.as_str())
+        .collect();
+    if !dead.is_empty() {
+        println!(
+            "WARNING: {} never change(s) the result, the sweep need not cover them",
+            dead.join(", ")
+        );
+    }
+}
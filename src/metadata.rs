@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::Command;
+
+/// Note key of the commit the run's code was built from
+pub const GIT_COMMIT: &str = "git_commit";
+/// Note key of what the run is meant to show
+pub const HYPOTHESIS: &str = "hypothesis";
+/// Note key of who made the run
+pub const AUTHOR: &str = "author";
+
+/// Free-form tags and notes kept with a run's result, so a research run can still be
+/// read weeks later
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunMetadata {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Notes by key, e.g. [`GIT_COMMIT`], [`HYPOTHESIS`] or [`AUTHOR`]
+    #[serde(default)]
+    pub notes: BTreeMap<String, String>,
+}
+
+impl RunMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a tag, once
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        if !self.has_tag(tag) {
+            self.tags.push(tag.to_string());
+        }
+        self
+    }
+
+    /// Set the note under `key`, replacing any earlier one
+    pub fn with_note(mut self, key: &str, value: &str) -> Self {
+        self.notes.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_hypothesis(self, hypothesis: &str) -> Self {
+        self.with_note(HYPOTHESIS, hypothesis)
+    }
+
+    pub fn with_author(self, author: &str) -> Self {
+        self.with_note(AUTHOR, author)
+    }
+
+    /// Note the commit checked out in the working directory, suffixed `-dirty` with
+    /// uncommitted changes. Nothing is noted outside a git repository.
+    pub fn with_git_commit(self) -> Self {
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .output()
+                .ok()
+                .filter(|out| out.status.success())
+                .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        };
+        let Some(commit) = git(&["rev-parse", "HEAD"]) else {
+            return self;
+        };
+        let dirty = git(&["status", "--porcelain"]).is_some_and(|s| !s.is_empty());
+        let commit = if dirty {
+            format!("{}-dirty", commit)
+        } else {
+            commit
+        };
+        self.with_note(GIT_COMMIT, &commit)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn note(&self, key: &str) -> Option<&str> {
+        self.notes.get(key).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.notes.is_empty()
+    }
+}
+
+pub fn display_run_metadata(metadata: &RunMetadata) {
+    println!("\n=== RUN METADATA ===");
+    if !metadata.tags.is_empty() {
+        println!("Tags: {}", metadata.tags.join(", "));
+    }
+    for (key, value) in &metadata.notes {
+        println!("{}: {}", key, value);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::event::MarketEvent;
+use crate::symbology::SymbologyMap;
+use databento::dbn::{BidAskPair, UNDEF_PRICE};
+
+/// Order book features of a market-by-price event, from its book levels best first.
+///
+/// MBP-1 carries a single level, so the depth features equal their top-of-book
+/// counterparts there; they differ once deeper levels are passed to [`BookFeatures::from_levels`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookFeatures {
+    pub bid: f64,
+    pub ask: f64,
+    pub bid_size: f64,
+    pub ask_size: f64,
+    /// `(bid_size - ask_size) / (bid_size + ask_size)` at the best level, in `[-1, 1]`,
+    /// positive when the bid is heavier
+    pub imbalance: f64,
+    /// The same over every level
+    pub depth_imbalance: f64,
+    /// Mid weighted toward the side with less size, `(bid * ask_size + ask * bid_size) /
+    /// (bid_size + ask_size)`, where the next trade is likelier to move the price
+    pub microprice: f64,
+    /// Mean of the size-weighted average bid and the size-weighted average ask over every
+    /// level
+    pub depth_weighted_mid: f64,
+    /// Ask minus bid in price units
+    pub spread: f64,
+    /// The spread in ticks, None without a known tick size
+    pub spread_ticks: Option<f64>,
+    /// Levels with a price and size on both sides
+    pub levels: usize,
+}
+
+impl BookFeatures {
+    /// None when the best level lacks a price or size on either side or is crossed.
+    /// Deeper levels stop at the first one missing a side.
+    pub fn from_levels(levels: &[BidAskPair], tick_size: Option<f64>) -> Option<Self> {
+        const SCALE: f64 = 1e-9;
+        let valid = |l: &&BidAskPair| {
+            l.bid_px != UNDEF_PRICE && l.ask_px != UNDEF_PRICE && l.bid_sz > 0 && l.ask_sz > 0
+        };
+        let top = levels.first().filter(valid)?;
+        let (bid, ask) = (top.bid_px as f64 * SCALE, top.ask_px as f64 * SCALE);
+        if bid <= 0.0 || ask < bid {
+            return None;
+        }
+        let (bid_size, ask_size) = (top.bid_sz as f64, top.ask_sz as f64);
+
+        let (mut bid_depth, mut ask_depth, mut bid_notional, mut ask_notional) =
+            (0.0, 0.0, 0.0, 0.0);
+        let mut depth = 0;
+        for level in levels.iter().take_while(valid) {
+            bid_depth += level.bid_sz as f64;
+            ask_depth += level.ask_sz as f64;
+            bid_notional += level.bid_px as f64 * SCALE * level.bid_sz as f64;
+            ask_notional += level.ask_px as f64 * SCALE * level.ask_sz as f64;
+            depth += 1;
+        }
+
+        let spread = ask - bid;
+        Some(Self {
+            bid,
+            ask,
+            bid_size,
+            ask_size,
+            imbalance: (bid_size - ask_size) / (bid_size + ask_size),
+            depth_imbalance: (bid_depth - ask_depth) / (bid_depth + ask_depth),
+            microprice: (bid * ask_size + ask * bid_size) / (bid_size + ask_size),
+            depth_weighted_mid: (bid_notional / bid_depth + ask_notional / ask_depth) / 2.0,
+            spread,
+            spread_ticks: tick_size
+                .filter(|&tick| tick.is_finite() && tick > 0.0)
+                .map(|tick| spread / tick),
+            levels: depth,
+        })
+    }
+
+    pub fn mid(&self) -> f64 {
+        (self.bid + self.ask) / 2.0
+    }
+}
+
+// Tick size of a book event's instrument from its definition in force, else `fallback`.
+// None for events without a book, which never need one.
+pub(crate) fn book_tick_size(
+    event: &MarketEvent,
+    symbology: &SymbologyMap,
+    fallback: Option<f64>,
+) -> Option<f64> {
+    let MarketEvent::Mbp1(m) = event else {
+        return None;
+    };
+    symbology
+        .get(m.hd.instrument_id, m.hd.ts_event)
+        .map(|info| info.tick_size)
+        .filter(|&tick| tick > 0.0)
+        .or(fallback)
+}
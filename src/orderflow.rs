@@ -0,0 +1,132 @@
+use crate::event::{FootprintLevel, MarketEvent};
+
+/// Price moving against the bar's order flow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaDivergence {
+    /// Closed lower than the previous bar on positive delta, sellers absorbed the buying
+    Bullish,
+    /// Closed higher than the previous bar on negative delta, buyers absorbed the selling
+    Bearish,
+}
+
+/// Order flow of one footprint bar, derived once by the engine and read through
+/// [`EventCtx::footprint`](crate::context::EventCtx::footprint)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FootprintFeatures {
+    pub ts_event: u64,
+    /// Levels of the bar, ascending price
+    pub levels: Vec<FootprintLevel>,
+    pub buy_volume: u64,
+    pub sell_volume: u64,
+    /// Buy minus sell volume
+    pub delta: i64,
+    /// Delta over the bar's volume, in `[-1, 1]`
+    pub delta_ratio: f64,
+    /// Sum of the bar deltas since the start of the stream
+    pub cumulative_delta: i64,
+    /// Price of the level with the most volume, the lowest of ties
+    pub poc: f64,
+    /// POC minus the previous bar's, None on the first bar
+    pub poc_shift: Option<f64>,
+    /// Bars in a row the POC moved the same way, positive up and negative down
+    pub poc_migration: i32,
+    pub divergence: Option<DeltaDivergence>,
+    /// Both sides traded at the bar's highest level, an auction the market tends to revisit
+    pub unfinished_high: bool,
+    /// Both sides traded at the bar's lowest level
+    pub unfinished_low: bool,
+}
+
+impl FootprintFeatures {
+    pub fn high(&self) -> Option<f64> {
+        self.levels.last().map(|l| l.price)
+    }
+
+    pub fn low(&self) -> Option<f64> {
+        self.levels.first().map(|l| l.price)
+    }
+}
+
+/// Footprint analytics maintained incrementally from the footprint bars in the stream, so
+/// strategies don't each parse the levels and track the previous bars
+#[derive(Debug, Clone, Default)]
+pub struct FootprintService {
+    current: Option<FootprintFeatures>,
+    prev_close: Option<f64>,
+}
+
+impl FootprintService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Derive the features of a footprint bar, other events are ignored. Bars without
+    /// levels are skipped.
+    pub fn update(&mut self, event: &MarketEvent) {
+        let MarketEvent::Footprint(bar) = event else {
+            return;
+        };
+        let levels = bar.levels();
+        let Some(poc_level) =
+            levels
+                .iter()
+                .fold(None, |best: Option<&FootprintLevel>, l| match best {
+                    Some(b) if b.total() >= l.total() => Some(b),
+                    _ => Some(l),
+                })
+        else {
+            return;
+        };
+        let poc = poc_level.price;
+
+        let buy_volume: u64 = levels.iter().map(|l| l.buy_volume).sum();
+        let sell_volume: u64 = levels.iter().map(|l| l.sell_volume).sum();
+        let delta = buy_volume as i64 - sell_volume as i64;
+        let volume = buy_volume + sell_volume;
+
+        let prev = self.current.as_ref();
+        let poc_shift = prev.map(|p| poc - p.poc);
+        let poc_migration = match (poc_shift, prev.map_or(0, |p| p.poc_migration)) {
+            (Some(shift), run) if shift > 0.0 => run.max(0) + 1,
+            (Some(shift), run) if shift < 0.0 => run.min(0) - 1,
+            _ => 0,
+        };
+        let divergence = self.prev_close.and_then(|prev_close| {
+            if bar.price < prev_close && delta > 0 {
+                Some(DeltaDivergence::Bullish)
+            } else if bar.price > prev_close && delta < 0 {
+                Some(DeltaDivergence::Bearish)
+            } else {
+                None
+            }
+        });
+        let both_sides =
+            |l: Option<&FootprintLevel>| l.is_some_and(|l| l.buy_volume > 0 && l.sell_volume > 0);
+
+        self.current = Some(FootprintFeatures {
+            ts_event: bar.ts_event,
+            buy_volume,
+            sell_volume,
+            delta,
+            delta_ratio: if volume > 0 {
+                delta as f64 / volume as f64
+            } else {
+                0.0
+            },
+            cumulative_delta: prev.map_or(0, |p| p.cumulative_delta) + delta,
+            poc,
+            poc_shift,
+            poc_migration,
+            divergence,
+            unfinished_high: both_sides(levels.last()),
+            unfinished_low: both_sides(levels.first()),
+            levels,
+        });
+        self.prev_close = Some(bar.price);
+    }
+
+    /// Features of the last footprint bar seen
+    pub fn current(&self) -> Option<&FootprintFeatures> {
+        self.current.as_ref()
+    }
+}
@@ -0,0 +1,435 @@
+use crate::context::EventCtx;
+use crate::event::MarketEvent;
+use crate::strategy::{ExitReason, Order, OrderType, Strategy};
+use std::collections::VecDeque;
+
+/// Estimates the hedge ratio between two prices, `y ≈ alpha + beta * x`
+pub trait HedgeRatio: Send {
+    /// Add one observation of both prices
+    fn update(&mut self, y: f64, x: f64);
+
+    /// `(alpha, beta)` once there is enough data
+    fn estimate(&self) -> Option<(f64, f64)>;
+}
+
+/// Least squares over the last `window` observations
+#[derive(Debug, Clone)]
+pub struct RollingOls {
+    pub window: usize,
+    samples: VecDeque<(f64, f64)>,
+}
+
+impl RollingOls {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            samples: VecDeque::with_capacity(window),
+        }
+    }
+}
+
+impl HedgeRatio for RollingOls {
+    fn update(&mut self, y: f64, x: f64) {
+        if self.samples.len() == self.window.max(2) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((y, x));
+    }
+
+    fn estimate(&self) -> Option<(f64, f64)> {
+        if self.samples.len() < self.window.max(2) {
+            return None;
+        }
+        let (ys, xs): (Vec<f64>, Vec<f64>) = self.samples.iter().copied().unzip();
+        ols(&ys, &xs)
+    }
+}
+
+/// Kalman filter over `(beta, alpha)` as a random walk, adapting the ratio every
+/// observation without a window.
+///
+/// `delta` sets how fast the state may drift, as the share of its variance added per
+/// step (1e-4 drifts slowly, 1e-2 quickly), and `observation_var` the noise of the price
+/// relation itself.
+#[derive(Debug, Clone)]
+pub struct KalmanHedgeRatio {
+    pub delta: f64,
+    pub observation_var: f64,
+    // State (beta, alpha) and its covariance
+    state: [f64; 2],
+    cov: [[f64; 2]; 2],
+    updates: usize,
+}
+
+impl Default for KalmanHedgeRatio {
+    fn default() -> Self {
+        Self::new(1e-4, 1e-3)
+    }
+}
+
+impl KalmanHedgeRatio {
+    pub fn new(delta: f64, observation_var: f64) -> Self {
+        Self {
+            delta,
+            observation_var,
+            state: [0.0; 2],
+            cov: [[0.0; 2]; 2],
+            updates: 0,
+        }
+    }
+}
+
+impl HedgeRatio for KalmanHedgeRatio {
+    fn update(&mut self, y: f64, x: f64) {
+        let drift = self.delta / (1.0 - self.delta);
+        let mut r = self.cov;
+        r[0][0] += drift;
+        r[1][1] += drift;
+        let f = [x, 1.0];
+        // R F' and the innovation variance F R F' + Ve
+        let rf = [
+            r[0][0] * f[0] + r[0][1] * f[1],
+            r[1][0] * f[0] + r[1][1] * f[1],
+        ];
+        let q = f[0] * rf[0] + f[1] * rf[1] + self.observation_var;
+        let error = y - (f[0] * self.state[0] + f[1] * self.state[1]);
+        let gain = [rf[0] / q, rf[1] / q];
+        self.state[0] += gain[0] * error;
+        self.state[1] += gain[1] * error;
+        // P = R - K F R, with F R = (R F')' as R is symmetric
+        for (i, row) in self.cov.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell = r[i][j] - gain[i] * rf[j];
+            }
+        }
+        self.updates += 1;
+    }
+
+    /// Available from the second observation, the first only seeds the state
+    fn estimate(&self) -> Option<(f64, f64)> {
+        (self.updates >= 2).then_some((self.state[1], self.state[0]))
+    }
+}
+
+/// Z-score of the latest spread against the last `window` spreads, itself included
+#[derive(Debug, Clone)]
+pub struct SpreadZScore {
+    pub window: usize,
+    spreads: VecDeque<f64>,
+}
+
+impl SpreadZScore {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            spreads: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Add a spread and return its z-score, None until the window is full or while the
+    /// spreads have no variance
+    pub fn update(&mut self, spread: f64) -> Option<f64> {
+        let window = self.window.max(2);
+        if self.spreads.len() == window {
+            self.spreads.pop_front();
+        }
+        self.spreads.push_back(spread);
+        if self.spreads.len() < window {
+            return None;
+        }
+        let n = self.spreads.len() as f64;
+        let mean = self.spreads.iter().sum::<f64>() / n;
+        let var = self.spreads.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (var > 0.0).then(|| (spread - mean) / var.sqrt())
+    }
+}
+
+/// Least squares fit of `y = alpha + beta * x`, None for fewer than two points or a
+/// constant `x`
+pub fn ols(y: &[f64], x: &[f64]) -> Option<(f64, f64)> {
+    let n = y.len().min(x.len());
+    if n < 2 {
+        return None;
+    }
+    let (mean_y, mean_x) = (
+        y[..n].iter().sum::<f64>() / n as f64,
+        x[..n].iter().sum::<f64>() / n as f64,
+    );
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (yi, xi) in y.iter().zip(x) {
+        sxy += (xi - mean_x) * (yi - mean_y);
+        sxx += (xi - mean_x).powi(2);
+    }
+    if sxx == 0.0 {
+        return None;
+    }
+    let beta = sxy / sxx;
+    Some((mean_y - beta * mean_x, beta))
+}
+
+/// Engle-Granger test of two price series
+#[derive(Debug, Clone)]
+pub struct Cointegration {
+    pub alpha: f64,
+    pub beta: f64,
+    /// Dickey-Fuller t-statistic of the residuals `y - alpha - beta * x`, more negative
+    /// is stronger evidence of mean reversion
+    pub adf_stat: f64,
+    /// MacKinnon's asymptotic critical values for two variables with a constant, at
+    /// 1%, 5% and 10%
+    pub critical_values: [f64; 3],
+    /// Observations for the residuals to revert half way, None when they do not revert
+    pub half_life: Option<f64>,
+}
+
+impl Cointegration {
+    /// Whether the test rejects no cointegration at 5%
+    pub fn is_cointegrated(&self) -> bool {
+        self.adf_stat < self.critical_values[1]
+    }
+}
+
+/// Regress `y` on `x` over the full sample and test the residuals for a unit root with a
+/// Dickey-Fuller regression without lags. None for fewer than three points or a
+/// constant series.
+pub fn engle_granger(y: &[f64], x: &[f64]) -> Option<Cointegration> {
+    let (alpha, beta) = ols(y, x)?;
+    let residuals: Vec<f64> = y.iter().zip(x).map(|(y, x)| y - alpha - beta * x).collect();
+    if residuals.len() < 3 {
+        return None;
+    }
+    // Δe_t = γ e_{t-1} + ε, the residuals having zero mean by construction
+    let (mut see, mut sde) = (0.0, 0.0);
+    for w in residuals.windows(2) {
+        see += w[0] * w[0];
+        sde += w[0] * (w[1] - w[0]);
+    }
+    if see == 0.0 {
+        return None;
+    }
+    let gamma = sde / see;
+    let sse: f64 = residuals
+        .windows(2)
+        .map(|w| (w[1] - w[0] - gamma * w[0]).powi(2))
+        .sum();
+    let se = (sse / (residuals.len() - 2) as f64 / see).sqrt();
+    Some(Cointegration {
+        alpha,
+        beta,
+        adf_stat: if se > 0.0 {
+            gamma / se
+        } else {
+            f64::NEG_INFINITY
+        },
+        critical_values: [-3.90, -3.34, -3.04],
+        half_life: half_life(&residuals),
+    })
+}
+
+/// Half-life of mean reversion of a spread in observations, from the AR(1) fit
+/// `Δs_t = a + λ s_{t-1}` as `-ln 2 / λ`. None when the spread does not revert.
+pub fn half_life(spread: &[f64]) -> Option<f64> {
+    let lagged = spread.get(..spread.len().checked_sub(1)?)?;
+    let changes: Vec<f64> = spread.windows(2).map(|w| w[1] - w[0]).collect();
+    let (_, lambda) = ols(&changes, lagged)?;
+    (lambda < 0.0).then(|| -std::f64::consts::LN_2 / lambda)
+}
+
+/// Thresholds and size of a [`PairsStrategy`]
+#[derive(Debug, Clone)]
+pub struct PairsConfig {
+    /// Spreads behind the z-score
+    pub z_window: usize,
+    /// Open when the z-score reaches this far from zero
+    pub entry_z: f64,
+    /// Close once the z-score is back within this far from zero
+    pub exit_z: f64,
+    /// Units of the first leg per position, the second leg trading `beta` times as many
+    pub units: f64,
+}
+
+impl Default for PairsConfig {
+    fn default() -> Self {
+        Self {
+            z_window: 100,
+            entry_z: 2.0,
+            exit_z: 0.5,
+            units: 1.0,
+        }
+    }
+}
+
+/// Mean reversion of the spread `y - alpha - beta * x` between two instruments of one
+/// stream, e.g. two symbols merged with [`interleave_streams`].
+///
+/// The first leg `y` is the main position, the second `x` a hedge leg sent with
+/// [`Order::on_instrument`]. On each event of the first leg the hedge ratio is updated
+/// with the latest price of the second, and the spread's z-score decides: below
+/// `-entry_z` buy the spread (long `y`, short `beta` units of `x` per unit), above
+/// `entry_z` sell it, and close once it is back within `exit_z`. The hedge order follows
+/// the main order on the next event, and is unwound in full with the position. Both legs
+/// are assumed to have the same multiplier.
+///
+/// Publishes `spread_z` and `hedge_ratio` through [`EventCtx::record`].
+///
+/// [`interleave_streams`]: crate::utils::chain::interleave_streams
+pub struct PairsStrategy {
+    pub leg_y: u32,
+    pub leg_x: u32,
+    pub config: PairsConfig,
+    hedge: Box<dyn HedgeRatio>,
+    zscore: SpreadZScore,
+    last_x: Option<f64>,
+    // +1 long the spread, -1 short, 0 flat
+    side: i8,
+    // Signed units of the second leg held, assuming its orders filled
+    hedge_units: f64,
+    pending_hedge: Option<Order>,
+    last_estimate: Option<(f64, f64)>,
+    last_z: Option<f64>,
+}
+
+impl PairsStrategy {
+    pub fn new(
+        leg_y: u32,
+        leg_x: u32,
+        hedge: impl HedgeRatio + 'static,
+        config: PairsConfig,
+    ) -> Self {
+        Self {
+            leg_y,
+            leg_x,
+            zscore: SpreadZScore::new(config.z_window),
+            config,
+            hedge: Box::new(hedge),
+            last_x: None,
+            side: 0,
+            hedge_units: 0.0,
+            pending_hedge: None,
+            last_estimate: None,
+            last_z: None,
+        }
+    }
+
+    /// `(alpha, beta)` at the last event of the first leg
+    pub fn hedge_ratio(&self) -> Option<(f64, f64)> {
+        self.last_estimate
+    }
+
+    pub fn zscore(&self) -> Option<f64> {
+        self.last_z
+    }
+
+    // Trade the second leg by `units`, signed
+    fn queue_hedge(&mut self, units: f64) {
+        let Some(price) = self.last_x else {
+            return;
+        };
+        if units.abs() < 1e-9 {
+            return;
+        }
+        let order_type = if units > 0.0 {
+            OrderType::MarketBuy
+        } else {
+            OrderType::MarketSell
+        };
+        self.hedge_units += units;
+        self.pending_hedge = Some(
+            Order::new(order_type, price)
+                .with_quantity(units.abs())
+                .on_instrument(self.leg_x),
+        );
+    }
+
+    // Update the hedge ratio and z-score with a price of the first leg
+    fn observe(&mut self, y: f64) -> Option<(f64, f64)> {
+        let x = self.last_x?;
+        self.hedge.update(y, x);
+        let (alpha, beta) = self.hedge.estimate()?;
+        self.last_estimate = Some((alpha, beta));
+        self.last_z = self.zscore.update(y - alpha - beta * x);
+        Some((self.last_z?, beta))
+    }
+
+    fn step(&mut self, event: &MarketEvent) -> Option<Order> {
+        let id = event.get_u64("instrument_id").map(|id| id as u32);
+        let price = event.price();
+        if id == Some(self.leg_x) {
+            self.last_x = Some(price);
+        }
+        // A queued hedge order goes out first, decisions wait for the next event
+        if let Some(order) = self.pending_hedge.take() {
+            if id == Some(self.leg_y) {
+                self.observe(price);
+            }
+            return Some(order);
+        }
+        if id != Some(self.leg_y) {
+            return None;
+        }
+        let (z, beta) = self.observe(price)?;
+
+        let units = self.config.units;
+        if self.side != 0 {
+            let reverted = if self.side > 0 {
+                z >= -self.config.exit_z
+            } else {
+                z <= self.config.exit_z
+            };
+            if !reverted {
+                return None;
+            }
+            let exit = if self.side > 0 {
+                OrderType::MarketSell
+            } else {
+                OrderType::MarketBuy
+            };
+            self.side = 0;
+            self.queue_hedge(-self.hedge_units);
+            return Some(Order::new(exit, price).with_quantity(units));
+        }
+
+        self.side = if z <= -self.config.entry_z {
+            1
+        } else if z >= self.config.entry_z {
+            -1
+        } else {
+            return None;
+        };
+        let entry = if self.side > 0 {
+            OrderType::MarketBuy
+        } else {
+            OrderType::MarketSell
+        };
+        self.queue_hedge(-(self.side as f64) * beta * units);
+        Some(Order::new(entry, price).with_quantity(units))
+    }
+}
+
+impl Strategy for PairsStrategy {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        self.step(event)
+    }
+
+    fn on_event_ctx(
+        &mut self,
+        event: &MarketEvent,
+        _prev: Option<&MarketEvent>,
+        ctx: &EventCtx,
+    ) -> Option<Order> {
+        let order = self.step(event);
+        if event.get_u64("instrument_id") == Some(self.leg_y as u64) {
+            if let (Some(z), Some((_, beta))) = (self.last_z, self.last_estimate) {
+                ctx.record("spread_z", z);
+                ctx.record("hedge_ratio", beta);
+            }
+        }
+        order
+    }
+
+    /// The engine closed the first leg, so the second is unwound with it
+    fn on_position_closed(&mut self, _reason: ExitReason) {
+        self.side = 0;
+        self.queue_hedge(-self.hedge_units);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::backtester::BacktestResult;
+use crate::event::MarketEvent;
+use crate::research::WarmSession;
+use crate::seeds::{SeededRng, PERMUTATION};
+use crate::strategy::{Strategy, StrategyParams};
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+
+/// How the returns of the real data are randomized for the null distribution
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permutation {
+    /// Shuffle the order of blocks of `block` consecutive returns, breaking any
+    /// predictability longer than a block while keeping volatility clusters within one
+    BlockShuffle { block: usize },
+    /// Flip the sign of each return at random, removing drift and any directional edge
+    SignFlip,
+}
+
+/// A strategy's metric on the real data against the same metric on randomized data
+#[derive(Debug, Clone)]
+pub struct PermutationTest {
+    pub method: Permutation,
+    pub real: f64,
+    /// The metric of each randomized run, in run order
+    pub null: Vec<f64>,
+    /// Share of randomized runs doing at least as well as the real one, counting the real
+    /// run itself
+    pub p_value: f64,
+}
+
+impl PermutationTest {
+    /// Whether the real result beats the randomized ones at level `alpha`
+    pub fn is_significant(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+/// A randomized version of a single-instrument stream: the price path is rebuilt from
+/// permuted returns between consecutive priced events and each event is rescaled onto
+/// it, keeping timestamps, volumes and the shape of each bar. Footprint levels move with
+/// their bar.
+///
+/// Option chains cannot be permuted consistently, so streams with option trades are
+/// refused, as are streams of several instruments, whose merged sequence would mix the
+/// returns of one into the path of another.
+pub fn permute(
+    events: &[MarketEvent],
+    method: Permutation,
+    rng: &mut SeededRng,
+) -> Result<Vec<MarketEvent>> {
+    if events
+        .iter()
+        .any(|e| matches!(e, MarketEvent::OptionTrade(_)))
+    {
+        return Err(anyhow!("Streams with option trades cannot be permuted"));
+    }
+    let mut instruments = events
+        .iter()
+        .filter(|e| !matches!(e, MarketEvent::Definition(_)))
+        .filter_map(|e| e.get_u64("instrument_id"));
+    if let Some(first) = instruments.next() {
+        if let Some(other) = instruments.find(|&id| id != first) {
+            return Err(anyhow!(
+                "Streams of several instruments cannot be permuted, found {} and {}",
+                first,
+                other
+            ));
+        }
+    }
+    let priced: Vec<usize> = (0..events.len())
+        .filter(|&i| !matches!(events[i], MarketEvent::Definition(_)))
+        .collect();
+    let prices: Vec<f64> = priced.iter().map(|&i| events[i].price()).collect();
+    if prices.iter().any(|p| !p.is_finite() || *p <= 0.0) {
+        return Err(anyhow!("Permutation needs positive prices on every event"));
+    }
+
+    let mut returns: Vec<f64> = prices.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+    match method {
+        Permutation::BlockShuffle { block } => {
+            let mut blocks: Vec<Vec<f64>> =
+                returns.chunks(block.max(1)).map(|b| b.to_vec()).collect();
+            // Fisher-Yates
+            for i in (1..blocks.len()).rev() {
+                blocks.swap(i, rng.below(i + 1));
+            }
+            returns = blocks.concat();
+        }
+        Permutation::SignFlip => {
+            for r in &mut returns {
+                if rng.below(2) == 1 {
+                    *r = -*r;
+                }
+            }
+        }
+    }
+
+    let mut permuted = events.to_vec();
+    let mut log_price = prices.first().map_or(0.0, |p| p.ln());
+    for (k, &i) in priced.iter().enumerate() {
+        if k > 0 {
+            log_price += returns[k - 1];
+        }
+        permuted[i].scale_prices(log_price.exp() / prices[k]);
+    }
+    Ok(permuted)
+}
+
+/// Run the strategy on the session's data and on `runs` randomized versions of it,
+/// comparing `metric` against the null distribution.
+///
+/// Permutations draw from the [`PERMUTATION`] component of the session's seeds, so a
+/// test is reproduced exactly by the same seeds.
+pub async fn permutation_test<F>(
+    session: &WarmSession,
+    params: &StrategyParams,
+    strategy_constructor: F,
+    method: Permutation,
+    runs: usize,
+    metric: fn(&BacktestResult) -> f64,
+) -> Result<PermutationTest>
+where
+    F: Fn(&StrategyParams) -> Result<Box<dyn Strategy>>,
+{
+    let mut strategy = strategy_constructor(params)?;
+    let real = metric(&session.run(strategy.as_mut()).await?);
+    if !real.is_finite() {
+        return Err(anyhow!(
+            "The metric of the real run is not finite: {}",
+            real
+        ));
+    }
+
+    let mut rng = session.options().seeds.rng(PERMUTATION);
+    let mut null = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let events = Arc::new(permute(session.events(), method, &mut rng)?);
+        let mut strategy = strategy_constructor(params)?;
+        null.push(metric(
+            &session.run_events(events, strategy.as_mut()).await?,
+        ));
+    }
+
+    let as_good = null.iter().filter(|&&m| m >= real).count();
+    Ok(PermutationTest {
+        method,
+        real,
+        p_value: (as_good + 1) as f64 / (runs + 1) as f64,
+        null,
+    })
+}
+
+pub fn display_permutation_test(label: &str, test: &PermutationTest) {
+    let mut null: Vec<f64> = test
+        .null
+        .iter()
+        .copied()
+        .filter(|m| m.is_finite())
+        .collect();
+    null.sort_by(|a, b| a.total_cmp(b));
+    let mean = null.iter().sum::<f64>() / null.len().max(1) as f64;
+    let p95 = null
+        .get((null.len() as f64 * 0.95) as usize)
+        .or(null.last())
+        .copied()
+        .unwrap_or(f64::NAN);
+
+    println!("\n=== PERMUTATION TEST ({:?}) ===", test.method);
+    println!(
+        "{}: Real: {:.4}, Null mean: {:.4}, Null 95th: {:.4}, Runs: {}, p-value: {:.3}",
+        label,
+        test.real,
+        mean,
+        p95,
+        test.null.len(),
+        test.p_value
+    );
+    if !test.is_significant(0.05) {
+        println!("WARNING: the edge does not survive permutation, it may be noise");
+    }
+}
@@ -1,6 +1,192 @@
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::backtester::{BacktestResult, RStatistics, Trade};
+use crate::beta::BetaPoint;
+use crate::context::IndicatorSeries;
+use crate::event::{FootprintLevel, FootprintMsg, MarketEvent};
+use crate::greeks::GreeksPoint;
+use crate::marginals::ParameterMarginal;
+use crate::replay::{self, TradeReplay};
+use crate::stress::StressPoint;
+use crate::time_of_day::TradeHeatmap;
 use eframe::egui;
-use egui::Color32;
-use egui_plot::{Legend, Line, Plot, PlotPoints};
+use egui::{Color32, Stroke};
+use egui_plot::{
+    Bar, BarChart, HLine, Legend, Line, LineStyle, MarkerShape, Plot, PlotPoint, PlotPoints,
+    Points, Polygon, Text,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PlotTheme {
+    Dark,
+    Light,
+}
+
+/// Quantity equity curves are drawn as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EquityScale {
+    #[default]
+    Dollars,
+    /// Return on each curve's first point in percent, so curves of different starting
+    /// equity or leverage share an axis
+    PercentReturn,
+    /// Dollars on a logarithmic axis, where equal returns span equal heights
+    Log,
+}
+
+impl EquityScale {
+    pub const ALL: [EquityScale; 3] = [
+        EquityScale::Dollars,
+        EquityScale::PercentReturn,
+        EquityScale::Log,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EquityScale::Dollars => "$",
+            EquityScale::PercentReturn => "% return",
+            EquityScale::Log => "Log $",
+        }
+    }
+
+    /// Plot coordinate of `equity` on a curve starting at `first`, NaN where the scale
+    /// cannot show it
+    pub fn apply(&self, equity: f64, first: f64) -> f64 {
+        match self {
+            EquityScale::Dollars => equity,
+            EquityScale::PercentReturn if first > 0.0 => (equity / first - 1.0) * 100.0,
+            EquityScale::PercentReturn => f64::NAN,
+            EquityScale::Log if equity > 0.0 => equity.log10(),
+            EquityScale::Log => f64::NAN,
+        }
+    }
+
+    /// Axis and hover text of a plot coordinate
+    fn format(&self, value: f64) -> String {
+        match self {
+            EquityScale::Dollars => format!("${:.2}", value),
+            EquityScale::PercentReturn => format!("{:.2}%", value),
+            EquityScale::Log => format!("${:.2}", 10f64.powf(value)),
+        }
+    }
+}
+
+/// Styling for the equity curve window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlotConfig {
+    pub theme: PlotTheme,
+    /// RGB colors cycled through for the curves; evenly spaced hues when empty
+    pub palette: Vec<[u8; 3]>,
+    pub line_width: f32,
+    pub benchmark_line_width: f32,
+    /// RGB color of the benchmark curve; white on dark and black on light when unset
+    pub benchmark_color: Option<[u8; 3]>,
+    /// Multiplier applied to every text style
+    pub font_scale: f32,
+    /// RGB plot and panel background; theme default when unset
+    pub background: Option<[u8; 3]>,
+    /// Where visible curves and the benchmark toggle are persisted; nothing is saved when unset
+    pub settings_path: Option<String>,
+    /// Quantity equity is drawn as until changed in the window, which saves the choice
+    /// with the other settings
+    pub equity_scale: EquityScale,
+}
+
+impl Default for PlotConfig {
+    fn default() -> Self {
+        Self {
+            theme: PlotTheme::Dark,
+            palette: Vec::new(),
+            line_width: 1.5,
+            benchmark_line_width: 2.0,
+            benchmark_color: None,
+            font_scale: 1.0,
+            background: None,
+            settings_path: Some("src/data/plot_settings.json".to_string()),
+            equity_scale: EquityScale::Dollars,
+        }
+    }
+}
+
+impl PlotConfig {
+    fn apply_style(&self, ctx: &egui::Context) {
+        let mut visuals = match self.theme {
+            PlotTheme::Dark => egui::Visuals::dark(),
+            PlotTheme::Light => egui::Visuals::light(),
+        };
+        if let Some([r, g, b]) = self.background {
+            visuals.panel_fill = Color32::from_rgb(r, g, b);
+            visuals.extreme_bg_color = Color32::from_rgb(r, g, b);
+        }
+        ctx.set_visuals(visuals);
+
+        if (self.font_scale - 1.0).abs() > f32::EPSILON {
+            let mut style = (*ctx.style()).clone();
+            for font in style.text_styles.values_mut() {
+                font.size *= self.font_scale;
+            }
+            ctx.set_style(style);
+        }
+    }
+
+    fn curve_colors(&self, count: usize) -> Vec<Color32> {
+        if self.palette.is_empty() {
+            generate_colors(count)
+        } else {
+            (0..count)
+                .map(|i| {
+                    let [r, g, b] = self.palette[i % self.palette.len()];
+                    Color32::from_rgb(r, g, b)
+                })
+                .collect()
+        }
+    }
+
+    fn benchmark_color(&self) -> Color32 {
+        match (self.benchmark_color, self.theme) {
+            (Some([r, g, b]), _) => Color32::from_rgb(r, g, b),
+            (None, PlotTheme::Dark) => Color32::WHITE,
+            (None, PlotTheme::Light) => Color32::BLACK,
+        }
+    }
+}
+
+/// User toggles remembered between sessions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PlotSettings {
+    hidden_curves: Vec<String>,
+    #[serde(default)]
+    hidden_baselines: Vec<String>,
+    #[serde(default)]
+    equity_scale: Option<EquityScale>,
+}
+
+impl PlotSettings {
+    fn load(path: &str) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &str) {
+        if let Some(parent) = Path::new(path).parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(path, json) {
+                    eprintln!("Failed to save plot settings to {}: {}", path, e);
+                }
+            }
+            Err(e) => eprintln!("Failed to serialize plot settings: {}", e),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct EquityCurve {
@@ -8,89 +194,346 @@ pub struct EquityCurve {
     pub equity_data: Vec<f64>,
     pub visible: bool,
     pub color: Color32,
+    /// Ranking score (e.g. Sharpe) for curves streamed in while a sweep runs
+    pub score: Option<f64>,
 }
 
 pub struct EquityPlotter {
     equity_curves: Vec<EquityCurve>,
-    benchmark: Option<Vec<f64>>,
-    show_benchmark: bool,
+    /// Reference curves drawn in the benchmark color, told apart by line style
+    baselines: Vec<EquityCurve>,
+    /// Strategy-recorded series drawn in a panel below, aligned on the equity curve
+    indicators: Vec<IndicatorSeries>,
+    /// None for series that are not equity, which are drawn as they are
+    scale: Option<EquityScale>,
+    config: PlotConfig,
 }
 
 impl EquityPlotter {
-    fn new(curves_data: Vec<(String, Vec<f64>)>, benchmark: Option<Vec<f64>>) -> Self {
-        let colors = generate_colors(curves_data.len());
+    fn new(
+        curves_data: Vec<(String, Vec<f64>)>,
+        baselines: Vec<(String, Vec<f64>)>,
+        config: PlotConfig,
+    ) -> Self {
+        let settings = config
+            .settings_path
+            .as_deref()
+            .map(PlotSettings::load)
+            .unwrap_or_default();
+
+        let colors = config.curve_colors(curves_data.len());
         let equity_curves = curves_data
             .into_iter()
             .enumerate()
             .map(|(i, (label, data))| EquityCurve {
+                visible: !settings.hidden_curves.contains(&label),
                 label,
                 equity_data: data,
-                visible: true,
                 color: colors[i],
+                score: None,
+            })
+            .collect();
+
+        let baselines = baselines
+            .into_iter()
+            .map(|(label, data)| EquityCurve {
+                visible: !settings.hidden_baselines.contains(&label),
+                label,
+                equity_data: data,
+                color: config.benchmark_color(),
+                score: None,
             })
             .collect();
 
         Self {
             equity_curves,
-            benchmark,
-            show_benchmark: true,
+            baselines,
+            indicators: Vec::new(),
+            scale: Some(settings.equity_scale.unwrap_or(config.equity_scale)),
+            config,
+        }
+    }
+
+    /// Merge this plotter's toggles into the saved settings, leaving other tabs' curves alone
+    fn save_settings(&self, path: &str) {
+        let mut settings = PlotSettings::load(path);
+        settings
+            .hidden_curves
+            .retain(|label| !self.equity_curves.iter().any(|c| &c.label == label));
+        settings.hidden_curves.extend(
+            self.equity_curves
+                .iter()
+                .filter(|c| !c.visible)
+                .map(|c| c.label.clone()),
+        );
+        settings
+            .hidden_baselines
+            .retain(|label| !self.baselines.iter().any(|c| &c.label == label));
+        settings.hidden_baselines.extend(
+            self.baselines
+                .iter()
+                .filter(|c| !c.visible)
+                .map(|c| c.label.clone()),
+        );
+        if self.scale.is_some() {
+            settings.equity_scale = self.scale;
+        }
+        settings.save(path);
+    }
+
+    fn push_curve(&mut self, label: String, equity_data: Vec<f64>, score: Option<f64>) {
+        let hidden = self
+            .config
+            .settings_path
+            .as_deref()
+            .map(|path| PlotSettings::load(path).hidden_curves.contains(&label))
+            .unwrap_or(false);
+
+        self.equity_curves.push(EquityCurve {
+            label,
+            equity_data,
+            visible: !hidden,
+            color: Color32::WHITE,
+            score,
+        });
+
+        // Keep the side panel ordered as a live ranking, best score first
+        if self.equity_curves.iter().any(|c| c.score.is_some()) {
+            self.equity_curves.sort_by(|a, b| {
+                b.score
+                    .unwrap_or(f64::NEG_INFINITY)
+                    .partial_cmp(&a.score.unwrap_or(f64::NEG_INFINITY))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+
+        let colors = self.config.curve_colors(self.equity_curves.len());
+        for (curve, color) in self.equity_curves.iter_mut().zip(colors) {
+            curve.color = color;
         }
     }
 }
 
 impl eframe::App for EquityPlotter {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.show(ctx);
+    }
+}
+
+impl EquityPlotter {
+    fn show(&mut self, ctx: &egui::Context) {
+        let mut toggled = false;
+        let mut rescaled = false;
+
         egui::SidePanel::right("controls")
             .min_width(220.0)
             .show(ctx, |ui| {
                 ui.heading("Strategy Controls");
+                if let Some(scale) = self.scale.as_mut() {
+                    ui.horizontal(|ui| {
+                        ui.label("Equity as:");
+                        for option in EquityScale::ALL {
+                            rescaled |= ui.radio_value(scale, option, option.label()).changed();
+                        }
+                    });
+                    ui.separator();
+                }
                 ui.label("Toggle visibility:");
                 ui.separator();
 
-                if self.benchmark.is_some() {
-                    ui.checkbox(&mut self.show_benchmark, "Benchmark");
+                if !self.baselines.is_empty() {
+                    for baseline in self.baselines.iter_mut() {
+                        toggled |= ui
+                            .checkbox(&mut baseline.visible, &baseline.label)
+                            .changed();
+                    }
                     ui.separator();
                 }
 
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    for curve in &mut self.equity_curves {
+                    for (rank, curve) in self.equity_curves.iter_mut().enumerate() {
                         ui.horizontal(|ui| {
                             ui.colored_label(curve.color, "●");
-                            ui.checkbox(&mut curve.visible, &curve.label);
+                            let text = match curve.score {
+                                Some(score) => {
+                                    format!("{}. {} ({:.2})", rank + 1, curve.label, score)
+                                }
+                                None => curve.label.clone(),
+                            };
+                            toggled |= ui.checkbox(&mut curve.visible, text).changed();
                         });
                     }
                 });
             });
 
+        if toggled || rescaled {
+            if let Some(path) = &self.config.settings_path {
+                self.save_settings(path);
+            }
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            Plot::new("equity_curves")
+            let height = if self.indicators.is_empty() {
+                ui.available_height()
+            } else {
+                ui.available_height() * 0.7
+            };
+            let scale = self.scale.unwrap_or_default();
+            let mut plot = Plot::new("equity_curves")
                 .legend(Legend::default())
-                .show(ui, |plot_ui| {
-                    if self.show_benchmark {
-                        if let Some(benchmark) = &self.benchmark {
-                            let points: PlotPoints = benchmark
-                                .iter()
-                                .enumerate()
-                                .map(|(i, &v)| [i as f64, v])
-                                .collect();
-                            plot_ui.line(Line::new(points).color(Color32::WHITE).width(2.0));
+                .height(height)
+                .link_axis("equity_indicators", true, false);
+            if scale != EquityScale::Dollars {
+                plot = plot
+                    .y_axis_formatter(move |mark, _| scale.format(mark.value))
+                    .label_formatter(move |name, point| {
+                        let value = format!("x = {:.0}\n{}", point.x, scale.format(point.y));
+                        if name.is_empty() {
+                            value
+                        } else {
+                            format!("{}\n{}", name, value)
                         }
+                    });
+            }
+            if rescaled {
+                // Refit the view to the new quantity
+                plot = plot.reset();
+            }
+            plot.show(ui, |plot_ui| {
+                for (i, baseline) in self.baselines.iter().enumerate() {
+                    if !baseline.visible {
+                        continue;
                     }
+                    let points = visible_points(plot_ui, &baseline.equity_data, scale);
+                    plot_ui.line(
+                        Line::new(points)
+                            .name(&baseline.label)
+                            .color(baseline.color)
+                            .style(baseline_style(i))
+                            .width(self.config.benchmark_line_width),
+                    );
+                }
 
-                    for curve in self.equity_curves.iter().filter(|c| c.visible) {
-                        let points: PlotPoints = curve
-                            .equity_data
-                            .iter()
-                            .enumerate()
-                            .map(|(i, &v)| [i as f64, v])
-                            .collect();
-                        plot_ui.line(Line::new(points).color(curve.color).width(1.5));
-                    }
-                });
+                for curve in self.equity_curves.iter().filter(|c| c.visible) {
+                    let points = visible_points(plot_ui, &curve.equity_data, scale);
+                    plot_ui.line(
+                        Line::new(points)
+                            .color(curve.color)
+                            .width(self.config.line_width),
+                    );
+                }
+            });
+
+            if !self.indicators.is_empty() {
+                let colors = self.config.curve_colors(self.indicators.len());
+                Plot::new("indicators")
+                    .legend(Legend::default())
+                    .link_axis("equity_indicators", true, false)
+                    .show(ui, |plot_ui| {
+                        for (series, color) in self.indicators.iter().zip(colors) {
+                            let points: Vec<[f64; 2]> = series
+                                .points
+                                .iter()
+                                .map(|p| [p.index as f64, p.value])
+                                .collect();
+                            plot_ui.line(
+                                Line::new(PlotPoints::new(points))
+                                    .name(&series.name)
+                                    .color(color)
+                                    .width(self.config.line_width),
+                            );
+                        }
+                    });
+            }
         });
     }
 }
 
+/// Line style of the `i`th baseline, the first one solid
+fn baseline_style(i: usize) -> LineStyle {
+    match i % 4 {
+        0 => LineStyle::Solid,
+        1 => LineStyle::dashed_loose(),
+        2 => LineStyle::dotted_loose(),
+        _ => LineStyle::dashed_dense(),
+    }
+}
+
+/// Points of a series indexed by position, reduced to what the current view can show and
+/// drawn as `scale`. Until the user zooms or pans the whole series is treated as visible.
+fn visible_points(plot_ui: &egui_plot::PlotUi, data: &[f64], scale: EquityScale) -> PlotPoints {
+    let columns = plot_ui.response().rect.width().max(1.0) as usize;
+    let bounds = plot_ui.plot_bounds();
+
+    let (start, end) = if plot_ui.auto_bounds().x || !bounds.is_valid() {
+        (0, data.len())
+    } else {
+        // One extra point either side keeps the line continuous at the edges
+        let lo = (bounds.min()[0].floor() - 1.0).max(0.0) as usize;
+        let hi = (bounds.max()[0].ceil() + 2.0).max(0.0) as usize;
+        (lo.min(data.len()), hi.min(data.len()))
+    };
+
+    // Every scale is monotonic, so it keeps the extremes M4 picked
+    let first = data.first().copied().unwrap_or(0.0);
+    let mut points = downsample_m4(data, start, end, columns);
+    if scale != EquityScale::Dollars {
+        for point in &mut points {
+            point[1] = scale.apply(point[1], first);
+        }
+        points.retain(|p| p[1].is_finite());
+    }
+    PlotPoints::new(points)
+}
+
+/// M4 downsampling of `data[start..end]` into `columns` buckets.
+///
+/// Each bucket keeps its first, last, minimum and maximum points, so every peak and trough
+/// that would land in a pixel column survives, unlike decimation which can skip them.
+pub fn downsample_m4(data: &[f64], start: usize, end: usize, columns: usize) -> Vec<[f64; 2]> {
+    let end = end.min(data.len());
+    if start >= end {
+        return Vec::new();
+    }
+
+    let len = end - start;
+    let columns = columns.max(1);
+    if len <= columns * 4 {
+        return (start..end).map(|i| [i as f64, data[i]]).collect();
+    }
+
+    let mut out = Vec::with_capacity(columns * 4);
+    for c in 0..columns {
+        let b_start = start + c * len / columns;
+        let b_end = start + (c + 1) * len / columns;
+        if b_start >= b_end {
+            continue;
+        }
+
+        let bucket = &data[b_start..b_end];
+        let (mut min_i, mut max_i) = (0, 0);
+        for (i, v) in bucket.iter().enumerate() {
+            if *v < bucket[min_i] {
+                min_i = i;
+            }
+            if *v > bucket[max_i] {
+                max_i = i;
+            }
+        }
+
+        let mut picks = [0, min_i, max_i, bucket.len() - 1];
+        picks.sort_unstable();
+        let mut last = None;
+        for i in picks {
+            if last != Some(i) {
+                out.push([(b_start + i) as f64, bucket[i]]);
+                last = Some(i);
+            }
+        }
+    }
+    out
+}
+
 fn generate_colors(count: usize) -> Vec<Color32> {
     (0..count)
         .map(|i| {
@@ -126,7 +569,46 @@ fn hsv_to_color32(h: f32, s: f32, v: f32) -> Color32 {
     )
 }
 
-pub fn plot_equity_curves(equity_curves: Vec<(String, Vec<f64>)>, benchmark: Option<Vec<f64>>) {
+pub fn plot_equity_curves(
+    equity_curves: Vec<(String, Vec<f64>)>,
+    benchmark: Option<Vec<f64>>,
+    config: PlotConfig,
+) {
+    plot_equity_curves_with_baselines(equity_curves, benchmark_baselines(benchmark), config)
+}
+
+/// Like [`plot_equity_curves`] with any number of labelled reference curves, each with
+/// its own toggle
+pub fn plot_equity_curves_with_baselines(
+    equity_curves: Vec<(String, Vec<f64>)>,
+    baselines: Vec<(String, Vec<f64>)>,
+    config: PlotConfig,
+) {
+    plot_equity_curves_with_indicators(equity_curves, baselines, Vec::new(), config)
+}
+
+/// Like [`plot_equity_curves_with_baselines`] with the series a strategy recorded through
+/// [`EventCtx::record`] in a panel below the curves, sharing their x axis. The series'
+/// indices are per-event equity points, so they line up with unthinned curves.
+///
+/// [`EventCtx::record`]: crate::context::EventCtx::record
+pub fn plot_equity_curves_with_indicators(
+    equity_curves: Vec<(String, Vec<f64>)>,
+    baselines: Vec<(String, Vec<f64>)>,
+    indicators: Vec<IndicatorSeries>,
+    config: PlotConfig,
+) {
+    run_plotter(equity_curves, baselines, indicators, config, true)
+}
+
+// The equity window, with `scaled` false for series that are not equity
+fn run_plotter(
+    equity_curves: Vec<(String, Vec<f64>)>,
+    baselines: Vec<(String, Vec<f64>)>,
+    indicators: Vec<IndicatorSeries>,
+    config: PlotConfig,
+    scaled: bool,
+) {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_title("InkBack from Scorsone Enterprises")
@@ -137,7 +619,1062 @@ pub fn plot_equity_curves(equity_curves: Vec<(String, Vec<f64>)>, benchmark: Opt
     if let Err(e) = eframe::run_native(
         "InkBack",
         options,
-        Box::new(move |_cc| Ok(Box::new(EquityPlotter::new(equity_curves, benchmark)))),
+        Box::new(move |cc| {
+            config.apply_style(&cc.egui_ctx);
+            let mut plotter = EquityPlotter::new(equity_curves, baselines, config);
+            plotter.indicators = indicators;
+            if !scaled {
+                plotter.scale = None;
+            }
+            Ok(Box::new(plotter))
+        }),
+    ) {
+        eprintln!("Error running egui application: {}", e);
+    }
+}
+
+/// Plot a backtest's net delta, gamma, vega and theta through time, one toggleable
+/// line each
+pub fn plot_greeks(series: &[GreeksPoint], config: PlotConfig) {
+    let line = |f: fn(&GreeksPoint) -> f64| series.iter().map(f).collect::<Vec<f64>>();
+    let curves = vec![
+        ("Net Delta".to_string(), line(|p| p.greeks.delta)),
+        ("Net Gamma".to_string(), line(|p| p.greeks.gamma)),
+        ("Net Vega".to_string(), line(|p| p.greeks.vega)),
+        ("Net Theta".to_string(), line(|p| p.greeks.theta)),
+    ];
+    run_plotter(curves, Vec::new(), Vec::new(), config, false)
+}
+
+/// Plot the equity at each day close against the equity had the worst stress scenario
+/// happened
+pub fn plot_stress(points: &[StressPoint], config: PlotConfig) {
+    let curves = vec![
+        (
+            "Equity at Close".to_string(),
+            points.iter().map(|p| p.equity).collect(),
+        ),
+        (
+            "Worst Case".to_string(),
+            points.iter().map(StressPoint::worst_case_equity).collect(),
+        ),
+    ];
+    plot_equity_curves_with_baselines(curves, Vec::new(), config)
+}
+
+/// Plot the rolling correlation and beta of the strategy to its underlying, one
+/// toggleable line each
+pub fn plot_beta(points: &[BetaPoint], config: PlotConfig) {
+    let curves = vec![
+        (
+            "Correlation".to_string(),
+            points.iter().map(|p| p.correlation).collect(),
+        ),
+        ("Beta".to_string(), points.iter().map(|p| p.beta).collect()),
+    ];
+    run_plotter(curves, Vec::new(), Vec::new(), config, false)
+}
+
+/// A lone benchmark curve as the single baseline it is shown as
+fn benchmark_baselines(benchmark: Option<Vec<f64>>) -> Vec<(String, Vec<f64>)> {
+    benchmark
+        .map(|curve| vec![("Benchmark".to_string(), curve)])
+        .unwrap_or_default()
+}
+
+/// Messages accepted by a plot window running on its own thread
+pub enum PlotMessage {
+    /// Open a new tab holding a set of equity curves
+    AddTab {
+        title: String,
+        curves: Vec<(String, Vec<f64>)>,
+        baselines: Vec<(String, Vec<f64>)>,
+    },
+    /// Append a curve to the named tab, opening the tab if it does not exist yet.
+    /// Curves carrying a score are listed as a ranking, best first.
+    AddCurve {
+        tab: String,
+        label: String,
+        equity: Vec<f64>,
+        score: Option<f64>,
+    },
+}
+
+/// Sending side of a plot window. Cheap to clone, so worker threads can each hold one.
+#[derive(Clone)]
+pub struct PlotSender {
+    sender: Sender<PlotMessage>,
+    ctx: Arc<Mutex<Option<egui::Context>>>,
+}
+
+impl PlotSender {
+    /// Send a message to the window, returns false once the window has been closed
+    pub fn send(&self, message: PlotMessage) -> bool {
+        let sent = self.sender.send(message).is_ok();
+        if let Some(ctx) = self.ctx.lock().ok().and_then(|c| c.clone()) {
+            ctx.request_repaint();
+        }
+        sent
+    }
+
+    pub fn add_tab(
+        &self,
+        title: &str,
+        curves: Vec<(String, Vec<f64>)>,
+        benchmark: Option<Vec<f64>>,
+    ) -> bool {
+        self.send(PlotMessage::AddTab {
+            title: title.to_string(),
+            curves,
+            baselines: benchmark_baselines(benchmark),
+        })
+    }
+
+    pub fn add_curve(&self, tab: &str, label: &str, equity: Vec<f64>) -> bool {
+        self.send(PlotMessage::AddCurve {
+            tab: tab.to_string(),
+            label: label.to_string(),
+            equity,
+            score: None,
+        })
+    }
+
+    pub fn add_ranked_curve(&self, tab: &str, label: &str, equity: Vec<f64>, score: f64) -> bool {
+        self.send(PlotMessage::AddCurve {
+            tab: tab.to_string(),
+            label: label.to_string(),
+            equity,
+            score: Some(score),
+        })
+    }
+}
+
+/// A plot window running on a dedicated thread, see [`spawn_plot_window`]
+pub struct PlotHandle {
+    sender: PlotSender,
+    thread: JoinHandle<()>,
+}
+
+impl PlotHandle {
+    pub fn sender(&self) -> PlotSender {
+        self.sender.clone()
+    }
+
+    pub fn add_tab(
+        &self,
+        title: &str,
+        curves: Vec<(String, Vec<f64>)>,
+        benchmark: Option<Vec<f64>>,
+    ) -> bool {
+        self.sender.add_tab(title, curves, benchmark)
+    }
+
+    pub fn add_curve(&self, tab: &str, label: &str, equity: Vec<f64>) -> bool {
+        self.sender.add_curve(tab, label, equity)
+    }
+
+    /// Block until the user closes the window
+    pub fn wait(self) {
+        if self.thread.join().is_err() {
+            eprintln!("Plot window thread panicked");
+        }
+    }
+}
+
+/// Tabbed window holding one [`EquityPlotter`] per analysis, fed over a channel
+struct PlotWorkspace {
+    tabs: Vec<(String, EquityPlotter)>,
+    selected: usize,
+    receiver: Receiver<PlotMessage>,
+    config: PlotConfig,
+}
+
+impl PlotWorkspace {
+    fn tab_index(&mut self, title: &str) -> usize {
+        if let Some(i) = self.tabs.iter().position(|(t, _)| t == title) {
+            return i;
+        }
+        self.tabs.push((
+            title.to_string(),
+            EquityPlotter::new(Vec::new(), Vec::new(), self.config.clone()),
+        ));
+        self.tabs.len() - 1
+    }
+
+    fn handle(&mut self, message: PlotMessage) {
+        match message {
+            PlotMessage::AddTab {
+                title,
+                curves,
+                baselines,
+            } => {
+                let plotter = EquityPlotter::new(curves, baselines, self.config.clone());
+                match self.tabs.iter().position(|(t, _)| *t == title) {
+                    Some(i) => self.tabs[i].1 = plotter,
+                    None => self.tabs.push((title, plotter)),
+                }
+            }
+            PlotMessage::AddCurve {
+                tab,
+                label,
+                equity,
+                score,
+            } => {
+                let i = self.tab_index(&tab);
+                self.tabs[i].1.push_curve(label, equity, score);
+            }
+        }
+    }
+}
+
+impl eframe::App for PlotWorkspace {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        while let Ok(message) = self.receiver.try_recv() {
+            self.handle(message);
+        }
+
+        egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (i, (title, _)) in self.tabs.iter().enumerate() {
+                    ui.selectable_value(&mut self.selected, i, title);
+                }
+            });
+        });
+
+        match self.tabs.get_mut(self.selected) {
+            Some((_, plotter)) => plotter.show(ctx),
+            None => {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.label("Waiting for results...");
+                });
+            }
+        }
+    }
+}
+
+/// Launch a tabbed plot window on its own thread and return a handle for sending it results.
+///
+/// The caller keeps running; tabs and curves can be added from any thread through
+/// [`PlotHandle::sender`]. Supported on Linux and Windows, where the windowing event loop
+/// may run off the main thread.
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+pub fn spawn_plot_window(config: PlotConfig) -> PlotHandle {
+    let (tx, rx) = channel();
+    let shared_ctx: Arc<Mutex<Option<egui::Context>>> = Arc::new(Mutex::new(None));
+    let ctx_slot = shared_ctx.clone();
+
+    let thread = std::thread::spawn(move || {
+        let options = eframe::NativeOptions {
+            viewport: egui::ViewportBuilder::default()
+                .with_title("InkBack from Scorsone Enterprises")
+                .with_inner_size([1200.0, 700.0]),
+            event_loop_builder: Some(Box::new(|builder| {
+                #[cfg(target_os = "linux")]
+                {
+                    winit::platform::x11::EventLoopBuilderExtX11::with_any_thread(builder, true);
+                    winit::platform::wayland::EventLoopBuilderExtWayland::with_any_thread(
+                        builder, true,
+                    );
+                }
+                #[cfg(target_os = "windows")]
+                winit::platform::windows::EventLoopBuilderExtWindows::with_any_thread(
+                    builder, true,
+                );
+            })),
+            ..Default::default()
+        };
+
+        if let Err(e) = eframe::run_native(
+            "InkBack",
+            options,
+            Box::new(move |cc| {
+                config.apply_style(&cc.egui_ctx);
+                if let Ok(mut slot) = ctx_slot.lock() {
+                    *slot = Some(cc.egui_ctx.clone());
+                }
+                Ok(Box::new(PlotWorkspace {
+                    tabs: Vec::new(),
+                    selected: 0,
+                    receiver: rx,
+                    config,
+                }))
+            }),
+        ) {
+            eprintln!("Error running egui application: {}", e);
+        }
+    });
+
+    PlotHandle {
+        sender: PlotSender {
+            sender: tx,
+            ctx: shared_ctx,
+        },
+        thread,
+    }
+}
+
+/// How footprint cells are colored in the heatmap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeatmapColorMode {
+    /// Bid and ask halves shaded by their own volume
+    Volume,
+    /// Whole cell shaded by buy minus sell volume
+    Delta,
+}
+
+struct HeatmapBar {
+    ts_event: u64,
+    close: f64,
+    levels: Vec<FootprintLevel>,
+}
+
+/// Price-ladder heatmap of footprint bars: sell (bid) volume on the left half of each
+/// cell, buy (ask) volume on the right half, with diagonal imbalances outlined
+pub struct FootprintHeatmap {
+    bars: Vec<HeatmapBar>,
+    tick_size: f64,
+    max_level_volume: u64,
+    max_level_delta: u64,
+    imbalance_ratio: f64,
+    color_mode: HeatmapColorMode,
+    show_labels: bool,
+}
+
+impl FootprintHeatmap {
+    fn new(bars: Vec<FootprintMsg>, imbalance_ratio: f64) -> Self {
+        let bars: Vec<HeatmapBar> = bars
+            .iter()
+            .map(|b| HeatmapBar {
+                ts_event: b.ts_event,
+                close: b.price,
+                levels: b.levels(),
+            })
+            .collect();
+
+        let levels = bars.iter().flat_map(|b| b.levels.iter());
+        let max_level_volume = levels.clone().map(|l| l.total()).max().unwrap_or(0);
+        let max_level_delta = levels.map(|l| l.delta().unsigned_abs()).max().unwrap_or(0);
+
+        Self {
+            tick_size: infer_tick_size(&bars),
+            bars,
+            max_level_volume,
+            max_level_delta,
+            imbalance_ratio,
+            color_mode: HeatmapColorMode::Volume,
+            show_labels: false,
+        }
+    }
+
+    /// Diagonal imbalances for a bar: (buy imbalance, sell imbalance) per level index.
+    /// Buying at a price is compared to selling one tick below, selling to buying one tick above.
+    fn imbalances(&self, levels: &[FootprintLevel]) -> Vec<(bool, bool)> {
+        let volume_at = |price: f64, buy: bool| {
+            levels
+                .iter()
+                .find(|l| (l.price - price).abs() < self.tick_size * 0.5)
+                .map(|l| if buy { l.buy_volume } else { l.sell_volume })
+                .unwrap_or(0) as f64
+        };
+
+        levels
+            .iter()
+            .map(|l| {
+                let sell_below = volume_at(l.price - self.tick_size, false);
+                let buy_above = volume_at(l.price + self.tick_size, true);
+                let buy_imb =
+                    l.buy_volume > 0 && l.buy_volume as f64 >= self.imbalance_ratio * sell_below;
+                let sell_imb =
+                    l.sell_volume > 0 && l.sell_volume as f64 >= self.imbalance_ratio * buy_above;
+                (buy_imb && sell_below > 0.0, sell_imb && buy_above > 0.0)
+            })
+            .collect()
+    }
+}
+
+impl eframe::App for FootprintHeatmap {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let mut hovered_bar: Option<usize> = None;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let response = Plot::new("footprint_heatmap")
+                .legend(Legend::default())
+                .x_axis_label("Bar")
+                .y_axis_label("Price")
+                .show(ui, |plot_ui| {
+                    let half_tick = self.tick_size * 0.5;
+
+                    for (i, bar) in self.bars.iter().enumerate() {
+                        let x = i as f64;
+                        let imbalances = self.imbalances(&bar.levels);
+
+                        for (level, (buy_imb, sell_imb)) in bar.levels.iter().zip(imbalances) {
+                            let (lo, hi) = (level.price - half_tick, level.price + half_tick);
+
+                            match self.color_mode {
+                                HeatmapColorMode::Volume => {
+                                    let bid = intensity(level.sell_volume, self.max_level_volume);
+                                    let ask = intensity(level.buy_volume, self.max_level_volume);
+                                    plot_ui.polygon(
+                                        cell(x - 0.45, x, lo, hi)
+                                            .fill_color(shade(Color32::RED, bid))
+                                            .stroke(cell_stroke(sell_imb)),
+                                    );
+                                    plot_ui.polygon(
+                                        cell(x, x + 0.45, lo, hi)
+                                            .fill_color(shade(Color32::GREEN, ask))
+                                            .stroke(cell_stroke(buy_imb)),
+                                    );
+                                }
+                                HeatmapColorMode::Delta => {
+                                    let delta = level.delta();
+                                    let base = if delta >= 0 {
+                                        Color32::GREEN
+                                    } else {
+                                        Color32::RED
+                                    };
+                                    let t = intensity(delta.unsigned_abs(), self.max_level_delta);
+                                    plot_ui.polygon(
+                                        cell(x - 0.45, x + 0.45, lo, hi)
+                                            .fill_color(shade(base, t))
+                                            .stroke(cell_stroke(buy_imb || sell_imb)),
+                                    );
+                                }
+                            }
+
+                            if self.show_labels {
+                                plot_ui.text(
+                                    Text::new(
+                                        PlotPoint::new(x, level.price),
+                                        format!("{} x {}", level.sell_volume, level.buy_volume),
+                                    )
+                                    .color(Color32::WHITE),
+                                );
+                            }
+                        }
+                    }
+
+                    let closes: PlotPoints = self
+                        .bars
+                        .iter()
+                        .enumerate()
+                        .map(|(i, b)| [i as f64, b.close])
+                        .collect();
+                    plot_ui.line(
+                        Line::new(closes)
+                            .color(Color32::WHITE)
+                            .width(1.0)
+                            .name("Close"),
+                    );
+
+                    plot_ui
+                        .pointer_coordinate()
+                        .map(|p| p.x.round() as i64)
+                        .filter(|&i| i >= 0 && (i as usize) < self.bars.len())
+                        .map(|i| i as usize)
+                });
+            hovered_bar = response.inner;
+        });
+
+        egui::SidePanel::right("heatmap_controls")
+            .min_width(220.0)
+            .show(ctx, |ui| {
+                ui.heading("Footprint Controls");
+                ui.separator();
+
+                ui.label("Coloring:");
+                ui.radio_value(
+                    &mut self.color_mode,
+                    HeatmapColorMode::Volume,
+                    "Bid / Ask volume",
+                );
+                ui.radio_value(&mut self.color_mode, HeatmapColorMode::Delta, "Delta");
+                ui.separator();
+
+                ui.add(
+                    egui::Slider::new(&mut self.imbalance_ratio, 1.0..=10.0)
+                        .text("Imbalance ratio"),
+                );
+                ui.checkbox(&mut self.show_labels, "Show volume labels");
+                ui.separator();
+
+                ui.label(format!("Bars: {}", self.bars.len()));
+                ui.label(format!("Tick size: {}", self.tick_size));
+
+                if let Some(bar) = hovered_bar.and_then(|i| self.bars.get(i)) {
+                    let buy: u64 = bar.levels.iter().map(|l| l.buy_volume).sum();
+                    let sell: u64 = bar.levels.iter().map(|l| l.sell_volume).sum();
+                    let poc = bar.levels.iter().max_by_key(|l| l.total());
+
+                    ui.separator();
+                    ui.label(format!("ts_event: {}", bar.ts_event));
+                    ui.label(format!("Close: {:.4}", bar.close));
+                    ui.label(format!("Buy volume: {}", buy));
+                    ui.label(format!("Sell volume: {}", sell));
+                    ui.label(format!("Delta: {}", buy as i64 - sell as i64));
+                    if let Some(poc) = poc {
+                        ui.label(format!("POC: {:.4}", poc.price));
+                    }
+                }
+            });
+    }
+}
+
+fn cell(x0: f64, x1: f64, y0: f64, y1: f64) -> Polygon {
+    Polygon::new(PlotPoints::new(vec![
+        [x0, y0],
+        [x1, y0],
+        [x1, y1],
+        [x0, y1],
+    ]))
+}
+
+fn cell_stroke(highlight: bool) -> Stroke {
+    if highlight {
+        Stroke::new(2.0, Color32::YELLOW)
+    } else {
+        Stroke::new(0.5, Color32::from_gray(40))
+    }
+}
+
+fn intensity(value: u64, max: u64) -> f32 {
+    if max == 0 {
+        0.0
+    } else {
+        (value as f32 / max as f32).clamp(0.0, 1.0)
+    }
+}
+
+fn shade(base: Color32, t: f32) -> Color32 {
+    let alpha = (40.0 + 215.0 * t) as u8;
+    Color32::from_rgba_unmultiplied(base.r(), base.g(), base.b(), alpha)
+}
+
+// Smallest gap between adjacent price levels across all bars, 1.0 when there is none
+fn infer_tick_size(bars: &[HeatmapBar]) -> f64 {
+    let tick = bars
+        .iter()
+        .flat_map(|b| b.levels.windows(2).map(|w| w[1].price - w[0].price))
+        .filter(|d| *d > 1e-9)
+        .fold(f64::INFINITY, f64::min);
+
+    if tick.is_finite() {
+        tick
+    } else {
+        1.0
+    }
+}
+
+/// Open a footprint heatmap window for the given bars. A level is highlighted as an
+/// imbalance when its volume is at least `imbalance_ratio` times the diagonal opposite level.
+pub fn plot_footprint_heatmap(bars: Vec<FootprintMsg>, imbalance_ratio: f64) {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("InkBack Footprint from Scorsone Enterprises")
+            .with_inner_size([1200.0, 800.0]),
+        ..Default::default()
+    };
+
+    if let Err(e) = eframe::run_native(
+        "InkBack Footprint",
+        options,
+        Box::new(move |_cc| Ok(Box::new(FootprintHeatmap::new(bars, imbalance_ratio)))),
+    ) {
+        eprintln!("Error running egui application: {}", e);
+    }
+}
+
+/// What the cells of a trade heatmap are shaded by
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TradeHeatmapView {
+    Entries,
+    Exits,
+    /// P&L of the trades entered in the cell, green for gains and red for losses
+    EntryPnl,
+}
+
+// Price by time of day grid of a trade log
+struct TradeHeatmapPlot {
+    heatmap: TradeHeatmap,
+    view: TradeHeatmapView,
+}
+
+impl TradeHeatmapPlot {
+    fn grid(&self) -> Vec<Vec<f64>> {
+        let counts = |grid: &Vec<Vec<usize>>| -> Vec<Vec<f64>> {
+            grid.iter()
+                .map(|row| row.iter().map(|&c| c as f64).collect())
+                .collect()
+        };
+        match self.view {
+            TradeHeatmapView::Entries => counts(&self.heatmap.entries),
+            TradeHeatmapView::Exits => counts(&self.heatmap.exits),
+            TradeHeatmapView::EntryPnl => self.heatmap.entry_pnl.clone(),
+        }
+    }
+}
+
+impl eframe::App for TradeHeatmapPlot {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let grid = self.grid();
+        let max = grid.iter().flatten().fold(0.0_f64, |m, v| m.max(v.abs()));
+        let step = self.heatmap.price_step;
+        let minutes = self.heatmap.minutes;
+        let mut hovered: Option<(usize, usize)> = None;
+
+        egui::SidePanel::right("trade_heatmap_controls")
+            .min_width(220.0)
+            .show(ctx, |ui| {
+                ui.heading("Trade Heatmap");
+                ui.separator();
+                ui.label("Shade by:");
+                ui.radio_value(&mut self.view, TradeHeatmapView::Entries, "Entries");
+                ui.radio_value(&mut self.view, TradeHeatmapView::Exits, "Exits");
+                ui.radio_value(&mut self.view, TradeHeatmapView::EntryPnl, "P&L of entries");
+                ui.separator();
+                ui.label(format!("Entries: {}", self.heatmap.total_entries()));
+                ui.label(format!("Price step: {}", step));
+                ui.label(format!("Time step: {} min", minutes));
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let response = Plot::new("trade_heatmap")
+                .x_axis_label("Time of day")
+                .y_axis_label("Price")
+                .x_axis_formatter(move |mark, _| {
+                    let start = (mark.value * minutes as f64).round();
+                    if !(0.0..=1440.0).contains(&start) {
+                        return String::new();
+                    }
+                    let start = start as u32;
+                    format!("{:02}:{:02}", start / 60, start % 60)
+                })
+                .show(ui, |plot_ui| {
+                    for (p, row) in grid.iter().enumerate() {
+                        let lo = self.heatmap.price_of(p);
+                        for (t, &value) in row.iter().enumerate() {
+                            if value == 0.0 {
+                                continue;
+                            }
+                            let base = if value > 0.0 || self.view != TradeHeatmapView::EntryPnl {
+                                Color32::GREEN
+                            } else {
+                                Color32::RED
+                            };
+                            let t_shade = if max > 0.0 {
+                                (value.abs() / max) as f32
+                            } else {
+                                0.0
+                            };
+                            plot_ui.polygon(
+                                cell(t as f64, t as f64 + 1.0, lo, lo + step)
+                                    .fill_color(shade(base, t_shade))
+                                    .stroke(cell_stroke(false)),
+                            );
+                        }
+                    }
+
+                    plot_ui.pointer_coordinate().and_then(|p| {
+                        let t = p.x.floor();
+                        let price = ((p.y - self.heatmap.price_origin) / step).floor();
+                        (t >= 0.0 && price >= 0.0).then_some((price as usize, t as usize))
+                    })
+                });
+            hovered = response.inner;
+        });
+
+        if let Some((p, t)) = hovered {
+            let (rows, columns) = (grid.len(), grid.first().map_or(0, Vec::len));
+            if p < rows && t < columns {
+                egui::TopBottomPanel::bottom("trade_heatmap_cell").show(ctx, |ui| {
+                    ui.label(format!(
+                        "{:.2} to {:.2} at {}: {} entries, {} exits, P&L of entries ${:.2}",
+                        self.heatmap.price_of(p),
+                        self.heatmap.price_of(p) + step,
+                        self.heatmap.time_label(t),
+                        self.heatmap.entries[p][t],
+                        self.heatmap.exits[p][t],
+                        self.heatmap.entry_pnl[p][t]
+                    ));
+                });
+            }
+        }
+    }
+}
+
+/// Plot a [`TradeHeatmap`] as a grid of price buckets by time of day, shaded by entries,
+/// exits or the P&L of the entries
+pub fn plot_trade_heatmap(heatmap: TradeHeatmap, config: PlotConfig) {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("InkBack Trade Heatmap from Scorsone Enterprises")
+            .with_inner_size([1200.0, 800.0]),
+        ..Default::default()
+    };
+
+    if let Err(e) = eframe::run_native(
+        "InkBack Trade Heatmap",
+        options,
+        Box::new(move |cc| {
+            config.apply_style(&cc.egui_ctx);
+            Ok(Box::new(TradeHeatmapPlot {
+                heatmap,
+                view: TradeHeatmapView::Entries,
+            }))
+        }),
+    ) {
+        eprintln!("Error running egui application: {}", e);
+    }
+}
+
+// Bar chart of R-multiple buckets, losses red and wins green
+struct RHistogram {
+    stats: RStatistics,
+}
+
+impl eframe::App for RHistogram {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let width = self.stats.bucket_width;
+        let bars: Vec<Bar> = self
+            .stats
+            .histogram
+            .iter()
+            .map(|&(start, count)| {
+                let color = if start < 0.0 {
+                    Color32::from_rgb(200, 60, 60)
+                } else {
+                    Color32::from_rgb(60, 170, 90)
+                };
+                Bar::new(start + width / 2.0, count as f64)
+                    .width(width * 0.9)
+                    .fill(color)
+            })
+            .collect();
+
+        egui::TopBottomPanel::top("r_summary").show(ctx, |ui| {
+            ui.label(format!(
+                "{} trades | Expectancy {:.2}R | Avg win {:.2}R | Avg loss {:.2}R",
+                self.stats.trades, self.stats.expectancy, self.stats.avg_win, self.stats.avg_loss
+            ));
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            Plot::new("r_histogram").show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars).name("Trades"));
+            });
+        });
+    }
+}
+
+/// Plot the R-multiple distribution of a backtest's risk-tagged trades
+pub fn plot_r_histogram(stats: RStatistics, config: PlotConfig) {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("InkBack R-Multiples from Scorsone Enterprises")
+            .with_inner_size([1000.0, 600.0]),
+        ..Default::default()
+    };
+
+    if let Err(e) = eframe::run_native(
+        "InkBack R-Multiples",
+        options,
+        Box::new(move |cc| {
+            config.apply_style(&cc.egui_ctx);
+            Ok(Box::new(RHistogram { stats }))
+        }),
+    ) {
+        eprintln!("Error running egui application: {}", e);
+    }
+}
+
+// Mean metric against one parameter at a time, over a band from the worst to the best
+// combination sharing each value
+struct MarginalPlot {
+    metric: String,
+    marginals: Vec<ParameterMarginal>,
+    selected: usize,
+}
+
+impl eframe::App for MarginalPlot {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::TopBottomPanel::top("marginal_parameters").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (i, marginal) in self.marginals.iter().enumerate() {
+                    ui.selectable_value(&mut self.selected, i, &marginal.parameter);
+                }
+            });
+        });
+        let Some(marginal) = self.marginals.get(self.selected) else {
+            return;
+        };
+        let band = Color32::from_rgba_unmultiplied(90, 140, 220, 60);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.label(format!(
+                "{} by {} ({:?})",
+                self.metric,
+                marginal.parameter,
+                marginal.trend()
+            ));
+            Plot::new(("marginal", self.selected))
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    // One trapezoid per step keeps every polygon convex
+                    for w in marginal.points.windows(2) {
+                        plot_ui.polygon(
+                            Polygon::new(PlotPoints::new(vec![
+                                [w[0].value, w[0].min],
+                                [w[1].value, w[1].min],
+                                [w[1].value, w[1].max],
+                                [w[0].value, w[0].max],
+                            ]))
+                            .fill_color(band)
+                            .stroke(Stroke::NONE)
+                            .name("Min to max"),
+                        );
+                    }
+                    let std_line = |sign: f64| {
+                        PlotPoints::new(
+                            marginal
+                                .points
+                                .iter()
+                                .map(|p| [p.value, p.mean + sign * p.std])
+                                .collect(),
+                        )
+                    };
+                    for sign in [-1.0, 1.0] {
+                        plot_ui.line(
+                            Line::new(std_line(sign))
+                                .style(LineStyle::dashed_loose())
+                                .color(Color32::GRAY)
+                                .name("Mean ± std"),
+                        );
+                    }
+                    let means: Vec<[f64; 2]> =
+                        marginal.points.iter().map(|p| [p.value, p.mean]).collect();
+                    plot_ui.line(
+                        Line::new(PlotPoints::new(means.clone()))
+                            .width(2.0)
+                            .name("Mean"),
+                    );
+                    plot_ui.points(Points::new(means).radius(3.0).name("Mean"));
+                });
+        });
+    }
+}
+
+/// Plot each swept parameter's marginal `metric`, such as from
+/// [`crate::marginals::sweep_marginals`], one parameter at a time
+pub fn plot_marginals(metric: &str, marginals: Vec<ParameterMarginal>, config: PlotConfig) {
+    if marginals.is_empty() {
+        println!("No parameter was swept over more than one value");
+        return;
+    }
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("InkBack Parameter Marginals from Scorsone Enterprises")
+            .with_inner_size([1000.0, 600.0]),
+        ..Default::default()
+    };
+
+    let metric = metric.to_string();
+    if let Err(e) = eframe::run_native(
+        "InkBack Parameter Marginals",
+        options,
+        Box::new(move |cc| {
+            config.apply_style(&cc.egui_ctx);
+            Ok(Box::new(MarginalPlot {
+                metric,
+                marginals,
+                selected: 0,
+            }))
+        }),
+    ) {
+        eprintln!("Error running egui application: {}", e);
+    }
+}
+
+// Trade list beside a zoomed chart of the selected trade
+struct TradeReplayViewer {
+    events: Arc<Vec<MarketEvent>>,
+    trades: Vec<Trade>,
+    audit: Vec<AuditRecord>,
+    padding: u64,
+    selected: Option<usize>,
+    replay: Option<TradeReplay>,
+}
+
+impl TradeReplayViewer {
+    fn select(&mut self, index: usize) {
+        self.selected = Some(index);
+        self.replay = replay::replay(&self.events, &self.trades[index], &self.audit, self.padding);
+    }
+}
+
+// Seconds from the trade's entry
+fn seconds_from(entry_ts: u64, ts: u64) -> f64 {
+    (ts as i128 - entry_ts as i128) as f64 / 1e9
+}
+
+impl eframe::App for TradeReplayViewer {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let mut clicked = None;
+        egui::SidePanel::left("trades")
+            .min_width(260.0)
+            .show(ctx, |ui| {
+                ui.heading("Trades");
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, t) in self.trades.iter().enumerate() {
+                        let color = if t.pnl >= 0.0 {
+                            Color32::from_rgb(60, 170, 90)
+                        } else {
+                            Color32::from_rgb(200, 60, 60)
+                        };
+                        let text = egui::RichText::new(format!(
+                            "{}. {} {} ${:.2}",
+                            i + 1,
+                            t.trade_type,
+                            t.entry_date,
+                            t.pnl
+                        ))
+                        .color(color);
+                        if ui
+                            .selectable_label(self.selected == Some(i), text)
+                            .clicked()
+                        {
+                            clicked = Some(i);
+                        }
+                    }
+                });
+            });
+        if let Some(i) = clicked {
+            self.select(i);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let Some(replay) = &self.replay else {
+                ui.label("Select a trade");
+                return;
+            };
+            let t = &replay.trade;
+            let entry_ts = t.entry_ts;
+            ui.label(format!(
+                "{} {:.2} @ {:.4} -> {:.4} | PnL ${:.2} | {:?} | seconds from entry",
+                t.trade_type, t.size, t.entry_price, t.exit_price, t.pnl, t.exit_reason
+            ));
+
+            let height = if replay.underlying.is_empty() {
+                ui.available_height()
+            } else {
+                ui.available_height() * 0.65
+            };
+            Plot::new("replay_price")
+                .legend(Legend::default())
+                .height(height)
+                .link_axis("replay", true, false)
+                .show(ui, |plot_ui| {
+                    let path: Vec<[f64; 2]> = replay
+                        .prices
+                        .iter()
+                        .map(|&(ts, p)| [seconds_from(entry_ts, ts), p])
+                        .collect();
+                    plot_ui.line(Line::new(PlotPoints::new(path)).name("Price"));
+                    if let Some(stop) = replay.stop {
+                        plot_ui.hline(
+                            HLine::new(stop)
+                                .name("Stop")
+                                .color(Color32::from_rgb(200, 60, 60))
+                                .style(LineStyle::dashed_loose()),
+                        );
+                    }
+                    for (i, target) in replay.targets.iter().enumerate() {
+                        plot_ui.hline(
+                            HLine::new(*target)
+                                .name(format!("Target {}R", i + 1))
+                                .color(Color32::from_rgb(60, 170, 90))
+                                .style(LineStyle::dashed_loose()),
+                        );
+                    }
+
+                    let orders: Vec<[f64; 2]> = replay
+                        .orders
+                        .iter()
+                        .filter(|r| matches!(r.event, AuditEvent::Submitted))
+                        .map(|r| [seconds_from(entry_ts, r.ts), r.price])
+                        .collect();
+                    plot_ui.points(
+                        Points::new(orders)
+                            .name("Orders")
+                            .shape(MarkerShape::Diamond)
+                            .radius(3.0),
+                    );
+                    plot_ui.points(
+                        Points::new(vec![[0.0, t.entry_price]])
+                            .name("Entry")
+                            .shape(MarkerShape::Up)
+                            .color(Color32::from_rgb(60, 170, 90))
+                            .radius(6.0),
+                    );
+                    plot_ui.points(
+                        Points::new(vec![[seconds_from(entry_ts, t.exit_ts), t.exit_price]])
+                            .name("Exit")
+                            .shape(MarkerShape::Down)
+                            .color(Color32::from_rgb(200, 60, 60))
+                            .radius(6.0),
+                    );
+                });
+
+            if !replay.underlying.is_empty() {
+                Plot::new("replay_underlying")
+                    .legend(Legend::default())
+                    .link_axis("replay", true, false)
+                    .show(ui, |plot_ui| {
+                        let path: Vec<[f64; 2]> = replay
+                            .underlying
+                            .iter()
+                            .map(|&(ts, p)| [seconds_from(entry_ts, ts), p])
+                            .collect();
+                        plot_ui.line(Line::new(PlotPoints::new(path)).name("Underlying"));
+                    });
+            }
+        });
+    }
+}
+
+/// Browse a backtest's trades, each replayed on a zoomed chart of the events it was
+/// run on with `padding` nanoseconds either side, its fills, orders, 1R stop and
+/// targets, and the underlying for options
+pub fn plot_trade_replays(
+    events: Arc<Vec<MarketEvent>>,
+    result: &BacktestResult,
+    padding: u64,
+    config: PlotConfig,
+) {
+    let mut viewer = TradeReplayViewer {
+        events,
+        trades: result.trades.clone(),
+        audit: result.audit.clone(),
+        padding,
+        selected: None,
+        replay: None,
+    };
+    if !viewer.trades.is_empty() {
+        viewer.select(0);
+    }
+
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("InkBack Trade Replay from Scorsone Enterprises")
+            .with_inner_size([1200.0, 700.0]),
+        ..Default::default()
+    };
+
+    if let Err(e) = eframe::run_native(
+        "InkBack Trade Replay",
+        options,
+        Box::new(move |cc| {
+            config.apply_style(&cc.egui_ctx);
+            Ok(Box::new(viewer))
+        }),
     ) {
         eprintln!("Error running egui application: {}", e);
     }
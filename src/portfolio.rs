@@ -0,0 +1,548 @@
+use crate::backtester::{BacktestResult, Trade, NANOS_PER_DAY};
+use crate::event::{date_string, MarketEvent};
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::ExitReason;
+use crate::symbology::SymbologyMap;
+use crate::utils::fetch::MarketStream;
+use anyhow::Result;
+use futures::StreamExt;
+use std::collections::{BTreeMap, BTreeSet};
+use time::OffsetDateTime;
+
+/// When a [`run_rebalancing_backtest`] asks the strategy for new weights
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RebalanceFrequency {
+    /// At every timestamp
+    EveryBar,
+    /// At the first timestamp of each UTC day, week (from Monday) or month
+    Daily,
+    Weekly,
+    Monthly,
+    /// At the first timestamp of each interval of this many nanoseconds from the epoch
+    Interval(u64),
+}
+
+impl RebalanceFrequency {
+    // Number of the period `ts` falls in
+    fn period(&self, ts: u64) -> u64 {
+        let days = ts / NANOS_PER_DAY;
+        match *self {
+            RebalanceFrequency::EveryBar => ts,
+            RebalanceFrequency::Daily => days,
+            // 1970-01-01 was a Thursday
+            RebalanceFrequency::Weekly => (days + 3) / 7,
+            RebalanceFrequency::Monthly => OffsetDateTime::from_unix_timestamp_nanos(ts as i128)
+                .map_or(days, |dt| dt.year() as u64 * 12 + dt.month() as u64),
+            RebalanceFrequency::Interval(interval) => ts / interval.max(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RebalanceConfig {
+    pub frequency: RebalanceFrequency,
+    /// Rebalance back to the last targets between scheduled dates once any weight has
+    /// drifted this far from its target, e.g. 0.05 for five percentage points
+    pub drift_band: Option<f64>,
+    /// Trades worth less than this are skipped, unless they close a holding
+    pub min_trade_value: f64,
+}
+
+impl Default for RebalanceConfig {
+    fn default() -> Self {
+        Self::new(RebalanceFrequency::Monthly)
+    }
+}
+
+impl RebalanceConfig {
+    pub fn new(frequency: RebalanceFrequency) -> Self {
+        Self {
+            frequency,
+            drift_band: None,
+            min_trade_value: 0.0,
+        }
+    }
+
+    pub fn with_drift_band(mut self, band: f64) -> Self {
+        self.drift_band = Some(band);
+        self
+    }
+
+    pub fn with_min_trade_value(mut self, value: f64) -> Self {
+        self.min_trade_value = value;
+        self
+    }
+}
+
+/// The portfolio as the strategy sees it at a rebalance, marked at the latest prices
+pub struct PortfolioView<'a> {
+    pub ts: u64,
+    pub equity: f64,
+    pub cash: f64,
+    /// Latest price of every instrument seen so far
+    pub prices: &'a BTreeMap<u32, f64>,
+    pub symbology: &'a SymbologyMap,
+    holdings: &'a BTreeMap<u32, Holding>,
+}
+
+impl PortfolioView<'_> {
+    /// Units held, negative when short
+    pub fn units(&self, instrument_id: u32) -> f64 {
+        self.holdings.get(&instrument_id).map_or(0.0, |h| h.units)
+    }
+
+    /// Current share of equity held in the instrument
+    pub fn weight(&self, instrument_id: u32) -> f64 {
+        let price = self.prices.get(&instrument_id).copied().unwrap_or(0.0);
+        let multiplier = multiplier(self.symbology, instrument_id);
+        if self.equity == 0.0 {
+            return 0.0;
+        }
+        self.units(instrument_id) * price * multiplier / self.equity
+    }
+
+    pub fn symbol(&self, instrument_id: u32) -> Option<&str> {
+        self.symbology
+            .symbol_at(instrument_id, self.ts)
+            .or_else(|| {
+                self.symbology
+                    .latest(instrument_id)
+                    .map(|i| i.raw_symbol.as_str())
+            })
+    }
+}
+
+/// A strategy that allocates equity across instruments instead of sending orders
+pub trait AllocationStrategy {
+    /// Called for every event before any rebalance at its timestamp, to keep indicators
+    fn on_event(&mut self, _event: &MarketEvent) {}
+
+    /// Share of equity to hold in each instrument, negative to short. Instruments left
+    /// out are closed. `None` keeps the current holdings.
+    fn target_weights(&mut self, view: &PortfolioView) -> Option<Vec<(u32, f64)>>;
+}
+
+/// The same weights at every rebalance, e.g. a 60/40 portfolio
+#[derive(Debug, Clone)]
+pub struct FixedWeights(pub Vec<(u32, f64)>);
+
+impl AllocationStrategy for FixedWeights {
+    fn target_weights(&mut self, _view: &PortfolioView) -> Option<Vec<(u32, f64)>> {
+        Some(self.0.clone())
+    }
+}
+
+/// An equal share of equity in every instrument priced so far
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EqualWeight;
+
+impl AllocationStrategy for EqualWeight {
+    fn target_weights(&mut self, view: &PortfolioView) -> Option<Vec<(u32, f64)>> {
+        let n = view.prices.len() as f64;
+        Some(view.prices.keys().map(|&id| (id, 1.0 / n)).collect())
+    }
+}
+
+/// One rebalance of a [`PortfolioRun`]
+#[derive(Debug, Clone)]
+pub struct RebalanceRecord {
+    pub ts: u64,
+    pub date: String,
+    pub equity: f64,
+    /// False when the drift band triggered it between scheduled dates
+    pub scheduled: bool,
+    /// Largest distance of a weight from the previous targets before trading
+    pub max_drift: f64,
+    /// Traded value over equity
+    pub turnover: f64,
+    pub costs: f64,
+    pub fills: usize,
+}
+
+pub struct PortfolioRun {
+    /// Equity at every timestamp, and a trade for every reduction of a holding at its
+    /// average cost. Holdings still open at the end are closed as
+    /// [`ExitReason::EndOfData`] trades at the last prices, without costs. Sharpe and
+    /// Sortino are annualized from the equity at each timestamp.
+    pub result: BacktestResult,
+    pub rebalances: Vec<RebalanceRecord>,
+    /// Weights held at the end, before the closing trades
+    pub final_weights: Vec<(u32, f64)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Holding {
+    units: f64,
+    avg_price: f64,
+    entry_ts: u64,
+    // Entry costs not yet charged to a closed trade
+    entry_costs: f64,
+}
+
+fn multiplier(symbology: &SymbologyMap, instrument_id: u32) -> f64 {
+    symbology
+        .latest(instrument_id)
+        .map_or(1.0, |info| info.multiplier)
+}
+
+struct Portfolio<'a> {
+    costs: &'a TransactionCosts,
+    cash: f64,
+    holdings: BTreeMap<u32, Holding>,
+    prices: BTreeMap<u32, f64>,
+    volumes: BTreeMap<u32, f64>,
+    symbology: SymbologyMap,
+    trades: Vec<Trade>,
+    total_costs: f64,
+}
+
+impl Portfolio<'_> {
+    fn equity(&self) -> f64 {
+        self.cash
+            + self
+                .holdings
+                .iter()
+                .map(|(id, h)| {
+                    h.units
+                        * self.prices.get(id).copied().unwrap_or(0.0)
+                        * multiplier(&self.symbology, *id)
+                })
+                .sum::<f64>()
+    }
+
+    fn weights(&self, equity: f64) -> BTreeMap<u32, f64> {
+        self.holdings
+            .iter()
+            .map(|(&id, h)| {
+                let value = h.units * self.prices[&id] * multiplier(&self.symbology, id);
+                (id, if equity != 0.0 { value / equity } else { 0.0 })
+            })
+            .collect()
+    }
+
+    // Trade `delta` units at the latest price, returning the value traded and its costs
+    fn trade(&mut self, ts: u64, id: u32, delta: f64) -> (f64, f64) {
+        let price = self.prices[&id];
+        let volume = self.volumes.get(&id).copied().unwrap_or(0.0);
+        let mult = multiplier(&self.symbology, id);
+        let buy = delta > 0.0;
+        let size = delta.abs();
+        let fill = self.costs.adjust_fill_price(price, size, buy);
+
+        let holding = self.holdings.entry(id).or_default();
+        let closed = if holding.units != 0.0 && (holding.units > 0.0) != buy {
+            size.min(holding.units.abs())
+        } else {
+            0.0
+        };
+        let opened = size - closed;
+        let exit_cost = if closed > 0.0 {
+            self.costs.calculate_exit_cost(price, closed, volume)
+        } else {
+            0.0
+        };
+        let entry_cost = if opened > 0.0 {
+            self.costs.calculate_entry_cost(price, opened, volume)
+        } else {
+            0.0
+        };
+
+        if closed > 0.0 {
+            let long = holding.units > 0.0;
+            let sign = if long { 1.0 } else { -1.0 };
+            let entry_share = holding.entry_costs * closed / holding.units.abs();
+            holding.entry_costs -= entry_share;
+            let gross = (fill - holding.avg_price) * closed * sign * mult;
+            let pnl = gross - entry_share - exit_cost;
+            let basis = holding.avg_price * closed * mult;
+            self.trades.push(Trade {
+                entry_date: date_string(holding.entry_ts),
+                exit_date: date_string(ts),
+                entry_price: holding.avg_price,
+                exit_price: fill,
+                size: closed,
+                pnl,
+                pnl_pct: if basis != 0.0 {
+                    pnl / basis * 100.0
+                } else {
+                    0.0
+                },
+                trade_type: if long { "Long" } else { "Short" }.to_string(),
+                exit_reason: ExitReason::Strategy,
+                transaction_costs: entry_share + exit_cost,
+                gross_pnl: (price - holding.avg_price) * closed * sign * mult,
+                rolled_in: false,
+                lot: 0,
+                r_multiple: None,
+                risk: None,
+                instrument_id: Some(id),
+                entry_ts: holding.entry_ts,
+                exit_ts: ts,
+            });
+            holding.units -= closed * sign;
+            if holding.units.abs() < 1e-12 {
+                *holding = Holding::default();
+            }
+        }
+        if opened > 0.0 {
+            let held = holding.units.abs();
+            if held == 0.0 {
+                holding.entry_ts = ts;
+            }
+            holding.avg_price = (holding.avg_price * held + fill * opened) / (held + opened);
+            holding.units += if buy { opened } else { -opened };
+            holding.entry_costs += entry_cost;
+        }
+        if holding.units == 0.0 {
+            self.holdings.remove(&id);
+        }
+
+        let costs = entry_cost + exit_cost;
+        self.cash -= delta * fill * mult + costs;
+        self.total_costs += costs;
+        (size * price * mult, costs)
+    }
+
+    fn rebalance(
+        &mut self,
+        ts: u64,
+        targets: &BTreeMap<u32, f64>,
+        min_trade_value: f64,
+    ) -> (f64, f64, usize) {
+        let equity = self.equity();
+        let ids: BTreeSet<u32> = targets
+            .keys()
+            .chain(self.holdings.keys())
+            .copied()
+            .filter(|id| self.prices.get(id).is_some_and(|&p| p > 0.0))
+            .collect();
+        let (mut traded, mut costs, mut fills) = (0.0, 0.0, 0);
+        for id in ids {
+            let value_per_unit = self.prices[&id] * multiplier(&self.symbology, id);
+            let target = targets.get(&id).copied().unwrap_or(0.0) * equity / value_per_unit;
+            let held = self.holdings.get(&id).map_or(0.0, |h| h.units);
+            let delta = target - held;
+            let closes = target == 0.0 && held != 0.0;
+            if delta == 0.0 || (!closes && delta.abs() * value_per_unit < min_trade_value) {
+                continue;
+            }
+            let (value, cost) = self.trade(ts, id, delta);
+            traded += value;
+            costs += cost;
+            fills += 1;
+        }
+        (traded, costs, fills)
+    }
+}
+
+/// Backtest an [`AllocationStrategy`] over a stream carrying several instruments, e.g.
+/// daily bars of a few ETFs merged with [`interleave_streams`].
+///
+/// Events are grouped by timestamp. After each group the portfolio is marked at the
+/// latest prices, and at the first group of every rebalance period the strategy's target
+/// weights are traded at those prices with `transaction_costs` applied per instrument.
+/// Between rebalances holdings drift with the prices, and with a drift band they are
+/// brought back to the last targets early once any weight leaves it. Instruments are
+/// keyed by instrument id, valued with the multiplier of their definition when the
+/// stream carries one and 1 otherwise; only cash limits nothing, so weights summing above
+/// one borrow.
+///
+/// [`interleave_streams`]: crate::utils::chain::interleave_streams
+pub async fn run_rebalancing_backtest(
+    mut stream: MarketStream,
+    strategy: &mut dyn AllocationStrategy,
+    transaction_costs: &TransactionCosts,
+    starting_equity: f64,
+    config: &RebalanceConfig,
+    time_range: Option<(u64, u64)>,
+) -> Result<PortfolioRun> {
+    let mut portfolio = Portfolio {
+        costs: transaction_costs,
+        cash: starting_equity,
+        holdings: BTreeMap::new(),
+        prices: BTreeMap::new(),
+        volumes: BTreeMap::new(),
+        symbology: SymbologyMap::new(),
+        trades: Vec::new(),
+        total_costs: 0.0,
+    };
+    let mut curve = vec![starting_equity];
+    let mut sampled = Vec::new();
+    let mut rebalances = Vec::new();
+    let mut targets: Option<BTreeMap<u32, f64>> = None;
+    let mut last_period: Option<u64> = None;
+    let mut group_ts: Option<u64> = None;
+
+    let mut close_group = |portfolio: &mut Portfolio,
+                           strategy: &mut dyn AllocationStrategy,
+                           ts: u64| {
+        let equity = portfolio.equity();
+        let weights = portfolio.weights(equity);
+        let max_drift = targets.as_ref().map_or(0.0, |targets| {
+            targets
+                .keys()
+                .chain(weights.keys())
+                .map(|id| {
+                    (weights.get(id).copied().unwrap_or(0.0)
+                        - targets.get(id).copied().unwrap_or(0.0))
+                    .abs()
+                })
+                .fold(0.0, f64::max)
+        });
+        let period = config.frequency.period(ts);
+        let scheduled = last_period != Some(period);
+        let drifted = config.drift_band.is_some_and(|band| max_drift > band);
+
+        if scheduled {
+            last_period = Some(period);
+            let view = PortfolioView {
+                ts,
+                equity,
+                cash: portfolio.cash,
+                prices: &portfolio.prices,
+                symbology: &portfolio.symbology,
+                holdings: &portfolio.holdings,
+            };
+            if let Some(weights) = strategy.target_weights(&view) {
+                targets = Some(weights.into_iter().collect());
+            }
+        }
+        if let Some(targets) = targets.as_ref().filter(|_| scheduled || drifted) {
+            let (traded, costs, fills) = portfolio.rebalance(ts, targets, config.min_trade_value);
+            if fills > 0 {
+                rebalances.push(RebalanceRecord {
+                    ts,
+                    date: date_string(ts),
+                    equity,
+                    scheduled,
+                    max_drift,
+                    turnover: if equity != 0.0 { traded / equity } else { 0.0 },
+                    costs,
+                    fills,
+                });
+            }
+        }
+        let equity = portfolio.equity();
+        curve.push(equity);
+        sampled.push((ts, equity));
+    };
+
+    while let Some(event) = stream.next().await {
+        let event = event?;
+        if let MarketEvent::Definition(def) = &event {
+            portfolio.symbology.update(def);
+            continue;
+        }
+        let ts = event.timestamp();
+        if let Some((start_ts, end_ts)) = time_range {
+            if ts < start_ts {
+                continue;
+            }
+            if ts >= end_ts {
+                break;
+            }
+        }
+        let price = event.price();
+        let Some(id) = event.get_u64("instrument_id").map(|id| id as u32) else {
+            continue;
+        };
+        if !price.is_finite() || price <= 0.0 {
+            continue;
+        }
+        if let Some(group) = group_ts.filter(|&group| group != ts) {
+            close_group(&mut portfolio, strategy, group);
+        }
+        group_ts = Some(ts);
+        portfolio.prices.insert(id, price);
+        portfolio.volumes.insert(id, event.volume() as f64);
+        strategy.on_event(&event);
+    }
+    if let Some(group) = group_ts {
+        close_group(&mut portfolio, strategy, group);
+    }
+
+    let ending_equity = portfolio.equity();
+    let final_weights = portfolio.weights(ending_equity).into_iter().collect();
+    // Close what is left at the marks, so every holding shows up in the trades
+    let end_ts = group_ts.unwrap_or(0);
+    for (id, holding) in std::mem::take(&mut portfolio.holdings) {
+        let price = portfolio.prices[&id];
+        let long = holding.units > 0.0;
+        let sign = if long { 1.0 } else { -1.0 };
+        let size = holding.units.abs();
+        let mult = multiplier(&portfolio.symbology, id);
+        let gross = (price - holding.avg_price) * size * sign * mult;
+        let basis = holding.avg_price * size * mult;
+        portfolio.trades.push(Trade {
+            entry_date: date_string(holding.entry_ts),
+            exit_date: date_string(end_ts),
+            entry_price: holding.avg_price,
+            exit_price: price,
+            size,
+            pnl: gross - holding.entry_costs,
+            pnl_pct: if basis != 0.0 {
+                (gross - holding.entry_costs) / basis * 100.0
+            } else {
+                0.0
+            },
+            trade_type: if long { "Long" } else { "Short" }.to_string(),
+            exit_reason: ExitReason::EndOfData,
+            transaction_costs: holding.entry_costs,
+            gross_pnl: gross,
+            rolled_in: false,
+            lot: 0,
+            r_multiple: None,
+            risk: None,
+            instrument_id: Some(id),
+            entry_ts: holding.entry_ts,
+            exit_ts: end_ts,
+        });
+    }
+
+    let mut result =
+        BacktestResult::calculate_metrics(starting_equity, ending_equity, curve, portfolio.trades);
+    result.total_transaction_costs = portfolio.total_costs;
+    result.sampled_equity = sampled;
+    result.apply_time_metrics();
+    Ok(PortfolioRun {
+        result,
+        rebalances,
+        final_weights,
+    })
+}
+
+pub fn display_rebalances(run: &PortfolioRun) {
+    let scheduled = run.rebalances.iter().filter(|r| r.scheduled).count();
+    let turnover: f64 = run.rebalances.iter().map(|r| r.turnover).sum();
+    let costs: f64 = run.rebalances.iter().map(|r| r.costs).sum();
+    println!(
+        "\n=== REBALANCES ({} scheduled, {} on drift) ===",
+        scheduled,
+        run.rebalances.len() - scheduled
+    );
+    println!(
+        "Total turnover {:.2}x equity, costs {:.2}, ending equity {:.2} ({:+.2}%)",
+        turnover, costs, run.result.ending_equity, run.result.total_return_pct
+    );
+    println!(
+        "{:<12} {:<10} {:<12} {:<10} {:<10} {:<8}",
+        "Date", "Trigger", "Equity", "Drift", "Turnover", "Costs"
+    );
+    for r in run.rebalances.iter().rev().take(10).rev() {
+        println!(
+            "{:<12} {:<10} {:<12.2} {:<10.4} {:<10.4} {:<8.2}",
+            r.date,
+            if r.scheduled { "schedule" } else { "drift" },
+            r.equity,
+            r.max_drift,
+            r.turnover,
+            r.costs
+        );
+    }
+    let weights: Vec<String> = run
+        .final_weights
+        .iter()
+        .map(|(id, w)| format!("{}: {:.1}%", id, w * 100.0))
+        .collect();
+    println!("Final weights: {}", weights.join(", "));
+}
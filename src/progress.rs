@@ -0,0 +1,151 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Events between clock reads, so reporting costs nothing measurable per event
+const CHECK_EVERY: u64 = 4096;
+
+/// State of a running backtest, handed to the progress callback
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunProgress {
+    /// Events processed so far, definitions excluded
+    pub events: u64,
+    /// Events per second since the run started
+    pub events_per_sec: f64,
+    pub elapsed: Duration,
+    /// Timestamp of the last event processed
+    pub ts: u64,
+    /// Share of the time range behind `ts`, `None` when the run has no known range
+    pub fraction: Option<f64>,
+    /// Wall time left at the pace so far, `None` without a fraction
+    pub remaining: Option<Duration>,
+    /// Equity marked with the open position
+    pub equity: f64,
+    /// Main position and hedge legs currently open
+    pub open_positions: usize,
+}
+
+impl fmt::Display for RunProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} events ({:.0}/s)", self.events, self.events_per_sec)?;
+        if let Some(fraction) = self.fraction {
+            write!(f, ", {:.1}% through", fraction * 100.0)?;
+        }
+        if let Some(remaining) = self.remaining {
+            write!(f, ", ~{}s left", remaining.as_secs())?;
+        }
+        write!(
+            f,
+            ", equity {:.2}, {} open",
+            self.equity, self.open_positions
+        )
+    }
+}
+
+pub type ProgressCallback = dyn Fn(&RunProgress) + Send + Sync;
+
+/// Periodic reports from inside a single backtest, so a long run can be told apart from
+/// a stuck one and its completion estimated
+#[derive(Clone)]
+pub struct ProgressConfig {
+    /// Wall time between reports
+    pub interval: Duration,
+    /// Time range the fraction is measured against when the run itself has none, e.g.
+    /// the dates a file was fetched for
+    pub span: Option<(u64, u64)>,
+    pub callback: Arc<ProgressCallback>,
+}
+
+impl ProgressConfig {
+    pub fn new(
+        interval: Duration,
+        callback: impl Fn(&RunProgress) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            interval,
+            span: None,
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Print a line per report
+    pub fn printing(interval: Duration) -> Self {
+        Self::new(interval, |progress| println!("Backtest: {}", progress))
+    }
+
+    pub fn with_span(mut self, start_ts: u64, end_ts: u64) -> Self {
+        self.span = Some((start_ts, end_ts));
+        self
+    }
+}
+
+impl fmt::Debug for ProgressConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressConfig")
+            .field("interval", &self.interval)
+            .field("span", &self.span)
+            .finish_non_exhaustive()
+    }
+}
+
+// Counts events and calls back once per interval
+pub(crate) struct ProgressMeter {
+    config: ProgressConfig,
+    span: Option<(u64, u64)>,
+    started: Instant,
+    last_report: Instant,
+    events: u64,
+}
+
+impl ProgressMeter {
+    pub(crate) fn new(config: &ProgressConfig, time_range: Option<(u64, u64)>) -> Self {
+        let now = Instant::now();
+        Self {
+            config: config.clone(),
+            span: time_range.or(config.span),
+            started: now,
+            last_report: now,
+            events: 0,
+        }
+    }
+
+    /// Count an event at `ts`, reporting when the interval has passed. The equity and open
+    /// positions are only computed for a report.
+    pub(crate) fn tick(&mut self, ts: u64, state: impl FnOnce() -> (f64, usize)) {
+        self.events += 1;
+        if !self.events.is_multiple_of(CHECK_EVERY)
+            || self.last_report.elapsed() < self.config.interval
+        {
+            return;
+        }
+        self.report(ts, state);
+    }
+
+    /// Report the end of the run regardless of the interval
+    pub(crate) fn finish(&mut self, ts: u64, state: impl FnOnce() -> (f64, usize)) {
+        self.report(ts, state);
+    }
+
+    fn report(&mut self, ts: u64, state: impl FnOnce() -> (f64, usize)) {
+        self.last_report = Instant::now();
+        let elapsed = self.started.elapsed();
+        let fraction = self
+            .span
+            .filter(|(start, end)| end > start)
+            .map(|(start, end)| (ts.saturating_sub(start) as f64 / (end - start) as f64).min(1.0));
+        let remaining = fraction
+            .filter(|&f| f > 0.0)
+            .map(|f| elapsed.mul_f64((1.0 - f) / f));
+        let (equity, open_positions) = state();
+        (self.config.callback)(&RunProgress {
+            events: self.events,
+            events_per_sec: self.events as f64 / elapsed.as_secs_f64().max(1e-9),
+            elapsed,
+            ts,
+            fraction,
+            remaining,
+            equity,
+            open_positions,
+        });
+    }
+}
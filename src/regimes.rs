@@ -0,0 +1,299 @@
+use crate::backtester::{BacktestResult, NANOS_PER_DAY};
+use crate::event::MarketEvent;
+use serde::{Deserialize, Serialize};
+
+/// Settings for labeling each day of the underlying with its market regime
+#[derive(Debug, Clone)]
+pub struct RegimeConfig {
+    /// Days of close-to-close returns in the realized volatility
+    pub vol_window: usize,
+    /// Wilder period of the ADX
+    pub adx_period: usize,
+    /// ADX at or above which a day is trending
+    pub adx_trend: f64,
+}
+
+impl Default for RegimeConfig {
+    fn default() -> Self {
+        Self {
+            vol_window: 20,
+            adx_period: 14,
+            adx_trend: 25.0,
+        }
+    }
+}
+
+/// Realized volatility tercile of a day within the backtest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VolRegime {
+    Low,
+    Mid,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trend {
+    Trending,
+    Choppy,
+}
+
+/// Regime of one day of the underlying
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegimeDay {
+    /// UTC day, nanoseconds since the epoch divided by a day
+    pub day: u64,
+    /// Annualized realized volatility over the trailing window
+    pub realized_vol: f64,
+    pub adx: f64,
+    pub vol: VolRegime,
+    pub trend: Trend,
+}
+
+// Daily high, low and close of the underlying
+#[derive(Debug, Clone, Copy)]
+struct DailyBar {
+    day: u64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+/// Builds daily bars of the traded underlying from the event stream
+#[derive(Debug, Default)]
+pub(crate) struct DailyBars {
+    bars: Vec<DailyBar>,
+}
+
+impl DailyBars {
+    pub(crate) fn observe(&mut self, event: &MarketEvent) {
+        // For options, the underlying carried on each trade rather than the premium
+        let (high, low, close) = match event {
+            MarketEvent::OptionTrade(m) => {
+                (m.underlying_price, m.underlying_price, m.underlying_price)
+            }
+            MarketEvent::Definition(_) => return,
+            _ => (event.high(), event.low(), event.price()),
+        };
+        if !(close.is_finite() && close > 0.0) {
+            return;
+        }
+        let day = event.timestamp() / NANOS_PER_DAY;
+        match self.bars.last_mut() {
+            Some(bar) if bar.day == day => {
+                bar.high = bar.high.max(high);
+                bar.low = bar.low.min(low);
+                bar.close = close;
+            }
+            _ => self.bars.push(DailyBar {
+                day,
+                high,
+                low,
+                close,
+            }),
+        }
+    }
+
+    /// Label every day with enough history for both indicators.
+    ///
+    /// Volatility terciles are taken over the whole backtest, so labels describe the
+    /// sample and are not tradable signals.
+    pub(crate) fn label(self, config: &RegimeConfig) -> Vec<RegimeDay> {
+        let bars = self.bars;
+        let n = bars.len();
+        let vols = realized_vol(&bars, config.vol_window.max(2));
+        let adx = adx(&bars, config.adx_period.max(1));
+
+        let mut sorted: Vec<f64> = vols.iter().flatten().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        if sorted.is_empty() {
+            return Vec::new();
+        }
+        let (low_cut, high_cut) = (
+            sorted[sorted.len() / 3],
+            sorted[(2 * sorted.len() / 3).min(sorted.len() - 1)],
+        );
+
+        (0..n)
+            .filter_map(|i| {
+                let (vol, adx) = (vols[i]?, adx[i]?);
+                Some(RegimeDay {
+                    day: bars[i].day,
+                    realized_vol: vol,
+                    adx,
+                    vol: if vol < low_cut {
+                        VolRegime::Low
+                    } else if vol < high_cut {
+                        VolRegime::Mid
+                    } else {
+                        VolRegime::High
+                    },
+                    trend: if adx >= config.adx_trend {
+                        Trend::Trending
+                    } else {
+                        Trend::Choppy
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+// Annualized standard deviation of the trailing `window` daily log returns
+fn realized_vol(bars: &[DailyBar], window: usize) -> Vec<Option<f64>> {
+    let returns: Vec<f64> = bars
+        .windows(2)
+        .map(|w| (w[1].close / w[0].close).ln())
+        .collect();
+    (0..bars.len())
+        .map(|i| {
+            // Returns ending at day i are returns[..i]
+            let window = returns.get(i.checked_sub(window)?..i)?;
+            let mean = window.iter().sum::<f64>() / window.len() as f64;
+            let var =
+                window.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (window.len() - 1) as f64;
+            Some((var * 252.0).sqrt())
+        })
+        .collect()
+}
+
+// Wilder's average directional index
+fn adx(bars: &[DailyBar], period: usize) -> Vec<Option<f64>> {
+    let p = period as f64;
+    let mut out = vec![None; bars.len()];
+    let (mut tr, mut plus, mut minus) = (0.0, 0.0, 0.0);
+    let mut dx_sum = 0.0;
+    let mut adx: Option<f64> = None;
+
+    for i in 1..bars.len() {
+        let (cur, prev) = (bars[i], bars[i - 1]);
+        let up = cur.high - prev.high;
+        let down = prev.low - cur.low;
+        let true_range = (cur.high - cur.low)
+            .max((cur.high - prev.close).abs())
+            .max((cur.low - prev.close).abs());
+        let plus_dm = if up > down && up > 0.0 { up } else { 0.0 };
+        let minus_dm = if down > up && down > 0.0 { down } else { 0.0 };
+
+        if i <= period {
+            tr += true_range;
+            plus += plus_dm;
+            minus += minus_dm;
+            if i < period {
+                continue;
+            }
+        } else {
+            tr = tr - tr / p + true_range;
+            plus = plus - plus / p + plus_dm;
+            minus = minus - minus / p + minus_dm;
+        }
+
+        let (plus_di, minus_di) = if tr > 0.0 {
+            (100.0 * plus / tr, 100.0 * minus / tr)
+        } else {
+            (0.0, 0.0)
+        };
+        let dx = if plus_di + minus_di > 0.0 {
+            100.0 * (plus_di - minus_di).abs() / (plus_di + minus_di)
+        } else {
+            0.0
+        };
+
+        // The first ADX averages `period` DX values, later ones are Wilder smoothed
+        let k = i - period + 1;
+        adx = match adx {
+            Some(prev) => Some((prev * (p - 1.0) + dx) / p),
+            None if k < period => {
+                dx_sum += dx;
+                None
+            }
+            None => Some((dx_sum + dx) / p),
+        };
+        out[i] = adx;
+    }
+    out
+}
+
+/// Performance of the trades entered in one regime
+#[derive(Debug, Clone)]
+pub struct RegimeStats {
+    pub vol: VolRegime,
+    pub trend: Trend,
+    /// Labeled days in this regime
+    pub days: usize,
+    pub trades: usize,
+    pub win_rate: f64,
+    pub total_pnl: f64,
+    pub avg_pnl: f64,
+    pub profit_factor: f64,
+}
+
+/// Break the result's trades down by the regime of their entry day, one row per
+/// volatility tercile and trend. Trades entered on unlabeled days are left out.
+pub fn regime_breakdown(result: &BacktestResult) -> Vec<RegimeStats> {
+    let regime_of = |ts: u64| {
+        let day = ts / NANOS_PER_DAY;
+        result
+            .regimes
+            .binary_search_by_key(&day, |r| r.day)
+            .ok()
+            .map(|i| (result.regimes[i].vol, result.regimes[i].trend))
+    };
+
+    let mut rows = Vec::new();
+    for vol in [VolRegime::Low, VolRegime::Mid, VolRegime::High] {
+        for trend in [Trend::Trending, Trend::Choppy] {
+            let pnls: Vec<f64> = result
+                .trades
+                .iter()
+                .filter(|t| t.pnl.is_finite() && regime_of(t.entry_ts) == Some((vol, trend)))
+                .map(|t| t.pnl)
+                .collect();
+            let wins: f64 = pnls.iter().filter(|p| **p > 0.0).sum();
+            let losses: f64 = -pnls.iter().filter(|p| **p < 0.0).sum::<f64>();
+            let total: f64 = pnls.iter().sum();
+            let count = pnls.len();
+            rows.push(RegimeStats {
+                vol,
+                trend,
+                days: result
+                    .regimes
+                    .iter()
+                    .filter(|r| r.vol == vol && r.trend == trend)
+                    .count(),
+                trades: count,
+                win_rate: if count > 0 {
+                    pnls.iter().filter(|p| **p > 0.0).count() as f64 / count as f64 * 100.0
+                } else {
+                    0.0
+                },
+                total_pnl: total,
+                avg_pnl: if count > 0 { total / count as f64 } else { 0.0 },
+                profit_factor: if losses > 0.0 {
+                    wins / losses
+                } else if wins > 0.0 {
+                    f64::INFINITY
+                } else {
+                    0.0
+                },
+            });
+        }
+    }
+    rows
+}
+
+pub fn display_regime_breakdown(result: &BacktestResult) {
+    println!("\n=== PERFORMANCE BY REGIME ===");
+    for row in regime_breakdown(result) {
+        println!(
+            "{:?} vol, {:?}: Days: {}, Trades: {}, WR: {:.1}%, PnL: ${:.2}, Avg: ${:.2}, PF: {:.2}",
+            row.vol,
+            row.trend,
+            row.days,
+            row.trades,
+            row.win_rate,
+            row.total_pnl,
+            row.avg_pnl,
+            row.profit_factor
+        );
+    }
+}
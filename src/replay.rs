@@ -0,0 +1,150 @@
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::backtester::{BacktestResult, Trade};
+use crate::event::MarketEvent;
+
+/// One trade with the market around it, rebuilt from the events it was run on
+#[derive(Debug, Clone)]
+pub struct TradeReplay {
+    pub trade: Trade,
+    /// Timestamp and price of the traded instrument from `padding` before the entry to
+    /// `padding` after the exit
+    pub prices: Vec<(u64, f64)>,
+    /// The underlying over the same window, empty unless the trade was an option
+    pub underlying: Vec<(u64, f64)>,
+    /// Audit records of the main position within the window, empty unless the run had
+    /// [`BacktestOptions::audit`] set
+    ///
+    /// [`BacktestOptions::audit`]: crate::backtester::BacktestOptions::audit
+    pub orders: Vec<AuditRecord>,
+    /// Stop at one initial risk against the entry, for entries tagged with a risk
+    pub stop: Option<f64>,
+    /// Targets at one and two initial risks in favor of the entry
+    pub targets: Vec<f64>,
+}
+
+/// Rebuild the `index`th trade of `result` from the events it was run on, e.g.
+/// [`WarmSession::events`], with `padding` nanoseconds of context on each side
+///
+/// [`WarmSession::events`]: crate::research::WarmSession::events
+pub fn replay_trade(
+    events: &[MarketEvent],
+    result: &BacktestResult,
+    index: usize,
+    padding: u64,
+) -> Option<TradeReplay> {
+    replay(events, result.trades.get(index)?, &result.audit, padding)
+}
+
+pub(crate) fn replay(
+    events: &[MarketEvent],
+    trade: &Trade,
+    audit: &[AuditRecord],
+    padding: u64,
+) -> Option<TradeReplay> {
+    let trade = trade.clone();
+    let (from, to) = (
+        trade.entry_ts.saturating_sub(padding),
+        trade.exit_ts.saturating_add(padding),
+    );
+    let start = events.partition_point(|e| e.timestamp() < from);
+    let end = events.partition_point(|e| e.timestamp() <= to);
+    let window = events.get(start..end.max(start))?;
+
+    let instrument = |e: &MarketEvent| e.get_u64("instrument_id").map(|id| id as u32);
+    let is_option = window
+        .iter()
+        .any(|e| matches!(e, MarketEvent::OptionTrade(_)) && instrument(e) == trade.instrument_id);
+
+    let mut prices = Vec::new();
+    let mut underlying = Vec::new();
+    for event in window {
+        let ts = event.timestamp();
+        match event {
+            MarketEvent::Definition(_) => {}
+            MarketEvent::OptionTrade(m) if is_option => {
+                underlying.push((ts, m.underlying_price));
+                if Some(m.instrument_id) == trade.instrument_id {
+                    prices.push((ts, m.price));
+                }
+            }
+            _ if is_option => underlying.push((ts, event.price())),
+            _ if trade.instrument_id.is_none() || instrument(event) == trade.instrument_id => {
+                prices.push((ts, event.price()))
+            }
+            _ => {}
+        }
+    }
+
+    let orders = audit
+        .iter()
+        .filter(|r| r.ts >= from && r.ts <= to && r.instrument.is_none())
+        .cloned()
+        .collect();
+
+    let long = trade.trade_type == "Long";
+    let direction = if long { 1.0 } else { -1.0 };
+    let risk = trade.risk.filter(|r| *r > 0.0);
+    Some(TradeReplay {
+        stop: risk.map(|r| trade.entry_price - direction * r),
+        targets: risk
+            .map(|r| {
+                vec![
+                    trade.entry_price + direction * r,
+                    trade.entry_price + direction * 2.0 * r,
+                ]
+            })
+            .unwrap_or_default(),
+        prices,
+        underlying,
+        orders,
+        trade,
+    })
+}
+
+/// Print a replayed trade's fills, levels and the orders placed while it was open
+pub fn display_trade_replay(replay: &TradeReplay) {
+    let t = &replay.trade;
+    println!("\n=== TRADE REPLAY ===");
+    println!(
+        "{} {:.2} @ {:.4} ({}) -> {:.4} ({}) | PnL: ${:.2} | Exit: {:?}",
+        t.trade_type,
+        t.size,
+        t.entry_price,
+        t.entry_date,
+        t.exit_price,
+        t.exit_date,
+        t.pnl,
+        t.exit_reason
+    );
+    let (low, high) = replay
+        .prices
+        .iter()
+        .filter(|(ts, _)| *ts >= t.entry_ts && *ts <= t.exit_ts)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &(_, p)| {
+            (lo.min(p), hi.max(p))
+        });
+    if low.is_finite() {
+        println!("Range while held: {:.4} - {:.4}", low, high);
+    }
+    if let Some(stop) = replay.stop {
+        println!(
+            "Stop (1R): {:.4} | Targets (1R, 2R): {:?}",
+            stop, replay.targets
+        );
+    }
+    if let (Some(first), Some(last)) = (replay.underlying.first(), replay.underlying.last()) {
+        println!("Underlying: {:.2} -> {:.2}", first.1, last.1);
+    }
+    for record in &replay.orders {
+        let detail = match &record.event {
+            AuditEvent::Filled { fill_price, .. } | AuditEvent::PartialFill { fill_price, .. } => {
+                format!("filled @ {:.4}", fill_price)
+            }
+            other => format!("{:?}", other),
+        };
+        println!(
+            "  {} {:?} {:?} @ {:.4}: {}",
+            record.ts, record.origin, record.order_type, record.price, detail
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::event::{EventKind, MarketEvent};
+use crate::utils::fetch::MarketStream;
+use anyhow::Result;
+use futures::{stream, StreamExt};
+
+// Events inspected for one the strategy can use before giving up on the data
+const SCAN_LIMIT: usize = 100_000;
+
+/// Event variants and fields a strategy needs from the data, checked before a backtest
+/// runs so a mismatch fails fast instead of never trading
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventRequirements {
+    /// Variants the strategy trades on, any one of them suffices. Empty accepts every
+    /// variant except definitions.
+    pub variants: Vec<EventKind>,
+    /// Fields an event of those variants must carry, e.g. `"underlying_bid"`
+    pub fields: Vec<&'static str>,
+}
+
+impl EventRequirements {
+    pub fn with_variant(mut self, kind: EventKind) -> Self {
+        self.variants.push(kind);
+        self
+    }
+
+    pub fn with_field(mut self, name: &'static str) -> Self {
+        self.fields.push(name);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty() && self.fields.is_empty()
+    }
+
+    fn accepts_kind(&self, kind: EventKind) -> bool {
+        if self.variants.is_empty() {
+            kind != EventKind::Definition
+        } else {
+            self.variants.contains(&kind)
+        }
+    }
+
+    /// Whether `event` is one the strategy can work with
+    pub fn accepts(&self, event: &MarketEvent) -> bool {
+        self.accepts_kind(event.kind()) && self.fields.iter().all(|f| event.has(f))
+    }
+}
+
+/// Check that `events` can satisfy `requirements` by reading ahead to the first event
+/// they accept, returning a stream that replays what was read followed by the rest
+pub(crate) async fn verify(
+    requirements: &EventRequirements,
+    mut events: MarketStream,
+) -> Result<MarketStream> {
+    if requirements.is_empty() {
+        return Ok(events);
+    }
+
+    let mut read = Vec::new();
+    // First event of each variant, to say what the data does carry
+    let mut samples: Vec<MarketEvent> = Vec::new();
+    let mut satisfied = false;
+    while read.len() < SCAN_LIMIT {
+        let Some(event) = events.next().await else {
+            break;
+        };
+        let event = event?;
+        satisfied = requirements.accepts(&event);
+        if !samples.iter().any(|s| s.kind() == event.kind()) {
+            samples.push(event.clone());
+        }
+        read.push(event);
+        if satisfied {
+            break;
+        }
+    }
+
+    if !satisfied {
+        return Err(anyhow::anyhow!(
+            "The data cannot satisfy the strategy: {}",
+            describe_mismatch(requirements, &samples, read.len())
+        ));
+    }
+    Ok(Box::pin(
+        stream::iter(read.into_iter().map(Ok)).chain(events),
+    ))
+}
+
+fn describe_mismatch(
+    requirements: &EventRequirements,
+    samples: &[MarketEvent],
+    inspected: usize,
+) -> String {
+    let needed = if requirements.variants.is_empty() {
+        "market".to_string()
+    } else {
+        requirements
+            .variants
+            .iter()
+            .map(|k| format!("{:?}", k))
+            .collect::<Vec<_>>()
+            .join(" or ")
+    };
+    let with_fields = if requirements.fields.is_empty() {
+        String::new()
+    } else {
+        format!(" carrying {}", requirements.fields.join(", "))
+    };
+    if samples.is_empty() {
+        return format!(
+            "it needs {} events{}, but the data is empty",
+            needed, with_fields
+        );
+    }
+
+    let found: Vec<String> = samples
+        .iter()
+        .map(|sample| {
+            let missing: Vec<&str> = requirements
+                .fields
+                .iter()
+                .copied()
+                .filter(|f| !sample.has(f))
+                .collect();
+            if requirements.accepts_kind(sample.kind()) && !missing.is_empty() {
+                format!("{:?} without {}", sample.kind(), missing.join(", "))
+            } else {
+                format!("{:?}", sample.kind())
+            }
+        })
+        .collect();
+    format!(
+        "it needs {} events{}, but the first {} events are only {}",
+        needed,
+        with_fields,
+        inspected,
+        found.join(", ")
+    )
+}
@@ -0,0 +1,205 @@
+use crate::backtester::{run_backtest_on_stream, BacktestOptions, BacktestResult};
+use crate::event::MarketEvent;
+use crate::holdout::Holdout;
+use crate::plot::{self, PlotConfig, PlotSender};
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::{Strategy, StrategyParams};
+use crate::utils::fetch::{self, BacktestManager, MarketStream};
+use crate::utils::snapshot::{fingerprint_data, DataFingerprint};
+use crate::InkBackSchema;
+use anyhow::{anyhow, Result};
+use databento::dbn::Schema;
+use futures::{stream, StreamExt};
+use std::io::Write;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// A dataset decoded once and kept in memory, so one strategy can be re-run as fast as
+/// the engine allows while its parameters are tweaked.
+///
+/// The whole dataset must fit in memory. Engine services such as VWAP and rolling
+/// volatility are rebuilt on every run since they depend on the options.
+pub struct WarmSession {
+    events: Arc<Vec<MarketEvent>>,
+    data_files: Vec<DataFingerprint>,
+    symbol: String,
+    custom_schema: Option<InkBackSchema>,
+    transaction_costs: TransactionCosts,
+    starting_equity: f64,
+    exposure: f64,
+    time_range: Option<(u64, u64)>,
+    options: BacktestOptions,
+    holdout: Option<Holdout>,
+}
+
+impl WarmSession {
+    /// Decode every event of the manager's data into memory
+    pub async fn load(
+        backtest_manager: &BacktestManager,
+        symbol: &str,
+        schema: Schema,
+        custom_schema: Option<InkBackSchema>,
+        transaction_costs: TransactionCosts,
+        starting_equity: f64,
+        exposure: f64,
+    ) -> Result<Self> {
+        if backtest_manager.data_path.is_empty() {
+            return Err(anyhow!("No data path provided"));
+        }
+        let data_files = fingerprint_data(&backtest_manager.data_path)?;
+        let mut data = fetch::get_data_stream(&backtest_manager.data_path, schema).await?;
+        // A holdout never enters memory
+        let locked_at = backtest_manager.holdout.as_ref().map(|h| h.start_ts);
+        let mut events = Vec::new();
+        while let Some(event) = data.next().await {
+            let event = event?;
+            if locked_at.is_some_and(|ts| event.timestamp() >= ts) {
+                break;
+            }
+            events.push(event);
+        }
+        println!("Loaded {} events for {} into memory", events.len(), symbol);
+
+        Ok(Self {
+            events: Arc::new(events),
+            data_files,
+            symbol: symbol.to_string(),
+            custom_schema,
+            transaction_costs,
+            starting_equity,
+            exposure,
+            time_range: None,
+            options: BacktestOptions::default(),
+            holdout: backtest_manager.holdout.clone(),
+        })
+    }
+
+    pub fn with_options(mut self, options: BacktestOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_time_range(mut self, time_range: Option<(u64, u64)>) -> Self {
+        self.time_range = time_range;
+        self
+    }
+
+    pub fn events(&self) -> &[MarketEvent] {
+        &self.events
+    }
+
+    pub fn options(&self) -> &BacktestOptions {
+        &self.options
+    }
+
+    /// Browse the trades of a run on this session's events, see
+    /// [`plot::plot_trade_replays`]
+    pub fn plot_trades(&self, result: &BacktestResult, padding: u64, config: PlotConfig) {
+        plot::plot_trade_replays(Arc::clone(&self.events), result, padding, config);
+    }
+
+    /// Backtest `strategy` over the in-memory events
+    pub async fn run(&self, strategy: &mut dyn Strategy) -> Result<BacktestResult> {
+        self.run_events(Arc::clone(&self.events), strategy).await
+    }
+
+    /// Backtest `strategy` over other events with the session's settings, e.g. a
+    /// permutation of its own
+    pub(crate) async fn run_events(
+        &self,
+        events: Arc<Vec<MarketEvent>>,
+        strategy: &mut dyn Strategy,
+    ) -> Result<BacktestResult> {
+        let time_range = match &self.holdout {
+            Some(holdout) => holdout.in_sample(self.time_range)?,
+            None => self.time_range,
+        };
+        let stream: MarketStream = Box::pin(stream::iter(
+            (0..events.len()).map(move |i| Ok(events[i].clone())),
+        ));
+        let mut result = run_backtest_on_stream(
+            &self.symbol,
+            stream,
+            strategy,
+            self.transaction_costs.clone(),
+            self.starting_equity,
+            self.exposure,
+            self.custom_schema.clone(),
+            time_range,
+            &self.options,
+        )
+        .await?;
+        result.data_files = self.data_files.clone();
+        Ok(result)
+    }
+
+    /// Prompt for parameter changes on stdin and re-run after each one, starting from
+    /// `params`. Each run is summarized on stdout and, with `plot`, added to the `tab` tab.
+    ///
+    /// Commands: `key=value` sets a parameter and runs, `run` runs again, `show` lists
+    /// the parameters and `quit` returns the last parameters.
+    pub async fn interactive<F>(
+        &self,
+        mut params: StrategyParams,
+        strategy_constructor: F,
+        plot: Option<(&PlotSender, &str)>,
+    ) -> Result<StrategyParams>
+    where
+        F: Fn(&StrategyParams) -> Result<Box<dyn Strategy>>,
+    {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut run = 0;
+        let mut dirty = true;
+
+        loop {
+            if dirty {
+                run += 1;
+                match strategy_constructor(&params) {
+                    Ok(mut strategy) => match self.run(strategy.as_mut()).await {
+                        Ok(result) => {
+                            let label =
+                                format!("Run_{} [{}]", run, params.to_string_representation());
+                            println!(
+                                "{}: Ret: {:.2}%, DD: {:.2}%, Sharpe: {:.2}, WR: {:.1}%, Trades: {}",
+                                label,
+                                result.total_return_pct,
+                                result.max_drawdown_pct,
+                                result.sharpe_ratio,
+                                result.win_rate,
+                                result.total_trades
+                            );
+                            if let Some((plot, tab)) = plot {
+                                plot.add_curve(tab, &label, result.equity_curve);
+                            }
+                        }
+                        Err(e) => eprintln!("Backtest failed: {}", e),
+                    },
+                    Err(e) => eprintln!("Invalid parameters: {}", e),
+                }
+                dirty = false;
+            }
+
+            print!("> ");
+            std::io::stdout().flush()?;
+            let Some(line) = lines.next_line().await? else {
+                return Ok(params);
+            };
+            match line.trim() {
+                "" => {}
+                "quit" | "q" => return Ok(params),
+                "run" => dirty = true,
+                "show" => println!("{}", params.to_string_representation()),
+                command => match command.split_once('=') {
+                    Some((key, value)) => match value.trim().parse::<f64>() {
+                        Ok(value) => {
+                            params.insert(key.trim(), value);
+                            dirty = true;
+                        }
+                        Err(_) => eprintln!("Not a number: {}", value.trim()),
+                    },
+                    None => eprintln!("Expected key=value, run, show or quit"),
+                },
+            }
+        }
+    }
+}
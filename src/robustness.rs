@@ -0,0 +1,328 @@
+use crate::backtester::BacktestResult;
+use crate::event::MarketEvent;
+use crate::research::WarmSession;
+use crate::seeds::{SeededRng, NOISE, START_OFFSET};
+use crate::strategy::{Strategy, StrategyParams};
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+
+const DAY_NS: u64 = 86_400_000_000_000;
+
+/// Where the later starts of a start-sensitivity test fall
+#[derive(Debug, Clone, PartialEq)]
+pub enum StartOffsets {
+    /// `runs` starts drawn uniformly within `max_offset` nanoseconds of the first event
+    Random { runs: usize, max_offset: u64 },
+    /// One start per entry, after dropping that many days from the front
+    DropDays(Vec<u32>),
+}
+
+/// One run from a later start
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartRun {
+    /// Nanoseconds of data dropped from the front
+    pub offset: u64,
+    pub metric: f64,
+    pub total_return_pct: f64,
+    pub total_trades: usize,
+}
+
+/// A strategy's metric on the full data against the same metric from later starts
+#[derive(Debug, Clone)]
+pub struct StartSensitivity {
+    pub full: f64,
+    /// Runs in offset order
+    pub runs: Vec<StartRun>,
+}
+
+impl StartSensitivity {
+    fn metrics(&self) -> impl Iterator<Item = f64> + '_ {
+        self.runs.iter().map(|r| r.metric).filter(|m| m.is_finite())
+    }
+
+    pub fn mean(&self) -> f64 {
+        let n = self.metrics().count();
+        self.metrics().sum::<f64>() / n.max(1) as f64
+    }
+
+    /// Sample standard deviation of the later starts' metric
+    pub fn std_dev(&self) -> f64 {
+        let n = self.metrics().count();
+        if n < 2 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        (self.metrics().map(|m| (m - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+    }
+
+    pub fn min(&self) -> f64 {
+        self.metrics().fold(f64::NAN, f64::min)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.metrics().fold(f64::NAN, f64::max)
+    }
+
+    /// Whether the full run beats every later start, so its result may hinge on the
+    /// earliest trades
+    pub fn hinges_on_start(&self) -> bool {
+        self.runs.iter().any(|r| r.metric.is_finite()) && self.full > self.max()
+    }
+}
+
+/// The session's events with everything priced before `start` dropped. Definitions are
+/// kept so the instruments stay known.
+fn starting_at(events: &[MarketEvent], start: u64) -> Vec<MarketEvent> {
+    events
+        .iter()
+        .filter(|e| matches!(e, MarketEvent::Definition(_)) || e.timestamp() >= start)
+        .cloned()
+        .collect()
+}
+
+/// Run the strategy on the session's data and again from each later start in
+/// `offsets`, comparing `metric` across starts.
+///
+/// Random offsets draw from the [`START_OFFSET`] component of the session's seeds, so a
+/// test is reproduced exactly by the same seeds.
+pub async fn start_sensitivity<F>(
+    session: &WarmSession,
+    params: &StrategyParams,
+    strategy_constructor: F,
+    offsets: StartOffsets,
+    metric: fn(&BacktestResult) -> f64,
+) -> Result<StartSensitivity>
+where
+    F: Fn(&StrategyParams) -> Result<Box<dyn Strategy>>,
+{
+    let mut priced = session
+        .events()
+        .iter()
+        .filter(|e| !matches!(e, MarketEvent::Definition(_)))
+        .map(MarketEvent::timestamp);
+    let first = priced
+        .next()
+        .ok_or_else(|| anyhow!("Start sensitivity needs priced events"))?;
+    let last = priced.next_back().unwrap_or(first);
+
+    let mut points: Vec<u64> = match offsets {
+        StartOffsets::Random { runs, max_offset } => {
+            let mut rng = session.options().seeds.rng(START_OFFSET);
+            (0..runs)
+                .map(|_| (rng.next_f64() * max_offset as f64) as u64)
+                .collect()
+        }
+        StartOffsets::DropDays(days) => days.iter().map(|&d| d as u64 * DAY_NS).collect(),
+    };
+    if let Some(&offset) = points.iter().find(|&&o| first + o > last) {
+        return Err(anyhow!(
+            "Start offset of {}ns leaves no data, the events span {}ns",
+            offset,
+            last - first
+        ));
+    }
+    points.sort_unstable();
+
+    let mut strategy = strategy_constructor(params)?;
+    let full = metric(&session.run(strategy.as_mut()).await?);
+    if !full.is_finite() {
+        return Err(anyhow!(
+            "The metric of the full run is not finite: {}",
+            full
+        ));
+    }
+
+    let mut runs = Vec::with_capacity(points.len());
+    for offset in points {
+        let events = Arc::new(starting_at(session.events(), first + offset));
+        let mut strategy = strategy_constructor(params)?;
+        let result = session.run_events(events, strategy.as_mut()).await?;
+        runs.push(StartRun {
+            offset,
+            metric: metric(&result),
+            total_return_pct: result.total_return_pct,
+            total_trades: result.total_trades,
+        });
+    }
+    Ok(StartSensitivity { full, runs })
+}
+
+pub fn display_start_sensitivity(label: &str, test: &StartSensitivity) {
+    println!("\n=== START SENSITIVITY ===");
+    println!(
+        "{}: Full: {:.4}, Later starts: {}, Mean: {:.4}, Std: {:.4}, Min: {:.4}, Max: {:.4}",
+        label,
+        test.full,
+        test.runs.len(),
+        test.mean(),
+        test.std_dev(),
+        test.min(),
+        test.max()
+    );
+    for run in &test.runs {
+        println!(
+            "  +{:>7.2}d: Metric: {:.4}, Return: {:.2}%, Trades: {}",
+            run.offset as f64 / DAY_NS as f64,
+            run.metric,
+            run.total_return_pct,
+            run.total_trades
+        );
+    }
+    if test.hinges_on_start() {
+        println!(
+            "WARNING: the full run beats every later start, it may hinge on its earliest trades"
+        );
+    } else if test.std_dev() > test.mean().abs() {
+        println!("WARNING: results disperse more than their mean across starts");
+    }
+}
+
+/// Size of the uniform noise added to every price of a noise-injection test
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceNoise {
+    /// Up to `ticks` ticks of `tick_size` either way, e.g. half a tick
+    Ticks { ticks: f64, tick_size: f64 },
+    /// Up to `fraction` of the price either way, for streams mixing price scales
+    Relative { fraction: f64 },
+}
+
+impl PriceNoise {
+    fn shift(&self, price: f64, rng: &mut SeededRng) -> f64 {
+        let width = match self {
+            PriceNoise::Ticks { ticks, tick_size } => ticks * tick_size,
+            PriceNoise::Relative { fraction } => fraction * price,
+        };
+        (2.0 * rng.next_f64() - 1.0) * width
+    }
+}
+
+/// A strategy's metric on the real prices against the same metric on noisy copies
+#[derive(Debug, Clone)]
+pub struct NoiseTest {
+    pub noise: PriceNoise,
+    pub real: f64,
+    /// The metric of each noisy run, in run order
+    pub noisy: Vec<f64>,
+}
+
+impl NoiseTest {
+    fn sorted(&self) -> Vec<f64> {
+        let mut noisy: Vec<f64> = self
+            .noisy
+            .iter()
+            .copied()
+            .filter(|m| m.is_finite())
+            .collect();
+        noisy.sort_by(|a, b| a.total_cmp(b));
+        noisy
+    }
+
+    pub fn mean(&self) -> f64 {
+        let noisy = self.sorted();
+        noisy.iter().sum::<f64>() / noisy.len().max(1) as f64
+    }
+
+    /// The `q` quantile of the noisy runs' metric, NaN without any
+    pub fn quantile(&self, q: f64) -> f64 {
+        let noisy = self.sorted();
+        noisy
+            .get((noisy.len() as f64 * q.clamp(0.0, 1.0)) as usize)
+            .or(noisy.last())
+            .copied()
+            .unwrap_or(f64::NAN)
+    }
+
+    /// Share of noisy runs doing at least as well as the real one
+    pub fn share_as_good(&self) -> f64 {
+        let as_good = self.noisy.iter().filter(|&&m| m >= self.real).count();
+        as_good as f64 / self.noisy.len().max(1) as f64
+    }
+}
+
+/// A noisy copy of a stream: each priced event is shifted by its own noise draw, moving
+/// every price of a bar or quote together so its shape is kept. Timestamps and sizes are
+/// untouched.
+///
+/// Option strikes would move with their prices, so streams with option trades are
+/// refused.
+pub fn add_noise(
+    events: &[MarketEvent],
+    noise: PriceNoise,
+    rng: &mut SeededRng,
+) -> Result<Vec<MarketEvent>> {
+    if events
+        .iter()
+        .any(|e| matches!(e, MarketEvent::OptionTrade(_)))
+    {
+        return Err(anyhow!("Streams with option trades cannot be perturbed"));
+    }
+    let mut noisy = events.to_vec();
+    for event in &mut noisy {
+        if matches!(event, MarketEvent::Definition(_)) {
+            continue;
+        }
+        let price = event.price();
+        if !price.is_finite() || price <= 0.0 {
+            continue;
+        }
+        let shifted = price + noise.shift(price, rng);
+        if shifted > 0.0 {
+            event.scale_prices(shifted / price);
+        }
+    }
+    Ok(noisy)
+}
+
+/// Run the strategy on the session's data and on `runs` copies with `noise` added to
+/// the prices, comparing `metric` across them.
+///
+/// Noise draws from the [`NOISE`] component of the session's seeds, so a test is
+/// reproduced exactly by the same seeds.
+pub async fn noise_test<F>(
+    session: &WarmSession,
+    params: &StrategyParams,
+    strategy_constructor: F,
+    noise: PriceNoise,
+    runs: usize,
+    metric: fn(&BacktestResult) -> f64,
+) -> Result<NoiseTest>
+where
+    F: Fn(&StrategyParams) -> Result<Box<dyn Strategy>>,
+{
+    let mut strategy = strategy_constructor(params)?;
+    let real = metric(&session.run(strategy.as_mut()).await?);
+    if !real.is_finite() {
+        return Err(anyhow!(
+            "The metric of the real run is not finite: {}",
+            real
+        ));
+    }
+
+    let mut rng = session.options().seeds.rng(NOISE);
+    let mut noisy = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let events = Arc::new(add_noise(session.events(), noise, &mut rng)?);
+        let mut strategy = strategy_constructor(params)?;
+        noisy.push(metric(
+            &session.run_events(events, strategy.as_mut()).await?,
+        ));
+    }
+    Ok(NoiseTest { noise, real, noisy })
+}
+
+pub fn display_noise_test(label: &str, test: &NoiseTest) {
+    println!("\n=== NOISE INJECTION ({:?}) ===", test.noise);
+    println!(
+        "{}: Real: {:.4}, Noisy mean: {:.4}, 5th: {:.4}, Median: {:.4}, 95th: {:.4}, Runs: {}",
+        label,
+        test.real,
+        test.mean(),
+        test.quantile(0.05),
+        test.quantile(0.5),
+        test.quantile(0.95),
+        test.noisy.len()
+    );
+    if !test.noisy.is_empty() && test.share_as_good() < 0.05 {
+        println!("WARNING: the real prices beat nearly every noisy copy, the edge may rest on exact prints");
+    }
+}
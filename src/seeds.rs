@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Component name of the significance bootstrap
+pub const BOOTSTRAP: &str = "bootstrap";
+/// Component name of the shuffled-data permutation test
+pub const PERMUTATION: &str = "permutation";
+/// Component name of the price noise of a noise-injection test
+pub const NOISE: &str = "noise";
+/// Component name of the random later starts of a start-sensitivity test
+pub const START_OFFSET: &str = "start_offset";
+
+/// Seeds of every stochastic component of a run, so it can be reproduced exactly.
+///
+/// Each component draws from its own generator, seeded from the master seed and the
+/// component's name unless overridden, so adding a component never shifts the draws of
+/// another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Seeds {
+    pub master: u64,
+    /// Seeds pinned per component name
+    #[serde(default)]
+    pub overrides: BTreeMap<String, u64>,
+}
+
+impl Default for Seeds {
+    fn default() -> Self {
+        Self::new(42)
+    }
+}
+
+impl Seeds {
+    pub fn new(master: u64) -> Self {
+        Self {
+            master,
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    /// Pin the seed of one component
+    pub fn with_override(mut self, component: &str, seed: u64) -> Self {
+        self.overrides.insert(component.to_string(), seed);
+        self
+    }
+
+    /// Seed of `component`
+    pub fn seed(&self, component: &str) -> u64 {
+        if let Some(seed) = self.overrides.get(component) {
+            return *seed;
+        }
+        // FNV-1a of the name mixed into the master seed
+        let name = component.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+        splitmix(self.master ^ name)
+    }
+
+    /// A fresh generator for `component`, the same sequence on every call
+    pub fn rng(&self, component: &str) -> SeededRng {
+        SeededRng::new(self.seed(component))
+    }
+}
+
+fn splitmix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Small deterministic generator (xorshift64*) for stochastic components
+#[derive(Debug, Clone)]
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift needs a non-zero state
+        Self(splitmix(seed).max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in [0, n)
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize
+    }
+
+    /// Standard normal draw, Box-Muller
+    pub fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+}
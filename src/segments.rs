@@ -0,0 +1,205 @@
+use crate::backtester::{run_backtest_on_stream, BacktestOptions, BacktestResult};
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::Strategy;
+use crate::utils::chain::{self, DataFile};
+use crate::utils::fetch::{self, BacktestManager, MarketStream};
+use crate::utils::snapshot::fingerprint_data;
+use crate::InkBackSchema;
+use anyhow::Result;
+use databento::dbn::Schema;
+use rayon::prelude::*;
+
+/// How a single backtest is split into time segments run on separate cores
+#[derive(Debug, Clone)]
+pub struct SegmentConfig {
+    /// Number of equal slices of the time range
+    pub segments: usize,
+    /// Nanoseconds of data before each segment fed to the strategy without trading, so
+    /// indicators are warm when the segment starts. Zero suits stateless strategies.
+    pub warmup: u64,
+}
+
+impl SegmentConfig {
+    pub fn new(segments: usize) -> Self {
+        Self {
+            segments,
+            warmup: 0,
+        }
+    }
+
+    pub fn with_warmup(mut self, warmup: u64) -> Self {
+        self.warmup = warmup;
+        self
+    }
+}
+
+/// One segment's `[start, end)` range and its own result, starting from the full
+/// starting equity
+pub struct Segment {
+    pub start: u64,
+    pub end: u64,
+    pub result: BacktestResult,
+}
+
+pub struct SegmentedRun {
+    /// The segments stitched into one run: trades concatenated, and each segment's equity
+    /// curve offset by the P&L of the segments before it
+    pub result: BacktestResult,
+    pub segments: Vec<Segment>,
+}
+
+/// Run one strategy over `time_range` split into [`SegmentConfig::segments`] slices in
+/// parallel, then stitch them into one result.
+///
+/// Every segment is a fresh strategy from `strategy_constructor` starting flat at
+/// `starting_equity`, and positions still open at a segment's end are closed there, so
+/// the stitched run matches an uninterrupted one only for strategies whose decisions
+/// depend on no more history than the warmup. Compounding restarts at each segment. Of
+/// the per-run reports only the trades, hedge trades, blocked entries, pre-trade rejections
+/// and assignment risks are combined, the rest stay in each [`Segment::result`].
+///
+/// Without `time_range` the data's own range is used, read from the DBN metadata or the
+/// CSV's events. Directories and globs of daily files only open the files each segment
+/// needs, while a single file is read from its start by every segment.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_segmented_backtest<F>(
+    symbol: &str,
+    backtest_manager: &BacktestManager,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    strategy_constructor: &F,
+    transaction_costs: &TransactionCosts,
+    starting_equity: f64,
+    exposure: f64,
+    time_range: Option<(u64, u64)>,
+    config: &SegmentConfig,
+    options: &BacktestOptions,
+) -> Result<SegmentedRun>
+where
+    F: Fn() -> Result<Box<dyn Strategy>> + Sync + Send,
+{
+    if config.segments == 0 {
+        return Err(anyhow::anyhow!("SegmentConfig::segments must be positive"));
+    }
+    let time_range = match &backtest_manager.holdout {
+        Some(holdout) => holdout.in_sample(time_range)?,
+        None => time_range,
+    };
+    let handle = tokio::runtime::Handle::current();
+    let data_path = &backtest_manager.data_path;
+    let files = chain::scanned_files(data_path).await?;
+    let (start, end) = match time_range {
+        Some(range) => range,
+        None => (
+            files.first().map_or(0, |f| f.start),
+            files.last().map_or(0, |f| f.end),
+        ),
+    };
+    if end == u64::MAX || end <= start {
+        return Err(anyhow::anyhow!(
+            "Cannot split {} into segments without a known time range, pass one",
+            data_path
+        ));
+    }
+
+    let span = (end - start).div_ceil(config.segments as u64);
+    let bounds: Vec<(u64, u64)> = (0..config.segments as u64)
+        .map(|i| (start + i * span, (start + (i + 1) * span).min(end)))
+        .filter(|(s, e)| s < e)
+        .collect();
+    println!(
+        "Running {} in {} segments of {:.2} days",
+        symbol,
+        bounds.len(),
+        span as f64 / 86_400e9
+    );
+
+    let multi_file = chain::is_multi_file(data_path);
+    let results: Vec<Result<Segment>> = bounds
+        .par_iter()
+        .map(|&(seg_start, seg_end)| {
+            let from = seg_start.saturating_sub(config.warmup);
+            let mut strategy = strategy_constructor()?;
+            let segment_options = BacktestOptions {
+                warmup_until: Some(seg_start),
+                ..options.clone()
+            };
+            let result = handle.block_on(async {
+                let stream: MarketStream = if multi_file {
+                    chain::chain_files(overlapping(&files, from, seg_end), schema)
+                } else {
+                    fetch::get_data_stream(data_path, schema).await?
+                };
+                run_backtest_on_stream(
+                    symbol,
+                    stream,
+                    strategy.as_mut(),
+                    transaction_costs.clone(),
+                    starting_equity,
+                    exposure,
+                    custom_schema.clone(),
+                    Some((from, seg_end)),
+                    &segment_options,
+                )
+                .await
+            })?;
+            Ok(Segment {
+                start: seg_start,
+                end: seg_end,
+                result,
+            })
+        })
+        .collect();
+    let segments = results.into_iter().collect::<Result<Vec<_>>>()?;
+
+    let mut result = stitch(starting_equity, &segments);
+    result.data_files = fingerprint_data(data_path)?;
+    Ok(SegmentedRun { result, segments })
+}
+
+// Files with events inside `[from, to)`
+fn overlapping(files: &[DataFile], from: u64, to: u64) -> Vec<DataFile> {
+    files
+        .iter()
+        .filter(|f| f.start < to && f.end > from)
+        .cloned()
+        .collect()
+}
+
+fn stitch(starting_equity: f64, segments: &[Segment]) -> BacktestResult {
+    let mut curve = vec![starting_equity];
+    let mut trades = Vec::new();
+    let mut offset = 0.0;
+    for segment in segments {
+        let r = &segment.result;
+        curve.extend(r.equity_curve.iter().skip(1).map(|eq| eq + offset));
+        trades.extend(r.trades.iter().cloned());
+        offset += r.ending_equity - r.starting_equity;
+    }
+
+    let mut result =
+        BacktestResult::calculate_metrics(starting_equity, starting_equity + offset, curve, trades);
+    for segment in segments {
+        let r = &segment.result;
+        result.hedge_trades.extend(r.hedge_trades.iter().cloned());
+        result.force_closed.extend(r.force_closed.iter().cloned());
+        result
+            .blocked_entries
+            .extend(r.blocked_entries.iter().cloned());
+        result
+            .pre_trade_rejections
+            .extend(r.pre_trade_rejections.iter().cloned());
+        result
+            .assignment_risks
+            .extend(r.assignment_risks.iter().cloned());
+    }
+    result.seeds = segments
+        .first()
+        .map(|s| s.result.seeds.clone())
+        .unwrap_or_default();
+    result.metadata = segments
+        .first()
+        .map(|s| s.result.metadata.clone())
+        .unwrap_or_default();
+    result
+}
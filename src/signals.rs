@@ -0,0 +1,279 @@
+use crate::context::EventCtx;
+use crate::event::MarketEvent;
+use crate::strategy::{ExitReason, Order, OrderType, Strategy};
+use anyhow::{Context, Result};
+use csv::ReaderBuilder;
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+
+/// A timestamped series of scores from outside the engine, e.g. a model's predictions.
+/// At an event the series reads as its latest value at or before the event.
+#[derive(Debug, Clone)]
+pub struct SignalSeries {
+    pub name: String,
+    // (ts, value) in time order
+    points: Vec<(u64, f64)>,
+    /// Nanoseconds a value stays current, after which the series reads as missing until
+    /// the next one. `None` keeps every value until it is replaced.
+    pub max_age: Option<u64>,
+}
+
+impl SignalSeries {
+    pub fn new(name: &str, mut points: Vec<(u64, f64)>) -> Self {
+        points.sort_by_key(|&(ts, _)| ts);
+        Self {
+            name: name.to_string(),
+            points,
+            max_age: None,
+        }
+    }
+
+    /// The column `column` of a signal CSV, see [`read_signal_csv`]
+    pub fn load(path: &str, column: &str) -> Result<Self> {
+        read_signal_csv(path)?
+            .into_iter()
+            .find(|series| series.name == column)
+            .ok_or_else(|| anyhow::anyhow!("{} has no column {:?}", path, column))
+    }
+
+    pub fn with_max_age(mut self, max_age: u64) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn points(&self) -> &[(u64, f64)] {
+        &self.points
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    // Value current at `ts`, moving `next` past every point at or before it. Timestamps
+    // must not decrease between calls.
+    pub(crate) fn advance(&self, next: &mut usize, ts: u64) -> Option<f64> {
+        while self.points.get(*next).is_some_and(|&(at, _)| at <= ts) {
+            *next += 1;
+        }
+        let (at, value) = *self.points.get(next.checked_sub(1)?)?;
+        let fresh = self
+            .max_age
+            .is_none_or(|max_age| ts.saturating_sub(at) <= max_age);
+        (fresh && value.is_finite()).then_some(value)
+    }
+}
+
+/// Read a CSV of signals (zstd compressed when it ends in `.zst`) with a header: the
+/// first column is `ts_event` in nanoseconds, every other column becomes a series named
+/// by its header. Empty cells are skipped, so series may be sparse.
+pub fn read_signal_csv(path: &str) -> Result<Vec<SignalSeries>> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path))?;
+    let reader: Box<dyn Read> = if path.ends_with(".zst") {
+        Box::new(zstd::Decoder::new(file).context("Failed to create zstd decoder")?)
+    } else {
+        Box::new(file)
+    };
+    let mut reader = ReaderBuilder::new().flexible(true).from_reader(reader);
+    let names: Vec<String> = reader
+        .headers()?
+        .iter()
+        .skip(1)
+        .map(|name| name.trim().to_string())
+        .collect();
+    let mut points: Vec<Vec<(u64, f64)>> = vec![Vec::new(); names.len()];
+    for (line, record) in reader.records().enumerate() {
+        let record = record?;
+        let field = |i: usize| record.get(i).map(str::trim).unwrap_or_default();
+        let ts = field(0)
+            .parse::<u64>()
+            .with_context(|| format!("{} row {}: bad ts_event {:?}", path, line + 2, field(0)))?;
+        for (i, (name, series)) in names.iter().zip(&mut points).enumerate() {
+            let cell = field(i + 1);
+            if cell.is_empty() {
+                continue;
+            }
+            let value = cell
+                .parse::<f64>()
+                .with_context(|| format!("{} row {}: bad {} {:?}", path, line + 2, name, cell))?;
+            series.push((ts, value));
+        }
+    }
+    Ok(names
+        .iter()
+        .zip(points)
+        .map(|(name, points)| SignalSeries::new(name, points))
+        .collect())
+}
+
+/// Signal series the engine aligns to the event stream and exposes through
+/// [`EventCtx::signal`]. Empty by default; clones share the series.
+#[derive(Clone, Default)]
+pub struct ExternalSignals {
+    series: Vec<Arc<SignalSeries>>,
+}
+
+impl ExternalSignals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every series of a signal CSV, see [`read_signal_csv`]
+    pub fn load(path: &str) -> Result<Self> {
+        Ok(read_signal_csv(path)?
+            .into_iter()
+            .fold(Self::new(), |signals, series| signals.with(series)))
+    }
+
+    /// Add a series, replacing one of the same name
+    pub fn with(mut self, series: SignalSeries) -> Self {
+        self.series.retain(|s| s.name != series.name);
+        self.series.push(Arc::new(series));
+        self
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.series.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+}
+
+impl fmt::Debug for ExternalSignals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// The value of every external signal at the current event, maintained by the engine
+pub struct SignalValues {
+    signals: ExternalSignals,
+    next: Vec<usize>,
+    values: Vec<Option<f64>>,
+}
+
+impl SignalValues {
+    pub fn new(signals: &ExternalSignals) -> Self {
+        let n = signals.series.len();
+        Self {
+            signals: signals.clone(),
+            next: vec![0; n],
+            values: vec![None; n],
+        }
+    }
+
+    pub fn update(&mut self, event: &MarketEvent) {
+        let ts = event.timestamp();
+        for ((series, next), value) in self
+            .signals
+            .series
+            .iter()
+            .zip(&mut self.next)
+            .zip(&mut self.values)
+        {
+            *value = series.advance(next, ts);
+        }
+    }
+
+    /// Current value of the series `name`, None before its first value, once it is
+    /// older than its max age, or without such a series
+    pub fn get(&self, name: &str) -> Option<f64> {
+        let i = self.signals.series.iter().position(|s| s.name == name)?;
+        self.values[i]
+    }
+}
+
+// Market orders that move a single position toward a target side: the exit when the
+// side changes, the new entry on the following call
+#[derive(Debug, Clone)]
+pub(crate) struct Threshold {
+    long_above: f64,
+    short_below: f64,
+    held: i8,
+}
+
+impl Threshold {
+    pub(crate) fn new(long_above: f64, short_below: f64) -> Self {
+        Self {
+            long_above,
+            short_below,
+            held: 0,
+        }
+    }
+
+    pub(crate) fn step(&mut self, value: Option<f64>, price: f64) -> Option<Order> {
+        let target = match value {
+            Some(v) if v > self.long_above => 1,
+            Some(v) if v < self.short_below => -1,
+            _ => 0,
+        };
+        if target == self.held {
+            return None;
+        }
+        if self.held != 0 {
+            let exit = if self.held > 0 {
+                OrderType::MarketSell
+            } else {
+                OrderType::MarketBuy
+            };
+            self.held = 0;
+            return Some(Order::new(exit, price));
+        }
+        self.held = target;
+        let entry = if target > 0 {
+            OrderType::MarketBuy
+        } else {
+            OrderType::MarketSell
+        };
+        Some(Order::new(entry, price))
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.held = 0;
+    }
+}
+
+/// Trades one external signal from [`BacktestOptions::signals`]: long while it is above
+/// `long_above`, short while it is below `short_below`, flat in between or while the
+/// signal is missing. Changes of side trade a market exit, then the entry on the next event.
+///
+/// [`BacktestOptions::signals`]: crate::backtester::BacktestOptions::signals
+pub struct SignalThresholdStrategy {
+    pub signal: String,
+    threshold: Threshold,
+}
+
+impl SignalThresholdStrategy {
+    pub fn new(signal: &str, long_above: f64, short_below: f64) -> Self {
+        Self {
+            signal: signal.to_string(),
+            threshold: Threshold::new(long_above, short_below),
+        }
+    }
+}
+
+impl Strategy for SignalThresholdStrategy {
+    /// The signal only reaches the strategy through the context
+    fn on_event(&mut self, _event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        None
+    }
+
+    fn on_event_ctx(
+        &mut self,
+        event: &MarketEvent,
+        _prev: Option<&MarketEvent>,
+        ctx: &EventCtx,
+    ) -> Option<Order> {
+        self.threshold.step(ctx.signal(&self.signal), event.price())
+    }
+
+    fn on_position_closed(&mut self, _reason: ExitReason) {
+        self.threshold.reset();
+    }
+}
@@ -0,0 +1,456 @@
+use crate::backtester::{BacktestResult, NANOS_PER_DAY};
+use crate::greeks::norm_cdf;
+use crate::seeds::{Seeds, BOOTSTRAP};
+use crate::utils::snapshot::check_same_data;
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+/// Is one return series really better than another, or than its peers in a sweep
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// Days both series have a return for
+    pub days: usize,
+    /// Mean daily return of `a` minus `b`
+    pub mean_diff: f64,
+    /// Paired t statistic of the daily differences
+    pub t_stat: f64,
+    /// Two-sided p-value of the paired t-test
+    pub t_p_value: f64,
+    /// Two-sided p-value of a bootstrap of the mean daily difference
+    pub bootstrap_p_value: f64,
+}
+
+/// Daily returns of a result keyed by UTC day, from its daily [`EquitySampling`].
+///
+/// [`EquitySampling`]: crate::backtester::EquitySampling
+pub fn daily_returns(result: &BacktestResult) -> Result<BTreeMap<u64, f64>> {
+    if result.sampled_equity.is_empty() || result.sample_interval != Some(NANOS_PER_DAY) {
+        return Err(anyhow!(
+            "Daily returns need a result run with EquitySampling::daily()"
+        ));
+    }
+
+    Ok(result
+        .sampled_equity
+        .windows(2)
+        .filter(|w| w[0].1 != 0.0)
+        .map(|w| (w[1].0 / NANOS_PER_DAY, w[1].1 / w[0].1 - 1.0))
+        .collect())
+}
+
+/// Compare the daily returns of `a` against `b` (another strategy or a benchmark) on the
+/// days both traded, with a paired t-test and a bootstrap of `resamples` draws seeded
+/// from the [`BOOTSTRAP`] component of `seeds`
+pub fn compare(
+    a: &BTreeMap<u64, f64>,
+    b: &BTreeMap<u64, f64>,
+    resamples: usize,
+    seeds: &Seeds,
+) -> Result<Comparison> {
+    let diffs: Vec<f64> = a
+        .iter()
+        .filter_map(|(day, ra)| b.get(day).map(|rb| ra - rb))
+        .collect();
+    let n = diffs.len();
+    if n < 3 {
+        return Err(anyhow!("Need at least 3 overlapping days, found {}", n));
+    }
+
+    let mean = diffs.iter().sum::<f64>() / n as f64;
+    let var = diffs.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    let se = (var / n as f64).sqrt();
+    let t_stat = if se > 0.0 { mean / se } else { 0.0 };
+    let t_p_value = 2.0 * (1.0 - student_t_cdf(t_stat.abs(), (n - 1) as f64));
+
+    // Resample the differences re-centred on zero, the null of no edge
+    let mut rng = seeds.rng(BOOTSTRAP);
+    let centred: Vec<f64> = diffs.iter().map(|d| d - mean).collect();
+    let mut extreme = 0usize;
+    for _ in 0..resamples {
+        let sample_mean = (0..n).map(|_| centred[rng.below(n)]).sum::<f64>() / n as f64;
+        if sample_mean.abs() >= mean.abs() {
+            extreme += 1;
+        }
+    }
+    let bootstrap_p_value = (extreme + 1) as f64 / (resamples + 1) as f64;
+
+    Ok(Comparison {
+        days: n,
+        mean_diff: mean,
+        t_stat,
+        t_p_value,
+        bootstrap_p_value,
+    })
+}
+
+/// Per-period Sharpe ratio, skewness and kurtosis of a return series
+fn moments(returns: &[f64]) -> (f64, f64, f64) {
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+    let std = var.sqrt();
+    if std == 0.0 {
+        return (0.0, 0.0, 3.0);
+    }
+    let skew = returns
+        .iter()
+        .map(|r| ((r - mean) / std).powi(3))
+        .sum::<f64>()
+        / n;
+    let kurt = returns
+        .iter()
+        .map(|r| ((r - mean) / std).powi(4))
+        .sum::<f64>()
+        / n;
+    (mean / std, skew, kurt)
+}
+
+/// Deflated Sharpe ratio (Bailey and López de Prado): the probability that the best
+/// strategy's true Sharpe ratio is above zero, after allowing for having picked it as
+/// the best of `trials` whose daily Sharpe ratios varied by `trial_sharpe_variance`.
+pub fn deflated_sharpe_ratio(returns: &[f64], trials: usize, trial_sharpe_variance: f64) -> f64 {
+    if returns.len() < 3 || trials == 0 {
+        return 0.0;
+    }
+    const EULER_GAMMA: f64 = 0.577_215_664_9;
+
+    let (sharpe, skew, kurt) = moments(returns);
+    let n = trials.max(2) as f64;
+    let expected_max = trial_sharpe_variance.max(0.0).sqrt()
+        * ((1.0 - EULER_GAMMA) * inverse_norm_cdf(1.0 - 1.0 / n)
+            + EULER_GAMMA * inverse_norm_cdf(1.0 - 1.0 / (n * std::f64::consts::E)));
+
+    let denom = (1.0 - skew * sharpe + (kurt - 1.0) / 4.0 * sharpe * sharpe).max(1e-12);
+    norm_cdf((sharpe - expected_max) * ((returns.len() - 1) as f64).sqrt() / denom.sqrt())
+}
+
+/// Deflated Sharpe ratio of the best of a sweep, the trials being every result in it.
+/// Each result needs a daily [`EquitySampling`].
+///
+/// [`EquitySampling`]: crate::backtester::EquitySampling
+pub fn deflated_sharpe_of_sweep(best: &BacktestResult, sweep: &[&BacktestResult]) -> Result<f64> {
+    check_same_data(
+        std::iter::once(("best", best)).chain(sweep.iter().map(|r| ("sweep result", *r))),
+    );
+    let best_returns: Vec<f64> = daily_returns(best)?.into_values().collect();
+    let sharpes: Vec<f64> = sweep
+        .iter()
+        .map(|r| {
+            let returns: Vec<f64> = daily_returns(r)?.into_values().collect();
+            Ok(if returns.len() >= 2 {
+                moments(&returns).0
+            } else {
+                0.0
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let n = sharpes.len().max(1) as f64;
+    let mean = sharpes.iter().sum::<f64>() / n;
+    let variance = sharpes.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+    Ok(deflated_sharpe_ratio(&best_returns, sweep.len(), variance))
+}
+
+/// How much of a sweep's best result is selection bias
+#[derive(Debug, Clone)]
+pub struct SelectionBias {
+    pub trials: usize,
+    /// Return periods the trials were compared over
+    pub periods: usize,
+    /// Probability of backtest overfitting: the share of CSCV splits in which the best
+    /// in-sample configuration ranks in the bottom half out of sample
+    pub pbo: Option<f64>,
+    /// Deflated Sharpe ratio of the selected configuration
+    pub deflated_sharpe: f64,
+}
+
+/// Selection bias of picking `results[0]` out of `results`, as [`display_results`] does
+/// with its sorted sweep.
+///
+/// Trials are compared on daily returns when every result has daily [`EquitySampling`],
+/// otherwise on per-event returns of their equity curves. PBO uses combinatorially
+/// symmetric cross-validation over `blocks` equal slices of the returns, None when there
+/// are too few periods to split.
+///
+/// [`display_results`]: crate::backtester::display_results
+/// [`EquitySampling`]: crate::backtester::EquitySampling
+pub fn selection_bias(results: &[&BacktestResult], blocks: usize) -> Option<SelectionBias> {
+    if results.len() < 2 {
+        return None;
+    }
+
+    let daily: Option<Vec<BTreeMap<u64, f64>>> =
+        results.iter().map(|r| daily_returns(r).ok()).collect();
+    let returns_of: Box<dyn Fn(usize) -> Vec<f64>> = match daily {
+        Some(daily) => {
+            let common: Vec<u64> = daily[0]
+                .keys()
+                .filter(|day| daily[1..].iter().all(|d| d.contains_key(day)))
+                .copied()
+                .collect();
+            Box::new(move |i| common.iter().map(|day| daily[i][day]).collect())
+        }
+        None => {
+            let len = results.iter().map(|r| r.equity_curve.len()).min()?;
+            Box::new(move |i| {
+                results[i].equity_curve[..len]
+                    .windows(2)
+                    .map(|w| if w[0] != 0.0 { w[1] / w[0] - 1.0 } else { 0.0 })
+                    .collect()
+            })
+        }
+    };
+
+    let selected = returns_of(0);
+    let periods = selected.len();
+    if periods < 3 {
+        return None;
+    }
+
+    // An even block count of at least two periods each, capped at C(16, 8) splits
+    let blocks = blocks.min(periods / 2).min(16) / 2 * 2;
+    let block_len = if blocks >= 2 { periods / blocks } else { 0 };
+
+    // Per trial, the count, sum and sum of squares of returns in each block
+    let mut sharpes = Vec::with_capacity(results.len());
+    let mut stats: Vec<Vec<(f64, f64, f64)>> = Vec::with_capacity(results.len());
+    for i in 0..results.len() {
+        let returns = if i == 0 {
+            selected.clone()
+        } else {
+            returns_of(i)
+        };
+        sharpes.push(moments(&returns).0);
+        if block_len > 0 {
+            stats.push(
+                returns[..blocks * block_len]
+                    .chunks(block_len)
+                    .map(|chunk| {
+                        chunk
+                            .iter()
+                            .fold((0.0, 0.0, 0.0), |(n, s, q), r| (n + 1.0, s + r, q + r * r))
+                    })
+                    .collect(),
+            );
+        }
+    }
+
+    let n = sharpes.len() as f64;
+    let mean = sharpes.iter().sum::<f64>() / n;
+    let variance = sharpes.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+
+    Some(SelectionBias {
+        trials: results.len(),
+        periods,
+        pbo: (block_len > 0).then(|| cscv(&stats, blocks)),
+        deflated_sharpe: deflated_sharpe_ratio(&selected, results.len(), variance),
+    })
+}
+
+// Combinatorially symmetric cross-validation (Bailey, Borwein, López de Prado and Zhu):
+// over every half of the blocks taken as in sample, how often the trial that is best in
+// sample has a logit of its out of sample rank at or below zero
+fn cscv(stats: &[Vec<(f64, f64, f64)>], blocks: usize) -> f64 {
+    let sharpe = |trial: &[(f64, f64, f64)], mask: u32, in_sample: bool| {
+        let (n, s, q) = trial
+            .iter()
+            .enumerate()
+            .filter(|(b, _)| (mask >> b & 1 == 1) == in_sample)
+            .fold((0.0, 0.0, 0.0), |acc, (_, x)| {
+                (acc.0 + x.0, acc.1 + x.1, acc.2 + x.2)
+            });
+        let mean = s / n;
+        let std = (q / n - mean * mean).max(0.0).sqrt();
+        if std > 0.0 {
+            mean / std
+        } else {
+            0.0
+        }
+    };
+
+    let trials = stats.len() as f64;
+    let (mut splits, mut overfit) = (0usize, 0usize);
+    for mask in (0u32..1 << blocks).filter(|m| m.count_ones() as usize == blocks / 2) {
+        let (best, _) = stats
+            .iter()
+            .map(|trial| sharpe(trial, mask, true))
+            .enumerate()
+            .fold(
+                (0, f64::NEG_INFINITY),
+                |acc, (i, s)| {
+                    if s > acc.1 {
+                        (i, s)
+                    } else {
+                        acc
+                    }
+                },
+            );
+        let oos: Vec<f64> = stats
+            .iter()
+            .map(|trial| sharpe(trial, mask, false))
+            .collect();
+        let rank = oos.iter().filter(|s| **s < oos[best]).count() as f64 + 1.0;
+        let omega = rank / (trials + 1.0);
+        splits += 1;
+        if (omega / (1.0 - omega)).ln() <= 0.0 {
+            overfit += 1;
+        }
+    }
+    overfit as f64 / splits as f64
+}
+
+pub fn display_selection_bias(bias: &SelectionBias) {
+    println!("\n=== SELECTION BIAS ===");
+    println!(
+        "Trials: {} | Periods: {} | PBO: {} | Deflated Sharpe of selected: {:.3}",
+        bias.trials,
+        bias.periods,
+        bias.pbo
+            .map_or("n/a".to_string(), |p| format!("{:.1}%", p * 100.0)),
+        bias.deflated_sharpe
+    );
+    if bias.pbo.is_some_and(|p| p >= 0.5) || bias.deflated_sharpe < 0.95 {
+        println!("The selected result is not clearly better than luck across this many trials");
+    }
+}
+
+pub fn display_comparison(label_a: &str, label_b: &str, comparison: &Comparison) {
+    println!("\n=== {} vs {} ===", label_a, label_b);
+    println!(
+        "Days: {} | Mean daily diff: {:.4}% | t: {:.2} | t-test p: {:.4} | Bootstrap p: {:.4}",
+        comparison.days,
+        comparison.mean_diff * 100.0,
+        comparison.t_stat,
+        comparison.t_p_value,
+        comparison.bootstrap_p_value
+    );
+}
+
+// Student t CDF through the regularized incomplete beta function
+fn student_t_cdf(t: f64, dof: f64) -> f64 {
+    let x = dof / (dof + t * t);
+    let tail = 0.5 * incomplete_beta(dof / 2.0, 0.5, x);
+    if t >= 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+fn ln_gamma(x: f64) -> f64 {
+    // Lanczos approximation, g = 7
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFS[0];
+    for (i, c) in COEFFS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Lentz's continued fraction for the incomplete beta function
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    d = if d.abs() < TINY { TINY } else { d };
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..200 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            d = if d.abs() < TINY { TINY } else { d };
+            c = 1.0 + numerator / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            d = 1.0 / d;
+            h *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+// Acklam's rational approximation of the standard normal quantile
+fn inverse_norm_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+
+    let p = p.clamp(1e-12, 1.0 - 1e-12);
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
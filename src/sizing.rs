@@ -0,0 +1,323 @@
+use crate::backtester::{BacktestResult, Trade};
+use crate::event::MarketEvent;
+use crate::slippage_models::InstrumentSelector;
+use crate::symbology::SymbologyMap;
+use serde::{Deserialize, Serialize};
+
+/// Equity that entries are sized off
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sizing {
+    /// Current realized equity, so position sizes grow and shrink with the account
+    #[default]
+    Compounding,
+    /// Starting equity, the same capital behind every entry
+    Fixed,
+}
+
+impl Sizing {
+    /// Equity an entry is sized off at the current `equity`
+    pub fn base(&self, equity: f64, starting_equity: f64) -> f64 {
+        match self {
+            Sizing::Compounding => equity,
+            Sizing::Fixed => starting_equity,
+        }
+    }
+}
+
+/// Quantity granularity of the instruments matching `selector`: sized quantities round
+/// down to a multiple of `step`. Instruments without one trade in whole units.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotSize {
+    pub selector: InstrumentSelector,
+    pub step: f64,
+}
+
+impl LotSize {
+    pub fn new(selector: InstrumentSelector, step: f64) -> Self {
+        Self { selector, step }
+    }
+
+    /// Fractional shares in steps of a millionth of a share for everything but option
+    /// trades. Equity streams rarely carry definitions to select stocks by class.
+    pub fn fractional_shares() -> Self {
+        Self::new(InstrumentSelector::Underlying, 1e-6)
+    }
+}
+
+/// Lot step of the event's instrument from the first matching [`LotSize`], else 1
+pub fn lot_step(lots: &[LotSize], event: &MarketEvent, symbology: &SymbologyMap) -> f64 {
+    lots.iter()
+        .find(|lot| lot.selector.matches(event, symbology))
+        .map(|lot| lot.step)
+        .filter(|&step| step.is_finite() && step > 0.0)
+        .unwrap_or(1.0)
+}
+
+/// `quantity` rounded down to a multiple of `step`, tolerating float error just below one
+pub fn round_to_lot(quantity: f64, step: f64) -> f64 {
+    if !quantity.is_finite() || quantity <= 0.0 {
+        return 0.0;
+    }
+    (quantity / step * (1.0 + 1e-12)).floor() * step
+}
+
+/// Scales entries down while equity is below its high-water mark, e.g. half size from
+/// 10% under it. The deepest tier reached applies, and lifts once equity recovers above it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DrawdownThrottle {
+    /// (drawdown from the high-water mark as a fraction, size multiple) by drawdown
+    pub tiers: Vec<(f64, f64)>,
+}
+
+impl DrawdownThrottle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Size entries at `scale` of full from `drawdown` below the high-water mark on
+    pub fn with_tier(mut self, drawdown: f64, scale: f64) -> Self {
+        self.tiers.push((drawdown, scale.max(0.0)));
+        self.tiers.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self
+    }
+
+    /// Size multiple at `drawdown` below the high-water mark
+    pub fn scale_at(&self, drawdown: f64) -> f64 {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|(from, _)| drawdown >= *from)
+            .map_or(1.0, |&(_, scale)| scale)
+    }
+}
+
+/// How a [`DrawdownThrottle`] sized a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThrottleReport {
+    pub entries: usize,
+    /// Entries sized below full
+    pub throttled_entries: usize,
+    /// Share of the run's time spent below full size, in percent
+    pub time_throttled_pct: f64,
+    /// Deepest drawdown of the marked equity from its high-water mark, in percent
+    pub max_drawdown_pct: f64,
+    /// (ts, size multiple) each time the multiple changed
+    pub changes: Vec<(u64, f64)>,
+}
+
+// The throttle's view of the marked equity over a run
+#[derive(Debug)]
+pub(crate) struct ThrottleState {
+    config: DrawdownThrottle,
+    high_water: f64,
+    scale: f64,
+    first_ts: Option<u64>,
+    last_ts: u64,
+    throttled_nanos: u64,
+    report: ThrottleReport,
+}
+
+impl ThrottleState {
+    pub(crate) fn new(config: &DrawdownThrottle, starting_equity: f64) -> Self {
+        Self {
+            config: config.clone(),
+            high_water: starting_equity,
+            scale: 1.0,
+            first_ts: None,
+            last_ts: 0,
+            throttled_nanos: 0,
+            report: ThrottleReport::default(),
+        }
+    }
+
+    /// Size multiple for entries now
+    pub(crate) fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub(crate) fn observe(&mut self, ts: u64, equity: f64) {
+        if !equity.is_finite() {
+            return;
+        }
+        if self.first_ts.is_none() {
+            self.first_ts = Some(ts);
+        } else if self.scale < 1.0 {
+            self.throttled_nanos += ts.saturating_sub(self.last_ts);
+        }
+        self.last_ts = ts;
+        self.high_water = self.high_water.max(equity);
+        let drawdown = if self.high_water > 0.0 {
+            1.0 - equity / self.high_water
+        } else {
+            0.0
+        };
+        self.report.max_drawdown_pct = self.report.max_drawdown_pct.max(drawdown * 100.0);
+        let scale = self.config.scale_at(drawdown);
+        if scale != self.scale {
+            self.scale = scale;
+            self.report.changes.push((ts, scale));
+        }
+    }
+
+    pub(crate) fn entry(&mut self) {
+        self.report.entries += 1;
+        if self.scale < 1.0 {
+            self.report.throttled_entries += 1;
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> ThrottleReport {
+        let span = self
+            .last_ts
+            .saturating_sub(self.first_ts.unwrap_or(self.last_ts));
+        if span > 0 {
+            self.report.time_throttled_pct = self.throttled_nanos as f64 / span as f64 * 100.0;
+        }
+        self.report
+    }
+}
+
+/// Realized performance of the trade sequence under one sizing mode
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SizingStats {
+    pub ending_equity: f64,
+    pub total_return_pct: f64,
+    /// Deepest drawdown of the equity at trade exits
+    pub max_drawdown_pct: f64,
+    pub return_to_drawdown: f64,
+}
+
+/// The trades of a run under both sizing modes: the mode it ran in as traded, the other
+/// rebuilt by resizing each trade to keep its return on the equity it was sized off
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SizingReport {
+    /// Mode the run was sized in
+    pub mode: Sizing,
+    pub compounded: SizingStats,
+    pub fixed: SizingStats,
+}
+
+/// Both sizing modes of a result's trades, with `mode` the one it was run in
+pub(crate) fn report(result: &BacktestResult, mode: Sizing) -> SizingReport {
+    let start = result.starting_equity;
+    let mut trades: Vec<&Trade> = result.trades.iter().filter(|t| t.pnl.is_finite()).collect();
+    trades.sort_by_key(|t| t.exit_ts);
+
+    // Equity each trade was sized off, realized P&L of hedges included when compounding
+    let mut realized: Vec<(u64, f64)> = trades
+        .iter()
+        .copied()
+        .chain(result.hedge_trades.iter().filter(|t| t.pnl.is_finite()))
+        .map(|t| (t.exit_ts, t.pnl))
+        .collect();
+    realized.sort_by_key(|r| r.0);
+    let mut cumulative = Vec::with_capacity(realized.len());
+    let mut sum = 0.0;
+    for (_, pnl) in &realized {
+        sum += pnl;
+        cumulative.push(sum);
+    }
+    let realized_before = |ts: u64| {
+        let n = realized.partition_point(|r| r.0 <= ts);
+        if n > 0 {
+            cumulative[n - 1]
+        } else {
+            0.0
+        }
+    };
+    let returns: Vec<f64> = trades
+        .iter()
+        .map(|t| {
+            let base = mode.base(start + realized_before(t.entry_ts), start);
+            if base > 0.0 {
+                t.pnl / base
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    let fixed: Vec<f64> = returns.iter().map(|r| r * start).collect();
+
+    // Compounded P&L of each trade, sized off the rebuilt equity at its entry
+    let mut compounded: Vec<f64> = Vec::with_capacity(trades.len());
+    let mut rebuilt = Vec::with_capacity(trades.len());
+    let mut sum = 0.0;
+    for (t, r) in trades.iter().zip(&returns) {
+        let n = trades
+            .partition_point(|o| o.exit_ts <= t.entry_ts)
+            .min(rebuilt.len());
+        let before = if n > 0 { rebuilt[n - 1] } else { 0.0 };
+        let pnl = r * (start + before).max(0.0);
+        sum += pnl;
+        compounded.push(pnl);
+        rebuilt.push(sum);
+    }
+
+    SizingReport {
+        mode,
+        compounded: stats(start, &compounded),
+        fixed: stats(start, &fixed),
+    }
+}
+
+fn stats(start: f64, pnls: &[f64]) -> SizingStats {
+    let mut equity = start;
+    let mut peak = start;
+    let mut max_dd = 0.0_f64;
+    for pnl in pnls {
+        equity += pnl;
+        peak = peak.max(equity);
+        if peak > 0.0 {
+            max_dd = max_dd.max((peak - equity) / peak * 100.0);
+        }
+    }
+    let total_return_pct = if start > 0.0 {
+        (equity - start) / start * 100.0
+    } else {
+        0.0
+    };
+    SizingStats {
+        ending_equity: equity,
+        total_return_pct,
+        max_drawdown_pct: max_dd,
+        return_to_drawdown: if max_dd > 0.0 {
+            total_return_pct / max_dd
+        } else {
+            0.0
+        },
+    }
+}
+
+pub fn display_sizing(result: &BacktestResult) {
+    let report = &result.sizing;
+    println!("\n=== COMPOUNDED VS FIXED SIZE (ran {:?}) ===", report.mode);
+    for (label, stats) in [
+        ("Compounded", &report.compounded),
+        ("Fixed size", &report.fixed),
+    ] {
+        println!(
+            "{}: Ending equity: ${:.2}, Return: {:.2}%, Max DD at exits: {:.2}%, Return/DD: {:.2}",
+            label,
+            stats.ending_equity,
+            stats.total_return_pct,
+            stats.max_drawdown_pct,
+            stats.return_to_drawdown
+        );
+    }
+}
+
+pub fn display_throttle(result: &BacktestResult) {
+    let Some(report) = &result.throttle else {
+        return;
+    };
+    println!("\n=== DRAWDOWN THROTTLE ===");
+    println!(
+        "Entries: {} ({} below full size), Time throttled: {:.1}%, Max DD from HWM: {:.2}%, Size changes: {}",
+        report.entries,
+        report.throttled_entries,
+        report.time_throttled_pct,
+        report.max_drawdown_pct,
+        report.changes.len()
+    );
+}
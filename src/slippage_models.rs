@@ -1,3 +1,6 @@
+use crate::event::MarketEvent;
+use crate::symbology::SymbologyMap;
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -5,6 +8,63 @@ pub struct TransactionCosts {
     pub commission: CommissionModel,
     pub slippage: SlippageModel,
     pub spread: SpreadModel,
+    /// Further costs charged on top of the three above, in order
+    #[serde(default)]
+    pub components: Vec<CostComponent>,
+    /// Fill around the mid instead of crossing the full half-spread
+    #[serde(default)]
+    pub mid_fill: Option<MidFill>,
+}
+
+/// Fill convention "mid + `fraction` of the half-spread", for instruments such as options
+/// that are usually worked near the mid rather than taken at the far touch.
+///
+/// The quoted spread is used when the event carries the instrument's own quote, the
+/// modeled [`SpreadModel`] spread otherwise. With `full_cross_size` set, the share paid grows
+/// linearly with order size until orders that large cross the full half-spread.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MidFill {
+    /// 0.0 fills at the mid, 1.0 at the far touch
+    pub fraction: f64,
+    pub full_cross_size: Option<f64>,
+}
+
+impl MidFill {
+    pub fn new(fraction: f64) -> Self {
+        Self {
+            fraction,
+            full_cross_size: None,
+        }
+    }
+
+    pub fn with_full_cross_size(mut self, size: f64) -> Self {
+        self.full_cross_size = Some(size);
+        self
+    }
+
+    /// Share of the half-spread paid by an order of `size`
+    pub fn share(&self, size: f64) -> f64 {
+        let fraction = self.fraction.clamp(0.0, 1.0);
+        match self.full_cross_size {
+            Some(full) if full > 0.0 => fraction + (1.0 - fraction) * (size / full).min(1.0),
+            _ => fraction,
+        }
+    }
+}
+
+/// One entry of a composed cost model, see [`TransactionCosts::with_component`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CostComponent {
+    Commission(CommissionModel),
+    Slippage(SlippageModel),
+    Spread(SpreadModel),
+    /// Exchange and regulatory fees per share or contract, charged on entry and exit
+    ExchangeFee(f64),
+    /// Annual stock borrow rate charged on the entry notional of short positions for
+    /// the time they are held (0.03 = 3%)
+    Borrow {
+        annual_rate: f64,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,23 +109,82 @@ pub enum SpreadModel {
 }
 
 impl TransactionCosts {
+    /// Append a further cost component
+    pub fn with_component(mut self, component: CostComponent) -> Self {
+        self.components.push(component);
+        self
+    }
+
+    pub fn with_mid_fill(mut self, mid_fill: MidFill) -> Self {
+        self.mid_fill = Some(mid_fill);
+        self
+    }
+
+    // Share of the half-spread an order of `size` pays
+    fn spread_share(&self, size: f64) -> f64 {
+        self.mid_fill.map_or(1.0, |fill| fill.share(size))
+    }
+
     pub fn calculate_entry_cost(&self, price: f64, size: f64, volume: f64) -> f64 {
         let commission = self.calculate_commission(price, size, volume);
         let slippage = self.calculate_slippage(price, size, volume, true);
-        let spread = self.calculate_spread(price) / 2.0; // Half spread for market orders
+        // Half spread for market orders
+        let spread = self.calculate_spread(price) / 2.0 * self.spread_share(size);
 
-        commission + slippage + spread
+        commission + slippage + spread + self.component_cost(price, size, volume)
     }
 
     pub fn calculate_exit_cost(&self, price: f64, size: f64, volume: f64) -> f64 {
         let commission = self.calculate_commission(price, size, volume);
         let slippage = self.calculate_slippage(price, size, volume, false);
-        let spread = self.calculate_spread(price) / 2.0;
+        let spread = self.calculate_spread(price) / 2.0 * self.spread_share(size);
 
-        commission + slippage + spread
+        commission + slippage + spread + self.component_cost(price, size, volume)
+    }
+
+    /// Borrow charged for a short held `held_ns` nanoseconds
+    pub fn calculate_borrow_cost(&self, entry_price: f64, size: f64, held_ns: u64) -> f64 {
+        const NANOS_PER_YEAR: f64 = 365.25 * 86_400.0 * 1e9;
+        let years = held_ns as f64 / NANOS_PER_YEAR;
+
+        self.components
+            .iter()
+            .map(|c| match c {
+                CostComponent::Borrow { annual_rate } => entry_price * size * annual_rate * years,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    // Per-side cost of the composed components
+    fn component_cost(&self, price: f64, size: f64, volume: f64) -> f64 {
+        self.components
+            .iter()
+            .map(|c| match c {
+                CostComponent::Commission(model) => commission_cost(model, price, size),
+                CostComponent::Slippage(model) => {
+                    slippage_cost(model, price, size, volume, self.calculate_spread(price))
+                }
+                CostComponent::Spread(model) => spread_cost(model, price) / 2.0,
+                CostComponent::ExchangeFee(fee) => fee * size,
+                CostComponent::Borrow { .. } => 0.0,
+            })
+            .sum()
     }
 
     pub fn adjust_fill_price(&self, order_price: f64, size: f64, is_buy: bool) -> f64 {
+        self.fill_price(order_price, size, is_buy, None)
+    }
+
+    /// [`adjust_fill_price`](Self::adjust_fill_price) given the instrument's own
+    /// `(bid, ask)` when the event has one, which [`MidFill`] fills around
+    pub fn fill_price(
+        &self,
+        order_price: f64,
+        size: f64,
+        is_buy: bool,
+        quote: Option<(f64, f64)>,
+    ) -> f64 {
         let slippage_amount = match &self.slippage {
             SlippageModel::Fixed(bps) => (bps / 10000.0) * order_price,
             SlippageModel::Linear(factor) => {
@@ -101,105 +220,192 @@ impl TransactionCosts {
             }
         };
 
-        let spread_cost = self.calculate_spread(order_price) / 2.0;
+        let (base, spread_cost) = match (self.mid_fill, quote) {
+            (Some(fill), Some((bid, ask))) => {
+                ((bid + ask) / 2.0, (ask - bid) / 2.0 * fill.share(size))
+            }
+            (Some(fill), None) => (
+                order_price,
+                self.calculate_spread(order_price) / 2.0 * fill.share(size),
+            ),
+            (None, _) => (order_price, self.calculate_spread(order_price) / 2.0),
+        };
         let total_impact = slippage_amount + spread_cost;
 
         if is_buy {
-            order_price + total_impact
+            base + total_impact
         } else {
-            order_price - total_impact
+            base - total_impact
         }
     }
 
     fn calculate_commission(&self, price: f64, size: f64, _volume: f64) -> f64 {
-        match &self.commission {
-            CommissionModel::Fixed(fee) => *fee,
-            CommissionModel::PerShare(rate) => rate * size,
-            CommissionModel::Percentage(pct) => (pct / 100.0) * price * size,
-            CommissionModel::Tiered(tiers) => {
-                let trade_value = price * size;
-                for (threshold, rate) in tiers {
-                    if trade_value <= *threshold {
-                        return rate * trade_value;
-                    }
+        commission_cost(&self.commission, price, size)
+    }
+
+    fn calculate_slippage(&self, price: f64, size: f64, volume: f64, _is_entry: bool) -> f64 {
+        slippage_cost(
+            &self.slippage,
+            price,
+            size,
+            volume,
+            self.calculate_spread(price),
+        )
+    }
+
+    fn calculate_spread(&self, price: f64) -> f64 {
+        spread_cost(&self.spread, price)
+    }
+}
+
+fn commission_cost(model: &CommissionModel, price: f64, size: f64) -> f64 {
+    match model {
+        CommissionModel::Fixed(fee) => *fee,
+        CommissionModel::PerShare(rate) => rate * size,
+        CommissionModel::Percentage(pct) => (pct / 100.0) * price * size,
+        CommissionModel::Tiered(tiers) => {
+            let trade_value = price * size;
+            for (threshold, rate) in tiers {
+                if trade_value <= *threshold {
+                    return rate * trade_value;
                 }
-                // If above all tiers, use the last tier rate
-                tiers.last().map_or(0.0, |(_, rate)| rate * trade_value)
             }
+            // If above all tiers, use the last tier rate
+            tiers.last().map_or(0.0, |(_, rate)| rate * trade_value)
         }
     }
+}
 
-    fn calculate_slippage(&self, price: f64, size: f64, volume: f64, _is_entry: bool) -> f64 {
-        match &self.slippage {
-            SlippageModel::Fixed(bps) => (bps / 10000.0) * price * size,
-            SlippageModel::Linear(factor) => {
-                let impact = factor * (size / volume).min(1.0);
-                (impact / 10000.0) * price * size
-            }
-            SlippageModel::SquareRoot(factor) => {
-                let impact = factor * (size / volume).sqrt();
-                (impact / 10000.0) * price * size
-            }
-            SlippageModel::TickBased(ticks) => ticks * size,
-            SlippageModel::MarketImpact {
-                permanent,
-                temporary,
-                liquidity_factor,
-            } => {
-                let participation_rate = size / volume;
-                let perm_impact = permanent * participation_rate.powf(0.5);
-                let temp_impact = temporary * participation_rate.powf(0.5);
-                let liquidity_adj = 1.0 + liquidity_factor * (1.0 - (volume / 1000000.0).min(1.0));
+// `spread` is the quoted spread, used by the options model
+fn slippage_cost(model: &SlippageModel, price: f64, size: f64, volume: f64, spread: f64) -> f64 {
+    match model {
+        SlippageModel::Fixed(bps) => (bps / 10000.0) * price * size,
+        SlippageModel::Linear(factor) => {
+            let impact = factor * (size / volume).min(1.0);
+            (impact / 10000.0) * price * size
+        }
+        SlippageModel::SquareRoot(factor) => {
+            let impact = factor * (size / volume).sqrt();
+            (impact / 10000.0) * price * size
+        }
+        SlippageModel::TickBased(ticks) => ticks * size,
+        SlippageModel::MarketImpact {
+            permanent,
+            temporary,
+            liquidity_factor,
+        } => {
+            let participation_rate = size / volume;
+            let perm_impact = permanent * participation_rate.powf(0.5);
+            let temp_impact = temporary * participation_rate.powf(0.5);
+            let liquidity_adj = 1.0 + liquidity_factor * (1.0 - (volume / 1000000.0).min(1.0));
 
-                ((perm_impact + temp_impact) * liquidity_adj / 10000.0) * price * size
-            }
-            SlippageModel::OptionsSlippage {
-                base_slippage_bps,
-                liquidity_factor,
-                bid_ask_multiplier,
-            } => {
-                let participation_rate = (size / volume).min(1.0);
-                let liquidity_penalty = if participation_rate > 0.1 {
-                    liquidity_factor * participation_rate
-                } else {
-                    1.0
-                };
+            ((perm_impact + temp_impact) * liquidity_adj / 10000.0) * price * size
+        }
+        SlippageModel::OptionsSlippage {
+            base_slippage_bps,
+            liquidity_factor,
+            bid_ask_multiplier,
+        } => {
+            let participation_rate = (size / volume).min(1.0);
+            let liquidity_penalty = if participation_rate > 0.1 {
+                liquidity_factor * participation_rate
+            } else {
+                1.0
+            };
 
-                // Base slippage cost
-                let base_cost = (base_slippage_bps * liquidity_penalty / 10000.0) * price * size;
+            // Base slippage cost
+            let base_cost = (base_slippage_bps * liquidity_penalty / 10000.0) * price * size;
 
-                // Additional bid-ask spread cost
-                let spread = self.calculate_spread(price);
-                let spread_cost = bid_ask_multiplier * spread * size;
+            // Additional bid-ask spread cost
+            let spread_cost = bid_ask_multiplier * spread * size;
 
-                base_cost + spread_cost
-            }
+            base_cost + spread_cost
         }
     }
+}
 
-    fn calculate_spread(&self, price: f64) -> f64 {
-        match &self.spread {
-            SpreadModel::Fixed(spread) => *spread,
-            SpreadModel::Percentage(pct) => (pct / 100.0) * price,
-            SpreadModel::TimeDependent(_) => {
-                // Simplified - not every schema has bid and ask. Assuming constant spread for now.
-                0.01 * price // 1% default
-            }
-            SpreadModel::OptionsBidAsk {
-                min_spread,
-                spread_pct,
-                max_spread_pct,
-            } => {
-                let percentage_spread = (spread_pct / 100.0) * price;
-                let max_spread = (max_spread_pct / 100.0) * price;
+fn spread_cost(model: &SpreadModel, price: f64) -> f64 {
+    match model {
+        SpreadModel::Fixed(spread) => *spread,
+        SpreadModel::Percentage(pct) => (pct / 100.0) * price,
+        SpreadModel::TimeDependent(_) => {
+            // Simplified - not every schema has bid and ask. Assuming constant spread for now.
+            0.01 * price // 1% default
+        }
+        SpreadModel::OptionsBidAsk {
+            min_spread,
+            spread_pct,
+            max_spread_pct,
+        } => {
+            let percentage_spread = (spread_pct / 100.0) * price;
+            let max_spread = (max_spread_pct / 100.0) * price;
 
-                // Use the larger of minimum spread or percentage spread, but cap at max
-                percentage_spread.max(*min_spread).min(max_spread)
-            }
+            // Use the larger of minimum spread or percentage spread, but cap at max
+            percentage_spread.max(*min_spread).min(max_spread)
         }
     }
 }
 
+/// Which instruments a [`CostOverride`] applies to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InstrumentSelector {
+    InstrumentId(u32),
+    /// Instrument class from the definitions, e.g. 'F' futures, 'C' / 'P' options, 'K' stocks
+    InstrumentClass(char),
+    /// Raw symbols starting with the prefix
+    SymbolPrefix(String),
+    /// Every event that is not an option trade, the underlying rows of a combined options
+    /// stream
+    Underlying,
+}
+
+impl InstrumentSelector {
+    pub(crate) fn matches(&self, event: &MarketEvent, symbology: &SymbologyMap) -> bool {
+        let instrument_id = event.get_u64("instrument_id").map(|id| id as u32);
+        let info = instrument_id.and_then(|id| symbology.get(id, event.timestamp()));
+
+        match self {
+            InstrumentSelector::InstrumentId(id) => instrument_id == Some(*id),
+            InstrumentSelector::InstrumentClass(class) => match event {
+                // Option legs of a combined stream carry their type without a definition
+                MarketEvent::OptionTrade(m) => m.option_type.starts_with(*class),
+                _ => info.is_some_and(|i| i.instrument_class == *class),
+            },
+            InstrumentSelector::SymbolPrefix(prefix) => match event {
+                MarketEvent::OptionTrade(m) => m.symbol.starts_with(prefix.as_str()),
+                _ => info.is_some_and(|i| i.raw_symbol.starts_with(prefix.as_str())),
+            },
+            InstrumentSelector::Underlying => !matches!(event, MarketEvent::OptionTrade(_)),
+        }
+    }
+}
+
+/// Costs used instead of the backtest's default for the instruments matching `selector`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostOverride {
+    pub selector: InstrumentSelector,
+    pub costs: TransactionCosts,
+}
+
+impl CostOverride {
+    pub fn new(selector: InstrumentSelector, costs: TransactionCosts) -> Self {
+        Self { selector, costs }
+    }
+}
+
+/// The costs of the first override matching the event, `default` when none does
+pub fn resolve_costs<'a>(
+    overrides: &'a [CostOverride],
+    default: &'a TransactionCosts,
+    event: &MarketEvent,
+    symbology: &SymbologyMap,
+) -> &'a TransactionCosts {
+    overrides
+        .iter()
+        .find(|o| o.selector.matches(event, symbology))
+        .map_or(default, |o| &o.costs)
+}
+
 // configurations for different markets
 impl TransactionCosts {
     pub fn equity_trading() -> Self {
@@ -207,6 +413,8 @@ impl TransactionCosts {
             commission: CommissionModel::Fixed(0.0), // Many brokers are zero commission now
             slippage: SlippageModel::Fixed(2.0),     // 2 basis points
             spread: SpreadModel::Percentage(0.01),   // 1 basis point
+            components: Vec::new(),
+            mid_fill: None,
         }
     }
 
@@ -215,6 +423,8 @@ impl TransactionCosts {
             commission: CommissionModel::Fixed(2.50),
             slippage: SlippageModel::TickBased(tick_size), // 1 tick of slippage
             spread: SpreadModel::Fixed(tick_size), // tick size for the future you are testing
+            components: Vec::new(),
+            mid_fill: None,
         }
     }
 
@@ -231,6 +441,110 @@ impl TransactionCosts {
                 spread_pct: 2.0,      // 2% of option price
                 max_spread_pct: 50.0, // Cap at 50% for very cheap options
             },
+            components: Vec::new(),
+            mid_fill: None,
+        }
+    }
+
+    /// IBKR Pro tiered US equities at the lowest volume tier: $0.0035 per share plus
+    /// clearing and pass-through fees. The $0.35 minimum and 1% cap are not modelled.
+    pub fn ibkr_tiered_equities() -> Self {
+        Self {
+            commission: CommissionModel::PerShare(0.0035),
+            slippage: SlippageModel::Fixed(2.0),
+            spread: SpreadModel::Percentage(0.01),
+            components: vec![CostComponent::ExchangeFee(0.0003)],
+            mid_fill: None,
+        }
+    }
+
+    /// IBKR tiered CME futures: $0.85 per contract plus roughly $1.40 exchange and
+    /// regulatory fees for an E-mini
+    pub fn ibkr_futures(tick_size: f64) -> Self {
+        Self {
+            commission: CommissionModel::PerShare(0.85),
+            slippage: SlippageModel::TickBased(tick_size),
+            spread: SpreadModel::Fixed(tick_size),
+            components: vec![CostComponent::ExchangeFee(1.40)],
+            mid_fill: None,
+        }
+    }
+
+    /// tastytrade equity options: $1.00 per contract to open and nothing to close, charged
+    /// here as $0.50 each side, plus $0.10 clearing per contract. The $10 per leg cap is not
+    /// modelled.
+    pub fn tastytrade_options() -> Self {
+        Self {
+            commission: CommissionModel::PerShare(0.50),
+            components: vec![CostComponent::ExchangeFee(0.10)],
+            ..Self::options_trading()
+        }
+    }
+
+    /// Frictionless fills, the ideal against which cost drag is measured
+    pub fn zero() -> Self {
+        Self {
+            commission: CommissionModel::Fixed(0.0),
+            slippage: SlippageModel::Fixed(0.0),
+            spread: SpreadModel::Fixed(0.0),
+            components: Vec::new(),
+            mid_fill: None,
+        }
+    }
+
+    /// Crypto exchange taker at a 0.10% fee on notional
+    pub fn crypto_taker() -> Self {
+        Self {
+            commission: CommissionModel::Percentage(0.10),
+            slippage: SlippageModel::Fixed(5.0),
+            spread: SpreadModel::Percentage(0.02),
+            components: Vec::new(),
+            mid_fill: None,
+        }
+    }
+
+    /// Look a preset up by name, for picking costs from configuration. Futures presets
+    /// take the tick size after a colon, e.g. `"ibkr_futures:0.25"`; see [`PRESET_NAMES`].
+    pub fn preset(name: &str) -> Result<Self> {
+        let (name, arg) = match name.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (name, None),
+        };
+        let tick_size = || -> Result<f64> {
+            let arg = arg.ok_or_else(|| {
+                anyhow::anyhow!("Cost preset {} needs a tick size, e.g. {}:0.25", name, name)
+            })?;
+            arg.trim()
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid tick size {} for cost preset {}", arg, name))
+        };
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "equity" => Ok(Self::equity_trading()),
+            "futures" => Ok(Self::futures_trading(tick_size()?)),
+            "options" => Ok(Self::options_trading()),
+            "ibkr_tiered_equities" => Ok(Self::ibkr_tiered_equities()),
+            "ibkr_futures" => Ok(Self::ibkr_futures(tick_size()?)),
+            "tastytrade_options" => Ok(Self::tastytrade_options()),
+            "crypto_taker" => Ok(Self::crypto_taker()),
+            "zero" => Ok(Self::zero()),
+            other => Err(anyhow::anyhow!(
+                "Unknown cost preset {}, expected one of {}",
+                other,
+                PRESET_NAMES.join(", ")
+            )),
         }
     }
 }
+
+/// Names accepted by [`TransactionCosts::preset`]
+pub const PRESET_NAMES: &[&str] = &[
+    "equity",
+    "futures:<tick_size>",
+    "options",
+    "ibkr_tiered_equities",
+    "ibkr_futures:<tick_size>",
+    "tastytrade_options",
+    "crypto_taker",
+    "zero",
+];
@@ -1,12 +1,50 @@
+use crate::context::EventCtx;
 use crate::event::MarketEvent;
+use crate::hedge::UNDERLYING_LEG;
+use crate::indicators::BarHistory;
+use crate::requirements::EventRequirements;
+use crate::symbology::SymbologyMap;
+use databento::dbn::InstrumentDefMsg;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 pub trait Strategy {
     fn on_event(&mut self, event: &MarketEvent, prev: Option<&MarketEvent>) -> Option<Order>;
+
+    /// What the engine calls for every event. Override instead of `on_event` to read the
+    /// engine services (VWAP, ...) in the context; the default forwards to `on_event`.
+    fn on_event_ctx(
+        &mut self,
+        event: &MarketEvent,
+        prev: Option<&MarketEvent>,
+        _ctx: &EventCtx,
+    ) -> Option<Order> {
+        self.on_event(event, prev)
+    }
+
+    /// Called for every instrument definition in the stream, after the engine has recorded
+    /// it in the point-in-time symbology map. Definitions never reach `on_event`; override
+    /// this to opt in to them.
+    fn on_definition(&mut self, _def: &InstrumentDefMsg, _symbology: &SymbologyMap) {}
+
+    /// Called when the engine cancels a pending limit order under its time in force
+    fn on_order_cancelled(&mut self, _order: &Order, _reason: CancelReason) {}
+
+    /// Called when a pre-trade check refuses an order, with the check's name and reason
+    fn on_order_rejected(&mut self, _order: &Order, _check: &str, _reason: &str) {}
+
+    /// Called when the engine itself closes the position, e.g. an option settling at
+    /// expiration, so the strategy can reset its view of it
+    fn on_position_closed(&mut self, _reason: ExitReason) {}
+
+    /// Event variants and fields the strategy needs, verified against the data before the
+    /// backtest runs. The default requires nothing.
+    fn requirements(&self) -> EventRequirements {
+        EventRequirements::default()
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum OrderType {
     MarketBuy,
     MarketSell,
@@ -14,14 +52,225 @@ pub enum OrderType {
     LimitSell,
 }
 
+/// How long a limit order rests before the engine cancels it. Market orders fill on the
+/// next event regardless.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TimeInForce {
+    /// Good till cancelled, the order rests until it fills
+    #[default]
+    Gtc,
+    /// Cancelled at the next session open (see `VwapConfig::session_open`)
+    Day,
+    /// Good till the nanosecond timestamp
+    Gtd(u64),
+    /// Immediate or cancel: fill on the next event or be cancelled
+    Ioc,
+    /// Fill or kill: fill in full on the next event, or be cancelled. Requires the event's
+    /// volume to cover the order size.
+    Fok,
+}
+
+/// Why the engine cancelled a pending order
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CancelReason {
+    /// A Day or GTD order reached its expiry
+    Expired,
+    /// An IOC or FOK order could not fill on the next event
+    NotFilled,
+    /// The position a reduce-only order was resting against closed before it filled
+    PositionClosed,
+}
+
+/// Why a position was closed, set by whichever component closed it
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+pub enum ExitReason {
+    /// A strategy exit without a more specific reason
+    #[default]
+    Strategy,
+    Target,
+    Stop,
+    Trailing,
+    TimeStop,
+    /// An option held to expiration, settled at intrinsic value
+    Expiration,
+    RiskLimit,
+    #[serde(alias = "End")]
+    EndOfData,
+    /// Closed by a roll into the next expiry
+    Roll,
+}
+
+/// Distance of a bracket leg from the entry price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BracketDistance {
+    /// Fixed distance in price
+    Price(f64),
+    /// `multiple` average true ranges of the instrument's last `window` events
+    Atr { multiple: f64, window: usize },
+    /// `multiple` times the entry price times the standard deviation of the instrument's
+    /// last `window` returns
+    RealizedVol { multiple: f64, window: usize },
+}
+
+impl BracketDistance {
+    /// `multiple * ATR(window)`, e.g. `BracketDistance::atr(2.0, 14)`
+    pub fn atr(multiple: f64, window: usize) -> Self {
+        BracketDistance::Atr { multiple, window }
+    }
+
+    pub fn realized_vol(multiple: f64, window: usize) -> Self {
+        BracketDistance::RealizedVol { multiple, window }
+    }
+
+    /// Distance in price from an entry at `price`, None while the indicator lacks history
+    /// or the distance is not positive
+    pub fn resolve(
+        &self,
+        price: f64,
+        instrument: Option<u32>,
+        history: &BarHistory,
+    ) -> Option<f64> {
+        let distance = match *self {
+            BracketDistance::Price(distance) => distance,
+            BracketDistance::Atr { multiple, window } => {
+                multiple * history.atr(instrument, window)?
+            }
+            BracketDistance::RealizedVol { multiple, window } => {
+                multiple * price * history.realized_vol(instrument, window)?
+            }
+        };
+        (distance.is_finite() && distance > 0.0).then_some(distance)
+    }
+}
+
+/// Stop and target the engine attaches to the position an entry opens, resolved to prices
+/// at the fill
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bracket {
+    pub stop: Option<BracketDistance>,
+    pub target: Option<BracketDistance>,
+}
+
+impl Bracket {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_stop(mut self, distance: BracketDistance) -> Self {
+        self.stop = Some(distance);
+        self
+    }
+
+    pub fn with_target(mut self, distance: BracketDistance) -> Self {
+        self.target = Some(distance);
+        self
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Order {
     pub order_type: OrderType,
     pub price: f64,
+    pub time_in_force: TimeInForce,
+    /// Units to trade, entries capped at what equity and exposure allow. None sizes entries
+    /// from equity and exposure and closes the whole position on exit. A sized order in the
+    /// direction of the open position adds a lot.
+    pub quantity: Option<f64>,
+    /// Instrument id of a hedge leg. Such orders trade a separate leg held alongside the
+    /// main position and fill on that instrument's next event.
+    pub instrument: Option<u32>,
+    /// Initial risk per unit in price, typically the stop distance, for R-multiples
+    pub risk: Option<f64>,
+    /// Reason recorded on the trades an exit order closes
+    pub exit_reason: ExitReason,
+    /// Only ever reduce the position: never open, add to or flip it. Reduce-only limit
+    /// orders against the open position rest and close part of it when the price reaches
+    /// them.
+    pub reduce_only: bool,
+    /// Nanoseconds an entry's position may be held before the engine closes it at the next
+    /// price of its instrument, tagged [`ExitReason::TimeStop`]
+    pub max_holding: Option<u64>,
+    /// Stop and target the engine manages for the position this entry opens
+    pub bracket: Option<Bracket>,
+}
+
+impl Order {
+    /// A good-till-cancelled order
+    pub fn new(order_type: OrderType, price: f64) -> Self {
+        Self {
+            order_type,
+            price,
+            time_in_force: TimeInForce::Gtc,
+            quantity: None,
+            instrument: None,
+            risk: None,
+            exit_reason: ExitReason::Strategy,
+            reduce_only: false,
+            max_holding: None,
+            bracket: None,
+        }
+    }
+
+    pub fn with_quantity(mut self, quantity: f64) -> Self {
+        self.quantity = Some(quantity);
+        self
+    }
+
+    /// Tag an entry with its initial risk per unit, e.g. `(entry - stop).abs()`
+    pub fn with_risk(mut self, risk: f64) -> Self {
+        self.risk = Some(risk);
+        self
+    }
+
+    /// Record the exit as e.g. a stop or target rather than a plain strategy exit
+    pub fn with_exit_reason(mut self, reason: ExitReason) -> Self {
+        self.exit_reason = reason;
+        self
+    }
+
+    /// Make the order reduce-only, e.g. a take-profit on part of the position that must not
+    /// become an entry once the position is gone
+    pub fn reduce_only(mut self) -> Self {
+        self.reduce_only = true;
+        self
+    }
+
+    /// Close the position this entry opens once it has been held for `holding`
+    pub fn with_max_holding(mut self, holding: time::Duration) -> Self {
+        self.max_holding = Some(holding.whole_nanoseconds().max(0) as u64);
+        self
+    }
+
+    /// Attach a stop and target to the position this entry opens. The stop distance also
+    /// serves as the entry's risk unless [`Order::with_risk`] sets one.
+    pub fn with_bracket(mut self, bracket: Bracket) -> Self {
+        self.bracket = Some(bracket);
+        self
+    }
+
+    /// Route the order to the hedge leg of `instrument_id`
+    pub fn on_instrument(mut self, instrument_id: u32) -> Self {
+        self.instrument = Some(instrument_id);
+        self
+    }
+
+    /// Route the order to a leg in the underlying itself, which fills on the next event that
+    /// is not an option trade. In a combined options stream this trades the underlying
+    /// alongside the options held, long or short, e.g. to delta hedge.
+    pub fn on_underlying(self) -> Self {
+        self.on_instrument(UNDERLYING_LEG)
+    }
+
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
 }
 
 /// Holds parameters used to configure a trading strategy
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct StrategyParams {
     params: HashMap<String, f64>,
 }
@@ -54,4 +303,20 @@ impl StrategyParams {
         parts.sort();
         parts.join(", ")
     }
+
+    /// Keys and values in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.params.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+
+    /// Parse [`StrategyParams::to_string_representation`] back, None when a part is not
+    /// `key: value`
+    pub fn parse(representation: &str) -> Option<Self> {
+        let mut params = Self::new();
+        for part in representation.split(", ").filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once(": ")?;
+            params.insert(key, value.parse().ok()?);
+        }
+        Some(params)
+    }
 }
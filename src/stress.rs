@@ -0,0 +1,122 @@
+use crate::greeks;
+use serde::{Deserialize, Serialize};
+
+/// Shock grid applied to open positions at each day close
+#[derive(Debug, Clone)]
+pub struct StressConfig {
+    /// Relative underlying moves, e.g. -0.10 for a 10% drop
+    pub spot_shocks: Vec<f64>,
+    /// Absolute implied volatility moves, e.g. 0.05 for +5 vols; only options are affected
+    pub vol_shocks: Vec<f64>,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            spot_shocks: vec![-0.10, -0.05, 0.05, 0.10],
+            vol_shocks: vec![-0.05, 0.0, 0.05],
+        }
+    }
+}
+
+/// What an open position is exposed to
+#[derive(Debug, Clone, Copy)]
+pub enum Exposure {
+    /// The position is the underlying itself at `price`
+    Underlying { price: f64 },
+    /// An option on an underlying at `spot`, `years` to expiry
+    Option {
+        is_call: bool,
+        strike: f64,
+        spot: f64,
+        years: f64,
+        implied_vol: f64,
+        rate: f64,
+    },
+}
+
+/// An open position to stress, `quantity` negative when short
+#[derive(Debug, Clone, Copy)]
+pub struct StressedPosition {
+    pub quantity: f64,
+    /// Dollar value of a one point move per unit
+    pub multiplier: f64,
+    pub exposure: Exposure,
+}
+
+/// Hypothetical P&L of the position under one shock
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Scenario {
+    pub spot_shock: f64,
+    pub vol_shock: f64,
+    pub pnl: f64,
+}
+
+/// The stress grid of the position held at a day close
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StressPoint {
+    pub ts: u64,
+    pub date: String,
+    pub equity: f64,
+    pub scenarios: Vec<Scenario>,
+    /// Lowest P&L of the grid, zero when every scenario gains
+    pub worst_pnl: f64,
+}
+
+impl StressPoint {
+    /// Equity had the worst scenario happened
+    pub fn worst_case_equity(&self) -> f64 {
+        self.equity + self.worst_pnl
+    }
+}
+
+/// Reprice `position` under every spot and volatility shock of `config`.
+///
+/// Options are repriced with Black-Scholes against their model value at the unshocked
+/// inputs, so the P&L is the move alone. The underlying ignores the volatility shocks.
+pub fn stress_test(position: &StressedPosition, config: &StressConfig) -> Vec<Scenario> {
+    let units = position.quantity * position.multiplier;
+    let vol_shocks: &[f64] = match position.exposure {
+        Exposure::Underlying { .. } => &[0.0],
+        Exposure::Option { .. } => &config.vol_shocks,
+    };
+
+    let mut scenarios = Vec::with_capacity(config.spot_shocks.len() * vol_shocks.len());
+    for &spot_shock in &config.spot_shocks {
+        for &vol_shock in vol_shocks {
+            let move_per_unit = match position.exposure {
+                Exposure::Underlying { price } => price * spot_shock,
+                Exposure::Option {
+                    is_call,
+                    strike,
+                    spot,
+                    years,
+                    implied_vol,
+                    rate,
+                } => {
+                    let base = greeks::price(is_call, spot, strike, years, rate, implied_vol);
+                    let shocked = greeks::price(
+                        is_call,
+                        spot * (1.0 + spot_shock),
+                        strike,
+                        years,
+                        rate,
+                        (implied_vol + vol_shock).max(1e-4),
+                    );
+                    shocked - base
+                }
+            };
+            scenarios.push(Scenario {
+                spot_shock,
+                vol_shock,
+                pnl: move_per_unit * units,
+            });
+        }
+    }
+    scenarios
+}
+
+/// Worst P&L of a grid, never above zero
+pub fn worst_pnl(scenarios: &[Scenario]) -> f64 {
+    scenarios.iter().map(|s| s.pnl).fold(0.0, f64::min)
+}
@@ -0,0 +1,117 @@
+use anyhow::{Context, Result};
+use databento::dbn::{decode::AsyncDbnDecoder, InstrumentDefMsg};
+use std::collections::HashMap;
+
+/// Instrument attributes taken from a definition record, valid from `valid_from` until
+/// the next definition for the same instrument id
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentInfo {
+    pub instrument_id: u32,
+    pub raw_symbol: String,
+    pub instrument_class: char,
+    /// Minimum price increment in price units
+    pub tick_size: f64,
+    /// Dollar value of a one point move
+    pub multiplier: f64,
+    pub strike_price: Option<f64>,
+    /// Expiration in nanoseconds, None when undefined
+    pub expiration: Option<u64>,
+    pub valid_from: u64,
+}
+
+impl InstrumentInfo {
+    pub fn from_definition(def: &InstrumentDefMsg) -> Self {
+        let tick_size = def.min_price_increment_f64();
+        let tick_amount = def.min_price_increment_amount_f64();
+
+        // Futures publish the dollar value of one tick, options a contract multiplier
+        let multiplier = if tick_size.is_finite() && tick_size > 0.0 && tick_amount.is_finite() {
+            tick_amount / tick_size
+        } else if def.contract_multiplier > 0 && def.contract_multiplier != i32::MAX {
+            def.contract_multiplier as f64
+        } else {
+            1.0
+        };
+
+        let strike = def.strike_price_f64();
+
+        Self {
+            instrument_id: def.hd.instrument_id,
+            raw_symbol: def.raw_symbol().unwrap_or_default().to_string(),
+            instrument_class: def.instrument_class as u8 as char,
+            tick_size: if tick_size.is_finite() {
+                tick_size
+            } else {
+                0.0
+            },
+            multiplier,
+            strike_price: strike.is_finite().then_some(strike),
+            expiration: (def.expiration != u64::MAX).then_some(def.expiration),
+            valid_from: def.hd.ts_event,
+        }
+    }
+}
+
+/// Point-in-time map of instrument id to instrument attributes.
+///
+/// Instrument ids are reused by venues and definitions are re-published when contract
+/// terms change, so every definition is kept and lookups return the one in force at the
+/// requested timestamp.
+#[derive(Debug, Clone, Default)]
+pub struct SymbologyMap {
+    history: HashMap<u32, Vec<InstrumentInfo>>,
+}
+
+impl SymbologyMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every definition of a DBN definition file
+    pub async fn from_definition_file(path: &str) -> Result<Self> {
+        let mut decoder = AsyncDbnDecoder::from_zstd_file(path)
+            .await
+            .with_context(|| format!("Failed to open definition file {}", path))?;
+
+        let mut map = Self::new();
+        while let Some(def) = decoder.decode_record::<InstrumentDefMsg>().await? {
+            map.update(def);
+        }
+        Ok(map)
+    }
+
+    /// Record a definition, keeping each instrument's history sorted by `valid_from`
+    pub fn update(&mut self, def: &InstrumentDefMsg) {
+        let info = InstrumentInfo::from_definition(def);
+        let entries = self.history.entry(info.instrument_id).or_default();
+
+        match entries.binary_search_by_key(&info.valid_from, |e| e.valid_from) {
+            Ok(i) => entries[i] = info,
+            Err(i) => entries.insert(i, info),
+        }
+    }
+
+    /// The definition of `instrument_id` in force at `ts`
+    pub fn get(&self, instrument_id: u32, ts: u64) -> Option<&InstrumentInfo> {
+        let entries = self.history.get(&instrument_id)?;
+        let idx = entries.partition_point(|e| e.valid_from <= ts);
+        idx.checked_sub(1).map(|i| &entries[i])
+    }
+
+    /// The most recent definition of `instrument_id`
+    pub fn latest(&self, instrument_id: u32) -> Option<&InstrumentInfo> {
+        self.history.get(&instrument_id).and_then(|e| e.last())
+    }
+
+    pub fn symbol_at(&self, instrument_id: u32, ts: u64) -> Option<&str> {
+        self.get(instrument_id, ts).map(|i| i.raw_symbol.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+}
@@ -0,0 +1,444 @@
+use crate::audit::{AuditEvent, AuditRecord, OrderOrigin};
+use crate::backtester::{run_backtest_on_stream, BacktestOptions, BacktestResult, Trade};
+use crate::event::{to_fixed_price, MarketEvent};
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::{ExitReason, OrderType, Strategy};
+use crate::utils::fetch::MarketStream;
+use crate::utils::synthetic;
+use anyhow::Result;
+use databento::dbn::{BidAskPair, Mbp1Msg, OhlcvMsg, RType, RecordHeader, TradeMsg};
+use futures::StreamExt;
+
+/// Starting equity of [`MockStream::run`]
+pub const MOCK_EQUITY: f64 = 10_000.0;
+
+/// A short, hand-written sequence of events for strategy unit tests, run without data
+/// files or a DataBento key.
+///
+/// Each added event is stamped with the builder's clock, which then moves on by the
+/// interval.
+#[derive(Debug, Clone)]
+pub struct MockStream {
+    events: Vec<MarketEvent>,
+    next_ts: u64,
+    interval: u64,
+    instrument_id: u32,
+}
+
+impl Default for MockStream {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            // 2024-01-02 14:30 UTC, as in SyntheticMarket
+            next_ts: 1_704_205_800_000_000_000,
+            interval: 60_000_000_000,
+            instrument_id: 1,
+        }
+    }
+}
+
+impl MockStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stamp the next event `start_ts` and those after it `interval` nanoseconds apart
+    pub fn with_start(mut self, start_ts: u64, interval: u64) -> Self {
+        self.next_ts = start_ts;
+        self.interval = interval;
+        self
+    }
+
+    /// Instrument id of the events added from here on
+    pub fn with_instrument(mut self, instrument_id: u32) -> Self {
+        self.instrument_id = instrument_id;
+        self
+    }
+
+    fn header<R: databento::dbn::HasRType>(&mut self, rtype: RType) -> (RecordHeader, u64) {
+        let ts = self.next_ts;
+        self.next_ts += self.interval;
+        (
+            RecordHeader::new::<R>(rtype.into(), 0, self.instrument_id, ts),
+            ts,
+        )
+    }
+
+    /// A one-minute OHLCV bar
+    pub fn bar(mut self, open: f64, high: f64, low: f64, close: f64, volume: u64) -> Self {
+        let (hd, _) = self.header::<OhlcvMsg>(RType::Ohlcv1M);
+        self.events.push(MarketEvent::Ohlcv(OhlcvMsg {
+            hd,
+            open: to_fixed_price(open),
+            high: to_fixed_price(high),
+            low: to_fixed_price(low),
+            close: to_fixed_price(close),
+            volume,
+        }));
+        self
+    }
+
+    /// One flat bar per price, opening, closing, and trading 1000 units at it
+    pub fn closes(self, prices: &[f64]) -> Self {
+        prices
+            .iter()
+            .fold(self, |mock, &p| mock.bar(p, p, p, p, 1000))
+    }
+
+    /// A trade print of unknown aggressor side
+    pub fn trade(self, price: f64, size: u32) -> Self {
+        self.trade_with_side(price, size, b'N')
+    }
+
+    /// A trade print with DataBento's side byte: `B` buyer initiated, `A` seller initiated
+    pub fn trade_with_side(mut self, price: f64, size: u32, side: u8) -> Self {
+        let (hd, ts) = self.header::<TradeMsg>(RType::Mbp0);
+        self.events.push(MarketEvent::Trade(TradeMsg {
+            hd,
+            price: to_fixed_price(price),
+            size,
+            action: b'T' as _,
+            side: side as _,
+            ts_recv: ts,
+            ..TradeMsg::default()
+        }));
+        self
+    }
+
+    /// A top-of-book update with 100 units on each side
+    pub fn quote(mut self, bid: f64, ask: f64) -> Self {
+        let (hd, ts) = self.header::<Mbp1Msg>(RType::Mbp1);
+        self.events.push(MarketEvent::Mbp1(Mbp1Msg {
+            hd,
+            price: to_fixed_price((bid + ask) / 2.0),
+            ts_recv: ts,
+            levels: [BidAskPair {
+                bid_px: to_fixed_price(bid),
+                ask_px: to_fixed_price(ask),
+                bid_sz: 100,
+                ask_sz: 100,
+                bid_ct: 1,
+                ask_ct: 1,
+            }],
+            ..Mbp1Msg::default()
+        }));
+        self
+    }
+
+    /// Any event, kept exactly as given. The clock does not move.
+    pub fn event(mut self, event: MarketEvent) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Leave `ns` nanoseconds without events before the next one, e.g. an overnight gap
+    pub fn gap(mut self, ns: u64) -> Self {
+        self.next_ts += ns;
+        self
+    }
+
+    /// Record the first `limit` events of a real stream, e.g. of a cached data file, to
+    /// replay them offline or extend them by hand
+    pub async fn record(mut stream: MarketStream, limit: usize) -> Result<Self> {
+        let mut mock = Self::new();
+        while mock.events.len() < limit {
+            match stream.next().await {
+                Some(event) => mock.events.push(event?),
+                None => break,
+            }
+        }
+        if let Some(last) = mock.events.last() {
+            mock.next_ts = last.timestamp() + mock.interval;
+        }
+        Ok(mock)
+    }
+
+    pub fn events(&self) -> &[MarketEvent] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn into_stream(self) -> MarketStream {
+        synthetic::stream(self.events)
+    }
+
+    /// Run `strategy` over the events without costs, from [`MOCK_EQUITY`] at full
+    /// exposure and with the order audit trail kept
+    pub async fn run(self, strategy: &mut dyn Strategy) -> Result<MockRun> {
+        self.run_with(
+            strategy,
+            TransactionCosts::zero(),
+            &BacktestOptions::default(),
+        )
+        .await
+    }
+
+    /// [`run`](Self::run) with costs and options of your own. The audit trail is kept
+    /// whatever `options` says, the order assertions read it.
+    pub async fn run_with(
+        self,
+        strategy: &mut dyn Strategy,
+        costs: TransactionCosts,
+        options: &BacktestOptions,
+    ) -> Result<MockRun> {
+        let options = BacktestOptions {
+            audit: true,
+            ..options.clone()
+        };
+        let result = run_backtest_on_stream(
+            "MOCK",
+            synthetic::stream(self.events.clone()),
+            strategy,
+            costs,
+            MOCK_EQUITY,
+            1.0,
+            None,
+            None,
+            &options,
+        )
+        .await?;
+        Ok(MockRun {
+            events: self.events,
+            result,
+        })
+    }
+}
+
+/// Relative tolerance of the price and P&L expectations
+const TOLERANCE: f64 = 1e-9;
+
+fn close_to(expected: f64, actual: f64) -> bool {
+    (expected - actual).abs() <= TOLERANCE * expected.abs().max(1.0)
+}
+
+/// An order the strategy should have submitted. Unset fields match anything.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedOrder {
+    pub order_type: OrderType,
+    pub price: Option<f64>,
+    /// Index of the event the order was submitted on
+    pub event: Option<usize>,
+}
+
+impl ExpectedOrder {
+    pub fn new(order_type: OrderType) -> Self {
+        Self {
+            order_type,
+            price: None,
+            event: None,
+        }
+    }
+
+    pub fn buy() -> Self {
+        Self::new(OrderType::MarketBuy)
+    }
+
+    pub fn sell() -> Self {
+        Self::new(OrderType::MarketSell)
+    }
+
+    pub fn at(mut self, price: f64) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn on_event(mut self, index: usize) -> Self {
+        self.event = Some(index);
+        self
+    }
+}
+
+/// A trade the run should have closed. Unset fields match anything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpectedTrade {
+    /// `Long` or `Short`
+    pub trade_type: Option<String>,
+    pub entry_price: Option<f64>,
+    pub exit_price: Option<f64>,
+    pub size: Option<f64>,
+    pub pnl: Option<f64>,
+    pub exit_reason: Option<ExitReason>,
+}
+
+impl ExpectedTrade {
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    pub fn long() -> Self {
+        Self {
+            trade_type: Some("Long".to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn short() -> Self {
+        Self {
+            trade_type: Some("Short".to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn entry(mut self, price: f64) -> Self {
+        self.entry_price = Some(price);
+        self
+    }
+
+    pub fn exit(mut self, price: f64) -> Self {
+        self.exit_price = Some(price);
+        self
+    }
+
+    pub fn size(mut self, size: f64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn pnl(mut self, pnl: f64) -> Self {
+        self.pnl = Some(pnl);
+        self
+    }
+
+    pub fn reason(mut self, reason: ExitReason) -> Self {
+        self.exit_reason = Some(reason);
+        self
+    }
+
+    /// What differs between this expectation and `trade`, empty when it matches
+    pub fn mismatches(&self, trade: &Trade) -> Vec<String> {
+        let mut diffs = Vec::new();
+        if let Some(t) = &self.trade_type {
+            if *t != trade.trade_type {
+                diffs.push(format!("type {} != {}", trade.trade_type, t));
+            }
+        }
+        for (name, expected, actual) in [
+            ("entry", self.entry_price, trade.entry_price),
+            ("exit", self.exit_price, trade.exit_price),
+            ("size", self.size, trade.size),
+            ("pnl", self.pnl, trade.pnl),
+        ] {
+            if let Some(expected) = expected {
+                if !close_to(expected, actual) {
+                    diffs.push(format!("{} {} != {}", name, actual, expected));
+                }
+            }
+        }
+        if let Some(reason) = self.exit_reason {
+            if reason != trade.exit_reason {
+                diffs.push(format!("reason {:?} != {:?}", trade.exit_reason, reason));
+            }
+        }
+        diffs
+    }
+}
+
+/// The result of a [`MockStream`] run, with the events it ran on
+#[derive(Debug)]
+pub struct MockRun {
+    pub events: Vec<MarketEvent>,
+    pub result: BacktestResult,
+}
+
+impl MockRun {
+    /// Orders the strategy submitted, in order
+    pub fn orders(&self) -> Vec<&AuditRecord> {
+        self.result
+            .audit
+            .iter()
+            .filter(|r| r.origin == OrderOrigin::Strategy && r.event == AuditEvent::Submitted)
+            .collect()
+    }
+
+    /// Fills of every order, the engine's exits included
+    pub fn fills(&self) -> Vec<&AuditRecord> {
+        self.result
+            .audit
+            .iter()
+            .filter(|r| {
+                matches!(
+                    r.event,
+                    AuditEvent::Filled { .. } | AuditEvent::PartialFill { .. }
+                )
+            })
+            .collect()
+    }
+
+    /// Panic unless the strategy submitted exactly the `expected` orders, in order
+    pub fn assert_orders(&self, expected: &[ExpectedOrder]) {
+        let orders = self.orders();
+        let mut diffs = Vec::new();
+        if orders.len() != expected.len() {
+            diffs.push(format!(
+                "expected {} orders, got {}",
+                expected.len(),
+                orders.len()
+            ));
+        }
+        for (i, (e, o)) in expected.iter().zip(&orders).enumerate() {
+            if e.order_type != o.order_type {
+                diffs.push(format!(
+                    "order {}: {:?} != {:?}",
+                    i, o.order_type, e.order_type
+                ));
+            }
+            if let Some(price) = e.price {
+                if !close_to(price, o.price) {
+                    diffs.push(format!("order {}: price {} != {}", i, o.price, price));
+                }
+            }
+            if let Some(index) = e.event {
+                let ts = self.events.get(index).map(MarketEvent::timestamp);
+                if ts != Some(o.ts) {
+                    diffs.push(format!(
+                        "order {}: submitted at {} instead of on event {} ({:?})",
+                        i, o.ts, index, ts
+                    ));
+                }
+            }
+        }
+        assert!(
+            diffs.is_empty(),
+            "orders differ:\n  {}\norders: {:#?}",
+            diffs.join("\n  "),
+            orders
+        );
+    }
+
+    /// Panic unless the run closed exactly the `expected` trades, in exit order
+    pub fn assert_trades(&self, expected: &[ExpectedTrade]) {
+        let trades = &self.result.trades;
+        let mut diffs = Vec::new();
+        if trades.len() != expected.len() {
+            diffs.push(format!(
+                "expected {} trades, got {}",
+                expected.len(),
+                trades.len()
+            ));
+        }
+        for (i, (e, t)) in expected.iter().zip(trades).enumerate() {
+            diffs.extend(
+                e.mismatches(t)
+                    .into_iter()
+                    .map(|d| format!("trade {}: {}", i, d)),
+            );
+        }
+        assert!(
+            diffs.is_empty(),
+            "trades differ:\n  {}\ntrades: {:#?}",
+            diffs.join("\n  "),
+            trades
+        );
+    }
+
+    pub fn assert_no_trades(&self) {
+        self.assert_trades(&[]);
+    }
+}
@@ -0,0 +1,481 @@
+use crate::backtester::{BacktestResult, Trade, NANOS_PER_HOUR};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
+use time::{Date, Month, OffsetDateTime, Weekday};
+
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Daylight saving rule of an exchange's time zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DstRule {
+    None,
+    /// Second Sunday of March to the first Sunday of November, at 2:00 local
+    UnitedStates,
+    /// Last Sunday of March to the last Sunday of October, at 1:00 UTC
+    Europe,
+}
+
+/// Converts UTC timestamps to an exchange's local time
+#[derive(Debug, Clone, Copy)]
+pub struct ExchangeClock {
+    /// Offset from UTC outside daylight saving, in minutes
+    pub standard_offset: i32,
+    pub dst: DstRule,
+}
+
+impl ExchangeClock {
+    pub fn utc() -> Self {
+        Self {
+            standard_offset: 0,
+            dst: DstRule::None,
+        }
+    }
+
+    /// NYSE, Nasdaq, CBOE
+    pub fn new_york() -> Self {
+        Self {
+            standard_offset: -5 * 60,
+            dst: DstRule::UnitedStates,
+        }
+    }
+
+    /// CME Globex
+    pub fn chicago() -> Self {
+        Self {
+            standard_offset: -6 * 60,
+            dst: DstRule::UnitedStates,
+        }
+    }
+
+    /// LSE, ICE Europe
+    pub fn london() -> Self {
+        Self {
+            standard_offset: 0,
+            dst: DstRule::Europe,
+        }
+    }
+
+    /// Eurex
+    pub fn frankfurt() -> Self {
+        Self {
+            standard_offset: 60,
+            dst: DstRule::Europe,
+        }
+    }
+
+    /// Local date and time of a nanosecond UTC timestamp, at the offset in force then
+    pub fn local(&self, ts: u64) -> OffsetDateTime {
+        let utc = OffsetDateTime::from_unix_timestamp_nanos(ts as i128)
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+        let standard = utc + time::Duration::minutes(self.standard_offset as i64);
+        let year = standard.year();
+        let in_dst = match self.dst {
+            DstRule::None => false,
+            DstRule::UnitedStates => {
+                // Starts at 2:00 standard time and ends at 2:00 daylight time, which is
+                // 1:00 standard
+                let start = nth_sunday(year, Month::March, 2).midnight().assume_utc()
+                    + time::Duration::hours(2);
+                let end = nth_sunday(year, Month::November, 1).midnight().assume_utc()
+                    + time::Duration::hours(1);
+                let local = standard.replace_offset(time::UtcOffset::UTC);
+                local >= start && local < end
+            }
+            DstRule::Europe => {
+                let start = last_sunday(year, Month::March).midnight().assume_utc()
+                    + time::Duration::hours(1);
+                let end = last_sunday(year, Month::October).midnight().assume_utc()
+                    + time::Duration::hours(1);
+                utc >= start && utc < end
+            }
+        };
+        let minutes = self.standard_offset + if in_dst { 60 } else { 0 };
+        let offset =
+            time::UtcOffset::from_whole_seconds(minutes * 60).unwrap_or(time::UtcOffset::UTC);
+        utc.to_offset(offset)
+    }
+}
+
+fn nth_sunday(year: i32, month: Month, n: u8) -> Date {
+    let first = Date::from_calendar_date(year, month, 1).unwrap_or(Date::MIN);
+    let to_sunday = (7 - first.weekday().number_days_from_sunday()) % 7;
+    first + time::Duration::days((to_sunday + 7 * (n - 1)) as i64)
+}
+
+fn last_sunday(year: i32, month: Month) -> Date {
+    let next = match month {
+        Month::December => Date::from_calendar_date(year + 1, Month::January, 1),
+        _ => Date::from_calendar_date(year, month.next(), 1),
+    }
+    .unwrap_or(Date::MAX);
+    let last = next - time::Duration::days(1);
+    last - time::Duration::days(last.weekday().number_days_from_sunday() as i64)
+}
+
+/// Trading days of an exchange in its local time: weekdays other than its holidays
+#[derive(Debug, Clone)]
+pub struct TradingCalendar {
+    pub clock: ExchangeClock,
+    /// Minutes after local midnight at which a trading day ends, later events count
+    /// toward the next day. 24 * 60 ends days at midnight.
+    pub day_end: u32,
+    pub holidays: BTreeSet<Date>,
+}
+
+impl TradingCalendar {
+    pub fn new(clock: ExchangeClock) -> Self {
+        Self {
+            clock,
+            day_end: 24 * 60,
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    /// NYSE hours, days ending at midnight New York time
+    pub fn nyse() -> Self {
+        Self::new(ExchangeClock::new_york())
+    }
+
+    /// CME Globex, whose trading day ends at 17:00 Chicago time
+    pub fn cme() -> Self {
+        Self::new(ExchangeClock::chicago()).with_day_end(17, 0)
+    }
+
+    pub fn with_day_end(mut self, hour: u32, minute: u32) -> Self {
+        self.day_end = hour * 60 + minute;
+        self
+    }
+
+    pub fn with_holiday(mut self, date: Date) -> Self {
+        self.holidays.insert(date);
+        self
+    }
+
+    pub fn with_holidays(mut self, dates: impl IntoIterator<Item = Date>) -> Self {
+        self.holidays.extend(dates);
+        self
+    }
+
+    /// The trading day a nanosecond UTC timestamp belongs to
+    pub fn trading_date(&self, ts: u64) -> Date {
+        let local = self.clock.local(ts);
+        let minutes = local.hour() as u32 * 60 + local.minute() as u32;
+        if minutes >= self.day_end {
+            local.date().next_day().unwrap_or(Date::MAX)
+        } else {
+            local.date()
+        }
+    }
+
+    pub fn is_trading_day(&self, date: Date) -> bool {
+        !matches!(date.weekday(), Weekday::Saturday | Weekday::Sunday)
+            && !self.holidays.contains(&date)
+    }
+}
+
+/// P&L bucketed by local weekday (Monday first) and hour
+#[derive(Debug, Clone)]
+pub struct PnlByTime {
+    /// Realized P&L of the trades exiting in each bucket
+    pub realized: [[f64; 24]; 7],
+    pub exits: [[usize; 24]; 7],
+    /// Change of the marked equity over each bucket, empty without
+    /// [`BacktestOptions::hourly_marks`]
+    ///
+    /// [`BacktestOptions::hourly_marks`]: crate::backtester::BacktestOptions::hourly_marks
+    pub marked: Option<[[f64; 24]; 7]>,
+}
+
+fn bucket(clock: &ExchangeClock, ts: u64) -> (usize, usize) {
+    let local = clock.local(ts);
+    (
+        local.weekday().number_days_from_monday() as usize,
+        local.hour() as usize,
+    )
+}
+
+/// Bucket a result's P&L by the local weekday and hour of `clock`
+pub fn pnl_by_time(result: &BacktestResult, clock: &ExchangeClock) -> PnlByTime {
+    let mut realized = [[0.0; 24]; 7];
+    let mut exits = [[0; 24]; 7];
+    for trade in result.trades.iter().filter(|t| t.pnl.is_finite()) {
+        let (day, hour) = bucket(clock, trade.exit_ts);
+        realized[day][hour] += trade.pnl;
+        exits[day][hour] += 1;
+    }
+
+    let marked = (!result.hourly_equity.is_empty()).then(|| {
+        let mut marked = [[0.0; 24]; 7];
+        let mut prev = result.starting_equity;
+        for &(ts, equity) in &result.hourly_equity {
+            // Each mark closes the hour its timestamp falls in
+            let (day, hour) = bucket(clock, ts - ts % NANOS_PER_HOUR);
+            marked[day][hour] += equity - prev;
+            prev = equity;
+        }
+        marked
+    });
+
+    PnlByTime {
+        realized,
+        exits,
+        marked,
+    }
+}
+
+fn display_grid(title: &str, grid: &[[f64; 24]; 7]) {
+    let hours: Vec<usize> = (0..24)
+        .filter(|&h| grid.iter().any(|day| day[h] != 0.0))
+        .collect();
+    if hours.is_empty() {
+        return;
+    }
+
+    println!("{}", title);
+    print!("     ");
+    for h in &hours {
+        print!("{:>8}", format!("{:02}h", h));
+    }
+    println!("{:>10}", "Total");
+    for (d, day) in grid.iter().enumerate() {
+        if day.iter().all(|v| *v == 0.0) {
+            continue;
+        }
+        print!("{:<5}", DAYS[d]);
+        for &h in &hours {
+            print!("{:>8.0}", day[h]);
+        }
+        println!("{:>10.0}", day.iter().sum::<f64>());
+    }
+    print!("{:<5}", "All");
+    for &h in &hours {
+        print!("{:>8.0}", grid.iter().map(|day| day[h]).sum::<f64>());
+    }
+    println!(
+        "{:>10.0}",
+        grid.iter().flat_map(|day| day.iter()).sum::<f64>()
+    );
+}
+
+/// Print the realized and, when recorded, marked P&L tables by local weekday and hour
+pub fn display_pnl_by_time(result: &BacktestResult, clock: &ExchangeClock) {
+    let pnl = pnl_by_time(result, clock);
+    println!("\n=== P&L BY WEEKDAY AND HOUR ===");
+    display_grid("Realized P&L by exit time ($)", &pnl.realized);
+    if let Some(marked) = &pnl.marked {
+        display_grid("Marked P&L ($)", marked);
+    }
+
+    // The bucket contributing most, the first candidate for a trading window
+    let best = (0..7)
+        .flat_map(|d| (0..24).map(move |h| (d, h)))
+        .max_by(|a, b| pnl.realized[a.0][a.1].total_cmp(&pnl.realized[b.0][b.1]));
+    let worst = (0..7)
+        .flat_map(|d| (0..24).map(move |h| (d, h)))
+        .min_by(|a, b| pnl.realized[a.0][a.1].total_cmp(&pnl.realized[b.0][b.1]));
+    if let (Some((bd, bh)), Some((wd, wh))) = (best, worst) {
+        println!(
+            "Best: {} {:02}h ${:.0} over {} exits | Worst: {} {:02}h ${:.0} over {} exits",
+            DAYS[bd],
+            bh,
+            pnl.realized[bd][bh],
+            pnl.exits[bd][bh],
+            DAYS[wd],
+            wh,
+            pnl.realized[wd][wh],
+            pnl.exits[wd][wh]
+        );
+    }
+}
+
+/// Entries and exits of a trade log counted by price bucket and exchange-local time of day
+#[derive(Debug, Clone)]
+pub struct TradeHeatmap {
+    /// Lower edge of the lowest price bucket
+    pub price_origin: f64,
+    pub price_step: f64,
+    /// Minutes per time of day bucket
+    pub minutes: u32,
+    /// Entries per `[price bucket][time bucket]`, lowest price first
+    pub entries: Vec<Vec<usize>>,
+    /// Exits per cell, bucketed by exit price and time
+    pub exits: Vec<Vec<usize>>,
+    /// Realized P&L of the trades entered in each cell
+    pub entry_pnl: Vec<Vec<f64>>,
+}
+
+/// One cell of a [`TradeHeatmap`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatmapCell {
+    /// Lower edge of the price bucket
+    pub price: f64,
+    pub time_bucket: usize,
+    pub entries: usize,
+    pub entry_pnl: f64,
+}
+
+impl TradeHeatmap {
+    /// Lower edge of a price bucket
+    pub fn price_of(&self, bucket: usize) -> f64 {
+        self.price_origin + bucket as f64 * self.price_step
+    }
+
+    /// Local start of a time bucket as `HH:MM`
+    pub fn time_label(&self, bucket: usize) -> String {
+        let start = bucket as u32 * self.minutes;
+        format!("{:02}:{:02}", start / 60, start % 60)
+    }
+
+    pub fn total_entries(&self) -> usize {
+        self.entries.iter().flatten().sum()
+    }
+
+    /// The `n` cells with the most entries, most first
+    pub fn busiest(&self, n: usize) -> Vec<HeatmapCell> {
+        let mut cells: Vec<HeatmapCell> = self
+            .entries
+            .iter()
+            .enumerate()
+            .flat_map(|(p, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, &count)| count > 0)
+                    .map(move |(t, &count)| (p, t, count))
+            })
+            .map(|(p, t, entries)| HeatmapCell {
+                price: self.price_of(p),
+                time_bucket: t,
+                entries,
+                entry_pnl: self.entry_pnl[p][t],
+            })
+            .collect();
+        cells.sort_by_key(|c| std::cmp::Reverse(c.entries));
+        cells.truncate(n);
+        cells
+    }
+}
+
+// A 1, 2 or 5 times a power of ten step near `raw`
+fn round_step(raw: f64) -> f64 {
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Count trades by price bucket and local time of day of `clock`, in `minutes` wide
+/// buckets. Prices are bucketed `price_step` apart, by default a round step giving about
+/// 20 buckets over the prices traded.
+pub fn trade_heatmap(
+    trades: &[Trade],
+    clock: &ExchangeClock,
+    price_step: Option<f64>,
+    minutes: u32,
+) -> Result<TradeHeatmap> {
+    if minutes == 0 || minutes > 1440 {
+        return Err(anyhow!(
+            "Time buckets must be 1 to 1440 minutes wide, got {}",
+            minutes
+        ));
+    }
+    let prices = trades
+        .iter()
+        .flat_map(|t| [t.entry_price, t.exit_price])
+        .filter(|p| p.is_finite());
+    let low = prices.clone().fold(f64::INFINITY, f64::min);
+    let high = prices.fold(f64::NEG_INFINITY, f64::max);
+    if !low.is_finite() {
+        return Err(anyhow!("No trades with prices to map"));
+    }
+    let price_step = match price_step {
+        Some(step) if step > 0.0 => step,
+        Some(step) => return Err(anyhow!("The price step must be positive, got {}", step)),
+        None => round_step(((high - low).max(low.abs() * 1e-3).max(1e-9)) / 20.0),
+    };
+
+    let price_origin = (low / price_step).floor() * price_step;
+    let price_buckets = ((high - price_origin) / price_step).floor() as usize + 1;
+    let time_buckets = 1440_u32.div_ceil(minutes) as usize;
+    let price_bucket = |price: f64| {
+        (((price - price_origin) / price_step).floor() as usize).min(price_buckets - 1)
+    };
+    let time_bucket = |ts: u64| {
+        let local = clock.local(ts);
+        ((local.hour() as u32 * 60 + local.minute() as u32) / minutes) as usize
+    };
+
+    let mut entries = vec![vec![0; time_buckets]; price_buckets];
+    let mut exits = vec![vec![0; time_buckets]; price_buckets];
+    let mut entry_pnl = vec![vec![0.0; time_buckets]; price_buckets];
+    for trade in trades {
+        if trade.entry_price.is_finite() {
+            let (p, t) = (price_bucket(trade.entry_price), time_bucket(trade.entry_ts));
+            entries[p][t] += 1;
+            if trade.pnl.is_finite() {
+                entry_pnl[p][t] += trade.pnl;
+            }
+        }
+        if trade.exit_price.is_finite() {
+            exits[price_bucket(trade.exit_price)][time_bucket(trade.exit_ts)] += 1;
+        }
+    }
+
+    Ok(TradeHeatmap {
+        price_origin,
+        price_step,
+        minutes,
+        entries,
+        exits,
+        entry_pnl,
+    })
+}
+
+/// Print the entry counts by price bucket, highest first, and time of day, with the
+/// busiest cells
+pub fn display_trade_heatmap(heatmap: &TradeHeatmap) {
+    println!("\n=== TRADE ENTRIES BY PRICE AND TIME OF DAY ===");
+    let times: Vec<usize> = (0..heatmap.entries.first().map_or(0, Vec::len))
+        .filter(|&t| heatmap.entries.iter().any(|row| row[t] > 0))
+        .collect();
+    print!("{:>12}", "Price");
+    for &t in &times {
+        print!("{:>7}", heatmap.time_label(t));
+    }
+    println!("{:>8}", "Total");
+    for (p, row) in heatmap.entries.iter().enumerate().rev() {
+        let total: usize = row.iter().sum();
+        if total == 0 {
+            continue;
+        }
+        print!("{:>12.2}", heatmap.price_of(p));
+        for &t in &times {
+            print!("{:>7}", row[t]);
+        }
+        println!("{:>8}", total);
+    }
+
+    let total = heatmap.total_entries();
+    println!("Busiest cells:");
+    for cell in heatmap.busiest(5) {
+        println!(
+            "  {:.2} to {:.2} at {}: {} entries ({:.1}%), P&L ${:.2}",
+            cell.price,
+            cell.price + heatmap.price_step,
+            heatmap.time_label(cell.time_bucket),
+            cell.entries,
+            cell.entries as f64 / total as f64 * 100.0,
+            cell.entry_pnl
+        );
+    }
+    if let Some(top) = heatmap.busiest(1).first() {
+        let share = top.entries as f64 / total as f64;
+        if total >= 20 && share >= 0.25 {
+            println!(
+                "WARNING: {:.0}% of entries fall in one price level and time of day, the risk is concentrated there",
+                share * 100.0
+            );
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::symbology::SymbologyMap;
+use anyhow::{Context, Result};
+use csv::Writer;
+use databento::{
+    dbn::{decode::AsyncDbnDecoder, OhlcvMsg, SType, Schema},
+    historical::timeseries::GetRangeToFileParams,
+    HistoricalClient,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use time::OffsetDateTime;
+
+/// How price gaps between consecutive contracts are removed at each roll
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdjustmentMethod {
+    /// Raw prices, rolls are only flagged
+    None,
+    /// Shift history by the price difference at each roll (Panama canal)
+    BackAdjusted,
+    /// Scale history by the price ratio at each roll, preserving percent returns
+    RatioAdjusted,
+}
+
+/// One bar of a continuous series with its raw and adjusted prices
+#[derive(Debug, Clone)]
+pub struct ContinuousBar {
+    pub ts_event: u64,
+    pub instrument_id: u32,
+    pub raw_symbol: String,
+    pub raw_open: f64,
+    pub raw_high: f64,
+    pub raw_low: f64,
+    pub raw_close: f64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+    /// Adjustment applied to this bar, an offset for back adjustment or a factor for ratio
+    pub adjustment: f64,
+    /// True for the first bar of a new contract
+    pub roll: bool,
+}
+
+/// Stitch the bars of a continuous symbol into an adjusted series.
+///
+/// A roll is detected whenever the instrument id changes. The gap is measured on the
+/// switch bar, between the incoming contract's close and the outgoing contract's close at
+/// the same timestamp looked up in `outgoing`, the bars of the individual contracts. Only
+/// when the outgoing contract has no bar then is its last close before the roll used.
+/// Every earlier bar is adjusted so the most recent contract keeps its traded prices.
+pub fn adjust_continuous(
+    bars: &[OhlcvMsg],
+    outgoing: &[OhlcvMsg],
+    symbology: &SymbologyMap,
+    method: AdjustmentMethod,
+) -> Vec<ContinuousBar> {
+    const SCALE: f64 = 1e-9;
+
+    let outgoing_close: HashMap<(u32, u64), f64> = outgoing
+        .iter()
+        .map(|bar| {
+            (
+                (bar.hd.instrument_id, bar.hd.ts_event),
+                bar.close as f64 * SCALE,
+            )
+        })
+        .collect();
+
+    let mut out: Vec<ContinuousBar> = bars
+        .iter()
+        .enumerate()
+        .map(|(i, bar)| {
+            let id = bar.hd.instrument_id;
+            let ts = bar.hd.ts_event;
+            ContinuousBar {
+                ts_event: ts,
+                instrument_id: id,
+                raw_symbol: symbology
+                    .symbol_at(id, ts)
+                    .or_else(|| symbology.latest(id).map(|i| i.raw_symbol.as_str()))
+                    .unwrap_or_default()
+                    .to_string(),
+                raw_open: bar.open as f64 * SCALE,
+                raw_high: bar.high as f64 * SCALE,
+                raw_low: bar.low as f64 * SCALE,
+                raw_close: bar.close as f64 * SCALE,
+                open: bar.open as f64 * SCALE,
+                high: bar.high as f64 * SCALE,
+                low: bar.low as f64 * SCALE,
+                close: bar.close as f64 * SCALE,
+                volume: bar.volume,
+                adjustment: if method == AdjustmentMethod::RatioAdjusted {
+                    1.0
+                } else {
+                    0.0
+                },
+                roll: i > 0 && bars[i - 1].hd.instrument_id != id,
+            }
+        })
+        .collect();
+
+    // Walk backwards accumulating the adjustment of every roll after each bar
+    let mut offset = 0.0;
+    let mut factor = 1.0;
+    for i in (0..out.len()).rev() {
+        match method {
+            AdjustmentMethod::None => {}
+            AdjustmentMethod::BackAdjusted => {
+                let bar = &mut out[i];
+                bar.open += offset;
+                bar.high += offset;
+                bar.low += offset;
+                bar.close += offset;
+                bar.adjustment = offset;
+            }
+            AdjustmentMethod::RatioAdjusted => {
+                let bar = &mut out[i];
+                bar.open *= factor;
+                bar.high *= factor;
+                bar.low *= factor;
+                bar.close *= factor;
+                bar.adjustment = factor;
+            }
+        }
+
+        if out[i].roll {
+            let new_close = out[i].raw_close;
+            let old_close = outgoing_close
+                .get(&(out[i - 1].instrument_id, out[i].ts_event))
+                .copied()
+                .unwrap_or(out[i - 1].raw_close);
+            offset += new_close - old_close;
+            if old_close != 0.0 {
+                factor *= new_close / old_close;
+            }
+        }
+    }
+
+    out
+}
+
+/// Download an OHLCV continuous contract (e.g. "ES.v.0") with its definitions and write a
+/// back-adjusted series to CSV. Returns the CSV path.
+///
+/// The CSV carries ts_event/open/high/low/close/volume columns so it can be fed to the
+/// backtester directly, plus the raw prices, contract symbol, adjustment and roll flag.
+pub async fn export_continuous(
+    dataset: &str,
+    symbol: &str,
+    schema: Schema,
+    start: OffsetDateTime,
+    end: OffsetDateTime,
+    method: AdjustmentMethod,
+) -> Result<String> {
+    if !matches!(
+        schema,
+        Schema::Ohlcv1S | Schema::Ohlcv1M | Schema::Ohlcv1H | Schema::Ohlcv1D
+    ) {
+        return Err(anyhow::anyhow!(
+            "export_continuous requires an OHLCV schema, got {:?}",
+            schema
+        ));
+    }
+
+    let bars_file = format!(
+        "src/data/{}_{}_{}-{}.zst",
+        symbol,
+        schema,
+        start.date(),
+        end.date()
+    );
+    let def_file = format!(
+        "src/data/{}_definition_{}-{}.zst",
+        symbol,
+        start.date(),
+        end.date()
+    );
+    let suffix = match method {
+        AdjustmentMethod::None => "raw",
+        AdjustmentMethod::BackAdjusted => "back_adjusted",
+        AdjustmentMethod::RatioAdjusted => "ratio_adjusted",
+    };
+    let csv_file = format!(
+        "src/data/continuous_{}_{}_{}_{}-{}.csv",
+        symbol,
+        schema,
+        suffix,
+        start.date(),
+        end.date()
+    );
+
+    for (path, file_schema) in [(&bars_file, schema), (&def_file, Schema::Definition)] {
+        if Path::new(path).exists() {
+            println!("Cached data found at: {}", path);
+            continue;
+        }
+
+        let mut client = HistoricalClient::builder()
+            .key_from_env()
+            .context("Missing DataBento Key in .env file")?
+            .build()
+            .context("Failed to build DataBento client")?;
+
+        client
+            .timeseries()
+            .get_range_to_file(
+                &GetRangeToFileParams::builder()
+                    .dataset(dataset)
+                    .stype_in(SType::Continuous)
+                    .date_time_range((start, end))
+                    .symbols(symbol)
+                    .schema(file_schema)
+                    .path(path)
+                    .build(),
+            )
+            .await?;
+        println!("Saved {} data to {}", file_schema, path);
+    }
+
+    let symbology = SymbologyMap::from_definition_file(&def_file).await?;
+
+    let mut decoder = AsyncDbnDecoder::from_zstd_file(&bars_file)
+        .await
+        .with_context(|| format!("Failed to open {}", bars_file))?;
+    let mut bars = Vec::new();
+    while let Some(bar) = decoder.decode_record::<OhlcvMsg>().await? {
+        bars.push(bar.clone());
+    }
+    if bars.is_empty() {
+        return Err(anyhow::anyhow!("No bars found for {}", symbol));
+    }
+
+    // The continuous feed only carries the front contract, so the outgoing contract's
+    // bar on each switch bar comes from its own series
+    let switches: HashSet<(u32, u64)> = bars
+        .windows(2)
+        .filter(|w| w[0].hd.instrument_id != w[1].hd.instrument_id)
+        .map(|w| (w[0].hd.instrument_id, w[1].hd.ts_event))
+        .collect();
+    let mut outgoing = Vec::new();
+    if !switches.is_empty() {
+        let outgoing_file = format!(
+            "src/data/{}_{}_outgoing_{}-{}.zst",
+            symbol,
+            schema,
+            start.date(),
+            end.date()
+        );
+        if Path::new(&outgoing_file).exists() {
+            println!("Cached data found at: {}", outgoing_file);
+        } else {
+            let mut ids: Vec<u32> = switches.iter().map(|&(id, _)| id).collect();
+            ids.sort_unstable();
+            ids.dedup();
+
+            let mut client = HistoricalClient::builder()
+                .key_from_env()
+                .context("Missing DataBento Key in .env file")?
+                .build()
+                .context("Failed to build DataBento client")?;
+
+            client
+                .timeseries()
+                .get_range_to_file(
+                    &GetRangeToFileParams::builder()
+                        .dataset(dataset)
+                        .stype_in(SType::InstrumentId)
+                        .date_time_range((start, end))
+                        .symbols(ids)
+                        .schema(schema)
+                        .path(&outgoing_file)
+                        .build(),
+                )
+                .await?;
+            println!("Saved outgoing contract bars to {}", outgoing_file);
+        }
+
+        let mut decoder = AsyncDbnDecoder::from_zstd_file(&outgoing_file)
+            .await
+            .with_context(|| format!("Failed to open {}", outgoing_file))?;
+        while let Some(bar) = decoder.decode_record::<OhlcvMsg>().await? {
+            if switches.contains(&(bar.hd.instrument_id, bar.hd.ts_event)) {
+                outgoing.push(bar.clone());
+            }
+        }
+    }
+
+    let series = adjust_continuous(&bars, &outgoing, &symbology, method);
+
+    let mut writer = Writer::from_path(&csv_file)?;
+    writer.write_record([
+        "ts_event",
+        "open",
+        "high",
+        "low",
+        "close",
+        "volume",
+        "instrument_id",
+        "raw_symbol",
+        "raw_open",
+        "raw_high",
+        "raw_low",
+        "raw_close",
+        "adjustment",
+        "roll",
+    ])?;
+    for bar in &series {
+        writer.write_record(&[
+            bar.ts_event.to_string(),
+            bar.open.to_string(),
+            bar.high.to_string(),
+            bar.low.to_string(),
+            bar.close.to_string(),
+            bar.volume.to_string(),
+            bar.instrument_id.to_string(),
+            bar.raw_symbol.clone(),
+            bar.raw_open.to_string(),
+            bar.raw_high.to_string(),
+            bar.raw_low.to_string(),
+            bar.raw_close.to_string(),
+            bar.adjustment.to_string(),
+            (bar.roll as u8).to_string(),
+        ])?;
+    }
+    writer.flush()?;
+
+    let rolls = series.iter().filter(|b| b.roll).count();
+    println!(
+        "Saved continuous series ({} bars, {} rolls) to {}",
+        series.len(),
+        rolls,
+        csv_file
+    );
+
+    Ok(csv_file)
+}
@@ -0,0 +1,150 @@
+use crate::backtester::BacktestResult;
+use crate::time_of_day::TradingCalendar;
+use crate::tools::parquet::{is_parquet_path, Column, ColumnType, ParquetTable};
+use anyhow::{anyhow, Context, Result};
+use csv::Writer;
+use std::collections::BTreeMap;
+use std::path::Path;
+use time::Date;
+
+/// One calendar day of a strategy's daily return series
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarDay {
+    pub date: Date,
+    pub trading: bool,
+    /// Marked equity at the day's last mark, carried over days without one
+    pub equity: f64,
+    /// Return since the previous trading day's close, NaN on days the exchange is shut
+    pub ret: f64,
+}
+
+/// Every calendar day from a result's first mark to its last, with returns on the
+/// trading days of `calendar`.
+///
+/// Days are cut from the finest marks the result has: its hourly marks when run with
+/// [`BacktestOptions::hourly_marks`], else its [`EquitySampling`] series. Daily UTC
+/// samples put an exchange's late session on the next UTC day, so pick a finer sampling
+/// when the calendar's day does not end at midnight UTC.
+///
+/// [`BacktestOptions::hourly_marks`]: crate::backtester::BacktestOptions::hourly_marks
+/// [`EquitySampling`]: crate::backtester::EquitySampling
+pub fn calendar_returns(
+    result: &BacktestResult,
+    calendar: &TradingCalendar,
+) -> Result<Vec<CalendarDay>> {
+    let marks = if result.hourly_equity.is_empty() {
+        &result.sampled_equity
+    } else {
+        &result.hourly_equity
+    };
+    let (Some(first), Some(last)) = (marks.first(), marks.last()) else {
+        return Err(anyhow!(
+            "Daily returns need equity marks through time, run with hourly_marks or an EquitySampling"
+        ));
+    };
+
+    let mut closes: BTreeMap<Date, f64> = BTreeMap::new();
+    for &(ts, equity) in marks {
+        closes.insert(calendar.trading_date(ts), equity);
+    }
+
+    let mut days = Vec::new();
+    let mut equity = result.starting_equity;
+    let mut prev_close = equity;
+    let mut date = calendar.trading_date(first.0);
+    let end = calendar.trading_date(last.0);
+    while date <= end {
+        if let Some(&close) = closes.get(&date) {
+            equity = close;
+        }
+        let trading = calendar.is_trading_day(date);
+        let ret = if !trading {
+            f64::NAN
+        } else if prev_close != 0.0 {
+            equity / prev_close - 1.0
+        } else {
+            0.0
+        };
+        if trading {
+            prev_close = equity;
+        }
+        days.push(CalendarDay {
+            date,
+            trading,
+            equity,
+            ret,
+        });
+        match date.next_day() {
+            Some(next) => date = next,
+            None => break,
+        }
+    }
+    Ok(days)
+}
+
+/// Write the daily returns of labelled results with a `date` column and one return
+/// column per result, one row per calendar day any of them covers. Days a result does
+/// not cover or the exchange is shut are NaN. A path ending in `.parquet` is written as
+/// Parquet with a date column, which needs the `parquet` feature, any other as CSV.
+pub fn export_daily_returns(
+    results: &[(&str, &BacktestResult)],
+    calendar: &TradingCalendar,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let mut columns = Vec::with_capacity(results.len());
+    for (label, result) in results {
+        let days = calendar_returns(result, calendar)
+            .with_context(|| format!("No daily returns for {}", label))?;
+        columns.push(
+            days.into_iter()
+                .map(|d| (d.date, d.ret))
+                .collect::<BTreeMap<Date, f64>>(),
+        );
+    }
+    let first = columns.iter().filter_map(|c| c.keys().next()).min();
+    let last = columns.iter().filter_map(|c| c.keys().next_back()).max();
+    let mut dates = Vec::new();
+    if let (Some(&first), Some(&last)) = (first, last) {
+        let mut date = first;
+        while date <= last {
+            dates.push(date);
+            match date.next_day() {
+                Some(next) => date = next,
+                None => break,
+            }
+        }
+    }
+    let ret =
+        |column: &BTreeMap<Date, f64>, date: &Date| column.get(date).copied().unwrap_or(f64::NAN);
+
+    let path_str = path.to_string_lossy();
+    if is_parquet_path(&path_str) {
+        let mut schema = vec![("date".to_string(), ColumnType::Date)];
+        schema.extend(
+            results
+                .iter()
+                .map(|(label, _)| (label.to_string(), ColumnType::Float64)),
+        );
+        let mut table = ParquetTable::create(&path_str, &schema)?;
+        let mut batch = vec![Column::Date(dates.clone())];
+        batch.extend(
+            columns
+                .iter()
+                .map(|c| Column::Float64(dates.iter().map(|date| Some(ret(c, date))).collect())),
+        );
+        table.write(batch)?;
+        return table.finish();
+    }
+
+    let mut writer =
+        Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    writer.write_record(std::iter::once("date").chain(results.iter().map(|(label, _)| *label)))?;
+    for date in &dates {
+        let mut row = vec![date.to_string()];
+        row.extend(columns.iter().map(|c| ret(c, date).to_string()));
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
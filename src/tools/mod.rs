@@ -0,0 +1,9 @@
+// src/tools/mod.rs
+pub mod continuous;
+pub mod daily_returns;
+pub mod parquet;
+pub mod trade_export;
+
+pub use continuous::export_continuous;
+pub use daily_returns::{calendar_returns, export_daily_returns};
+pub use trade_export::{export_fix, export_oms_csv, FixSession};
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use time::Date;
+
+/// Type of a column of a [`ParquetTable`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    UInt64,
+    Date,
+    /// Nullable, NaN kept as NaN
+    Float64,
+}
+
+/// Values of one column of a batch, in the order of their rows
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    UInt64(Vec<u64>),
+    Date(Vec<Date>),
+    Float64(Vec<Option<f64>>),
+}
+
+/// Exports write Parquet instead of CSV to paths ending in `.parquet`
+pub fn is_parquet_path(path: &str) -> bool {
+    path.ends_with(".parquet")
+}
+
+/// A zstd-compressed Parquet file written batch by batch, so a long export never holds
+/// more than one batch in memory. Needs the `parquet` feature.
+pub struct ParquetTable {
+    #[cfg(feature = "parquet")]
+    inner: enabled::Writer,
+}
+
+impl ParquetTable {
+    pub fn create(path: &str, columns: &[(String, ColumnType)]) -> Result<Self> {
+        #[cfg(feature = "parquet")]
+        {
+            Ok(Self {
+                inner: enabled::Writer::create(path, columns)?,
+            })
+        }
+        #[cfg(not(feature = "parquet"))]
+        {
+            let _ = columns;
+            Err(anyhow::anyhow!(
+                "Writing {} needs InkBack built with the `parquet` feature",
+                path
+            ))
+        }
+    }
+
+    /// Append rows, one [`Column`] per column of the table, all of the same length
+    pub fn write(&mut self, columns: Vec<Column>) -> Result<()> {
+        #[cfg(feature = "parquet")]
+        {
+            self.inner.write(columns)
+        }
+        #[cfg(not(feature = "parquet"))]
+        {
+            let _ = columns;
+            unreachable!("ParquetTable is only created with the parquet feature")
+        }
+    }
+
+    pub fn finish(self) -> Result<()> {
+        #[cfg(feature = "parquet")]
+        {
+            self.inner.finish()
+        }
+        #[cfg(not(feature = "parquet"))]
+        {
+            unreachable!("ParquetTable is only created with the parquet feature")
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod enabled {
+    use super::{Column, ColumnType};
+    use anyhow::{Context, Result};
+    use arrow_array::{ArrayRef, Date32Array, Float64Array, RecordBatch, UInt64Array};
+    use arrow_schema::{DataType, Field, Schema, SchemaRef};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::{Compression, ZstdLevel};
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+    use time::{Date, Month};
+
+    pub(super) struct Writer {
+        schema: SchemaRef,
+        writer: ArrowWriter<std::fs::File>,
+    }
+
+    fn unix_days(date: Date) -> i32 {
+        let epoch = Date::from_calendar_date(1970, Month::January, 1).expect("valid date");
+        (date - epoch).whole_days() as i32
+    }
+
+    impl Writer {
+        pub(super) fn create(path: &str, columns: &[(String, ColumnType)]) -> Result<Self> {
+            let fields: Vec<Field> = columns
+                .iter()
+                .map(|(name, kind)| match kind {
+                    ColumnType::UInt64 => Field::new(name, DataType::UInt64, false),
+                    ColumnType::Date => Field::new(name, DataType::Date32, false),
+                    ColumnType::Float64 => Field::new(name, DataType::Float64, true),
+                })
+                .collect();
+            let schema = Arc::new(Schema::new(fields));
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path))?;
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::ZSTD(ZstdLevel::try_new(3)?))
+                .build();
+            let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))
+                .with_context(|| format!("Failed to start Parquet file {}", path))?;
+            Ok(Self { schema, writer })
+        }
+
+        pub(super) fn write(&mut self, columns: Vec<Column>) -> Result<()> {
+            let arrays: Vec<ArrayRef> = columns
+                .into_iter()
+                .map(|column| -> ArrayRef {
+                    match column {
+                        Column::UInt64(values) => Arc::new(UInt64Array::from(values)),
+                        Column::Date(values) => Arc::new(Date32Array::from(
+                            values.into_iter().map(unix_days).collect::<Vec<_>>(),
+                        )),
+                        Column::Float64(values) => Arc::new(Float64Array::from(values)),
+                    }
+                })
+                .collect();
+            let batch = RecordBatch::try_new(self.schema.clone(), arrays)
+                .context("Parquet batch does not match the table's columns")?;
+            self.writer.write(&batch)?;
+            Ok(())
+        }
+
+        pub(super) fn finish(self) -> Result<()> {
+            self.writer
+                .close()
+                .context("Failed to finish Parquet file")?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::backtester::Trade;
+use anyhow::{Context, Result};
+use csv::Writer;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use time::OffsetDateTime;
+
+/// Which way a fill traded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+    SellShort,
+}
+
+impl Side {
+    /// FIX tag 54 value
+    fn fix_code(self) -> &'static str {
+        match self {
+            Side::Buy => "1",
+            Side::Sell => "2",
+            Side::SellShort => "5",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+            Side::SellShort => "SELL_SHORT",
+        }
+    }
+}
+
+/// One side of a round trip: the entry or the exit of a trade
+#[derive(Debug, Clone)]
+pub struct Fill {
+    /// Execution id, `<trade number>-O` for the entry and `<trade number>-C` for the exit
+    pub exec_id: String,
+    /// Order id shared by the fills of one trade
+    pub order_id: String,
+    pub side: Side,
+    /// True for the fill that opened the position
+    pub opening: bool,
+    pub quantity: f64,
+    pub price: f64,
+    pub ts: u64,
+}
+
+/// Split trades into their entry and exit fills, in time order.
+///
+/// Trades from before fill timestamps were recorded have `entry_ts` and `exit_ts` of 0
+/// and sort first.
+pub fn fills(trades: &[Trade]) -> Vec<Fill> {
+    let mut out = Vec::with_capacity(trades.len() * 2);
+    for (i, trade) in trades.iter().enumerate() {
+        let long = !trade.trade_type.ends_with("Short");
+        let order_id = format!("T{}", i + 1);
+        out.push(Fill {
+            exec_id: format!("{}-O", i + 1),
+            order_id: order_id.clone(),
+            side: if long { Side::Buy } else { Side::SellShort },
+            opening: true,
+            quantity: trade.size,
+            price: trade.entry_price,
+            ts: trade.entry_ts,
+        });
+        out.push(Fill {
+            exec_id: format!("{}-C", i + 1),
+            order_id,
+            side: if long { Side::Sell } else { Side::Buy },
+            opening: false,
+            quantity: trade.size,
+            price: trade.exit_price,
+            ts: trade.exit_ts,
+        });
+    }
+    out.sort_by_key(|f| f.ts);
+    out
+}
+
+// FIX UTCTimestamp, YYYYMMDD-HH:MM:SS.sss
+fn fix_timestamp(ts: u64) -> String {
+    match OffsetDateTime::from_unix_timestamp_nanos(ts as i128) {
+        Ok(t) => format!(
+            "{:04}{:02}{:02}-{:02}:{:02}:{:02}.{:03}",
+            t.year(),
+            t.month() as u8,
+            t.day(),
+            t.hour(),
+            t.minute(),
+            t.second(),
+            t.millisecond()
+        ),
+        Err(_) => "19700101-00:00:00.000".to_string(),
+    }
+}
+
+// ISO 8601 UTC with nanoseconds
+fn iso_timestamp(ts: u64) -> String {
+    match OffsetDateTime::from_unix_timestamp_nanos(ts as i128) {
+        Ok(t) => format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+            t.year(),
+            t.month() as u8,
+            t.day(),
+            t.hour(),
+            t.minute(),
+            t.second(),
+            t.nanosecond()
+        ),
+        Err(_) => String::new(),
+    }
+}
+
+/// Sender and target of the exported FIX session
+#[derive(Debug, Clone)]
+pub struct FixSession {
+    pub sender_comp_id: String,
+    pub target_comp_id: String,
+}
+
+impl FixSession {
+    pub fn new(sender_comp_id: &str, target_comp_id: &str) -> Self {
+        Self {
+            sender_comp_id: sender_comp_id.to_string(),
+            target_comp_id: target_comp_id.to_string(),
+        }
+    }
+}
+
+/// A FIX 4.4 execution report (35=8) for a full fill, SOH delimited with BodyLength and
+/// CheckSum computed
+pub fn execution_report(
+    fill: &Fill,
+    seq_num: usize,
+    session: &FixSession,
+    account: &str,
+    symbol: &str,
+) -> String {
+    const SOH: char = '\x01';
+    let time = fix_timestamp(fill.ts);
+    let fields = [
+        ("35", "8".to_string()),
+        ("49", session.sender_comp_id.clone()),
+        ("56", session.target_comp_id.clone()),
+        ("34", seq_num.to_string()),
+        ("52", time.clone()),
+        ("1", account.to_string()),
+        ("37", fill.order_id.clone()),
+        ("17", fill.exec_id.clone()),
+        // ExecType Trade, OrdStatus Filled
+        ("150", "F".to_string()),
+        ("39", "2".to_string()),
+        ("55", symbol.to_string()),
+        ("54", fill.side.fix_code().to_string()),
+        ("38", fill.quantity.to_string()),
+        ("32", fill.quantity.to_string()),
+        ("31", fill.price.to_string()),
+        ("151", "0".to_string()),
+        ("14", fill.quantity.to_string()),
+        ("6", fill.price.to_string()),
+        // PositionEffect Open or Close
+        ("77", if fill.opening { "O" } else { "C" }.to_string()),
+        ("60", time),
+    ];
+
+    let body: String = fields
+        .iter()
+        .map(|(tag, value)| format!("{}={}{}", tag, value, SOH))
+        .collect();
+    let head = format!("8=FIX.4.4{}9={}{}", SOH, body.len(), SOH);
+    let checksum = head
+        .bytes()
+        .chain(body.bytes())
+        .fold(0u32, |sum, b| sum + b as u32)
+        % 256;
+    format!("{}{}10={:03}{}", head, body, checksum, SOH)
+}
+
+/// Write every fill of `trades` as a FIX 4.4 execution report, one message per line.
+/// Hedge trades are in another instrument and are exported with their own symbol.
+pub fn export_fix(
+    trades: &[Trade],
+    session: &FixSession,
+    account: &str,
+    symbol: &str,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let mut file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    for (i, fill) in fills(trades).iter().enumerate() {
+        writeln!(
+            file,
+            "{}",
+            execution_report(fill, i + 1, session, account, symbol)
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct OmsRow<'a> {
+    account: &'a str,
+    symbol: &'a str,
+    order_id: &'a str,
+    exec_id: &'a str,
+    side: &'a str,
+    position_effect: &'a str,
+    quantity: f64,
+    price: f64,
+    ts_event: u64,
+    transact_time: String,
+}
+
+/// Write every fill of `trades` as a generic OMS CSV with account, symbol, side,
+/// quantity, price and nanosecond and ISO 8601 timestamps
+pub fn export_oms_csv(
+    trades: &[Trade],
+    account: &str,
+    symbol: &str,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let mut writer =
+        Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    for fill in fills(trades) {
+        writer.serialize(OmsRow {
+            account,
+            symbol,
+            order_id: &fill.order_id,
+            exec_id: &fill.exec_id,
+            side: fill.side.label(),
+            position_effect: if fill.opening { "OPEN" } else { "CLOSE" },
+            quantity: fill.quantity,
+            price: fill.price,
+            ts_event: fill.ts,
+            transact_time: iso_timestamp(fill.ts),
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
+}
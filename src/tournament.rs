@@ -0,0 +1,381 @@
+use crate::backtester::{run_backtest, BacktestResult, Trade};
+use crate::plot::{plot_equity_curves, PlotConfig};
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::{Strategy, StrategyParams};
+use crate::utils::fetch::BacktestManager;
+use crate::utils::snapshot::check_same_data;
+use crate::InkBackSchema;
+use databento::dbn::Schema;
+use rayon::prelude::*;
+
+/// Builds a contestant's strategy from its parameters
+pub type StrategyConstructor =
+    Box<dyn Fn(&StrategyParams) -> anyhow::Result<Box<dyn Strategy>> + Sync + Send>;
+
+/// A named strategy entered into a tournament with its already tuned parameters.
+pub struct Contestant {
+    pub name: String,
+    pub params: StrategyParams,
+    pub constructor: StrategyConstructor,
+    /// Share of capital in the combined portfolio, normalised across contestants.
+    pub weight: f64,
+}
+
+impl Contestant {
+    pub fn new<F>(name: &str, params: StrategyParams, constructor: F) -> Self
+    where
+        F: Fn(&StrategyParams) -> anyhow::Result<Box<dyn Strategy>> + Sync + Send + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            params,
+            constructor: Box::new(constructor),
+            weight: 1.0,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+/// One contestant's run over the shared data.
+pub struct ContestantResult {
+    pub name: String,
+    pub weight: f64,
+    pub result: BacktestResult,
+    /// Position on the equity curve of the deepest drawdown's trough.
+    pub max_drawdown_index: usize,
+}
+
+/// Head-to-head statistics of two contestants.
+#[derive(Debug, Clone)]
+pub struct PairStats {
+    pub a: String,
+    pub b: String,
+    /// Pearson correlation of per-event equity returns.
+    pub correlation: f64,
+    /// Share of the events where either is under water that both are.
+    pub drawdown_overlap: f64,
+    /// Events between the two deepest drawdown troughs, positive when `b` bottoms later.
+    pub trough_lag: i64,
+}
+
+pub struct TournamentSummary {
+    pub contestants: Vec<ContestantResult>,
+    /// Every pair of contestants, in entry order.
+    pub pairs: Vec<PairStats>,
+    /// Weighted, never rebalanced portfolio of all contestants.
+    pub portfolio: BacktestResult,
+}
+
+impl TournamentSummary {
+    /// The combined portfolio under different weights, in contestant order.
+    pub fn portfolio_with_weights(&self, weights: &[f64]) -> BacktestResult {
+        let results: Vec<(&BacktestResult, f64)> = self
+            .contestants
+            .iter()
+            .zip(weights)
+            .map(|(c, w)| (&c.result, *w))
+            .collect();
+        combine_portfolio(&results)
+    }
+}
+
+/// Run each contestant over the same data and compare them head to head.
+///
+/// Contestants are run in parallel; one whose strategy cannot be built or whose backtest
+/// fails is reported and left out of the comparison.
+#[allow(clippy::too_many_arguments)]
+pub fn run_tournament(
+    contestants: Vec<Contestant>,
+    backtest_manager: BacktestManager,
+    symbol: &str,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    starting_equity: f64,
+    exposure: f64,
+    transaction_costs: TransactionCosts,
+) -> TournamentSummary {
+    println!("\n=== TOURNAMENT ({} strategies) ===", contestants.len());
+
+    let handle = tokio::runtime::Handle::current();
+
+    let results: Vec<ContestantResult> = contestants
+        .par_iter()
+        .filter_map(|contestant| {
+            let mut strategy = match (contestant.constructor)(&contestant.params) {
+                Ok(strategy) => strategy,
+                Err(e) => {
+                    eprintln!("  {}: failed to build strategy ({})", contestant.name, e);
+                    return None;
+                }
+            };
+
+            let result = handle.block_on(run_backtest(
+                symbol,
+                backtest_manager.clone(),
+                strategy.as_mut(),
+                transaction_costs.clone(),
+                starting_equity,
+                exposure,
+                schema,
+                custom_schema.clone(),
+                None,
+            ));
+
+            match result {
+                Ok(result) => Some(ContestantResult {
+                    name: contestant.name.clone(),
+                    weight: contestant.weight,
+                    max_drawdown_index: trough_index(&result.equity_curve),
+                    result,
+                }),
+                Err(e) => {
+                    eprintln!("  {}: backtest failed ({})", contestant.name, e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    check_same_data(results.iter().map(|c| (c.name.as_str(), &c.result)));
+
+    let mut pairs = Vec::new();
+    for i in 0..results.len() {
+        for j in i + 1..results.len() {
+            pairs.push(pair_stats(&results[i], &results[j]));
+        }
+    }
+
+    let weighted: Vec<(&BacktestResult, f64)> =
+        results.iter().map(|c| (&c.result, c.weight)).collect();
+    let portfolio = combine_portfolio(&weighted);
+
+    TournamentSummary {
+        contestants: results,
+        pairs,
+        portfolio,
+    }
+}
+
+/// Per-event simple returns of an equity curve
+fn returns(curve: &[f64]) -> Vec<f64> {
+    curve
+        .windows(2)
+        .map(|w| if w[0] != 0.0 { w[1] / w[0] - 1.0 } else { 0.0 })
+        .collect()
+}
+
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    if n < 2 {
+        return 0.0;
+    }
+    let (a, b) = (&a[..n], &b[..n]);
+    let mean_a = a.iter().sum::<f64>() / n as f64;
+    let mean_b = b.iter().sum::<f64>() / n as f64;
+
+    let mut cov = 0.0;
+    let mut var_a = 0.0;
+    let mut var_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+
+    if var_a > 0.0 && var_b > 0.0 {
+        cov / (var_a.sqrt() * var_b.sqrt())
+    } else {
+        0.0
+    }
+}
+
+/// Whether each point of an equity curve sits below its running peak
+fn under_water(curve: &[f64]) -> Vec<bool> {
+    let mut peak = f64::NEG_INFINITY;
+    curve
+        .iter()
+        .map(|&eq| {
+            peak = peak.max(eq);
+            eq < peak
+        })
+        .collect()
+}
+
+fn trough_index(curve: &[f64]) -> usize {
+    let mut peak = f64::NEG_INFINITY;
+    let mut max_dd = 0.0;
+    let mut trough = 0;
+    for (i, &eq) in curve.iter().enumerate() {
+        peak = peak.max(eq);
+        let dd = if peak > 0.0 { (peak - eq) / peak } else { 0.0 };
+        if dd > max_dd {
+            max_dd = dd;
+            trough = i;
+        }
+    }
+    trough
+}
+
+fn pair_stats(a: &ContestantResult, b: &ContestantResult) -> PairStats {
+    let curve_a = &a.result.equity_curve;
+    let curve_b = &b.result.equity_curve;
+
+    let water_a = under_water(curve_a);
+    let water_b = under_water(curve_b);
+    let (mut both, mut either) = (0usize, 0usize);
+    for (x, y) in water_a.iter().zip(&water_b) {
+        both += (*x && *y) as usize;
+        either += (*x || *y) as usize;
+    }
+
+    PairStats {
+        a: a.name.clone(),
+        b: b.name.clone(),
+        correlation: correlation(&returns(curve_a), &returns(curve_b)),
+        drawdown_overlap: if either > 0 {
+            both as f64 / either as f64
+        } else {
+            0.0
+        },
+        trough_lag: b.max_drawdown_index as i64 - a.max_drawdown_index as i64,
+    }
+}
+
+/// Split the starting equity across results by weight and hold each sleeve to the end.
+///
+/// Curves are aligned by event position and cut to the shortest; trades keep their
+/// percentage return with P&L scaled to the sleeve.
+fn combine_portfolio(results: &[(&BacktestResult, f64)]) -> BacktestResult {
+    let total_weight: f64 = results.iter().map(|(_, w)| w.max(0.0)).sum();
+    let starting_equity = results.first().map_or(0.0, |(r, _)| r.starting_equity);
+
+    if results.is_empty() || total_weight <= 0.0 {
+        return BacktestResult::calculate_metrics(
+            starting_equity,
+            starting_equity,
+            vec![starting_equity],
+            Vec::new(),
+        );
+    }
+
+    let len = results
+        .iter()
+        .map(|(r, _)| r.equity_curve.len())
+        .min()
+        .unwrap_or(0);
+
+    let mut curve = vec![0.0; len];
+    let mut trades: Vec<Trade> = Vec::new();
+    let mut ending_equity = 0.0;
+    for (result, weight) in results {
+        let share = weight.max(0.0) / total_weight;
+        // Each sleeve is the result's curve rescaled to its slice of the capital
+        let scale = if result.starting_equity != 0.0 {
+            share * starting_equity / result.starting_equity
+        } else {
+            0.0
+        };
+
+        for (point, eq) in curve.iter_mut().zip(&result.equity_curve) {
+            *point += eq * scale;
+        }
+        ending_equity += result.ending_equity * scale;
+        trades.extend(result.trades.iter().map(|t| Trade {
+            size: t.size * scale,
+            pnl: t.pnl * scale,
+            transaction_costs: t.transaction_costs * scale,
+            gross_pnl: t.gross_pnl * scale,
+            ..t.clone()
+        }));
+    }
+
+    let mut portfolio =
+        BacktestResult::calculate_metrics(starting_equity, ending_equity, curve, trades);
+    portfolio.data_files = results[0].0.data_files.clone();
+    portfolio
+}
+
+pub fn display_tournament_results(summary: &TournamentSummary) {
+    println!("\n=== TOURNAMENT RESULTS ===");
+    println!(
+        "{:<20} {:<8} {:<10} {:<10} {:<10} {:<10} {:<8}",
+        "Strategy", "Weight", "Ret%", "DD%", "Sharpe", "Sortino", "Trades"
+    );
+    println!("{}", "-".repeat(80));
+
+    let total_weight: f64 = summary.contestants.iter().map(|c| c.weight.max(0.0)).sum();
+    for c in &summary.contestants {
+        println!(
+            "{:<20} {:<8.2} {:<10.2} {:<10.2} {:<10.2} {:<10.2} {:<8}",
+            truncate(&c.name, 20),
+            if total_weight > 0.0 {
+                c.weight.max(0.0) / total_weight
+            } else {
+                0.0
+            },
+            c.result.total_return_pct,
+            c.result.max_drawdown_pct,
+            c.result.sharpe_ratio,
+            c.result.sortino_ratio,
+            c.result.total_trades,
+        );
+    }
+
+    if !summary.pairs.is_empty() {
+        println!("\n=== HEAD TO HEAD ===");
+        println!(
+            "{:<20} {:<20} {:<8} {:<10} {:<12}",
+            "A", "B", "Corr", "DD Overlap", "Trough Lag"
+        );
+        println!("{}", "-".repeat(74));
+        for p in &summary.pairs {
+            println!(
+                "{:<20} {:<20} {:<8.2} {:<10.1} {:<12}",
+                truncate(&p.a, 20),
+                truncate(&p.b, 20),
+                p.correlation,
+                p.drawdown_overlap * 100.0,
+                p.trough_lag,
+            );
+        }
+    }
+
+    println!("{}", "-".repeat(80));
+    println!(
+        "Portfolio | Ret: {:.2}% | DD: {:.2}% | Sharpe: {:.2} | Sortino: {:.2} | Calmar: {:.2} | Trades: {}",
+        summary.portfolio.total_return_pct,
+        summary.portfolio.max_drawdown_pct,
+        summary.portfolio.sharpe_ratio,
+        summary.portfolio.sortino_ratio,
+        summary.portfolio.calmar_ratio,
+        summary.portfolio.total_trades,
+    );
+}
+
+fn truncate(label: &str, width: usize) -> String {
+    if label.chars().count() > width {
+        format!("{}…", label.chars().take(width - 1).collect::<String>())
+    } else {
+        label.to_string()
+    }
+}
+
+/// Plot every contestant's equity curve with the combined portfolio.
+pub fn plot_tournament(summary: &TournamentSummary) {
+    let mut curves: Vec<(String, Vec<f64>)> = summary
+        .contestants
+        .iter()
+        .map(|c| (c.name.clone(), c.result.equity_curve.clone()))
+        .collect();
+
+    curves.push((
+        "Portfolio".to_string(),
+        summary.portfolio.equity_curve.clone(),
+    ));
+
+    plot_equity_curves(curves, None, PlotConfig::default());
+}
@@ -0,0 +1,219 @@
+use crate::backtester::{run_parallel_backtest_internal, BacktestResult};
+use crate::strategy::{Strategy, StrategyParams};
+use crate::utils::fetch::BacktestManager;
+use crate::vectorized::{Bars, VectorBacktest, VectorResult};
+use crate::InkBackSchema;
+use databento::dbn::Schema;
+use std::collections::HashMap;
+
+/// How many of the vectorized scan's best combinations go on to the event engine
+#[derive(Debug, Clone)]
+pub struct TwoPhaseConfig {
+    /// Share of the scanned combinations kept, 0.1 keeps the top decile
+    pub keep_fraction: f64,
+    /// Kept regardless of the fraction, for small grids
+    pub min_keep: usize,
+}
+
+impl Default for TwoPhaseConfig {
+    fn default() -> Self {
+        Self {
+            keep_fraction: 0.1,
+            min_keep: 5,
+        }
+    }
+}
+
+impl TwoPhaseConfig {
+    pub fn keeping(keep_fraction: f64) -> Self {
+        Self {
+            keep_fraction,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_min_keep(mut self, min_keep: usize) -> Self {
+        self.min_keep = min_keep;
+        self
+    }
+}
+
+/// One combination kept after the coarse scan
+pub struct Candidate {
+    /// Label of the vectorized scan
+    pub label: String,
+    pub params: StrategyParams,
+    /// 1-based rank by Sharpe in each phase. The refined rank is `None` when the engine
+    /// run failed or produced a non-finite equity curve.
+    pub coarse_rank: usize,
+    pub refined_rank: Option<usize>,
+    pub coarse: VectorResult,
+    pub refined: Option<BacktestResult>,
+}
+
+impl Candidate {
+    /// Places gained (positive) or lost between the phases
+    pub fn rank_shift(&self) -> Option<i64> {
+        self.refined_rank
+            .map(|refined| self.coarse_rank as i64 - refined as i64)
+    }
+}
+
+pub struct TwoPhaseSummary {
+    /// Combinations in the coarse scan
+    pub scanned: usize,
+    /// Kept combinations by refined rank, failed engine runs last
+    pub candidates: Vec<Candidate>,
+    /// Spearman correlation of the two rankings over the refined candidates, `None` with
+    /// fewer than two
+    pub rank_correlation: Option<f64>,
+}
+
+impl TwoPhaseSummary {
+    /// Best combination under full event-driven fills and costs
+    pub fn best(&self) -> Option<&Candidate> {
+        self.candidates.first().filter(|c| c.refined.is_some())
+    }
+}
+
+/// Scan every combination on bars with `scan`, then re-run the top
+/// [`TwoPhaseConfig::keep_fraction`] through the event engine with the scan's costs,
+/// starting equity and exposure, ranking both phases by Sharpe.
+///
+/// `signal` and `strategy_constructor` should express the same strategy, and the scan's
+/// bars the same data as `backtest_manager`, or the phases measure different things.
+#[allow(clippy::too_many_arguments)]
+pub fn run_two_phase_optimization<S, F>(
+    scan: &VectorBacktest,
+    parameter_combinations: &[StrategyParams],
+    signal: &S,
+    backtest_manager: &BacktestManager,
+    symbol: &str,
+    schema: Schema,
+    custom_schema: Option<InkBackSchema>,
+    strategy_constructor: F,
+    config: &TwoPhaseConfig,
+) -> TwoPhaseSummary
+where
+    S: Fn(&Bars, &StrategyParams, &mut [f64]) + Sync,
+    F: Fn(&StrategyParams) -> anyhow::Result<Box<dyn Strategy>> + Sync + Send,
+{
+    let scanned = parameter_combinations.len();
+    let coarse = scan.scan(parameter_combinations, signal);
+    let keep = ((scanned as f64 * config.keep_fraction).ceil() as usize)
+        .max(config.min_keep)
+        .min(coarse.len());
+    println!(
+        "Scanned {} combinations on {} bars, refining the top {} with the event engine...",
+        scanned,
+        scan.bars().len(),
+        keep
+    );
+
+    let kept: Vec<StrategyParams> = coarse[..keep].iter().map(|r| r.params.clone()).collect();
+    let refined = run_parallel_backtest_internal(
+        &kept,
+        backtest_manager,
+        symbol,
+        schema,
+        custom_schema,
+        &strategy_constructor,
+        scan.starting_equity,
+        scan.exposure,
+        &scan.transaction_costs,
+        None,
+        None,
+    );
+    let mut refined: HashMap<String, (usize, BacktestResult)> = refined
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (_, params, result, _))| {
+            (params.to_string_representation(), (rank + 1, result))
+        })
+        .collect();
+
+    let mut candidates: Vec<Candidate> = coarse
+        .into_iter()
+        .take(keep)
+        .enumerate()
+        .map(|(rank, coarse)| {
+            let refined = refined.remove(&coarse.params.to_string_representation());
+            Candidate {
+                label: coarse.label.clone(),
+                params: coarse.params.clone(),
+                coarse_rank: rank + 1,
+                refined_rank: refined.as_ref().map(|(rank, _)| *rank),
+                coarse,
+                refined: refined.map(|(_, result)| result),
+            }
+        })
+        .collect();
+    candidates.sort_by_key(|c| c.refined_rank.unwrap_or(usize::MAX));
+
+    let rank_correlation = spearman(&candidates);
+    TwoPhaseSummary {
+        scanned,
+        candidates,
+        rank_correlation,
+    }
+}
+
+// Spearman's rho between the phases, re-ranking the coarse ranks among the refined
+// candidates so both sides run 1..n
+fn spearman(candidates: &[Candidate]) -> Option<f64> {
+    let mut pairs: Vec<(usize, usize)> = candidates
+        .iter()
+        .filter_map(|c| c.refined_rank.map(|refined| (c.coarse_rank, refined)))
+        .collect();
+    let n = pairs.len();
+    if n < 2 {
+        return None;
+    }
+    pairs.sort_unstable();
+    let d2: f64 = pairs
+        .iter()
+        .enumerate()
+        .map(|(coarse, &(_, refined))| (coarse as f64 + 1.0 - refined as f64).powi(2))
+        .sum();
+    let n = n as f64;
+    Some(1.0 - 6.0 * d2 / (n * (n * n - 1.0)))
+}
+
+pub fn display_two_phase_results(summary: &TwoPhaseSummary) {
+    println!(
+        "\n=== TWO-PHASE OPTIMIZATION ({} scanned, {} refined) ===",
+        summary.scanned,
+        summary.candidates.len()
+    );
+    println!(
+        "{:<8} {:<8} {:<7} {:<10} {:<10} {:<10} {:<10} {:<14} Params",
+        "Engine", "Scan", "Shift", "Sharpe", "Scan Shp", "Ret%", "Scan Ret%", "Trades"
+    );
+    println!("{}", "-".repeat(100));
+    for c in &summary.candidates {
+        let (rank, shift) = match (c.refined_rank, c.rank_shift()) {
+            (Some(rank), Some(shift)) => (rank.to_string(), format!("{:+}", shift)),
+            _ => ("failed".to_string(), String::new()),
+        };
+        let (sharpe, ret, trades) = c.refined.as_ref().map_or((f64::NAN, f64::NAN, 0), |r| {
+            (r.sharpe_ratio, r.total_return_pct, r.total_trades)
+        });
+        println!(
+            "{:<8} {:<8} {:<7} {:<10.3} {:<10.3} {:<10.2} {:<10.2} {:<14} {}",
+            rank,
+            c.coarse_rank,
+            shift,
+            sharpe,
+            c.coarse.sharpe_ratio,
+            ret,
+            c.coarse.total_return_pct,
+            format!("{}/{}", trades, c.coarse.total_trades),
+            c.params.to_string_representation(),
+        );
+    }
+    println!("{}", "-".repeat(100));
+    match summary.rank_correlation {
+        Some(rho) => println!("Rank correlation (Spearman) between phases: {:.2}", rho),
+        None => println!("Rank correlation: fewer than two refined candidates"),
+    }
+}
@@ -0,0 +1,219 @@
+use databento::dbn::UNDEF_PRICE;
+
+/// Rule deciding whether a trade bought from the ask or sold into the bid when footprint
+/// bars are built. Venues and datasets disagree on the side byte, so pick the rule that
+/// matches the data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SideClassifier {
+    /// The record's side byte: values in `buy` are buys and values in `sell` sells. Other
+    /// values are classified by the tick rule with `tick_fallback`, else left out.
+    SideField {
+        buy: Vec<u8>,
+        sell: Vec<u8>,
+        tick_fallback: bool,
+    },
+    /// An uptick is a buy and a downtick a sell; an unchanged price repeats the side of
+    /// the last price change
+    TickRule,
+    /// Above the prevailing mid a buy and below it a sell, as in Lee-Ready. Trades at the
+    /// mid or without a quote are classified by the tick rule.
+    QuoteRule,
+}
+
+impl Default for SideClassifier {
+    /// DataBento's aggressor side: `B` buys, `A` sells, `N` unknown. `S` is read as a sell
+    /// too, as some feeds use it.
+    fn default() -> Self {
+        SideClassifier::SideField {
+            buy: vec![b'B'],
+            sell: vec![b'A', b'S'],
+            tick_fallback: false,
+        }
+    }
+}
+
+impl SideClassifier {
+    /// Suffix telling cached files built under this rule apart, empty for the default
+    pub fn file_tag(&self) -> String {
+        match self {
+            _ if *self == Self::default() => String::new(),
+            SideClassifier::SideField {
+                buy,
+                sell,
+                tick_fallback,
+            } => format!(
+                "_side-{}-{}{}",
+                String::from_utf8_lossy(buy),
+                String::from_utf8_lossy(sell),
+                if *tick_fallback { "-tick" } else { "" }
+            ),
+            SideClassifier::TickRule => "_tick".to_string(),
+            SideClassifier::QuoteRule => "_quote".to_string(),
+        }
+    }
+}
+
+/// Which side of the book a trade took liquidity from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggressor {
+    Buy,
+    Sell,
+}
+
+/// How many trades each part of a [`SideClassifier`] decided
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClassificationStats {
+    pub trades: u64,
+    pub by_side_field: u64,
+    pub by_quote: u64,
+    pub by_tick: u64,
+    /// Trades no part of the rule could classify, left out of the footprint
+    pub unclassified: u64,
+    pub unclassified_volume: u64,
+    pub buys: u64,
+    pub sells: u64,
+}
+
+impl ClassificationStats {
+    /// Share of trades classified, in `[0, 1]`
+    pub fn classified_rate(&self) -> f64 {
+        if self.trades == 0 {
+            return 0.0;
+        }
+        1.0 - self.unclassified as f64 / self.trades as f64
+    }
+}
+
+pub fn display_classification_stats(source: &str, stats: &ClassificationStats) {
+    let share = |n: u64| {
+        if stats.trades == 0 {
+            0.0
+        } else {
+            n as f64 / stats.trades as f64 * 100.0
+        }
+    };
+    println!("\n=== AGGRESSOR CLASSIFICATION: {} ===", source);
+    println!("Trades: {}", stats.trades);
+    for (label, n) in [
+        ("Side field", stats.by_side_field),
+        ("Quote rule", stats.by_quote),
+        ("Tick rule", stats.by_tick),
+        ("Unclassified", stats.unclassified),
+    ] {
+        if n > 0 {
+            println!("{:<14} {:>10} ({:.1}%)", label, n, share(n));
+        }
+    }
+    println!(
+        "Buys: {} | Sells: {} | Classified: {:.1}%",
+        stats.buys,
+        stats.sells,
+        stats.classified_rate() * 100.0
+    );
+    if stats.trades > 0 && stats.classified_rate() < 0.9 {
+        println!(
+            "WARNING: {} trades ({} contracts) have no side and are missing from the footprint, \
+             check the side values or use the tick rule",
+            stats.unclassified, stats.unclassified_volume
+        );
+    }
+}
+
+/// A [`SideClassifier`] applied to one instrument's trades in time order, remembering
+/// the price changes the tick rule needs
+#[derive(Debug, Clone)]
+pub struct AggressorClassifier {
+    rule: SideClassifier,
+    last_price: Option<i64>,
+    last_tick: Option<Aggressor>,
+    stats: ClassificationStats,
+}
+
+impl AggressorClassifier {
+    pub fn new(rule: SideClassifier) -> Self {
+        Self {
+            rule,
+            last_price: None,
+            last_tick: None,
+            stats: ClassificationStats::default(),
+        }
+    }
+
+    /// Side of a trade at the fixed-point `price` with the record's `side` byte, given the
+    /// prevailing fixed-point `(bid, ask)` when known. None when the rule cannot tell.
+    pub fn classify(
+        &mut self,
+        price: i64,
+        side: u8,
+        size: u64,
+        quote: Option<(i64, i64)>,
+    ) -> Option<Aggressor> {
+        // Every trade moves the tick state, whichever part of the rule ends up deciding
+        let tick = match self.last_price {
+            Some(last) if price > last => Some(Aggressor::Buy),
+            Some(last) if price < last => Some(Aggressor::Sell),
+            _ => self.last_tick,
+        };
+        self.last_price = Some(price);
+        self.last_tick = tick;
+
+        let by_quote = || {
+            let (bid, ask) = quote.filter(|&(bid, ask)| {
+                bid != UNDEF_PRICE && ask != UNDEF_PRICE && bid > 0 && ask >= bid
+            })?;
+            // Compared at twice the price, so the mid stays an integer
+            match (2 * price as i128).cmp(&(bid as i128 + ask as i128)) {
+                std::cmp::Ordering::Greater => Some(Aggressor::Buy),
+                std::cmp::Ordering::Less => Some(Aggressor::Sell),
+                std::cmp::Ordering::Equal => None,
+            }
+        };
+
+        let stats = &mut self.stats;
+        stats.trades += 1;
+        let decided = match &self.rule {
+            SideClassifier::SideField {
+                buy,
+                sell,
+                tick_fallback,
+            } => {
+                let field = if buy.contains(&side) {
+                    Some(Aggressor::Buy)
+                } else if sell.contains(&side) {
+                    Some(Aggressor::Sell)
+                } else {
+                    None
+                };
+                match field {
+                    Some(s) => {
+                        stats.by_side_field += 1;
+                        Some(s)
+                    }
+                    None if *tick_fallback => tick.inspect(|_| stats.by_tick += 1),
+                    None => None,
+                }
+            }
+            SideClassifier::TickRule => tick.inspect(|_| stats.by_tick += 1),
+            SideClassifier::QuoteRule => match by_quote() {
+                Some(s) => {
+                    stats.by_quote += 1;
+                    Some(s)
+                }
+                None => tick.inspect(|_| stats.by_tick += 1),
+            },
+        };
+        match decided {
+            Some(Aggressor::Buy) => stats.buys += 1,
+            Some(Aggressor::Sell) => stats.sells += 1,
+            None => {
+                stats.unclassified += 1;
+                stats.unclassified_volume += size;
+            }
+        }
+        decided
+    }
+
+    pub fn stats(&self) -> ClassificationStats {
+        self.stats
+    }
+}
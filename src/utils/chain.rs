@@ -0,0 +1,358 @@
+use crate::event::MarketEvent;
+use crate::time_of_day::{ExchangeClock, TradingCalendar};
+use crate::utils::fetch::{get_file_stream, MarketStream};
+use anyhow::{Context, Result};
+use databento::dbn::{
+    decode::{AsyncDbnDecoder, DbnMetadata},
+    Schema,
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// A data file and the `[start, end)` nanosecond range it covers
+#[derive(Debug, Clone)]
+pub struct DataFile {
+    pub path: String,
+    pub start: u64,
+    pub end: u64,
+    /// True when the range is the query range from DBN metadata, false when it is only
+    /// the span of the events found in the file
+    pub exact: bool,
+}
+
+/// True when `path` names a directory or a `*` pattern rather than a single file
+pub fn is_multi_file(path: &str) -> bool {
+    path.contains('*') || Path::new(path).is_dir()
+}
+
+/// Files of a directory, or matching a `*` wildcard in the file name
+/// (e.g. `src/data/ES/*.trades.dbn.zst`), in name order
+pub fn resolve_files(path: &str) -> Result<Vec<PathBuf>> {
+    let (dir, pattern) = if Path::new(path).is_dir() {
+        (PathBuf::from(path), "*".to_string())
+    } else {
+        let p = Path::new(path);
+        let dir = p
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        if dir.to_string_lossy().contains('*') {
+            return Err(anyhow::anyhow!(
+                "Wildcards are only supported in the file name: {}",
+                path
+            ));
+        }
+        let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("*");
+        (dir.to_path_buf(), name.to_string())
+    };
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| !n.starts_with('.') && wildcard_match(&pattern, n))
+        })
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("No data files found for {}", path));
+    }
+    Ok(files)
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+// DBN files carry their query range in the metadata, CSVs are read for the ts_event of
+// their first and last rows
+async fn time_range(path: &Path) -> Result<(u64, u64, bool)> {
+    let path_str = path.to_string_lossy();
+    let is_dbn = path_str.ends_with(".dbn")
+        || (path_str.ends_with(".zst") && !path_str.ends_with(".csv.zst"));
+    if is_dbn {
+        let decoder = AsyncDbnDecoder::from_zstd_file(path)
+            .await
+            .with_context(|| format!("Failed to read metadata of {}", path_str))?;
+        let metadata = decoder.metadata();
+        let end = metadata.end.map_or(u64::MAX, |e| e.get());
+        return Ok((metadata.start, end, true));
+    }
+
+    let (first, last) =
+        csv_time_range(path)?.ok_or_else(|| anyhow::anyhow!("{} contains no events", path_str))?;
+    Ok((first, last + 1, false))
+}
+
+// The ts_event of the first and last rows of a CSV, which this crate writes in time
+// order. A plain file is read at both ends. A compressed one cannot seek, so its rows are
+// decompressed and split but only the last is parsed
+fn csv_time_range(path: &Path) -> Result<Option<(u64, u64)>> {
+    let path_str = path.to_string_lossy();
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path_str))?;
+    let compressed = path_str.ends_with(".zst");
+    let source: Box<dyn Read> = if compressed {
+        Box::new(zstd::Decoder::new(file).context("Failed to create zstd decoder")?)
+    } else {
+        Box::new(file)
+    };
+    let mut reader = csv::Reader::from_reader(source);
+    let column = reader
+        .byte_headers()?
+        .iter()
+        .position(|h| h == b"ts_event")
+        .ok_or_else(|| anyhow::anyhow!("{} has no ts_event column", path_str))?;
+    let ts_of = |record: &csv::ByteRecord| -> Result<u64> {
+        let raw = record.get(column).unwrap_or_default();
+        std::str::from_utf8(raw)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{}: ts_event {:?} is not a timestamp",
+                    path_str,
+                    String::from_utf8_lossy(raw)
+                )
+            })
+    };
+
+    let mut record = csv::ByteRecord::new();
+    if !reader.read_byte_record(&mut record)? {
+        return Ok(None);
+    }
+    let first = ts_of(&record)?;
+
+    if compressed {
+        let mut last = record.clone();
+        while reader.read_byte_record(&mut record)? {
+            std::mem::swap(&mut last, &mut record);
+        }
+        return Ok(Some((first, ts_of(&last)?)));
+    }
+
+    let tail = last_line(path)?;
+    let mut tail_reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(tail.as_slice());
+    if !tail_reader.read_byte_record(&mut record)? {
+        return Ok(Some((first, first)));
+    }
+    Ok(Some((first, ts_of(&record)?)))
+}
+
+// The last non-empty line of a file, read backwards in growing chunks from its end
+fn last_line(path: &Path) -> Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut size = 4096u64;
+    loop {
+        let start = len.saturating_sub(size);
+        file.seek(SeekFrom::Start(start))?;
+        let mut chunk = Vec::with_capacity((len - start) as usize);
+        (&mut file).take(len - start).read_to_end(&mut chunk)?;
+        while chunk.last().is_some_and(|&b| b == b'\n' || b == b'\r') {
+            chunk.pop();
+        }
+        if let Some(i) = chunk.iter().rposition(|&b| b == b'\n') {
+            return Ok(chunk.split_off(i + 1));
+        }
+        if start == 0 {
+            return Ok(chunk);
+        }
+        size *= 4;
+    }
+}
+
+// True when a trading day of `calendar` falls within `[start_ns, end_ns)`
+fn has_trading_day(calendar: &TradingCalendar, start_ns: u64, end_ns: u64) -> bool {
+    let mut day = calendar.trading_date(start_ns);
+    let last = calendar.trading_date(end_ns.saturating_sub(1));
+    while day <= last {
+        if calendar.is_trading_day(day) {
+            return true;
+        }
+        match day.next_day() {
+            Some(next) => day = next,
+            None => break,
+        }
+    }
+    false
+}
+
+/// Resolve `path` to its files in chronological order and check that consecutive files
+/// neither overlap nor leave a gap. Gaps that only cover a weekend are allowed, and gaps
+/// are only detectable between DBN files whose metadata records the requested range.
+/// Holiday gaps need [`scan_files_on`] and the exchange's calendar.
+pub async fn scan_files(path: &str) -> Result<Vec<DataFile>> {
+    scan_files_on(path, &TradingCalendar::new(ExchangeClock::utc())).await
+}
+
+/// [`scan_files`] allowing gaps that cover no trading day of `calendar`, such as an
+/// exchange holiday. The accepted scan is kept for the process, so the chained stream of
+/// `path` starts without scanning its files again until one of them changes.
+pub async fn scan_files_on(path: &str, calendar: &TradingCalendar) -> Result<Vec<DataFile>> {
+    let resolved = resolve_files(path)?;
+    let stamps = file_stamps(&resolved);
+    let mut files = Vec::new();
+    for file in resolved {
+        let (start, end, exact) = time_range(&file).await?;
+        files.push(DataFile {
+            path: file.to_string_lossy().into_owned(),
+            start,
+            end,
+            exact,
+        });
+    }
+    files.sort_by_key(|f| f.start);
+
+    for pair in files.windows(2) {
+        let (prev, next) = (&pair[0], &pair[1]);
+        if next.start < prev.end {
+            return Err(anyhow::anyhow!(
+                "{} overlaps {} ({} < {})",
+                next.path,
+                prev.path,
+                next.start,
+                prev.end
+            ));
+        }
+        // Event spans of CSVs never meet exactly, so gaps are only checked on DBN ranges
+        let exact = prev.exact && next.exact;
+        if exact && next.start > prev.end && has_trading_day(calendar, prev.end, next.start) {
+            return Err(anyhow::anyhow!(
+                "Gap between {} and {} ({} to {})",
+                prev.path,
+                next.path,
+                prev.end,
+                next.start
+            ));
+        }
+    }
+
+    if let Ok(mut scans) = scans().lock() {
+        scans.insert(
+            path.to_string(),
+            CachedScan {
+                stamps,
+                files: files.clone(),
+            },
+        );
+    }
+    Ok(files)
+}
+
+// Scans accepted this process, keyed by data path, with the length and modification time
+// of every file so a changed directory is scanned again
+struct CachedScan {
+    stamps: Vec<(PathBuf, u64, Option<SystemTime>)>,
+    files: Vec<DataFile>,
+}
+
+fn scans() -> &'static Mutex<HashMap<String, CachedScan>> {
+    static SCANS: OnceLock<Mutex<HashMap<String, CachedScan>>> = OnceLock::new();
+    SCANS.get_or_init(Default::default)
+}
+
+fn file_stamps(files: &[PathBuf]) -> Vec<(PathBuf, u64, Option<SystemTime>)> {
+    files
+        .iter()
+        .map(|file| {
+            let meta = std::fs::metadata(file).ok();
+            (
+                file.clone(),
+                meta.as_ref().map_or(0, |m| m.len()),
+                meta.and_then(|m| m.modified().ok()),
+            )
+        })
+        .collect()
+}
+
+/// The files of `path` as last accepted by [`scan_files`] or [`scan_files_on`], scanned
+/// with the weekday calendar when they never were or have changed since
+pub async fn scanned_files(path: &str) -> Result<Vec<DataFile>> {
+    let stamps = file_stamps(&resolve_files(path)?);
+    let cached = scans().lock().ok().and_then(|scans| {
+        scans
+            .get(path)
+            .filter(|scan| scan.stamps == stamps)
+            .map(|scan| scan.files.clone())
+    });
+    match cached {
+        Some(files) => Ok(files),
+        None => scan_files(path).await,
+    }
+}
+
+/// Stream every file of a directory or glob in chronological order as one dataset
+pub async fn get_chained_stream(path: &str, schema: Schema) -> Result<MarketStream> {
+    Ok(chain_files(scanned_files(path).await?, schema))
+}
+
+/// Stream already scanned `files` one after another, each opened once the previous ends
+pub fn chain_files(files: Vec<DataFile>, schema: Schema) -> MarketStream {
+    let stream = stream::iter(files)
+        .then(move |file| async move { get_file_stream(&file.path, schema).await })
+        .try_flatten();
+    Box::pin(stream) as MarketStream
+}
+
+/// Merge streams that cover the same period, e.g. one file per symbol, into one in
+/// timestamp order. Ties go to the earlier stream, and each stream's own order is kept.
+/// An error from any stream is passed on and that stream ends.
+pub fn interleave_streams(streams: Vec<MarketStream>) -> MarketStream {
+    let heads: Vec<Option<Result<MarketEvent>>> = streams.iter().map(|_| None).collect();
+    let done = vec![false; streams.len()];
+    let stream = stream::unfold(
+        (streams, heads, done),
+        |(mut streams, mut heads, mut done)| async move {
+            for (i, stream) in streams.iter_mut().enumerate() {
+                if heads[i].is_none() && !done[i] {
+                    heads[i] = stream.next().await;
+                    done[i] = heads[i].is_none();
+                }
+            }
+            // Errors first, then the earliest event
+            let next = heads
+                .iter()
+                .enumerate()
+                .filter_map(|(i, head)| match head {
+                    Some(Err(_)) => Some((i, 0)),
+                    Some(Ok(event)) => Some((i, event.timestamp())),
+                    None => None,
+                })
+                .min_by_key(|&(_, ts)| ts)?
+                .0;
+            let item = heads[next].take()?;
+            if item.is_err() {
+                done[next] = true;
+            }
+            Some((item, (streams, heads, done)))
+        },
+    );
+    Box::pin(stream) as MarketStream
+}
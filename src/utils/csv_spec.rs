@@ -0,0 +1,231 @@
+use crate::utils::fetch::BacktestManager;
+use crate::utils::skips::{SkipLog, SkipReason};
+use anyhow::{Context, Result};
+use csv::Writer;
+use databento::dbn::Schema;
+use std::collections::HashSet;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, PrimitiveDateTime};
+
+/// How the timestamp column of a third-party CSV is encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimestampFormat {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+    /// `YYYY-MM-DD`, `YYYY-MM-DD HH:MM:SS` or RFC 3339, taken as UTC
+    DateTime,
+}
+
+/// Column mapping for CSVs that don't use InkBack's own column names
+#[derive(Debug, Clone)]
+pub struct CsvSpec {
+    pub ts_column: String,
+    pub ts_format: TimestampFormat,
+    pub open_column: Option<String>,
+    pub high_column: Option<String>,
+    pub low_column: Option<String>,
+    pub close_column: String,
+    pub volume_column: Option<String>,
+    /// Multiplier applied to every price, e.g. 0.01 for prices quoted in cents
+    pub price_scale: f64,
+    pub delimiter: u8,
+}
+
+impl Default for CsvSpec {
+    fn default() -> Self {
+        Self {
+            ts_column: "ts_event".to_string(),
+            ts_format: TimestampFormat::Nanoseconds,
+            open_column: Some("open".to_string()),
+            high_column: Some("high".to_string()),
+            low_column: Some("low".to_string()),
+            close_column: "close".to_string(),
+            volume_column: Some("volume".to_string()),
+            price_scale: 1.0,
+            delimiter: b',',
+        }
+    }
+}
+
+// configurations for common exports
+impl CsvSpec {
+    /// Yahoo Finance daily history download
+    pub fn yahoo() -> Self {
+        Self {
+            ts_column: "Date".to_string(),
+            ts_format: TimestampFormat::DateTime,
+            open_column: Some("Open".to_string()),
+            high_column: Some("High".to_string()),
+            low_column: Some("Low".to_string()),
+            close_column: "Close".to_string(),
+            volume_column: Some("Volume".to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Yahoo Finance history using the split and dividend adjusted close
+    pub fn yahoo_adjusted() -> Self {
+        Self {
+            close_column: "Adj Close".to_string(),
+            ..Self::yahoo()
+        }
+    }
+
+    /// Close-only series with an epoch millisecond timestamp, as most broker APIs export
+    pub fn epoch_millis(ts_column: &str, close_column: &str) -> Self {
+        Self {
+            ts_column: ts_column.to_string(),
+            ts_format: TimestampFormat::Milliseconds,
+            open_column: None,
+            high_column: None,
+            low_column: None,
+            close_column: close_column.to_string(),
+            volume_column: None,
+            ..Self::default()
+        }
+    }
+
+    fn parse_timestamp(&self, raw: &str) -> Result<u64> {
+        let raw = raw.trim();
+        let epoch = |mult: u64| -> Result<u64> {
+            // Some exports write epochs as floats
+            let value: f64 = raw
+                .parse()
+                .with_context(|| format!("Invalid timestamp '{}'", raw))?;
+            Ok((value * mult as f64) as u64)
+        };
+
+        match self.ts_format {
+            TimestampFormat::Seconds => epoch(1_000_000_000),
+            TimestampFormat::Milliseconds => epoch(1_000_000),
+            TimestampFormat::Microseconds => epoch(1_000),
+            TimestampFormat::Nanoseconds => raw
+                .parse::<u64>()
+                .with_context(|| format!("Invalid timestamp '{}'", raw)),
+            TimestampFormat::DateTime => {
+                let dt = if let Ok(dt) = time::OffsetDateTime::parse(raw, &Rfc3339) {
+                    dt
+                } else if let Ok(dt) = PrimitiveDateTime::parse(
+                    raw,
+                    format_description!("[year]-[month]-[day] [hour]:[minute]:[second]"),
+                ) {
+                    dt.assume_utc()
+                } else {
+                    Date::parse(raw, format_description!("[year]-[month]-[day]"))
+                        .with_context(|| format!("Invalid timestamp '{}'", raw))?
+                        .midnight()
+                        .assume_utc()
+                };
+                u64::try_from(dt.unix_timestamp_nanos())
+                    .with_context(|| format!("Timestamp before 1970: '{}'", raw))
+            }
+        }
+    }
+}
+
+/// Convert a third-party CSV to InkBack's OHLCV CSV layout and return a manager for it.
+///
+/// The converted file is written to `src/data/imported_<name>.csv` next to the other data
+/// files, rows are sorted by timestamp and rows with unparsable prices (e.g. Yahoo's
+/// "null" rows on holidays) are skipped.
+pub fn load_csv(path: &str, symbol: &str, spec: &CsvSpec) -> Result<BacktestManager> {
+    let stem = Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("data");
+    let out_path = format!("src/data/imported_{}.csv", stem);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .delimiter(spec.delimiter)
+        .from_path(path)
+        .with_context(|| format!("Failed to open {}", path))?;
+
+    let headers = reader.headers()?.clone();
+    let index = |name: &str| -> Result<usize> {
+        headers
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| anyhow::anyhow!("Column '{}' not found in {}", name, path))
+    };
+    let optional =
+        |name: &Option<String>| -> Result<Option<usize>> { name.as_deref().map(index).transpose() };
+
+    let ts_idx = index(&spec.ts_column)?;
+    let close_idx = index(&spec.close_column)?;
+    let open_idx = optional(&spec.open_column)?;
+    let high_idx = optional(&spec.high_column)?;
+    let low_idx = optional(&spec.low_column)?;
+    let volume_idx = optional(&spec.volume_column)?;
+
+    let mut rows: Vec<(u64, [f64; 4], u64)> = Vec::new();
+    let mut skips = SkipLog::default();
+
+    for record in reader.records() {
+        let record = record?;
+        let ts = spec.parse_timestamp(record.get(ts_idx).unwrap_or(""))?;
+
+        let price = |idx: Option<usize>| -> Option<f64> {
+            record
+                .get(idx?)
+                .and_then(|s| s.trim().parse::<f64>().ok())
+                .filter(|p| p.is_finite())
+                .map(|p| p * spec.price_scale)
+        };
+
+        let Some(close) = price(Some(close_idx)) else {
+            skips.skip(SkipReason::MissingPrice, || {
+                record.iter().collect::<Vec<_>>().join(",")
+            });
+            continue;
+        };
+        let open = price(open_idx).unwrap_or(close);
+        let high = price(high_idx).unwrap_or(open.max(close));
+        let low = price(low_idx).unwrap_or(open.min(close));
+        let volume = volume_idx
+            .and_then(|i| record.get(i))
+            .and_then(|s| s.trim().parse::<f64>().ok())
+            .map_or(0, |v| v as u64);
+
+        rows.push((ts, [open, high, low, close], volume));
+    }
+
+    if rows.is_empty() {
+        return Err(anyhow::anyhow!("No usable rows found in {}", path));
+    }
+    rows.sort_by_key(|r| r.0);
+
+    let mut writer = Writer::from_path(&out_path)?;
+    writer.write_record(["ts_event", "open", "high", "low", "close", "volume"])?;
+    for (ts, [open, high, low, close], volume) in &rows {
+        writer.write_record(&[
+            ts.to_string(),
+            open.to_string(),
+            high.to_string(),
+            low.to_string(),
+            close.to_string(),
+            volume.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+
+    println!(
+        "Imported {} rows ({} skipped) from {} to {}",
+        rows.len(),
+        skips.total(),
+        path,
+        out_path
+    );
+    skips.display(path);
+
+    Ok(BacktestManager {
+        symbols: HashSet::from([symbol.to_string()]),
+        schema: Schema::Ohlcv1D,
+        data_path: out_path,
+        holdout: None,
+    })
+}
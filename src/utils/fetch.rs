@@ -1,85 +1,125 @@
-use crate::event::{FootprintMsg, MarketEvent, OptionTradeMsg};
-use crate::InkBackSchema;
+use crate::event::{
+    date_string, format_fixed_price, parse_fixed_price, FootprintMsg, MarketEvent, OptionTradeMsg,
+};
+use crate::holdout::Holdout;
+use crate::time_of_day::{ExchangeClock, TradingCalendar};
+use crate::utils::aggressor::{
+    display_classification_stats, Aggressor, AggressorClassifier, ClassificationStats,
+    SideClassifier,
+};
+use crate::utils::chain;
+use crate::utils::skips::{SkipLog, SkipReason};
+use crate::{InkBackSchema, UnderlyingQuotes};
 use anyhow::{Context, Result};
 use csv::Writer;
 use databento::dbn::FlagSet;
 use databento::{
     dbn::{
-        decode::AsyncDbnDecoder, InstrumentDefMsg, MboMsg, Mbp1Msg, OhlcvMsg, RType, RecordHeader,
-        SType, Schema, TradeMsg,
+        decode::{AsyncDbnDecoder, DbnDecoder, DbnMetadata, DecodeRecord},
+        BboMsg, HasRType, InstrumentDefMsg, MboMsg, Mbp1Msg, OhlcvMsg, RType, RecordHeader, SType,
+        Schema, TradeMsg, UNDEF_PRICE,
     },
     historical::timeseries::GetRangeToFileParams,
     HistoricalClient,
 };
-use futures::stream::{self, Stream};
-use std::collections::{HashMap, HashSet};
+use futures::stream::{self, Stream, StreamExt};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::path::Path;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use time::OffsetDateTime;
 
 pub type MarketStream = Pin<Box<dyn Stream<Item = Result<MarketEvent>> + Send>>;
 
+/// Stream the events of a data file, or of every file in a directory / `*` glob chained
+/// in chronological order
 pub async fn get_data_stream(path_str: &str, schema: Schema) -> Result<MarketStream> {
+    if chain::is_multi_file(path_str) {
+        return chain::get_chained_stream(path_str, schema).await;
+    }
+    get_file_stream(path_str, schema).await
+}
+
+// Records decoded per batch handed from the decoding thread
+const DECODE_BATCH: usize = 4096;
+// Batches decoded ahead of the consumer
+const BATCHES_AHEAD: usize = 4;
+
+/// Stream the records of `decoder` as events. Records are decoded on a blocking thread in
+/// batches ahead of the consumer, so decompression overlaps the backtest and the per-event
+/// cost downstream is an iterator step rather than an await on the decoder. A decode error
+/// ends the stream after the events before it.
+fn decode_batches<T, R>(mut decoder: DbnDecoder<R>, to_event: fn(&T) -> MarketEvent) -> MarketStream
+where
+    T: HasRType + 'static,
+    R: std::io::Read + Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::mpsc::channel::<Vec<Result<MarketEvent>>>(BATCHES_AHEAD);
+    tokio::task::spawn_blocking(move || loop {
+        let mut batch = Vec::with_capacity(DECODE_BATCH);
+        let mut done = false;
+        while batch.len() < DECODE_BATCH {
+            match decoder.decode_record::<T>() {
+                Ok(Some(rec)) => batch.push(Ok(to_event(rec))),
+                Ok(None) => {
+                    done = true;
+                    break;
+                }
+                Err(e) => {
+                    batch.push(Err(anyhow::anyhow!(e)));
+                    done = true;
+                    break;
+                }
+            }
+        }
+        // A send fails once the stream is dropped, which stops the decoding too
+        if batch.is_empty() || sender.blocking_send(batch).is_err() || done {
+            break;
+        }
+    });
+    let batches = stream::unfold(receiver, |mut receiver| async move {
+        let batch = receiver.recv().await?;
+        Some((batch, receiver))
+    });
+    Box::pin(batches.flat_map(stream::iter))
+}
+
+/// Stream the events of a single data file
+pub async fn get_file_stream(path_str: &str, schema: Schema) -> Result<MarketStream> {
     let path = Path::new(path_str);
     let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
 
+    // Compressed CSV artifacts share the .zst extension with DBN files
+    if path_str.ends_with(".csv.zst") {
+        let file = std::fs::File::open(path)?;
+        let decoder = zstd::Decoder::new(file).context("Failed to create zstd decoder")?;
+        return csv_stream(decoder);
+    }
+
     match extension {
         "zst" | "dbn" => {
-            let decoder = AsyncDbnDecoder::from_zstd_file(path)
-                .await
-                .context("Failed to create AsyncDbnDecoder")?;
+            let decoder =
+                DbnDecoder::from_zstd_file(path).context("Failed to create DbnDecoder")?;
 
             // Match based on the Schema to know which struct to decode
             match schema {
-                Schema::Trades => {
-                    let stream = stream::unfold(decoder, |mut dec| async move {
-                        match dec.decode_record::<TradeMsg>().await {
-                            Ok(Some(rec)) => Some((Ok(MarketEvent::Trade(rec.clone())), dec)),
-                            Ok(None) => None,
-                            Err(e) => Some((Err(anyhow::anyhow!(e)), dec)),
-                        }
-                    });
-                    Ok(Box::pin(stream) as MarketStream)
-                }
-                Schema::Mbo => {
-                    let stream = stream::unfold(decoder, |mut dec| async move {
-                        match dec.decode_record::<MboMsg>().await {
-                            Ok(Some(rec)) => Some((Ok(MarketEvent::Mbo(rec.clone())), dec)),
-                            Ok(None) => None,
-                            Err(e) => Some((Err(anyhow::anyhow!(e)), dec)),
-                        }
-                    });
-                    Ok(Box::pin(stream) as MarketStream)
-                }
-                Schema::Mbp1 => {
-                    let stream = stream::unfold(decoder, |mut dec| async move {
-                        match dec.decode_record::<Mbp1Msg>().await {
-                            Ok(Some(rec)) => Some((Ok(MarketEvent::Mbp1(rec.clone())), dec)),
-                            Ok(None) => None,
-                            Err(e) => Some((Err(anyhow::anyhow!(e)), dec)),
-                        }
-                    });
-                    Ok(Box::pin(stream) as MarketStream)
-                }
-                Schema::Definition => {
-                    let stream = stream::unfold(decoder, |mut dec| async move {
-                        match dec.decode_record::<InstrumentDefMsg>().await {
-                            Ok(Some(rec)) => Some((Ok(MarketEvent::Definition(rec.clone())), dec)),
-                            Ok(None) => None,
-                            Err(e) => Some((Err(anyhow::anyhow!(e)), dec)),
-                        }
-                    });
-                    Ok(Box::pin(stream) as MarketStream)
-                }
+                Schema::Trades => Ok(decode_batches(decoder, |rec: &TradeMsg| {
+                    MarketEvent::Trade(rec.clone())
+                })),
+                Schema::Mbo => Ok(decode_batches(decoder, |rec: &MboMsg| {
+                    MarketEvent::Mbo(rec.clone())
+                })),
+                Schema::Mbp1 => Ok(decode_batches(decoder, |rec: &Mbp1Msg| {
+                    MarketEvent::Mbp1(rec.clone())
+                })),
+                Schema::Definition => Ok(decode_batches(decoder, |rec: &InstrumentDefMsg| {
+                    MarketEvent::Definition(Box::new(rec.clone()))
+                })),
                 Schema::Ohlcv1S | Schema::Ohlcv1M | Schema::Ohlcv1H | Schema::Ohlcv1D => {
-                    let stream = stream::unfold(decoder, |mut dec| async move {
-                        match dec.decode_record::<OhlcvMsg>().await {
-                            Ok(Some(rec)) => Some((Ok(MarketEvent::Ohlcv(rec.clone())), dec)),
-                            Ok(None) => None,
-                            Err(e) => Some((Err(anyhow::anyhow!(e)), dec)),
-                        }
-                    });
-                    Ok(Box::pin(stream) as MarketStream)
+                    Ok(decode_batches(decoder, |rec: &OhlcvMsg| {
+                        MarketEvent::Ohlcv(rec.clone())
+                    }))
                 }
                 _ => Err(anyhow::anyhow!(
                     "Schema {:?} not yet supported in get_data_stream",
@@ -89,136 +129,247 @@ pub async fn get_data_stream(path_str: &str, schema: Schema) -> Result<MarketStr
         }
         "csv" => {
             let file = std::fs::File::open(path)?;
-            let mut reader = csv::ReaderBuilder::new()
-                .has_headers(true)
-                .from_reader(file);
-
-            let headers = reader.headers()?.clone();
-            let is_footprint = headers.iter().any(|h| h == "footprint_data");
-            let is_merged_options = headers.iter().any(|h| h == "option_type");
-
-            let iter = reader.into_deserialize().map(move |result| {
-                let record: std::collections::HashMap<String, String> =
-                    result.map_err(|e| anyhow::anyhow!(e))?;
-
-                // Helper for parsing
-                let parse_f64 = |key: &str| {
-                    record
-                        .get(key)
-                        .and_then(|s| s.parse::<f64>().ok())
-                        .unwrap_or(0.0)
-                };
-                let parse_u64 = |key: &str| {
-                    record
-                        .get(key)
-                        .and_then(|s| s.parse::<u64>().ok())
-                        .unwrap_or(0)
+            csv_stream(file)
+        }
+        _ => Err(anyhow::anyhow!("Unsupported file extension: {}", extension)),
+    }
+}
+
+// Parse an InkBack CSV (footprint, merged options or OHLCV) from any reader
+fn csv_stream<R: std::io::Read + Send + 'static>(source: R) -> Result<MarketStream> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(source);
+
+    // Column of each header, so rows are read into one reused record instead of a map
+    let columns: HashMap<String, usize> = reader
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.to_string(), i))
+        .collect();
+    let is_footprint = columns.contains_key("footprint_data");
+    let is_merged_options = columns.contains_key("option_type");
+    let skips = Arc::new(Mutex::new(SkipLog::default()));
+    let summary = skips.clone();
+
+    let mut row = csv::StringRecord::new();
+    let iter = std::iter::from_fn(move || {
+        match reader.read_record(&mut row) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => return Some(Err(anyhow::anyhow!(e))),
+        }
+        let get = |key: &str| columns.get(key).and_then(|&i| row.get(i));
+
+        // Helper for parsing, fields present but unparsable read as zero and are counted
+        let field = |key: &str, parse: &dyn Fn(&str) -> bool| {
+            let raw = get(key)?;
+            if !raw.is_empty() && !parse(raw) {
+                if let Ok(mut skips) = skips.lock() {
+                    skips.skip(SkipReason::UnparsableField, || format!("{}={:?}", key, raw));
+                }
+            }
+            Some(raw)
+        };
+        let parse_f64 = |key: &str| {
+            field(key, &|s| s.parse::<f64>().is_ok())
+                .and_then(|s| s.parse::<f64>().ok())
+                .unwrap_or(0.0)
+        };
+        let parse_u64 = |key: &str| {
+            field(key, &|s| s.parse::<u64>().is_ok())
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+        let parse_u32 = |key: &str| {
+            field(key, &|s| s.parse::<u32>().is_ok())
+                .and_then(|s| s.parse::<u32>().ok())
+                .unwrap_or(0)
+        };
+        // Fixed-point prices are parsed from the decimal text, never via f64
+        let parse_px = |key: &str| {
+            field(key, &|s| parse_fixed_price(s).is_some())
+                .and_then(parse_fixed_price)
+                .unwrap_or(0)
+        };
+
+        let ts = parse_u64("ts_event");
+        Some(if is_merged_options {
+            let event_type = get("event_type").unwrap_or("");
+            let und_bid = parse_f64("underlying_bid");
+            let und_ask = parse_f64("underlying_ask");
+            let und_bid_sz = parse_u32("underlying_bid_sz");
+            let und_ask_sz = parse_u32("underlying_ask_sz");
+
+            if event_type == "OPT" {
+                Ok(MarketEvent::OptionTrade(OptionTradeMsg {
+                    ts_event: ts,
+                    price: parse_f64("price"),
+                    size: parse_u64("size"),
+                    instrument_id: parse_u64("instrument_id") as u32,
+                    symbol: get("symbol").unwrap_or_default().to_string(),
+                    strike_price: parse_f64("strike_price"),
+                    expiration: parse_u64("expiration"),
+                    option_type: get("option_type").unwrap_or_default().to_string(),
+                    underlying_price: parse_f64("underlying_price"),
+                    underlying_bid: und_bid,
+                    underlying_ask: und_ask,
+                    underlying_bid_sz: und_bid_sz,
+                    underlying_ask_sz: und_ask_sz,
+                    // Optional column, present once statistics are merged
+                    open_interest: get("open_interest").and_then(|s| s.parse::<u64>().ok()),
+                    // Optional column, absent from merges made before it was recorded
+                    underlying_ts: get("underlying_ts").and_then(|s| s.parse::<u64>().ok()),
+                    underlying_stale: false,
+                }))
+            } else {
+                let price_scaled = parse_px("price");
+                let bid_px_scaled = parse_px("underlying_bid");
+                let ask_px_scaled = parse_px("underlying_ask");
+                let size = parse_u64("size") as u32;
+
+                let mut levels = [databento::dbn::BidAskPair::default()];
+                levels[0] = databento::dbn::BidAskPair {
+                    bid_px: bid_px_scaled,
+                    ask_px: ask_px_scaled,
+                    bid_sz: und_bid_sz,
+                    ask_sz: und_ask_sz,
+                    bid_ct: 0,
+                    ask_ct: 0,
                 };
-                let parse_u32 = |key: &str| {
-                    record
-                        .get(key)
-                        .and_then(|s| s.parse::<u32>().ok())
-                        .unwrap_or(0)
+
+                let msg = databento::dbn::Mbp1Msg {
+                    hd: RecordHeader::new::<databento::dbn::Mbp1Msg>(RType::Mbp1.into(), 0, 1, ts),
+                    action: 0,
+                    side: 0,
+                    depth: 0,
+                    price: price_scaled,
+                    size,
+                    flags: FlagSet::default(),
+                    ts_in_delta: 0,
+                    sequence: 0,
+                    ts_recv: ts,
+                    levels,
                 };
+                Ok(MarketEvent::Mbp1(msg))
+            }
+        } else if is_footprint {
+            let footprint_data = get("footprint_data").unwrap_or_default().to_string();
+            Ok(MarketEvent::Footprint(FootprintMsg {
+                ts_event: ts,
+                price: parse_f64("close"), // Use close as the price anchor
+                volume: parse_u64("volume"),
+                data: footprint_data,
+            }))
+        } else {
+            let msg = databento::dbn::OhlcvMsg {
+                hd: RecordHeader::new::<databento::dbn::OhlcvMsg>(RType::Ohlcv1S.into(), 0, 1, ts),
+                open: parse_px("open"),
+                high: parse_px("high"),
+                low: parse_px("low"),
+                close: parse_px("close"),
+                volume: parse_u64("volume"),
+            };
+            Ok(MarketEvent::Ohlcv(msg))
+        })
+    });
+    // Summarize once the file is exhausted, taking the log so a re-poll prints nothing
+    let iter = iter.chain(std::iter::from_fn(move || {
+        if let Ok(mut skips) = summary.lock() {
+            std::mem::take(&mut *skips).display("CSV");
+        }
+        None
+    }));
 
-                let ts = parse_u64("ts_event");
-                if is_merged_options {
-                    let event_type = record.get("event_type").map(|s| s.as_str()).unwrap_or("");
-                    let und_bid = parse_f64("underlying_bid");
-                    let und_ask = parse_f64("underlying_ask");
-                    let und_bid_sz = parse_u32("underlying_bid_sz");
-                    let und_ask_sz = parse_u32("underlying_ask_sz");
-
-                    if event_type == "OPT" {
-                        Ok(MarketEvent::OptionTrade(OptionTradeMsg {
-                            ts_event: ts,
-                            price: parse_f64("price"),
-                            size: parse_u64("size"),
-                            instrument_id: parse_u64("instrument_id") as u32,
-                            symbol: record.get("symbol").cloned().unwrap_or_default(),
-                            strike_price: parse_f64("strike_price"),
-                            expiration: parse_u64("expiration"),
-                            option_type: record.get("option_type").cloned().unwrap_or_default(),
-                            underlying_price: parse_f64("underlying_price"),
-                            underlying_bid: und_bid,
-                            underlying_ask: und_ask,
-                            underlying_bid_sz: und_bid_sz,
-                            underlying_ask_sz: und_ask_sz,
-                        }))
-                    } else {
-                        let price_scaled = (parse_f64("price") * 1e9) as i64;
-                        let bid_px_scaled = (parse_f64("underlying_bid") * 1e9) as i64;
-                        let ask_px_scaled = (parse_f64("underlying_ask") * 1e9) as i64;
-                        let size = parse_u64("size") as u32;
-
-                        let mut levels = [databento::dbn::BidAskPair::default()];
-                        levels[0] = databento::dbn::BidAskPair {
-                            bid_px: bid_px_scaled,
-                            ask_px: ask_px_scaled,
-                            bid_sz: und_bid_sz,
-                            ask_sz: und_ask_sz,
-                            bid_ct: 0,
-                            ask_ct: 0,
-                        };
-
-                        let msg = databento::dbn::Mbp1Msg {
-                            hd: RecordHeader::new::<databento::dbn::Mbp1Msg>(
-                                RType::Mbp1.into(),
-                                0,
-                                1,
-                                ts,
-                            ),
-                            action: 0,
-                            side: 0,
-                            depth: 0,
-                            price: price_scaled,
-                            size,
-                            flags: FlagSet::default(),
-                            ts_in_delta: 0,
-                            sequence: 0,
-                            ts_recv: ts,
-                            levels,
-                        };
-                        Ok(MarketEvent::Mbp1(msg))
-                    }
-                } else if is_footprint {
-                    let footprint_data = record.get("footprint_data").cloned().unwrap_or_default();
-                    Ok(MarketEvent::Footprint(FootprintMsg {
-                        ts_event: ts,
-                        price: parse_f64("close"), // Use close as the price anchor
-                        volume: parse_u64("volume"),
-                        data: footprint_data,
-                    }))
-                } else {
-                    let msg = databento::dbn::OhlcvMsg {
-                        hd: RecordHeader::new::<databento::dbn::OhlcvMsg>(
-                            RType::Ohlcv1S.into(),
-                            0,
-                            1,
-                            ts,
-                        ),
-                        open: (parse_f64("open") * 1e9) as i64,
-                        high: (parse_f64("high") * 1e9) as i64,
-                        low: (parse_f64("low") * 1e9) as i64,
-                        close: (parse_f64("close") * 1e9) as i64,
-                        volume: parse_u64("volume"),
-                    };
-                    Ok(MarketEvent::Ohlcv(msg))
-                }
-            });
+    Ok(Box::pin(stream::iter(iter)) as MarketStream)
+}
 
-            Ok(Box::pin(stream::iter(iter)) as MarketStream)
+/// Load the footprint bars of a footprint CSV, optionally restricted to `[start, end)` nanoseconds
+pub async fn load_footprint_bars(
+    path: &str,
+    time_range: Option<(u64, u64)>,
+) -> Result<Vec<FootprintMsg>> {
+    use futures::StreamExt;
+
+    let mut data_iter = get_data_stream(path, Schema::Trades).await?;
+    let mut bars = Vec::new();
+
+    while let Some(event) = data_iter.next().await {
+        if let MarketEvent::Footprint(bar) = event? {
+            if let Some((start_ts, end_ts)) = time_range {
+                if bar.ts_event < start_ts {
+                    continue;
+                }
+                if bar.ts_event >= end_ts {
+                    break;
+                }
+            }
+            bars.push(bar);
         }
-        _ => Err(anyhow::anyhow!("Unsupported file extension: {}", extension)),
     }
+
+    if bars.is_empty() {
+        return Err(anyhow::anyhow!("No footprint bars found in {}", path));
+    }
+    Ok(bars)
 }
 
 #[derive(Clone)]
-#[allow(dead_code)]
 pub struct BacktestManager {
     pub symbols: HashSet<String>,
     pub schema: Schema,
     pub data_path: String,
+    /// Locked end of the data, see [`BacktestManager::with_holdout`]
+    pub holdout: Option<Holdout>,
+}
+
+impl BacktestManager {
+    /// Backtest over a directory of per-day files or a `*` glob such as
+    /// `src/data/ES/glbx-mdp3-*.trades.dbn.zst`. The files are validated up front and
+    /// streamed in chronological order as one continuous dataset.
+    pub async fn from_files(path: &str, symbol: &str, schema: Schema) -> Result<Self> {
+        Self::from_files_on(
+            path,
+            symbol,
+            schema,
+            &TradingCalendar::new(ExchangeClock::utc()),
+        )
+        .await
+    }
+
+    /// [`BacktestManager::from_files`] accepting gaps over the holidays of `calendar`
+    pub async fn from_files_on(
+        path: &str,
+        symbol: &str,
+        schema: Schema,
+        calendar: &TradingCalendar,
+    ) -> Result<Self> {
+        let files = chain::scan_files_on(path, calendar).await?;
+        println!("Found {} contiguous data files for {}", files.len(), path);
+        Ok(Self {
+            symbols: HashSet::from([symbol.to_string()]),
+            schema,
+            data_path: path.to_string(),
+            holdout: None,
+        })
+    }
+
+    /// Lock the final `fraction` of the data's time range away as a holdout. Every run
+    /// through this manager is then limited to the in-sample part, and only
+    /// [`evaluate_holdout`] runs on the holdout.
+    ///
+    /// [`evaluate_holdout`]: crate::holdout::evaluate_holdout
+    pub async fn with_holdout(mut self, fraction: f64) -> Result<Self> {
+        let holdout = Holdout::of_data(&self.data_path, fraction).await?;
+        println!(
+            "Holdout locked from {} ({:.0}% of the data)",
+            date_string(holdout.start_ts),
+            fraction * 100.0
+        );
+        self.holdout = Some(holdout);
+        Ok(self)
+    }
 }
 
 // Struct to holding Option Definition Data
@@ -229,6 +380,181 @@ struct OptionDef {
     option_type: String, // "C" or "P"
 }
 
+impl OptionDef {
+    /// Read `def`, counting a malformed symbol and keeping its lossy conversion
+    fn from_definition(def: &InstrumentDefMsg, skips: &mut SkipLog) -> Self {
+        let sym_str = match raw_symbol(def) {
+            Ok(symbol) => symbol.to_string(),
+            Err(e) => {
+                skips.skip(SkipReason::MalformedSymbol, || {
+                    format!("instrument {}: {}", def.hd.instrument_id, e)
+                });
+                lossy_raw_symbol(def)
+            }
+        };
+
+        let type_char = def.instrument_class as u8 as char;
+        let opt_type = if type_char == 'C' { "C" } else { "P" }.to_string();
+
+        OptionDef {
+            symbol: sym_str,
+            strike_price: (def.strike_price as f64) * 1e-9,
+            expiration: def.expiration,
+            option_type: opt_type,
+        }
+    }
+}
+
+fn raw_symbol(def: &InstrumentDefMsg) -> Result<&str> {
+    def.raw_symbol()
+        .map(|s| s.trim_matches(char::from(0)))
+        .context("Malformed raw_symbol")
+}
+
+// Symbol bytes up to the first nul, with invalid UTF-8 replaced
+fn lossy_raw_symbol(def: &InstrumentDefMsg) -> String {
+    let bytes: Vec<u8> = def
+        .raw_symbol
+        .iter()
+        .map(|&c| c as u8)
+        .take_while(|&b| b != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// Sorted, disjoint, inclusive instrument id ranges. OPRA assigns a chain's ids in
+// contiguous blocks, so the traded contracts of a merge collapse to few ranges
+struct InstrumentIdRanges(Vec<(u32, u32)>);
+
+impl InstrumentIdRanges {
+    fn from_ids(ids: HashSet<u32>) -> Self {
+        let mut ids: Vec<u32> = ids.into_iter().collect();
+        ids.sort_unstable();
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for id in ids {
+            match ranges.last_mut() {
+                Some((_, hi)) if *hi + 1 == id => *hi = id,
+                _ => ranges.push((id, id)),
+            }
+        }
+        Self(ranges)
+    }
+
+    fn contains(&self, id: u32) -> bool {
+        let i = self.0.partition_point(|&(_, hi)| hi < id);
+        self.0.get(i).is_some_and(|&(lo, _)| lo <= id)
+    }
+}
+
+// Instrument ids traded in the options files. Unreadable files are left to the merge
+// itself to report
+async fn traded_instrument_ids(options_paths: &[String]) -> InstrumentIdRanges {
+    let mut ids = HashSet::new();
+    for path in options_paths {
+        let Ok(mut dec) = AsyncDbnDecoder::from_zstd_file(path).await else {
+            continue;
+        };
+        while let Ok(Some(trade)) = dec.decode_record::<TradeMsg>().await {
+            ids.insert(trade.hd.instrument_id);
+        }
+    }
+    InstrumentIdRanges::from_ids(ids)
+}
+
+/// Expiry-windowed view of a definition file for a time-ordered merge.
+///
+/// Definitions are decoded lazily up to the trade being merged, only for the instrument
+/// id ranges that trade, and contracts are evicted once they expire, so memory tracks the
+/// traded live chain rather than every contract listed over the whole date range.
+struct DefinitionWindow<R: tokio::io::AsyncReadExt + Unpin> {
+    decoder: AsyncDbnDecoder<R>,
+    // Ids whose definitions are kept, every other definition is dropped undecoded
+    wanted: InstrumentIdRanges,
+    // Next definition not yet in force at the cursor
+    pending: Option<InstrumentDefMsg>,
+    // Set once the file ends or fails to decode
+    exhausted: bool,
+    live: HashMap<u32, OptionDef>,
+    expiries: BinaryHeap<Reverse<(u64, u32)>>,
+    peak: usize,
+}
+
+impl<R: tokio::io::AsyncReadExt + Unpin> DefinitionWindow<R> {
+    /// Bring the definitions in force at `ts` into the window. A decode error ends the
+    /// definition file, counted in `skips`, rather than the merge.
+    async fn advance_to(&mut self, ts: u64, skips: &mut SkipLog, path: &str) {
+        loop {
+            if self.pending.is_none() && !self.exhausted {
+                self.pending = decoded(
+                    self.decoder.decode_record::<InstrumentDefMsg>().await,
+                    skips,
+                    path,
+                );
+                self.exhausted = self.pending.is_none();
+            }
+            match self.pending.take() {
+                Some(def) if def.hd.ts_event <= ts => {
+                    let id = def.hd.instrument_id;
+                    if !self.wanted.contains(id) {
+                        continue;
+                    }
+                    let info = OptionDef::from_definition(&def, skips);
+                    // Definitions are republished daily, so the heap only takes a new
+                    // entry when the contract is new or its expiration moved
+                    if self.live.get(&id).map(|d| d.expiration) != Some(info.expiration) {
+                        self.expiries.push(Reverse((info.expiration, id)));
+                    }
+                    self.live.insert(id, info);
+                }
+                later => {
+                    self.pending = later;
+                    break;
+                }
+            }
+        }
+
+        // Evict expired contracts, skipping heap entries superseded by a newer definition
+        while let Some(&Reverse((expiration, id))) = self.expiries.peek() {
+            if expiration >= ts {
+                break;
+            }
+            self.expiries.pop();
+            if self
+                .live
+                .get(&id)
+                .is_some_and(|d| d.expiration == expiration)
+            {
+                self.live.remove(&id);
+            }
+        }
+
+        self.peak = self.peak.max(self.live.len());
+    }
+
+    fn get(&self, instrument_id: u32) -> Option<&OptionDef> {
+        self.live.get(&instrument_id)
+    }
+}
+
+async fn open_definition_window(
+    path: &str,
+    wanted: InstrumentIdRanges,
+) -> Result<DefinitionWindow<impl tokio::io::AsyncReadExt + Unpin>> {
+    let decoder = AsyncDbnDecoder::from_zstd_file(path)
+        .await
+        .context("Failed to open definition file")?;
+    Ok(DefinitionWindow {
+        decoder,
+        wanted,
+        pending: None,
+        exhausted: false,
+        live: HashMap::new(),
+        expiries: BinaryHeap::new(),
+        peak: 0,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn fetch_and_save_data(
     dataset: &str,
     stype_in: SType,
@@ -242,8 +568,10 @@ pub async fn fetch_and_save_data(
 ) -> Result<BacktestManager> {
     let req_schema = if let Some(ref cs) = custom_schema {
         match cs {
-            InkBackSchema::FootPrint => Schema::Trades,
-            InkBackSchema::CombinedOptionsUnderlying => Schema::Trades,
+            InkBackSchema::FootPrint { quotes: false, .. } => Schema::Trades,
+            InkBackSchema::FootPrint { quotes: true, .. } => Schema::Tbbo,
+            InkBackSchema::CombinedOptionsUnderlying { .. } => Schema::Trades,
+            InkBackSchema::TimeBars { .. } => Schema::Trades,
         }
     } else {
         schema
@@ -254,61 +582,71 @@ pub async fn fetch_and_save_data(
         symbol, req_schema, start, end
     );
 
-    let final_data_path: String = if custom_schema.is_none() {
-        // Standard
-        let filename = format!(
-            "src/data/{}_{}_{}-{}.zst",
-            symbol,
-            schema,
-            start.date(),
-            end.date()
-        );
-
-        // If file exists, skip request
-        if Path::new(&filename).exists() {
-            println!("Creating cached Data found at: {}", filename);
-            filename
-        } else {
-            let mut client = HistoricalClient::builder()
-                .key_from_env()
-                .context("Missing DataBento Key in .env file")?
-                .build()
-                .context("Failed to build DataBento client")?;
-
-            client
-                .timeseries()
-                .get_range_to_file(
-                    &GetRangeToFileParams::builder()
-                        .dataset(dataset)
-                        .stype_in(stype_in)
-                        .date_time_range((start, end))
-                        .symbols(symbol)
-                        .schema(schema)
-                        .path(&filename)
-                        .build(),
-                )
-                .await?;
+    let final_data_path: String = match custom_schema {
+        None => {
+            // Standard
+            let filename = format!(
+                "src/data/{}_{}_{}-{}.zst",
+                symbol,
+                schema,
+                start.date(),
+                end.date()
+            );
 
-            println!("Saved Data (Standard)");
-            filename
+            // If file exists, skip request
+            if Path::new(&filename).exists() {
+                println!("Creating cached Data found at: {}", filename);
+                filename
+            } else {
+                let mut client = HistoricalClient::builder()
+                    .key_from_env()
+                    .context("Missing DataBento Key in .env file")?
+                    .build()
+                    .context("Failed to build DataBento client")?;
+
+                client
+                    .timeseries()
+                    .get_range_to_file(
+                        &GetRangeToFileParams::builder()
+                            .dataset(dataset)
+                            .stype_in(stype_in)
+                            .date_time_range((start, end))
+                            .symbols(symbol)
+                            .schema(schema)
+                            .path(&filename)
+                            .build(),
+                    )
+                    .await?;
+
+                println!("Saved Data (Standard)");
+                filename
+            }
         }
-    } else {
-        match custom_schema.unwrap() {
+        Some(custom) => match custom {
             // Footprint
-            InkBackSchema::FootPrint => {
+            InkBackSchema::FootPrint { sides, quotes } => {
+                let source = if quotes {
+                    "footprint_tbbo"
+                } else {
+                    "footprint"
+                };
                 let filename = format!(
-                    "src/data/footprint_{}_{}_{}-{}.zst",
+                    "src/data/{}_{}_{}_{}-{}.zst",
+                    source,
                     symbol,
                     schema,
                     start.date(),
                     end.date()
                 );
+                // Bars classified under another rule are cached apart
                 let csv_filename = format!(
-                    "src/data/footprint_{}_{}_{}-{}.csv",
+                    "src/data/{}_{}_{}_{}-{}{}.csv",
+                    source,
                     symbol,
                     schema,
                     start.date(),
-                    end.date()
+                    end.date(),
+                    sides.file_tag()
                 );
 
                 // If the final CSV exists, we are done
@@ -318,6 +656,7 @@ pub async fn fetch_and_save_data(
                         symbols: HashSet::from([symbol.to_string()]),
                         schema: req_schema,
                         data_path: csv_filename,
+                        holdout: None,
                     });
                 }
 
@@ -338,7 +677,7 @@ pub async fn fetch_and_save_data(
                                 .stype_in(stype_in)
                                 .date_time_range((start, end))
                                 .symbols(symbol)
-                                .schema(Schema::Trades)
+                                .schema(req_schema)
                                 .path(&filename)
                                 .build(),
                         )
@@ -350,77 +689,84 @@ pub async fn fetch_and_save_data(
 
                 // Process ZST to CSV
                 println!("Processing Footprint ZST to CSV...");
-                let file = std::fs::File::create(&csv_filename)?;
-                let mut writer = Writer::from_writer(file);
-                let mut decoder = AsyncDbnDecoder::from_zstd_file(&filename).await.ok();
+                footprint_to_csv(
+                    &filename,
+                    &csv_filename,
+                    bar_interval_ns.unwrap_or(15_000_000_000u64),
+                    &sides,
+                )
+                .await?;
+                println!("Saved Data (Footprint CSV)");
+                csv_filename
+            }
 
-                writer.write_record(&[
-                    "ts_event",
-                    "open",
-                    "high",
-                    "low",
-                    "close",
-                    "volume",
-                    "footprint_data",
-                ])?;
+            // Time bars
+            InkBackSchema::TimeBars { interval } => {
+                if interval == 0 {
+                    return Err(anyhow::anyhow!("TimeBars interval must be positive"));
+                }
+                let filename = format!(
+                    "src/data/{}_{}_{}-{}.zst",
+                    symbol,
+                    Schema::Trades,
+                    start.date(),
+                    end.date()
+                );
+                let csv_filename = format!(
+                    "src/data/bars_{}ns_{}_{}-{}.csv",
+                    interval,
+                    symbol,
+                    start.date(),
+                    end.date()
+                );
 
-                let mut current_bar_start: Option<u64> = None;
-                let mut current_bar_trades: Vec<TradeMsg> = Vec::new();
-                let scaling_factor = 1e-9;
-
-                let interval_ns = bar_interval_ns.unwrap_or(15_000_000_000u64);
-                if let Some(dec) = &mut decoder {
-                    while let Ok(Some(msg)) = dec.decode_record::<TradeMsg>().await {
-                        let trade_time = msg.ts_recv;
-                        let bar_start = (trade_time / interval_ns) * interval_ns;
-
-                        if let Some(prev_bar_start) = current_bar_start {
-                            if bar_start != prev_bar_start {
-                                let footprint_bar =
-                                    process_footprint_bar(&current_bar_trades, scaling_factor);
-                                writer.write_record(&[
-                                    prev_bar_start.to_string(),
-                                    footprint_bar.open.to_string(),
-                                    footprint_bar.high.to_string(),
-                                    footprint_bar.low.to_string(),
-                                    footprint_bar.close.to_string(),
-                                    footprint_bar.volume.to_string(),
-                                    footprint_bar.footprint_data,
-                                ])?;
-                                current_bar_trades.clear();
-                            }
-                        }
-                        current_bar_start = Some(bar_start);
-                        current_bar_trades.push(msg.clone());
-                    }
+                if Path::new(&csv_filename).exists() {
+                    println!("Time bar CSV found at: {}", csv_filename);
+                    return Ok(BacktestManager {
+                        symbols: HashSet::from([symbol.to_string()]),
+                        schema: req_schema,
+                        data_path: csv_filename,
+                        holdout: None,
+                    });
+                }
 
-                    // Process final bar
-                    if !current_bar_trades.is_empty() {
-                        if let Some(final_bar_start) = current_bar_start {
-                            let footprint_bar =
-                                process_footprint_bar(&current_bar_trades, scaling_factor);
-                            writer.write_record(&[
-                                final_bar_start.to_string(),
-                                footprint_bar.open.to_string(),
-                                footprint_bar.high.to_string(),
-                                footprint_bar.low.to_string(),
-                                footprint_bar.close.to_string(),
-                                footprint_bar.volume.to_string(),
-                                footprint_bar.footprint_data,
-                            ])?;
-                        }
-                    }
+                // The raw trades are shared by every bar interval
+                if !Path::new(&filename).exists() {
+                    let mut client = HistoricalClient::builder()
+                        .key_from_env()
+                        .context("Missing DataBento Key in .env file")?
+                        .build()
+                        .context("Failed to build DataBento client")?;
+
+                    client
+                        .timeseries()
+                        .get_range_to_file(
+                            &GetRangeToFileParams::builder()
+                                .dataset(dataset)
+                                .stype_in(stype_in)
+                                .date_time_range((start, end))
+                                .symbols(symbol)
+                                .schema(Schema::Trades)
+                                .path(&filename)
+                                .build(),
+                        )
+                        .await?;
+                    println!("Downloaded Raw Trades (ZST)");
+                } else {
+                    println!("Raw Trades (ZST) found, skipping download.");
                 }
-                writer.flush()?;
-                println!("Saved Data (Footprint CSV)");
+
+                println!("Aggregating trades into {}ns bars...", interval);
+                aggregate_time_bars(&filename, &csv_filename, interval).await?;
                 csv_filename
             }
 
             // Options Underlying
-            InkBackSchema::CombinedOptionsUnderlying => {
+            InkBackSchema::CombinedOptionsUnderlying { underlying } => {
                 let underlying_file = format!(
-                    "src/data/{}_mbp1_{}-{}.zst",
+                    "src/data/{}_{}_{}-{}.zst",
                     symbol,
+                    underlying.tag(),
                     start.date(),
                     end.date()
                 );
@@ -437,12 +783,22 @@ pub async fn fetch_and_save_data(
                     end.date()
                 );
 
-                let final_merged_csv = format!(
-                    "src/data/MERGED_{}_{}-{}.csv",
-                    symbol,
-                    start.date(),
-                    end.date()
-                );
+                // Mbp1 merges keep the name they had before the quote source was configurable
+                let final_merged_csv = match underlying {
+                    UnderlyingQuotes::Mbp1 => format!(
+                        "src/data/MERGED_{}_{}-{}.csv.zst",
+                        symbol,
+                        start.date(),
+                        end.date()
+                    ),
+                    quotes => format!(
+                        "src/data/MERGED_{}_{}_{}-{}.csv.zst",
+                        symbol,
+                        quotes.tag(),
+                        start.date(),
+                        end.date()
+                    ),
+                };
 
                 // Check if merged file already exists
                 if Path::new(&final_merged_csv).exists() {
@@ -451,13 +807,14 @@ pub async fn fetch_and_save_data(
                         symbols: HashSet::from([symbol.to_string()]),
                         schema,
                         data_path: final_merged_csv,
+                        holdout: None,
                     });
                 }
 
                 println!("Merged data not found. Starting download and merge process...");
 
                 if !Path::new(&underlying_file).exists() {
-                    println!("Downloading Underlying...");
+                    println!("Downloading Underlying ({:?})...", underlying);
                     let mut client = HistoricalClient::builder().key_from_env()?.build()?;
                     client
                         .timeseries()
@@ -467,7 +824,7 @@ pub async fn fetch_and_save_data(
                                 .stype_in(stype_in)
                                 .date_time_range((start, end))
                                 .symbols(symbol)
-                                .schema(Schema::Mbp1)
+                                .schema(underlying.schema())
                                 .path(&underlying_file)
                                 .build(),
                         )
@@ -518,6 +875,10 @@ pub async fn fetch_and_save_data(
                     }
                 }
 
+                // Definitions are re-published daily, request each contract once
+                opt_ids.sort_unstable();
+                opt_ids.dedup();
+
                 if opt_ids.is_empty() {
                     return Err(anyhow::anyhow!("No relevant options found for {}", symbol));
                 }
@@ -581,6 +942,7 @@ pub async fn fetch_and_save_data(
                 println!("Merging Underlying and Options into CSV...");
                 merge_streams_to_csv(
                     &underlying_file,
+                    underlying,
                     &options_files,
                     &opt_def_file,
                     &final_merged_csv,
@@ -589,7 +951,7 @@ pub async fn fetch_and_save_data(
 
                 final_merged_csv
             }
-        }
+        },
     };
 
     // Construct the manager
@@ -597,20 +959,29 @@ pub async fn fetch_and_save_data(
         symbols: HashSet::from([symbol.to_string()]),
         schema: req_schema,
         data_path: final_data_path,
+        holdout: None,
     };
 
     Ok(backtest_manager)
 }
 
-async fn merge_streams_to_csv(
+/// Merge the underlying's `quotes` with the option trades of `options_paths` in time
+/// order, enriching each trade with its definition and the prevailing quote, into the
+/// zstd-compressed CSV at `output_path`
+pub async fn merge_streams_to_csv(
     underlying_path: &str,
+    quotes: UnderlyingQuotes,
     options_paths: &[String],
     def_path: &str,
     output_path: &str,
 ) -> Result<()> {
-    let mut writer = Writer::from_path(output_path)?;
+    // Merged chains reach tens of GB as plain text, so the CSV is zstd compressed on the fly
+    let file = std::fs::File::create(output_path)
+        .with_context(|| format!("Failed to create {}", output_path))?;
+    let encoder = zstd::Encoder::new(file, 3).context("Failed to create zstd encoder")?;
+    let mut writer = Writer::from_writer(encoder);
 
-    writer.write_record(&[
+    writer.write_record([
         "ts_event",
         "event_type",
         "instrument_id",
@@ -620,60 +991,51 @@ async fn merge_streams_to_csv(
         "strike_price",
         "expiration",
         "option_type",
+        "underlying_price",
         "underlying_bid",
         "underlying_ask",
         "underlying_bid_sz",
         "underlying_ask_sz",
+        "underlying_ts",
     ])?;
 
-    // Pre-load definitions so every trade lookup is instant
-    println!("Pre-loading definitions from {}...", def_path);
-    let mut def_map: HashMap<u32, OptionDef> = HashMap::new();
-    {
-        let mut def_decoder = AsyncDbnDecoder::from_zstd_file(def_path)
-            .await
-            .context("Failed to open definition file")?;
-
-        while let Ok(Some(def)) = def_decoder.decode_record::<InstrumentDefMsg>().await {
-            let sym_str = std::str::from_utf8(unsafe {
-                std::slice::from_raw_parts(
-                    def.raw_symbol.as_ptr() as *const u8,
-                    def.raw_symbol.len(),
-                )
-            })
-            .unwrap_or("")
-            .trim_matches(char::from(0))
-            .to_string();
-
-            let type_char = def.instrument_class as u8 as char;
-            let opt_type = if type_char == 'C' { "C" } else { "P" }.to_string();
-
-            def_map.insert(
-                def.hd.instrument_id,
-                OptionDef {
-                    symbol: sym_str,
-                    strike_price: (def.strike_price as f64) * 1e-9,
-                    expiration: def.expiration,
-                    option_type: opt_type,
-                },
-            );
-        }
-    }
-    println!("Loaded {} definitions.", def_map.len());
+    // Definitions are streamed alongside the trades instead of pre-loaded, so only
+    // contracts that trade and are listed and unexpired at the merge cursor are held in
+    // memory. A first pass over the trades finds the instrument id ranges to keep
+    let traded = traded_instrument_ids(options_paths).await;
+    println!(
+        "Options trades span {} instrument id range(s)",
+        traded.0.len()
+    );
+    let mut definitions = open_definition_window(def_path, traded).await?;
 
     // Stream 0 = underlying, streams 1..=N = one per options batch file
     // Each slot: Option<(timestamp, msg)>
     #[derive(Clone)]
     enum StreamMsg {
-        Underlying(Mbp1Msg),
+        Underlying(UnderlyingQuote),
         Option(TradeMsg),
     }
 
-    let mut und_decoder = AsyncDbnDecoder::from_zstd_file(underlying_path).await.ok();
+    let mut skips = SkipLog::default();
+    let mut und_decoder = match AsyncDbnDecoder::from_zstd_file(underlying_path).await {
+        Ok(dec) => Some(dec),
+        Err(e) => {
+            skips.skip(SkipReason::UnreadableFile, || {
+                format!("{}: {}", underlying_path, e)
+            });
+            None
+        }
+    };
     let mut opt_decoders: Vec<_> = Vec::new();
+    let mut opt_paths: Vec<&str> = Vec::new();
     for path in options_paths {
-        if let Ok(dec) = AsyncDbnDecoder::from_zstd_file(path).await {
-            opt_decoders.push(Some(dec));
+        match AsyncDbnDecoder::from_zstd_file(path).await {
+            Ok(dec) => {
+                opt_decoders.push(Some(dec));
+                opt_paths.push(path);
+            }
+            Err(e) => skips.skip(SkipReason::UnreadableFile, || format!("{}: {}", path, e)),
         }
     }
 
@@ -683,24 +1045,36 @@ async fn merge_streams_to_csv(
 
     // Prime the underlying slot
     if let Some(dec) = &mut und_decoder {
-        if let Ok(Some(msg)) = dec.decode_record::<Mbp1Msg>().await {
-            slots[0] = Some((msg.hd.ts_event, StreamMsg::Underlying(msg.clone())));
+        if let Some(quote) = next_underlying(dec, quotes, &mut skips, underlying_path).await {
+            slots[0] = Some((quote.ts, StreamMsg::Underlying(quote)));
         }
     }
     // Prime each options slot
     for (i, opt_dec) in opt_decoders.iter_mut().enumerate() {
         if let Some(dec) = opt_dec {
-            if let Ok(Some(msg)) = dec.decode_record::<TradeMsg>().await {
+            if let Some(msg) = decoded(
+                dec.decode_record::<TradeMsg>().await,
+                &mut skips,
+                opt_paths[i],
+            ) {
                 slots[i + 1] = Some((msg.hd.ts_event, StreamMsg::Option(msg.clone())));
             }
         }
     }
 
-    let mut last_und_bid = 0.0f64;
-    let mut last_und_ask = 0.0f64;
+    // Quotes carry forward to every option trade until the next underlying record, so
+    // with Bbo1s they are up to a second stale
+    let mut last_und_ts: Option<u64> = None;
+    let mut last_und_price = 0i64;
+    let mut last_und_bid = 0i64;
+    let mut last_und_ask = 0i64;
     let mut last_und_bid_sz = 0u32;
     let mut last_und_ask_sz = 0u32;
 
+    let mut und_rows = 0u64;
+    let mut opt_rows = 0u64;
+    let mut unmatched_opts = 0u64;
+
     println!("Starting Merge ({} options file(s))...", opt_decoders.len());
 
     loop {
@@ -720,58 +1094,94 @@ async fn merge_streams_to_csv(
         if let Some((_, msg)) = slots[idx].take() {
             match msg {
                 StreamMsg::Underlying(u) => {
-                    let price = (u.price as f64) * 1e-9;
-                    if !u.levels.is_empty() {
-                        last_und_bid = (u.levels[0].bid_px as f64) * 1e-9;
-                        last_und_ask = (u.levels[0].ask_px as f64) * 1e-9;
-                        last_und_bid_sz = u.levels[0].bid_sz;
-                        last_und_ask_sz = u.levels[0].ask_sz;
+                    last_und_ts = Some(u.ts);
+                    last_und_price = u.price;
+                    if let Some(level) = u.level {
+                        last_und_bid = level.bid_px;
+                        last_und_ask = level.ask_px;
+                        last_und_bid_sz = level.bid_sz;
+                        last_und_ask_sz = level.ask_sz;
                     }
                     writer.write_record(&[
-                        u.hd.ts_event.to_string(),
+                        u.ts.to_string(),
                         "UND".to_string(),
                         "0".to_string(),
                         "UNDERLYING".to_string(),
-                        price.to_string(),
+                        format_fixed_price(u.price),
                         u.size.to_string(),
                         "".to_string(),
                         "".to_string(),
                         "".to_string(),
-                        last_und_bid.to_string(),
-                        last_und_ask.to_string(),
+                        "".to_string(),
+                        format_fixed_price(last_und_bid),
+                        format_fixed_price(last_und_ask),
                         last_und_bid_sz.to_string(),
                         last_und_ask_sz.to_string(),
+                        "".to_string(),
                     ])?;
+                    und_rows += 1;
                     // Refill underlying
                     if let Some(dec) = &mut und_decoder {
-                        if let Ok(Some(m)) = dec.decode_record::<Mbp1Msg>().await {
-                            slots[0] = Some((m.hd.ts_event, StreamMsg::Underlying(m.clone())));
+                        if let Some(quote) =
+                            next_underlying(dec, quotes, &mut skips, underlying_path).await
+                        {
+                            slots[0] = Some((quote.ts, StreamMsg::Underlying(quote)));
                         }
                     }
                 }
                 StreamMsg::Option(o) => {
-                    if let Some(def) = def_map.get(&o.hd.instrument_id) {
-                        let price = (o.price as f64) * 1e-9;
+                    definitions
+                        .advance_to(o.hd.ts_event, &mut skips, def_path)
+                        .await;
+                    if let Some(def) = definitions.get(o.hd.instrument_id) {
                         writer.write_record(&[
                             o.hd.ts_event.to_string(),
                             "OPT".to_string(),
                             o.hd.instrument_id.to_string(),
                             def.symbol.clone(),
-                            price.to_string(),
+                            format_fixed_price(o.price),
                             o.size.to_string(),
                             def.strike_price.to_string(),
                             def.expiration.to_string(),
                             def.option_type.clone(),
-                            last_und_bid.to_string(),
-                            last_und_ask.to_string(),
+                            // Prevailing mid, the last price while the quote is one-sided
+                            format_fixed_price(
+                                if last_und_bid > 0
+                                    && last_und_ask > 0
+                                    && last_und_bid != i64::MAX
+                                    && last_und_ask != i64::MAX
+                                {
+                                    (last_und_bid + last_und_ask) / 2
+                                } else {
+                                    last_und_price
+                                },
+                            ),
+                            format_fixed_price(last_und_bid),
+                            format_fixed_price(last_und_ask),
                             last_und_bid_sz.to_string(),
                             last_und_ask_sz.to_string(),
+                            last_und_ts.map(|ts| ts.to_string()).unwrap_or_default(),
                         ])?;
+                        opt_rows += 1;
+                    } else {
+                        unmatched_opts += 1;
+                        skips.skip(SkipReason::UnknownInstrument, || {
+                            format!(
+                                "ts_event {} instrument_id {} price {}",
+                                o.hd.ts_event,
+                                o.hd.instrument_id,
+                                format_fixed_price(o.price)
+                            )
+                        });
                     }
                     // Refill this options slot
                     let opt_idx = idx - 1;
                     if let Some(dec) = &mut opt_decoders[opt_idx] {
-                        if let Ok(Some(m)) = dec.decode_record::<TradeMsg>().await {
+                        if let Some(m) = decoded(
+                            dec.decode_record::<TradeMsg>().await,
+                            &mut skips,
+                            opt_paths[opt_idx],
+                        ) {
                             slots[idx] = Some((m.hd.ts_event, StreamMsg::Option(m.clone())));
                         }
                     }
@@ -781,9 +1191,320 @@ async fn merge_streams_to_csv(
     }
 
     writer.flush()?;
+    writer
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("Failed to flush merged CSV: {}", e))?
+        .finish()
+        .context("Failed to finish zstd stream")?;
+
+    let size_mb = std::fs::metadata(output_path)
+        .map(|m| m.len() as f64 / (1024.0 * 1024.0))
+        .unwrap_or(0.0);
+    println!(
+        "Definition window peaked at {} live contracts",
+        definitions.peak
+    );
+    println!(
+        "Merged {} rows ({} underlying, {} options, {} options trades without a definition skipped) into {} ({:.1} MB compressed)",
+        und_rows + opt_rows,
+        und_rows,
+        opt_rows,
+        unmatched_opts,
+        output_path,
+        size_mb
+    );
+    skips.display(output_path);
+
     Ok(())
 }
 
+// An underlying record of the merge, whichever schema it was read from
+#[derive(Clone)]
+struct UnderlyingQuote {
+    ts: u64,
+    price: i64,
+    size: u32,
+    // Top of book, None for trades
+    level: Option<databento::dbn::BidAskPair>,
+}
+
+// The next usable underlying record, skipping those without a defined price
+async fn next_underlying<R: tokio::io::AsyncReadExt + Unpin>(
+    dec: &mut AsyncDbnDecoder<R>,
+    quotes: UnderlyingQuotes,
+    skips: &mut SkipLog,
+    path: &str,
+) -> Option<UnderlyingQuote> {
+    loop {
+        let quote = match quotes {
+            UnderlyingQuotes::Mbp1 => {
+                let m = decoded(dec.decode_record::<Mbp1Msg>().await, skips, path)?;
+                UnderlyingQuote {
+                    ts: m.hd.ts_event,
+                    price: m.price,
+                    size: m.size,
+                    level: Some(m.levels[0].clone()),
+                }
+            }
+            UnderlyingQuotes::Bbo1s => {
+                // Sampled records are stamped at the end of their interval
+                let m = decoded(dec.decode_record::<BboMsg>().await, skips, path)?;
+                let level = m.levels[0].clone();
+                let mid = (level.bid_px != i64::MAX && level.ask_px != i64::MAX)
+                    .then(|| (level.bid_px + level.ask_px) / 2);
+                UnderlyingQuote {
+                    ts: m.ts_recv,
+                    // No trade yet in the session, marked at the mid
+                    price: if m.price == i64::MAX {
+                        mid.unwrap_or(i64::MAX)
+                    } else {
+                        m.price
+                    },
+                    size: m.size,
+                    level: Some(level),
+                }
+            }
+            UnderlyingQuotes::Trades => {
+                let m = decoded(dec.decode_record::<TradeMsg>().await, skips, path)?;
+                UnderlyingQuote {
+                    ts: m.hd.ts_event,
+                    price: m.price,
+                    size: m.size,
+                    level: (m.price != i64::MAX).then_some(databento::dbn::BidAskPair {
+                        bid_px: m.price,
+                        ask_px: m.price,
+                        ..Default::default()
+                    }),
+                }
+            }
+        };
+        if quote.price != i64::MAX {
+            return Some(quote);
+        }
+        skips.skip(SkipReason::UndefinedPrice, || {
+            format!("{} ts {}", path, quote.ts)
+        });
+    }
+}
+
+// A decoded record, counting a decode error as the end of its file
+fn decoded<T: Clone, E: std::fmt::Display>(
+    result: std::result::Result<Option<&T>, E>,
+    skips: &mut SkipLog,
+    path: &str,
+) -> Option<T> {
+    match result {
+        Ok(msg) => msg.cloned(),
+        Err(e) => {
+            skips.skip(SkipReason::DecodeError, || format!("{}: {}", path, e));
+            None
+        }
+    }
+}
+
+/// Aggregate a trades DBN file into footprint bars of `interval_ns`, keyed by the bar
+/// start of each trade's `ts_recv`, with the per-level volumes as JSON. Each trade's
+/// side is decided by `sides`; trades it cannot classify count toward the bar's
+/// volume but no level's.
+///
+/// A TBBO or MBP-1 file gives each trade its prevailing quote, for `sides` to classify
+/// against and for every level to split its volume traded at the bid and at the ask.
+pub async fn footprint_to_csv(
+    trades_path: &str,
+    csv_path: &str,
+    interval_ns: u64,
+    sides: &SideClassifier,
+) -> Result<ClassificationStats> {
+    let file = std::fs::File::create(csv_path)?;
+    let mut writer = Writer::from_writer(file);
+    let mut skips = SkipLog::default();
+    let mut decoder = match AsyncDbnDecoder::from_zstd_file(trades_path).await {
+        Ok(dec) => Some(dec),
+        Err(e) => {
+            skips.skip(SkipReason::UnreadableFile, || {
+                format!("{}: {}", trades_path, e)
+            });
+            None
+        }
+    };
+
+    writer.write_record([
+        "ts_event",
+        "open",
+        "high",
+        "low",
+        "close",
+        "volume",
+        "footprint_data",
+    ])?;
+
+    let mut current_bar_start: Option<u64> = None;
+    let mut current_bar_trades: Vec<FootprintTrade> = Vec::new();
+    let mut classifier = AggressorClassifier::new(sides.clone());
+    let scaling_factor = 1e-9;
+
+    if let Some(dec) = &mut decoder {
+        let quoted = matches!(dec.metadata().schema, Some(Schema::Tbbo | Schema::Mbp1));
+        loop {
+            // (ts_recv, price, size, side, quote before the trade), None for the book
+            // updates of an MBP-1 file
+            let decoded = if quoted {
+                dec.decode_record::<Mbp1Msg>().await.map(|m| {
+                    m.map(|m| {
+                        let quote = (m.levels[0].bid_px, m.levels[0].ask_px);
+                        (m.action as u8 == b'T').then_some((
+                            m.ts_recv,
+                            m.price,
+                            m.size,
+                            m.side,
+                            Some(quote),
+                        ))
+                    })
+                })
+            } else {
+                dec.decode_record::<TradeMsg>()
+                    .await
+                    .map(|m| m.map(|m| Some((m.ts_recv, m.price, m.size, m.side, None))))
+            };
+            let (ts_recv, price, size, side, quote) = match decoded {
+                Ok(Some(Some(trade))) => trade,
+                Ok(Some(None)) => continue,
+                Ok(None) => break,
+                Err(e) => {
+                    skips.skip(SkipReason::DecodeError, || {
+                        format!("{}: {}", trades_path, e)
+                    });
+                    break;
+                }
+            };
+            let bar_start = (ts_recv / interval_ns) * interval_ns;
+
+            if let Some(prev_bar_start) = current_bar_start {
+                if bar_start != prev_bar_start {
+                    let footprint_bar = process_footprint_bar(&current_bar_trades, scaling_factor);
+                    writer.write_record(&[
+                        prev_bar_start.to_string(),
+                        footprint_bar.open.to_string(),
+                        footprint_bar.high.to_string(),
+                        footprint_bar.low.to_string(),
+                        footprint_bar.close.to_string(),
+                        footprint_bar.volume.to_string(),
+                        footprint_bar.footprint_data,
+                    ])?;
+                    current_bar_trades.clear();
+                }
+            }
+            current_bar_start = Some(bar_start);
+            current_bar_trades.push(FootprintTrade {
+                price,
+                size: size as u64,
+                side: classifier.classify(price, side as u8, size as u64, quote),
+                quote,
+            });
+        }
+
+        // Process final bar
+        if !current_bar_trades.is_empty() {
+            if let Some(final_bar_start) = current_bar_start {
+                let footprint_bar = process_footprint_bar(&current_bar_trades, scaling_factor);
+                writer.write_record(&[
+                    final_bar_start.to_string(),
+                    footprint_bar.open.to_string(),
+                    footprint_bar.high.to_string(),
+                    footprint_bar.low.to_string(),
+                    footprint_bar.close.to_string(),
+                    footprint_bar.volume.to_string(),
+                    footprint_bar.footprint_data,
+                ])?;
+            }
+        }
+    }
+    writer.flush()?;
+    skips.display(trades_path);
+    let stats = classifier.stats();
+    display_classification_stats(trades_path, &stats);
+    Ok(stats)
+}
+
+/// Aggregate a trades DBN file into OHLCV bars of `interval_ns`, keyed by `ts_event`.
+/// Bars without trades are omitted, matching DataBento's own OHLCV schemas.
+async fn aggregate_time_bars(trades_path: &str, csv_path: &str, interval_ns: u64) -> Result<()> {
+    let mut decoder = AsyncDbnDecoder::from_zstd_file(trades_path)
+        .await
+        .context("Failed to open trades file")?;
+    let mut writer = Writer::from_path(csv_path)?;
+    writer.write_record(["ts_event", "open", "high", "low", "close", "volume"])?;
+
+    // (bar_start, open, high, low, close, volume) in fixed-point prices
+    let mut bar: Option<(u64, i64, i64, i64, i64, u64)> = None;
+    let mut bars = 0u64;
+    let mut skips = SkipLog::default();
+    let write_bar = |writer: &mut Writer<std::fs::File>,
+                     (ts, open, high, low, close, volume): (u64, i64, i64, i64, i64, u64)|
+     -> Result<()> {
+        writer.write_record(&[
+            ts.to_string(),
+            format_fixed_price(open),
+            format_fixed_price(high),
+            format_fixed_price(low),
+            format_fixed_price(close),
+            volume.to_string(),
+        ])?;
+        Ok(())
+    };
+
+    while let Some(trade) = decoder.decode_record::<TradeMsg>().await? {
+        if trade.price == i64::MAX {
+            skips.skip(SkipReason::UndefinedPrice, || {
+                format!("ts_event {} size {}", trade.hd.ts_event, trade.size)
+            });
+            continue;
+        }
+        let bar_start = (trade.hd.ts_event / interval_ns) * interval_ns;
+        match &mut bar {
+            // Trades are ordered by ts_recv, so a late ts_event folds into the open bar
+            Some((ts, _, high, low, close, volume)) if bar_start <= *ts => {
+                *high = (*high).max(trade.price);
+                *low = (*low).min(trade.price);
+                *close = trade.price;
+                *volume += trade.size as u64;
+            }
+            _ => {
+                if let Some(done) = bar.take() {
+                    write_bar(&mut writer, done)?;
+                    bars += 1;
+                }
+                bar = Some((
+                    bar_start,
+                    trade.price,
+                    trade.price,
+                    trade.price,
+                    trade.price,
+                    trade.size as u64,
+                ));
+            }
+        }
+    }
+    if let Some(done) = bar {
+        write_bar(&mut writer, done)?;
+        bars += 1;
+    }
+
+    writer.flush()?;
+    println!("Saved {} time bars to {}", bars, csv_path);
+    skips.display(trades_path);
+    Ok(())
+}
+
+#[derive(Debug)]
+struct FootprintTrade {
+    price: i64,
+    size: u64,
+    side: Option<Aggressor>,
+    /// Fixed-point bid and ask prevailing before the trade
+    quote: Option<(i64, i64)>,
+}
+
 #[derive(Debug)]
 struct FootprintBar {
     open: f64,
@@ -794,8 +1515,8 @@ struct FootprintBar {
     footprint_data: String,
 }
 
-fn process_footprint_bar(trades: &[TradeMsg], scaling_factor: f64) -> FootprintBar {
-    use std::collections::HashMap;
+fn process_footprint_bar(trades: &[FootprintTrade], scaling_factor: f64) -> FootprintBar {
+    use std::collections::BTreeMap;
 
     if trades.is_empty() {
         return FootprintBar {
@@ -811,13 +1532,14 @@ fn process_footprint_bar(trades: &[TradeMsg], scaling_factor: f64) -> FootprintB
     // Calculate OHLCV
     let first_price = (trades[0].price as f64) * scaling_factor;
     let last_price = (trades[trades.len() - 1].price as f64) * scaling_factor;
+    let quoted = trades[0].quote.is_some();
 
     let mut high = first_price;
     let mut low = first_price;
     let mut total_volume = 0u64;
 
-    // Map to store footprint data: price -> (buy_volume, sell_volume)
-    let mut footprint_map: HashMap<String, (u64, u64)> = HashMap::new();
+    // Footprint data keyed by the raw fixed-point price: [buy, sell, at_bid, at_ask]
+    let mut footprint_map: BTreeMap<i64, [u64; 4]> = BTreeMap::new();
 
     for trade in trades {
         let price = (trade.price as f64) * scaling_factor;
@@ -830,23 +1552,53 @@ fn process_footprint_bar(trades: &[TradeMsg], scaling_factor: f64) -> FootprintB
         if price < low {
             low = price;
         }
-        total_volume += size as u64;
-
-        // Determine if trade is buy or sell
-        // In your data, side 66 = 'B' (buy), side 83 = 'S' (sell)
-        // side 65 = 'A' (ask/sell), side 78 = 'N' (unknown - we'll ignore)
-        let price_key = format!("{:.4}", price);
-        let entry = footprint_map.entry(price_key).or_insert((0, 0));
+        total_volume += size;
 
+        let entry = footprint_map.entry(trade.price).or_insert([0; 4]);
         match trade.side {
-            66 => entry.0 += size as u64,      // Buy side
-            65 | 83 => entry.1 += size as u64, // Sell side (Ask or Sell)
-            _ => {}                            // Ignore other sides (like 'N')
+            Some(Aggressor::Buy) => entry[0] += size,
+            Some(Aggressor::Sell) => entry[1] += size,
+            None => {}
+        }
+        // Trades inside the spread count toward neither side of the book
+        if let Some((bid, ask)) = trade.quote {
+            if bid != UNDEF_PRICE && trade.price <= bid {
+                entry[2] += size;
+            } else if ask != UNDEF_PRICE && trade.price >= ask {
+                entry[3] += size;
+            }
         }
     }
 
-    // Convert footprint map to JSON string
-    let footprint_json = serde_json::to_string(&footprint_map).unwrap_or_else(|_| "{}".to_string());
+    let level_json = |[buy, sell, at_bid, at_ask]: [u64; 4]| {
+        if quoted {
+            serde_json::json!([buy, sell, at_bid, at_ask])
+        } else {
+            serde_json::json!([buy, sell])
+        }
+    };
+    // Price keys are formatted once per level, merging levels that round alike
+    let mut levels = serde_json::Map::new();
+    let mut level: Option<(String, [u64; 4])> = None;
+    for (price, volumes) in footprint_map {
+        let key = format!("{:.4}", (price as f64) * scaling_factor);
+        match &mut level {
+            Some((current, merged)) if *current == key => {
+                for (m, v) in merged.iter_mut().zip(volumes) {
+                    *m += v;
+                }
+            }
+            _ => {
+                if let Some((key, merged)) = level.replace((key, volumes)) {
+                    levels.insert(key, level_json(merged));
+                }
+            }
+        }
+    }
+    if let Some((key, merged)) = level {
+        levels.insert(key, level_json(merged));
+    }
+    let footprint_json = serde_json::Value::Object(levels).to_string();
 
     FootprintBar {
         open: first_price,
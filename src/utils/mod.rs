@@ -1,2 +1,8 @@
 // src/utils/mod.rs
+pub mod aggressor;
+pub mod chain;
+pub mod csv_spec;
 pub mod fetch;
+pub mod skips;
+pub mod snapshot;
+pub mod synthetic;
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+// Sample records kept per reason
+const SAMPLES: usize = 3;
+
+/// Why a record never became an event, or lost data on the way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SkipReason {
+    /// A data file that could not be opened
+    UnreadableFile,
+    /// A record that failed to decode, ending its file early
+    DecodeError,
+    /// An option trade without a live definition at the merge cursor
+    UnknownInstrument,
+    /// A trade carrying DataBento's undefined price
+    UndefinedPrice,
+    /// A definition whose raw symbol is not valid UTF-8, kept with a lossy conversion
+    MalformedSymbol,
+    /// A CSV row without a usable price
+    MissingPrice,
+    /// A CSV field that did not parse and was read as zero
+    UnparsableField,
+}
+
+impl SkipReason {
+    fn describe(&self) -> &'static str {
+        match self {
+            SkipReason::UnreadableFile => "unreadable files",
+            SkipReason::DecodeError => "decode errors",
+            SkipReason::UnknownInstrument => "option trades without a definition",
+            SkipReason::UndefinedPrice => "trades with an undefined price",
+            SkipReason::MalformedSymbol => "definitions with a malformed symbol",
+            SkipReason::MissingPrice => "rows without a usable price",
+            SkipReason::UnparsableField => "unparsable fields read as zero",
+        }
+    }
+}
+
+/// Counts of dropped records per reason with a few samples of each, so data loss during
+/// fetching and loading is reported instead of silent
+#[derive(Debug, Default)]
+pub struct SkipLog {
+    tallies: BTreeMap<SkipReason, (usize, Vec<String>)>,
+}
+
+impl SkipLog {
+    /// Count one record dropped for `reason`, `sample` describing it for the first few
+    pub fn skip(&mut self, reason: SkipReason, sample: impl FnOnce() -> String) {
+        let (count, samples) = self.tallies.entry(reason).or_default();
+        *count += 1;
+        if samples.len() < SAMPLES {
+            samples.push(sample());
+        }
+    }
+
+    pub fn count(&self, reason: SkipReason) -> usize {
+        self.tallies.get(&reason).map_or(0, |(count, _)| *count)
+    }
+
+    pub fn total(&self) -> usize {
+        self.tallies.values().map(|(count, _)| count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tallies.is_empty()
+    }
+
+    /// Print the counts and samples of everything dropped from `source`, nothing when
+    /// nothing was
+    pub fn display(&self, source: &str) {
+        if self.is_empty() {
+            return;
+        }
+        println!("\n=== SKIPPED RECORDS: {} ===", source);
+        for (reason, (count, samples)) in &self.tallies {
+            println!("{} {}", count, reason.describe());
+            for sample in samples {
+                println!("  e.g. {}", sample);
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::backtester::BacktestResult;
+use crate::utils::chain;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Content hash of one input file at the time of a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataFingerprint {
+    pub path: String,
+    pub bytes: u64,
+    /// FNV-1a 64 of the file contents, in hex
+    pub hash: String,
+}
+
+// Hashes already computed this process, keyed by path, length and modification time so
+// the files of a sweep are read once rather than once per combination
+type FingerprintCache = HashMap<(PathBuf, u64, Option<SystemTime>), String>;
+
+fn cache() -> &'static Mutex<FingerprintCache> {
+    static CACHE: OnceLock<Mutex<FingerprintCache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn fnv1a(path: &PathBuf) -> Result<String> {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {} for hashing", path.display()))?;
+    let mut buf = vec![0u8; 1 << 20];
+    let mut hash = OFFSET;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for &b in &buf[..n] {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+/// Fingerprint every file behind a data path: a single file, or each file of a directory
+/// or `*` glob
+pub fn fingerprint_data(data_path: &str) -> Result<Vec<DataFingerprint>> {
+    let files = if chain::is_multi_file(data_path) {
+        chain::resolve_files(data_path)?
+    } else {
+        vec![PathBuf::from(data_path)]
+    };
+
+    files
+        .into_iter()
+        .map(|path| {
+            let meta = std::fs::metadata(&path)
+                .with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+            let key = (path.clone(), meta.len(), meta.modified().ok());
+
+            let cached = cache().lock().ok().and_then(|c| c.get(&key).cloned());
+            let hash = match cached {
+                Some(hash) => hash,
+                None => {
+                    let hash = fnv1a(&path)?;
+                    if let Ok(mut c) = cache().lock() {
+                        c.insert(key, hash.clone());
+                    }
+                    hash
+                }
+            };
+            Ok(DataFingerprint {
+                path: path.to_string_lossy().into_owned(),
+                bytes: meta.len(),
+                hash,
+            })
+        })
+        .collect()
+}
+
+/// Warn loudly when labelled results were run on different data and return false.
+///
+/// Results are compared with the first one carrying fingerprints. Results without any,
+/// such as baselines or results saved before fingerprints were recorded, are skipped.
+pub fn check_same_data<'a>(
+    results: impl IntoIterator<Item = (&'a str, &'a BacktestResult)>,
+) -> bool {
+    let mut reference: Option<(&str, &[DataFingerprint])> = None;
+    let mut mismatches = Vec::new();
+
+    for (label, result) in results {
+        if result.data_files.is_empty() {
+            continue;
+        }
+        match reference {
+            None => reference = Some((label, &result.data_files)),
+            Some((_, files)) if files != result.data_files.as_slice() => {
+                mismatches.push((label, &result.data_files))
+            }
+            Some(_) => {}
+        }
+    }
+
+    let Some((reference_label, reference_files)) = reference else {
+        return true;
+    };
+    if mismatches.is_empty() {
+        return true;
+    }
+
+    eprintln!("\n!!! WARNING: RESULTS WERE RUN ON DIFFERENT DATA, THEY ARE NOT COMPARABLE !!!");
+    eprintln!("Reference: {}", reference_label);
+    for (label, files) in mismatches {
+        eprintln!("  {} differs:", label);
+        for file in files {
+            match reference_files.iter().find(|f| f.path == file.path) {
+                Some(f) if f == file => {}
+                Some(f) => eprintln!(
+                    "    {} changed: {} ({} bytes) -> {} ({} bytes)",
+                    file.path, f.hash, f.bytes, file.hash, file.bytes
+                ),
+                None => eprintln!("    {} is not in the reference data", file.path),
+            }
+        }
+        for f in reference_files {
+            if !files.iter().any(|file| file.path == f.path) {
+                eprintln!("    {} is missing", f.path);
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,220 @@
+use crate::event::{MarketEvent, OptionTradeMsg};
+use crate::greeks;
+use crate::seeds::SeededRng;
+use crate::utils::fetch::MarketStream;
+use databento::dbn::{BidAskPair, Mbp1Msg, OhlcvMsg, RType, RecordHeader, TradeMsg};
+use futures::stream;
+
+const NANOS_PER_DAY: u64 = 86_400_000_000_000;
+
+/// Deterministic synthetic market data, for testing strategies and the engine without
+/// downloading anything.
+///
+/// Prices follow a seeded log-normal random walk rounded to cents, so the same settings
+/// always produce the same events.
+#[derive(Debug, Clone)]
+pub struct SyntheticMarket {
+    pub seed: u64,
+    /// Timestamp of the first event in nanoseconds since the epoch
+    pub start_ts: u64,
+    /// Nanoseconds between events
+    pub interval: u64,
+    pub start_price: f64,
+    /// Mean log return per step
+    pub drift: f64,
+    /// Standard deviation of the log return per step
+    pub volatility: f64,
+}
+
+impl Default for SyntheticMarket {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            // 2024-01-02 14:30 UTC, a US session open
+            start_ts: 1_704_205_800_000_000_000,
+            interval: 60_000_000_000,
+            start_price: 100.0,
+            drift: 0.0,
+            volatility: 0.002,
+        }
+    }
+}
+
+fn fixed(price: f64) -> i64 {
+    (price * 100.0).round() as i64 * 10_000_000
+}
+
+fn cents(price: f64) -> f64 {
+    (price * 100.0).round() / 100.0
+}
+
+impl SyntheticMarket {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_start(mut self, start_ts: u64, interval: u64) -> Self {
+        self.start_ts = start_ts;
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_price(mut self, start_price: f64) -> Self {
+        self.start_price = start_price;
+        self
+    }
+
+    pub fn with_drift(mut self, drift: f64) -> Self {
+        self.drift = drift;
+        self
+    }
+
+    pub fn with_volatility(mut self, volatility: f64) -> Self {
+        self.volatility = volatility;
+        self
+    }
+
+    fn ts(&self, i: usize) -> u64 {
+        self.start_ts + i as u64 * self.interval
+    }
+
+    // `steps` prices of the walk after the start price
+    fn walk(&self, rng: &mut SeededRng, steps: usize) -> Vec<f64> {
+        let mut price = self.start_price;
+        (0..steps)
+            .map(|_| {
+                price *= (self.drift + self.volatility * rng.normal()).exp();
+                price = price.max(0.01);
+                cents(price)
+            })
+            .collect()
+    }
+
+    /// `n` OHLCV bars, each spanning four steps of the walk
+    pub fn ohlcv(&self, n: usize) -> Vec<MarketEvent> {
+        let mut rng = SeededRng::new(self.seed);
+        let path = self.walk(&mut rng, n * 4);
+        let mut open = cents(self.start_price);
+
+        path.chunks(4)
+            .enumerate()
+            .map(|(i, steps)| {
+                let close = steps[3];
+                let high = steps.iter().fold(open, |a, &b| a.max(b));
+                let low = steps.iter().fold(open, |a, &b| a.min(b));
+                let bar = OhlcvMsg {
+                    hd: RecordHeader::new::<OhlcvMsg>(RType::Ohlcv1M.into(), 0, 1, self.ts(i)),
+                    open: fixed(open),
+                    high: fixed(high),
+                    low: fixed(low),
+                    close: fixed(close),
+                    volume: 100 + rng.below(1000) as u64,
+                };
+                open = close;
+                MarketEvent::Ohlcv(bar)
+            })
+            .collect()
+    }
+
+    /// `n` trade prints, buyer initiated on upticks and seller initiated otherwise
+    pub fn trades(&self, n: usize) -> Vec<MarketEvent> {
+        let mut rng = SeededRng::new(self.seed);
+        let path = self.walk(&mut rng, n);
+        let mut prev = cents(self.start_price);
+
+        path.into_iter()
+            .enumerate()
+            .map(|(i, price)| {
+                let ts = self.ts(i);
+                let side = if price > prev { b'B' } else { b'A' };
+                prev = price;
+                MarketEvent::Trade(TradeMsg {
+                    hd: RecordHeader::new::<TradeMsg>(RType::Mbp0.into(), 0, 1, ts),
+                    price: fixed(price),
+                    size: 1 + rng.below(100) as u32,
+                    action: b'T' as _,
+                    side: side as _,
+                    ts_recv: ts,
+                    ..TradeMsg::default()
+                })
+            })
+            .collect()
+    }
+
+    /// `n` steps of a merged options stream, the layout of
+    /// [`InkBackSchema::CombinedOptionsUnderlying`], each an underlying quote followed by
+    /// a trade in one of the calls and puts struck around the start price.
+    ///
+    /// Options expire `days_to_expiry` days after the start and are priced with
+    /// Black-Scholes at a 20% volatility.
+    ///
+    /// [`InkBackSchema::CombinedOptionsUnderlying`]: crate::InkBackSchema::CombinedOptionsUnderlying
+    pub fn merged_options(&self, n: usize, days_to_expiry: u64) -> Vec<MarketEvent> {
+        let mut rng = SeededRng::new(self.seed);
+        let path = self.walk(&mut rng, n);
+        let expiration = self.start_ts + days_to_expiry * NANOS_PER_DAY;
+        let atm = self.start_price.round();
+        let strikes = [atm * 0.95, atm, atm * 1.05].map(|k| k.round());
+        let instrument = |is_call: bool, k: usize| 1000 + 2 * k as u32 + is_call as u32;
+
+        let mut events = Vec::with_capacity(2 * n);
+        for (i, spot) in path.into_iter().enumerate() {
+            let ts = self.ts(i);
+            let (bid, ask) = (cents(spot - 0.01), cents(spot + 0.01));
+            let (bid_sz, ask_sz) = (1 + rng.below(500) as u32, 1 + rng.below(500) as u32);
+            events.push(MarketEvent::Mbp1(Mbp1Msg {
+                hd: RecordHeader::new::<Mbp1Msg>(RType::Mbp1.into(), 0, 1, ts),
+                price: fixed(spot),
+                size: 1 + rng.below(100) as u32,
+                ts_recv: ts,
+                levels: [BidAskPair {
+                    bid_px: fixed(bid),
+                    ask_px: fixed(ask),
+                    bid_sz,
+                    ask_sz,
+                    bid_ct: 1,
+                    ask_ct: 1,
+                }],
+                ..Mbp1Msg::default()
+            }));
+
+            if ts >= expiration {
+                continue;
+            }
+            let k = rng.below(strikes.len());
+            let is_call = rng.below(2) == 1;
+            let t = greeks::years(expiration - ts);
+            let price = cents(greeks::price(is_call, spot, strikes[k], t, 0.0, 0.2)).max(0.01);
+            let option_type = if is_call { "C" } else { "P" };
+            events.push(MarketEvent::OptionTrade(OptionTradeMsg {
+                ts_event: ts,
+                price,
+                size: 1 + rng.below(20) as u64,
+                instrument_id: instrument(is_call, k),
+                symbol: format!("SYN {}{:08}", option_type, (strikes[k] * 1000.0) as u64),
+                strike_price: strikes[k],
+                expiration,
+                option_type: option_type.to_string(),
+                underlying_bid: bid,
+                underlying_ask: ask,
+                underlying_price: spot,
+                underlying_bid_sz: bid_sz,
+                underlying_ask_sz: ask_sz,
+                open_interest: None,
+                underlying_ts: Some(ts),
+                underlying_stale: false,
+            }));
+        }
+        events
+    }
+}
+
+/// Turn generated events into a stream for [`run_backtest_on_stream`]
+///
+/// [`run_backtest_on_stream`]: crate::backtester::run_backtest_on_stream
+pub fn stream(events: Vec<MarketEvent>) -> MarketStream {
+    Box::pin(stream::iter(events.into_iter().map(Ok)))
+}
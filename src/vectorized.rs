@@ -0,0 +1,387 @@
+use crate::event::MarketEvent;
+use crate::slippage_models::TransactionCosts;
+use crate::strategy::StrategyParams;
+use crate::utils::fetch::{self, MarketStream};
+use anyhow::Result;
+use databento::dbn::Schema;
+use futures::StreamExt;
+use rayon::prelude::*;
+
+// Bars handled per step of the elementwise kernels, wide enough for the compiler to
+// keep every AVX / NEON register busy
+const LANES: usize = 8;
+
+/// Columns of bar data the vectorized path works on, one entry per bar
+#[derive(Debug, Clone, Default)]
+pub struct Bars {
+    pub ts: Vec<u64>,
+    pub open: Vec<f64>,
+    pub high: Vec<f64>,
+    pub low: Vec<f64>,
+    pub close: Vec<f64>,
+    pub volume: Vec<f64>,
+}
+
+impl Bars {
+    /// One bar per event. OHLCV records keep their bar, any other event becomes a bar
+    /// whose four prices are its price. Definitions are skipped.
+    pub fn from_events(events: &[MarketEvent]) -> Self {
+        let mut bars = Self::default();
+        for event in events {
+            bars.push(event);
+        }
+        bars
+    }
+
+    /// Drain a stream into bars, see [`Bars::from_events`]
+    pub async fn from_stream(mut stream: MarketStream) -> Result<Self> {
+        let mut bars = Self::default();
+        while let Some(event) = stream.next().await {
+            bars.push(&event?);
+        }
+        Ok(bars)
+    }
+
+    /// Load a data file the way [`fetch::get_data_stream`] reads it
+    pub async fn load(path: &str, schema: Schema) -> Result<Self> {
+        Self::from_stream(fetch::get_data_stream(path, schema).await?).await
+    }
+
+    fn push(&mut self, event: &MarketEvent) {
+        let open = match event {
+            MarketEvent::Definition(_) => return,
+            MarketEvent::Ohlcv(m) => m.open as f64 * 1e-9,
+            _ => event.price(),
+        };
+        self.ts.push(event.timestamp());
+        self.open.push(open);
+        self.high.push(event.high());
+        self.low.push(event.low());
+        self.close.push(event.price());
+        self.volume.push(event.volume() as f64);
+    }
+
+    /// Aggregate into bars of `interval` nanoseconds, each stamped at its first event, so
+    /// tick data can be scanned at a coarse resolution
+    pub fn resample(&self, interval: u64) -> Self {
+        let mut bars = Self::default();
+        let mut bucket = None;
+        for i in 0..self.len() {
+            let b = self.ts[i] / interval.max(1);
+            if bucket == Some(b) {
+                let last = bars.len() - 1;
+                bars.high[last] = bars.high[last].max(self.high[i]);
+                bars.low[last] = bars.low[last].min(self.low[i]);
+                bars.close[last] = self.close[i];
+                bars.volume[last] += self.volume[i];
+                continue;
+            }
+            bucket = Some(b);
+            bars.ts.push(self.ts[i]);
+            bars.open.push(self.open[i]);
+            bars.high.push(self.high[i]);
+            bars.low.push(self.low[i]);
+            bars.close.push(self.close[i]);
+            bars.volume.push(self.volume[i]);
+        }
+        bars
+    }
+
+    pub fn len(&self) -> usize {
+        self.close.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.close.is_empty()
+    }
+}
+
+/// Simple moving average, NaN until `window` values are in
+pub fn sma(values: &[f64], window: usize) -> Vec<f64> {
+    let mut out = vec![f64::NAN; values.len()];
+    if window == 0 {
+        return out;
+    }
+    let mut sum = 0.0;
+    for i in 0..values.len() {
+        sum += values[i];
+        if i >= window {
+            sum -= values[i - window];
+        }
+        if i + 1 >= window {
+            out[i] = sum / window as f64;
+        }
+    }
+    out
+}
+
+/// Exponential moving average with smoothing `2 / (window + 1)`, seeded with the first value
+pub fn ema(values: &[f64], window: usize) -> Vec<f64> {
+    let alpha = 2.0 / (window as f64 + 1.0);
+    let mut out = Vec::with_capacity(values.len());
+    let mut last = values.first().copied().unwrap_or(f64::NAN);
+    for &v in values {
+        last += alpha * (v - last);
+        out.push(last);
+    }
+    out
+}
+
+/// Outcome of one vectorized run
+#[derive(Debug, Clone)]
+pub struct VectorResult {
+    pub label: String,
+    pub params: StrategyParams,
+    pub ending_equity: f64,
+    pub total_return_pct: f64,
+    pub max_drawdown_pct: f64,
+    /// Mean over standard deviation of the per-trade returns, as in [`BacktestResult`]
+    ///
+    /// [`BacktestResult`]: crate::backtester::BacktestResult
+    pub sharpe_ratio: f64,
+    pub total_trades: usize,
+    pub win_rate: f64,
+    /// Equity after every bar, empty for the runs of a [`VectorBacktest::scan`]
+    pub equity_curve: Vec<f64>,
+}
+
+/// Fully vectorized backtest of target positions over fixed bars, for scanning
+/// thousands of parameter sets before refining the best with the event-driven engine.
+///
+/// A position written for bar `i` is taken at that bar's close and held over the next
+/// bar, paying the one-way cost of the change in position. There are no order types,
+/// lots, limits or margin: only the position series, compounded at `exposure` of equity.
+pub struct VectorBacktest {
+    bars: Bars,
+    // Close-to-close return of each bar, zero for the first
+    returns: Vec<f64>,
+    // One-way cost of trading a full position at each bar, as a fraction of its notional
+    costs: Vec<f64>,
+    pub(crate) transaction_costs: TransactionCosts,
+    pub(crate) starting_equity: f64,
+    pub(crate) exposure: f64,
+}
+
+impl VectorBacktest {
+    /// The costs, fill price impact included, are evaluated once per bar for the notional
+    /// of a full position at `starting_equity`, so fixed and size-dependent charges are
+    /// approximated at that size.
+    pub fn new(
+        bars: Bars,
+        transaction_costs: &TransactionCosts,
+        starting_equity: f64,
+        exposure: f64,
+    ) -> Self {
+        let returns = std::iter::once(0.0)
+            .chain(
+                bars.close
+                    .windows(2)
+                    .map(|w| if w[0] != 0.0 { w[1] / w[0] - 1.0 } else { 0.0 }),
+            )
+            .take(bars.len())
+            .collect();
+        let notional = starting_equity * exposure;
+        let costs = bars
+            .close
+            .iter()
+            .zip(&bars.volume)
+            .map(|(&price, &volume)| {
+                if price <= 0.0 || notional <= 0.0 {
+                    return 0.0;
+                }
+                let size = notional / price;
+                let impact = (transaction_costs.adjust_fill_price(price, size, true) - price).abs();
+                (impact * size + transaction_costs.calculate_entry_cost(price, size, volume))
+                    / notional
+            })
+            .collect();
+        Self {
+            bars,
+            returns,
+            costs,
+            transaction_costs: transaction_costs.clone(),
+            starting_equity,
+            exposure,
+        }
+    }
+
+    pub fn bars(&self) -> &Bars {
+        &self.bars
+    }
+
+    /// Run one series of target positions, one per bar in `[-1, 1]` (1 = fully long).
+    /// NaN positions count as flat.
+    pub fn run(&self, positions: &[f64]) -> Result<VectorResult> {
+        if positions.len() != self.bars.len() {
+            return Err(anyhow::anyhow!(
+                "{} positions for {} bars",
+                positions.len(),
+                self.bars.len()
+            ));
+        }
+        Ok(self.simulate(positions, true))
+    }
+
+    /// Run every parameter set in parallel, `signal` writing each one's positions for the
+    /// bars, ranked by Sharpe ratio like [`run_parallel_backtest`] and labelled the same way.
+    ///
+    /// [`run_parallel_backtest`]: crate::backtester::run_parallel_backtest
+    pub fn scan<F>(
+        &self,
+        parameter_combinations: &[StrategyParams],
+        signal: &F,
+    ) -> Vec<VectorResult>
+    where
+        F: Fn(&Bars, &StrategyParams, &mut [f64]) + Sync,
+    {
+        let n = self.bars.len();
+        let mut results: Vec<VectorResult> = parameter_combinations
+            .par_iter()
+            .enumerate()
+            .map_init(
+                || vec![0.0; n],
+                |positions, (index, params)| {
+                    positions.fill(0.0);
+                    signal(&self.bars, params, positions);
+                    let mut result = self.simulate(positions, false);
+                    result.label = format!(
+                        "Strategy_{} [{}]",
+                        index + 1,
+                        params.to_string_representation()
+                    );
+                    result.params = params.clone();
+                    result
+                },
+            )
+            .collect();
+        results.sort_by(|a, b| {
+            b.sharpe_ratio
+                .partial_cmp(&a.sharpe_ratio)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        results
+    }
+
+    // Compound the per-bar returns into equity, drawdown and per-trade returns, a block
+    // of bars at a time
+    fn simulate(&self, positions: &[f64], keep_curve: bool) -> VectorResult {
+        let mut equity = self.starting_equity;
+        let mut peak = equity;
+        let mut max_dd_pct: f64 = 0.0;
+        let mut curve = Vec::with_capacity(if keep_curve { positions.len() } else { 0 });
+        let mut trade_returns = Vec::new();
+        // Growth of the position held since the last change, `None` while flat
+        let mut open: Option<f64> = None;
+        let mut held = 0.0;
+
+        let mut block = [0.0; LANES];
+        for start in (0..positions.len()).step_by(LANES) {
+            let len = bar_returns(positions, &self.returns, &self.costs, start, &mut block);
+            for (j, i) in (start..start + len).enumerate() {
+                equity *= 1.0 + self.exposure * block[j];
+                peak = peak.max(equity);
+                if peak > 0.0 {
+                    max_dd_pct = max_dd_pct.max((peak - equity) / peak * 100.0);
+                }
+                if keep_curve {
+                    curve.push(equity);
+                }
+
+                if let Some(growth) = open.as_mut() {
+                    *growth *= 1.0 + held * self.returns[i];
+                }
+                let position = flat_if_nan(positions[i]);
+                if position != held {
+                    if let Some(growth) = open.take() {
+                        trade_returns.push(growth - 1.0 - self.costs[i] * held.abs());
+                    }
+                    if position != 0.0 {
+                        open = Some(1.0 - self.costs[i] * position.abs());
+                    }
+                    held = position;
+                }
+            }
+        }
+        if let Some(growth) = open {
+            trade_returns.push(growth - 1.0);
+        }
+
+        let trades = trade_returns.len();
+        let sharpe_ratio = if trades >= 2 {
+            let mean = trade_returns.iter().sum::<f64>() / trades as f64;
+            let variance = trade_returns
+                .iter()
+                .map(|r| (r - mean).powi(2))
+                .sum::<f64>()
+                / trades as f64;
+            if variance > 0.0 {
+                mean / variance.sqrt()
+            } else {
+                0.0
+            }
+        } else {
+            0.0
+        };
+        let wins = trade_returns.iter().filter(|&&r| r > 0.0).count();
+
+        VectorResult {
+            label: String::new(),
+            params: StrategyParams::new(),
+            ending_equity: equity,
+            total_return_pct: (equity / self.starting_equity - 1.0) * 100.0,
+            max_drawdown_pct: max_dd_pct,
+            sharpe_ratio,
+            total_trades: trades,
+            win_rate: if trades == 0 {
+                0.0
+            } else {
+                wins as f64 / trades as f64 * 100.0
+            },
+            equity_curve: curve,
+        }
+    }
+}
+
+fn flat_if_nan(position: f64) -> f64 {
+    if position.is_nan() {
+        0.0
+    } else {
+        position
+    }
+}
+
+// Returns of the up to LANES bars from `start`: position[i-1] * return[i] minus
+// |position[i] - position[i-1]| * cost[i]. Full blocks use fixed-size slices so the loop
+// compiles to SIMD.
+fn bar_returns(
+    positions: &[f64],
+    returns: &[f64],
+    costs: &[f64],
+    start: usize,
+    out: &mut [f64; LANES],
+) -> usize {
+    let end = (start + LANES).min(positions.len());
+    if start > 0 && end - start == LANES {
+        let (p, c, r, k) = (
+            &positions[start - 1..end - 1],
+            &positions[start..end],
+            &returns[start..end],
+            &costs[start..end],
+        );
+        for j in 0..LANES {
+            let held = flat_if_nan(p[j]);
+            out[j] = held * r[j] - (flat_if_nan(c[j]) - held).abs() * k[j];
+        }
+    } else {
+        for i in start..end {
+            let held = if i == 0 {
+                0.0
+            } else {
+                flat_if_nan(positions[i - 1])
+            };
+            out[i - start] =
+                held * returns[i] - (flat_if_nan(positions[i]) - held).abs() * costs[i];
+        }
+    }
+    end - start
+}
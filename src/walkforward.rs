@@ -1,5 +1,5 @@
 use crate::backtester::{run_backtest, run_parallel_backtest_internal, BacktestResult, Trade};
-use crate::plot::plot_equity_curves;
+use crate::plot::{plot_equity_curves, PlotConfig};
 use crate::slippage_models::TransactionCosts;
 use crate::strategy::{Strategy, StrategyParams};
 use crate::utils::fetch::BacktestManager;
@@ -48,6 +48,7 @@ pub struct WalkForwardSummary {
 ///   2. Run the best IS params on the OOS period with equity carried forward.
 ///
 /// Returns a [`WalkForwardSummary`] with per-window detail and a chained OOS equity curve.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_walk_forward<F>(
     config: WalkForwardConfig,
     parameter_combinations: Vec<StrategyParams>,
@@ -108,6 +109,7 @@ where
             exposure,
             &transaction_costs,
             Some((window_start, is_end)),
+            None,
         );
 
         if is_results.is_empty() {
@@ -199,8 +201,8 @@ pub fn display_walk_forward_results(summary: &WalkForwardSummary) {
 
     for w in &summary.windows {
         // Truncate param label to fit
-        let label = if w.best_params.len() > 10 {
-            format!("{}…", &w.best_params[..9])
+        let label = if w.best_params.chars().count() > 10 {
+            format!("{}…", w.best_params.chars().take(9).collect::<String>())
         } else {
             w.best_params.clone()
         };
@@ -248,5 +250,5 @@ pub fn plot_walk_forward(summary: &WalkForwardSummary) {
         summary.combined_oos_equity.clone(),
     ));
 
-    plot_equity_curves(curves, None);
+    plot_equity_curves(curves, None, PlotConfig::default());
 }
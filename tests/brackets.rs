@@ -0,0 +1,102 @@
+//! Volatility brackets and time stops the engine attaches to entries.
+
+use anyhow::Result;
+use inkback::event::MarketEvent;
+use inkback::strategy::{Bracket, BracketDistance, ExitReason, Order, OrderType, Strategy};
+use inkback::testing::{ExpectedTrade, MockStream};
+use std::collections::HashMap;
+
+/// Places the scripted order of each event index
+struct Scripted {
+    orders: HashMap<usize, Order>,
+    seen: usize,
+}
+
+impl Scripted {
+    fn new(orders: &[(usize, Order)]) -> Self {
+        Self {
+            orders: orders.iter().copied().collect(),
+            seen: 0,
+        }
+    }
+}
+
+impl Strategy for Scripted {
+    fn on_event(&mut self, _event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let index = self.seen;
+        self.seen += 1;
+        self.orders.get(&index).copied()
+    }
+}
+
+// Bars two points wide around 100, so the two-bar ATR is 2 when the entry fills on the
+// fourth: the stop rests at 98 and the target at 104
+fn atr_entry() -> Scripted {
+    let bracket = Bracket::new()
+        .with_stop(BracketDistance::atr(1.0, 2))
+        .with_target(BracketDistance::atr(2.0, 2));
+    Scripted::new(&[(
+        2,
+        Order::new(OrderType::MarketBuy, 100.0)
+            .with_quantity(10.0)
+            .with_bracket(bracket),
+    )])
+}
+
+fn two_point_bars() -> MockStream {
+    (0..4).fold(MockStream::new(), |mock, _| {
+        mock.bar(100.0, 101.0, 99.0, 100.0, 1000)
+    })
+}
+
+#[tokio::test]
+async fn an_atr_stop_closes_at_its_level() -> Result<()> {
+    let run = two_point_bars()
+        .bar(100.0, 100.5, 97.0, 97.5, 1000)
+        .closes(&[95.0])
+        .run(&mut atr_entry())
+        .await?;
+
+    run.assert_trades(&[ExpectedTrade::long()
+        .entry(100.0)
+        .exit(98.0)
+        .size(10.0)
+        .pnl(-20.0)
+        .reason(ExitReason::Stop)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn an_atr_target_closes_at_its_level() -> Result<()> {
+    let run = two_point_bars()
+        .bar(100.0, 105.0, 100.0, 104.5, 1000)
+        .closes(&[106.0])
+        .run(&mut atr_entry())
+        .await?;
+
+    run.assert_trades(&[ExpectedTrade::long()
+        .entry(100.0)
+        .exit(104.0)
+        .pnl(40.0)
+        .reason(ExitReason::Target)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_max_holding_time_closes_at_the_deadline() -> Result<()> {
+    let order = Order::new(OrderType::MarketBuy, 100.0)
+        .with_quantity(10.0)
+        .with_max_holding(time::Duration::minutes(2));
+    // Filled at 101 on the second bar, so the deadline falls on the fourth
+    let run = MockStream::new()
+        .closes(&[100.0, 101.0, 102.0, 103.0, 104.0])
+        .run(&mut Scripted::new(&[(0, order)]))
+        .await?;
+
+    run.assert_trades(&[ExpectedTrade::long()
+        .entry(101.0)
+        .exit(103.0)
+        .size(10.0)
+        .reason(ExitReason::TimeStop)]);
+    Ok(())
+}
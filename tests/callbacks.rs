@@ -0,0 +1,83 @@
+//! Position lifecycle callbacks of the strategy, on hand-written events.
+
+use anyhow::Result;
+use inkback::event::MarketEvent;
+use inkback::strategy::{ExitReason, Order, OrderType, Strategy};
+use inkback::testing::{ExpectedTrade, MockStream};
+use std::collections::HashMap;
+
+/// Places the scripted orders at the price of the event of each index and records every
+/// position close it is told about
+struct Scripted {
+    orders: HashMap<usize, (OrderType, Option<f64>)>,
+    seen: usize,
+    closed: Vec<(usize, ExitReason)>,
+}
+
+impl Scripted {
+    fn new(orders: &[(usize, OrderType, Option<f64>)]) -> Self {
+        Self {
+            orders: orders.iter().map(|&(i, t, q)| (i, (t, q))).collect(),
+            seen: 0,
+            closed: Vec::new(),
+        }
+    }
+}
+
+impl Strategy for Scripted {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let index = self.seen;
+        self.seen += 1;
+        self.orders.get(&index).map(|&(order_type, quantity)| {
+            let order = Order::new(order_type, event.price());
+            match quantity {
+                Some(q) => order.with_quantity(q),
+                None => order,
+            }
+        })
+    }
+
+    fn on_position_closed(&mut self, reason: ExitReason) {
+        // The callback follows the event whose order closed the position
+        self.closed.push((self.seen - 1, reason));
+    }
+}
+
+#[tokio::test]
+async fn a_strategy_market_exit_reports_the_closed_position() -> Result<()> {
+    let mut strategy = Scripted::new(&[
+        (0, OrderType::MarketBuy, None),
+        (2, OrderType::MarketSell, None),
+    ]);
+    let run = MockStream::new()
+        .closes(&[100.0, 101.0, 103.0, 102.0])
+        .run(&mut strategy)
+        .await?;
+
+    run.assert_trades(&[ExpectedTrade::long()
+        .entry(101.0)
+        .exit(103.0)
+        .reason(ExitReason::Strategy)]);
+    assert_eq!(strategy.closed, vec![(2, ExitReason::Strategy)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_partial_market_exit_leaves_the_position_open() -> Result<()> {
+    let mut strategy = Scripted::new(&[
+        (0, OrderType::MarketBuy, Some(10.0)),
+        (2, OrderType::MarketSell, Some(4.0)),
+        (3, OrderType::MarketSell, Some(6.0)),
+    ]);
+    let run = MockStream::new()
+        .closes(&[100.0, 101.0, 103.0, 104.0, 102.0])
+        .run(&mut strategy)
+        .await?;
+
+    run.assert_trades(&[
+        ExpectedTrade::long().entry(101.0).exit(103.0).size(4.0),
+        ExpectedTrade::long().entry(101.0).exit(104.0).size(6.0),
+    ]);
+    assert_eq!(strategy.closed, vec![(3, ExitReason::Strategy)]);
+    Ok(())
+}
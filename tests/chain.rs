@@ -0,0 +1,92 @@
+//! Time ranges and gap checks of chained data files.
+
+use anyhow::Result;
+use databento::dbn::encode::{dbn::Encoder, EncodeRecord};
+use databento::dbn::{Metadata, SType, Schema, TradeMsg};
+use inkback::time_of_day::{ExchangeClock, TradingCalendar};
+use inkback::utils::chain::{scan_files, scan_files_on, scanned_files};
+use std::io::Write;
+use std::num::NonZeroU64;
+use std::path::PathBuf;
+use time::macros::{date, datetime};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("inkback_chain_{}_{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn ns(dt: time::OffsetDateTime) -> u64 {
+    dt.unix_timestamp_nanos() as u64
+}
+
+// An empty trades file whose metadata records the query range `[start, end)`
+fn write_dbn(path: &PathBuf, start: u64, end: u64) {
+    let metadata = Metadata::builder()
+        .dataset("GLBX.MDP3".to_string())
+        .schema(Some(Schema::Trades))
+        .start(start)
+        .end(NonZeroU64::new(end))
+        .stype_in(Some(SType::RawSymbol))
+        .stype_out(SType::InstrumentId)
+        .build();
+    let file = std::fs::File::create(path).unwrap();
+    let mut encoder = Encoder::with_zstd(file, &metadata).unwrap();
+    let mut trade = TradeMsg::default();
+    trade.hd.ts_event = start;
+    encoder.encode_record(&trade).unwrap();
+}
+
+fn csv_rows(timestamps: &[u64]) -> String {
+    let mut csv = "ts_event,open,high,low,close,volume\n".to_string();
+    for ts in timestamps {
+        csv.push_str(&format!("{},1,1,1,1,1\n", ts));
+    }
+    csv
+}
+
+#[tokio::test]
+async fn csv_ranges_come_from_their_first_and_last_rows() -> Result<()> {
+    let dir = temp_dir("csv");
+    std::fs::write(dir.join("a.csv"), csv_rows(&[100, 150, 200]))?;
+    let mut encoder = zstd::Encoder::new(std::fs::File::create(dir.join("b.csv.zst"))?, 3)?;
+    encoder.write_all(csv_rows(&[300, 350, 400]).as_bytes())?;
+    encoder.finish()?;
+
+    let files = scan_files(dir.to_str().unwrap()).await?;
+    let ranges: Vec<(u64, u64, bool)> = files.iter().map(|f| (f.start, f.end, f.exact)).collect();
+    assert_eq!(ranges, vec![(100, 201, false), (300, 401, false)]);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn holiday_gaps_are_accepted_on_the_exchange_calendar() -> Result<()> {
+    let dir = temp_dir("holiday");
+    let path = dir.to_str().unwrap().to_string();
+    // Friday then Tuesday, with Monday 2024-01-15 a CME holiday
+    write_dbn(
+        &dir.join("a.dbn.zst"),
+        ns(datetime!(2024-01-12 00:00 UTC)),
+        ns(datetime!(2024-01-13 00:00 UTC)),
+    );
+    write_dbn(
+        &dir.join("b.dbn.zst"),
+        ns(datetime!(2024-01-16 00:00 UTC)),
+        ns(datetime!(2024-01-17 00:00 UTC)),
+    );
+
+    let err = scan_files(&path).await.unwrap_err();
+    assert!(err.to_string().starts_with("Gap between"), "{}", err);
+
+    let calendar = TradingCalendar::new(ExchangeClock::utc()).with_holiday(date!(2024 - 01 - 15));
+    let files = scan_files_on(&path, &calendar).await?;
+    assert_eq!(files.len(), 2);
+    // The chained stream reuses the accepted scan rather than failing on the gap
+    assert_eq!(scanned_files(&path).await?.len(), 2);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use databento::dbn::{OhlcvMsg, Schema};
+use inkback::symbology::SymbologyMap;
+use inkback::tools::continuous::{adjust_continuous, AdjustmentMethod};
+
+fn bar(instrument_id: u32, ts_event: u64, close: f64) -> OhlcvMsg {
+    let mut bar = OhlcvMsg::default_for_schema(Schema::Ohlcv1D);
+    bar.hd.instrument_id = instrument_id;
+    bar.hd.ts_event = ts_event;
+    let px = (close * 1e9) as i64;
+    bar.open = px;
+    bar.high = px;
+    bar.low = px;
+    bar.close = px;
+    bar
+}
+
+fn closes(bars: &[OhlcvMsg], outgoing: &[OhlcvMsg], method: AdjustmentMethod) -> Vec<f64> {
+    adjust_continuous(bars, outgoing, &SymbologyMap::new(), method)
+        .iter()
+        .map(|b| (b.close * 1e6).round() / 1e6)
+        .collect()
+}
+
+#[test]
+fn roll_gap_is_measured_on_the_switch_bar() {
+    // The outgoing contract moves from 101 to 103 on the switch bar, which is market
+    // movement rather than roll spread
+    let bars = [bar(1, 1, 100.0), bar(1, 2, 101.0), bar(2, 3, 106.0)];
+    let outgoing = [bar(1, 3, 103.0)];
+
+    assert_eq!(
+        closes(&bars, &outgoing, AdjustmentMethod::BackAdjusted),
+        vec![103.0, 104.0, 106.0]
+    );
+    let ratio = closes(&bars, &outgoing, AdjustmentMethod::RatioAdjusted);
+    assert!((ratio[1] - 101.0 * 106.0 / 103.0).abs() < 1e-6);
+    assert_eq!(ratio[2], 106.0);
+}
+
+#[test]
+fn roll_gap_falls_back_to_the_last_outgoing_close() {
+    let bars = [bar(1, 1, 100.0), bar(1, 2, 101.0), bar(2, 3, 106.0)];
+    // A bar of the outgoing contract at another timestamp is not the switch bar
+    let outgoing = [bar(1, 4, 103.0)];
+
+    assert_eq!(
+        closes(&bars, &outgoing, AdjustmentMethod::BackAdjusted),
+        vec![105.0, 106.0, 106.0]
+    );
+    assert_eq!(
+        closes(&bars, &[], AdjustmentMethod::None),
+        vec![100.0, 101.0, 106.0]
+    );
+}
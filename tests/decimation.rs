@@ -0,0 +1,158 @@
+//! Decimated equity curves: the kept curve thins, the headline metrics and the indicator
+//! alignment do not change
+
+use anyhow::Result;
+use inkback::backtester::{BacktestOptions, BacktestResult, CurveDecimation};
+use inkback::context::EventCtx;
+use inkback::event::MarketEvent;
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{Order, OrderType, Strategy};
+use inkback::testing::MockStream;
+use inkback::utils::synthetic::SyntheticMarket;
+use std::collections::VecDeque;
+
+const EVENTS: usize = 400;
+
+/// Fast/slow moving average cross, recording how many events it has seen
+#[derive(Default)]
+struct MaCross {
+    closes: VecDeque<f64>,
+    long: Option<bool>,
+    seen: usize,
+}
+
+impl Strategy for MaCross {
+    fn on_event(&mut self, _event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        None
+    }
+
+    fn on_event_ctx(
+        &mut self,
+        event: &MarketEvent,
+        _prev: Option<&MarketEvent>,
+        ctx: &EventCtx,
+    ) -> Option<Order> {
+        self.seen += 1;
+        ctx.record("seen", self.seen as f64);
+        let price = event.price();
+        self.closes.push_back(price);
+        if self.closes.len() > 20 {
+            self.closes.pop_front();
+        }
+        if self.closes.len() < 20 {
+            return None;
+        }
+        let fast = self.closes.iter().rev().take(5).sum::<f64>() / 5.0;
+        let slow = self.closes.iter().sum::<f64>() / 20.0;
+        let want_long = fast > slow;
+        match self.long {
+            Some(long) if long == want_long => None,
+            Some(long) => {
+                self.long = None;
+                let exit = if long {
+                    OrderType::MarketSell
+                } else {
+                    OrderType::MarketBuy
+                };
+                Some(Order::new(exit, price))
+            }
+            None => {
+                self.long = Some(want_long);
+                let entry = if want_long {
+                    OrderType::MarketBuy
+                } else {
+                    OrderType::MarketSell
+                };
+                Some(Order::new(entry, price))
+            }
+        }
+    }
+}
+
+async fn run(decimation: Option<CurveDecimation>) -> Result<BacktestResult> {
+    let mut stream = MockStream::new();
+    for event in SyntheticMarket::new(21).ohlcv(EVENTS) {
+        stream = stream.event(event);
+    }
+    let run = stream
+        .run_with(
+            &mut MaCross::default(),
+            TransactionCosts::equity_trading(),
+            &BacktestOptions {
+                decimation,
+                ..BacktestOptions::default()
+            },
+        )
+        .await?;
+    Ok(run.result)
+}
+
+fn log_path(every: usize) -> String {
+    std::env::temp_dir()
+        .join(format!(
+            "inkback_{}_curve_{}.csv",
+            std::process::id(),
+            every
+        ))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[tokio::test]
+async fn metrics_do_not_depend_on_the_decimation() -> Result<()> {
+    let full = run(None).await?;
+    assert!(full.total_trades > 2, "the cross trades");
+    assert!(full.max_drawdown > 0.0);
+
+    for every in [7, 50] {
+        let path = log_path(every);
+        let thin = run(Some(CurveDecimation::new(every, &path))).await?;
+        let rows = csv::Reader::from_path(&path)?.records().count();
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(thin.total_trades, full.total_trades);
+        for (name, a, b) in [
+            ("ending equity", thin.ending_equity, full.ending_equity),
+            ("max drawdown", thin.max_drawdown, full.max_drawdown),
+            (
+                "max drawdown %",
+                thin.max_drawdown_pct,
+                full.max_drawdown_pct,
+            ),
+            ("sharpe", thin.sharpe_ratio, full.sharpe_ratio),
+            ("sortino", thin.sortino_ratio, full.sortino_ratio),
+            ("calmar", thin.calmar_ratio, full.calmar_ratio),
+        ] {
+            assert_eq!(a, b, "{} at every {} events", name, every);
+        }
+
+        // Point k is the full curve after event `every * k`, the last one after the last
+        let last = full.equity_curve.len() - 1;
+        assert_eq!(thin.equity_curve.len(), 1 + last.div_ceil(every));
+        assert_eq!(rows, thin.equity_curve.len() - 1);
+        for (k, &equity) in thin.equity_curve.iter().enumerate() {
+            assert_eq!(equity, full.equity_curve[(k * every).min(last)]);
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn indicators_line_up_with_the_kept_curve() -> Result<()> {
+    let full = run(None).await?;
+    let seen = &full.indicators[0];
+    assert_eq!(seen.points.len(), EVENTS);
+    assert!(seen.points.iter().all(|p| p.value == p.index as f64));
+
+    let every = 7;
+    let path = log_path(100 + every);
+    let thin = run(Some(CurveDecimation::new(every, &path))).await?;
+    std::fs::remove_file(&path)?;
+    let seen = &thin.indicators[0];
+    assert_eq!(seen.points.len(), thin.equity_curve.len() - 1);
+    for point in &seen.points {
+        // Each point keeps the value of the last event that landed in it
+        assert_eq!(point.value, (point.index * every).min(EVENTS) as f64);
+    }
+    Ok(())
+}
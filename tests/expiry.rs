@@ -0,0 +1,87 @@
+//! Expiration accessors of option trades and definitions, including DataBento's undefined
+//! price and timestamp sentinels.
+
+use databento::dbn::{InstrumentDefMsg, RType, RecordHeader, UNDEF_PRICE, UNDEF_TIMESTAMP};
+use inkback::event::MarketEvent;
+use inkback::utils::synthetic::SyntheticMarket;
+
+const NANOS_PER_DAY: u64 = 86_400 * 1_000_000_000;
+
+fn first_option_trade(market: &SyntheticMarket, days_to_expiry: u64) -> MarketEvent {
+    market
+        .merged_options(10, days_to_expiry)
+        .into_iter()
+        .find(|e| matches!(e, MarketEvent::OptionTrade(_)))
+        .expect("merged options contain option trades")
+}
+
+fn definition(expiration: u64, strike_price: i64) -> MarketEvent {
+    MarketEvent::Definition(Box::new(InstrumentDefMsg {
+        hd: RecordHeader::new::<InstrumentDefMsg>(RType::InstrumentDef.into(), 0, 1, 0),
+        expiration,
+        strike_price,
+        ..InstrumentDefMsg::default()
+    }))
+}
+
+#[test]
+fn option_trades_count_days_to_their_expiration() {
+    let market = SyntheticMarket::new(7);
+    let event = first_option_trade(&market, 30);
+    let start = market.start_ts;
+    let expiration = start + 30 * NANOS_PER_DAY;
+
+    assert_eq!(event.expiration(), Some(expiration));
+    let days = event.days_to_expiry(start).unwrap();
+    assert!((days - 30.0).abs() < 1e-9, "{} days", days);
+    let half_day = event
+        .days_to_expiry(expiration + NANOS_PER_DAY / 2)
+        .unwrap();
+    assert!((half_day + 0.5).abs() < 1e-9, "{} days", half_day);
+
+    assert!(!event.is_expired(expiration - 1));
+    assert!(event.is_expired(expiration));
+
+    let datetime = event.expiration_datetime().unwrap();
+    assert_eq!(datetime.unix_timestamp_nanos(), expiration as i128);
+}
+
+#[test]
+fn undefined_expirations_have_no_expiry() {
+    let market = SyntheticMarket::new(7);
+    for sentinel in [0, UNDEF_TIMESTAMP] {
+        let mut event = first_option_trade(&market, 30);
+        if let MarketEvent::OptionTrade(m) = &mut event {
+            m.expiration = sentinel;
+        }
+        assert_eq!(event.expiration(), None);
+        assert_eq!(event.expiration_datetime(), None);
+        assert_eq!(event.days_to_expiry(market.start_ts), None);
+        assert!(!event.is_expired(u64::MAX - 1));
+    }
+}
+
+#[test]
+fn definitions_read_expiration_and_skip_sentinels() {
+    let expiration = 1_735_000_000 * 1_000_000_000;
+    let listed = definition(expiration, 150_000_000_000);
+    assert_eq!(listed.expiration(), Some(expiration));
+    assert_eq!(listed.get("strike_price"), Some(150.0));
+    assert!(listed.is_expired(expiration));
+
+    let undefined = definition(UNDEF_TIMESTAMP, UNDEF_PRICE);
+    assert_eq!(undefined.expiration(), None);
+    assert_eq!(undefined.field("expiration"), None);
+    assert_eq!(undefined.get("strike_price"), None);
+    assert_eq!(undefined.days_to_expiry(0), None);
+    assert!(!undefined.is_expired(u64::MAX - 1));
+}
+
+#[test]
+fn other_events_have_no_expiration() {
+    let market = SyntheticMarket::new(7);
+    for event in market.ohlcv(1).into_iter().chain(market.trades(1)) {
+        assert_eq!(event.expiration(), None);
+        assert!(!event.is_expired(u64::MAX - 1));
+    }
+}
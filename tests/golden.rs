@@ -0,0 +1,430 @@
+//! Golden-file regression tests: reference strategies run over deterministic synthetic
+//! data, their results compared against the outputs checked in under `tests/golden/`.
+//!
+//! After an intended change to fills, costs or metrics, regenerate the files with
+//! `INKBACK_UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+
+use anyhow::Result;
+use databento::dbn::{InstrumentDefMsg, MboMsg, RType, RecordHeader};
+use inkback::backtester::{run_backtest_on_stream, BacktestOptions, BacktestResult, Trade};
+use inkback::event::{FootprintMsg, MarketEvent};
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{ExitReason, Order, OrderType, Strategy, TimeInForce};
+use inkback::utils::synthetic::{self, SyntheticMarket};
+use inkback::{InkBackSchema, UnderlyingQuotes};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// Long/short moving average cross, always in the market once both averages exist
+struct MaCross {
+    fast: usize,
+    slow: usize,
+    closes: VecDeque<f64>,
+    long: Option<bool>,
+}
+
+impl MaCross {
+    fn new(fast: usize, slow: usize) -> Self {
+        Self {
+            fast,
+            slow,
+            closes: VecDeque::new(),
+            long: None,
+        }
+    }
+
+    fn mean(&self, n: usize) -> f64 {
+        self.closes.iter().rev().take(n).sum::<f64>() / n as f64
+    }
+}
+
+impl Strategy for MaCross {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let price = event.price();
+        self.closes.push_back(price);
+        if self.closes.len() > self.slow {
+            self.closes.pop_front();
+        }
+        if self.closes.len() < self.slow {
+            return None;
+        }
+
+        let want_long = self.mean(self.fast) > self.mean(self.slow);
+        match self.long {
+            Some(long) if long == want_long => None,
+            Some(long) => {
+                self.long = None;
+                let exit = if long {
+                    OrderType::MarketSell
+                } else {
+                    OrderType::MarketBuy
+                };
+                Some(Order::new(exit, price))
+            }
+            None => {
+                self.long = Some(want_long);
+                let entry = if want_long {
+                    OrderType::MarketBuy
+                } else {
+                    OrderType::MarketSell
+                };
+                Some(Order::new(entry, price))
+            }
+        }
+    }
+}
+
+/// Bids an IOC limit below each print while flat, scales out in two halves once filled
+struct LimitScalper {
+    offset: f64,
+    events_held: usize,
+    working: Option<f64>,
+    held: Option<usize>,
+}
+
+impl Strategy for LimitScalper {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let price = event.price();
+        // The bid had this event to fill, at or below its limit
+        if let Some(limit) = self.working.take() {
+            if price <= limit {
+                self.held = Some(0);
+            }
+        }
+        match self.held {
+            None => {
+                let limit = price - self.offset;
+                self.working = Some(limit);
+                Some(
+                    Order::new(OrderType::LimitBuy, limit)
+                        .with_quantity(100.0)
+                        .with_time_in_force(TimeInForce::Ioc),
+                )
+            }
+            Some(n) if n == self.events_held / 2 => {
+                self.held = Some(n + 1);
+                Some(Order::new(OrderType::MarketSell, price).with_quantity(50.0))
+            }
+            Some(n) if n >= self.events_held => {
+                self.held = None;
+                Some(Order::new(OrderType::MarketSell, price))
+            }
+            Some(n) => {
+                self.held = Some(n + 1);
+                None
+            }
+        }
+    }
+}
+
+/// Buys a call through the merged options stream and holds it for a while, the last one
+/// through expiration
+struct CallBuyer {
+    hold: usize,
+    held: Option<usize>,
+    entries: usize,
+}
+
+impl Strategy for CallBuyer {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let MarketEvent::OptionTrade(m) = event else {
+            // Orders placed on an underlying quote fill at the next option trade
+            return match self.held {
+                None if self.entries < 3 => {
+                    self.entries += 1;
+                    self.held = Some(0);
+                    Some(Order::new(OrderType::MarketBuy, event.price()).with_quantity(5.0))
+                }
+                _ => None,
+            };
+        };
+        match self.held {
+            Some(n) if n >= self.hold && self.entries < 3 && m.option_type == "C" => {
+                self.held = None;
+                Some(Order::new(OrderType::MarketSell, m.price))
+            }
+            Some(n) => {
+                self.held = Some(n + 1);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn on_position_closed(&mut self, _reason: ExitReason) {
+        self.held = None;
+    }
+}
+
+fn options() -> BacktestOptions {
+    BacktestOptions {
+        paranoid: true,
+        audit: true,
+        ..BacktestOptions::default()
+    }
+}
+
+async fn run(
+    events: Vec<MarketEvent>,
+    strategy: &mut dyn Strategy,
+    costs: TransactionCosts,
+    custom_schema: Option<InkBackSchema>,
+) -> Result<BacktestResult> {
+    run_backtest_on_stream(
+        "SYN",
+        synthetic::stream(events),
+        strategy,
+        costs,
+        100_000.0,
+        1.0,
+        custom_schema,
+        None,
+        &options(),
+    )
+    .await
+}
+
+fn trade_json(trade: &Trade) -> Value {
+    json!({
+        "entry_ts": trade.entry_ts,
+        "exit_ts": trade.exit_ts,
+        "entry_price": trade.entry_price,
+        "exit_price": trade.exit_price,
+        "size": trade.size,
+        "pnl": trade.pnl,
+        "transaction_costs": trade.transaction_costs,
+        "exit_reason": format!("{:?}", trade.exit_reason),
+        "lot": trade.lot,
+    })
+}
+
+// The parts of a result an engine change can move
+fn summary(result: &BacktestResult) -> Value {
+    json!({
+        "ending_equity": result.ending_equity,
+        "total_return_pct": result.total_return_pct,
+        "max_drawdown_pct": result.max_drawdown_pct,
+        "win_rate": result.win_rate,
+        "profit_factor": result.profit_factor,
+        "sharpe_ratio": result.sharpe_ratio,
+        "sortino_ratio": result.sortino_ratio,
+        "total_trades": result.total_trades,
+        "total_transaction_costs": result.total_transaction_costs,
+        "equity_points": result.equity_curve.len(),
+        "audit_records": result.audit.len(),
+        "trades": result.trades.iter().map(trade_json).collect::<Vec<_>>(),
+    })
+}
+
+// Equal up to float noise, so a change of summation order alone does not fail the test
+fn matches(expected: &Value, actual: &Value, path: &str, diffs: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Number(e), Value::Number(a)) => {
+            let (e, a) = (e.as_f64().unwrap(), a.as_f64().unwrap());
+            if (e - a).abs() > 1e-9 * e.abs().max(1.0) {
+                diffs.push(format!("{}: expected {}, got {}", path, e, a));
+            }
+        }
+        (Value::Array(e), Value::Array(a)) => {
+            if e.len() != a.len() {
+                diffs.push(format!(
+                    "{}: expected {} items, got {}",
+                    path,
+                    e.len(),
+                    a.len()
+                ));
+            }
+            for (i, (e, a)) in e.iter().zip(a).enumerate() {
+                matches(e, a, &format!("{}[{}]", path, i), diffs);
+            }
+        }
+        (Value::Object(e), Value::Object(a)) => {
+            for (key, e) in e {
+                match a.get(key) {
+                    Some(a) => matches(e, a, &format!("{}.{}", path, key), diffs),
+                    None => diffs.push(format!("{}.{}: missing", path, key)),
+                }
+            }
+        }
+        (e, a) if e != a => diffs.push(format!("{}: expected {}, got {}", path, e, a)),
+        _ => {}
+    }
+}
+
+fn check_golden(name: &str, result: &BacktestResult) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.json", name));
+    let actual = summary(result);
+
+    if std::env::var_os("INKBACK_UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+        return;
+    }
+
+    let expected: Value = serde_json::from_str(
+        &std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e)),
+    )
+    .unwrap();
+    let mut diffs = Vec::new();
+    matches(&expected, &actual, name, &mut diffs);
+    assert!(
+        diffs.is_empty(),
+        "{} differs from {}:\n  {}",
+        name,
+        path.display(),
+        diffs.join("\n  ")
+    );
+}
+
+#[tokio::test]
+async fn ma_cross_on_ohlcv() -> Result<()> {
+    let events = SyntheticMarket::new(1).ohlcv(400);
+    let result = run(
+        events,
+        &mut MaCross::new(5, 20),
+        TransactionCosts::equity_trading(),
+        None,
+    )
+    .await?;
+    assert!(result.total_trades > 0);
+    check_golden("ma_cross_ohlcv", &result);
+    Ok(())
+}
+
+#[tokio::test]
+async fn limit_scalper_on_trades() -> Result<()> {
+    let events = SyntheticMarket::new(2).with_volatility(0.001).trades(2000);
+    let mut strategy = LimitScalper {
+        offset: 0.1,
+        events_held: 20,
+        working: None,
+        held: None,
+    };
+    let result = run(
+        events,
+        &mut strategy,
+        TransactionCosts::equity_trading(),
+        None,
+    )
+    .await?;
+    assert!(result.trades.iter().any(|t| t.size == 50.0));
+    check_golden("limit_scalper_trades", &result);
+    Ok(())
+}
+
+#[tokio::test]
+async fn call_buyer_on_merged_options() -> Result<()> {
+    let hour = 3_600_000_000_000;
+    let events = SyntheticMarket::new(3)
+        .with_start(SyntheticMarket::default().start_ts, hour)
+        .merged_options(150, 4);
+    let mut strategy = CallBuyer {
+        hold: 10,
+        held: None,
+        entries: 0,
+    };
+    let result = run(
+        events,
+        &mut strategy,
+        TransactionCosts::options_trading(),
+        Some(InkBackSchema::CombinedOptionsUnderlying {
+            underlying: UnderlyingQuotes::default(),
+        }),
+    )
+    .await?;
+    assert!(result.total_trades > 0);
+    check_golden("call_buyer_options", &result);
+    Ok(())
+}
+
+#[test]
+fn synthetic_data_is_deterministic() {
+    let market = SyntheticMarket::new(7);
+    let a = format!("{:?}", market.ohlcv(50));
+    assert_eq!(a, format!("{:?}", market.ohlcv(50)));
+    assert_ne!(a, format!("{:?}", SyntheticMarket::new(8).ohlcv(50)));
+
+    for event in market.ohlcv(50) {
+        assert!(event.low() <= event.price() && event.price() <= event.high());
+    }
+}
+
+/// Trades on every event it sees, alternating entries and exits
+#[derive(Default)]
+struct EveryEvent {
+    events: usize,
+    definitions: usize,
+    long: bool,
+}
+
+impl Strategy for EveryEvent {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        self.events += 1;
+        self.long = !self.long;
+        let order_type = if self.long {
+            OrderType::MarketBuy
+        } else {
+            OrderType::MarketSell
+        };
+        Some(Order::new(order_type, event.price()).with_quantity(1.0))
+    }
+
+    fn on_definition(
+        &mut self,
+        _def: &InstrumentDefMsg,
+        _symbology: &inkback::symbology::SymbologyMap,
+    ) {
+        self.definitions += 1;
+    }
+}
+
+#[tokio::test]
+async fn every_event_variant_runs_through_the_engine() -> Result<()> {
+    let market = SyntheticMarket::new(4);
+    let ts = market.start_ts;
+    let options = market.merged_options(1, 30);
+
+    let definition = InstrumentDefMsg {
+        hd: RecordHeader::new::<InstrumentDefMsg>(RType::InstrumentDef.into(), 0, 1, ts),
+        ..InstrumentDefMsg::default()
+    };
+    let mbo = MboMsg {
+        hd: RecordHeader::new::<MboMsg>(RType::Mbo.into(), 0, 1, ts),
+        price: 100_010_000_000,
+        size: 3,
+        action: b'F' as _,
+        side: b'B' as _,
+        ts_recv: ts,
+        ..MboMsg::default()
+    };
+    let footprint = FootprintMsg {
+        ts_event: ts,
+        price: 100.02,
+        volume: 10,
+        data: "{}".to_string(),
+    };
+
+    let mut events = vec![MarketEvent::Definition(Box::new(definition))];
+    events.extend(market.trades(1));
+    events.extend(options);
+    events.extend(market.ohlcv(1));
+    events.push(MarketEvent::Mbo(mbo));
+    events.push(MarketEvent::Footprint(footprint));
+    let variants: Vec<_> = events.iter().map(std::mem::discriminant).collect();
+    for (i, v) in variants.iter().enumerate() {
+        assert!(!variants[..i].contains(v), "duplicate event variant");
+    }
+    assert_eq!(variants.len(), 7);
+
+    let mut strategy = EveryEvent::default();
+    let result = run(events, &mut strategy, TransactionCosts::zero(), None).await?;
+    assert_eq!(strategy.definitions, 1);
+    assert_eq!(strategy.events, 6);
+    assert_eq!(result.equity_curve.len(), 7);
+    assert!(result.ending_equity.is_finite());
+    Ok(())
+}
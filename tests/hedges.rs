@@ -0,0 +1,130 @@
+//! Hedge legs charge the cost model they opened under, whichever model closes them
+
+use inkback::hedge::HedgeBook;
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::ExitReason;
+
+const DAY: u64 = 86_400_000_000_000;
+
+fn round_trip_costs(costs: &TransactionCosts, entry: f64, exit: f64, size: f64) -> f64 {
+    costs.calculate_entry_cost(entry, size, 1_000.0)
+        + costs.calculate_exit_cost(exit, size, 1_000.0)
+}
+
+#[test]
+fn a_strategy_close_charges_the_leg_costs() {
+    let futures = TransactionCosts::futures_trading(0.25);
+    let options = TransactionCosts::options_trading();
+    let mut book = HedgeBook::new();
+    book.fill(
+        7,
+        2.0,
+        100.0,
+        50.0,
+        &futures,
+        1_000.0,
+        0,
+        "2024-01-02",
+        ExitReason::Strategy,
+    );
+    let trade = book
+        .fill(
+            7,
+            -2.0,
+            101.0,
+            50.0,
+            &options,
+            1_000.0,
+            DAY,
+            "2024-01-03",
+            ExitReason::Strategy,
+        )
+        .expect("the sell closes the leg");
+
+    let expected = round_trip_costs(&futures, 100.0, 101.0, 2.0);
+    assert!((trade.transaction_costs - expected).abs() < 1e-9);
+    assert!((trade.gross_pnl - 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn liquidation_charges_the_leg_costs() {
+    let equity = TransactionCosts::equity_trading();
+    let futures = TransactionCosts::futures_trading(0.25);
+    let mut book = HedgeBook::new();
+    book.fill(
+        1,
+        -10.0,
+        50.0,
+        1.0,
+        &equity,
+        1_000.0,
+        0,
+        "2024-01-02",
+        ExitReason::Strategy,
+    );
+    book.fill(
+        2,
+        1.0,
+        4_000.0,
+        50.0,
+        &futures,
+        1_000.0,
+        0,
+        "2024-01-02",
+        ExitReason::Strategy,
+    );
+    book.mark(1, 49.0);
+    book.mark(2, 4_010.0);
+
+    let closes = book.liquidate(1_000.0, DAY, "2024-01-03");
+    assert_eq!(closes.len(), 2);
+    for (trade, costs) in closes.iter().zip([&equity, &futures]) {
+        assert_eq!(trade.exit_reason, ExitReason::EndOfData);
+        let expected = round_trip_costs(costs, trade.entry_price, trade.exit_price, trade.size);
+        assert!((trade.transaction_costs - expected).abs() < 1e-9);
+    }
+    assert_eq!(book.open_legs(), 0);
+}
+
+#[test]
+fn a_reopened_leg_takes_the_new_costs() {
+    let equity = TransactionCosts::equity_trading();
+    let futures = TransactionCosts::futures_trading(0.25);
+    let mut book = HedgeBook::new();
+    book.fill(
+        3,
+        1.0,
+        100.0,
+        1.0,
+        &equity,
+        1_000.0,
+        0,
+        "2024-01-02",
+        ExitReason::Strategy,
+    );
+    book.fill(
+        3,
+        -1.0,
+        100.0,
+        1.0,
+        &equity,
+        1_000.0,
+        DAY,
+        "2024-01-03",
+        ExitReason::Strategy,
+    );
+    book.fill(
+        3,
+        1.0,
+        100.0,
+        1.0,
+        &futures,
+        1_000.0,
+        2 * DAY,
+        "2024-01-04",
+        ExitReason::Strategy,
+    );
+    let trade = book.liquidate(1_000.0, 3 * DAY, "2024-01-05").remove(0);
+    let expected = round_trip_costs(&futures, 100.0, 100.0, 1.0);
+    assert!((trade.transaction_costs - expected).abs() < 1e-9);
+}
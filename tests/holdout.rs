@@ -0,0 +1,110 @@
+//! The holdout is used up by its run alone: failures before it leave it unevaluated
+
+use anyhow::{anyhow, Result};
+use databento::dbn::Schema;
+use inkback::backtester::BacktestOptions;
+use inkback::event::MarketEvent;
+use inkback::holdout::{evaluate_holdout, Holdout};
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{Order, OrderType, Strategy, StrategyParams};
+use inkback::utils::fetch::BacktestManager;
+use inkback::utils::synthetic::SyntheticMarket;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Buys on the first event and holds
+struct Hold {
+    bought: bool,
+}
+
+impl Strategy for Hold {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        if self.bought {
+            return None;
+        }
+        self.bought = true;
+        Some(Order::new(OrderType::MarketBuy, event.price()))
+    }
+}
+
+fn hold(_: &StrategyParams) -> Result<Box<dyn Strategy>> {
+    Ok(Box::new(Hold { bought: false }))
+}
+
+// An OHLCV CSV of synthetic bars and the manager over it, with its last fifth held out
+fn manager(name: &str) -> Result<(BacktestManager, PathBuf)> {
+    let bars = SyntheticMarket::new(9).ohlcv(200);
+    let path = std::env::temp_dir().join(format!("inkback_{}_{}.csv", std::process::id(), name));
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(file, "ts_event,open,high,low,close,volume")?;
+    for bar in &bars {
+        writeln!(
+            file,
+            "{},{},{},{},{},{}",
+            bar.timestamp(),
+            bar.field("open").and_then(|v| v.as_f64()).unwrap(),
+            bar.high(),
+            bar.low(),
+            bar.price(),
+            bar.volume()
+        )?;
+    }
+    let start = bars[0].timestamp();
+    let end = bars[bars.len() - 1].timestamp() + 1;
+    let manager = BacktestManager {
+        symbols: HashSet::from(["SYN".to_string()]),
+        schema: Schema::Ohlcv1M,
+        data_path: path.to_str().unwrap().to_string(),
+        holdout: Some(Holdout::last_fraction(start, end, 0.2)?),
+    };
+    Ok((manager, path))
+}
+
+async fn evaluate<F>(manager: &BacktestManager, constructor: F) -> Result<()>
+where
+    F: Fn(&StrategyParams) -> Result<Box<dyn Strategy>>,
+{
+    evaluate_holdout(
+        &StrategyParams::new(),
+        constructor,
+        manager,
+        "SYN",
+        Schema::Ohlcv1M,
+        None,
+        TransactionCosts::equity_trading(),
+        100_000.0,
+        1.0,
+        &BacktestOptions::default(),
+    )
+    .await
+    .map(|_| ())
+}
+
+#[tokio::test]
+async fn a_failing_strategy_constructor_leaves_the_holdout_unused() -> Result<()> {
+    let (manager, path) = manager("constructor")?;
+    let failing = |_: &StrategyParams| -> Result<Box<dyn Strategy>> { Err(anyhow!("bad params")) };
+    assert!(evaluate(&manager, failing).await.is_err());
+    assert!(!manager.holdout.as_ref().unwrap().is_evaluated());
+
+    evaluate(&manager, hold).await?;
+    assert!(manager.holdout.as_ref().unwrap().is_evaluated());
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_failing_in_sample_run_leaves_the_holdout_unused() -> Result<()> {
+    let (mut manager, path) = manager("in_sample")?;
+    let data_path = std::mem::replace(&mut manager.data_path, "/nonexistent/bars.csv".into());
+    assert!(evaluate(&manager, hold).await.is_err());
+    assert!(!manager.holdout.as_ref().unwrap().is_evaluated());
+
+    manager.data_path = data_path;
+    evaluate(&manager, hold).await?;
+    let again = evaluate(&manager.clone(), hold).await.unwrap_err();
+    assert!(again.to_string().contains("already evaluated"));
+    std::fs::remove_file(path)?;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Account ledger of a run reconciled against its trades, on hand-written events.
+
+use anyhow::Result;
+use inkback::backtester::BacktestOptions;
+use inkback::event::MarketEvent;
+use inkback::ledger::LedgerConfig;
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{Order, OrderType, Strategy};
+use inkback::testing::{MockStream, MOCK_EQUITY};
+use std::collections::HashMap;
+
+/// Places the scripted order of each event index
+struct Scripted {
+    orders: HashMap<usize, Order>,
+    seen: usize,
+}
+
+impl Scripted {
+    fn new(orders: &[(usize, Order)]) -> Self {
+        Self {
+            orders: orders.iter().copied().collect(),
+            seen: 0,
+        }
+    }
+}
+
+impl Strategy for Scripted {
+    fn on_event(&mut self, _event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let index = self.seen;
+        self.seen += 1;
+        self.orders.get(&index).copied()
+    }
+}
+
+fn close_to(expected: f64, actual: f64) -> bool {
+    (expected - actual).abs() < 1e-9
+}
+
+// A 10 unit long from 101 to 104, then a short from 103 held to the end at 105
+fn long_then_short() -> Scripted {
+    Scripted::new(&[
+        (
+            0,
+            Order::new(OrderType::MarketBuy, 100.0).with_quantity(10.0),
+        ),
+        (2, Order::new(OrderType::MarketSell, 104.0)),
+        (
+            3,
+            Order::new(OrderType::MarketSell, 104.0).with_quantity(10.0),
+        ),
+    ])
+}
+
+fn mock() -> MockStream {
+    MockStream::new().closes(&[100.0, 101.0, 104.0, 104.0, 103.0, 106.0, 105.0])
+}
+
+#[tokio::test]
+async fn cash_moves_only_by_the_realized_pnl() -> Result<()> {
+    let options = BacktestOptions {
+        ledger: Some(LedgerConfig::default()),
+        ..Default::default()
+    };
+    let run = mock()
+        .run_with(&mut long_then_short(), TransactionCosts::zero(), &options)
+        .await?;
+    let ledger = &run.result.ledger;
+    assert!(!ledger.is_empty());
+
+    for entry in ledger {
+        assert!(
+            close_to(MOCK_EQUITY + entry.realized_pnl, entry.cash),
+            "{:?}",
+            entry
+        );
+        assert!(
+            close_to(entry.cash + entry.unrealized_pnl, entry.net_liquidation),
+            "{:?}",
+            entry
+        );
+    }
+
+    // Opened at 101, marked to 104 and closed there
+    let opened = ledger.iter().find(|e| !e.positions.is_empty()).unwrap();
+    assert_eq!(opened.positions[0].quantity, 10.0);
+    assert_eq!(opened.positions[0].entry, 101.0);
+    let closed = ledger
+        .iter()
+        .find(|e| e.positions.is_empty() && e.realized_pnl != 0.0)
+        .unwrap();
+    assert!(close_to(30.0, closed.realized_pnl), "{:?}", closed);
+
+    // Short from 103 closed at 105 at the end of the data
+    let last = ledger.last().unwrap();
+    assert!(last.positions.is_empty(), "{:?}", last);
+    assert!(close_to(10.0, last.realized_pnl), "{:?}", last);
+    assert!(close_to(run.result.ending_equity, last.net_liquidation));
+    let realized: f64 = run.result.trades.iter().map(|t| t.pnl).sum();
+    assert!(close_to(realized, last.realized_pnl));
+    Ok(())
+}
+
+#[tokio::test]
+async fn an_interval_marks_open_positions_between_fills() -> Result<()> {
+    let options = BacktestOptions {
+        ledger: Some(LedgerConfig::every(60_000_000_000)),
+        ..Default::default()
+    };
+    let run = mock()
+        .run_with(&mut long_then_short(), TransactionCosts::zero(), &options)
+        .await?;
+
+    // The short is marked at 106 on the bar between its entry and the end
+    let marked = run
+        .result
+        .ledger
+        .iter()
+        .find(|e| e.positions.first().is_some_and(|p| p.mark == 106.0))
+        .expect("a mark at 106");
+    assert_eq!(marked.positions[0].quantity, -10.0);
+    assert!(close_to(-30.0, marked.unrealized_pnl), "{:?}", marked);
+    assert!(close_to(-1060.0, marked.market_value), "{:?}", marked);
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use databento::dbn::encode::{dbn::Encoder, EncodeRecord};
+use databento::dbn::{InstrumentDefMsg, Mbp1Msg, Metadata, SType, Schema, TradeMsg};
+use inkback::utils::fetch::merge_streams_to_csv;
+use inkback::UnderlyingQuotes;
+use std::io::Read;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("inkback_merge_{}_{}", std::process::id(), name))
+}
+
+fn write_dbn<R: databento::dbn::encode::DbnEncodable>(
+    path: &PathBuf,
+    schema: Schema,
+    records: &[R],
+) {
+    let metadata = Metadata::builder()
+        .dataset("OPRA.PILLAR".to_string())
+        .schema(Some(schema))
+        .start(0)
+        .stype_in(Some(SType::RawSymbol))
+        .stype_out(SType::InstrumentId)
+        .build();
+    let file = std::fs::File::create(path).unwrap();
+    let mut encoder = Encoder::with_zstd(file, &metadata).unwrap();
+    for record in records {
+        encoder.encode_record(record).unwrap();
+    }
+}
+
+fn definition(
+    instrument_id: u32,
+    ts_event: u64,
+    expiration: u64,
+    symbol: &str,
+) -> InstrumentDefMsg {
+    let mut def = InstrumentDefMsg::default();
+    def.hd.instrument_id = instrument_id;
+    def.hd.ts_event = ts_event;
+    def.expiration = expiration;
+    def.strike_price = 100_000_000_000;
+    def.instrument_class = b'C' as _;
+    for (slot, byte) in def.raw_symbol.iter_mut().zip(symbol.bytes()) {
+        *slot = byte as _;
+    }
+    def
+}
+
+fn trade(instrument_id: u32, ts_event: u64) -> TradeMsg {
+    let mut trade = TradeMsg::default();
+    trade.hd.instrument_id = instrument_id;
+    trade.hd.ts_event = ts_event;
+    trade.price = 1_500_000_000;
+    trade.size = 1;
+    trade
+}
+
+// (instrument_id, symbol) of every merged options row
+fn merged_options(path: &PathBuf) -> Vec<(u32, String)> {
+    let mut csv = String::new();
+    zstd::Decoder::new(std::fs::File::open(path).unwrap())
+        .unwrap()
+        .read_to_string(&mut csv)
+        .unwrap();
+    let mut reader = csv::Reader::from_reader(csv.as_bytes());
+    reader
+        .records()
+        .map(|r| r.unwrap())
+        .filter(|r| &r[1] == "OPT")
+        .map(|r| (r[2].parse().unwrap(), r[3].to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn merge_keeps_republished_definitions_and_drops_expired_contracts() {
+    let und = temp_path("und.dbn.zst");
+    let opts = temp_path("opts.dbn.zst");
+    let defs = temp_path("defs.dbn.zst");
+    let out = temp_path("merged.csv.zst");
+
+    let mut quote = Mbp1Msg::default();
+    quote.hd.ts_event = 50;
+    quote.price = 100_000_000_000;
+    write_dbn(&und, Schema::Mbp1, &[quote]);
+
+    // Contract 10 is republished every "day", 11 never trades, 12 expires mid-merge
+    write_dbn(
+        &defs,
+        Schema::Definition,
+        &[
+            definition(10, 100, 3_000, "SPY 10C"),
+            definition(11, 100, 3_000, "SPY 11C"),
+            definition(12, 100, 1_500, "SPY 12C"),
+            definition(10, 1_100, 3_000, "SPY 10C"),
+            definition(11, 1_100, 3_000, "SPY 11C"),
+            definition(10, 2_100, 3_000, "SPY 10C"),
+        ],
+    );
+    write_dbn(
+        &opts,
+        Schema::Trades,
+        &[
+            trade(10, 500),
+            trade(12, 600),
+            trade(12, 2_000),
+            trade(10, 2_500),
+        ],
+    );
+
+    merge_streams_to_csv(
+        und.to_str().unwrap(),
+        UnderlyingQuotes::Mbp1,
+        &[opts.to_str().unwrap().to_string()],
+        defs.to_str().unwrap(),
+        out.to_str().unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        merged_options(&out),
+        vec![
+            (10, "SPY 10C".to_string()),
+            (12, "SPY 12C".to_string()),
+            (10, "SPY 10C".to_string()),
+        ]
+    );
+
+    for path in [und, opts, defs, out] {
+        let _ = std::fs::remove_file(path);
+    }
+}
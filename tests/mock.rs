@@ -0,0 +1,147 @@
+//! The mock stream builder and its order and trade assertions, on hand-written events.
+
+use anyhow::Result;
+use inkback::event::MarketEvent;
+use inkback::strategy::{ExitReason, Order, OrderType, Strategy};
+use inkback::testing::{ExpectedOrder, ExpectedTrade, MockStream, MOCK_EQUITY};
+use std::collections::HashMap;
+
+const MINUTE: u64 = 60_000_000_000;
+
+/// Places the scripted order type at the price of the event of each index
+struct Scripted {
+    orders: HashMap<usize, OrderType>,
+    seen: usize,
+}
+
+impl Scripted {
+    fn new(orders: &[(usize, OrderType)]) -> Self {
+        Self {
+            orders: orders.iter().copied().collect(),
+            seen: 0,
+        }
+    }
+}
+
+impl Strategy for Scripted {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let index = self.seen;
+        self.seen += 1;
+        self.orders
+            .get(&index)
+            .map(|&order_type| Order::new(order_type, event.price()))
+    }
+}
+
+fn round_trip() -> Scripted {
+    Scripted::new(&[(1, OrderType::MarketBuy), (3, OrderType::MarketSell)])
+}
+
+#[tokio::test]
+async fn orders_and_trades_of_a_round_trip() -> Result<()> {
+    let run = MockStream::new()
+        .closes(&[100.0, 101.0, 102.0, 104.0, 103.0])
+        .run(&mut round_trip())
+        .await?;
+
+    run.assert_orders(&[
+        ExpectedOrder::buy().at(101.0).on_event(1),
+        ExpectedOrder::sell().at(104.0).on_event(3),
+    ]);
+    // All in at the next bar's open, 98 shares of the mock equity
+    let size = (MOCK_EQUITY / 102.0).floor();
+    run.assert_trades(&[ExpectedTrade::long()
+        .entry(102.0)
+        .exit(104.0)
+        .size(size)
+        .pnl(2.0 * size)]);
+    assert_eq!(run.fills().len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_last_open_position_closes_at_the_end_of_the_data() -> Result<()> {
+    let run = MockStream::new()
+        .closes(&[50.0, 50.0, 55.0])
+        .run(&mut Scripted::new(&[(0, OrderType::MarketSell)]))
+        .await?;
+    run.assert_orders(&[ExpectedOrder::sell().on_event(0)]);
+    run.assert_trades(&[ExpectedTrade::short()
+        .entry(50.0)
+        .exit(55.0)
+        .reason(ExitReason::EndOfData)]);
+    Ok(())
+}
+
+#[tokio::test]
+#[should_panic(expected = "trade 0: exit 104 != 105")]
+async fn trade_assertions_name_what_differs() {
+    let run = MockStream::new()
+        .closes(&[100.0, 101.0, 102.0, 104.0])
+        .run(&mut round_trip())
+        .await
+        .unwrap();
+    run.assert_trades(&[ExpectedTrade::long().entry(102.0).exit(105.0)]);
+}
+
+#[tokio::test]
+#[should_panic(expected = "expected 1 orders, got 2")]
+async fn order_assertions_count_orders() {
+    let run = MockStream::new()
+        .closes(&[100.0, 101.0, 102.0, 104.0])
+        .run(&mut round_trip())
+        .await
+        .unwrap();
+    run.assert_orders(&[ExpectedOrder::buy()]);
+}
+
+#[test]
+fn events_are_stamped_by_the_builder_clock() {
+    let start = 1_700_000_000_000_000_000;
+    let mock = MockStream::new()
+        .with_start(start, MINUTE)
+        .quote(99.5, 100.5)
+        .trade_with_side(100.5, 10, b'B')
+        .gap(60 * MINUTE)
+        .with_instrument(7)
+        .bar(100.0, 101.0, 99.0, 100.75, 500);
+
+    let ts: Vec<u64> = mock.events().iter().map(MarketEvent::timestamp).collect();
+    assert_eq!(ts, [start, start + MINUTE, start + 62 * MINUTE]);
+    let [quote, trade, bar] = mock.events() else {
+        panic!("expected three events");
+    };
+    assert_eq!(quote.get("bid_px"), Some(99.5));
+    assert_eq!(quote.get("ask_px"), Some(100.5));
+    assert_eq!(trade.price(), 100.5);
+    assert_eq!(trade.size(), Some(10));
+    assert_eq!(bar.price(), 100.75);
+    assert_eq!(bar.get("instrument_id"), Some(7.0));
+}
+
+#[tokio::test]
+async fn recorded_streams_replay_the_same_run() -> Result<()> {
+    let original = MockStream::new().closes(&[100.0, 101.0, 102.0, 104.0, 103.0, 99.0]);
+    let recorded = MockStream::record(original.clone().into_stream(), 5).await?;
+    assert_eq!(recorded.len(), 5);
+
+    // Events added after a recording carry on from its last timestamp
+    let extended = recorded.closes(&[99.0]);
+    let ts: Vec<u64> = extended
+        .events()
+        .iter()
+        .map(MarketEvent::timestamp)
+        .collect();
+    let expected: Vec<u64> = original
+        .events()
+        .iter()
+        .map(MarketEvent::timestamp)
+        .collect();
+    assert_eq!(ts, expected);
+
+    let replayed = extended.run(&mut round_trip()).await?;
+    let live = original.run(&mut round_trip()).await?;
+    assert_eq!(replayed.result.trades.len(), 1);
+    assert_eq!(replayed.result.ending_equity, live.result.ending_equity);
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! Parquet output of the exports, read back with the arrow reader. Runs with
+//! `cargo test --features parquet`.
+#![cfg(feature = "parquet")]
+
+use anyhow::Result;
+use arrow_array::{Array, Date32Array, Float64Array, UInt64Array};
+use inkback::backtester::{run_backtest_on_stream, BacktestOptions};
+use inkback::event::MarketEvent;
+use inkback::features::{export_features, FeatureSet, Horizon};
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{Order, OrderType, Strategy};
+use inkback::time_of_day::TradingCalendar;
+use inkback::tools::export_daily_returns;
+use inkback::utils::synthetic::{self, SyntheticMarket};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("inkback_{}_{}", std::process::id(), name))
+}
+
+fn read(path: &PathBuf) -> Result<(Vec<String>, Vec<arrow_array::RecordBatch>)> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path)?)?;
+    let columns = reader
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().clone())
+        .collect();
+    let batches = reader
+        .build()?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok((columns, batches))
+}
+
+#[tokio::test]
+async fn features_and_labels_round_trip_through_parquet() -> Result<()> {
+    let events = SyntheticMarket::new(3).ohlcv(200);
+    let path = temp_path("features.parquet");
+    let export = export_features(
+        synthetic::stream(events.clone()),
+        &mut FeatureSet::standard(),
+        &[Horizon::Events(5)],
+        path.to_str().unwrap(),
+        &BacktestOptions::default(),
+        None,
+    )
+    .await?;
+
+    let (columns, batches) = read(&path)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(columns, export.columns);
+    assert_eq!(
+        batches.iter().map(|b| b.num_rows()).sum::<usize>(),
+        export.rows
+    );
+
+    let batch = &batches[0];
+    let ts = batch
+        .column(0)
+        .as_any()
+        .downcast_ref::<UInt64Array>()
+        .unwrap();
+    assert_eq!(ts.value(0), events[0].timestamp());
+    let label = batch
+        .column(columns.len() - 1)
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap();
+    assert_eq!(label.len() - label.null_count(), export.labelled[0]);
+    let expected = events[5].price() / events[0].price() - 1.0;
+    assert!((label.value(0) - expected).abs() < 1e-12);
+    Ok(())
+}
+
+/// Buys on the first event and holds
+struct Hold {
+    bought: bool,
+}
+
+impl Strategy for Hold {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        if self.bought {
+            return None;
+        }
+        self.bought = true;
+        Some(Order::new(OrderType::MarketBuy, event.price()))
+    }
+}
+
+#[tokio::test]
+async fn daily_returns_parquet_matches_the_csv() -> Result<()> {
+    // Two weeks of hourly bars from Monday 2024-01-01 UTC
+    let events = SyntheticMarket::new(5)
+        .with_start(1_704_067_200_000_000_000, 3_600_000_000_000)
+        .ohlcv(24 * 14);
+    let result = run_backtest_on_stream(
+        "SYN",
+        synthetic::stream(events),
+        &mut Hold { bought: false },
+        TransactionCosts::equity_trading(),
+        100_000.0,
+        1.0,
+        None,
+        None,
+        &BacktestOptions {
+            hourly_marks: true,
+            ..BacktestOptions::default()
+        },
+    )
+    .await?;
+
+    let calendar = TradingCalendar::nyse();
+    let csv_path = temp_path("daily.csv");
+    let parquet_path = temp_path("daily.parquet");
+    export_daily_returns(&[("hold", &result)], &calendar, &csv_path)?;
+    export_daily_returns(&[("hold", &result)], &calendar, &parquet_path)?;
+
+    let mut csv_rows = Vec::new();
+    for record in csv::Reader::from_path(&csv_path)?.records() {
+        let record = record?;
+        csv_rows.push((record[0].to_string(), record[1].parse::<f64>()?));
+    }
+    let (columns, batches) = read(&parquet_path)?;
+    std::fs::remove_file(&csv_path)?;
+    std::fs::remove_file(&parquet_path)?;
+    assert_eq!(columns, ["date", "hold"]);
+
+    let batch = &batches[0];
+    let dates = batch
+        .column(0)
+        .as_any()
+        .downcast_ref::<Date32Array>()
+        .unwrap();
+    let returns = batch
+        .column(1)
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap();
+    assert_eq!(batch.num_rows(), csv_rows.len());
+    assert!(csv_rows.iter().any(|(_, ret)| ret.is_nan()));
+    for (i, (date, ret)) in csv_rows.iter().enumerate() {
+        assert_eq!(&dates.value_as_date(i).unwrap().to_string(), date);
+        let value = returns.value(i);
+        assert!(value == *ret || (value.is_nan() && ret.is_nan()));
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Permuted streams: footprint ladders move with their bar, and streams the permutation
+//! cannot rebuild consistently are refused
+
+use inkback::event::{FootprintMsg, MarketEvent};
+use inkback::permutation::{permute, Permutation};
+use inkback::seeds::SeededRng;
+use inkback::utils::synthetic::SyntheticMarket;
+
+fn footprints() -> Vec<MarketEvent> {
+    SyntheticMarket::new(11)
+        .ohlcv(50)
+        .iter()
+        .map(|bar| {
+            let price = (bar.price() * 4.0).round() / 4.0;
+            MarketEvent::Footprint(FootprintMsg {
+                ts_event: bar.timestamp(),
+                price,
+                volume: 30,
+                data: format!(r#"{{"{}":[5,10],"{}":[7,8]}}"#, price - 0.25, price),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn footprint_levels_are_rescaled_with_their_bar() {
+    let events = footprints();
+    let permuted = permute(&events, Permutation::SignFlip, &mut SeededRng::new(3)).unwrap();
+    let mut moved = false;
+    for (real, permuted) in events.iter().zip(&permuted) {
+        let (MarketEvent::Footprint(real), MarketEvent::Footprint(permuted)) = (real, permuted)
+        else {
+            panic!("permutation keeps the event kinds");
+        };
+        let factor = permuted.price / real.price;
+        moved |= (factor - 1.0).abs() > 1e-9;
+        let (real_levels, permuted_levels) = (real.levels(), permuted.levels());
+        assert_eq!(real_levels.len(), permuted_levels.len());
+        for (a, b) in real_levels.iter().zip(&permuted_levels) {
+            assert!((b.price - a.price * factor).abs() < 1e-9);
+            assert_eq!((a.buy_volume, a.sell_volume), (b.buy_volume, b.sell_volume));
+        }
+    }
+    assert!(moved, "the sign flips move the path");
+}
+
+#[test]
+fn several_instruments_are_refused() {
+    let mut events = SyntheticMarket::new(5).trades(20);
+    if let MarketEvent::Trade(m) = &mut events[7] {
+        m.hd.instrument_id += 1;
+    }
+    let error = permute(&events, Permutation::SignFlip, &mut SeededRng::new(1)).unwrap_err();
+    assert!(error.to_string().contains("several instruments"));
+
+    let single = SyntheticMarket::new(5).trades(20);
+    assert!(permute(&single, Permutation::SignFlip, &mut SeededRng::new(1)).is_ok());
+}
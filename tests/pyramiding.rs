@@ -0,0 +1,190 @@
+//! Adds to an open position, their limits, and which lots partial exits close.
+
+use anyhow::Result;
+use inkback::backtester::{BacktestOptions, BlockReason, LotMethod, PyramidRules};
+use inkback::event::MarketEvent;
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{ExitReason, Order, OrderType, Strategy};
+use inkback::testing::{ExpectedTrade, MockRun, MockStream};
+use std::collections::HashMap;
+
+/// Places the scripted order of each event index
+struct Scripted {
+    orders: HashMap<usize, Order>,
+    seen: usize,
+}
+
+impl Scripted {
+    fn new(orders: &[(usize, Order)]) -> Self {
+        Self {
+            orders: orders.iter().copied().collect(),
+            seen: 0,
+        }
+    }
+}
+
+impl Strategy for Scripted {
+    fn on_event(&mut self, _event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let index = self.seen;
+        self.seen += 1;
+        self.orders.get(&index).copied()
+    }
+}
+
+fn buy(quantity: f64) -> Order {
+    Order::new(OrderType::MarketBuy, 100.0).with_quantity(quantity)
+}
+
+async fn run(
+    mock: MockStream,
+    orders: &[(usize, Order)],
+    options: BacktestOptions,
+) -> Result<MockRun> {
+    mock.run_with(
+        &mut Scripted::new(orders),
+        TransactionCosts::zero(),
+        &options,
+    )
+    .await
+}
+
+fn blocks(run: &MockRun) -> Vec<BlockReason> {
+    run.result
+        .blocked_entries
+        .iter()
+        .map(|b| b.reason)
+        .collect()
+}
+
+#[tokio::test]
+async fn adds_stop_at_max_adds() -> Result<()> {
+    let options = BacktestOptions {
+        pyramiding: Some(PyramidRules {
+            max_adds: 2,
+            max_exposure: 3.0,
+        }),
+        ..Default::default()
+    };
+    let orders: Vec<(usize, Order)> = (0..4).map(|i| (i, buy(10.0))).collect();
+    let run = run(
+        MockStream::new().closes(&[100.0, 100.0, 100.0, 100.0, 100.0, 110.0]),
+        &orders,
+        options,
+    )
+    .await?;
+
+    let lot = ExpectedTrade::long()
+        .entry(100.0)
+        .exit(110.0)
+        .size(10.0)
+        .pnl(100.0)
+        .reason(ExitReason::EndOfData);
+    run.assert_trades(&[lot.clone(), lot.clone(), lot]);
+    let lots: Vec<usize> = run.result.trades.iter().map(|t| t.lot).collect();
+    assert_eq!(lots, vec![0, 1, 2]);
+    assert_eq!(blocks(&run), vec![BlockReason::MaxAdds]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn an_add_is_cut_to_the_exposure_left() -> Result<()> {
+    // 2500 of the 10000 account may be held: 1000, 2000, then 500 of the third add
+    let options = BacktestOptions {
+        pyramiding: Some(PyramidRules {
+            max_adds: 5,
+            max_exposure: 0.25,
+        }),
+        ..Default::default()
+    };
+    let orders: Vec<(usize, Order)> = (0..4).map(|i| (i, buy(10.0))).collect();
+    let run = run(
+        MockStream::new().closes(&[100.0, 100.0, 100.0, 100.0, 100.0, 110.0]),
+        &orders,
+        options,
+    )
+    .await?;
+
+    let sizes: Vec<f64> = run.result.trades.iter().map(|t| t.size).collect();
+    assert_eq!(sizes, vec![10.0, 10.0, 5.0]);
+    assert_eq!(blocks(&run), vec![BlockReason::MaxExposure]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn without_pyramiding_an_unsized_entry_does_not_add() -> Result<()> {
+    let run = run(
+        MockStream::new().closes(&[100.0, 101.0, 105.0, 107.0]),
+        &[(0, buy(10.0)), (1, Order::new(OrderType::MarketBuy, 101.0))],
+        BacktestOptions::default(),
+    )
+    .await?;
+
+    run.assert_trades(&[ExpectedTrade::long().entry(101.0).exit(107.0).size(10.0)]);
+    Ok(())
+}
+
+// 10 bought at 101 and 10 added at 105, then a market sell of 15 filled at 108
+async fn partial_exit(lot_method: LotMethod) -> Result<MockRun> {
+    let options = BacktestOptions {
+        pyramiding: Some(PyramidRules::default()),
+        lot_method,
+        ..Default::default()
+    };
+    run(
+        MockStream::new().closes(&[100.0, 101.0, 105.0, 108.0, 107.0]),
+        &[
+            (0, buy(10.0)),
+            (1, buy(10.0)),
+            (
+                3,
+                Order::new(OrderType::MarketSell, 108.0).with_quantity(15.0),
+            ),
+        ],
+        options,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn fifo_market_exits_close_the_oldest_lot_first() -> Result<()> {
+    partial_exit(LotMethod::Fifo).await?.assert_trades(&[
+        ExpectedTrade::long()
+            .entry(101.0)
+            .exit(108.0)
+            .size(10.0)
+            .pnl(70.0),
+        ExpectedTrade::long()
+            .entry(105.0)
+            .exit(108.0)
+            .size(5.0)
+            .pnl(15.0),
+        ExpectedTrade::long()
+            .entry(105.0)
+            .exit(107.0)
+            .size(5.0)
+            .reason(ExitReason::EndOfData),
+    ]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn lifo_market_exits_close_the_newest_lot_first() -> Result<()> {
+    partial_exit(LotMethod::Lifo).await?.assert_trades(&[
+        ExpectedTrade::long()
+            .entry(105.0)
+            .exit(108.0)
+            .size(10.0)
+            .pnl(30.0),
+        ExpectedTrade::long()
+            .entry(101.0)
+            .exit(108.0)
+            .size(5.0)
+            .pnl(35.0),
+        ExpectedTrade::long()
+            .entry(101.0)
+            .exit(107.0)
+            .size(5.0)
+            .reason(ExitReason::EndOfData),
+    ]);
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Reduce-only orders closing part of a position built from several lots.
+
+use anyhow::Result;
+use inkback::audit::AuditEvent;
+use inkback::backtester::{BacktestOptions, LotMethod};
+use inkback::event::MarketEvent;
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{ExitReason, Order, OrderType, Strategy};
+use inkback::testing::{ExpectedTrade, MockStream};
+use std::collections::HashMap;
+
+/// Places the scripted order of each event index
+struct Scripted {
+    orders: HashMap<usize, Order>,
+    seen: usize,
+}
+
+impl Strategy for Scripted {
+    fn on_event(&mut self, _event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let index = self.seen;
+        self.seen += 1;
+        self.orders.get(&index).copied()
+    }
+}
+
+// 10 bought at 101 and 10 added at 105, then a resting reduce-only sell of 15 at 110
+fn two_lots_then_partial_exit() -> Scripted {
+    Scripted {
+        orders: HashMap::from([
+            (
+                0,
+                Order::new(OrderType::MarketBuy, 100.0).with_quantity(10.0),
+            ),
+            (
+                1,
+                Order::new(OrderType::MarketBuy, 101.0).with_quantity(10.0),
+            ),
+            (
+                2,
+                Order::new(OrderType::LimitSell, 110.0)
+                    .with_quantity(15.0)
+                    .reduce_only(),
+            ),
+        ]),
+        seen: 0,
+    }
+}
+
+fn mock() -> MockStream {
+    MockStream::new().closes(&[100.0, 101.0, 105.0, 106.0, 110.0, 108.0, 107.0])
+}
+
+#[tokio::test]
+async fn a_fifo_partial_exit_leaves_the_newer_lot_at_its_basis() -> Result<()> {
+    let run = mock().run(&mut two_lots_then_partial_exit()).await?;
+
+    run.assert_trades(&[
+        ExpectedTrade::long().entry(101.0).exit(110.0).size(10.0),
+        ExpectedTrade::long().entry(105.0).exit(110.0).size(5.0),
+        ExpectedTrade::long()
+            .entry(105.0)
+            .exit(107.0)
+            .size(5.0)
+            .reason(ExitReason::EndOfData),
+    ]);
+    let lots: Vec<usize> = run.result.trades.iter().map(|t| t.lot).collect();
+    assert_eq!(lots, vec![0, 1, 1]);
+    let partial = run.fills().into_iter().find_map(|r| match r.event {
+        AuditEvent::PartialFill {
+            fill_price,
+            quantity,
+            remaining,
+        } => Some((fill_price, quantity, remaining)),
+        _ => None,
+    });
+    assert_eq!(partial, Some((110.0, 15.0, 5.0)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_lifo_partial_exit_leaves_the_older_lot_at_its_basis() -> Result<()> {
+    let options = BacktestOptions {
+        lot_method: LotMethod::Lifo,
+        ..Default::default()
+    };
+    let run = mock()
+        .run_with(
+            &mut two_lots_then_partial_exit(),
+            TransactionCosts::zero(),
+            &options,
+        )
+        .await?;
+
+    run.assert_trades(&[
+        ExpectedTrade::long().entry(105.0).exit(110.0).size(10.0),
+        ExpectedTrade::long().entry(101.0).exit(110.0).size(5.0),
+        ExpectedTrade::long()
+            .entry(101.0)
+            .exit(107.0)
+            .size(5.0)
+            .reason(ExitReason::EndOfData),
+    ]);
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Margin of short options sold against an underlying leg held alongside them.
+
+use anyhow::Result;
+use inkback::backtester::{
+    run_backtest_on_stream, BacktestOptions, BacktestResult, BlockReason, ShortOptionRules,
+};
+use inkback::event::{MarketEvent, OptionTradeMsg};
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{Order, OrderType, Strategy};
+use inkback::testing::MockStream;
+use inkback::{InkBackSchema, UnderlyingQuotes};
+
+const START: u64 = 1_704_205_800_000_000_000;
+const MINUTE: u64 = 60_000_000_000;
+const DAY: u64 = 1_440 * MINUTE;
+
+/// Buys `shares` of the underlying on the first quote, then sells the first option it sees
+struct SellAgainstStock {
+    shares: f64,
+    hedged: bool,
+    sold: bool,
+}
+
+impl Strategy for SellAgainstStock {
+    fn on_event(&mut self, event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        match event {
+            MarketEvent::OptionTrade(_) if self.hedged && !self.sold => {
+                self.sold = true;
+                Some(Order::new(OrderType::MarketSell, event.price()))
+            }
+            MarketEvent::Mbp1(_) if !self.hedged => {
+                self.hedged = true;
+                (self.shares > 0.0).then(|| {
+                    Order::new(OrderType::MarketBuy, event.price())
+                        .with_quantity(self.shares)
+                        .on_underlying()
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn option_trade(ts: u64, option_type: &str) -> MarketEvent {
+    MarketEvent::OptionTrade(OptionTradeMsg {
+        ts_event: ts,
+        price: 2.0,
+        size: 50,
+        instrument_id: 1001,
+        symbol: format!("SYN {}00100000", option_type),
+        strike_price: 100.0,
+        expiration: START + 30 * DAY,
+        option_type: option_type.to_string(),
+        underlying_bid: 99.99,
+        underlying_ask: 100.01,
+        underlying_price: 100.0,
+        underlying_bid_sz: 100,
+        underlying_ask_sz: 100,
+        open_interest: None,
+        underlying_ts: Some(ts),
+        underlying_stale: false,
+    })
+}
+
+// Two quotes to buy the underlying on, then three prints of an at-the-money option
+async fn run(option_type: &str, shares: f64, equity: f64) -> Result<BacktestResult> {
+    let mut mock = MockStream::new()
+        .with_start(START, MINUTE)
+        .quote(99.99, 100.01)
+        .quote(99.99, 100.01);
+    for i in 2..5 {
+        mock = mock.event(option_trade(START + i * MINUTE, option_type));
+    }
+    let options = BacktestOptions {
+        short_options: Some(ShortOptionRules::default()),
+        ..BacktestOptions::default()
+    };
+    run_backtest_on_stream(
+        "SYN",
+        mock.into_stream(),
+        &mut SellAgainstStock {
+            shares,
+            hedged: false,
+            sold: false,
+        },
+        TransactionCosts::zero(),
+        equity,
+        1.0,
+        Some(InkBackSchema::CombinedOptionsUnderlying {
+            underlying: UnderlyingQuotes::Mbp1,
+        }),
+        None,
+        &options,
+    )
+    .await
+}
+
+fn contracts_sold(result: &BacktestResult) -> f64 {
+    result.trades.first().map_or(0.0, |t| t.size)
+}
+
+#[tokio::test]
+async fn covered_calls_are_sized_past_the_naked_margin() -> Result<()> {
+    // $25,000 margins eleven naked contracts at $2,200 each, 200 shares cover two more
+    let covered = run("C", 200.0, 25_000.0).await?;
+    let naked = run("P", 200.0, 25_000.0).await?;
+    assert_eq!(contracts_sold(&naked), 11.0);
+    assert_eq!(contracts_sold(&covered), 13.0);
+    assert_eq!(contracts_sold(&run("C", 0.0, 25_000.0).await?), 11.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn covered_calls_need_no_margin() -> Result<()> {
+    // Too little for one naked contract
+    let naked = run("P", 200.0, 2_000.0).await?;
+    assert!(naked
+        .blocked_entries
+        .iter()
+        .any(|b| b.reason == BlockReason::InsufficientMargin));
+    assert!(naked.trades.is_empty());
+
+    let covered = run("C", 200.0, 2_000.0).await?;
+    assert!(covered.blocked_entries.is_empty());
+    assert_eq!(contracts_sold(&covered), 2.0);
+    Ok(())
+}
+
+#[test]
+fn only_whole_hundreds_on_the_right_side_cover() {
+    assert_eq!(ShortOptionRules::covered_contracts(true, 250.0), 2.0);
+    assert_eq!(ShortOptionRules::covered_contracts(true, -300.0), 0.0);
+    assert_eq!(ShortOptionRules::covered_contracts(false, -300.0), 3.0);
+    assert_eq!(ShortOptionRules::covered_contracts(false, 300.0), 0.0);
+
+    let rules = ShortOptionRules::default();
+    let naked = rules.margin_per_contract(true, 100.0, 100.0, 2.0, false);
+    assert_eq!(
+        rules.position_margin(true, 100.0, 100.0, 2.0, 3.0, 200.0),
+        naked
+    );
+    assert_eq!(
+        rules.position_margin(true, 100.0, 100.0, 2.0, 2.0, 200.0),
+        0.0
+    );
+}
@@ -0,0 +1,93 @@
+//! Entry sizing under a drawdown throttle, on hand-written events.
+
+use anyhow::Result;
+use inkback::backtester::BacktestOptions;
+use inkback::event::MarketEvent;
+use inkback::sizing::DrawdownThrottle;
+use inkback::slippage_models::TransactionCosts;
+use inkback::strategy::{Order, OrderType, Strategy};
+use inkback::testing::{ExpectedTrade, MockStream};
+use std::collections::HashMap;
+
+/// Places the scripted order of each event index
+struct Scripted {
+    orders: HashMap<usize, Order>,
+    seen: usize,
+}
+
+impl Scripted {
+    fn new(orders: &[(usize, Order)]) -> Self {
+        Self {
+            orders: orders.iter().copied().collect(),
+            seen: 0,
+        }
+    }
+}
+
+impl Strategy for Scripted {
+    fn on_event(&mut self, _event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let index = self.seen;
+        self.seen += 1;
+        self.orders.get(&index).copied()
+    }
+}
+
+// A full-size long from 100 closed at 90, then the same long again at 90
+fn loss_then_reentry() -> Scripted {
+    Scripted::new(&[
+        (0, Order::new(OrderType::MarketBuy, 100.0)),
+        (2, Order::new(OrderType::MarketSell, 90.0)),
+        (3, Order::new(OrderType::MarketBuy, 90.0)),
+    ])
+}
+
+fn mock() -> MockStream {
+    MockStream::new().closes(&[100.0, 100.0, 90.0, 90.0, 90.0, 99.0])
+}
+
+#[tokio::test]
+async fn entries_are_scaled_below_the_high_water_mark() -> Result<()> {
+    let options = BacktestOptions {
+        throttle: Some(DrawdownThrottle::new().with_tier(0.05, 0.5)),
+        ..Default::default()
+    };
+    let run = mock()
+        .run_with(&mut loss_then_reentry(), TransactionCosts::zero(), &options)
+        .await?;
+
+    // 10% under the mark after the loss, so the 9000 left buys half of 100 units
+    run.assert_trades(&[
+        ExpectedTrade::long()
+            .entry(100.0)
+            .exit(90.0)
+            .size(100.0)
+            .pnl(-1000.0),
+        ExpectedTrade::long()
+            .entry(90.0)
+            .exit(99.0)
+            .size(50.0)
+            .pnl(450.0),
+    ]);
+    let report = run.result.throttle.expect("throttle report");
+    assert_eq!(report.entries, 2);
+    assert_eq!(report.throttled_entries, 1);
+    assert!(
+        (report.max_drawdown_pct - 10.0).abs() < 1e-9,
+        "{}",
+        report.max_drawdown_pct
+    );
+    assert_eq!(report.changes.first().map(|&(_, scale)| scale), Some(0.5));
+    Ok(())
+}
+
+#[tokio::test]
+async fn without_a_throttle_entries_keep_full_size() -> Result<()> {
+    let run = mock().run(&mut loss_then_reentry()).await?;
+
+    run.assert_trades(&[
+        ExpectedTrade::long().entry(100.0).exit(90.0).size(100.0),
+        ExpectedTrade::long().entry(90.0).exit(99.0).size(100.0),
+    ]);
+    assert!(run.result.throttle.is_none());
+    Ok(())
+}
@@ -0,0 +1,162 @@
+//! Expiry and fill-or-kill of resting limit orders, on hand-written events.
+
+use anyhow::Result;
+use inkback::audit::AuditEvent;
+use inkback::event::MarketEvent;
+use inkback::strategy::{CancelReason, ExitReason, Order, OrderType, Strategy, TimeInForce};
+use inkback::testing::{ExpectedTrade, MockRun, MockStream};
+use std::collections::HashMap;
+
+const NANOS_PER_HOUR: u64 = 3_600_000_000_000;
+
+/// Places the scripted order of each event index
+struct Scripted {
+    orders: HashMap<usize, Order>,
+    seen: usize,
+}
+
+impl Scripted {
+    fn new(orders: &[(usize, Order)]) -> Self {
+        Self {
+            orders: orders.iter().copied().collect(),
+            seen: 0,
+        }
+    }
+}
+
+impl Strategy for Scripted {
+    fn on_event(&mut self, _event: &MarketEvent, _prev: Option<&MarketEvent>) -> Option<Order> {
+        let index = self.seen;
+        self.seen += 1;
+        self.orders.get(&index).copied()
+    }
+}
+
+fn limit_buy(price: f64, time_in_force: TimeInForce) -> Order {
+    Order::new(OrderType::LimitBuy, price)
+        .with_quantity(10.0)
+        .with_time_in_force(time_in_force)
+}
+
+fn cancels(run: &MockRun) -> Vec<CancelReason> {
+    run.result
+        .audit
+        .iter()
+        .filter_map(|r| match r.event {
+            AuditEvent::Cancelled { reason } => Some(reason),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn an_ioc_limit_is_cancelled_when_the_next_event_misses_it() -> Result<()> {
+    let mock = MockStream::new().closes(&[100.0, 99.0, 94.0, 96.0]);
+
+    let ioc = mock
+        .clone()
+        .run(&mut Scripted::new(&[(
+            0,
+            limit_buy(95.0, TimeInForce::Ioc),
+        )]))
+        .await?;
+    ioc.assert_trades(&[]);
+    assert_eq!(cancels(&ioc), vec![CancelReason::NotFilled]);
+
+    let gtc = mock
+        .run(&mut Scripted::new(&[(
+            0,
+            limit_buy(95.0, TimeInForce::Gtc),
+        )]))
+        .await?;
+    gtc.assert_trades(&[ExpectedTrade::long()
+        .entry(95.0)
+        .exit(96.0)
+        .size(10.0)
+        .reason(ExitReason::EndOfData)]);
+    assert!(cancels(&gtc).is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_fok_limit_needs_the_volume_to_fill_in_full() -> Result<()> {
+    let order = limit_buy(100.0, TimeInForce::Fok);
+
+    let killed = MockStream::new()
+        .closes(&[100.0])
+        .bar(100.0, 100.0, 100.0, 100.0, 5)
+        .closes(&[102.0])
+        .run(&mut Scripted::new(&[(0, order)]))
+        .await?;
+    killed.assert_trades(&[]);
+    assert_eq!(cancels(&killed), vec![CancelReason::NotFilled]);
+
+    // The order's own quantity is what must be covered, not a full-exposure size
+    let filled = MockStream::new()
+        .closes(&[100.0])
+        .bar(100.0, 100.0, 100.0, 100.0, 10)
+        .closes(&[102.0])
+        .run(&mut Scripted::new(&[(0, order)]))
+        .await?;
+    filled.assert_trades(&[ExpectedTrade::long()
+        .entry(100.0)
+        .exit(102.0)
+        .size(10.0)
+        .reason(ExitReason::EndOfData)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_day_limit_expires_at_the_next_session_open() -> Result<()> {
+    // 14:30 and 14:31 on 2 January, then 14:32 on the 3rd once the session has rolled
+    let mock = MockStream::new()
+        .closes(&[100.0, 99.0])
+        .gap(24 * NANOS_PER_HOUR)
+        .closes(&[94.0, 96.0]);
+
+    let day = mock
+        .clone()
+        .run(&mut Scripted::new(&[(
+            0,
+            limit_buy(95.0, TimeInForce::Day),
+        )]))
+        .await?;
+    day.assert_trades(&[]);
+    assert_eq!(cancels(&day), vec![CancelReason::Expired]);
+
+    let gtc = mock
+        .run(&mut Scripted::new(&[(
+            0,
+            limit_buy(95.0, TimeInForce::Gtc),
+        )]))
+        .await?;
+    gtc.assert_trades(&[ExpectedTrade::long().entry(95.0).exit(96.0)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_gtd_limit_expires_at_its_timestamp() -> Result<()> {
+    let mock = MockStream::new().closes(&[100.0, 99.0, 98.0, 94.0, 96.0]);
+    let third = mock.events()[2].timestamp();
+    let fourth = mock.events()[3].timestamp();
+
+    let expired = mock
+        .clone()
+        .run(&mut Scripted::new(&[(
+            0,
+            limit_buy(95.0, TimeInForce::Gtd(third)),
+        )]))
+        .await?;
+    expired.assert_trades(&[]);
+    assert_eq!(cancels(&expired), vec![CancelReason::Expired]);
+
+    // Still live on the event before its expiry
+    let live = mock
+        .run(&mut Scripted::new(&[(
+            0,
+            limit_buy(95.0, TimeInForce::Gtd(fourth + 1)),
+        )]))
+        .await?;
+    live.assert_trades(&[ExpectedTrade::long().entry(95.0).exit(96.0)]);
+    Ok(())
+}